@@ -13,6 +13,10 @@ enum BuildCommand {
         /// The deployment cluster
         #[structopt(long)]
         cluster: Cluster,
+
+        /// Enables the additional runtime checks of the `hardened` feature (elusiv only)
+        #[structopt(long)]
+        hardened: bool,
     },
 
     /// Test a program
@@ -24,6 +28,10 @@ enum BuildCommand {
         /// The test-kind (unit, integration, tarpaulin)
         #[structopt(long)]
         test_kind: TestKind,
+
+        /// Enables the additional runtime checks of the `hardened` feature (elusiv only)
+        #[structopt(long)]
+        hardened: bool,
     },
 }
 
@@ -58,7 +66,11 @@ fn main() {
     let mut features = Vec::new();
 
     match BuildCommand::from_args() {
-        BuildCommand::Build { target, cluster } => {
+        BuildCommand::Build {
+            target,
+            cluster,
+            hardened,
+        } => {
             build_target = target;
             command = "build-bpf";
             use_bpf = true;
@@ -68,8 +80,16 @@ fn main() {
                 Cluster::Devnet => features.push("devnet"),
                 _ => {}
             }
+
+            if hardened {
+                features.push("hardened");
+            }
         }
-        BuildCommand::Test { target, test_kind } => {
+        BuildCommand::Test {
+            target,
+            test_kind,
+            hardened,
+        } => {
             build_target = target;
 
             match test_kind {
@@ -90,6 +110,10 @@ fn main() {
                     features.push("test-unit");
                 }
             }
+
+            if hardened {
+                features.push("hardened");
+            }
         }
     }
 
@@ -110,9 +134,11 @@ fn main() {
     } else {
         vec![]
     };
-    if !features.is_empty() {
-        features.insert(0, "--features");
-    }
+    let features = if features.is_empty() {
+        vec![]
+    } else {
+        vec![String::from("--features"), features.join(",")]
+    };
 
     let exit_code = Command::new("cargo")
         .arg(command)
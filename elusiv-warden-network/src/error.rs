@@ -14,7 +14,9 @@ pub enum ElusivWardenNetworkError {
     Overflow = 0x08,
     Underflow = 0x09,
 
-    /// Placeholder, [`elusiv_types::token::TokenError`] uses 0x1xx error codes
+    HeartbeatTooFrequent = 0x0A,
+
+    /// Placeholder, [`elusiv_types::tokens::TokenError`] uses the codes in `TOKEN_ERROR_CODES_START..TOKEN_ERROR_CODES_END` (see [`elusiv_types::constants`])
     TokenError = 0x100,
 
     // APA inception errors
@@ -25,6 +27,21 @@ pub enum ElusivWardenNetworkError {
     WardenAlreadyConfirmed = 0x204,
 }
 
+// Error codes must not collide with the `TokenError` codes (checked for the last code of each group)
+const _: () = {
+    use elusiv_types::constants::is_token_error_code;
+
+    assert!(!is_token_error_code(
+        ElusivWardenNetworkError::HeartbeatTooFrequent as u32
+    ));
+    assert!(!is_token_error_code(
+        ElusivWardenNetworkError::TokenError as u32
+    ));
+    assert!(!is_token_error_code(
+        ElusivWardenNetworkError::ApplicationAlreadyComplete as u32
+    ));
+};
+
 impl From<ElusivWardenNetworkError> for ProgramError {
     fn from(e: ElusivWardenNetworkError) -> Self {
         ProgramError::Custom(e as u32)
@@ -170,6 +170,13 @@ pub enum ElusivWardenNetworkInstruction {
         uses_proxy: bool,
     },
 
+    // -------- Basic Warden liveness --------
+    #[acc(warden, { signer })]
+    #[pda(warden_account, BasicWardenAccount, pda_offset = Some(warden_id), { writable })]
+    UpdateBasicWardenHeartbeat {
+        warden_id: ElusivWardenID,
+    },
+
     // -------- Program state management --------
     #[cfg(not(feature = "mainnet"))]
     #[acc(payer, { signer })]
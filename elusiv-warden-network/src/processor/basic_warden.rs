@@ -1,5 +1,5 @@
 use crate::error::ElusivWardenNetworkError;
-use crate::processor::{current_timestamp, unix_timestamp_to_day_and_year};
+use crate::processor::{current_slot, current_timestamp, unix_timestamp_to_day_and_year};
 use crate::warden::{
     BasicWardenAccount, BasicWardenAttesterMapAccount, BasicWardenMapAccount,
     BasicWardenStatsAccount, Timezone, WardenRegion,
@@ -8,7 +8,8 @@ use crate::{
     network::BasicWardenNetworkAccount,
     warden::{ElusivBasicWarden, ElusivBasicWardenConfig, ElusivWardenID, WardensAccount},
 };
-use elusiv_types::UnverifiedAccountInfo;
+use elusiv_types::constants::elusiv_instruction_tags;
+use elusiv_types::{UnverifiedAccountInfo, WardenView};
 use elusiv_utils::{
     close_account, guard, open_pda_account_with_associated_pubkey, open_pda_account_with_offset,
     pda_account,
@@ -19,6 +20,9 @@ use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_program::sysvar::instructions;
 
+/// The minimum number of slots between two heartbeats of a Warden
+pub const BASIC_WARDEN_HEARTBEAT_INTERVAL: u64 = 150;
+
 pub fn register_basic_warden<'a, 'b>(
     warden: &AccountInfo<'b>,
     mut warden_account: UnverifiedAccountInfo<'a, 'b>,
@@ -38,6 +42,14 @@ pub fn register_basic_warden<'a, 'b>(
         &config.tokens,
     )?;
 
+    let view = WardenView {
+        warden_id,
+        key: config.key,
+        is_active: false,
+        last_heartbeat_slot: current_slot()?,
+        tokens: config.tokens,
+    };
+
     let current_timestamp = current_timestamp()?;
     let basic_warden = ElusivBasicWarden {
         config,
@@ -73,6 +85,7 @@ pub fn register_basic_warden<'a, 'b>(
         BasicWardenAccount,
         warden_account.get_safe()?
     );
+    warden_account.set_view(&view);
     warden_account.set_warden(&basic_warden);
 
     // `warden_map_account` is used to store the `warden_id` and prevent duplicate registrations
@@ -115,6 +128,36 @@ pub fn update_basic_warden_state(
     basic_warden.is_active = is_active;
     warden_account.set_warden(&basic_warden);
 
+    let mut view = warden_account.get_view();
+    view.is_active = is_active;
+    warden_account.set_view(&view);
+
+    Ok(())
+}
+
+pub fn update_basic_warden_heartbeat(
+    warden: &AccountInfo,
+    warden_account: &mut BasicWardenAccount,
+
+    _warden_id: ElusivWardenID,
+) -> ProgramResult {
+    let mut view = warden_account.get_view();
+    guard!(
+        *warden.key == view.key,
+        ProgramError::MissingRequiredSignature
+    );
+
+    let slot = current_slot()?;
+    guard!(
+        slot >= view
+            .last_heartbeat_slot
+            .saturating_add(BASIC_WARDEN_HEARTBEAT_INTERVAL),
+        ElusivWardenNetworkError::HeartbeatTooFrequent
+    );
+
+    view.last_heartbeat_slot = slot;
+    warden_account.set_view(&view);
+
     Ok(())
 }
 
@@ -276,17 +319,17 @@ pub struct TrackableElusivInstruction {
 pub const TRACKABLE_ELUSIV_INSTRUCTIONS: [TrackableElusivInstruction; 3] = [
     // FinalizeBaseCommitmentHash
     TrackableElusivInstruction {
-        instruction_id: 2,
+        instruction_id: elusiv_instruction_tags::FINALIZE_BASE_COMMITMENT_HASH,
         warden_index: 0,
     },
     // FinalizeVerificationTransferLamports
     TrackableElusivInstruction {
-        instruction_id: 13,
+        instruction_id: elusiv_instruction_tags::FINALIZE_VERIFICATION_TRANSFER_LAMPORTS,
         warden_index: 1,
     },
     // FinalizeVerificationTransferToken
     TrackableElusivInstruction {
-        instruction_id: 14,
+        instruction_id: elusiv_instruction_tags::FINALIZE_VERIFICATION_TRANSFER_TOKEN,
         warden_index: 3,
     },
 ];
@@ -5,6 +5,10 @@ pub fn current_timestamp() -> Result<u64, ProgramError> {
     Ok(clock.unix_timestamp.try_into().unwrap())
 }
 
+pub fn current_slot() -> Result<u64, ProgramError> {
+    Ok(Clock::get()?.slot)
+}
+
 pub fn get_day_and_year() -> Result<(u32, u16), ProgramError> {
     let clock = Clock::get()?;
     let timestamp = clock.unix_timestamp.try_into().unwrap();
@@ -3,7 +3,7 @@ use crate::{
     macros::{elusiv_account, BorshSerDeSized},
 };
 use borsh::{BorshDeserialize, BorshSerialize};
-use elusiv_types::{accounts::PDAAccountData, ElusivOption, TOKENS};
+use elusiv_types::{accounts::PDAAccountData, ElusivOption, WardenView, TOKENS};
 use elusiv_utils::guard;
 use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 use std::net::Ipv4Addr;
@@ -170,12 +170,17 @@ pub struct ElusivBasicWarden {
 }
 
 /// An account associated with a single [`ElusivBasicWarden`]
+///
+/// # Note
+///
+/// `view` needs to directly follow `pda_data` (see [`WardenView::from_account_data`]).
 #[elusiv_account(eager_type: true)]
 pub struct BasicWardenAccount {
     #[no_getter]
     #[no_setter]
     pda_data: PDAAccountData,
 
+    pub view: WardenView,
     pub warden: ElusivBasicWarden,
 }
 
@@ -284,3 +289,30 @@ pub struct ApaWardenAccount {
     pub network_member_index: u32,
     // pub latest_quote: Quote,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elusiv_types::{BorshSerDeSized, ProgramAccount, SizedAccount};
+
+    #[test]
+    fn test_warden_view_from_account_data() {
+        let view = WardenView {
+            warden_id: 7,
+            key: Pubkey::new_unique(),
+            is_active: true,
+            last_heartbeat_slot: 123_456,
+            tokens: [true; TOKENS.len()],
+        };
+
+        let mut data = vec![0; BasicWardenAccount::SIZE];
+        let mut account = BasicWardenAccount::new(&mut data).unwrap();
+        account.set_view(&view);
+
+        assert_eq!(WardenView::from_account_data(&data).unwrap(), view);
+        assert_eq!(
+            WardenView::from_account_data(&data[..PDAAccountData::SIZE + WardenView::SIZE - 1]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+}
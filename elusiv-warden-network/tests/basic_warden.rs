@@ -4,7 +4,10 @@ use common::*;
 use elusiv_types::{ProgramAccount, SignerAccount, UserAccount, WritableSignerAccount, TOKENS};
 use elusiv_warden_network::{
     instruction::ElusivWardenNetworkInstruction,
-    processor::{unix_timestamp_to_day_and_year, TRACKABLE_ELUSIV_INSTRUCTIONS},
+    processor::{
+        unix_timestamp_to_day_and_year, BASIC_WARDEN_HEARTBEAT_INTERVAL,
+        TRACKABLE_ELUSIV_INSTRUCTIONS,
+    },
     warden::{
         BasicWardenAccount, BasicWardenFeatures, BasicWardenMapAccount, BasicWardenStatsAccount,
         ElusivBasicWardenConfig, Timezone, WardenFeatures, WardenRegion,
@@ -97,6 +100,12 @@ async fn test_register() {
     assert_eq!(basic_warden.lut, Pubkey::new_from_array([0; 32]));
     assert!(!basic_warden.is_active);
 
+    let view = basic_warden_account.view;
+    assert_eq!(view.warden_id, 0);
+    assert_eq!(view.key, config.key);
+    assert!(!view.is_active);
+    assert_eq!(view.tokens, config.tokens);
+
     // TODO: Check join_timestamp and activation_timestamp
 }
 
@@ -219,6 +228,7 @@ async fn test_update_state() {
 
     let basic_warden_account = test.eager_account::<BasicWardenAccount, _>(Some(0)).await;
     assert!(basic_warden_account.warden.is_active);
+    assert!(basic_warden_account.view.is_active);
     assert_eq!(basic_warden_account.warden.activation_timestamp, timestamp);
 
     set_timestamp(&mut test, 0).await;
@@ -226,6 +236,7 @@ async fn test_update_state() {
 
     let basic_warden_account = test.eager_account::<BasicWardenAccount, _>(Some(0)).await;
     assert!(!basic_warden_account.warden.is_active);
+    assert!(!basic_warden_account.view.is_active);
     assert_eq!(basic_warden_account.warden.activation_timestamp, timestamp);
     let timestamp = basic_warden_account.warden.activation_timestamp;
 
@@ -290,6 +301,79 @@ async fn test_update_lut() {
     assert_eq!(basic_warden_account.warden.lut, lut);
 }
 
+#[tokio::test]
+async fn test_update_heartbeat() {
+    let mut test = start_test_with_setup().await;
+
+    let mut warden = Actor::new(&mut test).await;
+    register_warden(&mut test, &mut warden).await;
+
+    let registration_slot = test
+        .eager_account::<BasicWardenAccount, _>(Some(0))
+        .await
+        .view
+        .last_heartbeat_slot;
+
+    // Invalid signer
+    test.ix_should_fail_simple(
+        ElusivWardenNetworkInstruction::update_basic_warden_heartbeat_instruction(
+            0,
+            SignerAccount(test.payer()),
+        ),
+    )
+    .await;
+
+    // Too early after the registration
+    test.ix_should_fail(
+        ElusivWardenNetworkInstruction::update_basic_warden_heartbeat_instruction(
+            0,
+            SignerAccount(warden.pubkey),
+        ),
+        &[&warden.keypair],
+    )
+    .await;
+
+    let slot = registration_slot + BASIC_WARDEN_HEARTBEAT_INTERVAL;
+    test.context().warp_to_slot(slot).unwrap();
+
+    test.ix_should_succeed(
+        ElusivWardenNetworkInstruction::update_basic_warden_heartbeat_instruction(
+            0,
+            SignerAccount(warden.pubkey),
+        ),
+        &[&warden.keypair],
+    )
+    .await;
+
+    let basic_warden_account = test.eager_account::<BasicWardenAccount, _>(Some(0)).await;
+    assert_eq!(basic_warden_account.view.last_heartbeat_slot, slot);
+
+    // Rate limited
+    test.context()
+        .warp_to_slot(slot + BASIC_WARDEN_HEARTBEAT_INTERVAL - 1)
+        .unwrap();
+    test.ix_should_fail(
+        ElusivWardenNetworkInstruction::update_basic_warden_heartbeat_instruction(
+            0,
+            SignerAccount(warden.pubkey),
+        ),
+        &[&warden.keypair],
+    )
+    .await;
+
+    test.context()
+        .warp_to_slot(slot + BASIC_WARDEN_HEARTBEAT_INTERVAL)
+        .unwrap();
+    test.ix_should_succeed(
+        ElusivWardenNetworkInstruction::update_basic_warden_heartbeat_instruction(
+            0,
+            SignerAccount(warden.pubkey),
+        ),
+        &[&warden.keypair],
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn test_open_stats_account() {
     let mut test = start_test_with_setup().await;
@@ -12,7 +12,7 @@ macro_rules! div_ceiling {
             if divisor == 0 {
                 panic!()
             }
-            (divident + divisor - 1) / divisor
+            divident / divisor + (divident % divisor != 0) as $ty
         }
     };
 }
@@ -50,6 +50,34 @@ pub fn find<N: BorshSerialize + BorshSerDeSized>(
     v: &N,
     data: &[u8],
     length: usize,
+) -> Option<usize> {
+    find_with_hint(v, data, length, 0)
+}
+
+/// Same as [`find`], but the byte at `hint_byte_index` is compared before the full comparison
+///
+/// # Note
+///
+/// For values with a low entropy leading byte (e.g. clustered hashes), a higher entropy hint byte (e.g. `31` for `U256`) results in fewer full comparisons.
+///
+/// # Panics
+///
+/// Panics if `hint_byte_index >= N::SIZE`
+pub fn find_with_hint<N: BorshSerialize + BorshSerDeSized>(
+    v: &N,
+    data: &[u8],
+    length: usize,
+    hint_byte_index: usize,
+) -> Option<usize> {
+    find_with_hint_counted(v, data, length, hint_byte_index, &mut 0)
+}
+
+fn find_with_hint_counted<N: BorshSerialize + BorshSerDeSized>(
+    v: &N,
+    data: &[u8],
+    length: usize,
+    hint_byte_index: usize,
+    full_comparisons: &mut usize,
 ) -> Option<usize> {
     let bytes = match N::try_to_vec(v) {
         Ok(v) => v,
@@ -57,21 +85,17 @@ pub fn find<N: BorshSerialize + BorshSerDeSized>(
     };
 
     assert!(data.len() >= length);
+    assert!(hint_byte_index < N::SIZE);
 
     // TODO: optimize with byte alignment
 
-    let last_index = N::SIZE - 1;
+    let hint_byte = bytes[hint_byte_index];
     let mut offset = 0;
     for i in 0..length {
-        if data[offset] == bytes[0] {
-            for j in 1..N::SIZE {
-                if data[offset + j] != bytes[j] {
-                    break;
-                }
-
-                if j == last_index {
-                    return Some(i);
-                }
+        if data[offset + hint_byte_index] == hint_byte {
+            *full_comparisons += 1;
+            if data[offset..offset + N::SIZE] == bytes[..] {
+                return Some(i);
             }
         }
 
@@ -110,6 +134,7 @@ pub fn slice_to_array<N: Default + Copy, const SIZE: usize>(s: &[N]) -> [N; SIZE
 mod tests {
     use super::*;
     use crate::macros::BorshSerDeSized;
+    use crate::types::U256;
     use borsh::BorshDeserialize;
     use solana_program::pubkey::Pubkey;
 
@@ -124,6 +149,32 @@ mod tests {
         assert_eq!(div_ceiling_u32(3, 2), 2);
         assert_eq!(div_ceiling_u64(4, 3), 2);
         assert_eq!(div_ceiling_usize(7, 3), 3);
+        assert_eq!(div_ceiling_u64(6, 3), 2);
+        assert_eq!(div_ceiling_u64(0, 3), 0);
+        assert_eq!(div_ceiling_u64(u64::MAX, 1), u64::MAX);
+        assert_eq!(div_ceiling_u64(u64::MAX, 2), u64::MAX / 2 + 1);
+    }
+
+    #[test]
+    #[allow(clippy::manual_div_ceil)]
+    fn test_div_ceiling_matches_naive_formula() {
+        for divident in 0..64u64 {
+            for divisor in 1..16u64 {
+                assert_eq!(
+                    div_ceiling_u64(divident, divisor),
+                    (divident + divisor - 1) / divisor
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_div_ceiling_no_overflow() {
+        assert_eq!(div_ceiling_u64(u64::MAX, 1), u64::MAX);
+        assert_eq!(div_ceiling_u64(u64::MAX, 2), u64::MAX / 2 + 1);
+        assert_eq!(div_ceiling_u64(u64::MAX, u64::MAX), 1);
+        assert_eq!(div_ceiling_u32(u32::MAX, u32::MAX - 1), 2);
+        assert_eq!(div_ceiling_usize(usize::MAX, 2), usize::MAX / 2 + 1);
     }
 
     #[test]
@@ -219,6 +270,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_find_with_hint() {
+        // Clustered values: identical leading bytes, distinct trailing bytes
+        let length = 256usize;
+        let values: Vec<U256> = (0..length)
+            .map(|i| {
+                let mut v = [7; 32];
+                v[31] = i as u8;
+                v
+            })
+            .collect();
+        let data: Vec<u8> = values.iter().flatten().copied().collect();
+
+        let mut default_comparisons = 0;
+        let mut hint_comparisons = 0;
+        for (i, v) in values.iter().enumerate() {
+            assert_eq!(find(v, &data, length), Some(i));
+            assert_eq!(find_with_hint(v, &data, length, 31), Some(i));
+
+            find_with_hint_counted(v, &data, length, 0, &mut default_comparisons);
+            find_with_hint_counted(v, &data, length, 31, &mut hint_comparisons);
+        }
+
+        // Each search with the hint only requires a single full comparison
+        assert_eq!(hint_comparisons, length);
+        assert_eq!(default_comparisons, length * (length + 1) / 2);
+
+        let missing: U256 = [8; 32];
+        assert_eq!(find_with_hint(&missing, &data, length, 31), None);
+        assert_eq!(find_with_hint(&[7u8; 32], &data, length, 0), Some(7));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_find_with_hint_invalid_index() {
+        find_with_hint(&[0u8; 32], &[0; 32], 1, 32);
+    }
+
     #[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized)]
     struct A {
         d: [u8; 11],
@@ -257,6 +346,17 @@ mod tests {
         assert_eq!(TestEnum::len(0), 8);
         assert_eq!(TestEnum::len(1), 16);
         assert_eq!(TestEnum::len(2), 25);
+
+        // Unknown variant
+        assert_eq!(TestEnum::len(3), 0);
+    }
+
+    #[test]
+    fn test_deserialize_enum_unknown_variant() {
+        let mut data = vec![3];
+        data.extend(vec![0; TestEnum::SIZE - 1]);
+        assert!(TestEnum::deserialize_enum(&mut &data[..]).is_err());
+        assert!(TestEnum::deserialize_enum_full(&mut &data[..]).is_err());
     }
 
     #[test]
@@ -2,15 +2,16 @@
 mod poseidon_constants;
 pub mod poseidon_hash;
 
+use crate::macros::{guard, two_pow};
 use crate::{
     bytes::usize_as_u32_safe,
     commitment::poseidon_hash::{binary_poseidon_hash_partial, TOTAL_POSEIDON_ROUNDS},
+    computation_metadata::{self, BASE_COMMITMENT_HASH},
     error::ElusivError,
     state::commitment::{BaseCommitmentHashingAccount, CommitmentHashingAccount},
 };
 use elusiv_computation::PartialComputation;
 use elusiv_proc_macros::elusiv_hash_compute_units;
-use elusiv_utils::{guard, two_pow};
 use solana_program::program_error::ProgramError;
 
 pub struct BaseCommitmentHashComputation;
@@ -24,18 +25,18 @@ pub fn compute_base_commitment_hash_partial(
 ) -> Result<(), ProgramError> {
     let instruction = hashing_account.get_instruction();
     guard!(
-        (instruction as usize) < BaseCommitmentHashComputation::IX_COUNT,
+        (instruction as usize) < BASE_COMMITMENT_HASH.ix_count,
         ElusivError::ComputationIsAlreadyFinished
     );
 
     let start_round = hashing_account.get_round();
-    let rounds = BaseCommitmentHashComputation::INSTRUCTION_ROUNDS[instruction as usize] as u32;
+    let rounds = BASE_COMMITMENT_HASH.instruction_rounds[instruction as usize] as u32;
 
     let mut state = hashing_account.get_state();
 
     for round in start_round..start_round + rounds {
         guard!(
-            round < BaseCommitmentHashComputation::TOTAL_ROUNDS,
+            round < BASE_COMMITMENT_HASH.total_rounds,
             ElusivError::ComputationIsAlreadyFinished
         );
         binary_poseidon_hash_partial(round, &mut state);
@@ -60,7 +61,7 @@ pub const MAX_COMMITMENT_BATCHING_RATE: usize = 4;
 /// 2^batching_rate is the amount of commitments per batch.
 ///
 /// Batch sizes range: `[0; MAX_COMMITMENT_BATCHING_RATE]`.
-struct CommitmentHashComputation<const BATCHING_RATE: usize>;
+pub(crate) struct CommitmentHashComputation<const BATCHING_RATE: usize>;
 
 /// Generates a [`CommitmentHashComputation`] with a specific `BATCHING_RATE`
 ///
@@ -88,30 +89,15 @@ commitment_batch_hashing!(2, 21, 25);
 commitment_batch_hashing!(3, 24, 29);
 commitment_batch_hashing!(4, 31, 37);
 
-macro_rules! commitment_hash_computation {
-    ($batching_rate: ident, $field: ident) => {
-        match $batching_rate {
-            0 => &CommitmentHashComputation::<0>::$field,
-            1 => &CommitmentHashComputation::<1>::$field,
-            2 => &CommitmentHashComputation::<2>::$field,
-            3 => &CommitmentHashComputation::<3>::$field,
-            4 => &CommitmentHashComputation::<4>::$field,
-            _ => {
-                panic!()
-            }
-        }
-    };
-}
-
 pub const COMMITMENT_HASH_COMPUTE_BUDGET: u32 =
     <CommitmentHashComputation<0>>::COMPUTE_BUDGET_PER_IX;
 
-pub fn commitment_hash_computation_instructions<'a>(batching_rate: u32) -> &'a [u8] {
-    commitment_hash_computation!(batching_rate, INSTRUCTION_ROUNDS)
+pub fn commitment_hash_computation_instructions(batching_rate: u32) -> &'static [u8] {
+    computation_metadata::commitment_hash(batching_rate).instruction_rounds
 }
 
 pub fn commitment_hash_computation_rounds(batching_rate: u32) -> u32 {
-    *commitment_hash_computation!(batching_rate, TOTAL_ROUNDS)
+    computation_metadata::commitment_hash(batching_rate).total_rounds
 }
 
 pub const MT_HEIGHT: usize = crate::state::storage::MT_HEIGHT as usize;
@@ -150,15 +136,16 @@ pub fn compute_commitment_hash_partial(
 ) -> Result<(), ProgramError> {
     let batching_rate = hashing_account.get_batching_rate();
     let instruction = hashing_account.get_instruction();
-    let instructions = commitment_hash_computation_instructions(batching_rate);
+    let computation = computation_metadata::commitment_hash(batching_rate);
+    let instructions = computation.instruction_rounds;
     guard!(
-        (instruction as usize) < instructions.len(),
+        (instruction as usize) < computation.ix_count,
         ElusivError::ComputationIsAlreadyFinished
     );
 
     let start_round = hashing_account.get_round();
     let rounds = instructions[instruction as usize] as u32;
-    let total_rounds = commitment_hash_computation_rounds(batching_rate);
+    let total_rounds = computation.total_rounds;
     guard!(
         start_round + rounds <= total_rounds,
         ElusivError::ComputationIsAlreadyFinished
@@ -263,6 +250,7 @@ mod tests {
                 compute_base_commitment_hash_partial(&mut account),
                 Err(ElusivError::ComputationIsAlreadyFinished.into())
             );
+            assert_eq!(account.get_round(), BASE_COMMITMENT_HASH.total_rounds);
             assert_eq!(
                 account.get_state().result(),
                 u256_to_fr_skip_mr(&request.commitment.reduce())
@@ -321,6 +309,10 @@ mod tests {
                 compute_commitment_hash_partial(&mut account),
                 Err(ElusivError::ComputationIsAlreadyFinished.into())
             );
+            assert_eq!(
+                account.get_round(),
+                computation_metadata::commitment_hash(batching_rate).total_rounds
+            );
             assert_eq!(
                 account.get_state().result(),
                 u256_to_fr_skip_mr(&request.valid_root)
@@ -1,7 +1,7 @@
 use ark_bn254::Fr;
 use ark_ff::BigInteger256;
 #[allow(dead_code)]
-pub fn constants(round: usize) -> [Fr; 3] {
+pub const fn constants(round: usize) -> [Fr; 3] {
     match round {
         0usize => [
             Fr::new(BigInteger256([
@@ -4,8 +4,8 @@ use crate::{
     fields::{fr_to_u256_le, u256_to_fr_skip_mr},
     types::U256,
 };
-use ark_bn254::Fr;
-use ark_ff::{Field, Zero};
+use ark_bn254::{Fr, FrParameters};
+use ark_ff::{Field, FpParameters, Zero};
 use borsh::{BorshDeserialize, BorshSerialize};
 
 pub const TOTAL_POSEIDON_ROUNDS: u32 = 65;
@@ -113,6 +113,142 @@ pub fn full_poseidon2_hash(a: Fr, b: Fr) -> Fr {
     state.result()
 }
 
+/// Const-evaluable counterpart of [`full_poseidon2_hash`] operating on mr-form limbs
+/// - far too expensive for on-chain usage, but allows us to compute (and check) constants at compile time
+pub const fn const_full_poseidon2_hash(a: [u64; 4], b: [u64; 4]) -> [u64; 4] {
+    let mut state = [[0; 4], a, b];
+    let mut round = 0;
+    while round < TOTAL_POSEIDON_ROUNDS {
+        let constants = constants(round as usize);
+
+        // Ark
+        let mut i = 0;
+        while i < 3 {
+            state[i] = const_fr_add(state[i], constants[i].0 .0);
+            i += 1;
+        }
+
+        // Sbox
+        let sbox_count = if round < 4 || round >= 61 { 3 } else { 1 };
+        let mut i = 0;
+        while i < sbox_count {
+            let square = const_fr_mul(state[i], state[i]);
+            state[i] = const_fr_mul(const_fr_mul(square, square), state[i]);
+            i += 1;
+        }
+
+        // Mix
+        let mut new_state = [[0; 4]; 3];
+        let mut i = 0;
+        while i < 3 {
+            let mut j = 0;
+            while j < 3 {
+                new_state[i] =
+                    const_fr_add(new_state[i], const_fr_mul(MATRIX[i * 3 + j].0 .0, state[j]));
+                j += 1;
+            }
+            i += 1;
+        }
+        state = new_state;
+
+        round += 1;
+    }
+    state[0]
+}
+
+/// `a + b + carry` returning the result and the new carry
+const fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + b as u128 + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+/// `a + b * c + carry` returning the result and the new carry
+const fn mac(a: u64, b: u64, c: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + (b as u128) * (c as u128) + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+/// Subtracts the scalar field modulus once, if `a` is not smaller than it
+const fn const_fr_reduce(a: [u64; 4]) -> [u64; 4] {
+    let m = FrParameters::MODULUS.0;
+
+    let mut i = 4;
+    while i > 0 {
+        i -= 1;
+        if a[i] < m[i] {
+            return a;
+        }
+        if a[i] > m[i] {
+            break;
+        }
+    }
+
+    let mut r = [0; 4];
+    let mut borrow = false;
+    let mut i = 0;
+    while i < 4 {
+        let (d, b0) = a[i].overflowing_sub(m[i]);
+        let (d, b1) = d.overflowing_sub(borrow as u64);
+        r[i] = d;
+        borrow = b0 || b1;
+        i += 1;
+    }
+    r
+}
+
+const fn const_fr_add(a: [u64; 4], b: [u64; 4]) -> [u64; 4] {
+    let mut r = [0; 4];
+    let mut carry = 0;
+    let mut i = 0;
+    while i < 4 {
+        let (v, c) = adc(a[i], b[i], carry);
+        r[i] = v;
+        carry = c;
+        i += 1;
+    }
+
+    // The modulus has two spare bits, so `carry` is always zero
+    const_fr_reduce(r)
+}
+
+/// Montgomery multiplication (CIOS) of two mr-form elements
+const fn const_fr_mul(a: [u64; 4], b: [u64; 4]) -> [u64; 4] {
+    let m = FrParameters::MODULUS.0;
+    let mut t = [0; 6];
+
+    let mut i = 0;
+    while i < 4 {
+        let mut carry = 0;
+        let mut j = 0;
+        while j < 4 {
+            let (v, c) = mac(t[j], a[j], b[i], carry);
+            t[j] = v;
+            carry = c;
+            j += 1;
+        }
+        let (v, c) = adc(t[4], carry, 0);
+        t[4] = v;
+        t[5] = c;
+
+        let k = t[0].wrapping_mul(FrParameters::INV);
+        let (_, mut carry) = mac(t[0], k, m[0], 0);
+        let mut j = 1;
+        while j < 4 {
+            let (v, c) = mac(t[j], k, m[j], carry);
+            t[j - 1] = v;
+            carry = c;
+            j += 1;
+        }
+        let (v, c) = adc(t[4], carry, 0);
+        t[3] = v;
+        t[4] = t[5] + c;
+
+        i += 1;
+    }
+
+    const_fr_reduce([t[0], t[1], t[2], t[3]])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -204,6 +340,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_const_full_poseidon2_hash() {
+        let values = [
+            Fr::zero(),
+            Fr::one(),
+            Fr::from_str("4631032765893457899344").unwrap(),
+            Fr::from_str(
+                "21888242871839275222246405745257275088548364400416034343698204186575808495616",
+            )
+            .unwrap(),
+        ];
+
+        for a in values {
+            for b in values {
+                assert_eq!(
+                    const_full_poseidon2_hash(a.0 .0, b.0 .0),
+                    full_poseidon2_hash(a, b).0 .0
+                );
+            }
+        }
+    }
+
     #[test]
     fn test_new_hashing_state() {
         let a = BinarySpongeHashingState::new(Fr::zero(), Fr::one(), false);
@@ -0,0 +1,171 @@
+//! Uniform metadata of all partial computations
+//!
+//! The metadata is derived from the outputs of the `elusiv_hash_compute_units!` and `elusiv_computations!` macros,
+//! so fees, clients and the computations themselves all rely on the same facts.
+
+use crate::commitment::{
+    BaseCommitmentHashComputation, CommitmentHashComputation, MAX_COMMITMENT_BATCHING_RATE,
+};
+use crate::proof::verifier::{
+    max_prepare_public_inputs_instructions, prepare_public_inputs_rounds, CombinedMillerLoop,
+    FinalExponentiation,
+};
+use crate::proof::vkey::VerifyingKeyInfo;
+use elusiv_computation::PartialComputation;
+
+/// Metadata of a partial computation with a fixed number of instructions
+#[derive(PartialEq, Debug)]
+pub struct ComputationInfo {
+    /// Number of compute-instructions
+    pub ix_count: usize,
+
+    /// Number of transactions (a transaction can contain multiple compute-instructions)
+    pub tx_count: usize,
+
+    /// Rounds performed across all instructions
+    pub total_rounds: u32,
+
+    /// Rounds performed by each instruction
+    pub instruction_rounds: &'static [u8],
+
+    /// Compute units required by all rounds
+    pub total_compute_units: u32,
+
+    /// Compute unit limit requested for each instruction
+    pub compute_budget_per_ix: u32,
+}
+
+macro_rules! computation_info {
+    ($ty: ty) => {
+        ComputationInfo {
+            ix_count: <$ty>::IX_COUNT,
+            tx_count: <$ty>::TX_COUNT,
+            total_rounds: <$ty>::TOTAL_ROUNDS,
+            instruction_rounds: &<$ty>::INSTRUCTION_ROUNDS,
+            total_compute_units: <$ty>::TOTAL_COMPUTE_UNITS,
+            compute_budget_per_ix: <$ty>::COMPUTE_BUDGET_PER_IX,
+        }
+    };
+}
+
+pub const BASE_COMMITMENT_HASH: ComputationInfo = computation_info!(BaseCommitmentHashComputation);
+
+/// The commitment hash computations for all batching rates (indexed by the batching rate)
+pub const COMMITMENT_HASH: [ComputationInfo; MAX_COMMITMENT_BATCHING_RATE + 1] = [
+    computation_info!(CommitmentHashComputation<0>),
+    computation_info!(CommitmentHashComputation<1>),
+    computation_info!(CommitmentHashComputation<2>),
+    computation_info!(CommitmentHashComputation<3>),
+    computation_info!(CommitmentHashComputation<4>),
+];
+
+pub const COMBINED_MILLER_LOOP: ComputationInfo = computation_info!(CombinedMillerLoop);
+pub const FINAL_EXPONENTIATION: ComputationInfo = computation_info!(FinalExponentiation);
+
+/// Transactions of a proof verification, apart from the input preparation
+/// - one for the init, one for the finalization and the ones of the combined miller loop and the final exponentiation
+pub const PROOF_BASE_TX_COUNT: usize =
+    1 + COMBINED_MILLER_LOOP.tx_count + FINAL_EXPONENTIATION.tx_count + 1;
+
+/// # Panics
+///
+/// For a `batching_rate` above [`MAX_COMMITMENT_BATCHING_RATE`].
+pub fn commitment_hash(batching_rate: u32) -> &'static ComputationInfo {
+    &COMMITMENT_HASH[batching_rate as usize]
+}
+
+/// Metadata of the public input preparation of a verifying key
+///
+/// # Note
+///
+/// The instructions depend on the actual public inputs (since zero-inputs are skipped), so only upper bounds are known upfront.
+#[derive(PartialEq, Debug)]
+pub struct InputPreparationInfo {
+    pub public_inputs_count: usize,
+
+    /// Rounds required if no public input is zero
+    pub max_rounds: u32,
+
+    /// Transactions required if no public input is zero (one compute-instruction per transaction)
+    pub max_tx_count: usize,
+}
+
+pub fn input_preparation<VKey: VerifyingKeyInfo>() -> InputPreparationInfo {
+    let public_inputs_count = VKey::public_inputs_count();
+
+    InputPreparationInfo {
+        public_inputs_count,
+        max_rounds: prepare_public_inputs_rounds(public_inputs_count) as u32,
+        max_tx_count: max_prepare_public_inputs_instructions(public_inputs_count),
+    }
+}
+
+// The instructions of each commitment hash computation fit into the compute unit limit
+#[cfg(test)]
+const_assert!(
+    BASE_COMMITMENT_HASH.compute_budget_per_ix <= elusiv_computation::MAX_COMPUTE_UNIT_LIMIT
+);
+#[cfg(test)]
+const_assert!(
+    COMMITMENT_HASH[0].compute_budget_per_ix <= elusiv_computation::MAX_COMPUTE_UNIT_LIMIT
+);
+
+#[cfg(test)]
+const_assert_eq!(PROOF_BASE_TX_COUNT, 62);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proof::vkey::{MigrateUnaryVKey, SendQuadraVKey};
+
+    #[test]
+    fn test_computation_info() {
+        let computations = COMMITMENT_HASH.iter().chain([
+            &BASE_COMMITMENT_HASH,
+            &COMBINED_MILLER_LOOP,
+            &FINAL_EXPONENTIATION,
+        ]);
+
+        for info in computations {
+            assert_eq!(info.instruction_rounds.len(), info.ix_count);
+            assert_eq!(
+                info.instruction_rounds
+                    .iter()
+                    .map(|&rounds| rounds as u32)
+                    .sum::<u32>(),
+                info.total_rounds
+            );
+            assert!(info.tx_count <= info.ix_count);
+        }
+    }
+
+    #[test]
+    fn test_commitment_hash() {
+        for batching_rate in 0..=MAX_COMMITMENT_BATCHING_RATE as u32 {
+            assert_eq!(
+                commitment_hash(batching_rate),
+                &COMMITMENT_HASH[batching_rate as usize]
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_commitment_hash_invalid_batching_rate() {
+        commitment_hash(MAX_COMMITMENT_BATCHING_RATE as u32 + 1);
+    }
+
+    #[test]
+    fn test_input_preparation() {
+        for info in [
+            input_preparation::<SendQuadraVKey>(),
+            input_preparation::<MigrateUnaryVKey>(),
+        ] {
+            assert!(info.max_tx_count > 0);
+            assert_eq!(
+                info.max_rounds as usize,
+                prepare_public_inputs_rounds(info.public_inputs_count)
+            );
+        }
+    }
+}
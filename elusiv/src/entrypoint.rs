@@ -1,7 +1,11 @@
-use crate::instruction;
+use crate::bytes::BorshSerDeSized;
+use crate::error::ElusivError;
+use crate::instruction::{self, ElusivInstruction};
+use crate::types::Proof;
 use borsh::BorshDeserialize;
+use elusiv_types::accounts::AccountVersionError;
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    account_info::AccountInfo, entrypoint::ProgramResult, msg, program_error::ProgramError,
     pubkey::Pubkey,
 };
 
@@ -34,10 +38,182 @@ pub fn process_instruction(
         return Err(ProgramError::InvalidInstructionData);
     }
 
+    validate_proof_data_size(instruction_data)?;
+
     match instruction::ElusivInstruction::deserialize(&mut &instruction_data[..]) {
         Ok(instruction) => {
             instruction::ElusivInstruction::process(program_id, accounts, instruction)
+                .map_err(map_account_version_error)
         }
         Err(_) => Err(ProgramError::InvalidInstructionData),
     }
 }
+
+/// Reports accounts written by a newer program version as [`ElusivError::AccountVersionTooNew`]
+///
+/// # Notes
+///
+/// The version checks of the instruction accounts are shared with other programs and return an [`AccountVersionError`].
+fn map_account_version_error(err: ProgramError) -> ProgramError {
+    if err == AccountVersionError::TooNew.into() {
+        ElusivError::AccountVersionTooNew.into()
+    } else {
+        err
+    }
+}
+
+/// Size of the `InitVerificationProof` instruction data (tag, verification account index, proof)
+const INIT_VERIFICATION_PROOF_DATA_SIZE: usize = 1 + 1 + Proof::SIZE;
+
+/// Ensures that the proof segment of an `InitVerificationProof` instruction has exactly `Proof::SIZE` bytes
+///
+/// # Notes
+///
+/// Borsh deserialization ignores trailing bytes and only yields a generic error for short data.
+fn validate_proof_data_size(instruction_data: &[u8]) -> ProgramResult {
+    if instruction_data[0] != ElusivInstruction::INIT_VERIFICATION_PROOF_INDEX {
+        return Ok(());
+    }
+
+    if instruction_data.len() != INIT_VERIFICATION_PROOF_DATA_SIZE {
+        msg!(
+            "Proof must be exactly {} bytes (got {})",
+            Proof::SIZE,
+            instruction_data.len().saturating_sub(2)
+        );
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::account_info;
+    use crate::proof::verifier::proof_from_str;
+    use crate::state::governor::GovernorAccount;
+    use crate::state::program_account::{PDAAccount, PDAAccountData, SizedAccount};
+    use borsh::BorshSerialize;
+    use solana_program::pubkey::Pubkey;
+
+    fn init_verification_proof_data() -> Vec<u8> {
+        let proof = proof_from_str(
+            (
+                "10026859857882131638516328056627849627085232677511724829502598764489185541935",
+                "19685960310506634721912121951341598678325833230508240750559904196809564625591",
+                false,
+            ),
+            (
+                (
+                    "857882131638516328056627849627085232677511724829502598764489185541935",
+                    "685960310506634721912121951341598678325833230508240750559904196809564625591",
+                ),
+                (
+                    "837064132573119120838379738103457054645361649757131991036638108422638197362",
+                    "86803555845400161937398579081414146527572885637089779856221229551142844794",
+                ),
+                false,
+            ),
+            (
+                "21186803555845400161937398579081414146527572885637089779856221229551142844794",
+                "85960310506634721912121951341598678325833230508240750559904196809564625591",
+                false,
+            ),
+        );
+
+        ElusivInstruction::InitVerificationProof {
+            verification_account_index: 0,
+            proof,
+        }
+        .try_to_vec()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_proof_data_size() {
+        let data = init_verification_proof_data();
+        assert_eq!(data.len(), INIT_VERIFICATION_PROOF_DATA_SIZE);
+        assert_eq!(validate_proof_data_size(&data), Ok(()));
+
+        // Under-length
+        assert_eq!(
+            validate_proof_data_size(&data[..data.len() - 1]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+        assert_eq!(
+            validate_proof_data_size(&data[..1]),
+            Err(ProgramError::InvalidInstructionData)
+        );
+
+        // Over-length
+        let mut data = data;
+        data.push(0);
+        assert_eq!(
+            validate_proof_data_size(&data),
+            Err(ProgramError::InvalidInstructionData)
+        );
+    }
+
+    #[test]
+    fn test_validate_proof_data_size_other_instructions() {
+        let data = ElusivInstruction::ComputeBaseCommitmentHash {
+            hash_account_index: 0,
+        }
+        .try_to_vec()
+        .unwrap();
+        assert_eq!(validate_proof_data_size(&data), Ok(()));
+
+        let mut data = data;
+        data.push(0);
+        assert_eq!(validate_proof_data_size(&data), Ok(()));
+    }
+
+    #[test]
+    fn test_process_instruction_account_version_too_new() {
+        let (governor_pubkey, bump_seed) = GovernorAccount::find(None);
+        let mut data = vec![0; GovernorAccount::SIZE];
+        PDAAccountData {
+            bump_seed,
+            version: GovernorAccount::CURRENT_VERSION + 1,
+        }
+        .serialize(&mut &mut data[..])
+        .unwrap();
+
+        account_info!(sol_price_account, Pubkey::new_unique());
+        account_info!(token_price_account, Pubkey::new_unique());
+        account_info!(governor, governor_pubkey, data);
+
+        let instruction_data = ElusivInstruction::QuoteBaseCommitmentFee {
+            token_id: 0,
+            amount: 0,
+        }
+        .try_to_vec()
+        .unwrap();
+
+        assert_eq!(
+            process_instruction(
+                &crate::id(),
+                &[sol_price_account, token_price_account, governor],
+                &instruction_data
+            ),
+            Err(ElusivError::AccountVersionTooNew.into())
+        );
+    }
+
+    #[test]
+    fn test_map_account_version_error() {
+        assert_eq!(
+            map_account_version_error(AccountVersionError::TooNew.into()),
+            ElusivError::AccountVersionTooNew.into()
+        );
+        assert_eq!(
+            map_account_version_error(AccountVersionError::Outdated.into()),
+            AccountVersionError::Outdated.into()
+        );
+        assert_eq!(
+            map_account_version_error(ProgramError::InvalidArgument),
+            ProgramError::InvalidArgument
+        );
+    }
+}
@@ -1,10 +1,17 @@
+#[cfg(feature = "error-log")]
+use elusiv_types::TokenError;
+#[cfg(feature = "error-log")]
+use solana_program::msg;
 use solana_program::program_error::ProgramError;
 use std::fmt;
 
 pub type ElusivResult = Result<(), ElusivError>;
 
 #[derive(Copy, Clone, PartialEq)]
-#[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
+#[cfg_attr(
+    any(test, feature = "elusiv-client", feature = "error-log"),
+    derive(Debug)
+)]
 pub enum ElusivError {
     InvalidInstructionData,
     InputsMismatch,
@@ -59,8 +66,69 @@ pub enum ElusivError {
     // Accounts
     ChildAccountAlreadyExists,
     ChildAccouttDoesNotExists,
+
+    // Verification
+    ProofNotSubmitted,
+    ComputationInProgress,
+
+    // Commitment hashing
+    ComputationCorrupted,
+
+    // Queue
+    CommitmentQueueCongested,
+
+    // Verification boost
+    TooManyBoosts,
+
+    // Warden rewards
+    NoClaimableRewards,
+
+    // Downgrades
+    UnsupportedAccountState,
+
+    // Input preparation
+    InputPreparationInstructionsExceeded,
+
+    // Verification account reuse
+    VerificationIndexInUse,
+
+    // Lamports sends
+    RecipientBelowRentExemption,
+
+    // Key-only accounts
+    WritableOrSigningKeyOnlyAccount,
+
+    // Fee updates
+    FeeUpdateAlreadyScheduled,
+
+    // MT rollover
+    MaxTreesReached,
+
+    // Partial computations
+    CorruptedComputationSchedule,
+
+    // Delayed sends
+    FinalizationDelayed,
+    CancellationWindowClosed,
+
+    // Fee-version retirement
+    FeeVersionInUse,
+
+    // Degraded oracle
+    OracleUnavailable,
+
+    // Storage initialization
+    StorageNotFullyInitialized,
+
+    // Account migrations
+    AccountVersionTooNew,
 }
 
+// Error codes must not collide with the `TokenError` codes (checked for the last variant)
+const _: () = assert!(
+    (ElusivError::AccountVersionTooNew as u32) < elusiv_types::constants::TOKEN_ERROR_CODES_START
+);
+
 #[cfg(not(tarpaulin_include))]
 impl From<ElusivError> for ProgramError {
     fn from(e: ElusivError) -> Self {
@@ -75,6 +143,45 @@ impl fmt::Display for ElusivError {
     }
 }
 
+#[cfg(feature = "error-log")]
+impl ElusivError {
+    /// Logs the error together with a `context` before converting it into a [`ProgramError`]
+    pub fn into_program_error_with_log(self, context: &str) -> ProgramError {
+        self.log(context).into()
+    }
+}
+
+/// Errors raised by `guard!` with the `error-log` feature enabled
+#[cfg(feature = "error-log")]
+pub trait GuardError: Sized {
+    /// Logs the error together with a `context`
+    fn log(self, context: &str) -> Self;
+}
+
+#[cfg(feature = "error-log")]
+impl GuardError for ElusivError {
+    fn log(self, context: &str) -> Self {
+        msg!("ElusivError::{:?} in {}", self, context);
+        self
+    }
+}
+
+#[cfg(feature = "error-log")]
+impl GuardError for TokenError {
+    fn log(self, context: &str) -> Self {
+        msg!("TokenError::{:?} in {}", self, context);
+        self
+    }
+}
+
+#[cfg(feature = "error-log")]
+impl GuardError for ProgramError {
+    fn log(self, context: &str) -> Self {
+        msg!("{} in {}", self, context);
+        self
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -84,4 +191,14 @@ mod test {
     fn test_sdk_error_codes() {
         assert_eq!(ProgramError::Custom(105), TokenError::PriceError.into());
     }
+
+    #[cfg(feature = "error-log")]
+    #[test]
+    fn test_into_program_error_with_log() {
+        assert_eq!(
+            ElusivError::InvalidAccount.into_program_error_with_log("test"),
+            ElusivError::InvalidAccount.into()
+        );
+        assert_eq!(TokenError::PriceError.log("test"), TokenError::PriceError);
+    }
 }
@@ -9,18 +9,29 @@ use crate::state::{
         BaseCommitmentBufferAccount, BaseCommitmentHashingAccount, CommitmentBufferAccount,
         CommitmentHashingAccount, CommitmentQueueAccount,
     },
-    fee::{FeeAccount, ProgramFee},
-    governor::{FeeCollectorAccount, GovernorAccount, PoolAccount},
+    commitment_index::CommitmentIndexAccount,
+    fee::{FeeAccount, FeeVersionUsageAccount, ProgramFee},
+    governor::{FeeCollectorAccount, GovernorAccount, LogLevel, PoolAccount},
     metadata::{CommitmentMetadata, MetadataAccount, MetadataQueueAccount},
+    note::EncryptedNote,
     nullifier::NullifierAccount,
-    proof::VerificationAccount,
+    proof::{ExternalReference, VerificationAccount},
     storage::StorageAccount,
     vkey::VKeyAccount,
+    warden::WardenRewardsAccount,
 };
-use crate::types::Proof;
+use crate::types::{Proof, U256};
 use borsh::{BorshDeserialize, BorshSerialize};
 use elusiv_types::{AccountRepr, ElusivOption};
-use solana_program::{pubkey::Pubkey, system_program, sysvar::instructions};
+use solana_program::{
+    pubkey::Pubkey,
+    system_program,
+    sysvar::{clock, instructions},
+};
+
+// Only required for the PDA derivation of the `account_info` note-log in the client instruction builders
+#[cfg(feature = "elusiv-client")]
+use crate::state::note::NoteLogAccount;
 
 #[cfg(feature = "elusiv-client")]
 pub use elusiv_types::accounts::{
@@ -49,6 +60,7 @@ pub enum ElusivInstruction {
     #[pda(buffer, BaseCommitmentBufferAccount, { writable })]
     #[acc(token_program)] // if `token_id = 0` { `system_program` } else { `token_program` }
     #[sys(system_program, key = system_program::ID)]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable })]
     StoreBaseCommitment {
         hash_account_index: u32,
         hash_account_bump: u8,
@@ -90,6 +102,9 @@ pub enum ElusivInstruction {
 
     #[pda(commitment_hashing_account, CommitmentHashingAccount, { writable })]
     #[pda(storage_account, StorageAccount, { include_child_accounts, writable })]
+    #[pda(commitment_index, CommitmentIndexAccount, { writable, include_child_accounts })]
+    #[pda(governor, GovernorAccount)]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable })]
     FinalizeCommitmentHash,
 
     // -------- Proof Verification --------
@@ -98,22 +113,32 @@ pub enum ElusivInstruction {
     #[pda(verification_account, VerificationAccount, pda_pubkey = fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable, account_info, find_pda })]
     #[pda(vkey_account, VKeyAccount, pda_offset = Some(vkey_id))]
     #[acc(nullifier_duplicate_account, { writable })]
+    #[acc(raced_verification_account)] // only required if `skip_nullifier_pda`
     #[sys(system_program, key = system_program::ID, { ignore })]
-    #[acc(identifier_account)]
+    #[acc(identifier_account)] // key-only: never writable or a signer
+    #[acc(identifier_lock_account, { writable })] // only required if `lock_identifier`
     #[pda(storage_account, StorageAccount)]
     #[pda(buffer, CommitmentBufferAccount, { writable })]
     #[pda(nullifier_account0, NullifierAccount, pda_offset = Some(tree_indices[0]), { include_child_accounts })]
     #[pda(nullifier_account1, NullifierAccount, pda_offset = Some(tree_indices[1]), { include_child_accounts })]
+    #[sys(clock, key = clock::ID)]
+    #[pda(governor, GovernorAccount)]
     InitVerification {
         verification_account_index: u8,
         vkey_id: u32,
         tree_indices: [u32; MAX_MT_COUNT],
         request: ProofRequest,
         skip_nullifier_pda: bool,
+        external_reference: ExternalReference,
+        lock_identifier: bool,
+        rent_beneficiary: ElusivOption<Pubkey>,
+        encrypted_note: ElusivOption<EncryptedNote>,
+        earliest_finalize_time: u64,
     },
 
     #[acc(fee_payer, { writable, signer })]
     #[acc(fee_payer_account, { writable })]
+    #[acc(original_fee_payer, { ignore })]
     #[pda(pool, PoolAccount, { writable, account_info })]
     #[acc(pool_account, { writable })]
     #[pda(fee_collector, FeeCollectorAccount, { writable, account_info })]
@@ -121,23 +146,30 @@ pub enum ElusivInstruction {
     #[acc(sol_price_account)]
     #[acc(token_price_account)]
     #[pda(governor, GovernorAccount)]
-    #[pda(verification_account, VerificationAccount, pda_pubkey = fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
+    #[pda(commitment_hash_queue, CommitmentQueueAccount, { writable })]
+    #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
     #[acc(token_program)] // if `token_id = 0` { `system_program` } else { `token_program` }
     #[sys(system_program, key = system_program::ID)]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable })]
+    #[sys(clock, key = clock::ID)]
     InitVerificationTransferFee { verification_account_index: u8 },
 
     #[acc(fee_payer, { signer })]
-    #[pda(verification_account, VerificationAccount, pda_pubkey = fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
+    #[acc(original_fee_payer, { ignore })]
+    #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
     InitVerificationProof {
         verification_account_index: u8,
         proof: Proof,
     },
 
     /// Proof verification computation
+    #[acc(warden, { writable, signer })]
     #[acc(original_fee_payer, { ignore })]
     #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
     #[pda(vkey_account, VKeyAccount, pda_offset = Some(vkey_id), { include_child_accounts })]
     #[sys(instructions_account, key = instructions::ID)]
+    #[pda(pool, PoolAccount, { writable, account_info })]
+    #[sys(system_program, key = system_program::ID)]
     ComputeVerification {
         verification_account_index: u8,
         vkey_id: u32,
@@ -145,14 +177,17 @@ pub enum ElusivInstruction {
 
     /// Finalizing proofs
     #[acc(recipient)]
-    #[acc(identifier_account)]
-    #[acc(transaction_reference_account)]
+    #[acc(identifier_account)] // key-only: never writable or a signer
+    #[acc(transaction_reference_account)] // key-only: never writable or a signer
     #[acc(original_fee_payer, { ignore })]
     #[pda(commitment_hash_queue, CommitmentQueueAccount, { writable })]
     #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
     #[pda(storage_account, StorageAccount)]
     #[pda(buffer, CommitmentBufferAccount, { writable })]
     #[sys(instructions_account, key = instructions::ID)]
+    #[pda(note_log, NoteLogAccount, { writable, skip_pda_verification, account_info })]
+    // only required if the verification carries an encrypted note
+    #[sys(clock, key = clock::ID)]
     FinalizeVerificationSend {
         verification_account_index: u8,
         data: FinalizeSendData,
@@ -161,10 +196,12 @@ pub enum ElusivInstruction {
 
     #[acc(original_fee_payer, { ignore })]
     #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
-    #[pda(nullifier_account, NullifierAccount, pda_offset = Some(verification_account.get_tree_indices(0)), { writable, include_child_accounts, skip_abi })]
+    #[pda(nullifier_account, NullifierAccount, pda_offset = Some(processor::nullifier_insertion_tree_index(&verification_account)), { writable, include_child_accounts, skip_abi })]
     FinalizeVerificationInsertNullifier { verification_account_index: u8 },
 
-    #[acc(original_fee_payer, { signer, writable })]
+    #[acc(original_fee_payer, { ignore })]
+    #[acc(fee_payer, { signer, writable })]
+    #[acc(rent_beneficiary, { writable })]
     #[acc(recipient, { writable })]
     #[pda(pool, PoolAccount, { account_info, writable })]
     #[pda(fee_collector, FeeCollectorAccount, { account_info, writable })]
@@ -173,12 +210,21 @@ pub enum ElusivInstruction {
     #[pda(metadata_queue, MetadataQueueAccount, { writable })]
     #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable, account_info })]
     #[acc(nullifier_duplicate_account, { writable, owned })]
+    #[acc(identifier_lock_account, { writable })] // only required if the identifier is locked
     #[sys(system_program, key = system_program::ID, { ignore })]
     #[sys(instructions_account, key = instructions::ID)]
+    #[pda(governor, GovernorAccount)]
+    #[pda(warden_rewards, WardenRewardsAccount, pda_pubkey = fee_payer.pubkey(), { writable, account_info, find_pda })] // only used if opened by `fee_payer`
+    #[sys(clock, key = clock::ID)]
+    #[acc(booster0, { writable })] // only required for refunding an invalid proof's boosts
+    #[acc(booster1, { writable })]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable })]
     FinalizeVerificationTransferLamports { verification_account_index: u8 },
 
-    #[acc(original_fee_payer, { signer, writable })]
-    #[acc(original_fee_payer_account, { writable })]
+    #[acc(original_fee_payer, { ignore })]
+    #[acc(fee_payer, { signer, writable })]
+    #[acc(fee_payer_account, { writable })]
+    #[acc(rent_beneficiary, { writable })]
     #[acc(recipient, { writable })]
     #[acc(recipient_wallet)]
     #[pda(pool, PoolAccount, { account_info, writable })]
@@ -190,11 +236,18 @@ pub enum ElusivInstruction {
     #[pda(metadata_queue, MetadataQueueAccount, { writable })]
     #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable, account_info })]
     #[acc(nullifier_duplicate_account, { writable, owned })]
+    #[acc(identifier_lock_account, { writable })] // only required if the identifier is locked
     #[sys(a_token_program, key = spl_associated_token_account::ID, { ignore })]
     #[sys(token_program, key = spl_token::ID)]
     #[sys(system_program, key = system_program::ID, { ignore })]
     #[acc(mint_account)]
     #[sys(instructions_account, key = instructions::ID)]
+    #[acc(booster0, { writable })] // only required for refunding an invalid proof's boosts
+    #[acc(booster1, { writable })]
+    #[pda(governor, GovernorAccount)]
+    #[pda(warden_rewards, WardenRewardsAccount, pda_pubkey = fee_payer.pubkey(), { writable, account_info, find_pda })] // only used if opened by `fee_payer`
+    #[sys(clock, key = clock::ID)]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable })]
     FinalizeVerificationTransferToken { verification_account_index: u8 },
 
     // -------- Verifying key management --------
@@ -316,6 +369,245 @@ pub enum ElusivInstruction {
     #[sys(system_program, key = system_program::ID, { ignore })]
     CreateNewAccountsV1,
 
+    /// Tops up the reward of an in-progress verification
+    #[acc(booster, { writable, signer })]
+    #[acc(original_fee_payer, { ignore })]
+    #[pda(pool, PoolAccount, { writable, account_info })]
+    #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
+    #[sys(system_program, key = system_program::ID)]
+    BoostVerification {
+        verification_account_index: u8,
+        amount: u64,
+    },
+
+    /// Opens the account in which the proof verification rewards of `warden` accrue (if epoch rewards are enabled)
+    #[acc(warden, { writable, signer })]
+    #[pda(warden_rewards, WardenRewardsAccount, pda_pubkey = warden.pubkey(), { writable, account_info, find_pda })]
+    #[sys(system_program, key = system_program::ID, { ignore })]
+    OpenWardenRewardsAccount,
+
+    /// Transfers the rewards of `token_id` a warden accrued in all completed epochs
+    #[acc(warden, { signer })]
+    #[acc(warden_account, { writable })] // if `token_id = 0` { `warden` } else { token account of `warden` }
+    #[pda(pool, PoolAccount, { writable, account_info })]
+    #[acc(pool_account, { writable })]
+    #[pda(governor, GovernorAccount)]
+    #[pda(warden_rewards, WardenRewardsAccount, pda_pubkey = warden.pubkey(), { writable })]
+    #[sys(clock, key = clock::ID)]
+    #[acc(token_program)] // if `token_id = 0` { `system_program` } else { `token_program` }
+    ClaimEpochRewards { token_id: u16 },
+
+    /// Fails a verification that exceeded the verification TTL (settled by the regular finalization)
+    #[acc(original_fee_payer, { ignore })]
+    #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
+    #[pda(governor, GovernorAccount)]
+    #[sys(clock, key = clock::ID)]
+    ExpireVerification { verification_account_index: u8 },
+
+    // -------- Balance reports --------
+    /// Writes a snapshot of the pool balances into a program-owned `report_account`
+    /// - the token accounts are ordered by their token-id
+    #[pda(pool, PoolAccount, { account_info })]
+    #[acc(pool_token_account1)]
+    #[acc(pool_token_account2)]
+    #[acc(pool_token_account3)]
+    #[acc(pool_token_account4)]
+    #[acc(pool_token_account5)]
+    #[acc(pool_token_account6)]
+    #[acc(pool_token_account7)]
+    #[acc(pool_token_account8)]
+    #[acc(report_account, { signer, writable, owned })]
+    #[sys(clock, key = clock::ID)]
+    ReportPoolBalances { emit_event: bool },
+
+    /// Writes a snapshot of the fee collector balances into a program-owned `report_account`
+    #[pda(fee_collector, FeeCollectorAccount, { account_info })]
+    #[acc(fee_collector_token_account1)]
+    #[acc(fee_collector_token_account2)]
+    #[acc(fee_collector_token_account3)]
+    #[acc(fee_collector_token_account4)]
+    #[acc(fee_collector_token_account5)]
+    #[acc(fee_collector_token_account6)]
+    #[acc(fee_collector_token_account7)]
+    #[acc(fee_collector_token_account8)]
+    #[acc(report_account, { signer, writable, owned })]
+    #[sys(clock, key = clock::ID)]
+    ReportFeeCollectorBalances { emit_event: bool },
+
+    // -------- Downgrades --------
+    /// Fails a verification with an unknown state after the verification TTL (settled by the regular finalization)
+    #[acc(authority, { signer })]
+    #[acc(original_fee_payer, { ignore })]
+    #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
+    #[pda(governor, GovernorAccount)]
+    #[sys(clock, key = clock::ID)]
+    ForceSettleUnknownState { verification_account_index: u8 },
+
+    // -------- Views --------
+    /// Returns the commitment at `leaf_index` of the active MT as return data
+    #[pda(storage_account, StorageAccount, { include_child_accounts })]
+    ViewCommitment { leaf_index: u32 },
+
+    // -------- Governance --------
+    /// Sets the maximum join-split amount of sends in `token_id`-Token (zero: no limit)
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetMaxTokenAmount { token_id: u16, max: u64 },
+
+    /// Logs all supported tokens (see [`crate::processor::token_list`])
+    ListTokens,
+
+    /// Sets the verbosity of the program logs
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetLogLevel { log_level: LogLevel },
+
+    /// Quarantines corrupted requests of the commitment queue (see [`crate::processor::validate_commitment_queue`])
+    #[pda(commitment_hash_queue, CommitmentQueueAccount, { writable })]
+    #[pda(metadata_queue, MetadataQueueAccount, { writable })]
+    #[pda(governor, GovernorAccount)]
+    ValidateCommitmentQueue { max_entries: u32 },
+
+    /// Discards or force-hashes a quarantined commitment
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount)]
+    #[pda(commitment_hash_queue, CommitmentQueueAccount, { writable })]
+    #[pda(metadata_queue, MetadataQueueAccount, { writable })]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable })]
+    SettleQuarantinedCommitment {
+        quarantine_index: u32,
+        force_hash: bool,
+    },
+
+    /// Closes the child-accounts of a fully-spent closed MT's `NullifierAccount` (the root is retained)
+    #[acc(authority, { signer })]
+    #[pda(storage_account, StorageAccount)]
+    #[pda(nullifier_account, NullifierAccount, pda_offset = Some(mt_index), { writable, include_child_accounts })]
+    #[pda(fee_collector, FeeCollectorAccount, { writable, account_info })]
+    DecommissionNullifierAccount { mt_index: u32 },
+
+    /// Schedules the `ProgramFee` of the next fee-version (accepted for proofs from `activation_slot` on)
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    #[sys(clock, key = clock::ID)]
+    #[pda(fee_version_usage, FeeVersionUsageAccount)]
+    ScheduleFeeUpdate {
+        program_fee: ProgramFee,
+        activation_slot: u64,
+    },
+
+    /// Cancels a scheduled fee update before its activation
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    #[sys(clock, key = clock::ID)]
+    CancelScheduledFeeUpdate,
+
+    #[acc(payer, { writable, signer })]
+    #[pda(note_log, NoteLogAccount, { writable, skip_pda_verification, account_info })]
+    #[sys(system_program, key = system_program::ID, { ignore })]
+    CreateNewAccountsV3,
+
+    /// Enables or disables the encrypted notes of new verifications (see [`crate::state::note::NoteLogAccount`])
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetNoteLogEnabled { enabled: bool },
+
+    #[acc(payer, { writable, signer })]
+    #[pda(commitment_index, CommitmentIndexAccount, { writable, skip_pda_verification, account_info })]
+    #[pda(storage_account, StorageAccount)]
+    #[sys(system_program, key = system_program::ID, { ignore })]
+    CreateNewAccountsV4,
+
+    #[pda(commitment_index, CommitmentIndexAccount, { writable })]
+    #[acc(child_account, { owned, writable })]
+    EnableCommitmentIndexChildAccount { child_index: u32 },
+
+    /// Returns the leaf index of `commitment` in the active MT as return data (see [`crate::state::commitment_index`])
+    #[pda(storage_account, StorageAccount)]
+    #[pda(commitment_index, CommitmentIndexAccount, { include_child_accounts })]
+    LookupCommitment { commitment: U256 },
+
+    /// Cancels a delayed send before its `earliest_finalize_time` (settled by the regular finalization)
+    #[acc(identifier, { signer })]
+    #[acc(original_fee_payer, { ignore })]
+    #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable })]
+    #[sys(clock, key = clock::ID)]
+    CancelDelayedSend { verification_account_index: u8 },
+
+    #[acc(payer, { writable, signer })]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable, skip_pda_verification, account_info })]
+    #[pda(governor, GovernorAccount)]
+    #[sys(system_program, key = system_program::ID, { ignore })]
+    CreateNewAccountsV5,
+
+    /// Retires the oldest tracked fee-version once it has no pending uses (see [`crate::state::fee::FeeVersionUsageAccount`])
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount)]
+    #[pda(fee_version_usage, FeeVersionUsageAccount, { writable })]
+    RetireFeeVersion { fee_version: u32 },
+
+    /// Marks the price accounts as (un)available, only Lamports requests are admitted while they are unavailable
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetOracleDegraded { oracle_degraded: bool },
+
+    /// Returns the fee of a deposit (net of the subvention) as return data
+    #[acc(sol_price_account)]
+    #[acc(token_price_account)]
+    #[pda(governor, GovernorAccount)]
+    QuoteBaseCommitmentFee { token_id: u16, amount: u64 },
+
+    /// Transfers an in-progress verification (and the rewards of its finalization) to `new_fee_payer`
+    #[acc(fee_payer, { writable, signer })]
+    #[acc(new_fee_payer, { writable, signer })]
+    #[acc(new_fee_payer_account)]
+    #[acc(original_fee_payer, { ignore })]
+    #[pda(verification_account, VerificationAccount, pda_pubkey = original_fee_payer.pubkey(), pda_offset = Some(verification_account_index.into()), { writable, account_info })]
+    #[acc(nullifier_duplicate_account)]
+    #[acc(identifier_lock_account)] // only required if the identifier is locked
+    #[sys(system_program, key = system_program::ID)]
+    TransferVerificationOwnership { verification_account_index: u8 },
+
+    /// Sets the number of slots after which an incomplete verification can be expired (zero: verifications never expire)
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetVerificationTtlSlots { verification_ttl_slots: u64 },
+
+    /// Sets the number of slots per warden-reward epoch (zero: rewards are paid per proof)
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetWardenRotationEpoch { warden_rotation_epoch: u64 },
+
+    /// Sets the maximum proof-subvention in basis points of the join-split amount (zero: no cap)
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetProofSubventionCap { proof_subvention_cap: u64 },
+
+    /// Sets the commitment queue length above which no new proof verifications are admitted (zero: the queue capacity)
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    SetCommitmentQueueHighWaterMark {
+        commitment_queue_high_water_mark: u32,
+    },
+
+    /// Sets the grace window of scheduled fee updates (see [`crate::state::governor::GovernorAccount`])
+    #[acc(authority, { signer })]
+    #[pda(governor, GovernorAccount, { writable })]
+    #[sys(clock, key = clock::ID)]
+    SetFeeVersionGraceSlots { fee_version_grace_slots: u64 },
+
+    /// Resizes the `GovernorAccount` to the current data layout and migrates it to the current version
+    #[acc(payer, { writable, signer })]
+    #[pda(governor, GovernorAccount, { writable, account_info })]
+    #[sys(system_program, key = system_program::ID)]
+    MigrateGovernorAccount,
+
+    /// Resizes the `FeeAccount` of `fee_version` to the current data layout and migrates it to the current version
+    #[acc(payer, { writable, signer })]
+    #[pda(fee, FeeAccount, pda_offset = Some(fee_version), { writable, account_info })]
+    #[sys(system_program, key = system_program::ID)]
+    MigrateFeeAccount { fee_version: u32 },
+
     // -------- NOP --------
     /// NOP-instruction
     Nop,
@@ -360,6 +652,7 @@ impl ElusivInstruction {
             verification_account_index,
             WritableSignerAccount(warden),
             WritableUserAccount(warden),
+            UserAccount(warden),
             WritableUserAccount(PoolAccount::find(None).0),
             WritableUserAccount(FeeCollectorAccount::find(None).0),
             UserAccount(spl_token::id()),
@@ -382,6 +675,7 @@ impl ElusivInstruction {
             verification_account_index,
             WritableSignerAccount(warden),
             WritableUserAccount(warden_account),
+            UserAccount(warden),
             WritableUserAccount(pool_account),
             WritableUserAccount(fee_collector_account),
             UserAccount(elusiv_token(0).unwrap().pyth_usd_price_key),
@@ -391,9 +685,40 @@ impl ElusivInstruction {
     }
 }
 
+#[cfg(feature = "elusiv-client")]
+impl std::fmt::Display for ElusivInstruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.short_description())
+    }
+}
+
+// Instruction tags tracked by the warden-network
+const _: () = {
+    use elusiv_types::constants::elusiv_instruction_tags;
+
+    assert!(
+        ElusivInstruction::FINALIZE_BASE_COMMITMENT_HASH_INDEX
+            == elusiv_instruction_tags::FINALIZE_BASE_COMMITMENT_HASH
+    );
+    assert!(
+        ElusivInstruction::FINALIZE_VERIFICATION_TRANSFER_LAMPORTS_INDEX
+            == elusiv_instruction_tags::FINALIZE_VERIFICATION_TRANSFER_LAMPORTS
+    );
+    assert!(
+        ElusivInstruction::FINALIZE_VERIFICATION_TRANSFER_TOKEN_INDEX
+            == elusiv_instruction_tags::FINALIZE_VERIFICATION_TRANSFER_TOKEN
+    );
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fields::u256_from_str_skip_mr;
+    use crate::proof::verifier::proof_from_str;
+    use crate::proof::vkey::{SendQuadraVKey, VerifyingKeyInfo};
+    use crate::types::{
+        InputCommitment, JoinSplitPublicInputs, OptionalFee, RawU256, SendPublicInputs,
+    };
 
     macro_rules! get_variant_tag {
         ($v: expr) => {
@@ -425,4 +750,522 @@ mod tests {
             ElusivInstruction::FINALIZE_VERIFICATION_TRANSFER_TOKEN_INDEX
         );
     }
+
+    #[test]
+    fn test_short_description() {
+        let proof = proof_from_str(
+            (
+                "10026859857882131638516328056627849627085232677511724829502598764489185541935",
+                "19685960310506634721912121951341598678325833230508240750559904196809564625591",
+                false,
+            ),
+            (
+                (
+                    "857882131638516328056627849627085232677511724829502598764489185541935",
+                    "685960310506634721912121951341598678325833230508240750559904196809564625591",
+                ),
+                (
+                    "837064132573119120838379738103457054645361649757131991036638108422638197362",
+                    "86803555845400161937398579081414146527572885637089779856221229551142844794",
+                ),
+                false,
+            ),
+            (
+                "21186803555845400161937398579081414146527572885637089779856221229551142844794",
+                "85960310506634721912121951341598678325833230508240750559904196809564625591",
+                false,
+            ),
+        );
+        let request = ProofRequest::Send(SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(RawU256::new(u256_from_str_skip_mr("22"))),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("333")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("44444")),
+                recent_commitment_index: 0,
+                fee_version: 0,
+                amount: 0,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            hashed_inputs: [0; 32],
+            recipient_is_associated_token_account: false,
+            solana_pay_transfer: false,
+        });
+        let base_commitment_request = BaseCommitmentHashRequest {
+            base_commitment: RawU256::new([0; 32]),
+            recent_commitment_index: 0,
+            amount: 0,
+            token_id: 0,
+            commitment: RawU256::new([0; 32]),
+            fee_version: 0,
+            min_batching_rate: 0,
+        };
+
+        let cases = [
+            (
+                ElusivInstruction::StoreBaseCommitment {
+                    hash_account_index: 1,
+                    hash_account_bump: 255,
+                    request: base_commitment_request,
+                    metadata: CommitmentMetadata::default(),
+                },
+                "StoreBaseCommitment(hash_account_index=1, hash_account_bump=255)",
+            ),
+            (
+                ElusivInstruction::ComputeBaseCommitmentHash {
+                    hash_account_index: 1,
+                },
+                "ComputeBaseCommitmentHash(hash_account_index=1)",
+            ),
+            (
+                ElusivInstruction::FinalizeBaseCommitmentHash {
+                    hash_account_index: 1,
+                    fee_version: 2,
+                },
+                "FinalizeBaseCommitmentHash(hash_account_index=1, fee_version=2)",
+            ),
+            (
+                ElusivInstruction::InitCommitmentHashSetup {
+                    insertion_can_fail: true,
+                },
+                "InitCommitmentHashSetup(insertion_can_fail=true)",
+            ),
+            (
+                ElusivInstruction::InitCommitmentHash {
+                    insertion_can_fail: false,
+                },
+                "InitCommitmentHash(insertion_can_fail=false)",
+            ),
+            (
+                ElusivInstruction::ComputeCommitmentHash {
+                    fee_version: 0,
+                    nonce: 7,
+                },
+                "ComputeCommitmentHash(fee_version=0, nonce=7)",
+            ),
+            (
+                ElusivInstruction::FinalizeCommitmentHash,
+                "FinalizeCommitmentHash",
+            ),
+            (
+                ElusivInstruction::InitVerification {
+                    verification_account_index: 2,
+                    vkey_id: 0,
+                    tree_indices: [0, 1],
+                    request,
+                    skip_nullifier_pda: false,
+                    external_reference: [0; 16],
+                    lock_identifier: false,
+                    rent_beneficiary: ElusivOption::None,
+                    encrypted_note: ElusivOption::None,
+                    earliest_finalize_time: 1_700_000_000,
+                },
+                "InitVerification(verification_account_index=2, vkey_id=0, skip_nullifier_pda=false, lock_identifier=false, earliest_finalize_time=1700000000)",
+            ),
+            (
+                ElusivInstruction::InitVerificationTransferFee {
+                    verification_account_index: 2,
+                },
+                "InitVerificationTransferFee(verification_account_index=2)",
+            ),
+            (
+                ElusivInstruction::InitVerificationProof {
+                    verification_account_index: 2,
+                    proof,
+                },
+                "InitVerificationProof(verification_account_index=2)",
+            ),
+            (
+                ElusivInstruction::ComputeVerification {
+                    verification_account_index: 2,
+                    vkey_id: 1,
+                },
+                "ComputeVerification(verification_account_index=2, vkey_id=1)",
+            ),
+            (
+                ElusivInstruction::FinalizeVerificationSend {
+                    verification_account_index: 2,
+                    data: FinalizeSendData::default(),
+                    uses_memo: true,
+                },
+                "FinalizeVerificationSend(verification_account_index=2, uses_memo=true)",
+            ),
+            (
+                ElusivInstruction::FinalizeVerificationInsertNullifier {
+                    verification_account_index: 2,
+                },
+                "FinalizeVerificationInsertNullifier(verification_account_index=2)",
+            ),
+            (
+                ElusivInstruction::FinalizeVerificationTransferLamports {
+                    verification_account_index: 2,
+                },
+                "FinalizeVerificationTransferLamports(verification_account_index=2)",
+            ),
+            (
+                ElusivInstruction::FinalizeVerificationTransferToken {
+                    verification_account_index: 2,
+                },
+                "FinalizeVerificationTransferToken(verification_account_index=2)",
+            ),
+            (
+                ElusivInstruction::CreateVkeyAccount {
+                    vkey_id: 1,
+                    public_inputs_count: 14,
+                    deploy_authority: ElusivOption::None,
+                },
+                "CreateVkeyAccount(vkey_id=1, public_inputs_count=14)",
+            ),
+            (
+                ElusivInstruction::CreateNewVkeyVersion { vkey_id: 1 },
+                "CreateNewVkeyVersion(vkey_id=1)",
+            ),
+            (
+                ElusivInstruction::UpdateVkeyVersion { vkey_id: 1 },
+                "UpdateVkeyVersion(vkey_id=1)",
+            ),
+            (
+                ElusivInstruction::SetVkeyData {
+                    vkey_id: 1,
+                    data_position: 3,
+                    packet: VKeyAccountDataPacket(vec![]),
+                },
+                "SetVkeyData(vkey_id=1, data_position=3)",
+            ),
+            (
+                ElusivInstruction::FreezeVkey { vkey_id: 1 },
+                "FreezeVkey(vkey_id=1)",
+            ),
+            (
+                ElusivInstruction::ChangeVkeyAuthority {
+                    vkey_id: 1,
+                    authority: Pubkey::new_unique(),
+                },
+                "ChangeVkeyAuthority(vkey_id=1)",
+            ),
+            (
+                ElusivInstruction::ResetActiveMerkleTree { active_mt_index: 4 },
+                "ResetActiveMerkleTree(active_mt_index=4)",
+            ),
+            (
+                ElusivInstruction::ArchiveClosedMerkleTree { closed_mt_index: 3 },
+                "ArchiveClosedMerkleTree(closed_mt_index=3)",
+            ),
+            (
+                ElusivInstruction::OpenSingleInstanceAccounts,
+                "OpenSingleInstanceAccounts",
+            ),
+            (
+                ElusivInstruction::OpenNullifierAccount { mt_index: 3 },
+                "OpenNullifierAccount(mt_index=3)",
+            ),
+            (
+                ElusivInstruction::EnableStorageChildAccount { child_index: 5 },
+                "EnableStorageChildAccount(child_index=5)",
+            ),
+            (
+                ElusivInstruction::EnableNullifierChildAccount {
+                    mt_index: 3,
+                    child_index: 5,
+                },
+                "EnableNullifierChildAccount(mt_index=3, child_index=5)",
+            ),
+            (
+                ElusivInstruction::EnableMetadataChildAccount { child_index: 5 },
+                "EnableMetadataChildAccount(child_index=5)",
+            ),
+            (
+                ElusivInstruction::SetupGovernorAccount,
+                "SetupGovernorAccount",
+            ),
+            (
+                ElusivInstruction::UpgradeGovernorState {
+                    fee_version: 1,
+                    batching_rate: 4,
+                },
+                "UpgradeGovernorState(fee_version=1, batching_rate=4)",
+            ),
+            (
+                ElusivInstruction::InitNewFeeVersion {
+                    fee_version: 1,
+                    program_fee: ProgramFee::new(0, 0, 0, 0, 0, 0, 0).unwrap(),
+                },
+                "InitNewFeeVersion(fee_version=1)",
+            ),
+            (
+                ElusivInstruction::CloseProgramAccount,
+                "CloseProgramAccount",
+            ),
+            (ElusivInstruction::CreateNewAccountsV1, "CreateNewAccountsV1"),
+            (ElusivInstruction::ListTokens, "ListTokens"),
+            (
+                ElusivInstruction::SetLogLevel {
+                    log_level: LogLevel::Off,
+                },
+                "SetLogLevel",
+            ),
+            (
+                ElusivInstruction::ValidateCommitmentQueue { max_entries: 16 },
+                "ValidateCommitmentQueue(max_entries=16)",
+            ),
+            (
+                ElusivInstruction::SettleQuarantinedCommitment {
+                    quarantine_index: 1,
+                    force_hash: true,
+                },
+                "SettleQuarantinedCommitment(quarantine_index=1, force_hash=true)",
+            ),
+            (
+                ElusivInstruction::DecommissionNullifierAccount { mt_index: 2 },
+                "DecommissionNullifierAccount(mt_index=2)",
+            ),
+            (
+                ElusivInstruction::CancelScheduledFeeUpdate,
+                "CancelScheduledFeeUpdate",
+            ),
+            (
+                ElusivInstruction::OpenWardenRewardsAccount,
+                "OpenWardenRewardsAccount",
+            ),
+            (
+                ElusivInstruction::ClaimEpochRewards { token_id: 1 },
+                "ClaimEpochRewards(token_id=1)",
+            ),
+            (ElusivInstruction::CreateNewAccountsV3, "CreateNewAccountsV3"),
+            (
+                ElusivInstruction::SetNoteLogEnabled { enabled: true },
+                "SetNoteLogEnabled(enabled=true)",
+            ),
+            (ElusivInstruction::CreateNewAccountsV4, "CreateNewAccountsV4"),
+            (
+                ElusivInstruction::EnableCommitmentIndexChildAccount { child_index: 1 },
+                "EnableCommitmentIndexChildAccount(child_index=1)",
+            ),
+            (
+                ElusivInstruction::LookupCommitment {
+                    commitment: [1; 32],
+                },
+                "LookupCommitment",
+            ),
+            (
+                ElusivInstruction::CancelDelayedSend {
+                    verification_account_index: 3,
+                },
+                "CancelDelayedSend(verification_account_index=3)",
+            ),
+            (ElusivInstruction::CreateNewAccountsV5, "CreateNewAccountsV5"),
+            (
+                ElusivInstruction::RetireFeeVersion { fee_version: 2 },
+                "RetireFeeVersion(fee_version=2)",
+            ),
+            (
+                ElusivInstruction::SetOracleDegraded {
+                    oracle_degraded: true,
+                },
+                "SetOracleDegraded(oracle_degraded=true)",
+            ),
+            (
+                ElusivInstruction::QuoteBaseCommitmentFee {
+                    token_id: 1,
+                    amount: 2,
+                },
+                "QuoteBaseCommitmentFee(token_id=1, amount=2)",
+            ),
+            (
+                ElusivInstruction::TransferVerificationOwnership {
+                    verification_account_index: 4,
+                },
+                "TransferVerificationOwnership(verification_account_index=4)",
+            ),
+            (
+                ElusivInstruction::SetVerificationTtlSlots {
+                    verification_ttl_slots: 100,
+                },
+                "SetVerificationTtlSlots(verification_ttl_slots=100)",
+            ),
+            (
+                ElusivInstruction::SetWardenRotationEpoch {
+                    warden_rotation_epoch: 1_000,
+                },
+                "SetWardenRotationEpoch(warden_rotation_epoch=1000)",
+            ),
+            (
+                ElusivInstruction::SetProofSubventionCap {
+                    proof_subvention_cap: 50,
+                },
+                "SetProofSubventionCap(proof_subvention_cap=50)",
+            ),
+            (
+                ElusivInstruction::SetCommitmentQueueHighWaterMark {
+                    commitment_queue_high_water_mark: 20,
+                },
+                "SetCommitmentQueueHighWaterMark(commitment_queue_high_water_mark=20)",
+            ),
+            (
+                ElusivInstruction::SetFeeVersionGraceSlots {
+                    fee_version_grace_slots: 10,
+                },
+                "SetFeeVersionGraceSlots(fee_version_grace_slots=10)",
+            ),
+            (
+                ElusivInstruction::MigrateGovernorAccount,
+                "MigrateGovernorAccount",
+            ),
+            (
+                ElusivInstruction::MigrateFeeAccount { fee_version: 1 },
+                "MigrateFeeAccount(fee_version=1)",
+            ),
+            (ElusivInstruction::Nop, "Nop"),
+        ];
+
+        for (instruction, description) in cases {
+            assert_eq!(instruction.short_description(), description);
+            assert_eq!(instruction.to_string(), description);
+        }
+    }
+
+    #[test]
+    fn test_accounts_meta_compute_verification() {
+        let warden = Pubkey::new_unique();
+        let original_fee_payer = Pubkey::new_unique();
+        let instruction = ElusivInstruction::compute_verification_instruction(
+            3,
+            SendQuadraVKey::VKEY_ID,
+            WritableSignerAccount(warden),
+            UserAccount(original_fee_payer),
+            &[],
+        );
+        let data = ElusivInstruction::try_from_slice(&instruction.data).unwrap();
+
+        assert_eq!(
+            data.accounts_meta(&crate::id(), &[warden, original_fee_payer]),
+            instruction.accounts
+        );
+
+        // Accounts read by the processor
+        let accounts = data.accounts_meta(&crate::id(), &[warden, original_fee_payer]);
+        assert_eq!(accounts.len(), 7);
+        assert_eq!(accounts[0].pubkey, warden);
+        assert!(accounts[0].is_signer && accounts[0].is_writable);
+        assert_eq!(
+            accounts[2].pubkey,
+            VerificationAccount::find_with_pubkey(original_fee_payer, Some(3)).0
+        );
+        assert!(accounts[2].is_writable);
+        assert_eq!(
+            accounts[3].pubkey,
+            VKeyAccount::find(Some(SendQuadraVKey::VKEY_ID)).0
+        );
+        assert!(!accounts[3].is_writable);
+        assert_eq!(accounts[4].pubkey, instructions::ID);
+        assert_eq!(accounts[5].pubkey, PoolAccount::find(None).0);
+        assert!(accounts[5].is_writable);
+        assert_eq!(accounts[6].pubkey, system_program::ID);
+    }
+
+    #[test]
+    fn test_accounts_meta_init_verification() {
+        let fee_payer = Pubkey::new_unique();
+        let nullifier_duplicate_account = Pubkey::new_unique();
+        let raced_verification_account = Pubkey::new_unique();
+        let identifier = Pubkey::new_unique();
+        let identifier_lock = Pubkey::new_unique();
+
+        let instruction = ElusivInstruction::init_verification_instruction(
+            1,
+            SendQuadraVKey::VKEY_ID,
+            [2, 3],
+            ProofRequest::Send(SendPublicInputs {
+                join_split: JoinSplitPublicInputs {
+                    input_commitments: vec![InputCommitment {
+                        root: Some(RawU256::new(u256_from_str_skip_mr("22"))),
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("333")),
+                    }],
+                    output_commitment: RawU256::new(u256_from_str_skip_mr("44444")),
+                    recent_commitment_index: 0,
+                    fee_version: 0,
+                    amount: 0,
+                    fee: 0,
+                    optional_fee: OptionalFee::default(),
+                    token_id: 0,
+                    metadata: CommitmentMetadata::default(),
+                },
+                hashed_inputs: [0; 32],
+                recipient_is_associated_token_account: false,
+                solana_pay_transfer: false,
+            }),
+            false,
+            [0; 16],
+            true,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
+            WritableSignerAccount(fee_payer),
+            WritableUserAccount(nullifier_duplicate_account),
+            UserAccount(raced_verification_account),
+            UserAccount(identifier),
+            WritableUserAccount(identifier_lock),
+            &[],
+            &[],
+        );
+        let data = ElusivInstruction::try_from_slice(&instruction.data).unwrap();
+        let accounts = data.accounts_meta(
+            &crate::id(),
+            &[
+                fee_payer,
+                nullifier_duplicate_account,
+                raced_verification_account,
+                identifier,
+                identifier_lock,
+            ],
+        );
+
+        assert_eq!(accounts, instruction.accounts);
+
+        // Accounts read by the processor
+        assert_eq!(accounts.len(), 14);
+        assert!(accounts[0].is_signer && accounts[0].is_writable);
+        assert_eq!(
+            accounts[1].pubkey,
+            VerificationAccount::find_with_pubkey(fee_payer, Some(1)).0
+        );
+        assert_eq!(
+            accounts[2].pubkey,
+            VKeyAccount::find(Some(SendQuadraVKey::VKEY_ID)).0
+        );
+        assert_eq!(accounts[4].pubkey, raced_verification_account);
+        assert!(!accounts[4].is_writable);
+        assert_eq!(accounts[5].pubkey, system_program::ID);
+        assert_eq!(accounts[10].pubkey, NullifierAccount::find(Some(2)).0);
+        assert_eq!(accounts[11].pubkey, NullifierAccount::find(Some(3)).0);
+        assert_eq!(accounts[12].pubkey, clock::ID);
+        assert_eq!(accounts[13].pubkey, GovernorAccount::find(None).0);
+    }
+
+    #[test]
+    fn test_accounts_meta_program_id() {
+        let program_id = Pubkey::new_unique();
+        let accounts = ElusivInstruction::ResetActiveMerkleTree { active_mt_index: 1 }
+            .accounts_meta(&program_id, &[]);
+
+        assert_eq!(
+            accounts[2].pubkey,
+            NullifierAccount::find_with_program_id(&program_id, None, Some(1)).0
+        );
+        assert_ne!(accounts[2].pubkey, NullifierAccount::find(Some(1)).0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_accounts_meta_missing_user_account() {
+        ElusivInstruction::ComputeVerification {
+            verification_account_index: 0,
+            vkey_id: 0,
+        }
+        .accounts_meta(&crate::id(), &[]);
+    }
 }
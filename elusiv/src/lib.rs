@@ -3,6 +3,7 @@
 pub mod buffer;
 pub mod bytes;
 pub mod commitment;
+pub mod computation_metadata;
 pub mod entrypoint;
 mod error;
 pub mod fields;
@@ -1,6 +1,39 @@
 pub use elusiv_derive::*;
 pub use elusiv_proc_macros::*;
-pub use elusiv_utils::{guard, pda_account, two_pow};
+pub use elusiv_utils::{pda_account, two_pow};
+
+#[cfg(not(feature = "error-log"))]
+pub use elusiv_utils::guard;
+
+/// Guard statement that logs the raised error and the location of the guard
+#[cfg(feature = "error-log")]
+macro_rules! guard {
+    ($assertion: expr, $error: expr) => {
+        if !$assertion {
+            return Err(
+                crate::error::GuardError::log($error, concat!(file!(), ":", line!())).into(),
+            );
+        }
+    };
+}
+
+#[cfg(feature = "error-log")]
+pub(crate) use guard;
+
+/// [`guard!`] statement that is only enforced by `hardened` builds
+///
+/// # Note
+///
+/// The assertion is compiled in all builds (single code path), but optimized out without the `hardened` feature.
+macro_rules! hardened_guard {
+    ($assertion: expr, $error: expr) => {
+        if cfg!(feature = "hardened") {
+            crate::macros::guard!($assertion, $error);
+        }
+    };
+}
+
+pub(crate) use hardened_guard;
 
 /// Creates a dummy pyth-price-account [`solana_program::account_info::AccountInfo`] for testing
 ///
@@ -16,6 +49,26 @@ macro_rules! pyth_price_account_info {
     };
 }
 
+/// Creates a clock-sysvar [`solana_program::account_info::AccountInfo`] for testing
+///
+/// # Usage
+///
+/// - `clock_account_info!($id: ident, $slot: expr)`
+/// - `clock_account_info!($id: ident, $slot: expr, $unix_timestamp: expr)`
+#[cfg(test)]
+macro_rules! clock_account_info {
+    ($id: ident, $slot: expr) => {
+        crate::macros::clock_account_info!($id, $slot, 0);
+    };
+    ($id: ident, $slot: expr, $unix_timestamp: expr) => {
+        // `slot` is the first and `unix_timestamp` the last field of the serialized `Clock`
+        let mut data = vec![0; <solana_program::clock::Clock as solana_program::sysvar::Sysvar>::size_of()];
+        data[..8].copy_from_slice(&u64::to_le_bytes($slot));
+        data[32..].copy_from_slice(&i64::to_le_bytes($unix_timestamp));
+        crate::macros::account_info!($id, solana_program::sysvar::clock::id(), data);
+    };
+}
+
 /// Create a dummy [`solana_program::account_info::AccountInfo`] for testing
 ///
 /// # Usage
@@ -168,18 +221,26 @@ macro_rules! parent_account {
     };
 
     ($id: ident, $ty: ty) => {
-        crate::macros::parent_account!(internal $ty, child_accounts, data);
-        let $id = <$ty as elusiv_types::ParentAccount>::new_with_child_accounts(&mut data, child_accounts).unwrap();
+        crate::macros::parent_account!(mut $id, $ty);
+        let $id = $id;
     };
     (mut $id: ident, $ty: ty) => {
         crate::macros::parent_account!(internal $ty, child_accounts, data);
+        let child_pubkeys: Vec<_> = child_accounts.iter().map(|a| *a.unwrap().key).collect();
         let mut $id = <$ty as elusiv_types::ParentAccount>::new_with_child_accounts(&mut data, child_accounts).unwrap();
+
+        // All child-accounts are registered
+        for (i, pubkey) in child_pubkeys.into_iter().enumerate() {
+            elusiv_types::ParentAccount::set_child_pubkey(&mut $id, i, crate::bytes::ElusivOption::Some(pubkey));
+        }
     };
 }
 
 #[cfg(test)]
 pub(crate) use account_info;
 #[cfg(test)]
+pub(crate) use clock_account_info;
+#[cfg(test)]
 pub(crate) use parent_account;
 #[cfg(test)]
 pub(crate) use program_token_account_info;
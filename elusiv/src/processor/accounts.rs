@@ -7,23 +7,28 @@ use crate::state::commitment::{
     BaseCommitmentBufferAccount, CommitmentBufferAccount, CommitmentHashingAccount,
     CommitmentQueue, CommitmentQueueAccount,
 };
+use crate::state::commitment_index::CommitmentIndexAccount;
 use crate::state::metadata::{MetadataAccount, MetadataQueueAccount};
+use crate::state::note::NoteLogAccount;
 use crate::state::queue::RingQueue;
 use crate::state::{
-    fee::{FeeAccount, ProgramFee},
-    governor::{FeeCollectorAccount, GovernorAccount, PoolAccount},
+    fee::{FeeAccount, FeeVersionUsageAccount, ProgramFee},
+    governor::{FeeCollectorAccount, GovernorAccount, LogLevel, PoolAccount},
     nullifier::{NullifierAccount, NullifierChildAccount},
     queue::Queue,
-    storage::{StorageAccount, MT_COMMITMENT_COUNT},
+    storage::{StorageAccount, MAX_TREES, MT_COMMITMENT_COUNT},
+    warden::WardenRewardsAccount,
 };
+use crate::token::TOKENS;
 use crate::{bytes::usize_as_u32_safe, map::ElusivMap};
 use elusiv_types::{
     split_child_account_data_mut, ChildAccount, ChildAccountConfig, ParentAccount, SizedAccount,
-    UnverifiedAccountInfo,
+    UnverifiedAccountInfo, VersionedAccount,
 };
+use elusiv_utils::resize_account;
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError, rent::Rent,
-    sysvar::Sysvar,
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult,
+    program_error::ProgramError, rent::Rent, sysvar::Sysvar,
 };
 
 /// Opens one single instance [`elusiv_types::PDAAccount`], as long this PDA does not already exist
@@ -154,6 +159,23 @@ pub fn enable_metadata_child_account(
     )
 }
 
+/// Enables the supplied child-account for the [`CommitmentIndexAccount`]
+pub fn enable_commitment_index_child_account(
+    commitment_index: &mut CommitmentIndexAccount,
+    child_account: &AccountInfo,
+
+    child_index: u32,
+) -> ProgramResult {
+    // Note: we don't zero-check these accounts, since we will never access data that has not been set by the program
+    setup_child_account(
+        commitment_index,
+        child_account,
+        child_index as usize,
+        false,
+        None,
+    )
+}
+
 /// Closes the active MT and activates the next one
 ///
 /// # Notes
@@ -161,6 +183,8 @@ pub fn enable_metadata_child_account(
 /// There are two scenarios in which this is required/allowed:
 ///     1. the active MT is full
 ///     2. the active MT is not full but the remaining places in the MT are < than the batching rate of the next commitment in the commitment queue
+///
+/// The active MT can only be closed as long as less than [`MAX_TREES`] MTs are in use.
 pub fn reset_active_merkle_tree(
     storage_account: &mut StorageAccount,
     queue: &mut CommitmentQueueAccount,
@@ -172,6 +196,11 @@ pub fn reset_active_merkle_tree(
         storage_account.get_trees_count() == active_merkle_tree_index,
         ElusivError::InvalidInstructionData
     );
+    guard!(
+        active_merkle_tree_index + 1 < MAX_TREES,
+        ElusivError::MaxTreesReached
+    );
+    storage_account.verify_fully_initialized()?;
 
     let queue = CommitmentQueue::new(queue);
     guard!(
@@ -239,6 +268,82 @@ pub fn create_new_accounts_v1<'a, 'b>(
     Ok(())
 }
 
+/// Opens the [`WardenRewardsAccount`] of `warden`
+pub fn open_warden_rewards_account<'a>(
+    warden: &AccountInfo<'a>,
+    warden_rewards: &AccountInfo<'a>,
+) -> ProgramResult {
+    open_pda_account_with_associated_pubkey::<WardenRewardsAccount>(
+        &crate::id(),
+        warden,
+        warden_rewards,
+        warden.key,
+        None,
+        None,
+    )
+}
+
+pub fn create_new_accounts_v3<'a, 'b>(
+    payer: &AccountInfo<'b>,
+    note_log: UnverifiedAccountInfo<'a, 'b>,
+) -> ProgramResult {
+    open_pda_account_without_offset::<NoteLogAccount>(
+        &crate::id(),
+        payer,
+        note_log.get_unsafe(),
+        None,
+    )
+}
+
+pub fn create_new_accounts_v4<'a, 'b>(
+    payer: &AccountInfo<'b>,
+    commitment_index: UnverifiedAccountInfo<'a, 'b>,
+    storage_account: &StorageAccount,
+) -> ProgramResult {
+    open_pda_account_without_offset::<CommitmentIndexAccount>(
+        &crate::id(),
+        payer,
+        commitment_index.get_unsafe(),
+        None,
+    )?;
+
+    // The commitments of the active MT inserted so far are not indexed
+    let commitment_index = commitment_index.get_unsafe();
+    pda_account!(
+        mut commitment_index,
+        CommitmentIndexAccount,
+        commitment_index
+    );
+    commitment_index.set_tree_index(&storage_account.get_trees_count());
+    commitment_index.set_unindexed_count(&storage_account.get_next_commitment_ptr());
+
+    Ok(())
+}
+
+pub fn create_new_accounts_v5<'a, 'b>(
+    payer: &AccountInfo<'b>,
+    fee_version_usage: UnverifiedAccountInfo<'a, 'b>,
+    governor: &GovernorAccount,
+) -> ProgramResult {
+    open_pda_account_without_offset::<FeeVersionUsageAccount>(
+        &crate::id(),
+        payer,
+        fee_version_usage.get_unsafe(),
+        None,
+    )?;
+
+    // Uses of lower fee-versions cannot be counted anymore, so these are retired
+    let fee_version_usage = fee_version_usage.get_unsafe();
+    pda_account!(
+        mut fee_version_usage,
+        FeeVersionUsageAccount,
+        fee_version_usage
+    );
+    fee_version_usage.set_min_fee_version(&governor.get_fee_version());
+
+    Ok(())
+}
+
 fn is_mt_full(
     storage_account: &StorageAccount,
     queue: &CommitmentQueue,
@@ -272,6 +377,49 @@ pub fn archive_closed_merkle_tree<'a>(
     panic!("N-SMT not implemented yet");
 }
 
+/// Closes the child-accounts of a closed MT's [`NullifierAccount`] and moves their rent to the [`FeeCollectorAccount`]
+///
+/// # Notes
+///
+/// Can only be called by the program authority.
+///
+/// Only fully-spent MTs are eligible, since no further nullifiers can be inserted into them.
+/// The [`NullifierAccount`] itself is retained as a record of the MT root (for historical root validation).
+pub fn decommission_nullifier_account<'t>(
+    authority: &AccountInfo,
+    storage_account: &StorageAccount,
+    nullifier_account: &mut NullifierAccount<'_, '_, 't>,
+    fee_collector: &AccountInfo<'t>,
+
+    mt_index: u32,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    // The active MT still receives nullifiers
+    guard!(
+        storage_account.get_trees_count() > mt_index,
+        ElusivError::InvalidInstructionData
+    );
+    guard!(
+        nullifier_account.is_fully_spent(),
+        ElusivError::InvalidAccountState
+    );
+
+    for child_index in 0..NullifierAccount::COUNT {
+        if nullifier_account.get_child_pubkey(child_index).is_none() {
+            continue;
+        }
+
+        let child_account = unsafe { nullifier_account.get_child_account_unsafe(child_index) }?;
+        child_account.data.borrow_mut().fill(0);
+        close_account(fee_collector, child_account)?;
+
+        nullifier_account.set_child_pubkey(child_index, ElusivOption::None);
+    }
+
+    Ok(())
+}
+
 /// Setup the [`GovernorAccount`] with the default values
 ///
 /// # Note
@@ -294,6 +442,28 @@ pub fn setup_governor_account<'b>(
     Ok(())
 }
 
+/// Resizes a [`GovernorAccount`] of an older version to the current data layout and migrates it to the current version
+///
+/// # Note
+///
+/// - The added bytes are zeroed, the migration initializes the added fields with their defaults.
+/// - `payer` funds the additional rent.
+pub fn migrate_governor_account<'a>(
+    payer: &AccountInfo<'a>,
+    governor_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    resize_account(
+        payer,
+        governor_account,
+        system_program,
+        GovernorAccount::SIZE,
+    )?;
+
+    pda_account!(mut governor, GovernorAccount, governor_account);
+    governor.migrate_if_needed()
+}
+
 /// Changes the state of the [`GovernorAccount`]
 pub fn upgrade_governor_state(
     _authority: &AccountInfo,
@@ -308,11 +478,160 @@ pub fn upgrade_governor_state(
     // TODO: fee changes require empty queues
 }
 
+/// Sets the maximum join-split amount of sends in `token_id`-Token (zero: no limit)
+///
+/// # Note
+///
+/// Can only be called by the program authority.
+pub fn set_max_token_amount(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    token_id: u16,
+    max: u64,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+    guard!(
+        (token_id as usize) < TOKENS.len(),
+        ElusivError::InvalidInstructionData
+    );
+
+    governor.set_max_token_amounts(token_id as usize, &max);
+
+    Ok(())
+}
+
+/// Sets the verbosity of the program logs
+///
+/// # Note
+///
+/// Can only be called by the program authority.
+pub fn set_log_level(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    log_level: LogLevel,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    governor.set_log_level(&ElusivOption::Some(log_level));
+
+    Ok(())
+}
+
+pub fn set_note_log_enabled(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    enabled: bool,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    governor.set_note_log_enabled(&enabled);
+
+    Ok(())
+}
+
+/// Token requests are rejected while the oracle is degraded, already admitted requests are finalized with their locked prices
+pub fn set_oracle_degraded(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    oracle_degraded: bool,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    governor.set_oracle_degraded(&oracle_degraded);
+
+    Ok(())
+}
+
+/// Sets the number of slots after which an incomplete verification can be expired (zero: verifications never expire)
+///
+/// # Note
+///
+/// - Can only be called by the program authority.
+/// - Enables [`crate::processor::expire_verification`] and [`crate::processor::force_settle_unknown_state`].
+pub fn set_verification_ttl_slots(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    verification_ttl_slots: u64,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    governor.set_verification_ttl_slots(&verification_ttl_slots);
+
+    Ok(())
+}
+
+/// Sets the number of slots per warden-reward epoch (zero: rewards are paid per proof)
+///
+/// # Note
+///
+/// - Can only be called by the program authority.
+/// - Epochs are numbered `slot / warden_rotation_epoch`, accrued rewards become claimable once the current epoch number exceeds the one they were accrued in.
+/// - A change renumbers the epochs, which also affects the already accrued rewards:
+///   a shorter epoch makes them claimable immediately, a longer epoch locks them (together with the rewards accrued on top of them) until the new epoch number exceeds their old one.
+/// - Zero pays new rewards per proof and makes all accrued rewards claimable.
+pub fn set_warden_rotation_epoch(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    warden_rotation_epoch: u64,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    governor.set_warden_rotation_epoch(&warden_rotation_epoch);
+
+    Ok(())
+}
+
+/// Sets the maximum proof-subvention in basis points of the join-split amount
+///
+/// # Note
+///
+/// - Can only be called by the program authority.
+/// - Zero disables the cap, so every proof receives the full flat `proof_subvention` of its [`ProgramFee`].
+/// - Applies to verifications whose fee is transferred afterwards (the subvention is fixed at the fee transfer).
+pub fn set_proof_subvention_cap(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    proof_subvention_cap: u64,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    governor.set_proof_subvention_cap(&proof_subvention_cap);
+
+    Ok(())
+}
+
+/// Sets the commitment queue length (including pending commitments) above which no new proof verifications are admitted
+///
+/// # Note
+///
+/// - Can only be called by the program authority.
+/// - Zero (or a mark above the queue capacity) falls back to the capacity (see [`CommitmentQueue::admit_pending_commitment`]).
+/// - Already admitted verifications keep their reserved queue slots.
+pub fn set_commitment_queue_high_water_mark(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+
+    commitment_queue_high_water_mark: u32,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    governor.set_commitment_queue_high_water_mark(&commitment_queue_high_water_mark);
+
+    Ok(())
+}
+
 /// Setup a new [`FeeAccount`]
 ///
 /// # Note
 ///
-/// There is no way of upgrading the program fees atm.
+/// Besides the current fee-version, the [`FeeAccount`] of a scheduled fee update can be setup (required for hashing its commitments).
 pub fn init_new_fee_version<'b>(
     payer: &AccountInfo<'b>,
     governor: &mut GovernorAccount,
@@ -321,11 +640,27 @@ pub fn init_new_fee_version<'b>(
     fee_version: u32,
     program_fee: ProgramFee,
 ) -> ProgramResult {
-    // Note: we have no upgrade-authroity check here since with the current setup it's impossible to have a fee version higher than zero, so will be added once that changes
+    // Note: we have no upgrade-authority check here, since fee-versions above zero can only be reached through scheduled fee updates (which fix the program fee)
+    let current_fee_version = governor.get_fee_version();
+    let fixed_program_fee = match governor.get_scheduled_fee_update() {
+        ElusivOption::Some(update) if fee_version == current_fee_version + 1 => {
+            Some(update.program_fee)
+        }
+        _ if fee_version == current_fee_version && fee_version > 0 => {
+            Some(governor.get_program_fee())
+        }
+        _ => None,
+    };
     guard!(
-        fee_version == governor.get_fee_version(),
+        fee_version == current_fee_version || fixed_program_fee.is_some(),
         ElusivError::InvalidFeeVersion
     );
+    if let Some(fixed_program_fee) = fixed_program_fee {
+        guard!(
+            program_fee == fixed_program_fee,
+            ElusivError::InvalidInstructionData
+        );
+    }
     guard!(program_fee.is_valid(), ElusivError::InvalidInstructionData);
 
     open_pda_account_with_offset::<FeeAccount>(
@@ -338,7 +673,113 @@ pub fn init_new_fee_version<'b>(
 
     pda_account!(mut fee_account, FeeAccount, new_fee_account.get_safe()?);
     fee_account.set_program_fee(&program_fee);
-    governor.set_program_fee(&program_fee);
+    if fee_version == current_fee_version {
+        governor.set_program_fee(&program_fee);
+    }
+
+    Ok(())
+}
+
+/// Resizes the [`FeeAccount`] of `fee_version` to the current data layout and migrates it to the current version
+///
+/// # Note
+///
+/// - The added bytes are zeroed, the migration initializes the added fields with their defaults.
+/// - `payer` funds the additional rent.
+pub fn migrate_fee_account<'a>(
+    payer: &AccountInfo<'a>,
+    fee_account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+
+    _fee_version: u32,
+) -> ProgramResult {
+    resize_account(payer, fee_account, system_program, FeeAccount::SIZE)?;
+
+    pda_account!(mut fee, FeeAccount, fee_account);
+    fee.migrate_if_needed()
+}
+
+/// Schedules `program_fee` as the fee of the next fee-version, which proofs can use from `activation_slot` on
+///
+/// # Note
+///
+/// Can only be called by the program authority.
+pub fn schedule_fee_update(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+    clock: &AccountInfo,
+    fee_version_usage: &FeeVersionUsageAccount,
+
+    program_fee: ProgramFee,
+    activation_slot: u64,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+    guard!(program_fee.is_valid(), ElusivError::InvalidInstructionData);
+
+    // The scheduled fee-version has to be tracked (a previous update is applied first)
+    fee_version_usage.verify_trackable(governor.latest_fee_version() + 1)?;
+
+    let slot = Clock::from_account_info(clock)?.slot;
+    governor.schedule_fee_update(program_fee, activation_slot, slot)?;
+
+    Ok(())
+}
+
+/// Cancels a scheduled fee update before its activation
+///
+/// # Note
+///
+/// Can only be called by the program authority.
+pub fn cancel_scheduled_fee_update(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+    clock: &AccountInfo,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    let slot = Clock::from_account_info(clock)?.slot;
+    governor.cancel_scheduled_fee_update(slot)?;
+
+    Ok(())
+}
+
+/// Retires `fee_version`, so that it is refused by all new proof verifications and base commitments
+///
+/// # Note
+///
+/// - Can only be called by the program authority.
+/// - Fee-versions are retired in order, each once it has no pending uses (see [`FeeVersionUsageAccount`]).
+pub fn retire_fee_version(
+    authority: &AccountInfo,
+    governor: &GovernorAccount,
+    fee_version_usage: &mut FeeVersionUsageAccount,
+
+    fee_version: u32,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    fee_version_usage.retire(fee_version, governor.get_fee_version())?;
+
+    Ok(())
+}
+
+/// Sets the number of slots after the activation of a scheduled fee update during which the previous fee-version is still accepted
+///
+/// # Note
+///
+/// - Can only be called by the program authority.
+/// - Refused once a scheduled fee update is activated, the grace window of an activated update is fixed.
+pub fn set_fee_version_grace_slots(
+    authority: &AccountInfo,
+    governor: &mut GovernorAccount,
+    clock: &AccountInfo,
+
+    fee_version_grace_slots: u64,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    let slot = Clock::from_account_info(clock)?.slot;
+    governor.update_fee_version_grace_slots(fee_version_grace_slots, slot)?;
 
     Ok(())
 }
@@ -446,10 +887,19 @@ mod tests {
     use crate::{
         macros::account_info,
         processor::CommitmentHashRequest,
-        state::{program_account::SizedAccount, queue::RingQueue, storage::StorageChildAccount},
+        state::{
+            fee::{BasisPointFee, ProgramFeeV0, FEE_VERSION_USAGE_WINDOW},
+            governor::ScheduledFeeUpdate,
+            nullifier::NULLIFIERS_COUNT,
+            program_account::SizedAccount,
+            queue::RingQueue,
+            storage::StorageChildAccount,
+        },
+        token::Lamports,
         types::U256,
     };
-    use elusiv_types::ProgramAccount;
+    use borsh::BorshSerialize;
+    use elusiv_types::{PDAAccount, PDAAccountData, ProgramAccount};
     use solana_program::{pubkey::Pubkey, system_program};
 
     #[test]
@@ -495,6 +945,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_enable_all_storage_child_accounts() {
+        let mut data = vec![0; StorageAccount::SIZE];
+        let mut storage_account = StorageAccount::new(&mut data).unwrap();
+
+        for child_index in 0..StorageAccount::COUNT {
+            // Partially initialized
+            assert_eq!(
+                storage_account.verify_fully_initialized(),
+                Err(ElusivError::StorageNotFullyInitialized.into())
+            );
+
+            account_info!(
+                child_account,
+                Pubkey::new_unique(),
+                vec![0; StorageChildAccount::SIZE]
+            );
+            assert_eq!(
+                enable_storage_child_account(
+                    &mut storage_account,
+                    &child_account,
+                    child_index as u32
+                ),
+                Ok(())
+            );
+        }
+
+        assert_eq!(storage_account.verify_fully_initialized(), Ok(()));
+    }
+
     #[test]
     fn test_enable_nullifier_child_account() {
         let mut data = vec![0; NullifierAccount::SIZE];
@@ -540,12 +1020,10 @@ mod tests {
 
     #[test]
     fn test_is_mt_full() {
-        let mut data = vec![0; StorageAccount::SIZE];
-        let mut storage_account = StorageAccount::new(&mut data).unwrap();
+        let mut storage_account = StorageAccount::zeroed();
         storage_account.set_next_commitment_ptr(&(MT_COMMITMENT_COUNT as u32));
 
-        let mut q_data = vec![0; CommitmentQueueAccount::SIZE];
-        let mut queue = CommitmentQueueAccount::new(&mut q_data).unwrap();
+        let mut queue = CommitmentQueueAccount::zeroed();
         let mut queue = CommitmentQueue::new(&mut queue);
         queue
             .enqueue(CommitmentHashRequest {
@@ -575,10 +1053,8 @@ mod tests {
     #[should_panic]
     fn test_archive_closed_merkle_tree() {
         test_account_info!(payer, 0);
-        let mut data = vec![0; StorageAccount::SIZE];
-        let mut storage_account = StorageAccount::new(&mut data).unwrap();
-        let mut data = vec![0; NullifierAccount::SIZE];
-        let mut nullifier_account = NullifierAccount::new(&mut data).unwrap();
+        let mut storage_account = StorageAccount::zeroed();
+        let mut nullifier_account = NullifierAccount::zeroed();
         test_account_info!(archived_tree_account, 0);
 
         archive_closed_merkle_tree(
@@ -591,6 +1067,104 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn test_decommission_nullifier_account() {
+        account_info!(authority, crate::id());
+        test_account_info!(fee_collector, 0);
+        let mut storage_account = StorageAccount::zeroed();
+        storage_account.set_trees_count(&2);
+        parent_account!(mut nullifier_account, NullifierAccount);
+
+        for child_index in 0..NullifierAccount::COUNT {
+            let pubkey = unsafe { nullifier_account.get_child_account_unsafe(child_index) }
+                .unwrap()
+                .key;
+            nullifier_account.set_child_pubkey(child_index, ElusivOption::Some(*pubkey));
+        }
+        nullifier_account.set_root(&[1; 32]);
+        nullifier_account.set_nullifier_hash_count(&(NULLIFIERS_COUNT as u32 - 1));
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            decommission_nullifier_account(
+                &signer,
+                &storage_account,
+                &mut nullifier_account,
+                &fee_collector,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Active or not yet opened MT
+        for mt_index in [2, 3] {
+            assert_eq!(
+                decommission_nullifier_account(
+                    &authority,
+                    &storage_account,
+                    &mut nullifier_account,
+                    &fee_collector,
+                    mt_index
+                ),
+                Err(ElusivError::InvalidInstructionData.into())
+            );
+        }
+
+        // Not fully-spent MT
+        assert_eq!(
+            decommission_nullifier_account(
+                &authority,
+                &storage_account,
+                &mut nullifier_account,
+                &fee_collector,
+                1
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+
+        nullifier_account.set_nullifier_hash_count(&(NULLIFIERS_COUNT as u32));
+        let child_lamports: u64 = (0..NullifierAccount::COUNT)
+            .map(|i| {
+                unsafe { nullifier_account.get_child_account_unsafe(i) }
+                    .unwrap()
+                    .lamports()
+            })
+            .sum();
+        let fee_collector_lamports = fee_collector.lamports();
+
+        assert_eq!(
+            decommission_nullifier_account(
+                &authority,
+                &storage_account,
+                &mut nullifier_account,
+                &fee_collector,
+                1
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            fee_collector.lamports(),
+            fee_collector_lamports + child_lamports
+        );
+        for child_index in 0..NullifierAccount::COUNT {
+            assert_eq!(nullifier_account.get_child_pubkey(child_index), None);
+
+            let child_account =
+                unsafe { nullifier_account.get_child_account_unsafe(child_index) }.unwrap();
+            assert_eq!(child_account.lamports(), 0);
+            assert!(is_zero(&child_account.data.borrow()));
+        }
+
+        // The root is retained, but no further nullifiers can be inserted
+        assert_eq!(nullifier_account.get_root(), [1; 32]);
+        assert_eq!(
+            nullifier_account.can_insert_nullifier_hash([0; 32]),
+            Err(ElusivError::CouldNotInsertNullifier.into())
+        );
+    }
+
     #[test]
     #[should_panic]
     fn test_upgrade_governor_state() {
@@ -601,6 +1175,532 @@ mod tests {
         upgrade_governor_state(&authority, &mut governor_account, &commitment_queue, 1, 1).unwrap();
     }
 
+    #[test]
+    fn test_migrate_governor_account() -> ProgramResult {
+        test_account_info!(payer, 0);
+        account_info!(sys, system_program::id());
+        let program_fee = ProgramFee {
+            merge_fee_discount: BasisPointFee(2_000),
+            warden_hash_commitment_reward: Lamports(100),
+            ..ProgramFee::new(5000, 11, 100, 33, 44, 300, 555).unwrap()
+        };
+
+        let mut data = vec![0; GovernorAccount::SIZE];
+        {
+            let mut governor = GovernorAccount::new(&mut data).unwrap();
+            governor.set_fee_version(&1);
+            governor.set_program_fee(&program_fee);
+            governor.set_commitment_batching_rate(&4);
+        }
+        account_info!(governor_account, GovernorAccount::find(None).0, data);
+
+        migrate_governor_account(&payer, &governor_account, &sys).unwrap();
+
+        // The fields of version 0 are kept, the appended `ProgramFee` fields did not exist before
+        {
+            pda_account!(governor, GovernorAccount, governor_account);
+            assert_eq!(governor.get_pda_version(), 1);
+            assert_eq!(governor.get_fee_version(), 1);
+            assert_eq!(governor.get_commitment_batching_rate(), 4);
+            assert_eq!(
+                governor.get_program_fee(),
+                ProgramFee {
+                    merge_fee_discount: BasisPointFee(0),
+                    warden_hash_commitment_reward: Lamports(0),
+                    ..program_fee.clone()
+                }
+            );
+        }
+
+        // Current version
+        {
+            pda_account!(mut governor, GovernorAccount, governor_account);
+            governor.set_program_fee(&program_fee);
+        }
+        migrate_governor_account(&payer, &governor_account, &sys).unwrap();
+        pda_account!(governor, GovernorAccount, governor_account);
+        assert_eq!(governor.get_program_fee(), program_fee);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_fee_account() -> ProgramResult {
+        test_account_info!(payer, 0);
+        account_info!(sys, system_program::id());
+        let program_fee = ProgramFee {
+            merge_fee_discount: BasisPointFee(2_000),
+            warden_hash_commitment_reward: Lamports(100),
+            ..ProgramFee::new(5000, 11, 100, 33, 44, 300, 555).unwrap()
+        };
+
+        let mut data = vec![0; FeeAccount::SIZE];
+        FeeAccount::new(&mut data)
+            .unwrap()
+            .set_program_fee(&program_fee);
+        account_info!(fee_account, FeeAccount::find(Some(0)).0, data);
+
+        migrate_fee_account(&payer, &fee_account, &sys, 0).unwrap();
+
+        pda_account!(fee, FeeAccount, fee_account);
+        assert_eq!(fee.get_pda_version(), 1);
+        assert_eq!(
+            fee.get_program_fee(),
+            ProgramFee {
+                merge_fee_discount: BasisPointFee(0),
+                warden_hash_commitment_reward: Lamports(0),
+                ..program_fee
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_baseline_accounts() -> ProgramResult {
+        test_account_info!(payer, 0);
+        account_info!(sys, system_program::id());
+        let program_fee = ProgramFee::new(5000, 11, 100, 33, 44, 300, 555).unwrap();
+
+        // Layouts before version 1: `GovernorAccount { pda_data, fee_version, program_fee, commitment_batching_rate, program_version }` and `FeeAccount { pda_data, program_fee }`
+        let mut governor_data = Vec::new();
+        PDAAccountData {
+            bump_seed: 1,
+            version: 0,
+        }
+        .serialize(&mut governor_data)?;
+        3u32.serialize(&mut governor_data)?;
+        program_fee.v0().serialize(&mut governor_data)?;
+        4u32.serialize(&mut governor_data)?;
+        7u32.serialize(&mut governor_data)?;
+        assert_eq!(
+            governor_data.len(),
+            PDAAccountData::SIZE + 4 + ProgramFeeV0::SIZE + 4 + 4
+        );
+        assert!(GovernorAccount::new(&mut governor_data).is_err());
+
+        let mut fee_data = Vec::new();
+        PDAAccountData {
+            bump_seed: 1,
+            version: 0,
+        }
+        .serialize(&mut fee_data)?;
+        program_fee.v0().serialize(&mut fee_data)?;
+        assert_eq!(fee_data.len(), PDAAccountData::SIZE + ProgramFeeV0::SIZE);
+        assert!(FeeAccount::new(&mut fee_data).is_err());
+
+        // The resize zeroes the appended bytes (skipped in unit tests)
+        governor_data.resize(GovernorAccount::SIZE, 0);
+        fee_data.resize(FeeAccount::SIZE, 0);
+        account_info!(
+            governor_account,
+            GovernorAccount::find(None).0,
+            governor_data
+        );
+        account_info!(fee_account, FeeAccount::find(Some(3)).0, fee_data);
+
+        migrate_governor_account(&payer, &governor_account, &sys)?;
+        migrate_fee_account(&payer, &fee_account, &sys, 3)?;
+
+        pda_account!(governor, GovernorAccount, governor_account);
+        assert_eq!(governor.get_pda_version(), 1);
+        assert_eq!(governor.get_fee_version(), 3);
+        assert_eq!(governor.get_program_fee(), program_fee);
+        assert_eq!(governor.get_commitment_batching_rate(), 4);
+        assert_eq!(governor.get_commitment_queue_high_water_mark(), 0);
+        assert_eq!(governor.get_log_level(), ElusivOption::None);
+        assert_eq!(governor.get_scheduled_fee_update(), ElusivOption::None);
+        assert!(!governor.get_oracle_degraded());
+
+        // Without a per-commitment reward, the batches of the migrated fee-version are rewarded as before
+        pda_account!(fee, FeeAccount, fee_account);
+        assert_eq!(fee.get_pda_version(), 1);
+        assert_eq!(fee.get_program_fee(), program_fee);
+        assert_eq!(
+            fee.get_program_fee().commitment_hash_warden_reward(4).0,
+            program_fee.commitment_hash_warden_reward(0).0
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_max_token_amount() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_max_token_amount(&signer, &mut governor, 0, 100),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid token-id
+        assert_eq!(
+            set_max_token_amount(&authority, &mut governor, TOKENS.len() as u16, 100),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+
+        for token_id in 0..TOKENS.len() as u16 {
+            set_max_token_amount(&authority, &mut governor, token_id, 100 + token_id as u64)
+                .unwrap();
+            assert_eq!(
+                governor.get_max_token_amount(token_id),
+                Ok(100 + token_id as u64)
+            );
+        }
+
+        // Removing the limit
+        set_max_token_amount(&authority, &mut governor, 0, 0).unwrap();
+        assert_eq!(governor.get_max_token_amount(0), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn test_schedule_fee_update() {
+        zero_program_account!(fee_version_usage, FeeVersionUsageAccount);
+        account_info!(authority, crate::id());
+        clock_account_info!(clock, 100);
+        zero_program_account!(mut governor, GovernorAccount);
+        let program_fee = ProgramFee::new(5000, 11, 100, 33, 44, 300, 555).unwrap();
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            schedule_fee_update(
+                &signer,
+                &mut governor,
+                &clock,
+                &fee_version_usage,
+                program_fee.clone(),
+                200
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+        assert_eq!(
+            cancel_scheduled_fee_update(&signer, &mut governor, &clock),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Activation slot not after the current slot
+        assert_eq!(
+            schedule_fee_update(
+                &authority,
+                &mut governor,
+                &clock,
+                &fee_version_usage,
+                program_fee.clone(),
+                100
+            ),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+
+        schedule_fee_update(
+            &authority,
+            &mut governor,
+            &clock,
+            &fee_version_usage,
+            program_fee.clone(),
+            200,
+        )
+        .unwrap();
+        assert_eq!(
+            governor.get_scheduled_fee_update(),
+            ElusivOption::Some(ScheduledFeeUpdate {
+                program_fee: program_fee.clone(),
+                activation_slot: 200,
+            })
+        );
+
+        // Overlapping schedule
+        assert_eq!(
+            schedule_fee_update(
+                &authority,
+                &mut governor,
+                &clock,
+                &fee_version_usage,
+                program_fee,
+                300
+            ),
+            Err(ElusivError::FeeUpdateAlreadyScheduled.into())
+        );
+
+        cancel_scheduled_fee_update(&authority, &mut governor, &clock).unwrap();
+        assert_eq!(governor.get_scheduled_fee_update(), ElusivOption::None);
+    }
+
+    #[test]
+    fn test_schedule_fee_update_untracked_fee_version() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        account_info!(authority, crate::id());
+        clock_account_info!(clock, 100);
+        zero_program_account!(mut governor, GovernorAccount);
+        let program_fee = ProgramFee::new(5000, 11, 100, 33, 44, 300, 555).unwrap();
+
+        // The next fee-version would not be countable
+        governor.set_fee_version(&(FEE_VERSION_USAGE_WINDOW as u32 - 1));
+        assert_eq!(
+            schedule_fee_update(
+                &authority,
+                &mut governor,
+                &clock,
+                &fee_version_usage,
+                program_fee.clone(),
+                200
+            ),
+            Err(ElusivError::FeeVersionInUse.into())
+        );
+
+        retire_fee_version(&authority, &governor, &mut fee_version_usage, 0).unwrap();
+        schedule_fee_update(
+            &authority,
+            &mut governor,
+            &clock,
+            &fee_version_usage,
+            program_fee,
+            200,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_retire_fee_version() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+        governor.set_fee_version(&2);
+        fee_version_usage.admit(0).unwrap();
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            retire_fee_version(&signer, &governor, &mut fee_version_usage, 0),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Pending use
+        assert_eq!(
+            retire_fee_version(&authority, &governor, &mut fee_version_usage, 0),
+            Err(ElusivError::FeeVersionInUse.into())
+        );
+
+        fee_version_usage.release(0, 1);
+        retire_fee_version(&authority, &governor, &mut fee_version_usage, 0).unwrap();
+        retire_fee_version(&authority, &governor, &mut fee_version_usage, 1).unwrap();
+
+        // The current fee-version cannot be retired
+        assert_eq!(
+            retire_fee_version(&authority, &governor, &mut fee_version_usage, 2),
+            Err(ElusivError::InvalidFeeVersion.into())
+        );
+        assert_eq!(fee_version_usage.get_min_fee_version(), 2);
+    }
+
+    #[test]
+    fn test_set_fee_version_grace_slots() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+        clock_account_info!(clock, 99);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_fee_version_grace_slots(&signer, &mut governor, &clock, 10),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_fee_version_grace_slots(&authority, &mut governor, &clock, 10).unwrap();
+        assert_eq!(governor.get_fee_version_grace_slots(), 10);
+
+        // Possible before the activation of a scheduled update
+        governor.set_scheduled_fee_update(&ElusivOption::Some(ScheduledFeeUpdate {
+            program_fee: governor.get_program_fee(),
+            activation_slot: 100,
+        }));
+        set_fee_version_grace_slots(&authority, &mut governor, &clock, 20).unwrap();
+        assert_eq!(governor.get_fee_version_grace_slots(), 20);
+
+        // Refused once the update is activated
+        clock_account_info!(clock, 100);
+        assert_eq!(
+            set_fee_version_grace_slots(&authority, &mut governor, &clock, 30),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+        assert_eq!(governor.get_fee_version_grace_slots(), 20);
+    }
+
+    #[test]
+    fn test_reset_active_merkle_tree_max_trees() {
+        parent_account!(mut storage, StorageAccount);
+        parent_account!(mut nullifier, NullifierAccount);
+        zero_program_account!(mut queue, CommitmentQueueAccount);
+
+        storage.set_next_commitment_ptr(&(MT_COMMITMENT_COUNT as u32));
+        storage.set_trees_count(&(MAX_TREES - 2));
+        reset_active_merkle_tree(&mut storage, &mut queue, &mut nullifier, MAX_TREES - 2).unwrap();
+        assert_eq!(storage.get_trees_count(), MAX_TREES - 1);
+
+        // The last MT cannot be closed
+        storage.set_next_commitment_ptr(&(MT_COMMITMENT_COUNT as u32));
+        assert_eq!(
+            reset_active_merkle_tree(&mut storage, &mut queue, &mut nullifier, MAX_TREES - 1),
+            Err(ElusivError::MaxTreesReached.into())
+        );
+        assert_eq!(storage.get_trees_count(), MAX_TREES - 1);
+    }
+
+    #[test]
+    fn test_set_log_level() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_log_level(&signer, &mut governor, LogLevel::Off),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_log_level(&authority, &mut governor, LogLevel::Off).unwrap();
+        assert_eq!(governor.get_log_level(), ElusivOption::Some(LogLevel::Off));
+    }
+
+    #[test]
+    fn test_set_note_log_enabled() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_note_log_enabled(&signer, &mut governor, true),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_note_log_enabled(&authority, &mut governor, true).unwrap();
+        assert!(governor.get_note_log_enabled());
+
+        set_note_log_enabled(&authority, &mut governor, false).unwrap();
+        assert!(!governor.get_note_log_enabled());
+    }
+
+    #[test]
+    fn test_set_oracle_degraded() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_oracle_degraded(&signer, &mut governor, true),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_oracle_degraded(&authority, &mut governor, true).unwrap();
+        assert_eq!(
+            governor.verify_oracle_available(1),
+            Err(ElusivError::OracleUnavailable)
+        );
+        assert_eq!(governor.verify_oracle_available(0), Ok(()));
+
+        set_oracle_degraded(&authority, &mut governor, false).unwrap();
+        assert_eq!(governor.verify_oracle_available(1), Ok(()));
+    }
+
+    #[test]
+    fn test_set_verification_ttl_slots() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_verification_ttl_slots(&signer, &mut governor, 100),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_verification_ttl_slots(&authority, &mut governor, 100).unwrap();
+        assert_eq!(governor.get_verification_ttl_slots(), 100);
+
+        set_verification_ttl_slots(&authority, &mut governor, 0).unwrap();
+        assert_eq!(governor.get_verification_ttl_slots(), 0);
+    }
+
+    #[test]
+    fn test_set_warden_rotation_epoch() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_warden_rotation_epoch(&signer, &mut governor, 1_000),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_warden_rotation_epoch(&authority, &mut governor, 1_000).unwrap();
+        assert_eq!(governor.get_warden_rotation_epoch(), 1_000);
+
+        set_warden_rotation_epoch(&authority, &mut governor, 0).unwrap();
+        assert_eq!(governor.get_warden_rotation_epoch(), 0);
+    }
+
+    #[test]
+    fn test_set_proof_subvention_cap() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_proof_subvention_cap(&signer, &mut governor, 50),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_proof_subvention_cap(&authority, &mut governor, 50).unwrap();
+        assert_eq!(governor.get_proof_subvention_cap(), 50);
+
+        set_proof_subvention_cap(&authority, &mut governor, 0).unwrap();
+        assert_eq!(governor.get_proof_subvention_cap(), 0);
+    }
+
+    #[test]
+    fn test_set_commitment_queue_high_water_mark() {
+        account_info!(authority, crate::id());
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+
+        // Invalid authority
+        test_account_info!(signer, 0);
+        assert_eq!(
+            set_commitment_queue_high_water_mark(&signer, &mut governor, 2),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        set_commitment_queue_high_water_mark(&authority, &mut governor, 2).unwrap();
+        assert_eq!(governor.get_commitment_queue_high_water_mark(), 2);
+
+        // Admissions fail once the mark is reached
+        let mut queue = CommitmentQueue::new(&mut commitment_queue);
+        for _ in 0..2 {
+            queue
+                .admit_pending_commitment(governor.get_commitment_queue_high_water_mark())
+                .unwrap();
+        }
+        assert_eq!(
+            queue.admit_pending_commitment(governor.get_commitment_queue_high_water_mark()),
+            Err(ElusivError::CommitmentQueueCongested.into())
+        );
+        assert_eq!(queue.pending_commitments(), 2);
+
+        // Raising the mark admits further verifications
+        set_commitment_queue_high_water_mark(&authority, &mut governor, 3).unwrap();
+        queue
+            .admit_pending_commitment(governor.get_commitment_queue_high_water_mark())
+            .unwrap();
+        assert_eq!(queue.pending_commitments(), 3);
+    }
+
     #[test]
     fn test_verify_extern_data_account() {
         let pk = Pubkey::new_unique();
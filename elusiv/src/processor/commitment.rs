@@ -4,10 +4,10 @@ use crate::bytes::usize_as_u32_safe;
 use crate::commitment::{
     commitment_hash_computation_instructions, commitments_per_batch,
     compute_base_commitment_hash_partial, compute_commitment_hash_partial,
-    BaseCommitmentHashComputation, MAX_HT_COMMITMENTS,
+    MAX_COMMITMENT_BATCHING_RATE, MAX_HT_COMMITMENTS, MT_HEIGHT,
 };
 use crate::error::ElusivError;
-use crate::fields::{fr_to_u256_le, is_element_scalar_field, u256_to_big_uint, u256_to_fr_skip_mr};
+use crate::fields::{is_element_scalar_field, u256_to_big_uint, u256_to_fr_skip_mr};
 use crate::macros::{guard, pda_account, BorshSerDeSized};
 use crate::processor::utils::{
     transfer_lamports_from_pda_checked, transfer_token, transfer_token_from_pda,
@@ -17,24 +17,26 @@ use crate::state::commitment::{
     BaseCommitmentBufferAccount, BaseCommitmentHashingAccount, CommitmentHashingAccount,
     CommitmentQueue, CommitmentQueueAccount, COMMITMENT_BUFFER_LEN,
 };
+use crate::state::commitment_index::CommitmentIndexAccount;
 use crate::state::governor::FeeCollectorAccount;
 use crate::state::metadata::{
     CommitmentMetadata, MetadataAccount, MetadataQueue, MetadataQueueAccount,
 };
-use crate::state::storage::{StorageAccount, MT_COMMITMENT_COUNT};
+use crate::state::storage::{StorageAccount, EMPTY_TREE, MT_COMMITMENT_COUNT};
 use crate::state::{
-    fee::FeeAccount,
-    governor::GovernorAccount,
+    fee::{BaseCommitmentFee, FeeAccount, FeeVersionUsageAccount},
+    governor::{GovernorAccount, LogEvent},
     queue::{Queue, RingQueue},
 };
 use crate::token::{Token, TokenPrice};
-use crate::types::{RawU256, U256};
+use crate::types::{u256_to_le_limbs, RawU256, U256};
 use ark_bn254::Fr;
 use ark_ff::BigInteger256;
 use borsh::{BorshDeserialize, BorshSerialize};
-use elusiv_computation::PartialComputation;
 use elusiv_types::UnverifiedAccountInfo;
-use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+};
 
 #[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
@@ -59,6 +61,36 @@ pub struct CommitmentHashRequest {
     pub min_batching_rate: u32,
 }
 
+impl CommitmentHashRequest {
+    /// Checks the invariants of a request that are independent of the program state
+    /// - the commitment is non-zero and a scalar field element
+    /// - the batching rate is in `[0; MAX_COMMITMENT_BATCHING_RATE]`
+    pub fn verify(&self) -> ProgramResult {
+        guard!(self.commitment != [0; 32], ElusivError::MissingValue);
+        guard!(
+            is_element_scalar_field(BigInteger256(u256_to_le_limbs(self.commitment))),
+            ElusivError::NonScalarValue
+        );
+        guard!(
+            self.min_batching_rate as usize <= MAX_COMMITMENT_BATCHING_RATE,
+            ElusivError::InvalidBatchingRate
+        );
+
+        Ok(())
+    }
+
+    /// [`Self::verify`] and the `fee_version` not exceeding the current `fee_version`
+    pub fn verify_with_fee_version(&self, fee_version: u32) -> ProgramResult {
+        self.verify()?;
+        guard!(
+            self.fee_version <= fee_version,
+            ElusivError::InvalidFeeVersion
+        );
+
+        Ok(())
+    }
+}
+
 /// poseidon(0, 0)
 const ZERO_BASE_COMMITMENT: Fr = Fr::new(BigInteger256::new([
     3162363550698150530,
@@ -74,6 +106,10 @@ pub const ZERO_COMMITMENT: U256 = [
 ];
 
 /// poseidon(poseidon(0, 0), 0)
+///
+/// # Note
+///
+/// Hardcoded since the Poseidon hash cannot be evaluated in a const context, `test_zero_commitment` checks it against the hash function.
 pub const ZERO_COMMITMENT_RAW: U256 = [
     106, 77, 49, 231, 137, 82, 142, 103, 122, 195, 234, 157, 189, 191, 2, 42, 174, 41, 59, 182, 21,
     225, 230, 119, 13, 86, 164, 94, 87, 82, 83, 23,
@@ -113,6 +149,7 @@ pub fn store_base_commitment<'a, 'b>(
     base_commitment_buffer: &mut BaseCommitmentBufferAccount,
     token_program: &AccountInfo<'a>,
     system_program: &AccountInfo<'a>,
+    fee_version_usage: &mut FeeVersionUsageAccount,
 
     hash_account_index: u32,
     hash_account_bump: u8,
@@ -120,6 +157,7 @@ pub fn store_base_commitment<'a, 'b>(
     metadata: CommitmentMetadata,
 ) -> ProgramResult {
     let token_id = request.token_id;
+    governor.verify_oracle_available(token_id)?;
     let amount = Token::new_checked(token_id, request.amount)?;
     let price = TokenPrice::new(sol_usd_price_account, token_usd_price_account, token_id)?;
 
@@ -153,17 +191,20 @@ pub fn store_base_commitment<'a, 'b>(
         ElusivError::InvalidBatchingRate
     );
 
-    let fee = governor.get_program_fee();
-    let subvention = fee
-        .base_commitment_subvention
-        .into_token(&price, token_id)?;
-    let computation_fee = (fee.base_commitment_hash_computation_fee()
-        + fee.commitment_hash_computation_fee(request.min_batching_rate))?;
-    let computation_fee_token = computation_fee.into_token(&price, token_id)?;
-    let network_fee = Token::new(
+    // The use of the fee-version ends with the insertion of the commitment
+    fee_version_usage.admit(request.fee_version)?;
+
+    let BaseCommitmentFee {
+        computation_fee,
+        computation_fee_token,
+        network_fee,
+        subvention,
+    } = governor.get_program_fee().base_commitment_fee(
+        request.min_batching_rate,
+        amount.amount(),
         token_id,
-        fee.base_commitment_network_fee.calc(amount.amount()),
-    );
+        &price,
+    )?;
 
     verify_program_token_account(pool, pool_account, token_id)?;
     verify_program_token_account(fee_collector, fee_collector_account, token_id)?;
@@ -281,10 +322,7 @@ pub fn finalize_base_commitment_hash<'a>(
         hashing_account.get_fee_payer() == original_fee_payer.key.to_bytes(),
         ElusivError::InvalidAccount
     );
-    guard!(
-        (hashing_account.get_instruction() as usize) == BaseCommitmentHashComputation::IX_COUNT,
-        ElusivError::ComputationIsNotYetFinished
-    );
+    let commitment = hashing_account.finalize()?;
 
     // `pool` transfers `base_commitment_hash_fee` to `original_fee_payer` (lamports)
     transfer_lamports_from_pda_checked(
@@ -295,17 +333,17 @@ pub fn finalize_base_commitment_hash<'a>(
             .0,
     )?;
 
-    let commitment = hashing_account.get_state().result();
     let mut commitment_queue = CommitmentQueue::new(commitment_hash_queue);
     let mut metadata_queue = MetadataQueue::new(metadata_queue);
 
     enqueue_commitment(
         &mut commitment_queue,
         &mut metadata_queue,
-        fr_to_u256_le(&commitment),
+        commitment,
         hashing_account.get_metadata(),
         fee_version,
         hashing_account.get_min_batching_rate(),
+        false,
     )?;
 
     // Close hashing account
@@ -314,6 +352,11 @@ pub fn finalize_base_commitment_hash<'a>(
 }
 
 /// Enques a commitment and it's associated metadata into the corresponding queues
+///
+/// # Note
+///
+/// - `is_pending`: the commitment uses the slot reserved at the admission of its proof verification.
+/// - Otherwise the slots reserved for pending commitments are not available.
 pub fn enqueue_commitment(
     commitment_queue: &mut CommitmentQueue,
     metadata_queue: &mut MetadataQueue,
@@ -321,16 +364,96 @@ pub fn enqueue_commitment(
     metadata: CommitmentMetadata,
     fee_version: u32,
     min_batching_rate: u32,
+    is_pending: bool,
 ) -> ProgramResult {
-    commitment_queue.enqueue(CommitmentHashRequest {
+    if is_pending {
+        commitment_queue.release_pending_commitment();
+    } else if commitment_queue.pending_commitments() > 0 {
+        guard!(
+            commitment_queue.unreserved_slots() > 0,
+            ElusivError::CommitmentQueueCongested
+        );
+    }
+
+    // The fee version has already been checked against the governor by all callers
+    let request = CommitmentHashRequest {
         commitment,
         fee_version,
         min_batching_rate,
-    })?;
+    };
+    request.verify()?;
 
+    commitment_queue.enqueue(request)?;
     metadata_queue.enqueue(metadata)
 }
 
+/// Moves corrupted requests out of the commitment queue (checking up to `max_entries` requests per call)
+///
+/// # Note
+///
+/// Permissionless, since a corrupted request would otherwise block the queue once it's reached by [`init_commitment_hash`].
+pub fn validate_commitment_queue(
+    commitment_queue: &mut CommitmentQueueAccount,
+    metadata_queue: &mut MetadataQueueAccount,
+    governor: &GovernorAccount,
+
+    max_entries: u32,
+) -> ProgramResult {
+    let mut commitment_queue = CommitmentQueue::new(commitment_queue);
+    let mut metadata_queue = MetadataQueue::new(metadata_queue);
+
+    let quarantined = commitment_queue.validate(
+        &mut metadata_queue,
+        governor.latest_fee_version(),
+        max_entries,
+    )?;
+    if quarantined > 0 {
+        solana_program::msg!("Quarantined {} commitment(s)", quarantined);
+    }
+
+    Ok(())
+}
+
+/// Settles a quarantined request by either discarding it or by enqueuing its commitment again
+///
+/// # Note
+///
+/// - A force-hashed commitment is enqueued with the current fee version and batching rate (and still has to be a valid commitment).
+/// - The use of the request's fee version ends either way (a force-hashed commitment starts a use of the current fee version).
+/// - Since requests don't record their sender, refunds for discarded requests happen outside of the program.
+pub fn settle_quarantined_commitment(
+    authority: &AccountInfo,
+    governor: &GovernorAccount,
+    commitment_queue: &mut CommitmentQueueAccount,
+    metadata_queue: &mut MetadataQueueAccount,
+    fee_version_usage: &mut FeeVersionUsageAccount,
+
+    quarantine_index: u32,
+    force_hash: bool,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    let mut commitment_queue = CommitmentQueue::new(commitment_queue);
+    let mut metadata_queue = MetadataQueue::new(metadata_queue);
+
+    let quarantined = commitment_queue.quarantined_request(quarantine_index as usize)?;
+    fee_version_usage.release(quarantined.request.fee_version, 1);
+    if force_hash {
+        fee_version_usage.admit(governor.get_fee_version())?;
+        enqueue_commitment(
+            &mut commitment_queue,
+            &mut metadata_queue,
+            quarantined.request.commitment,
+            quarantined.metadata,
+            governor.get_fee_version(),
+            governor.get_commitment_batching_rate(),
+            false,
+        )?;
+    }
+
+    commitment_queue.release_quarantined_request(quarantine_index as usize)
+}
+
 /// Places the hash siblings into the hashing account
 pub fn init_commitment_hash_setup(
     hashing_account: &mut CommitmentHashingAccount,
@@ -359,6 +482,7 @@ fn init_commitment_hash_setup_inner(
         !hashing_account.get_is_active(),
         ElusivError::ComputationIsNotYetFinished
     );
+    storage_account.verify_fully_initialized()?;
 
     let ordering = storage_account.get_next_commitment_ptr();
     let siblings = storage_account.get_mt_opening(ordering as usize)?;
@@ -412,9 +536,16 @@ fn init_commitment_hash_inner(
     let (batch, batching_rate) = commitment_queue.next_batch()?;
     commitment_queue.remove(usize_as_u32_safe(batch.len()))?;
 
+    // A batch closed early is padded with empty commitments (and empty metadata)
+    let batch_size = commitments_per_batch(batching_rate);
+
     let mut metadata_queue = MetadataQueue::new(metadata_queue);
-    for _ in 0..batch.len() {
-        let metadata = metadata_queue.dequeue_first()?;
+    for i in 0..batch_size {
+        let metadata = if i < batch.len() {
+            metadata_queue.dequeue_first()?
+        } else {
+            CommitmentMetadata::default()
+        };
         metadata_account.add_commitment_metadata(&metadata)?;
     }
 
@@ -423,13 +554,16 @@ fn init_commitment_hash_inner(
 
     // Check for room for the commitment batch
     guard!(
-        hashing_account.get_ordering() as usize + batch.len() <= MT_COMMITMENT_COUNT,
+        hashing_account.get_ordering() as usize + batch_size <= MT_COMMITMENT_COUNT,
         ElusivError::NoRoomForCommitment
     );
 
     let mut commitments = [[0; 32]; MAX_HT_COMMITMENTS];
-    for i in 0..batch.len() {
-        commitments[i] = batch[i].commitment;
+    for (i, commitment) in commitments.iter_mut().take(batch_size).enumerate() {
+        *commitment = match batch.get(i) {
+            Some(request) => request.commitment,
+            None => EMPTY_TREE[0],
+        };
     }
 
     hashing_account.reset(batching_rate, fee_version, &commitments)
@@ -458,7 +592,9 @@ pub fn compute_commitment_hash<'a>(
     transfer_lamports_from_pda_checked(
         pool,
         fee_payer,
-        fee.get_program_fee().hash_tx_compensation().0,
+        fee.get_program_fee()
+            .commitment_hash_tx_compensation(hashing_account.get_batching_rate())
+            .0,
     )
 }
 
@@ -466,6 +602,9 @@ pub fn compute_commitment_hash<'a>(
 pub fn finalize_commitment_hash(
     hashing_account: &mut CommitmentHashingAccount,
     storage_account: &mut StorageAccount,
+    commitment_index: &mut CommitmentIndexAccount,
+    governor: &GovernorAccount,
+    fee_version_usage: &mut FeeVersionUsageAccount,
 ) -> ProgramResult {
     guard!(
         hashing_account.get_is_active(),
@@ -492,35 +631,157 @@ pub fn finalize_commitment_hash(
             <= MT_COMMITMENT_COUNT,
         ElusivError::NoRoomForCommitment
     );
+    storage_account.verify_fully_initialized()?;
 
     hashing_account.update_mt(storage_account, finalization_ix);
     hashing_account.set_finalization_ix(&(finalization_ix + 1));
     if finalization_ix == batching_rate {
         hashing_account.set_is_active(&false);
         hashing_account.set_setup(&false);
+
+        let inserted = commitment_inserted_events(hashing_account, storage_account)?;
+        fee_version_usage.release(
+            hashing_account.get_fee_version(),
+            usize_as_u32_safe(inserted.len()),
+        );
+
+        let next_commitment_ptr = storage_account.get_next_commitment_ptr();
+        for event in &inserted {
+            commitment_index.insert(
+                &event.commitment,
+                event.leaf_index as u32,
+                event.tree_index,
+                next_commitment_ptr,
+            )?;
+        }
+
+        if governor.logs(LogEvent::CommitmentInserted) {
+            for event in inserted {
+                solana_program::log::sol_log_data(&[CommitmentInserted::TAG, &event.try_to_vec()?]);
+            }
+        }
     }
     Ok(())
 }
 
+/// Returns the commitment at `leaf_index` of the active MT (as return data)
+pub fn view_commitment(storage_account: &StorageAccount, leaf_index: u32) -> ProgramResult {
+    storage_account.verify_fully_initialized()?;
+    let commitment = storage_account.get_commitment(leaf_index as usize)?;
+    solana_program::program::set_return_data(&commitment);
+
+    Ok(())
+}
+
+/// Returns the fee of a deposit of `amount` with the current fee-version (as return data, a borsh-serialized `u64` in `token_id`-Token)
+/// - the fee is net of the subvention and paid on top of `amount` (see [`store_base_commitment`])
+pub fn quote_base_commitment_fee(
+    sol_usd_price_account: &AccountInfo,
+    token_usd_price_account: &AccountInfo,
+    governor: &GovernorAccount,
+
+    token_id: u16,
+    amount: u64,
+) -> ProgramResult {
+    governor.verify_oracle_available(token_id)?;
+    let amount = Token::new_checked(token_id, amount)?;
+    let price = TokenPrice::new(sol_usd_price_account, token_usd_price_account, token_id)?;
+
+    let quote = governor.get_program_fee().base_commitment_fee_quote(
+        governor.get_commitment_batching_rate(),
+        amount.amount(),
+        token_id,
+        &price,
+    )?;
+    solana_program::program::set_return_data(&quote.amount().try_to_vec()?);
+
+    Ok(())
+}
+
+/// Returns the leaf index of `commitment` in the active MT (as return data, a borsh-serialized `Option<u32>`)
+pub fn lookup_commitment(
+    storage_account: &StorageAccount,
+    commitment_index: &CommitmentIndexAccount,
+    commitment: U256,
+) -> ProgramResult {
+    let leaf_index = commitment_index.lookup(
+        &commitment,
+        storage_account.get_trees_count(),
+        storage_account.get_next_commitment_ptr(),
+    )?;
+    solana_program::program::set_return_data(&leaf_index.try_to_vec()?);
+
+    Ok(())
+}
+
+/// Event emitted for every commitment inserted into the active MT
+#[derive(BorshDeserialize, BorshSerialize, PartialEq, Clone)]
+#[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
+pub struct CommitmentInserted {
+    pub leaf_index: u64,
+    pub commitment: U256,
+    pub new_root: U256,
+    pub tree_index: u32,
+}
+
+impl CommitmentInserted {
+    pub const TAG: &'static [u8] = b"commitment_inserted";
+}
+
+/// The [`CommitmentInserted`] events of the batch inserted by the last [`finalize_commitment_hash`]
+fn commitment_inserted_events(
+    hashing_account: &CommitmentHashingAccount,
+    storage_account: &StorageAccount,
+) -> Result<Vec<CommitmentInserted>, ProgramError> {
+    let ordering = hashing_account.get_ordering() as usize;
+    let batch_size = commitments_per_batch(hashing_account.get_batching_rate());
+    let new_root = storage_account.get_root()?;
+    let tree_index = storage_account.get_trees_count();
+
+    let mut events = Vec::new();
+    for leaf_index in ordering..ordering + batch_size {
+        let commitment = storage_account.get_node(leaf_index, MT_HEIGHT)?;
+
+        // Skip the padding of batches closed early
+        if commitment == EMPTY_TREE[0] {
+            continue;
+        }
+
+        events.push(CommitmentInserted {
+            leaf_index: leaf_index as u64,
+            commitment,
+            new_root,
+            tree_index,
+        });
+    }
+
+    Ok(events)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::commitment::poseidon_hash::full_poseidon2_hash;
+    use crate::commitment::poseidon_hash::{full_poseidon2_hash, BinarySpongeHashingState};
+    use crate::commitment::BaseCommitmentHashComputation;
     use crate::fields::{
-        big_uint_to_u256, fr_to_u256_le_repr, u256_from_str_skip_mr, SCALAR_MODULUS_RAW,
+        big_uint_to_u256, fr_to_u256_le, fr_to_u256_le_repr, u256_from_str_skip_mr,
+        SCALAR_MODULUS_RAW,
     };
     use crate::macros::{
         account_info, parent_account, program_token_account_info, pyth_price_account_info,
         test_account_info, test_pda_account_info, zero_program_account,
     };
     use crate::processor::mutate;
-    use crate::state::governor::PoolAccount;
+    use crate::state::commitment::QuarantinedCommitment;
+    use crate::state::fee::ProgramFee;
+    use crate::state::governor::{LogLevel, PoolAccount};
     use crate::state::program_account::{PDAAccount, SizedAccount};
     use crate::state::storage::{EMPTY_TREE, MT_HEIGHT};
-    use crate::token::{lamports_token, usdc_token, LAMPORTS_TOKEN_ID, USDC_TOKEN_ID};
+    use crate::token::{lamports_token, usdc_token, Lamports, LAMPORTS_TOKEN_ID, USDC_TOKEN_ID};
     use ark_ff::Zero;
+    use elusiv_computation::PartialComputation;
     use elusiv_types::tokens::Price;
-    use elusiv_types::{BorshSerDeSized, TokenError};
+    use elusiv_types::{BorshSerDeSized, ElusivOption, ParentAccount, TokenError};
     use solana_program::native_token::LAMPORTS_PER_SOL;
     use solana_program::program_error::ProgramError;
     use solana_program::pubkey::Pubkey;
@@ -545,6 +806,13 @@ mod tests {
         );
 
         assert_eq!(RawU256::new(ZERO_COMMITMENT_RAW).reduce(), ZERO_COMMITMENT);
+        assert_eq!(
+            fr_to_u256_le_repr(&full_poseidon2_hash(
+                full_poseidon2_hash(Fr::zero(), Fr::zero()),
+                Fr::zero()
+            )),
+            ZERO_COMMITMENT_RAW
+        );
 
         assert_eq!(
             full_poseidon2_hash(Fr::zero(), Fr::zero()),
@@ -573,6 +841,7 @@ mod tests {
 
     #[test]
     fn test_store_base_commitment_lamports() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
         zero_program_account!(mut governor, GovernorAccount);
         zero_program_account!(storage, StorageAccount);
         zero_program_account!(mut buffer, BaseCommitmentBufferAccount);
@@ -684,6 +953,7 @@ mod tests {
                     &mut buffer,
                     &sys,
                     &sys,
+                    &mut fee_version_usage,
                     0,
                     bump,
                     request,
@@ -712,6 +982,7 @@ mod tests {
                 &mut buffer,
                 &sys,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -739,6 +1010,7 @@ mod tests {
                 &mut buffer,
                 &sys,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -766,6 +1038,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -793,6 +1066,7 @@ mod tests {
                 &mut buffer,
                 &sys,
                 &sys,
+                &mut fee_version_usage,
                 1,
                 bump,
                 request.clone(),
@@ -820,6 +1094,7 @@ mod tests {
                 &mut buffer,
                 &sys,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 0,
                 request.clone(),
@@ -846,6 +1121,7 @@ mod tests {
                 &mut buffer,
                 &sys,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -873,6 +1149,7 @@ mod tests {
                 &mut buffer,
                 &sys,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request,
@@ -884,7 +1161,8 @@ mod tests {
 
     #[test]
     fn test_store_base_commitment_token() {
-        zero_program_account!(governor, GovernorAccount);
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(mut governor, GovernorAccount);
         zero_program_account!(storage, StorageAccount);
         zero_program_account!(mut buffer, BaseCommitmentBufferAccount);
         test_account_info!(sender);
@@ -964,6 +1242,7 @@ mod tests {
                     &mut buffer,
                     &spl,
                     &sys,
+                    &mut fee_version_usage,
                     0,
                     bump,
                     request,
@@ -973,6 +1252,36 @@ mod tests {
             );
         }
 
+        // Token deposits are rejected while the oracle is degraded
+        governor.set_oracle_degraded(&true);
+        assert_eq!(
+            store_base_commitment(
+                &sender,
+                &sender_token,
+                &fee_payer,
+                &fee_payer_token,
+                &pool,
+                &pool_token,
+                &fee_c,
+                &fee_c_token,
+                &sol,
+                &usdc,
+                &governor,
+                &storage,
+                UnverifiedAccountInfo::new(&hashing_acc),
+                &mut buffer,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                0,
+                bump,
+                request.clone(),
+                metadata,
+            ),
+            Err(ElusivError::OracleUnavailable.into())
+        );
+        governor.set_oracle_degraded(&false);
+
         // Invalid pool_account
         assert_eq!(
             store_base_commitment(
@@ -992,6 +1301,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1019,6 +1329,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1046,6 +1357,7 @@ mod tests {
                 &mut buffer,
                 &sys,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1073,6 +1385,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 1,
                 bump,
                 request.clone(),
@@ -1100,6 +1413,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1127,6 +1441,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1154,6 +1469,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1181,6 +1497,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1207,6 +1524,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request.clone(),
@@ -1234,6 +1552,7 @@ mod tests {
                 &mut buffer,
                 &spl,
                 &sys,
+                &mut fee_version_usage,
                 0,
                 bump,
                 request,
@@ -1368,6 +1687,11 @@ mod tests {
         {
             pda_account!(mut h, BaseCommitmentHashingAccount, h_account);
             h.set_instruction(&(BaseCommitmentHashComputation::IX_COUNT as u32));
+            h.set_state(&BinarySpongeHashingState([
+                Fr::from(1u64),
+                Fr::zero(),
+                Fr::zero(),
+            ]));
 
             let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
             for _ in 0..CommitmentQueue::CAPACITY {
@@ -1394,7 +1718,21 @@ mod tests {
             Err(ElusivError::QueueIsFull.into())
         );
 
+        // Remaining slot is reserved for a pending commitment
         zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        {
+            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            for _ in 0..CommitmentQueue::CAPACITY - 1 {
+                commitment_queue
+                    .enqueue(CommitmentHashRequest {
+                        commitment: [0; 32],
+                        min_batching_rate: 0,
+                        fee_version: 0,
+                    })
+                    .unwrap();
+            }
+            commitment_queue.admit_pending_commitment(0).unwrap();
+        }
         assert_eq!(
             finalize_base_commitment_hash(
                 &fee_payer,
@@ -1406,122 +1744,445 @@ mod tests {
                 0,
                 0
             ),
-            Ok(())
+            Err(ElusivError::CommitmentQueueCongested.into())
         );
 
-        Ok(())
-    }
-
-    #[test]
-    fn test_init_commitment_hash_empty_queue() {
-        parent_account!(storage_account, StorageAccount);
-        parent_account!(mut metadata_account, MetadataAccount);
         zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
-        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
-        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
-
-        init_commitment_hash_setup(&mut hashing_account, &storage_account, false).unwrap();
         assert_eq!(
-            init_commitment_hash(
+            finalize_base_commitment_hash(
+                &fee_payer,
+                &pool,
+                &fee,
+                &h_account,
                 &mut commitment_queue,
                 &mut metadata_queue,
-                &mut hashing_account,
-                &mut metadata_account,
-                false
+                0,
+                0
             ),
-            Err(ElusivError::QueueIsEmpty.into())
+            Ok(())
         );
+
+        Ok(())
     }
 
     #[test]
-    fn test_init_commitment_hash_active_computation() {
-        parent_account!(mut metadata_account, MetadataAccount);
+    fn test_enqueue_commitment_pending() {
         zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
         zero_program_account!(mut metadata_queue, MetadataQueueAccount);
-        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+        let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+        let high_water_mark = 10;
+
+        // Admission until the high-water mark
+        for _ in 0..high_water_mark {
+            commitment_queue
+                .admit_pending_commitment(high_water_mark)
+                .unwrap();
+        }
+        assert_eq!(
+            commitment_queue.admit_pending_commitment(high_water_mark),
+            Err(ElusivError::CommitmentQueueCongested.into())
+        );
 
-        {
-            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
-            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+        // Fill all unreserved slots
+        for _ in 0..commitment_queue.unreserved_slots() {
             enqueue_commitment(
                 &mut commitment_queue,
                 &mut metadata_queue,
-                [0; 32],
+                [1; 32],
                 CommitmentMetadata::default(),
                 0,
                 0,
+                false,
             )
             .unwrap();
         }
-
-        hashing_account.set_is_active(&true);
-        hashing_account.set_setup(&true);
         assert_eq!(
-            init_commitment_hash(
+            enqueue_commitment(
                 &mut commitment_queue,
                 &mut metadata_queue,
-                &mut hashing_account,
-                &mut metadata_account,
-                false
+                [1; 32],
+                CommitmentMetadata::default(),
+                0,
+                0,
+                false,
             ),
-            Err(ElusivError::ComputationIsNotYetFinished.into())
+            Err(ElusivError::CommitmentQueueCongested.into())
         );
-    }
 
-    #[test]
-    fn test_init_commitment_hash_full_storage() {
-        parent_account!(mut storage_account, StorageAccount);
-        parent_account!(mut metadata_account, MetadataAccount);
-        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
-        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
-        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+        // Every admitted commitment still finds a slot (while new admissions are rejected)
+        for i in 0..high_water_mark {
+            assert_eq!(
+                commitment_queue.admit_pending_commitment(high_water_mark),
+                Err(ElusivError::CommitmentQueueCongested.into())
+            );
 
-        {
-            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
-            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
             enqueue_commitment(
                 &mut commitment_queue,
                 &mut metadata_queue,
-                [0; 32],
+                [1; 32],
                 CommitmentMetadata::default(),
                 0,
                 0,
+                true,
             )
             .unwrap();
+            assert_eq!(
+                commitment_queue.pending_commitments(),
+                high_water_mark - i - 1
+            );
         }
+        assert_eq!(commitment_queue.empty_slots(), 0);
+    }
 
-        storage_account.set_next_commitment_ptr(&(MT_COMMITMENT_COUNT as u32));
-        init_commitment_hash_setup(&mut hashing_account, &storage_account, false).unwrap();
-        assert_eq!(
-            init_commitment_hash(
-                &mut commitment_queue,
-                &mut metadata_queue,
-                &mut hashing_account,
-                &mut metadata_account,
-                false
+    #[test]
+    fn test_enqueue_commitment_invalid() {
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+
+        for (commitment, min_batching_rate, err) in [
+            ([0; 32], 0, ElusivError::MissingValue),
+            ([255; 32], 0, ElusivError::NonScalarValue),
+            (
+                [1; 32],
+                MAX_COMMITMENT_BATCHING_RATE as u32 + 1,
+                ElusivError::InvalidBatchingRate,
             ),
-            Err(ElusivError::NoRoomForCommitment.into())
-        );
+        ] {
+            assert_eq!(
+                enqueue_commitment(
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    commitment,
+                    CommitmentMetadata::default(),
+                    0,
+                    min_batching_rate,
+                    false,
+                ),
+                Err(err.into())
+            );
+        }
+
+        assert!(commitment_queue.is_empty());
+        assert!(metadata_queue.is_empty());
     }
 
     #[test]
-    fn test_init_commitment_hash_incomplete_batch() {
+    fn test_validate_commitment_queue() {
         parent_account!(storage_account, StorageAccount);
         parent_account!(mut metadata_account, MetadataAccount);
         zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
         zero_program_account!(mut metadata_queue, MetadataQueueAccount);
         zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        governor.set_fee_version(&1);
+
+        let request = |i: u8| CommitmentHashRequest {
+            commitment: [i; 32],
+            fee_version: 1,
+            min_batching_rate: 1,
+        };
 
+        // A corrupted request (bypassing the enqueue-time validation) between valid ones
         {
             let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
             let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
-            enqueue_commitment(
-                &mut commitment_queue,
-                &mut metadata_queue,
-                [0; 32],
-                CommitmentMetadata::default(),
+            for i in 1..=3 {
+                let r = if i == 2 {
+                    CommitmentHashRequest {
+                        fee_version: 2,
+                        ..request(i)
+                    }
+                } else {
+                    request(i)
+                };
+                commitment_queue.enqueue(r).unwrap();
+                metadata_queue
+                    .enqueue([i; CommitmentMetadata::SIZE])
+                    .unwrap();
+            }
+        }
+
+        // The cursor is stored in between calls
+        validate_commitment_queue(&mut commitment_queue, &mut metadata_queue, &governor, 1)
+            .unwrap();
+        assert_eq!(commitment_queue.get_validation_ptr(), 1);
+        validate_commitment_queue(&mut commitment_queue, &mut metadata_queue, &governor, 2)
+            .unwrap();
+
+        {
+            let commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            assert_eq!(commitment_queue.len(), 2);
+            assert_eq!(
+                commitment_queue.quarantined_requests()[0],
+                ElusivOption::Some(QuarantinedCommitment {
+                    request: CommitmentHashRequest {
+                        fee_version: 2,
+                        ..request(2)
+                    },
+                    metadata: [2; CommitmentMetadata::SIZE],
+                })
+            );
+        }
+
+        // Hashing proceeds past the quarantined request
+        init_commitment_hash_setup(&mut hashing_account, &storage_account, false).unwrap();
+        init_commitment_hash(
+            &mut commitment_queue,
+            &mut metadata_queue,
+            &mut hashing_account,
+            &mut metadata_account,
+            false,
+        )
+        .unwrap();
+        assert_eq!(hashing_account.get_hash_tree(0), [1; 32]);
+        assert_eq!(hashing_account.get_hash_tree(1), [3; 32]);
+        assert_eq!(hashing_account.get_fee_version(), 1);
+
+        assert_eq!(
+            metadata_account.get_commitment_metadata(0),
+            Ok([1; CommitmentMetadata::SIZE])
+        );
+        assert_eq!(
+            metadata_account.get_commitment_metadata(1),
+            Ok([3; CommitmentMetadata::SIZE])
+        );
+    }
+
+    #[test]
+    fn test_settle_quarantined_commitment() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        account_info!(authority, crate::id());
+        test_account_info!(signer, 0);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        governor.set_fee_version(&3);
+        governor.set_commitment_batching_rate(&2);
+
+        {
+            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+            for commitment in [[1; 32], [255; 32], [2; 32]] {
+                commitment_queue
+                    .enqueue(CommitmentHashRequest {
+                        commitment,
+                        fee_version: 4,
+                        min_batching_rate: 0,
+                    })
+                    .unwrap();
+                metadata_queue
+                    .enqueue([commitment[0]; CommitmentMetadata::SIZE])
+                    .unwrap();
+            }
+        }
+        validate_commitment_queue(&mut commitment_queue, &mut metadata_queue, &governor, 3)
+            .unwrap();
+
+        // Invalid authority
+        assert_eq!(
+            settle_quarantined_commitment(
+                &signer,
+                &governor,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &mut fee_version_usage,
                 0,
+                true
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Force-hash with the current fee version and batching rate
+        settle_quarantined_commitment(
+            &authority,
+            &governor,
+            &mut commitment_queue,
+            &mut metadata_queue,
+            &mut fee_version_usage,
+            0,
+            true,
+        )
+        .unwrap();
+
+        // A non-scalar commitment cannot be force-hashed, but only be discarded
+        assert_eq!(
+            settle_quarantined_commitment(
+                &authority,
+                &governor,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &mut fee_version_usage,
+                1,
+                true
+            ),
+            Err(ElusivError::NonScalarValue.into())
+        );
+        settle_quarantined_commitment(
+            &authority,
+            &governor,
+            &mut commitment_queue,
+            &mut metadata_queue,
+            &mut fee_version_usage,
+            1,
+            false,
+        )
+        .unwrap();
+
+        // Already settled
+        assert_eq!(
+            settle_quarantined_commitment(
+                &authority,
+                &governor,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &mut fee_version_usage,
                 1,
+                false
+            ),
+            Err(ElusivError::MissingValue.into())
+        );
+
+        let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        let metadata_queue = MetadataQueue::new(&mut metadata_queue);
+        assert_eq!(
+            commitment_queue.dequeue_first(),
+            Ok(CommitmentHashRequest {
+                commitment: [1; 32],
+                fee_version: 3,
+                min_batching_rate: 2,
+            })
+        );
+        assert!(commitment_queue.is_empty());
+        assert_eq!(
+            metadata_queue.view_first(),
+            Ok([1; CommitmentMetadata::SIZE])
+        );
+        assert_eq!(
+            commitment_queue
+                .quarantined_requests()
+                .iter()
+                .filter(|q| q.option().is_some())
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_init_commitment_hash_empty_queue() {
+        parent_account!(storage_account, StorageAccount);
+        parent_account!(mut metadata_account, MetadataAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        init_commitment_hash_setup(&mut hashing_account, &storage_account, false).unwrap();
+        assert_eq!(
+            init_commitment_hash(
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &mut hashing_account,
+                &mut metadata_account,
+                false
+            ),
+            Err(ElusivError::QueueIsEmpty.into())
+        );
+    }
+
+    #[test]
+    fn test_init_commitment_hash_active_computation() {
+        parent_account!(mut metadata_account, MetadataAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        {
+            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+            enqueue_commitment(
+                &mut commitment_queue,
+                &mut metadata_queue,
+                [1; 32],
+                CommitmentMetadata::default(),
+                0,
+                0,
+                false,
+            )
+            .unwrap();
+        }
+
+        hashing_account.set_is_active(&true);
+        hashing_account.set_setup(&true);
+        assert_eq!(
+            init_commitment_hash(
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &mut hashing_account,
+                &mut metadata_account,
+                false
+            ),
+            Err(ElusivError::ComputationIsNotYetFinished.into())
+        );
+    }
+
+    #[test]
+    fn test_init_commitment_hash_full_storage() {
+        parent_account!(mut storage_account, StorageAccount);
+        parent_account!(mut metadata_account, MetadataAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        {
+            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+            enqueue_commitment(
+                &mut commitment_queue,
+                &mut metadata_queue,
+                [1; 32],
+                CommitmentMetadata::default(),
+                0,
+                0,
+                false,
+            )
+            .unwrap();
+        }
+
+        storage_account.set_next_commitment_ptr(&(MT_COMMITMENT_COUNT as u32));
+        init_commitment_hash_setup(&mut hashing_account, &storage_account, false).unwrap();
+        assert_eq!(
+            init_commitment_hash(
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &mut hashing_account,
+                &mut metadata_account,
+                false
+            ),
+            Err(ElusivError::NoRoomForCommitment.into())
+        );
+    }
+
+    #[test]
+    fn test_init_commitment_hash_incomplete_batch() {
+        parent_account!(storage_account, StorageAccount);
+        parent_account!(mut metadata_account, MetadataAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        {
+            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+            enqueue_commitment(
+                &mut commitment_queue,
+                &mut metadata_queue,
+                [1; 32],
+                CommitmentMetadata::default(),
+                0,
+                1,
+                false,
             )
             .unwrap();
         }
@@ -1554,10 +2215,11 @@ mod tests {
                 enqueue_commitment(
                     &mut commitment_queue,
                     &mut metadata_queue,
-                    [0; 32],
+                    [1; 32],
                     CommitmentMetadata::default(),
                     0,
                     1,
+                    false,
                 )
                 .unwrap();
             }
@@ -1622,6 +2284,166 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_init_commitment_hash_recorded_batching_rate() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        parent_account!(mut storage_account, StorageAccount);
+        parent_account!(mut commitment_index, CommitmentIndexAccount);
+        parent_account!(mut metadata_account, MetadataAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut fee, FeeAccount);
+        test_account_info!(pool, 0);
+        test_account_info!(fee_payer, 0);
+
+        let program_fee = ProgramFee {
+            warden_hash_commitment_reward: Lamports(100),
+            ..ProgramFee::new(5000, 11, 100, 0, 0, 300, 555).unwrap()
+        };
+        assert!(program_fee.is_valid());
+        fee.set_program_fee(&program_fee);
+
+        // Commitments priced at rate 2
+        governor.set_commitment_batching_rate(&2);
+        {
+            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+            for i in 1..=4 {
+                enqueue_commitment(
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    [i; 32],
+                    CommitmentMetadata::default(),
+                    0,
+                    governor.get_commitment_batching_rate(),
+                    false,
+                )
+                .unwrap();
+            }
+
+            // Governance raises the rate, which only applies to new commitments
+            governor.set_commitment_batching_rate(&4);
+            enqueue_commitment(
+                &mut commitment_queue,
+                &mut metadata_queue,
+                [5; 32],
+                CommitmentMetadata::default(),
+                0,
+                governor.get_commitment_batching_rate(),
+                false,
+            )
+            .unwrap();
+        }
+
+        init_commitment_hash_setup(&mut hashing_account, &storage_account, false).unwrap();
+        init_commitment_hash(
+            &mut commitment_queue,
+            &mut metadata_queue,
+            &mut hashing_account,
+            &mut metadata_account,
+            false,
+        )
+        .unwrap();
+        assert_eq!(hashing_account.get_batching_rate(), 2);
+
+        // The new commitment remains queued under its own rate
+        let commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        assert_eq!(commitment_queue.len(), 1);
+        assert_eq!(commitment_queue.view_first().unwrap().min_batching_rate, 4);
+
+        // Hashing requires the rounds of rate 2
+        let pool_lamports = pool.lamports();
+        let tx_count = commitment_hash_computation_instructions(2).len();
+        for _ in 0..tx_count {
+            compute_commitment_hash(&fee_payer, &fee, &pool, &mut hashing_account, 0, 0).unwrap();
+        }
+        assert_eq!(
+            compute_commitment_hash(&fee_payer, &fee, &pool, &mut hashing_account, 0, 0),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+
+        // The warden compensation is covered by the fees escrowed at rate 2
+        let compensation = pool_lamports - pool.lamports();
+        assert_eq!(
+            compensation,
+            tx_count as u64 * program_fee.commitment_hash_tx_compensation(2).0
+        );
+        let escrowed = 4 * program_fee.commitment_hash_computation_fee(2).0;
+        assert!(escrowed >= compensation && escrowed - compensation < tx_count as u64 + 4);
+
+        for _ in 0..=2 {
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            )
+            .unwrap();
+        }
+        assert_eq!(storage_account.get_next_commitment_ptr(), 4);
+    }
+
+    #[test]
+    fn test_init_commitment_hash_batch_closed_early() {
+        parent_account!(storage_account, StorageAccount);
+        parent_account!(mut metadata_account, MetadataAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        {
+            let mut commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+            let mut metadata_queue = MetadataQueue::new(&mut metadata_queue);
+            for (i, min_batching_rate) in [2, 2, 1].iter().enumerate() {
+                enqueue_commitment(
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    [i as u8 + 1; 32],
+                    [i as u8 + 1; CommitmentMetadata::SIZE],
+                    0,
+                    *min_batching_rate,
+                    false,
+                )
+                .unwrap();
+            }
+        }
+
+        init_commitment_hash_setup(&mut hashing_account, &storage_account, false).unwrap();
+        init_commitment_hash(
+            &mut commitment_queue,
+            &mut metadata_queue,
+            &mut hashing_account,
+            &mut metadata_account,
+            false,
+        )
+        .unwrap();
+
+        // The rate-2 batch is padded with empty commitments
+        assert_eq!(hashing_account.get_batching_rate(), 2);
+        assert_eq!(hashing_account.get_hash_tree(0), [1; 32]);
+        assert_eq!(hashing_account.get_hash_tree(1), [2; 32]);
+        assert_eq!(hashing_account.get_hash_tree(2), EMPTY_TREE[0]);
+        assert_eq!(hashing_account.get_hash_tree(3), EMPTY_TREE[0]);
+
+        // Metadata stays aligned with the MT leaves
+        assert_eq!(metadata_account.get_next_metadata_ptr(), 4);
+        assert_eq!(
+            metadata_account.get_commitment_metadata(1),
+            Ok([2; CommitmentMetadata::SIZE])
+        );
+        assert_eq!(
+            metadata_account.get_commitment_metadata(3),
+            Ok(CommitmentMetadata::default())
+        );
+
+        let commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        assert_eq!(commitment_queue.view_first().unwrap().commitment, [3; 32]);
+        assert_eq!(MetadataQueue::new(&mut metadata_queue).len(), 1);
+    }
+
     #[test]
     fn test_init_commitment_hash_setup_insertion_can_fail() {
         parent_account!(storage_account, StorageAccount);
@@ -1695,14 +2517,23 @@ mod tests {
 
     #[test]
     fn test_finalize_commitment_hash() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(governor, GovernorAccount);
         parent_account!(mut storage_account, StorageAccount);
+        parent_account!(mut commitment_index, CommitmentIndexAccount);
         zero_program_account!(mut hashing_account, CommitmentHashingAccount);
 
         // Computation not finished
         hashing_account.set_is_active(&true);
         hashing_account.set_instruction(&0);
         assert_eq!(
-            finalize_commitment_hash(&mut hashing_account, &mut storage_account),
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            ),
             Err(ElusivError::ComputationIsNotYetFinished.into())
         );
 
@@ -1711,7 +2542,13 @@ mod tests {
         hashing_account
             .set_instruction(&(commitment_hash_computation_instructions(0).len() as u32));
         assert_eq!(
-            finalize_commitment_hash(&mut hashing_account, &mut storage_account),
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            ),
             Err(ElusivError::ComputationIsNotYetStarted.into())
         );
 
@@ -1719,17 +2556,59 @@ mod tests {
         hashing_account.set_is_active(&true);
         storage_account.set_next_commitment_ptr(&(MT_COMMITMENT_COUNT as u32));
         assert_eq!(
-            finalize_commitment_hash(&mut hashing_account, &mut storage_account),
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            ),
             Err(ElusivError::NoRoomForCommitment.into())
         );
 
         storage_account.set_next_commitment_ptr(&0);
-        finalize_commitment_hash(&mut hashing_account, &mut storage_account).unwrap();
+
+        // Storage account is not fully initialized
+        let last_child_pubkey = storage_account.get_child_pubkey(StorageAccount::COUNT - 1);
+        storage_account.set_child_pubkey(StorageAccount::COUNT - 1, ElusivOption::None);
+        assert_eq!(
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            ),
+            Err(ElusivError::StorageNotFullyInitialized.into())
+        );
+        hashing_account.set_is_active(&false);
+        assert_eq!(
+            init_commitment_hash_setup(&mut hashing_account, &storage_account, false),
+            Err(ElusivError::StorageNotFullyInitialized.into())
+        );
+        hashing_account.set_is_active(&true);
+        assert_eq!(
+            view_commitment(&storage_account, 0),
+            Err(ElusivError::StorageNotFullyInitialized.into())
+        );
+
+        storage_account.set_child_pubkey(StorageAccount::COUNT - 1, last_child_pubkey.into());
+        finalize_commitment_hash(
+            &mut hashing_account,
+            &mut storage_account,
+            &mut commitment_index,
+            &governor,
+            &mut fee_version_usage,
+        )
+        .unwrap();
     }
 
     #[test]
     fn test_finalize_commitment_hash_valid() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(governor, GovernorAccount);
         parent_account!(mut storage_account, StorageAccount);
+        parent_account!(mut commitment_index, CommitmentIndexAccount);
         zero_program_account!(mut hashing_account, CommitmentHashingAccount);
 
         let batching_rate = 4;
@@ -1751,8 +2630,20 @@ mod tests {
             }
         }
 
+        // One use of the fee-version per commitment (and one of a later batch)
+        for _ in 0..=commitment_count {
+            fee_version_usage.admit(0).unwrap();
+        }
+
         for _ in 0..=batching_rate {
-            finalize_commitment_hash(&mut hashing_account, &mut storage_account).unwrap();
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            )
+            .unwrap();
         }
 
         assert!(!hashing_account.get_is_active());
@@ -1761,6 +2652,7 @@ mod tests {
             storage_account.get_next_commitment_ptr(),
             commitment_count as u32
         );
+        assert_eq!(fee_version_usage.pending_count(0), Some(1));
 
         // Check that MT is updated
         for level_inv in 0..=MT_HEIGHT {
@@ -1774,4 +2666,265 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_quote_base_commitment_fee() {
+        zero_program_account!(mut governor, GovernorAccount);
+        test_account_info!(any, 0);
+        let sol_usd = Price {
+            price: 39,
+            conf: 1,
+            expo: 0,
+        };
+        let usdc_usd = Price {
+            price: 1,
+            conf: 1,
+            expo: 0,
+        };
+        pyth_price_account_info!(sol, LAMPORTS_TOKEN_ID, sol_usd);
+        pyth_price_account_info!(usdc, USDC_TOKEN_ID, usdc_usd);
+
+        // Lamports do not require price accounts
+        quote_base_commitment_fee(&any, &any, &governor, LAMPORTS_TOKEN_ID, LAMPORTS_PER_SOL)
+            .unwrap();
+        quote_base_commitment_fee(&sol, &usdc, &governor, USDC_TOKEN_ID, 1_000_000).unwrap();
+
+        // Invalid amount
+        assert_eq!(
+            quote_base_commitment_fee(&sol, &usdc, &governor, USDC_TOKEN_ID, usdc_token().min - 1),
+            Err(TokenError::InvalidAmount.into())
+        );
+
+        // Invalid sol_usd_price_account
+        assert_eq!(
+            quote_base_commitment_fee(&any, &usdc, &governor, USDC_TOKEN_ID, 1_000_000),
+            Err(TokenError::InvalidPriceAccount.into())
+        );
+
+        // Degraded oracle
+        governor.set_oracle_degraded(&true);
+        assert_eq!(
+            quote_base_commitment_fee(&sol, &usdc, &governor, USDC_TOKEN_ID, 1_000_000),
+            Err(ElusivError::OracleUnavailable.into())
+        );
+    }
+
+    #[test]
+    fn test_view_commitment() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(governor, GovernorAccount);
+        parent_account!(mut storage_account, StorageAccount);
+        parent_account!(mut commitment_index, CommitmentIndexAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        let batching_rate = 1;
+        let commitment_count = commitments_per_batch(batching_rate);
+        hashing_account.set_is_active(&true);
+        hashing_account.set_batching_rate(&batching_rate);
+        hashing_account.set_instruction(
+            &(commitment_hash_computation_instructions(batching_rate).len() as u32),
+        );
+        for i in 0..commitment_count {
+            hashing_account.set_hash_tree(i, &[i as u8 + 1; 32]);
+        }
+
+        assert_eq!(
+            view_commitment(&storage_account, 0),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+
+        for _ in 0..=batching_rate {
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            )
+            .unwrap();
+        }
+
+        for i in 0..commitment_count {
+            assert_eq!(
+                storage_account.get_commitment(i).unwrap(),
+                [i as u8 + 1; 32]
+            );
+            assert_eq!(view_commitment(&storage_account, i as u32), Ok(()));
+        }
+        assert_eq!(
+            view_commitment(&storage_account, commitment_count as u32),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+    }
+
+    #[test]
+    fn test_finalize_commitment_hash_commitment_index() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(governor, GovernorAccount);
+        parent_account!(mut storage_account, StorageAccount);
+        parent_account!(mut commitment_index, CommitmentIndexAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        let batching_rate = 2;
+        let ordering = 4;
+        let commitment_count = commitments_per_batch(batching_rate);
+        storage_account.set_next_commitment_ptr(&ordering);
+        storage_account.set_trees_count(&3);
+        hashing_account.set_is_active(&true);
+        hashing_account.set_batching_rate(&batching_rate);
+        hashing_account.set_ordering(&ordering);
+        hashing_account.set_instruction(
+            &(commitment_hash_computation_instructions(batching_rate).len() as u32),
+        );
+        for i in 0..commitment_count {
+            hashing_account.set_hash_tree(i, &[i as u8 + 1; 32]);
+        }
+
+        for _ in 0..=batching_rate {
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            )
+            .unwrap();
+        }
+
+        // Each inserted commitment is indexed at its leaf
+        let trees_count = storage_account.get_trees_count();
+        let next_commitment_ptr = storage_account.get_next_commitment_ptr();
+        for leaf_index in ordering..next_commitment_ptr {
+            let commitment = storage_account.get_commitment(leaf_index as usize).unwrap();
+            assert_eq!(
+                commitment_index.lookup(&commitment, trees_count, next_commitment_ptr),
+                Ok(Some(leaf_index))
+            );
+            assert_eq!(
+                lookup_commitment(&storage_account, &commitment_index, commitment),
+                Ok(())
+            );
+        }
+        assert_eq!(commitment_index.get_tree_index(), trees_count);
+        assert_eq!(commitment_index.get_unindexed_count(), 0);
+
+        assert_eq!(
+            commitment_index.lookup(&[0; 32], trees_count, next_commitment_ptr),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_finalize_commitment_hash_log_level() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        let mut roots = Vec::new();
+
+        // The log-level does not affect the MT update
+        for log_level in [LogLevel::Off, LogLevel::Verbose] {
+            zero_program_account!(mut governor, GovernorAccount);
+            governor.set_log_level(&ElusivOption::Some(log_level));
+            parent_account!(mut storage_account, StorageAccount);
+            parent_account!(mut commitment_index, CommitmentIndexAccount);
+            zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+            let batching_rate = 2;
+            hashing_account.set_is_active(&true);
+            hashing_account.set_batching_rate(&batching_rate);
+            hashing_account.set_instruction(
+                &(commitment_hash_computation_instructions(batching_rate).len() as u32),
+            );
+            for i in 0..commitments_per_batch(batching_rate) {
+                hashing_account.set_hash_tree(i, &[i as u8 + 1; 32]);
+            }
+
+            for _ in 0..=batching_rate {
+                finalize_commitment_hash(
+                    &mut hashing_account,
+                    &mut storage_account,
+                    &mut commitment_index,
+                    &governor,
+                    &mut fee_version_usage,
+                )
+                .unwrap();
+            }
+
+            assert!(!hashing_account.get_is_active());
+            roots.push((
+                storage_account.get_root().unwrap(),
+                storage_account.get_next_commitment_ptr(),
+            ));
+        }
+
+        assert_eq!(roots[0], roots[1]);
+    }
+
+    #[test]
+    fn test_commitment_inserted_events() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(governor, GovernorAccount);
+        parent_account!(mut storage_account, StorageAccount);
+        parent_account!(mut commitment_index, CommitmentIndexAccount);
+        zero_program_account!(mut hashing_account, CommitmentHashingAccount);
+
+        let batching_rate = 2;
+        let ordering = 4;
+        let commitment_count = commitments_per_batch(batching_rate);
+        storage_account.set_next_commitment_ptr(&ordering);
+        storage_account.set_trees_count(&3);
+        hashing_account.set_is_active(&true);
+        hashing_account.set_batching_rate(&batching_rate);
+        hashing_account.set_ordering(&ordering);
+        hashing_account.set_instruction(
+            &(commitment_hash_computation_instructions(batching_rate).len() as u32),
+        );
+        for i in 0..commitment_count {
+            hashing_account.set_hash_tree(i, &[i as u8 + 1; 32]);
+        }
+
+        for _ in 0..=batching_rate {
+            finalize_commitment_hash(
+                &mut hashing_account,
+                &mut storage_account,
+                &mut commitment_index,
+                &governor,
+                &mut fee_version_usage,
+            )
+            .unwrap();
+        }
+
+        let events = commitment_inserted_events(&hashing_account, &storage_account).unwrap();
+        assert_eq!(events.len(), commitment_count);
+
+        let root = storage_account.get_root().unwrap();
+        for (i, event) in events.iter().enumerate() {
+            let leaf_index = ordering as usize + i;
+            assert_eq!(
+                *event,
+                CommitmentInserted {
+                    leaf_index: leaf_index as u64,
+                    commitment: [i as u8 + 1; 32],
+                    new_root: root,
+                    tree_index: 3,
+                }
+            );
+
+            // The event matches the resulting MT
+            assert_eq!(
+                event.commitment,
+                storage_account
+                    .get_node(leaf_index, MT_HEIGHT as usize)
+                    .unwrap()
+            );
+
+            let serialized = event.try_to_vec().unwrap();
+            assert_eq!(
+                CommitmentInserted::try_from_slice(&serialized).unwrap(),
+                *event
+            );
+        }
+        assert_eq!(
+            storage_account.get_next_commitment_ptr() as usize,
+            ordering as usize + commitment_count
+        );
+    }
 }
@@ -1,11 +1,13 @@
 mod accounts;
 mod commitment;
 mod proof;
+mod report;
 mod utils;
 mod vkey;
 
 pub use accounts::*;
 pub use commitment::*;
 pub use proof::*;
+pub use report::*;
 pub use utils::{nop, program_token_account_address};
 pub use vkey::*;
@@ -1,4 +1,4 @@
-use super::utils::{DefaultInstructionsSysvar, InstructionsSysvar};
+use super::utils::{DefaultInstructionsSysvar, InstructionsSysvar, KeyOnlyAccount};
 use crate::buffer::RingBuffer;
 use crate::bytes::{usize_as_u32_safe, BorshSerDeSized, ElusivOption};
 use crate::error::ElusivError;
@@ -7,37 +7,50 @@ use crate::macros::{guard, pda_account, BorshSerDeSized, EnumVariantIndex};
 use crate::processor::utils::{
     close_account, create_associated_token_account, spl_token_account_rent,
     system_program_account_rent, transfer_lamports_from_pda_checked, transfer_token,
-    transfer_token_from_pda, verify_program_token_account,
+    transfer_token_from_pda, transfer_with_system_program,
+    verify_lamports_recipient_rent_exemption, verify_program_token_account, MATH_ERR,
 };
 use crate::processor::{enqueue_commitment, verify_recent_commitment_index, ZERO_COMMITMENT_RAW};
-use crate::proof::verifier::{prepare_public_inputs_instructions, verify_partial};
-use crate::proof::vkey::{MigrateUnaryVKey, SendQuadraVKey, VerifyingKey, VerifyingKeyInfo};
+use crate::proof::verifier::{
+    max_prepare_public_inputs_instructions, prepare_public_inputs_instructions, verify_partial,
+    VerificationStep, FINAL_EXPONENTIATION_IXS,
+};
+use crate::proof::vkey::{
+    MigrateUnaryVKey, SendQuadraVKey, SignalLayout, VerifyingKey, VerifyingKeyInfo,
+};
 use crate::state::commitment::{CommitmentBufferAccount, CommitmentQueue, CommitmentQueueAccount};
-use crate::state::governor::{FeeCollectorAccount, GovernorAccount, PoolAccount};
+use crate::state::fee::{FeeVersionUsageAccount, ProofFee};
+use crate::state::governor::{FeeCollectorAccount, GovernorAccount, LogEvent, PoolAccount};
 use crate::state::metadata::{MetadataQueue, MetadataQueueAccount};
+use crate::state::note::{EncryptedNote, NoteLog, NoteLogAccount, NoteLogEntry};
 use crate::state::nullifier::NullifierAccount;
 use crate::state::proof::{
-    NullifierDuplicateAccount, VerificationAccount, VerificationAccountData, VerificationState,
+    ExternalReference, IdentifierLockAccount, NullifierDuplicateAccount, VerificationAccount,
+    VerificationAccountData, VerificationFault, VerificationState, MAX_VERIFICATION_BOOSTS,
 };
 use crate::state::queue::{Queue, RingQueue};
-use crate::state::storage::{StorageAccount, MT_COMMITMENT_COUNT};
+use crate::state::storage::{StorageAccount, MAX_TREES, MT_COMMITMENT_COUNT};
 use crate::state::vkey::VKeyAccount;
+use crate::state::warden::{warden_reward_epoch, WardenRewardsAccount};
 use crate::token::{
     elusiv_token, verify_associated_token_account, verify_token_account, Lamports, Token,
-    TokenPrice,
+    TokenPrice, SPL_TOKEN_COUNT,
 };
 use crate::types::{
     generate_hashed_inputs, InputCommitment, JoinSplitPublicInputs, MigratePublicInputs, Proof,
     PublicInputs, RawU256, SendPublicInputs, JOIN_SPLIT_MAX_N_ARITY, U256,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
-use elusiv_types::ParentAccount;
+use elusiv_types::{PDAAccount, ParentAccount, SizedAccount, UnverifiedAccountInfo};
 use elusiv_utils::open_pda_account_with_associated_pubkey;
+use solana_program::clock::DEFAULT_MS_PER_SLOT;
 use solana_program::instruction::Instruction;
 use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use solana_program::system_instruction;
+use solana_program::sysvar::clock::Clock;
 use solana_program::sysvar::instructions;
+use solana_program::sysvar::Sysvar;
 use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult};
 use std::collections::HashSet;
 
@@ -68,6 +81,11 @@ impl ProofRequest {
         )
     }
 
+    /// The fee paid by the sender (in the join-split token)
+    pub fn fee(&self) -> u64 {
+        proof_request!(self, public_inputs, public_inputs.join_split_inputs().fee)
+    }
+
     /// The amount used to compute the fee
     pub fn proof_fee_amount(&self) -> u64 {
         match self {
@@ -82,11 +100,123 @@ impl ProofRequest {
             ProofRequest::Migrate(_) => MigrateUnaryVKey::VKEY_ID,
         }
     }
+
+    /// The [`SignalLayout`] of the public signals of the request
+    pub fn signal_layout(&self) -> SignalLayout {
+        match self {
+            ProofRequest::Send(_) => SendQuadraVKey::SIGNAL_LAYOUT,
+            ProofRequest::Migrate(_) => MigrateUnaryVKey::SIGNAL_LAYOUT,
+        }
+    }
 }
 
 /// We only allow two distinct MTs in a join-split (merges can be used to reduce the amount of MTs)
 pub const MAX_MT_COUNT: usize = 2;
 
+/// Position of a MT among the distinct MTs of a join-split
+///
+/// # Note
+///
+/// The slot of a MT determines the index of its root, its [`NullifierAccount`] and its [`GlobalTreeIndex`] in the join-split.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LocalTreeSlot(usize);
+
+impl LocalTreeSlot {
+    pub fn new(slot: usize) -> Option<Self> {
+        if slot < MAX_MT_COUNT {
+            Some(LocalTreeSlot(slot))
+        } else {
+            None
+        }
+    }
+
+    pub fn get<T: Copy>(&self, values: &[T; MAX_MT_COUNT]) -> T {
+        values[self.0]
+    }
+}
+
+/// A single step of [`finalize_verification_insert_nullifier`]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NullifierInsertionStep {
+    Insert {
+        slot: LocalTreeSlot,
+        nullifier_hash: U256,
+    },
+
+    /// Repeated until all moved nullifier-hashes of the MT have been inserted
+    MoveNullifierHashes { slot: LocalTreeSlot },
+}
+
+impl NullifierInsertionStep {
+    pub fn slot(&self) -> LocalTreeSlot {
+        match self {
+            NullifierInsertionStep::Insert { slot, .. } => *slot,
+            NullifierInsertionStep::MoveNullifierHashes { slot } => *slot,
+        }
+    }
+}
+
+/// The nullifier insertion steps of a join-split, indexed by the `instruction` of the [`VerificationAccount`]
+///
+/// # Note
+///
+/// The steps are ordered MT by MT, since only a single [`NullifierAccount`] is supplied per step.
+pub fn nullifier_insertion_steps(
+    public_inputs: &JoinSplitPublicInputs,
+) -> Vec<NullifierInsertionStep> {
+    let mut slots = Vec::with_capacity(public_inputs.input_commitments.len());
+    let mut roots_count = 0;
+    for input_commitment in &public_inputs.input_commitments {
+        match input_commitment.root {
+            Some(_) => {
+                slots.push(LocalTreeSlot(roots_count));
+                roots_count += 1;
+            }
+            None => slots.push(LocalTreeSlot(0)),
+        }
+    }
+
+    let mut steps = Vec::new();
+    for slot in (0..roots_count).map(LocalTreeSlot) {
+        for (i, input_commitment) in public_inputs.input_commitments.iter().enumerate() {
+            if slots[i] == slot {
+                steps.push(NullifierInsertionStep::Insert {
+                    slot,
+                    nullifier_hash: input_commitment.nullifier_hash.reduce(),
+                });
+            }
+        }
+        steps.push(NullifierInsertionStep::MoveNullifierHashes { slot });
+    }
+
+    steps
+}
+
+/// The tree index of the [`NullifierAccount`] required by the next [`finalize_verification_insert_nullifier`] call
+pub fn nullifier_insertion_tree_index(verification_account: &VerificationAccount) -> u32 {
+    let slot = match verification_account.get_request() {
+        Ok(ProofRequest::Send(public_inputs)) => {
+            nullifier_insertion_steps(&public_inputs.join_split)
+                .get(verification_account.get_instruction() as usize)
+                .map(|step| step.slot())
+        }
+        _ => None,
+    };
+
+    slot.unwrap_or(LocalTreeSlot(0))
+        .get(&verification_account.all_tree_indices())
+}
+
+/// Index of a MT in the [`StorageAccount`] history (the active MT has the index `trees_count`)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GlobalTreeIndex(pub u32);
+
+impl GlobalTreeIndex {
+    pub fn active(storage_account: &StorageAccount) -> Self {
+        GlobalTreeIndex(storage_account.get_trees_count())
+    }
+}
+
 /// The maximum [`PDAOffset`] for [`VerificationAccount`] for a single fee payer
 pub const RESERVED_VERIFICATION_ACCOUNT_IDS: u8 = 128;
 
@@ -99,19 +229,29 @@ pub fn init_verification<'a, 'b, 'c, 'd>(
     verification_account: &AccountInfo<'a>,
     vkey_account: &VKeyAccount,
     nullifier_duplicate_account: &AccountInfo<'a>,
-    _identifier_account: &AccountInfo,
+    raced_verification_account: &AccountInfo, // only required if `skip_nullifier_pda`
+    identifier_account: &AccountInfo,
+    identifier_lock_account: &AccountInfo<'a>,
     storage_account: &StorageAccount,
     commitment_buffer: &mut CommitmentBufferAccount,
     nullifier_account0: &NullifierAccount<'b, 'c, 'd>,
     nullifier_account1: &NullifierAccount<'b, 'c, 'd>,
+    clock: &AccountInfo,
+    governor: &GovernorAccount,
 
     verification_account_index: u8,
     vkey_id: u32,
     tree_indices: [u32; MAX_MT_COUNT],
     request: ProofRequest,
     skip_nullifier_pda: bool,
+    external_reference: ExternalReference,
+    lock_identifier: bool,
+    rent_beneficiary: ElusivOption<Pubkey>,
+    encrypted_note: ElusivOption<EncryptedNote>,
+    earliest_finalize_time: u64,
 ) -> ProgramResult {
     let raw_public_inputs = proof_request!(&request, public_inputs, public_inputs.public_signals());
+    let identifier_account = KeyOnlyAccount::new(identifier_account)?;
 
     // Verify that an immutable vkey is setup
     guard!(vkey_account.is_setup(), ElusivError::InvalidAccount);
@@ -122,13 +262,35 @@ pub fn init_verification<'a, 'b, 'c, 'd>(
         ElusivError::InvalidAccount
     );
 
+    // A closed `VerificationAccount` is drained of all lamports (and thereby purged), so an existing account is still in use
+    if verification_account.lamports() > 0 && *verification_account.owner == crate::id() {
+        pda_account!(existing_account, VerificationAccount, verification_account);
+        solana_program::msg!(
+            "Verification account index {} is in use (state: {:?})",
+            verification_account_index,
+            existing_account.get_state().ok()
+        );
+        return Err(ElusivError::VerificationIndexInUse.into());
+    }
+
+    // The proof's public inputs need to match the inputs the vkey was setup with
+    let public_inputs_count = vkey_account.get_public_inputs_count() as usize;
+    guard!(
+        raw_public_inputs.len() == public_inputs_count,
+        ElusivError::InvalidPublicInputs
+    );
+
     let instructions = prepare_public_inputs_instructions(
         &proof_request!(
             &request,
             public_inputs,
             public_inputs.public_signals_skip_mr()
         ),
-        vkey_account.get_public_inputs_count() as usize,
+        public_inputs_count,
+    );
+    guard!(
+        instructions.len() <= max_prepare_public_inputs_instructions(public_inputs_count),
+        ElusivError::InvalidPublicInputs
     );
 
     // TODO: reject zero-commitment nullifier
@@ -141,6 +303,11 @@ pub fn init_verification<'a, 'b, 'c, 'd>(
                 public_inputs.verify_additional_constraints(),
                 ElusivError::InvalidPublicInputs
             );
+            guard!(
+                public_inputs.join_split.amount
+                    <= governor.get_max_token_amount(public_inputs.join_split.token_id)?,
+                ElusivError::InvalidAmount
+            );
 
             &public_inputs.join_split
         }
@@ -154,9 +321,21 @@ pub fn init_verification<'a, 'b, 'c, 'd>(
         join_split,
         storage_account,
         [nullifier_account0, nullifier_account1],
-        &tree_indices,
+        &tree_indices.map(GlobalTreeIndex),
     )?;
 
+    // Encrypted notes are only accepted while the note log is enabled
+    guard!(
+        encrypted_note.option().is_none() || governor.get_note_log_enabled(),
+        ElusivError::FeatureNotAvailable
+    );
+
+    // Only sends bind a finalization delay (in their `hashed_inputs`)
+    guard!(
+        earliest_finalize_time == 0 || matches!(request, ProofRequest::Send(_)),
+        ElusivError::FeatureNotAvailable
+    );
+
     // Open [`NullifierDuplicateAccount`]
     // - this account is used to prevent two proof verifications (of the same nullifier-hashes) at the same time
     // - using `skip_nullifier_pda` a second verification can be initialized, for more details see OS-ELV-ADV-05
@@ -167,10 +346,36 @@ pub fn init_verification<'a, 'b, 'c, 'd>(
             ElusivError::InvalidAccount
         );
 
-        // TODO: add duplicate PDA verification
         if nullifier_duplicate_account.lamports() == 0 {
             return Err(ElusivError::InvalidInstructionData.into());
         }
+
+        // The raced verification has to be the initiating one, whose outcome still has to be uncertain
+        pda_account!(
+            mut nullifier_duplicate_account,
+            NullifierDuplicateAccount,
+            nullifier_duplicate_account
+        );
+        guard!(
+            nullifier_duplicate_account.get_initiator()
+                == ElusivOption::Some(*raced_verification_account.key),
+            ElusivError::InvalidAccount
+        );
+        {
+            pda_account!(
+                raced_verification_account,
+                VerificationAccount,
+                raced_verification_account
+            );
+            guard!(
+                raced_verification_account.get_is_verified() != ElusivOption::Some(true),
+                ElusivError::InvalidAccountState
+            );
+        }
+
+        let count = nullifier_duplicate_account.get_skip_verifications_count();
+        nullifier_duplicate_account
+            .set_skip_verifications_count(&count.checked_add(1).ok_or(MATH_ERR)?);
     } else {
         open_pda_account_with_associated_pubkey::<NullifierDuplicateAccount>(
             &crate::id(),
@@ -180,8 +385,41 @@ pub fn init_verification<'a, 'b, 'c, 'd>(
             None,
             None,
         )?;
+
+        pda_account!(
+            mut nullifier_duplicate_account,
+            NullifierDuplicateAccount,
+            nullifier_duplicate_account
+        );
+        nullifier_duplicate_account.set_initiator(&ElusivOption::Some(*verification_account.key));
     }
 
+    // Open [`IdentifierLockAccount`]
+    // - this account is used to prevent two send verifications with the same identifier at the same time
+    let locked_identifier = if lock_identifier {
+        guard!(
+            *identifier_account.key() != Pubkey::default(),
+            ElusivError::InvalidAccount
+        );
+        guard!(
+            identifier_lock_account.lamports() == 0,
+            ElusivError::DuplicateValue
+        );
+
+        open_pda_account_with_associated_pubkey::<IdentifierLockAccount>(
+            &crate::id(),
+            fee_payer,
+            identifier_lock_account,
+            identifier_account.key(),
+            None,
+            None,
+        )?;
+
+        identifier_account.key().to_bytes()
+    } else {
+        [0; 32]
+    };
+
     // Open `VerificationAccount`
     open_pda_account_with_associated_pubkey::<VerificationAccount>(
         &crate::id(),
@@ -203,13 +441,29 @@ pub fn init_verification<'a, 'b, 'c, 'd>(
 
     verification_account.setup(
         RawU256::new(fee_payer.key.to_bytes()),
+        rent_beneficiary
+            .option()
+            .map(|pubkey| RawU256::new(pubkey.to_bytes()))
+            .into(),
         skip_nullifier_pda,
         &raw_public_inputs,
         &instructions,
         vkey_id,
         request,
         tree_indices,
-    )
+        external_reference,
+    )?;
+    verification_account.set_creation_slot(&Clock::from_account_info(clock)?.slot);
+    verification_account.set_locked_identifier(&locked_identifier);
+    verification_account.set_encrypted_note(&encrypted_note);
+
+    // The identifier of a delayed send is bound by the `hashed_inputs` at the finalization
+    if earliest_finalize_time > 0 {
+        verification_account.set_earliest_finalize_time(&earliest_finalize_time);
+        verification_account.set_delay_identifier(&identifier_account.key().to_bytes());
+    }
+
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -227,14 +481,17 @@ pub fn init_verification_transfer_fee<'a>(
     token_usd_price_account: &AccountInfo,
 
     governor: &GovernorAccount,
+    commitment_hash_queue: &mut CommitmentQueueAccount,
     verification_account: &mut VerificationAccount,
     token_program: &AccountInfo<'a>,
     system_program: &AccountInfo<'a>,
+    fee_version_usage: &mut FeeVersionUsageAccount,
+    clock: &AccountInfo,
 
     _verification_account_index: u8,
 ) -> ProgramResult {
     guard!(
-        verification_account.get_state() == VerificationState::None,
+        verification_account.get_state()? == VerificationState::None,
         ElusivError::InvalidAccountState
     );
 
@@ -244,30 +501,68 @@ pub fn init_verification_transfer_fee<'a>(
         ElusivError::InvalidAccount
     );
 
-    let request = verification_account.get_request();
+    let request = verification_account.get_request()?;
     let join_split = proof_request!(&request, public_inputs, public_inputs.join_split_inputs());
+    governor.verify_oracle_available(join_split.token_id)?;
+
+    // The fee-version is checked against the slot at which the fee is actually transferred (not the creation slot)
+    let fee = governor
+        .accepted_program_fee(request.fee_version(), Clock::from_account_info(clock)?.slot)?;
 
+    // Retired fee-versions are refused (also during the grace window), the use ends with the insertion of the commitment or the failure
+    fee_version_usage.admit(request.fee_version())?;
+
+    let token_id = join_split.token_id;
+
+    // The fees are refunded to `fee_payer_token_account` in the finalization, so its mint is checked before anything is transferred
+    // TODO: switch fee_payer_token_account to associated-token-account
     guard!(
-        request.fee_version() == governor.get_fee_version(),
-        ElusivError::InvalidFeeVersion
+        verify_token_account(fee_payer_token_account, token_id)?,
+        ElusivError::InvalidAccount
     );
-    let token_id = join_split.token_id;
+
     let price = TokenPrice::new(sol_usd_price_account, token_usd_price_account, token_id)?;
     let min_batching_rate = governor.get_commitment_batching_rate();
-    let fee = governor.get_program_fee();
-    let subvention = fee.proof_subvention.into_token(&price, token_id)?;
     let input_preparation_tx_count =
         verification_account.get_prepare_inputs_instructions_count() as usize;
-    let proof_verification_fee = fee
-        .proof_verification_computation_fee(input_preparation_tx_count)
-        .into_token(&price, token_id)?;
-    let commitment_hash_fee = fee.commitment_hash_computation_fee(min_batching_rate);
-    let commitment_hash_fee_token = commitment_hash_fee.into_token(&price, token_id)?;
-    let network_fee = Token::new(token_id, fee.proof_network_fee.calc(join_split.amount));
+    let is_merge = proof_request!(&request, public_inputs, public_inputs.is_merge());
+    let mut proof_fee = fee.proof_fee(
+        input_preparation_tx_count,
+        min_batching_rate,
+        join_split.amount,
+        token_id,
+        is_merge,
+        &price,
+    )?;
+
+    // Dust withdrawals only receive a proportional subvention (prevents draining the fee collector)
+    proof_fee.cap_subvention(governor.get_proof_subvention_cap(), join_split.amount);
+    let ProofFee {
+        proof_verification_fee,
+        commitment_hash_fee,
+        commitment_hash_fee_token,
+        network_fee,
+        subvention,
+    } = proof_fee;
+
+    // The USD value of the amount is recorded for reporting (Lamports require the SOL price account, which is ignored while the oracle is degraded)
+    let amount_usd = if token_id == 0 {
+        if *sol_usd_price_account.key == elusiv_token(0)?.pyth_usd_price_key
+            && !governor.get_oracle_degraded()
+        {
+            let sol_price = TokenPrice::new_lamports_usd(sol_usd_price_account)?;
+            Some(sol_price.token_into_usd(Token::new(0, join_split.amount))?)
+        } else {
+            None
+        }
+    } else {
+        Some(price.token_into_usd(Token::new(token_id, join_split.amount))?)
+    };
 
-    let fee =
-        (((commitment_hash_fee_token + proof_verification_fee)? + network_fee)? - subvention)?;
-    guard!(join_split.fee >= fee.amount(), ElusivError::InvalidFee);
+    guard!(
+        join_split.fee >= proof_fee.total()?.amount(),
+        ElusivError::InvalidFee
+    );
 
     verify_program_token_account(pool, pool_account, token_id)?;
     verify_program_token_account(fee_collector, fee_collector_account, token_id)?;
@@ -275,7 +570,7 @@ pub fn init_verification_transfer_fee<'a>(
     let mut associated_token_account_rent = Lamports(0);
     let mut associated_token_account_rent_token = 0;
 
-    if let ProofRequest::Send(public_inputs) = request {
+    if let ProofRequest::Send(public_inputs) = &request {
         if public_inputs.recipient_is_associated_token_account && token_id == 0 {
             return Err(ElusivError::InvalidRecipient.into());
         }
@@ -325,15 +620,10 @@ pub fn init_verification_transfer_fee<'a>(
         None,
     )?;
 
-    // TODO: switch fee_payer_token_account to associated-token-account
-    guard!(
-        verify_token_account(fee_payer_token_account, token_id)?,
-        ElusivError::InvalidAccount
-    );
-
     verification_account.set_other_data(&VerificationAccountData {
         fee_payer: RawU256::new(fee_payer.key.to_bytes()),
         fee_payer_account: RawU256::new(fee_payer_token_account.key.to_bytes()),
+        rent_beneficiary: other_data.rent_beneficiary,
         recipient_wallet: ElusivOption::None,
         skip_nullifier_pda: other_data.skip_nullifier_pda,
         min_batching_rate,
@@ -344,9 +634,26 @@ pub fn init_verification_transfer_fee<'a>(
         commitment_hash_fee_token: commitment_hash_fee_token.amount(),
         proof_verification_fee: proof_verification_fee.amount(),
         associated_token_account_rent: associated_token_account_rent_token,
+        amount_usd: amount_usd.into(),
+        boost_count: 0,
+        boosts: Default::default(),
     });
 
-    verification_account.set_state(&VerificationState::FeeTransferred);
+    log_fee_breakdown(governor, &verification_account.get_other_data());
+
+    // Reserve a commitment queue slot, so that the commitment can always be enqueued at the finalization
+    CommitmentQueue::new(commitment_hash_queue)
+        .admit_pending_commitment(governor.get_commitment_queue_high_water_mark())?;
+
+    // Reserve the payout in `pool`, so that concurrent finalizations cannot use it
+    if token_id == 0 {
+        let reservation =
+            pending_lamport_reservation(&request, &verification_account.get_other_data())?;
+        pda_account!(mut pool, PoolAccount, pool);
+        pool.reserve_for_pending_verifications(reservation)?;
+    }
+
+    verification_account.transition_state(&VerificationState::FeeTransferred)?;
 
     Ok(())
 }
@@ -355,7 +662,7 @@ pub fn init_verification_transfer_fee<'a>(
 ///
 /// # Notes
 ///
-/// This instruction has to be called by the original `fee_payer`, that called [`init_verification`].
+/// This instruction has to be called by the current `fee_payer` (the one that called [`init_verification`], unless the verification has been transferred).
 ///
 /// Depending on the MT-count this has to be called in a different tx than the init-tx (-> require fee_payer signature).
 pub fn init_verification_proof(
@@ -366,7 +673,7 @@ pub fn init_verification_proof(
     proof: Proof,
 ) -> ProgramResult {
     guard!(
-        verification_account.get_state() == VerificationState::FeeTransferred,
+        verification_account.get_state()? == VerificationState::FeeTransferred,
         ElusivError::InvalidAccountState
     );
     guard!(
@@ -378,116 +685,487 @@ pub fn init_verification_proof(
         ElusivError::InvalidAccount
     );
 
+    // Last check of the input preparation schedule before the computation starts
+    // - an incomplete schedule would stall the input preparation
+    guard!(
+        verification_account.prepare_inputs_instructions_match_request()?,
+        ElusivError::CorruptedComputationSchedule
+    );
+
     verification_account.a.set(proof.a);
     verification_account.b.set(proof.b);
     verification_account.c.set(proof.c);
 
-    verification_account.set_state(&VerificationState::ProofSetup);
+    verification_account.transition_state(&VerificationState::ProofSetup)?;
 
     Ok(())
 }
 
-pub const COMPUTE_VERIFICATION_IX_COUNT: u16 = 7; // two compute-unit-instructions, five compute-instructions
-
-/// Partial proof verification computation
-pub fn compute_verification(
+/// Tops up the reward of an in-progress verification by `amount` Lamports
+///
+/// # Notes
+///
+/// The boost is paid to the `fee_payer` on a successful finalization and refunded to the `booster` for an invalid proof.
+pub fn boost_verification<'a>(
+    booster: &AccountInfo<'a>,
+    pool: &AccountInfo<'a>,
     verification_account: &mut VerificationAccount,
-    vkey_account: &VKeyAccount,
-    instructions_account: &AccountInfo,
+    system_program: &AccountInfo<'a>,
 
     _verification_account_index: u8,
-    vkey_id: u32,
+    amount: u64,
 ) -> ProgramResult {
-    // Verify that an immutable vkey is setup
-    guard!(vkey_account.is_setup(), ElusivError::InvalidAccount);
-
+    guard!(amount > 0, ElusivError::InvalidAmount);
     guard!(
-        verification_account.get_vkey_id() == vkey_id,
-        ElusivError::InvalidAccount
+        !matches!(
+            verification_account.get_state()?,
+            VerificationState::None | VerificationState::Closed
+        ),
+        ElusivError::InvalidAccountState
     );
-    guard!(
-        verification_account.get_is_verified().option().is_none(),
-        ElusivError::ComputationIsAlreadyFinished
+
+    let mut data = verification_account.get_other_data();
+    data.add_boost(RawU256::new(booster.key.to_bytes()), Lamports(amount))?;
+
+    // `booster` transfers `amount` to `pool` (lamports)
+    transfer_with_system_program(booster, pool, system_program, amount)?;
+
+    verification_account.set_other_data(&data);
+
+    Ok(())
+}
+
+/// Transfers an in-progress verification to `new_fee_payer`, who receives all rewards of its finalization
+///
+/// # Notes
+///
+/// Requires the signatures of both the current and the new `fee_payer` and is permitted in any state before [`VerificationState::Finalized`].
+/// If no explicit `rent_beneficiary` has been set, `new_fee_payer` reclaims the rent of the verification's accounts at the finalization.
+/// So `new_fee_payer` reimburses these rents (verification-account, nullifier-duplicate-PDA and identifier-lock) to the current `fee_payer`.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_verification_ownership<'a>(
+    fee_payer: &AccountInfo<'a>,
+    new_fee_payer: &AccountInfo<'a>,
+    new_fee_payer_account: &AccountInfo,
+    verification_account_info: &AccountInfo,
+    nullifier_duplicate_account: &AccountInfo,
+    identifier_lock_account: &AccountInfo,
+    system_program: &AccountInfo<'a>,
+
+    _verification_account_index: u8,
+) -> ProgramResult {
+    pda_account!(
+        mut verification_account,
+        VerificationAccount,
+        verification_account_info
     );
     guard!(
         matches!(
-            verification_account.get_state(),
-            VerificationState::None | VerificationState::ProofSetup
+            verification_account.get_state()?,
+            VerificationState::None
+                | VerificationState::FeeTransferred
+                | VerificationState::ProofSetup
+                | VerificationState::InsertNullifiers
         ),
         ElusivError::InvalidAccountState
     );
 
-    // instruction_index is used to allow a uniform number of ixs per tx
-    let instruction_index = if cfg!(test) {
-        COMPUTE_VERIFICATION_IX_COUNT - 1
-    } else {
-        instructions::load_current_index_checked(instructions_account)?
-    };
-
-    let result = vkey_account.execute_on_child_account_mut(0, |data| {
-        let vkey = VerifyingKey::new(data, vkey_account.get_public_inputs_count() as usize)
-            .ok_or(ElusivError::InvalidAccountState)?;
+    let mut data = verification_account.get_other_data();
+    guard!(
+        data.fee_payer.skip_mr() == fee_payer.key.to_bytes(),
+        ElusivError::InvalidAccount
+    );
+    guard!(
+        new_fee_payer.key != fee_payer.key,
+        ElusivError::InvalidAccount
+    );
 
-        verify_partial(verification_account, &vkey, instruction_index)
-    })?;
+    // The fees are refunded to `new_fee_payer_account` in the finalization
+    guard!(
+        verify_token_account(new_fee_payer_account, data.token_id)?,
+        ElusivError::InvalidAccount
+    );
 
-    match result {
-        Ok(result) => {
-            if let Some(final_result) = result {
-                // After last round we receive the verification result
-                verification_account.set_is_verified(&ElusivOption::Some(final_result));
-            }
+    // An explicit `rent_beneficiary` keeps the rent, independent of the owner
+    if data.rent_beneficiary.option().is_none() {
+        let mut rent = verification_account_info.lamports();
 
-            Ok(())
-        }
-        Err(e) => {
-            match e {
-                ElusivError::InvalidAccountState => Err(e.into()),
-                _ => {
-                    // An error (!= InvalidAccountState) can only happen with flawed inputs -> cancel verification
-                    verification_account.set_is_verified(&ElusivOption::Some(false));
-                    Ok(())
-                }
-            }
+        if !data.skip_nullifier_pda {
+            let request = verification_account.get_request()?;
+            let join_split =
+                proof_request!(&request, public_inputs, public_inputs.join_split_inputs());
+            guard!(
+                *nullifier_duplicate_account.key
+                    == join_split.create_nullifier_duplicate_pda(nullifier_duplicate_account)?,
+                ElusivError::InvalidAccount
+            );
+            rent += nullifier_duplicate_account.lamports();
         }
-    }
-}
 
-#[derive(BorshDeserialize, BorshSerialize, Clone, Default)]
-pub struct FinalizeSendData {
-    pub total_amount: u64,
-    pub token_id: u16,
+        let locked_identifier = verification_account.get_locked_identifier();
+        if locked_identifier != [0; 32] {
+            verify_identifier_lock(&locked_identifier, identifier_lock_account)?;
+            rent += identifier_lock_account.lamports();
+        }
 
-    /// Estimated index of the MT in which the next-commitment will be inserted
-    pub mt_index: u32,
+        // `new_fee_payer` reimburses the rent of all verification accounts to `fee_payer` (lamports)
+        transfer_with_system_program(new_fee_payer, fee_payer, system_program, rent)?;
+    }
 
-    /// Estimated index of the next-commitment in the MT
-    pub commitment_index: u32,
+    data.fee_payer = RawU256::new(new_fee_payer.key.to_bytes());
+    data.fee_payer_account = RawU256::new(new_fee_payer_account.key.to_bytes());
+    verification_account.set_other_data(&data);
 
-    pub iv: U256,
-    pub encrypted_owner: U256,
+    Ok(())
 }
 
-const SPL_MEMO_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
-    5, 74, 83, 90, 153, 41, 33, 6, 77, 36, 232, 113, 96, 218, 56, 124, 124, 53, 181, 221, 188, 146,
-    187, 129, 228, 31, 168, 64, 65, 5, 68, 141,
-]);
-
-/// First finalize instruction
+/// Fails a verification that has not been completed within `verification_ttl_slots`
 ///
 /// # Notes
 ///
-/// The complete transactions requires to include:
-/// - for a valid proof:
-///     [`finalize_verification_send`],
-///     [`finalize_verification_insert_nullifier`]+,
-///     [`finalize_verification_transfer_lamports`] or [`finalize_verification_transfer_token`].
-///
-/// - for an invalid proof:
-///     [`finalize_verification_send`],
-///     [`finalize_verification_transfer_lamports`] or [`finalize_verification_transfer_token`].
-#[allow(clippy::too_many_arguments)]
-pub fn finalize_verification_send(
+/// Permissionless, the failed verification is settled by [`finalize_verification_send`] and the transfer finalization.
+/// A verification without a submitted proof is moved to [`VerificationState::ProofSetup`] for this.
+pub fn expire_verification(
+    verification_account: &mut VerificationAccount,
+    governor: &GovernorAccount,
+    clock: &AccountInfo,
+
+    _verification_account_index: u8,
+) -> ProgramResult {
+    let ttl = governor.get_verification_ttl_slots();
+    guard!(ttl > 0, ElusivError::FeatureNotAvailable);
+
+    let state = verification_account.get_state()?;
+    guard!(
+        matches!(
+            state,
+            VerificationState::FeeTransferred | VerificationState::ProofSetup
+        ),
+        ElusivError::InvalidAccountState
+    );
+    guard!(
+        verification_account.get_is_verified().option().is_none(),
+        ElusivError::ComputationIsAlreadyFinished
+    );
+
+    let clock = Clock::from_account_info(clock)?;
+    let age = clock
+        .slot
+        .saturating_sub(verification_account.get_creation_slot());
+    guard!(age > ttl, ElusivError::ComputationInProgress);
+
+    // A delayed send cannot be finalized before `earliest_finalize_time`, so the warden is granted the TTL after it again
+    let earliest_finalize_time = verification_account.get_earliest_finalize_time();
+    if earliest_finalize_time > 0 {
+        let ttl_seconds = ttl.saturating_mul(DEFAULT_MS_PER_SLOT) / 1000;
+        guard!(
+            clock.unix_timestamp > earliest_finalize_time.saturating_add(ttl_seconds) as i64,
+            ElusivError::ComputationInProgress
+        );
+    }
+
+    // Completing the computation in time is the responsibility of the warden
+    verification_account.fail(VerificationFault::WardenFault);
+    verification_account.transition_state(&VerificationState::ProofSetup)?;
+
+    Ok(())
+}
+
+/// Fails a delayed send on behalf of its identifier before `earliest_finalize_time`
+///
+/// # Notes
+///
+/// Like [`expire_verification`], the cancelled verification is settled by the regular finalization.
+/// The cancellation is possible even after the proof has been verified, since it is meant to stop compromised proofs.
+pub fn cancel_delayed_send(
+    identifier: &AccountInfo,
+    verification_account: &mut VerificationAccount,
+    clock: &AccountInfo,
+
+    _verification_account_index: u8,
+) -> ProgramResult {
+    let earliest_finalize_time = verification_account.get_earliest_finalize_time();
+    guard!(earliest_finalize_time > 0, ElusivError::FeatureNotAvailable);
+    guard!(
+        identifier.key.to_bytes() == verification_account.get_delay_identifier(),
+        ElusivError::InvalidAccount
+    );
+
+    guard!(
+        matches!(
+            verification_account.get_state()?,
+            VerificationState::FeeTransferred | VerificationState::ProofSetup
+        ),
+        ElusivError::InvalidAccountState
+    );
+    guard!(
+        verification_account.get_is_verified() != ElusivOption::Some(false),
+        ElusivError::ComputationIsAlreadyFinished
+    );
+
+    let now = Clock::from_account_info(clock)?.unix_timestamp;
+    guard!(
+        now < earliest_finalize_time as i64,
+        ElusivError::CancellationWindowClosed
+    );
+
+    verification_account.fail(VerificationFault::Cancelled);
+    verification_account.transition_state(&VerificationState::ProofSetup)?;
+
+    Ok(())
+}
+
+/// Fails a verification with a [`VerificationState`] unknown to this program version (e.g. after a downgrade)
+///
+/// # Notes
+///
+/// Can only be called by the program authority and only after `verification_ttl_slots`.
+/// Like [`expire_verification`], the failed verification is settled by the regular finalization.
+pub fn force_settle_unknown_state(
+    authority: &AccountInfo,
+    verification_account: &mut VerificationAccount,
+    governor: &GovernorAccount,
+    clock: &AccountInfo,
+
+    _verification_account_index: u8,
+) -> ProgramResult {
+    guard!(*authority.key == crate::id(), ElusivError::InvalidAccount);
+
+    let ttl = governor.get_verification_ttl_slots();
+    guard!(ttl > 0, ElusivError::FeatureNotAvailable);
+
+    guard!(
+        verification_account.get_state() == Err(ElusivError::UnsupportedAccountState),
+        ElusivError::InvalidAccountState
+    );
+
+    // The settlement requires a known request
+    verification_account.get_request()?;
+
+    let age = Clock::from_account_info(clock)?
+        .slot
+        .saturating_sub(verification_account.get_creation_slot());
+    guard!(age > ttl, ElusivError::ComputationInProgress);
+
+    verification_account.fail(VerificationFault::Indeterminate);
+    // The unknown state is not part of the state-machine (see `VerificationState::can_transition_to`)
+    verification_account.set_state(&VerificationState::ProofSetup);
+
+    Ok(())
+}
+
+pub const COMPUTE_VERIFICATION_IX_COUNT: u16 = 7; // two compute-unit-instructions, five compute-instructions
+
+/// Lamports charged (from the signer) for a [`compute_verification`] call that does not advance the computation
+/// - wardens submit multiple calls per transaction, only the last one of which performs computations in some steps
+/// - all charges are refunded to the signer of the next call that advances the computation (usually in the same transaction)
+pub const COMPUTE_VERIFICATION_CALL_CHARGE: Lamports = Lamports(if cfg!(test) { 0 } else { 5_000 });
+
+/// Partial proof verification computation
+#[allow(clippy::too_many_arguments)]
+pub fn compute_verification<'a>(
+    warden: &AccountInfo<'a>,
+    verification_account: &mut VerificationAccount,
+    vkey_account: &VKeyAccount,
+    instructions_account: &AccountInfo,
+    pool: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+
+    _verification_account_index: u8,
+    vkey_id: u32,
+) -> ProgramResult {
+    // Verify that an immutable vkey is setup
+    guard!(vkey_account.is_setup(), ElusivError::InvalidAccount);
+
+    guard!(
+        verification_account.get_vkey_id() == vkey_id,
+        ElusivError::InvalidAccount
+    );
+    guard!(
+        verification_account.get_is_verified().option().is_none(),
+        ElusivError::ComputationIsAlreadyFinished
+    );
+    guard!(
+        !(matches!(
+            verification_account.get_step(),
+            VerificationStep::FinalExponentiation
+        ) && verification_account.get_instruction() as usize >= FINAL_EXPONENTIATION_IXS),
+        ElusivError::ComputationIsAlreadyFinished
+    );
+    guard!(
+        matches!(
+            verification_account.get_state()?,
+            VerificationState::None | VerificationState::ProofSetup
+        ),
+        ElusivError::InvalidAccountState
+    );
+
+    // instruction_index is used to allow a uniform number of ixs per tx
+    let instruction_index = if cfg!(test) {
+        COMPUTE_VERIFICATION_IX_COUNT - 1
+    } else {
+        instructions::load_current_index_checked(instructions_account)?
+    };
+
+    // Before the input preparation starts, the stored public inputs still have to match the request
+    if matches!(
+        verification_account.get_step(),
+        VerificationStep::PublicInputPreparation
+    ) && verification_account.get_instruction() == 0
+    {
+        // The input preparation can never take more instructions than the worst case of the vkey
+        guard!(
+            verification_account.get_prepare_inputs_instructions_count() as usize
+                <= max_prepare_public_inputs_instructions(
+                    vkey_account.get_public_inputs_count() as usize
+                ),
+            ElusivError::InputPreparationInstructionsExceeded
+        );
+
+        debug_assert!(
+            verification_account.public_inputs_match_request()?,
+            "Public inputs do not match the request"
+        );
+    }
+
+    let progress = computation_progress(verification_account);
+
+    let result = vkey_account.execute_on_child_account_mut(0, |data| {
+        let vkey = VerifyingKey::new(data, vkey_account.get_public_inputs_count() as usize)
+            .ok_or(ElusivError::InvalidAccountState)?;
+
+        verify_partial(verification_account, &vkey, instruction_index)
+    })?;
+
+    match result {
+        Ok(result) => {
+            if let Some(final_result) = result {
+                // After last round we receive the verification result
+                if final_result {
+                    verification_account.set_is_verified(&ElusivOption::Some(true));
+                } else {
+                    verification_account.fail(VerificationFault::UserFault);
+                }
+            }
+        }
+        Err(e) => {
+            match e {
+                ElusivError::InvalidAccountState
+                | ElusivError::UnsupportedAccountState
+                | ElusivError::ComputationIsAlreadyFinished => return Err(e.into()),
+                _ => {
+                    // Any other error can only happen with flawed inputs -> cancel verification
+                    verification_account.fail(VerificationFault::UserFault);
+                }
+            }
+        }
+    }
+
+    settle_compute_verification_call(
+        warden,
+        pool,
+        system_program,
+        verification_account,
+        COMPUTE_VERIFICATION_CALL_CHARGE,
+        computation_progress(verification_account) != progress,
+    )
+}
+
+/// The position of the computation (changed by every [`compute_verification`] call that advances the computation)
+fn computation_progress(verification_account: &VerificationAccount) -> (u8, u32, bool) {
+    (
+        verification_account.get_step() as u8,
+        verification_account.get_instruction(),
+        verification_account.get_is_verified().option().is_some(),
+    )
+}
+
+/// Charges `warden` for a [`compute_verification`] call that did not advance the computation
+/// or refunds all pending charges to `warden` if it did
+fn settle_compute_verification_call<'a>(
+    warden: &AccountInfo<'a>,
+    pool: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    verification_account: &mut VerificationAccount,
+    charge: Lamports,
+    advanced_computation: bool,
+) -> ProgramResult {
+    let pending_charges = verification_account.get_pending_call_charges();
+
+    if advanced_computation {
+        if pending_charges.0 > 0 {
+            verification_account.set_pending_call_charges(&Lamports(0));
+
+            // `pool` refunds all pending charges to `warden` (lamports)
+            transfer_lamports_from_pda_checked(pool, warden, pending_charges.0)?;
+        }
+    } else if charge.0 > 0 {
+        // `warden` transfers `charge` to `pool` (lamports)
+        transfer_with_system_program(warden, pool, system_program, charge.0)?;
+
+        verification_account.set_pending_call_charges(&Lamports(
+            pending_charges.0.checked_add(charge.0).ok_or(MATH_ERR)?,
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Default)]
+pub struct FinalizeSendData {
+    pub total_amount: u64,
+    pub token_id: u16,
+
+    /// Estimated index of the MT in which the next-commitment will be inserted
+    pub mt_index: u32,
+
+    /// Estimated index of the next-commitment in the MT
+    pub commitment_index: u32,
+
+    pub iv: U256,
+    pub encrypted_owner: U256,
+}
+
+impl FinalizeSendData {
+    /// Rejects values out of range for any proof, before they are matched against the proof
+    pub fn validate(&self) -> ProgramResult {
+        guard!(
+            self.token_id as usize <= SPL_TOKEN_COUNT,
+            ElusivError::UnsupportedToken
+        );
+        guard!(
+            self.mt_index < MAX_TREES,
+            ElusivError::InvalidInstructionData
+        );
+        guard!(
+            (self.commitment_index as usize) < MT_COMMITMENT_COUNT,
+            ElusivError::InvalidInstructionData
+        );
+
+        Ok(())
+    }
+}
+
+const SPL_MEMO_PROGRAM_ID: Pubkey = Pubkey::new_from_array([
+    5, 74, 83, 90, 153, 41, 33, 6, 77, 36, 232, 113, 96, 218, 56, 124, 124, 53, 181, 221, 188, 146,
+    187, 129, 228, 31, 168, 64, 65, 5, 68, 141,
+]);
+
+/// First finalize instruction
+///
+/// # Notes
+///
+/// The complete transactions requires to include:
+/// - for a valid proof:
+///     [`finalize_verification_send`],
+///     [`finalize_verification_insert_nullifier`]+,
+///     [`finalize_verification_transfer_lamports`] or [`finalize_verification_transfer_token`].
+///
+/// - for an invalid proof:
+///     [`finalize_verification_send`],
+///     [`finalize_verification_transfer_lamports`] or [`finalize_verification_transfer_token`].
+#[allow(clippy::too_many_arguments)]
+pub fn finalize_verification_send(
     recipient: &AccountInfo,
     identifier_account: &AccountInfo,
     transaction_reference: &AccountInfo, // if no reference is used, set this account to the same as `instructions_account`
@@ -496,17 +1174,26 @@ pub fn finalize_verification_send(
     storage_account: &StorageAccount,
     buffer: &mut CommitmentBufferAccount,
     instructions_account: &AccountInfo,
+    note_log: UnverifiedAccountInfo, // only required if the verification carries an encrypted note
+    clock: &AccountInfo,             // only required for a delayed send
 
     verification_account_index: u8,
     data: FinalizeSendData,
     uses_memo: bool,
 ) -> ProgramResult {
-    guard!(
-        verification_account.get_state() == VerificationState::ProofSetup,
-        ElusivError::InvalidAccountState
-    );
+    let identifier_account = KeyOnlyAccount::new(identifier_account)?;
+    let transaction_reference = KeyOnlyAccount::new(transaction_reference)?;
+    data.validate()?;
+
+    match verification_account.get_state()? {
+        VerificationState::ProofSetup => {}
+        VerificationState::None | VerificationState::FeeTransferred => {
+            return Err(ElusivError::ProofNotSubmitted.into())
+        }
+        _ => return Err(ElusivError::InvalidAccountState.into()),
+    }
 
-    let request = verification_account.get_request();
+    let request = verification_account.get_request()?;
     let public_inputs = match request {
         ProofRequest::Send(public_inputs) => public_inputs,
         _ => return Err(ElusivError::FeatureNotAvailable.into()),
@@ -523,13 +1210,14 @@ pub fn finalize_verification_send(
     };
 
     // Verify `hashed_inputs`
+    let encrypted_note = verification_account.get_encrypted_note().option();
     let hash = generate_hashed_inputs(
         &recipient.key.to_bytes(),
-        &identifier_account.key.to_bytes(),
+        &identifier_account.key().to_bytes(),
         &data.iv,
         &data.encrypted_owner,
-        &(if transaction_reference.key != instructions_account.key {
-            transaction_reference.key.to_bytes()
+        &(if transaction_reference.key() != instructions_account.key {
+            transaction_reference.key().to_bytes()
         } else {
             [0; 32]
         }),
@@ -537,21 +1225,37 @@ pub fn finalize_verification_send(
         &public_inputs.join_split.metadata,
         &public_inputs.join_split.optional_fee,
         &memo,
+        &encrypted_note,
+        verification_account.get_earliest_finalize_time(),
     );
     guard!(
         hash == public_inputs.hashed_inputs,
         ElusivError::InputsMismatch
     );
 
+    // The identifier allowed to cancel a delayed send has to be the identifier of the proof
+    let delay_identifier = verification_account.get_delay_identifier();
+    guard!(
+        delay_identifier == [0; 32] || delay_identifier == identifier_account.key().to_bytes(),
+        ElusivError::InvalidAccount
+    );
+
+    // A locked identifier has to be the identifier of the proof
+    let locked_identifier = verification_account.get_locked_identifier();
+    guard!(
+        locked_identifier == [0; 32] || locked_identifier == identifier_account.key().to_bytes(),
+        ElusivError::InvalidAccount
+    );
+
     // Set `recipient_wallet`
     verification_account.set_other_data(&mutate(&verification_account.get_other_data(), |data| {
         data.recipient_wallet = ElusivOption::Some(RawU256::new(recipient.key.to_bytes()))
     }));
 
     match verification_account.get_is_verified() {
-        ElusivOption::None => return Err(ElusivError::ComputationIsNotYetFinished.into()),
+        ElusivOption::None => return Err(ElusivError::ComputationInProgress.into()),
         ElusivOption::Some(false) => {
-            verification_account.set_state(&VerificationState::Finalized);
+            verification_account.transition_state(&VerificationState::Finalized)?;
 
             // Attempt to remove the commitment from the commitment-buffer
             if let Some(index) =
@@ -565,6 +1269,32 @@ pub fn finalize_verification_send(
         _ => {}
     }
 
+    // A valid delayed send can only be finalized once its cancellation window has passed
+    let earliest_finalize_time = verification_account.get_earliest_finalize_time();
+    if earliest_finalize_time > 0 {
+        guard!(
+            Clock::from_account_info(clock)?.unix_timestamp >= earliest_finalize_time as i64,
+            ElusivError::FinalizationDelayed
+        );
+    }
+
+    // Sends to the zero-address would burn the funds (merges use the zero-address as a dummy recipient)
+    guard!(
+        public_inputs.is_merge() || recipient.key.to_bytes() != [0; 32],
+        ElusivError::InvalidAccount
+    );
+
+    // Lamports sends must leave the recipient rent-exempt, since the transfer would be rejected by the runtime otherwise
+    if public_inputs.join_split.token_id == 0 {
+        verify_lamports_recipient_rent_exemption(
+            recipient,
+            public_inputs
+                .join_split
+                .amount
+                .saturating_sub(public_inputs.join_split.optional_fee.amount),
+        )?;
+    }
+
     enforce_finalize_send_instructions(
         instructions_account,
         public_inputs.join_split.token_id == 0,
@@ -590,7 +1320,23 @@ pub fn finalize_verification_send(
     );
     guard!(data.mt_index == mt_index, ElusivError::InputsMismatch);
 
-    verification_account.set_state(&VerificationState::InsertNullifiers);
+    // The validated `token_id` is used by the transfer step
+    verification_account.set_other_data(&mutate(&verification_account.get_other_data(), |d| {
+        d.token_id = data.token_id
+    }));
+
+    // The note is logged next to the commitment, which is enqueued by the transfer step
+    if let Some(encrypted_note) = encrypted_note {
+        let note_log = note_log.get_unsafe();
+        NoteLogAccount::verify_account(note_log, None)?;
+        pda_account!(mut note_log, NoteLogAccount, note_log);
+        NoteLog::new(&mut note_log).append(NoteLogEntry {
+            commitment: public_inputs.join_split.output_commitment.reduce(),
+            encrypted_note,
+        })?;
+    }
+
+    verification_account.transition_state(&VerificationState::InsertNullifiers)?;
     verification_account.set_instruction(&0);
 
     Ok(())
@@ -605,57 +1351,42 @@ pub fn finalize_verification_insert_nullifier(
     // TODO: Handle the case in which a duplicate verification has failed (funds flow to fee-collector)
 
     guard!(
-        verification_account.get_state() == VerificationState::InsertNullifiers,
+        verification_account.get_state()? == VerificationState::InsertNullifiers,
         ElusivError::InvalidAccountState
     );
 
-    let request = verification_account.get_request();
+    let request = verification_account.get_request()?;
     let public_inputs = match request {
         ProofRequest::Send(public_inputs) => public_inputs,
         _ => return Err(ElusivError::FeatureNotAvailable.into()),
     };
 
-    let input_commitment_index = verification_account.get_instruction() as usize;
-    if input_commitment_index < public_inputs.join_split.input_commitments.len() {
-        // Insert nullifier hashes
-        let mut tree_index = 0;
-        for (index, input_commitment) in public_inputs
-            .join_split
-            .input_commitments
-            .iter()
-            .enumerate()
-        {
-            let tree_index = match input_commitment.root {
-                Some(_) => {
-                    let t = tree_index;
-                    tree_index += 1;
-                    t
-                }
-                None => 0,
-            };
-
-            if tree_index != 0 {
-                // TODO: add support for arbitrary MTs
-                return Err(ElusivError::FeatureNotAvailable.into());
-            }
-
-            if index == input_commitment_index {
-                nullifier_account
-                    .try_insert_nullifier_hash(input_commitment.nullifier_hash.reduce())?;
-                break;
+    // Nullifier-hashes are inserted MT by MT, draining the moved nullifier-hashes of a MT before the next one
+    let steps = nullifier_insertion_steps(&public_inputs.join_split);
+    let mut step_index = verification_account.get_instruction() as usize;
+    match steps.get(step_index) {
+        Some(NullifierInsertionStep::Insert { nullifier_hash, .. }) => {
+            nullifier_account.try_insert_nullifier_hash(*nullifier_hash)?;
+            step_index += 1;
+        }
+        Some(NullifierInsertionStep::MoveNullifierHashes { .. }) => {
+            if !nullifier_account.is_moved_nullifier_empty() {
+                nullifier_account.move_nullifier_hashes_to_next_account()?;
             }
         }
+        None => return Err(ElusivError::InvalidAccountState.into()),
+    }
 
-        verification_account.set_instruction(&(input_commitment_index as u32 + 1));
-    } else if !nullifier_account.is_moved_nullifier_empty() {
-        // Insert moved nullifier hashes
-        nullifier_account.move_nullifier_hashes_to_next_account()?;
+    if let Some(NullifierInsertionStep::MoveNullifierHashes { .. }) = steps.get(step_index) {
+        if nullifier_account.is_moved_nullifier_empty() {
+            step_index += 1;
+        }
     }
 
-    if input_commitment_index >= public_inputs.join_split.input_commitments.len() - 1
-        && nullifier_account.is_moved_nullifier_empty()
-    {
-        verification_account.set_state(&VerificationState::Finalized);
+    verification_account.set_instruction(&(step_index as u32));
+
+    if step_index >= steps.len() {
+        verification_account.transition_state(&VerificationState::Finalized)?;
     }
 
     Ok(())
@@ -663,7 +1394,8 @@ pub fn finalize_verification_insert_nullifier(
 
 #[allow(clippy::too_many_arguments)]
 pub fn finalize_verification_transfer_lamports<'a>(
-    original_fee_payer: &AccountInfo<'a>,
+    fee_payer: &AccountInfo<'a>,
+    rent_beneficiary: &AccountInfo<'a>,
     recipient: &AccountInfo<'a>, // can be any account for merge/migrate
     pool: &AccountInfo<'a>,
     fee_collector: &AccountInfo<'a>,
@@ -672,7 +1404,14 @@ pub fn finalize_verification_transfer_lamports<'a>(
     metadata_queue: &mut MetadataQueueAccount,
     verification_account_info: &AccountInfo<'a>,
     nullifier_duplicate_account: &AccountInfo<'a>,
+    identifier_lock_account: &AccountInfo<'a>,
     instructions_account: &AccountInfo,
+    governor: &GovernorAccount,
+    warden_rewards: &AccountInfo,
+    clock: &AccountInfo,
+    booster0: &AccountInfo<'a>,
+    booster1: &AccountInfo<'a>,
+    fee_version_usage: &mut FeeVersionUsageAccount,
 
     _verification_account_index: u8,
 ) -> ProgramResult {
@@ -681,18 +1420,25 @@ pub fn finalize_verification_transfer_lamports<'a>(
         VerificationAccount,
         verification_account_info
     );
+
+    guard!(
+        verification_account.get_state()? == VerificationState::Finalized,
+        ElusivError::InvalidAccountState
+    );
+
     let data = verification_account.get_other_data();
-    let request = verification_account.get_request();
+    let request = verification_account.get_request()?;
     let join_split = proof_request!(&request, public_inputs, public_inputs.join_split_inputs());
+    let boosters = [booster0, booster1];
 
     guard!(join_split.token_id == 0, ElusivError::InvalidAccountState);
 
     guard!(
-        verification_account.get_state() == VerificationState::Finalized,
-        ElusivError::InvalidAccountState
+        fee_payer.key.to_bytes() == data.fee_payer.skip_mr(),
+        ElusivError::InvalidAccount
     );
     guard!(
-        original_fee_payer.key.to_bytes() == data.fee_payer.skip_mr(),
+        rent_beneficiary.key.to_bytes() == data.rent_beneficiary().skip_mr(),
         ElusivError::InvalidAccount
     );
     guard!(
@@ -700,22 +1446,57 @@ pub fn finalize_verification_transfer_lamports<'a>(
             == join_split.create_nullifier_duplicate_pda(nullifier_duplicate_account)?,
         ElusivError::InvalidAccount
     );
+    let locked_identifier = verification_account.get_locked_identifier();
+    verify_identifier_lock(&locked_identifier, identifier_lock_account)?;
+
+    // Both PDAs are closed by the finalization, so they must not receive the transfer
+    guard!(
+        recipient.key != nullifier_duplicate_account.key
+            && recipient.key != verification_account_info.key,
+        ElusivError::InvalidAccount
+    );
+
+    // Release the reserved payout (a valid proof's payout must not use the reservations of other verifications)
+    let is_valid = verification_account.get_is_verified() != ElusivOption::Some(false);
+    {
+        let reservation = pending_lamport_reservation(&request, &data)?;
+        let pool_lamports = pool.lamports();
+        pda_account!(mut pool, PoolAccount, pool);
+        pool.release_reservation(pool_lamports, reservation, is_valid)?;
+    }
 
     // Invalid proof
-    if let ElusivOption::Some(false) = verification_account.get_is_verified() {
-        // `rent` and `commitment_hash_fee` flow to `fee_collector`
-        close_account(fee_collector, verification_account_info)?;
-        if !data.skip_nullifier_pda {
-            close_account(fee_collector, nullifier_duplicate_account)?;
-        }
+    if !is_valid {
+        log_failure(governor, &verification_account);
+        let (rent_recipient, fee_recipient) = if is_cancelled(&verification_account) {
+            (rent_beneficiary, fee_payer)
+        } else {
+            (fee_collector, fee_collector)
+        };
 
-        verification_account.set_state(&VerificationState::Closed);
+        // `pool` refunds all boosts to their boosters (lamports)
+        refund_boosts(pool, &data, &boosters)?;
+
+        // `rent` flows to `rent_recipient`
+        close_account(rent_recipient, verification_account_info)?;
+        release_nullifier_duplicate_account(
+            rent_recipient,
+            nullifier_duplicate_account,
+            data.skip_nullifier_pda,
+        )?;
+        close_identifier_lock(rent_recipient, identifier_lock_account, &locked_identifier)?;
+
+        verification_account.transition_state(&VerificationState::Closed)?;
+
+        // The reserved commitment queue slot and the use of the fee-version end without a commitment
+        CommitmentQueue::new(commitment_hash_queue).release_pending_commitment();
+        fee_version_usage.release(join_split.fee_version, 1);
 
         // `pool` transfers `subvention` to `fee_collector` (lamports)
         transfer_lamports_from_pda_checked(pool, fee_collector, data.subvention)?;
 
-        // `pool` transfers `commitment_hash_fee` to `fee_collector` (lamports)
-        transfer_lamports_from_pda_checked(pool, fee_collector, data.commitment_hash_fee.0)?;
+        // `pool` transfers `commitment_hash_fee` to `fee_recipient` (lamports)
+        transfer_lamports_from_pda_checked(pool, fee_recipient, data.commitment_hash_fee.0)?;
 
         return Ok(());
     }
@@ -734,20 +1515,23 @@ pub fn finalize_verification_transfer_lamports<'a>(
                 .checked_sub(public_inputs.join_split.optional_fee.amount)
                 .ok_or(ElusivError::InvalidAmount)?;
 
+            // The recipient's balance might have changed since `finalize_verification_send`
+            verify_lamports_recipient_rent_exemption(recipient, amount)?;
+
             if public_inputs.solana_pay_transfer {
-                // `pool` transfers `amount` to `original_fee_payer` (lamports)
+                // `pool` transfers `amount` to `fee_payer` (lamports)
                 transfer_lamports_from_pda_checked(
                     pool,
-                    original_fee_payer,
+                    fee_payer,
                     public_inputs.join_split.amount,
                 )?;
 
-                // Last instruction: `original_fee_payer` transfers `amount` to `recipient`
+                // Last instruction: `fee_payer` transfers `amount` to `recipient`
                 let instructions_sysvar = DefaultInstructionsSysvar(instructions_account);
                 enforce_instruction(
                     &instructions_sysvar,
                     instructions_sysvar.find_instruction_count()? - 1,
-                    &system_instruction::transfer(original_fee_payer.key, recipient.key, amount),
+                    &system_instruction::transfer(fee_payer.key, recipient.key, amount),
                     false,
                 )?;
             } else {
@@ -771,23 +1555,34 @@ pub fn finalize_verification_transfer_lamports<'a>(
         }
     }
 
-    // `pool` transfers `commitment_hash_fee_token (incl. subvention) + proof_verification_fee` to `fee_payer` (lamports)
-    transfer_lamports_from_pda_checked(
-        pool,
-        original_fee_payer,
-        (Lamports(data.commitment_hash_fee_token) + Lamports(data.proof_verification_fee))?.0,
-    )?;
+    // `commitment_hash_fee_token (incl. subvention) + proof_verification_fee` is accrued for the current epoch
+    let warden_reward =
+        (Lamports(data.commitment_hash_fee_token) + Lamports(data.proof_verification_fee))?;
+    let is_accrued = accrue_warden_reward(governor, clock, warden_rewards, 0, warden_reward.0)?;
+
+    if !is_accrued {
+        // `pool` transfers `commitment_hash_fee_token (incl. subvention) + proof_verification_fee` to `fee_payer` (lamports)
+        transfer_lamports_from_pda_checked(pool, fee_payer, warden_reward.0)?;
+    }
 
     // `pool` transfers `network_fee` to `fee_collector` (lamports)
     transfer_lamports_from_pda_checked(pool, fee_collector, data.network_fee)?;
 
-    // Close `verification_account` and `nullifier_duplicate_account`
+    // `pool` transfers all boosts to `fee_payer` (lamports)
+    transfer_lamports_from_pda_checked(pool, fee_payer, data.total_boost()?.0)?;
+
+    // Close `verification_account` and `nullifier_duplicate_account` (rent flows to `rent_beneficiary`)
     close_verification_pdas(
-        original_fee_payer,
+        rent_beneficiary,
         verification_account_info,
         nullifier_duplicate_account,
         data.skip_nullifier_pda,
     )?;
+    close_identifier_lock(
+        rent_beneficiary,
+        identifier_lock_account,
+        &locked_identifier,
+    )?;
 
     let mut commitment_queue = CommitmentQueue::new(commitment_hash_queue);
     let mut metadata_queue = MetadataQueue::new(metadata_queue);
@@ -799,17 +1594,25 @@ pub fn finalize_verification_transfer_lamports<'a>(
         join_split.metadata,
         join_split.fee_version,
         data.min_batching_rate,
+        true,
     )?;
 
-    verification_account.set_state(&VerificationState::Closed);
+    log_finalize_event(
+        governor,
+        &data,
+        &verification_account.get_external_reference(),
+    );
+
+    verification_account.transition_state(&VerificationState::Closed)?;
 
     Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn finalize_verification_transfer_token<'a>(
-    original_fee_payer: &AccountInfo<'a>,
-    original_fee_payer_account: &AccountInfo<'a>,
+    fee_payer: &AccountInfo<'a>,
+    fee_payer_account: &AccountInfo<'a>,
+    rent_beneficiary: &AccountInfo<'a>,
     recipient: &AccountInfo<'a>, // can be any account for merge/migrate
     recipient_wallet: &AccountInfo<'a>,
     pool: &AccountInfo<'a>,
@@ -821,9 +1624,16 @@ pub fn finalize_verification_transfer_token<'a>(
     metadata_queue: &mut MetadataQueueAccount,
     verification_account_info: &AccountInfo<'a>,
     nullifier_duplicate_account: &AccountInfo<'a>,
+    identifier_lock_account: &AccountInfo<'a>,
     token_program: &AccountInfo<'a>,
     mint_account: &AccountInfo<'a>,
     instructions_account: &AccountInfo,
+    booster0: &AccountInfo<'a>,
+    booster1: &AccountInfo<'a>,
+    governor: &GovernorAccount,
+    warden_rewards: &AccountInfo,
+    clock: &AccountInfo,
+    fee_version_usage: &mut FeeVersionUsageAccount,
 
     _verification_account_index: u8,
 ) -> ProgramResult {
@@ -832,24 +1642,35 @@ pub fn finalize_verification_transfer_token<'a>(
         VerificationAccount,
         verification_account_info
     );
+
+    guard!(
+        verification_account.get_state()? == VerificationState::Finalized,
+        ElusivError::InvalidAccountState
+    );
+
     let data = verification_account.get_other_data();
-    let request = verification_account.get_request();
+    let request = verification_account.get_request()?;
     let join_split = proof_request!(&request, public_inputs, public_inputs.join_split_inputs());
     let recipient_address = data.recipient_wallet.option().unwrap().skip_mr();
+    let boosters = [booster0, booster1];
 
-    let token_id = join_split.token_id;
+    let token_id = data.token_id;
     guard!(token_id > 0, ElusivError::InvalidAccountState);
-
     guard!(
-        verification_account.get_state() == VerificationState::Finalized,
+        token_id == join_split.token_id,
         ElusivError::InvalidAccountState
     );
+
+    guard!(
+        fee_payer.key.to_bytes() == data.fee_payer.skip_mr(),
+        ElusivError::InvalidAccount
+    );
     guard!(
-        original_fee_payer.key.to_bytes() == data.fee_payer.skip_mr(),
+        rent_beneficiary.key.to_bytes() == data.rent_beneficiary().skip_mr(),
         ElusivError::InvalidAccount
     );
     guard!(
-        original_fee_payer_account.key.to_bytes() == data.fee_payer_account.skip_mr(),
+        fee_payer_account.key.to_bytes() == data.fee_payer_account.skip_mr(),
         ElusivError::InvalidAccount
     );
     guard!(
@@ -857,21 +1678,45 @@ pub fn finalize_verification_transfer_token<'a>(
             == join_split.create_nullifier_duplicate_pda(nullifier_duplicate_account)?,
         ElusivError::InvalidAccount
     );
+    let locked_identifier = verification_account.get_locked_identifier();
+    verify_identifier_lock(&locked_identifier, identifier_lock_account)?;
+
+    // Both PDAs are closed by the finalization, so they must not receive the transfer
+    guard!(
+        recipient.key != nullifier_duplicate_account.key
+            && recipient.key != verification_account_info.key,
+        ElusivError::InvalidAccount
+    );
 
     verify_program_token_account(pool, pool_account, token_id)?;
     verify_program_token_account(fee_collector, fee_collector_account, token_id)?;
 
     // Invalid proof
     if let ElusivOption::Some(false) = verification_account.get_is_verified() {
-        // rent flows to `fee_collector`
+        log_failure(governor, &verification_account);
+        let (rent_recipient, fee_recipient) = if is_cancelled(&verification_account) {
+            (rent_beneficiary, fee_payer)
+        } else {
+            (fee_collector, fee_collector)
+        };
+
+        // `pool` refunds all boosts to their boosters (lamports)
+        refund_boosts(pool, &data, &boosters)?;
+
+        // rent flows to `rent_recipient`
         close_verification_pdas(
-            fee_collector,
+            rent_recipient,
             verification_account_info,
             nullifier_duplicate_account,
             data.skip_nullifier_pda,
         )?;
+        close_identifier_lock(rent_recipient, identifier_lock_account, &locked_identifier)?;
 
-        verification_account.set_state(&VerificationState::Closed);
+        verification_account.transition_state(&VerificationState::Closed)?;
+
+        // The reserved commitment queue slot and the use of the fee-version end without a commitment
+        CommitmentQueue::new(commitment_hash_queue).release_pending_commitment();
+        fee_version_usage.release(join_split.fee_version, 1);
 
         // `pool` transfers `subvention` to `fee_collector` (token)
         transfer_token_from_pda::<PoolAccount>(
@@ -884,17 +1729,18 @@ pub fn finalize_verification_transfer_token<'a>(
             None,
         )?;
 
-        // `pool` transfers `commitment_hash_fee` and `associated_token_account_rent` to `fee_collector` (lamports)
+        // `pool` transfers `commitment_hash_fee` and the `associated_token_account_rent` escrow to `fee_recipient` (lamports)
         transfer_lamports_from_pda_checked(
             pool,
-            fee_collector,
-            (data.commitment_hash_fee + spl_token_account_rent()?)?.0,
+            fee_recipient,
+            (data.commitment_hash_fee + associated_token_account_escrow(&request)?)?.0,
         )?;
 
         return Ok(());
     }
 
     let mut associated_token_account_rent_token = None;
+    let mut associated_token_account_escrow = associated_token_account_escrow(&request)?;
     if let ProofRequest::Send(public_inputs) = &request {
         if public_inputs.join_split.amount > 0 {
             let mut actual_recipient = recipient;
@@ -928,9 +1774,17 @@ pub fn finalize_verification_transfer_token<'a>(
                         ElusivError::InvalidAccount
                     );
 
-                    // We use signer (since it's an available system account) to sign the creation of the associated token account (refunded at the end)
+                    // `pool` funds the associated token account with the escrowed rent (lamports)
+                    // - the associated-token-program only transfers the missing rent from `fee_payer` (none)
+                    transfer_lamports_from_pda_checked(
+                        pool,
+                        recipient,
+                        associated_token_account_escrow.0,
+                    )?;
+                    associated_token_account_escrow = Lamports(0);
+
                     create_associated_token_account(
-                        original_fee_payer,
+                        fee_payer,
                         recipient_wallet,
                         recipient,
                         mint_account,
@@ -960,28 +1814,28 @@ pub fn finalize_verification_transfer_token<'a>(
             );
 
             if public_inputs.solana_pay_transfer {
-                // `pool` transfers `amount` to `original_fee_payer_account` (token)
+                // `pool` transfers `amount` to `fee_payer_account` (token)
                 transfer_token_from_pda::<PoolAccount>(
                     pool,
                     pool_account,
-                    original_fee_payer_account,
+                    fee_payer_account,
                     token_program,
                     token,
                     None,
                     None,
                 )?;
 
-                // Last instruction: `original_fee_payer_account` transfers `amount` to `recipient` (token)
+                // Last instruction: `fee_payer_account` transfers `amount` to `recipient` (token)
                 let instructions_sysvar = DefaultInstructionsSysvar(instructions_account);
                 enforce_instruction(
                     &instructions_sysvar,
                     instructions_sysvar.find_instruction_count()? - 1,
                     &spl_token::instruction::transfer(
                         token_program.key,
-                        original_fee_payer_account.key,
+                        fee_payer_account.key,
                         actual_recipient.key,
-                        original_fee_payer.key,
-                        &[original_fee_payer.key],
+                        fee_payer.key,
+                        &[fee_payer.key],
                         token.amount(),
                     )?,
                     false,
@@ -1019,15 +1873,26 @@ pub fn finalize_verification_transfer_token<'a>(
         }
     }
 
-    // `pool` transfers `commitment_hash_fee_token (incl. subvention) + proof_verification_fee + associated_token_account_rent_token?` to `fee_payer` (token)
+    // `commitment_hash_fee_token (incl. subvention) + proof_verification_fee` is accrued for the current epoch
+    let mut fee_payer_token = (Token::new(token_id, data.commitment_hash_fee_token)
+        + Token::new(token_id, data.proof_verification_fee))?;
+    if accrue_warden_reward(
+        governor,
+        clock,
+        warden_rewards,
+        token_id,
+        fee_payer_token.amount(),
+    )? {
+        fee_payer_token = Token::new(token_id, 0);
+    }
+
+    // `pool` transfers `commitment_hash_fee_token (incl. subvention) + proof_verification_fee (if not accrued) + associated_token_account_rent_token?` to `fee_payer` (token)
     transfer_token_from_pda::<PoolAccount>(
         pool,
         pool_account,
-        original_fee_payer_account,
+        fee_payer_account,
         token_program,
-        ((Token::new(token_id, data.commitment_hash_fee_token)
-            + Token::new(token_id, data.proof_verification_fee))?
-            + Token::new(token_id, associated_token_account_rent_token.unwrap_or(0)))?,
+        (fee_payer_token + Token::new(token_id, associated_token_account_rent_token.unwrap_or(0)))?,
         None,
         None,
     )?;
@@ -1043,16 +1908,25 @@ pub fn finalize_verification_transfer_token<'a>(
         None,
     )?;
 
-    // Close `verification_account` and `nullifier_duplicate_account`
+    // `pool` transfers all boosts to `fee_payer` (lamports)
+    transfer_lamports_from_pda_checked(pool, fee_payer, data.total_boost()?.0)?;
+
+    // Close `verification_account` and `nullifier_duplicate_account` (rent flows to `rent_beneficiary`)
     close_verification_pdas(
-        original_fee_payer,
+        rent_beneficiary,
         verification_account_info,
         nullifier_duplicate_account,
         data.skip_nullifier_pda,
     )?;
+    close_identifier_lock(
+        rent_beneficiary,
+        identifier_lock_account,
+        &locked_identifier,
+    )?;
 
-    if associated_token_account_rent_token.is_some() {
-        transfer_lamports_from_pda_checked(pool, original_fee_payer, spl_token_account_rent()?.0)?;
+    // An unused escrow is returned to `fee_payer` (lamports)
+    if associated_token_account_escrow.0 > 0 {
+        transfer_lamports_from_pda_checked(pool, fee_payer, associated_token_account_escrow.0)?;
     }
 
     let mut commitment_queue = CommitmentQueue::new(commitment_hash_queue);
@@ -1065,13 +1939,225 @@ pub fn finalize_verification_transfer_token<'a>(
         join_split.metadata,
         join_split.fee_version,
         data.min_batching_rate,
+        true,
     )?;
 
-    verification_account.set_state(&VerificationState::Closed);
+    log_finalize_event(
+        governor,
+        &data,
+        &verification_account.get_external_reference(),
+    );
+
+    verification_account.transition_state(&VerificationState::Closed)?;
+
+    Ok(())
+}
+
+/// Whether a failed verification has been cancelled by its identifier (see [`cancel_delayed_send`])
+///
+/// # Notes
+///
+/// A failed verification never collects fees from the user and its warden is never rewarded.
+/// Apart from that, only a cancellation changes the settlement: it is not caused by the warden,
+/// so the rent and the fees advanced by the `fee_payer` are refunded instead of flowing to the `fee_collector`.
+fn is_cancelled(verification_account: &VerificationAccount) -> bool {
+    verification_account.get_fault() == ElusivOption::Some(VerificationFault::Cancelled)
+}
+
+/// Logs the [`VerificationFault`] of a failed verification
+fn log_failure(governor: &GovernorAccount, verification_account: &VerificationAccount) {
+    if governor.logs(LogEvent::VerificationFailure) {
+        solana_program::msg!(
+            "Verification failed: {:?}",
+            verification_account.get_fault().option()
+        );
+    }
+}
+
+/// Emits the finalization event of a verification
+fn log_finalize_event(
+    governor: &GovernorAccount,
+    data: &VerificationAccountData,
+    external_reference: &ExternalReference,
+) {
+    if governor.logs(LogEvent::Finalize) {
+        let fields = finalize_event_fields(data, external_reference);
+        let fields: Vec<&[u8]> = fields.iter().map(|f| &f[..]).collect();
+        solana_program::log::sol_log_data(&fields);
+    }
+}
+
+/// Emits the fee breakdown of a verification
+fn log_fee_breakdown(governor: &GovernorAccount, data: &VerificationAccountData) {
+    if governor.logs(LogEvent::FeeBreakdown) {
+        let fields = fee_breakdown_event_fields(data);
+        let fields: Vec<&[u8]> = fields.iter().map(|f| &f[..]).collect();
+        solana_program::log::sol_log_data(&fields);
+    }
+}
+
+/// Fields of the fee breakdown event
+/// - all fees in the verification's token (except for the Lamports `commitment_hash_fee`)
+fn fee_breakdown_event_fields(data: &VerificationAccountData) -> Vec<Vec<u8>> {
+    vec![
+        b"fee_breakdown".to_vec(),
+        data.token_id.to_le_bytes().to_vec(),
+        data.proof_verification_fee.to_le_bytes().to_vec(),
+        data.commitment_hash_fee.0.to_le_bytes().to_vec(),
+        data.commitment_hash_fee_token.to_le_bytes().to_vec(),
+        data.network_fee.to_le_bytes().to_vec(),
+        data.subvention.to_le_bytes().to_vec(),
+    ]
+}
+
+/// Fields of the finalization event
+/// - the external reference
+/// - the recorded USD value of the amount (if available)
+fn finalize_event_fields(
+    data: &VerificationAccountData,
+    external_reference: &ExternalReference,
+) -> Vec<Vec<u8>> {
+    let mut fields = vec![b"finalize".to_vec(), external_reference.to_vec()];
+    if let ElusivOption::Some(amount_usd) = data.amount_usd {
+        fields.push(amount_usd.to_le_bytes().to_vec());
+    }
+    fields
+}
+
+/// Transfers the rewards of `token_id` that `warden` accrued in all completed epochs
+///
+/// # Notes
+///
+/// If epoch rewards have been disabled, all accrued rewards can be claimed.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_epoch_rewards<'a>(
+    warden: &AccountInfo<'a>,
+    warden_account: &AccountInfo<'a>,
+    pool: &AccountInfo<'a>,
+    pool_account: &AccountInfo<'a>,
+    governor: &GovernorAccount,
+    warden_rewards: &mut WardenRewardsAccount,
+    clock: &AccountInfo,
+    token_program: &AccountInfo<'a>,
+
+    token_id: u16,
+) -> ProgramResult {
+    verify_program_token_account(pool, pool_account, token_id)?;
+    if token_id == 0 {
+        guard!(
+            warden_account.key == warden.key,
+            ElusivError::InvalidAccount
+        );
+    } else {
+        guard!(
+            verify_token_account(warden_account, token_id)?,
+            ElusivError::InvalidAccount
+        );
+    }
+
+    let epoch = current_warden_reward_epoch(governor, clock)?.unwrap_or(u64::MAX);
+    let amount = warden_rewards.claim(token_id, epoch)?;
+
+    // `pool` transfers the rewards to `warden_account` (token)
+    transfer_token_from_pda::<PoolAccount>(
+        pool,
+        pool_account,
+        warden_account,
+        token_program,
+        Token::new(token_id, amount),
+        None,
+        None,
+    )
+}
+
+/// Accrues `amount` of `token_id` in the [`WardenRewardsAccount`] of a warden for the current epoch
+/// - returns false if epoch rewards are disabled or the warden has not opened its [`WardenRewardsAccount`]
+fn accrue_warden_reward(
+    governor: &GovernorAccount,
+    clock: &AccountInfo,
+    warden_rewards: &AccountInfo,
+    token_id: u16,
+    amount: u64,
+) -> Result<bool, ProgramError> {
+    let epoch = match current_warden_reward_epoch(governor, clock)? {
+        Some(epoch) => epoch,
+        None => return Ok(false),
+    };
+
+    if *warden_rewards.owner != crate::id()
+        || warden_rewards.data_len() != WardenRewardsAccount::SIZE
+    {
+        return Ok(false);
+    }
+
+    pda_account!(mut warden_rewards, WardenRewardsAccount, warden_rewards);
+    warden_rewards.accrue(token_id, epoch, amount)?;
+
+    Ok(true)
+}
+
+/// The current warden-reward epoch (`None` if epoch rewards are disabled)
+fn current_warden_reward_epoch(
+    governor: &GovernorAccount,
+    clock: &AccountInfo,
+) -> Result<Option<u64>, ProgramError> {
+    let epoch_length = governor.get_warden_rotation_epoch();
+    if epoch_length == 0 {
+        return Ok(None);
+    }
+
+    let slot = Clock::from_account_info(clock)?.slot;
+    Ok(warden_reward_epoch(slot, epoch_length))
+}
+
+/// Refunds each recorded boost from `pool` to its booster
+fn refund_boosts<'a>(
+    pool: &AccountInfo<'a>,
+    data: &VerificationAccountData,
+    boosters: &[&AccountInfo<'a>; MAX_VERIFICATION_BOOSTS],
+) -> ProgramResult {
+    for (boost, booster) in data.active_boosts().iter().zip(boosters) {
+        guard!(
+            booster.key.to_bytes() == boost.booster.skip_mr(),
+            ElusivError::InvalidAccount
+        );
+    }
+
+    for (boost, booster) in data.active_boosts().iter().zip(boosters) {
+        transfer_lamports_from_pda_checked(pool, booster, boost.amount.0)?;
+    }
 
     Ok(())
 }
 
+/// The Lamports reserved in `pool` for the payout of a Lamports proof verification
+fn pending_lamport_reservation(
+    request: &ProofRequest,
+    data: &VerificationAccountData,
+) -> Result<u64, ProgramError> {
+    let amount = match request {
+        ProofRequest::Send(public_inputs) => public_inputs.join_split.amount,
+        ProofRequest::Migrate(_) => 0,
+    };
+
+    data.commitment_hash_fee_token
+        .checked_add(data.proof_verification_fee)
+        .and_then(|fee| fee.checked_add(amount))
+        .ok_or_else(|| ElusivError::InvalidAmount.into())
+}
+
+/// The Lamports escrowed in `pool` by [`init_verification_transfer_fee`] for renting the recipient's associated-token-account
+fn associated_token_account_escrow(request: &ProofRequest) -> Result<Lamports, ProgramError> {
+    match request {
+        ProofRequest::Send(public_inputs)
+            if public_inputs.recipient_is_associated_token_account =>
+        {
+            spl_token_account_rent()
+        }
+        _ => Ok(Lamports(0)),
+    }
+}
+
 fn close_verification_pdas<'a>(
     beneficiary: &AccountInfo<'a>,
     verification_account: &AccountInfo<'a>,
@@ -1079,16 +2165,92 @@ fn close_verification_pdas<'a>(
     skipped_nullifier_pda: bool,
 ) -> ProgramResult {
     close_account(beneficiary, verification_account)?;
-    if !skipped_nullifier_pda {
+    release_nullifier_duplicate_account(
+        beneficiary,
+        nullifier_duplicate_account,
+        skipped_nullifier_pda,
+    )
+}
+
+/// Detaches a closed verification from its [`NullifierDuplicateAccount`], which is closed once no verification is attached anymore
+fn release_nullifier_duplicate_account<'a>(
+    beneficiary: &AccountInfo<'a>,
+    nullifier_duplicate_account: &AccountInfo<'a>,
+    skipped_nullifier_pda: bool,
+) -> ProgramResult {
+    // Accounts opened before the attachment-bookkeeping are only closed by their initiating verification
+    if nullifier_duplicate_account.data_len() != NullifierDuplicateAccount::SIZE {
+        if !skipped_nullifier_pda {
+            close_account(beneficiary, nullifier_duplicate_account)?;
+        }
+        return Ok(());
+    }
+
+    let is_attached = {
+        pda_account!(
+            mut nullifier_duplicate_account,
+            NullifierDuplicateAccount,
+            nullifier_duplicate_account
+        );
+
+        if skipped_nullifier_pda {
+            let count = nullifier_duplicate_account.get_skip_verifications_count();
+            nullifier_duplicate_account.set_skip_verifications_count(&count.saturating_sub(1));
+        } else {
+            nullifier_duplicate_account.set_initiator(&ElusivOption::None);
+        }
+
+        nullifier_duplicate_account
+            .get_initiator()
+            .option()
+            .is_some()
+            || nullifier_duplicate_account.get_skip_verifications_count() > 0
+    };
+
+    if !is_attached {
         close_account(beneficiary, nullifier_duplicate_account)?;
     }
 
     Ok(())
 }
 
-const TIMESTAMP_BITS_PRUNING: usize = 5;
+/// Verifies the [`IdentifierLockAccount`] of a verification that locked its identifier
+fn verify_identifier_lock(
+    locked_identifier: &U256,
+    identifier_lock_account: &AccountInfo,
+) -> ProgramResult {
+    if *locked_identifier != [0; 32] {
+        guard!(
+            *identifier_lock_account.key
+                == IdentifierLockAccount::create_with_pubkey(
+                    Pubkey::new_from_array(*locked_identifier),
+                    None,
+                    IdentifierLockAccount::get_bump(identifier_lock_account)?,
+                )?,
+            ElusivError::InvalidAccount
+        );
+    }
+
+    Ok(())
+}
+
+fn close_identifier_lock<'a>(
+    beneficiary: &AccountInfo<'a>,
+    identifier_lock_account: &AccountInfo<'a>,
+    locked_identifier: &U256,
+) -> ProgramResult {
+    if *locked_identifier != [0; 32] {
+        close_account(beneficiary, identifier_lock_account)?;
+    }
+
+    Ok(())
+}
+
+/// Maximum number of seconds an asserted time can lie in the future (covers clock drift and pruned timestamps)
+pub const MAX_FUTURE_TOLERANCE_SECONDS: u64 = 120;
+
 pub fn is_timestamp_valid(asserted_time: u64, timestamp: u64) -> bool {
-    (asserted_time >> TIMESTAMP_BITS_PRUNING) <= (timestamp >> TIMESTAMP_BITS_PRUNING)
+    asserted_time <= timestamp.saturating_add(MAX_FUTURE_TOLERANCE_SECONDS)
 }
 
 fn is_vec_duplicate_free<T: std::cmp::Eq + std::hash::Hash + std::clone::Clone>(
@@ -1113,7 +2275,7 @@ fn check_join_split_public_inputs(
     public_inputs: &JoinSplitPublicInputs,
     storage_account: &StorageAccount,
     nullifier_accounts: [&NullifierAccount; MAX_MT_COUNT],
-    tree_indices: &[u32; MAX_MT_COUNT],
+    tree_indices: &[GlobalTreeIndex; MAX_MT_COUNT],
 ) -> ProgramResult {
     // Check that the resulting commitment is not the zero-commitment
     guard!(
@@ -1139,10 +2301,10 @@ fn check_join_split_public_inputs(
         ElusivError::InvalidAmount
     );
 
-    let active_tree_index = storage_account.get_trees_count();
+    let active_tree_index = GlobalTreeIndex::active(storage_account);
 
     let mut roots = Vec::new();
-    let mut tree_index = Vec::with_capacity(public_inputs.input_commitments.len());
+    let mut slots = Vec::with_capacity(public_inputs.input_commitments.len());
     let mut nullifier_hashes = Vec::new();
     for InputCommitment {
         root,
@@ -1151,14 +2313,15 @@ fn check_join_split_public_inputs(
     {
         match root {
             Some(root) => {
-                let index = roots.len();
-                tree_index.push(index);
+                let slot =
+                    LocalTreeSlot::new(roots.len()).ok_or(ElusivError::InvalidPublicInputs)?;
+                slots.push(slot);
                 roots.push(root);
                 nullifier_hashes.push(vec![nullifier_hash]);
 
                 // Verify that root is valid
                 // Note: roots are stored in mr-form
-                if tree_indices[index] == active_tree_index {
+                if slot.get(tree_indices) == active_tree_index {
                     // Active tree
                     guard!(
                         storage_account.is_root_valid(&root.reduce()),
@@ -1167,13 +2330,13 @@ fn check_join_split_public_inputs(
                 } else {
                     // Closed tree
                     guard!(
-                        root.reduce() == nullifier_accounts[index].get_root(),
+                        root.reduce() == slot.get(&nullifier_accounts).get_root(),
                         ElusivError::InvalidMerkleRoot
                     );
                 }
             }
             None => {
-                tree_index.push(0);
+                slots.push(LocalTreeSlot(0));
                 nullifier_hashes[0].push(nullifier_hash);
             }
         }
@@ -1204,17 +2367,15 @@ fn check_join_split_public_inputs(
 
             if input_commitment.nullifier_hash == public_inputs.input_commitments[j].nullifier_hash
             {
-                guard!(
-                    tree_index[i] != tree_index[j],
-                    ElusivError::InvalidPublicInputs
-                );
+                guard!(slots[i] != slots[j], ElusivError::InvalidPublicInputs);
             }
         }
 
         // Check that `nullifier_hash` is new
         // Note: nullifier-hashes are stored in mr-form
         guard!(
-            nullifier_accounts[tree_index[i]]
+            slots[i]
+                .get(&nullifier_accounts)
                 .can_insert_nullifier_hash(input_commitment.nullifier_hash.reduce())?,
             ElusivError::CouldNotInsertNullifier
         );
@@ -1432,20 +2593,25 @@ mod tests {
     use super::*;
     use crate::fields::{u256_from_str, u256_from_str_skip_mr};
     use crate::macros::{
-        account_info, parent_account, program_token_account_info, pyth_price_account_info,
-        test_account_info, test_pda_account_info, two_pow, zero_program_account,
+        account_info, clock_account_info, parent_account, program_token_account_info,
+        pyth_price_account_info, test_account_info, test_pda_account_info, two_pow,
+        zero_program_account,
     };
     use crate::processor::{CommitmentHashRequest, ZERO_COMMITMENT_RAW};
-    use crate::proof::verifier::{
-        proof_from_str, COMBINED_MILLER_LOOP_IXS, FINAL_EXPONENTIATION_IXS,
-    };
+    use crate::proof::test_proofs::valid_proofs;
+    use crate::proof::verifier::{proof_from_str, COMBINED_MILLER_LOOP_IXS};
+    use crate::proof::vkey::TestVKey;
     use crate::state::commitment::COMMITMENT_BUFFER_LEN;
-    use crate::state::fee::ProgramFee;
+    use crate::state::fee::{BasisPointFee, ProgramFee};
     use crate::state::governor::PoolAccount;
+    use crate::state::governor::ScheduledFeeUpdate;
     use crate::state::metadata::CommitmentMetadata;
+    use crate::state::note::ENCRYPTED_NOTE_SIZE;
     use crate::state::nullifier::NullifierChildAccount;
     use crate::state::program_account::{PDAAccount, SizedAccount};
+    use crate::state::proof::VerificationBoost;
     use crate::state::storage::empty_root_raw;
+    use crate::state::warden::WardenRewardEntry;
     use crate::token::{spl_token_account_data, LAMPORTS_TOKEN_ID, USDC_TOKEN_ID, USDT_TOKEN_ID};
     use crate::types::{
         compute_fee_rec, compute_fee_rec_lamports, OptionalFee, Proof, RawU256,
@@ -1455,6 +2621,7 @@ mod tests {
     use elusiv_types::{ProgramAccount, TokenError};
     use solana_program::native_token::LAMPORTS_PER_SOL;
     use solana_program::pubkey::Pubkey;
+    use solana_program::rent::Rent;
     use solana_program::system_program;
 
     fn fee() -> ProgramFee {
@@ -1468,13 +2635,17 @@ mod tests {
         parent_account!(storage, StorageAccount);
         parent_account!(mut nullifier, NullifierAccount);
         zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 42);
+        zero_program_account!(governor, GovernorAccount);
         test_account_info!(fee_payer, 0);
         test_account_info!(identifier, 0);
+        test_account_info!(identifier_lock, 0);
         account_info!(
             v_acc,
             VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
             vec![0; VerificationAccount::SIZE]
         );
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
 
         let mut inputs = SendPublicInputs {
             join_split: JoinSplitPublicInputs {
@@ -1500,16 +2671,14 @@ mod tests {
         account_info!(
             n_duplicate_acc,
             inputs.join_split.nullifier_duplicate_pda().0,
-            vec![1]
+            vec![0; NullifierDuplicateAccount::SIZE]
         );
 
         let vkey_id = SendQuadraVKey::VKEY_ID;
-        let mut data = vec![0; VKeyAccount::SIZE];
-        let mut vkey = VKeyAccount::new(&mut data).unwrap();
+        let mut vkey = VKeyAccount::zeroed();
         vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
         vkey.set_version(&1);
 
-        // TODO: test skip nullifier pda
         // TODO: wrong vkey-id
         // TODO: vkey not checked
 
@@ -1520,16 +2689,25 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 RESERVED_VERIFICATION_ACCOUNT_IDS + 1,
                 vkey_id,
                 [0, 1],
                 Send(inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidAccount.into())
         );
@@ -1541,11 +2719,15 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
@@ -1553,6 +2735,11 @@ mod tests {
                     v.join_split.input_commitments.clear();
                 })),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidPublicInputs.into())
         );
@@ -1564,11 +2751,15 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
@@ -1577,6 +2768,11 @@ mod tests {
                         Some(RawU256::new(u256_from_str_skip_mr("1")));
                 })),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidMerkleRoot.into())
         );
@@ -1588,11 +2784,15 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
@@ -1600,6 +2800,11 @@ mod tests {
                     v.join_split.input_commitments[0].root = None;
                 })),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidPublicInputs.into())
         );
@@ -1611,11 +2816,15 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
@@ -1624,6 +2833,11 @@ mod tests {
                     compute_fee_rec_lamports::<SendQuadraVKey, _>(inputs, &fee());
                 })),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidRecentCommitmentIndex.into())
         );
@@ -1635,16 +2849,25 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [1, 0],
                 Send(inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidMerkleRoot.into())
         );
@@ -1656,11 +2879,15 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
@@ -1668,6 +2895,11 @@ mod tests {
                     v.join_split.output_commitment = RawU256::new(ZERO_COMMITMENT_RAW);
                 })),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidPublicInputs.into())
         );
@@ -1686,16 +2918,25 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
                 Send(inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::CouldNotInsertNullifier.into())
         );
@@ -1713,16 +2954,25 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &invalid_n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
                 Send(inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ProgramError::InvalidSeeds)
         );
@@ -1734,16 +2984,25 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &invalid_n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
                 Send(inputs.clone()),
                 true,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::InvalidAccount.into())
         );
@@ -1760,11 +3019,15 @@ mod tests {
                 &v_acc,
                 &migrate_vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 MigrateUnaryVKey::VKEY_ID,
                 [0, 1],
@@ -1774,6 +3037,11 @@ mod tests {
                     next_nsmt_root: RawU256::new([0; 32]),
                 }),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Err(ElusivError::FeatureNotAvailable.into())
         );
@@ -1784,29 +3052,46 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
                 Send(inputs.clone()),
                 false,
+                [42; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Ok(())
         );
 
-        let mut inputs = inputs.clone();
-        inputs.join_split.input_commitments[0].nullifier_hash =
+        {
+            let data = &mut v_acc.data.borrow_mut()[..];
+            let v_acc = VerificationAccount::new(data).unwrap();
+            assert_eq!(v_acc.get_external_reference(), [42; 16]);
+            assert_eq!(v_acc.get_creation_slot(), 42);
+            assert_eq!(v_acc.get_locked_identifier(), [0; 32]);
+        }
+
+        let mut inputs = inputs.clone();
+        inputs.join_split.input_commitments[0].nullifier_hash =
             RawU256::new(u256_from_str_skip_mr("2"));
         compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
 
         account_info!(
             n_duplicate_acc,
             inputs.join_split.nullifier_duplicate_pda().0,
-            vec![1]
+            vec![0; NullifierDuplicateAccount::SIZE]
         );
 
         // Duplicate commitment insertion will fail and only succeed after COMMITMENT_BUFFER_LEN insertions
@@ -1817,16 +3102,25 @@ mod tests {
                     &v_acc,
                     &vkey,
                     &n_duplicate_acc,
+                    &v_acc,
                     &identifier,
+                    &identifier_lock,
                     &storage,
                     &mut buffer,
                     &nullifier,
                     &nullifier,
+                    &clock,
+                    &governor,
                     0,
                     vkey_id,
                     [0, 1],
                     Send(inputs.clone()),
                     false,
+                    [0; 16],
+                    false,
+                    ElusivOption::None,
+                    ElusivOption::None,
+                    0,
                 ),
                 Err(ElusivError::DuplicateValue.into())
             );
@@ -1840,34 +3134,45 @@ mod tests {
                 &v_acc,
                 &vkey,
                 &n_duplicate_acc,
+                &v_acc,
                 &identifier,
+                &identifier_lock,
                 &storage,
                 &mut buffer,
                 &nullifier,
                 &nullifier,
+                &clock,
+                &governor,
                 0,
                 vkey_id,
                 [0, 1],
                 Send(inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
             ),
             Ok(())
         );
     }
 
     #[test]
-    #[should_panic]
-    fn test_init_verification_commitment_count_too_high() {
+    fn test_init_verification_skip_nullifier_pda() -> ProgramResult {
         parent_account!(storage, StorageAccount);
         parent_account!(nullifier, NullifierAccount);
         zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 42);
+        zero_program_account!(governor, GovernorAccount);
         test_account_info!(fee_payer, 0);
         test_account_info!(identifier, 0);
-        account_info!(
-            v_acc,
-            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
-            vec![0; VerificationAccount::SIZE]
-        );
+        test_account_info!(identifier_lock, 0);
+
+        let vkey_id = SendQuadraVKey::VKEY_ID;
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_version(&1);
 
         let mut inputs = SendPublicInputs {
             join_split: JoinSplitPublicInputs {
@@ -1876,7 +3181,7 @@ mod tests {
                     nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
                 }],
                 output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
-                recent_commitment_index: 123,
+                recent_commitment_index: 0,
                 fee_version: 0,
                 amount: LAMPORTS_PER_SOL,
                 fee: 0,
@@ -1890,52 +3195,120 @@ mod tests {
         };
         compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
 
-        account_info!(
-            n_duplicate_acc,
-            inputs.join_split.nullifier_duplicate_pda().0,
-            vec![1]
-        );
+        let (n_duplicate_pda, bump) = inputs.join_split.nullifier_duplicate_pda();
+        let mut n_duplicate_data = vec![0; NullifierDuplicateAccount::SIZE];
+        n_duplicate_data[0] = bump;
+        account_info!(n_duplicate_acc, n_duplicate_pda, n_duplicate_data);
+
+        macro_rules! v_acc {
+            ($id: ident, $index: literal) => {
+                account_info!(
+                    $id,
+                    VerificationAccount::find_with_pubkey(*fee_payer.key, Some($index)).0,
+                    vec![0; VerificationAccount::SIZE]
+                );
+                **$id.try_borrow_mut_lamports().unwrap() = 0;
+            };
+        }
+        v_acc!(v_acc0, 0);
+        v_acc!(v_acc1, 1);
+        v_acc!(v_acc2, 2);
 
-        let mut data = vec![0; VKeyAccount::SIZE];
-        let mut vkey = VKeyAccount::new(&mut data).unwrap();
-        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
-        vkey.set_is_frozen(&true);
+        macro_rules! init {
+            ($v_acc: ident, $index: literal, $raced: ident, $skip: literal, $output_commitment: literal) => {{
+                let mut inputs = inputs.clone();
+                inputs.join_split.output_commitment =
+                    RawU256::new(u256_from_str_skip_mr($output_commitment));
 
-        for i in inputs.join_split.input_commitments.len()..JOIN_SPLIT_MAX_N_ARITY + 1 {
-            inputs.join_split.input_commitments.push(InputCommitment {
-                root: None,
-                nullifier_hash: RawU256::new(u256_from_str_skip_mr(&i.to_string())),
-            });
+                init_verification(
+                    &fee_payer,
+                    &$v_acc,
+                    &vkey,
+                    &n_duplicate_acc,
+                    &$raced,
+                    &identifier,
+                    &identifier_lock,
+                    &storage,
+                    &mut buffer,
+                    &nullifier,
+                    &nullifier,
+                    &clock,
+                    &governor,
+                    $index,
+                    vkey_id,
+                    [0, 1],
+                    ProofRequest::Send(inputs),
+                    $skip,
+                    [0; 16],
+                    false,
+                    ElusivOption::None,
+                    ElusivOption::None,
+                    0,
+                )
+            }};
         }
 
-        let _ = init_verification(
-            &fee_payer,
-            &v_acc,
-            &vkey,
-            &n_duplicate_acc,
-            &identifier,
-            &storage,
-            &mut buffer,
-            &nullifier,
-            &nullifier,
-            0,
-            0,
-            [0, 1],
-            ProofRequest::Send(inputs),
-            false,
+        assert_eq!(init!(v_acc0, 0, v_acc0, false, "1"), Ok(()));
+        {
+            pda_account!(n_duplicate_acc, NullifierDuplicateAccount, n_duplicate_acc);
+            assert_eq!(
+                n_duplicate_acc.get_initiator(),
+                ElusivOption::Some(*v_acc0.key)
+            );
+            assert_eq!(n_duplicate_acc.get_skip_verifications_count(), 0);
+        }
+
+        // The raced verification has to be the initiator
+        assert_eq!(
+            init!(v_acc1, 1, v_acc2, true, "2"),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Racing the initiator is possible as long as its computation is not successful
+        assert_eq!(init!(v_acc1, 1, v_acc0, true, "2"), Ok(()));
+        {
+            pda_account!(n_duplicate_acc, NullifierDuplicateAccount, n_duplicate_acc);
+            assert_eq!(n_duplicate_acc.get_skip_verifications_count(), 1);
+        }
+
+        {
+            pda_account!(mut v_acc0, VerificationAccount, v_acc0);
+            v_acc0.set_is_verified(&ElusivOption::Some(true));
+        }
+        assert_eq!(
+            init!(v_acc2, 2, v_acc0, true, "3"),
+            Err(ElusivError::InvalidAccountState.into())
         );
+
+        {
+            pda_account!(mut v_acc0, VerificationAccount, v_acc0);
+            v_acc0.set_is_verified(&ElusivOption::Some(false));
+        }
+        assert_eq!(init!(v_acc2, 2, v_acc0, true, "3"), Ok(()));
+        {
+            pda_account!(n_duplicate_acc, NullifierDuplicateAccount, n_duplicate_acc);
+            assert_eq!(n_duplicate_acc.get_skip_verifications_count(), 2);
+        }
+
+        Ok(())
     }
 
     #[test]
-    fn test_init_verification_transfer_fee_lamports() {
+    fn test_init_verification_non_canonical_nullifier_duplicate_pda() {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 42);
+        zero_program_account!(governor, GovernorAccount);
         test_account_info!(fee_payer, 0);
-        test_account_info!(pool, 0);
-        test_account_info!(fee_collector, 0);
+        test_account_info!(identifier, 0);
+        test_account_info!(identifier_lock, 0);
         test_account_info!(any, 0);
-        account_info!(sys, system_program::id());
-        account_info!(spl, spl_token::id());
-        zero_program_account!(mut governor, GovernorAccount);
-        governor.set_program_fee(&fee());
+
+        let vkey_id = SendQuadraVKey::VKEY_ID;
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_version(&1);
 
         let mut inputs = SendPublicInputs {
             join_split: JoinSplitPublicInputs {
@@ -1944,7 +3317,7 @@ mod tests {
                     nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
                 }],
                 output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
-                recent_commitment_index: 123,
+                recent_commitment_index: 0,
                 fee_version: 0,
                 amount: LAMPORTS_PER_SOL,
                 fee: 0,
@@ -1952,242 +3325,182 @@ mod tests {
                 token_id: 0,
                 metadata: CommitmentMetadata::default(),
             },
-            recipient_is_associated_token_account: false,
+            recipient_is_associated_token_account: true,
             hashed_inputs: u256_from_str_skip_mr("1"),
             solana_pay_transfer: false,
         };
         compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
-        let instructions = prepare_public_inputs_instructions(
-            &inputs.public_signals_skip_mr(),
-            SendQuadraVKey::public_inputs_count(),
-        );
-
-        zero_program_account!(mut verification_acc, VerificationAccount);
-        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
-        verification_acc.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
-        verification_acc.set_other_data(&VerificationAccountData {
-            fee_payer: RawU256::new(fee_payer.key.to_bytes()),
-            ..Default::default()
-        });
 
-        // TODO: Associated token-account with lamports is invalid
+        // Any bump below the canonical one that still results in a valid PDA
+        let (_, canonical_bump) = inputs.join_split.nullifier_duplicate_pda();
+        let pubkey = inputs
+            .join_split
+            .associated_nullifier_duplicate_pda_pubkey();
+        let non_canonical_pda = (0..canonical_bump)
+            .rev()
+            .find_map(|bump| NullifierDuplicateAccount::create_with_pubkey(pubkey, None, bump).ok())
+            .unwrap();
 
-        // Invalid fee_payer
-        test_account_info!(fee_payer2, 0);
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer2,
-                &fee_payer,
-                &pool,
-                &pool,
-                &fee_collector,
-                &fee_collector,
-                &any,
-                &any,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
-                0,
-            ),
-            Err(ElusivError::InvalidAccount.into())
+        account_info!(
+            n_duplicate_acc,
+            non_canonical_pda,
+            vec![0; NullifierDuplicateAccount::SIZE]
         );
-
-        // Invalid verification account state
-        verification_acc.set_state(&VerificationState::FeeTransferred);
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &fee_payer,
-                &pool,
-                &pool,
-                &fee_collector,
-                &fee_collector,
-                &any,
-                &any,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
-                0,
-            ),
-            Err(ElusivError::InvalidAccountState.into())
+        account_info!(
+            v_acc,
+            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+            vec![0; VerificationAccount::SIZE]
         );
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
 
-        // Invalid fee_version
-        verification_acc.set_state(&VerificationState::None);
-        governor.set_fee_version(&1);
+        // A second lock for the same nullifier-hashes would circumvent the nullifier duplicate PDA
         assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
+            init_verification(
                 &fee_payer,
-                &pool,
-                &pool,
-                &fee_collector,
-                &fee_collector,
-                &any,
+                &v_acc,
+                &vkey,
+                &n_duplicate_acc,
                 &any,
+                &identifier,
+                &identifier_lock,
+                &storage,
+                &mut buffer,
+                &nullifier,
+                &nullifier,
+                &clock,
                 &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
                 0,
-            ),
-            Err(ElusivError::InvalidFeeVersion.into())
-        );
-
-        // Invalid fee (fee too low, since too high is allowed)
-        governor.set_fee_version(&0);
-        inputs.join_split.fee -= 1;
-        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &fee_payer,
-                &pool,
-                &pool,
-                &fee_collector,
-                &fee_collector,
-                &any,
-                &any,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
+                vkey_id,
+                [0, 1],
+                ProofRequest::Send(inputs),
+                false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
                 0,
             ),
-            Err(ElusivError::InvalidFee.into())
+            Err(ProgramError::InvalidSeeds)
         );
+    }
 
-        // Invalid system_program
-        inputs.join_split.fee = 0;
-        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
-        verification_acc.set_request(&ProofRequest::Send(inputs));
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &fee_payer,
-                &pool,
-                &pool,
-                &fee_collector,
-                &fee_collector,
-                &any,
-                &any,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &spl,
-                0,
-            ),
-            Err(ProgramError::IncorrectProgramId)
+    #[test]
+    fn test_init_verification_encrypted_note() -> ProgramResult {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 42);
+        zero_program_account!(mut governor, GovernorAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(identifier, 0);
+        test_account_info!(identifier_lock, 0);
+        account_info!(
+            v_acc,
+            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+            vec![0; VerificationAccount::SIZE]
         );
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
 
-        // Invalid pool_account
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &fee_payer,
-                &pool,
-                &any,
-                &fee_collector,
-                &fee_collector,
-                &any,
-                &any,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
-                0,
-            ),
-            Err(ElusivError::InvalidAccount.into())
-        );
+        let vkey_id = SendQuadraVKey::VKEY_ID;
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_version(&1);
 
-        // Invalid fee_collector_account
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &fee_payer,
-                &pool,
-                &pool,
-                &fee_collector,
-                &any,
-                &any,
-                &any,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
-                0,
-            ),
-            Err(ElusivError::InvalidAccount.into())
-        );
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 0,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: true,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
 
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &fee_payer,
-                &pool,
-                &pool,
-                &fee_collector,
-                &fee_collector,
-                &any,
-                &any,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
-                0,
-            ),
-            Ok(())
+        account_info!(
+            n_duplicate_acc,
+            inputs.join_split.nullifier_duplicate_pda().0,
+            vec![0; NullifierDuplicateAccount::SIZE]
         );
 
+        let encrypted_note = ElusivOption::Some([1; ENCRYPTED_NOTE_SIZE]);
+        macro_rules! init {
+            ($governor: ident) => {
+                init_verification(
+                    &fee_payer,
+                    &v_acc,
+                    &vkey,
+                    &n_duplicate_acc,
+                    &v_acc,
+                    &identifier,
+                    &identifier_lock,
+                    &storage,
+                    &mut buffer,
+                    &nullifier,
+                    &nullifier,
+                    &clock,
+                    &$governor,
+                    0,
+                    vkey_id,
+                    [0, 1],
+                    ProofRequest::Send(inputs.clone()),
+                    false,
+                    [0; 16],
+                    false,
+                    ElusivOption::None,
+                    encrypted_note,
+                    0,
+                )
+            };
+        }
+
+        // Note log is disabled
         assert_eq!(
-            verification_acc.get_state(),
-            VerificationState::FeeTransferred
+            init!(governor),
+            Err(ElusivError::FeatureNotAvailable.into())
         );
+
+        governor.set_note_log_enabled(&true);
+        assert_eq!(init!(governor), Ok(()));
+
+        pda_account!(v_acc, VerificationAccount, v_acc);
+        assert_eq!(v_acc.get_encrypted_note(), encrypted_note);
+
+        Ok(())
     }
 
     #[test]
-    fn test_init_verification_transfer_fee_token() {
+    fn test_init_verification_delayed_send() -> ProgramResult {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 42);
+        zero_program_account!(governor, GovernorAccount);
         test_account_info!(fee_payer, 0);
-        account_info!(sys, system_program::id());
-        account_info!(spl, spl_token::id());
-        zero_program_account!(mut governor, GovernorAccount);
-        governor.set_program_fee(&fee());
-
-        account_info!(
-            token_acc,
-            Pubkey::new_unique(),
-            spl_token_account_data(USDC_TOKEN_ID),
-            spl_token::id(),
-            false
-        );
+        let identifier_pk = Pubkey::new_unique();
+        account_info!(identifier, identifier_pk);
+        test_account_info!(identifier_lock, 0);
         account_info!(
-            wrong_token_acc,
-            Pubkey::new_unique(),
-            spl_token_account_data(USDT_TOKEN_ID),
-            spl_token::id(),
-            false
+            v_acc,
+            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+            vec![0; VerificationAccount::SIZE]
         );
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
 
-        test_pda_account_info!(pool, PoolAccount, None);
-        test_pda_account_info!(fee_collector, FeeCollectorAccount, None);
-        program_token_account_info!(pool_token, PoolAccount, USDC_TOKEN_ID);
-        program_token_account_info!(fee_collector_token, FeeCollectorAccount, USDC_TOKEN_ID);
-
-        let sol_usd = Price {
-            price: 39,
-            conf: 1,
-            expo: 0,
-        };
-        let usdc_usd = Price {
-            price: 1,
-            conf: 1,
-            expo: 0,
-        };
-        let price = TokenPrice::new_from_sol_price(sol_usd, usdc_usd, USDC_TOKEN_ID).unwrap();
-        pyth_price_account_info!(sol, LAMPORTS_TOKEN_ID, sol_usd);
-        pyth_price_account_info!(usdc, USDC_TOKEN_ID, usdc_usd);
+        let vkey_id = SendQuadraVKey::VKEY_ID;
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_version(&1);
 
         let mut inputs = SendPublicInputs {
             join_split: JoinSplitPublicInputs {
@@ -2196,832 +3509,5319 @@ mod tests {
                     nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
                 }],
                 output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
-                recent_commitment_index: 123,
+                recent_commitment_index: 0,
                 fee_version: 0,
-                amount: 1_000_000,
+                amount: LAMPORTS_PER_SOL,
                 fee: 0,
                 optional_fee: OptionalFee::default(),
-                token_id: USDC_TOKEN_ID,
+                token_id: 0,
                 metadata: CommitmentMetadata::default(),
             },
-            recipient_is_associated_token_account: false,
+            recipient_is_associated_token_account: true,
             hashed_inputs: u256_from_str_skip_mr("1"),
             solana_pay_transfer: false,
         };
-        compute_fee_rec::<SendQuadraVKey, _>(&mut inputs, &fee(), &price);
-        let instructions = prepare_public_inputs_instructions(
-            &inputs.public_signals_skip_mr(),
-            SendQuadraVKey::public_inputs_count(),
-        );
-
-        zero_program_account!(mut verification_acc, VerificationAccount);
-        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
-        verification_acc.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
-        verification_acc.set_other_data(&VerificationAccountData {
-            fee_payer: RawU256::new(fee_payer.key.to_bytes()),
-            ..Default::default()
-        });
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
 
-        // Invalid fee (fee too low, since too high is allowed)
-        inputs.join_split.fee -= 1;
-        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &token_acc,
-                &pool,
-                &pool_token,
-                &fee_collector,
-                &fee_collector_token,
-                &sol,
-                &usdc,
-                &governor,
-                &mut verification_acc,
-                &spl,
-                &sys,
-                0
-            ),
-            Err(ElusivError::InvalidFee.into())
+        account_info!(
+            n_duplicate_acc,
+            inputs.join_split.nullifier_duplicate_pda().0,
+            vec![0; NullifierDuplicateAccount::SIZE]
         );
 
-        inputs.join_split.fee = 0;
-        compute_fee_rec::<SendQuadraVKey, _>(&mut inputs, &fee(), &price);
-        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
-
-        // Invalid system_program
         assert_eq!(
-            init_verification_transfer_fee(
+            init_verification(
                 &fee_payer,
-                &token_acc,
-                &pool,
-                &pool_token,
-                &fee_collector,
-                &fee_collector_token,
-                &sol,
-                &usdc,
+                &v_acc,
+                &vkey,
+                &n_duplicate_acc,
+                &v_acc,
+                &identifier,
+                &identifier_lock,
+                &storage,
+                &mut buffer,
+                &nullifier,
+                &nullifier,
+                &clock,
                 &governor,
-                &mut verification_acc,
-                &spl,
-                &spl,
-                0
+                0,
+                vkey_id,
+                [0, 1],
+                ProofRequest::Send(inputs),
+                false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                1000,
             ),
-            Err(ProgramError::IncorrectProgramId)
+            Ok(())
         );
 
-        // Invalid token_program
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &token_acc,
-                &pool,
-                &pool_token,
-                &fee_collector,
-                &fee_collector_token,
-                &sol,
-                &usdc,
-                &governor,
-                &mut verification_acc,
-                &sys,
-                &sys,
-                0
-            ),
-            Err(ElusivError::InvalidAccount.into())
-        );
+        // The identifier is recorded as the cancellation authority
+        pda_account!(v_acc, VerificationAccount, v_acc);
+        assert_eq!(v_acc.get_earliest_finalize_time(), 1000);
+        assert_eq!(v_acc.get_delay_identifier(), identifier_pk.to_bytes());
 
-        // Invalid fee_payer_account
-        assert_eq!(
-            init_verification_transfer_fee(
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_nullifier_duplicate_account() -> ProgramResult {
+        let initiator = Pubkey::new_unique();
+
+        for skipped_first in [false, true] {
+            test_account_info!(beneficiary, 0);
+            account_info!(
+                n_duplicate_acc,
+                Pubkey::new_unique(),
+                vec![0; NullifierDuplicateAccount::SIZE]
+            );
+            {
+                pda_account!(
+                    mut n_duplicate_acc,
+                    NullifierDuplicateAccount,
+                    n_duplicate_acc
+                );
+                n_duplicate_acc.set_initiator(&ElusivOption::Some(initiator));
+                n_duplicate_acc.set_skip_verifications_count(&2);
+            }
+
+            let order = if skipped_first {
+                [true, true, false]
+            } else {
+                [false, true, true]
+            };
+            for (i, skipped) in order.into_iter().enumerate() {
+                assert_ne!(n_duplicate_acc.lamports(), 0);
+                release_nullifier_duplicate_account(&beneficiary, &n_duplicate_acc, skipped)
+                    .unwrap();
+
+                if i < order.len() - 1 {
+                    assert_ne!(n_duplicate_acc.lamports(), 0);
+                }
+            }
+
+            assert_eq!(n_duplicate_acc.lamports(), 0);
+        }
+
+        // Legacy accounts are only closed by the initiator
+        for skipped in [false, true] {
+            test_account_info!(beneficiary, 0);
+            account_info!(n_duplicate_acc, Pubkey::new_unique(), vec![1]);
+            release_nullifier_duplicate_account(&beneficiary, &n_duplicate_acc, skipped).unwrap();
+            assert_eq!(n_duplicate_acc.lamports() == 0, !skipped);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_init_verification_max_token_amount() {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+
+        let vkey_id = SendQuadraVKey::VKEY_ID;
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_version(&1);
+
+        let amount = LAMPORTS_PER_SOL;
+        for token_id in [LAMPORTS_TOKEN_ID, USDC_TOKEN_ID] {
+            let inputs = SendPublicInputs {
+                join_split: JoinSplitPublicInputs {
+                    input_commitments: vec![InputCommitment {
+                        root: Some(empty_root_raw()),
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                    }],
+                    output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                    recent_commitment_index: 0,
+                    fee_version: 0,
+                    amount,
+                    fee: 0,
+                    optional_fee: OptionalFee::default(),
+                    token_id,
+                    metadata: CommitmentMetadata::default(),
+                },
+                recipient_is_associated_token_account: true,
+                hashed_inputs: u256_from_str_skip_mr("1"),
+                solana_pay_transfer: false,
+            };
+
+            for (max, result) in [
+                (amount - 1, Err(ElusivError::InvalidAmount.into())),
+                (amount, Ok(())),
+            ] {
+                zero_program_account!(mut buffer, CommitmentBufferAccount);
+                zero_program_account!(mut governor, GovernorAccount);
+                governor.set_max_token_amounts(token_id as usize, &max);
+
+                // The limit is per token
+                let other_token_id = if token_id == LAMPORTS_TOKEN_ID {
+                    USDC_TOKEN_ID
+                } else {
+                    LAMPORTS_TOKEN_ID
+                };
+                governor.set_max_token_amounts(other_token_id as usize, &1);
+
+                clock_account_info!(clock, 42);
+                test_account_info!(fee_payer, 0);
+                test_account_info!(identifier, 0);
+                test_account_info!(identifier_lock, 0);
+                account_info!(
+                    v_acc,
+                    VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+                    vec![0; VerificationAccount::SIZE]
+                );
+                **v_acc.try_borrow_mut_lamports().unwrap() = 0;
+                account_info!(
+                    n_duplicate_acc,
+                    inputs.join_split.nullifier_duplicate_pda().0,
+                    vec![0; NullifierDuplicateAccount::SIZE]
+                );
+
+                assert_eq!(
+                    init_verification(
+                        &fee_payer,
+                        &v_acc,
+                        &vkey,
+                        &n_duplicate_acc,
+                        &v_acc,
+                        &identifier,
+                        &identifier_lock,
+                        &storage,
+                        &mut buffer,
+                        &nullifier,
+                        &nullifier,
+                        &clock,
+                        &governor,
+                        0,
+                        vkey_id,
+                        [0, 1],
+                        ProofRequest::Send(inputs.clone()),
+                        false,
+                        [0; 16],
+                        false,
+                        ElusivOption::None,
+                        ElusivOption::None,
+                        0,
+                    ),
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_init_verification_commitment_count_too_high() {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 0);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(identifier, 0);
+        test_account_info!(identifier_lock, 0);
+        account_info!(
+            v_acc,
+            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+            vec![0; VerificationAccount::SIZE]
+        );
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
+
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 123,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: true,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
+
+        account_info!(
+            n_duplicate_acc,
+            inputs.join_split.nullifier_duplicate_pda().0,
+            vec![0; NullifierDuplicateAccount::SIZE]
+        );
+
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_is_frozen(&true);
+
+        for i in inputs.join_split.input_commitments.len()..JOIN_SPLIT_MAX_N_ARITY + 1 {
+            inputs.join_split.input_commitments.push(InputCommitment {
+                root: None,
+                nullifier_hash: RawU256::new(u256_from_str_skip_mr(&i.to_string())),
+            });
+        }
+
+        let _ = init_verification(
+            &fee_payer,
+            &v_acc,
+            &vkey,
+            &n_duplicate_acc,
+            &v_acc,
+            &identifier,
+            &identifier_lock,
+            &storage,
+            &mut buffer,
+            &nullifier,
+            &nullifier,
+            &clock,
+            &governor,
+            0,
+            0,
+            [0, 1],
+            ProofRequest::Send(inputs),
+            false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
+        );
+    }
+
+    #[test]
+    fn test_init_verification_index_in_use() {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 42);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(identifier, 0);
+        test_account_info!(identifier_lock, 0);
+        account_info!(
+            v_acc,
+            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+            vec![0; VerificationAccount::SIZE]
+        );
+
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 0,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: true,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
+
+        account_info!(
+            n_duplicate_acc,
+            inputs.join_split.nullifier_duplicate_pda().0,
+            vec![0; NullifierDuplicateAccount::SIZE]
+        );
+
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_version(&1);
+
+        for i in 0..COMMITMENT_BUFFER_LEN {
+            buffer.try_insert(&[i as u8; 32]).unwrap();
+        }
+
+        macro_rules! init {
+            () => {
+                init_verification(
+                    &fee_payer,
+                    &v_acc,
+                    &vkey,
+                    &n_duplicate_acc,
+                    &v_acc,
+                    &identifier,
+                    &identifier_lock,
+                    &storage,
+                    &mut buffer,
+                    &nullifier,
+                    &nullifier,
+                    &clock,
+                    &governor,
+                    0,
+                    SendQuadraVKey::VKEY_ID,
+                    [0, 1],
+                    ProofRequest::Send(inputs.clone()),
+                    false,
+                    [0; 16],
+                    false,
+                    ElusivOption::None,
+                    ElusivOption::None,
+                    0,
+                )
+            };
+        }
+
+        // Active verification with the same index
+        {
+            let data = &mut v_acc.data.borrow_mut()[..];
+            let mut verification_account = VerificationAccount::new(data).unwrap();
+            verification_account.set_state(&VerificationState::ProofSetup);
+        }
+        assert_eq!(init!(), Err(ElusivError::VerificationIndexInUse.into()));
+
+        // Closed (drained) verification with the same index
+        {
+            let data = &mut v_acc.data.borrow_mut()[..];
+            let mut verification_account = VerificationAccount::new(data).unwrap();
+            verification_account.set_state(&VerificationState::Closed);
+            verification_account.set_vkey_id(&u32::MAX);
+        }
+        // The runtime purges the drained account after the closing transaction
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
+        v_acc.data.borrow_mut().fill(0);
+        assert_eq!(init!(), Ok(()));
+
+        let data = &mut v_acc.data.borrow_mut()[..];
+        let verification_account = VerificationAccount::new(data).unwrap();
+        assert_eq!(
+            verification_account.get_state().unwrap(),
+            VerificationState::None
+        );
+        assert_eq!(verification_account.get_vkey_id(), SendQuadraVKey::VKEY_ID);
+        assert_eq!(
+            verification_account.get_request().unwrap(),
+            ProofRequest::Send(inputs)
+        );
+        assert_eq!(
+            verification_account.get_other_data().fee_payer,
+            RawU256::new(fee_payer.key.to_bytes())
+        );
+        assert_eq!(verification_account.get_creation_slot(), 42);
+    }
+
+    #[test]
+    fn test_init_verification_public_inputs_count() {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 0);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(identifier, 0);
+        test_account_info!(identifier_lock, 0);
+        account_info!(
+            v_acc,
+            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+            vec![0; VerificationAccount::SIZE]
+        );
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
+
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 0,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: true,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
+
+        account_info!(
+            n_duplicate_acc,
+            inputs.join_split.nullifier_duplicate_pda().0,
+            vec![0; NullifierDuplicateAccount::SIZE]
+        );
+
+        let mut vkey = VKeyAccount::zeroed();
+        vkey.set_version(&1);
+
+        macro_rules! init {
+            () => {
+                init_verification(
+                    &fee_payer,
+                    &v_acc,
+                    &vkey,
+                    &n_duplicate_acc,
+                    &v_acc,
+                    &identifier,
+                    &identifier_lock,
+                    &storage,
+                    &mut buffer,
+                    &nullifier,
+                    &nullifier,
+                    &clock,
+                    &governor,
+                    0,
+                    SendQuadraVKey::VKEY_ID,
+                    [0, 1],
+                    ProofRequest::Send(inputs.clone()),
+                    false,
+                    [0; 16],
+                    false,
+                    ElusivOption::None,
+                    ElusivOption::None,
+                    0,
+                )
+            };
+        }
+
+        // Stored public inputs count differs from the proof's public inputs
+        for public_inputs_count in [
+            0,
+            SendQuadraVKey::PUBLIC_INPUTS_COUNT - 1,
+            SendQuadraVKey::PUBLIC_INPUTS_COUNT + 1,
+            MigrateUnaryVKey::PUBLIC_INPUTS_COUNT,
+        ] {
+            vkey.set_public_inputs_count(&public_inputs_count);
+            assert_eq!(init!(), Err(ElusivError::InvalidPublicInputs.into()));
+        }
+
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        assert_eq!(init!(), Ok(()));
+    }
+
+    #[test]
+    fn test_init_verification_identifier_lock() {
+        parent_account!(storage, StorageAccount);
+        parent_account!(nullifier, NullifierAccount);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        clock_account_info!(clock, 0);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(identifier, 0);
+        account_info!(
+            v_acc,
+            VerificationAccount::find_with_pubkey(*fee_payer.key, Some(0)).0,
+            vec![0; VerificationAccount::SIZE]
+        );
+        **v_acc.try_borrow_mut_lamports().unwrap() = 0;
+
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 0,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: true,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
+
+        account_info!(
+            n_duplicate_acc,
+            inputs.join_split.nullifier_duplicate_pda().0,
+            vec![0; NullifierDuplicateAccount::SIZE]
+        );
+
+        let vkey_id = SendQuadraVKey::VKEY_ID;
+        let mut data = vec![0; VKeyAccount::SIZE];
+        let mut vkey = VKeyAccount::new(&mut data).unwrap();
+        vkey.set_public_inputs_count(&SendQuadraVKey::PUBLIC_INPUTS_COUNT);
+        vkey.set_version(&1);
+
+        let lock_pubkey = IdentifierLockAccount::find_with_pubkey(*identifier.key, None).0;
+        account_info!(identifier_lock, lock_pubkey);
+        account_info!(invalid_identifier_lock, Pubkey::new_unique());
+        account_info!(zero_identifier, Pubkey::default());
+        **invalid_identifier_lock.try_borrow_mut_lamports().unwrap() = 0;
+
+        macro_rules! init_locked_verification {
+            ($identifier: ident, $identifier_lock: ident) => {
+                init_verification(
+                    &fee_payer,
+                    &v_acc,
+                    &vkey,
+                    &n_duplicate_acc,
+                    &v_acc,
+                    &$identifier,
+                    &$identifier_lock,
+                    &storage,
+                    &mut buffer,
+                    &nullifier,
+                    &nullifier,
+                    &clock,
+                    &governor,
+                    0,
+                    vkey_id,
+                    [0, 1],
+                    ProofRequest::Send(inputs.clone()),
+                    false,
+                    [0; 16],
+                    true,
+                    ElusivOption::None,
+                    ElusivOption::None,
+                    0,
+                )
+            };
+        }
+
+        // A concurrent send already locked the identifier
+        assert_eq!(
+            init_locked_verification!(identifier, identifier_lock),
+            Err(ElusivError::DuplicateValue.into())
+        );
+
+        // Invalid identifier_lock_account
+        assert_eq!(
+            init_locked_verification!(identifier, invalid_identifier_lock),
+            Err(ProgramError::InvalidSeeds)
+        );
+
+        // Zero identifier
+        assert_eq!(
+            init_locked_verification!(zero_identifier, invalid_identifier_lock),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        **identifier_lock.try_borrow_mut_lamports().unwrap() = 0;
+        assert_eq!(
+            init_locked_verification!(identifier, identifier_lock),
+            Ok(())
+        );
+
+        let data = &mut v_acc.data.borrow_mut()[..];
+        let v_acc = VerificationAccount::new(data).unwrap();
+        assert_eq!(v_acc.get_locked_identifier(), identifier.key.to_bytes());
+    }
+
+    #[test]
+    fn test_identifier_lock_finalization() {
+        test_account_info!(identifier, 0);
+        test_account_info!(any, 0);
+        test_account_info!(beneficiary, 0);
+        let (lock_pubkey, bump) = IdentifierLockAccount::find_with_pubkey(*identifier.key, None);
+        account_info!(identifier_lock, lock_pubkey, vec![bump]);
+        let identifier = identifier.key.to_bytes();
+
+        // Without a lock any account can be supplied
+        assert_eq!(verify_identifier_lock(&[0; 32], &any), Ok(()));
+        close_identifier_lock(&beneficiary, &any, &[0; 32]).unwrap();
+        assert_eq!(any.lamports(), u32::MAX as u64);
+
+        assert_eq!(
+            verify_identifier_lock(&identifier, &any),
+            Err(ProgramError::UninitializedAccount)
+        );
+        account_info!(invalid_identifier_lock, Pubkey::new_unique(), vec![bump]);
+        assert_eq!(
+            verify_identifier_lock(&identifier, &invalid_identifier_lock),
+            Err(ElusivError::InvalidAccount.into())
+        );
+        assert_eq!(
+            verify_identifier_lock(&identifier, &identifier_lock),
+            Ok(())
+        );
+
+        // The lock is released at the finalization
+        let lamports = beneficiary.lamports() + identifier_lock.lamports();
+        close_identifier_lock(&beneficiary, &identifier_lock, &identifier).unwrap();
+        assert_eq!(identifier_lock.lamports(), 0);
+        assert_eq!(beneficiary.lamports(), lamports);
+    }
+
+    #[test]
+    fn test_init_verification_transfer_fee_lamports() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        test_account_info!(any, 0);
+        account_info!(sys, system_program::id());
+        clock_account_info!(clock, 0);
+        account_info!(spl, spl_token::id());
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        governor.set_program_fee(&fee());
+
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 123,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: false,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
+        let instructions = prepare_public_inputs_instructions(
+            &inputs.public_signals_skip_mr(),
+            SendQuadraVKey::public_inputs_count(),
+        );
+
+        zero_program_account!(mut verification_acc, VerificationAccount);
+        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+        verification_acc.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
+        verification_acc.set_other_data(&VerificationAccountData {
+            fee_payer: RawU256::new(fee_payer.key.to_bytes()),
+            ..Default::default()
+        });
+
+        // The external reference has no influence on the fees
+        verification_acc.set_external_reference(&[9; 16]);
+
+        // Token-account with lamports is invalid
+        account_info!(
+            token_acc,
+            Pubkey::new_unique(),
+            spl_token_account_data(USDC_TOKEN_ID),
+            spl_token::id(),
+            false
+        );
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid fee_payer
+        test_account_info!(fee_payer2, 0);
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer2,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid verification account state
+        verification_acc.set_state(&VerificationState::FeeTransferred);
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+
+        // Invalid fee_version
+        verification_acc.set_state(&VerificationState::None);
+        governor.set_fee_version(&1);
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidFeeVersion.into())
+        );
+
+        // Retired fee_version
+        governor.set_fee_version(&0);
+        zero_program_account!(mut retired_fee_version_usage, FeeVersionUsageAccount);
+        retired_fee_version_usage.set_min_fee_version(&1);
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut retired_fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidFeeVersion.into())
+        );
+
+        // Scheduled fee update (a too low fee is used to stop after the fee-version check)
+        governor.set_fee_version_grace_slots(&5);
+        governor.set_scheduled_fee_update(&ElusivOption::Some(ScheduledFeeUpdate {
+            program_fee: fee(),
+            activation_slot: 100,
+        }));
+        for (fee_version, slot, result) in [
+            (1, 99, Err(ElusivError::InvalidFeeVersion)),
+            (1, 100, Err(ElusivError::InvalidFee)),
+            (0, 99, Err(ElusivError::InvalidFee)),
+            (0, 104, Err(ElusivError::InvalidFee)),
+            (0, 105, Err(ElusivError::InvalidFeeVersion)),
+            (2, 105, Err(ElusivError::InvalidFeeVersion)),
+        ] {
+            let mut inputs = inputs.clone();
+            inputs.join_split.fee_version = fee_version;
+            inputs.join_split.fee -= 1;
+            verification_acc.set_request(&ProofRequest::Send(inputs));
+            clock_account_info!(clock, slot);
+
+            assert_eq!(
+                init_verification_transfer_fee(
+                    &fee_payer,
+                    &fee_payer,
+                    &pool,
+                    &pool,
+                    &fee_collector,
+                    &fee_collector,
+                    &any,
+                    &any,
+                    &governor,
+                    &mut commitment_queue,
+                    &mut verification_acc,
+                    &sys,
+                    &sys,
+                    &mut fee_version_usage,
+                    &clock,
+                    0,
+                ),
+                result.map_err(|e| e.into())
+            );
+        }
+        governor.set_scheduled_fee_update(&ElusivOption::None);
+        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+
+        // Invalid fee (fee too low, since too high is allowed)
+        inputs.join_split.fee -= 1;
+        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidFee.into())
+        );
+
+        // Invalid system_program
+        inputs.join_split.fee = 0;
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
+        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &spl,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ProgramError::IncorrectProgramId)
+        );
+
+        // Invalid pool_account
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &any,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid fee_collector_account
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &any,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &any,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            verification_acc.get_state().unwrap(),
+            VerificationState::FeeTransferred
+        );
+        assert_eq!(
+            CommitmentQueue::new(&mut commitment_queue).pending_commitments(),
+            1
+        );
+
+        // The payout is reserved in the pool
+        let data = verification_acc.get_other_data();
+        {
+            let pool_data = &mut pool.data.borrow_mut()[..];
+            let pool = PoolAccount::new(pool_data).unwrap();
+            assert_eq!(
+                pool.get_pending_lamport_reservation(),
+                data.commitment_hash_fee_token
+                    + data.proof_verification_fee
+                    + inputs.join_split.amount
+            );
+        }
+
+        // Fee balance
+        let fees = verification_acc.get_accumulated_fees().unwrap();
+        assert_eq!(fees.fee_paid, inputs.join_split.fee);
+        assert_eq!(fees.subvention_received, data.subvention);
+        assert_eq!(fees.proof_verification_fee, data.proof_verification_fee);
+        assert_eq!(fees.network_fee, data.network_fee);
+        verification_acc.assert_fee_balance_invariant().unwrap();
+
+        // Without the SOL price account no USD value is recorded
+        assert_eq!(
+            verification_acc.get_other_data().amount_usd,
+            ElusivOption::None
+        );
+
+        // With the SOL price account the USD value is recorded
+        let sol_usd = Price {
+            price: 39,
+            conf: 1,
+            expo: 0,
+        };
+        pyth_price_account_info!(sol, LAMPORTS_TOKEN_ID, sol_usd);
+        verification_acc.set_state(&VerificationState::None);
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &sol,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Ok(())
+        );
+
+        let price = TokenPrice::new_lamports_usd(&sol).unwrap();
+        assert_eq!(
+            verification_acc.get_other_data().amount_usd,
+            ElusivOption::Some(
+                price
+                    .token_into_usd(Token::new(0, LAMPORTS_PER_SOL))
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            verification_acc.get_other_data().amount_usd,
+            ElusivOption::Some(39_000_000)
+        );
+
+        // Commitment queue congested
+        governor.set_commitment_queue_high_water_mark(&2);
+        verification_acc.set_state(&VerificationState::None);
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &fee_payer,
+                &pool,
+                &pool,
+                &fee_collector,
+                &fee_collector,
+                &sol,
+                &any,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0,
+            ),
+            Err(ElusivError::CommitmentQueueCongested.into())
+        );
+        assert_eq!(
+            CommitmentQueue::new(&mut commitment_queue).pending_commitments(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_init_verification_transfer_fee_lamports_oracle_degraded() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        account_info!(sys, system_program::id());
+        clock_account_info!(clock, 0);
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        governor.set_program_fee(&fee());
+        governor.set_oracle_degraded(&true);
+
+        // Garbage placeholders (also at the key of the SOL price account)
+        account_info!(
+            sol,
+            elusiv_token(LAMPORTS_TOKEN_ID).unwrap().pyth_usd_price_key,
+            vec![1; 8]
+        );
+        account_info!(token, Pubkey::new_unique(), vec![1; 8]);
+
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 123,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: false,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &fee());
+        let instructions = prepare_public_inputs_instructions(
+            &inputs.public_signals_skip_mr(),
+            SendQuadraVKey::public_inputs_count(),
+        );
+
+        zero_program_account!(mut verification_acc, VerificationAccount);
+        verification_acc.set_request(&ProofRequest::Send(inputs));
+        verification_acc.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
+        verification_acc.set_other_data(&VerificationAccountData {
+            fee_payer: RawU256::new(fee_payer.key.to_bytes()),
+            ..Default::default()
+        });
+
+        init_verification_transfer_fee(
+            &fee_payer,
+            &fee_payer,
+            &pool,
+            &pool,
+            &fee_collector,
+            &fee_collector,
+            &sol,
+            &token,
+            &governor,
+            &mut commitment_queue,
+            &mut verification_acc,
+            &sys,
+            &sys,
+            &mut fee_version_usage,
+            &clock,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            verification_acc.get_state().unwrap(),
+            VerificationState::FeeTransferred
+        );
+        assert_eq!(
+            verification_acc.get_other_data().amount_usd,
+            ElusivOption::None
+        );
+    }
+
+    #[test]
+    fn test_init_verification_transfer_fee_merge_discount() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        test_account_info!(any, 0);
+        account_info!(sys, system_program::id());
+        clock_account_info!(clock, 0);
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+
+        let fee_without_discount = fee();
+        let fee_with_discount = ProgramFee {
+            merge_fee_discount: BasisPointFee(2_500),
+            ..fee()
+        };
+        assert!(fee_with_discount.is_valid());
+        assert!(!ProgramFee {
+            merge_fee_discount: BasisPointFee(10_001),
+            ..fee()
+        }
+        .is_valid());
+
+        let mut results = Vec::new();
+        for program_fee in [&fee_without_discount, &fee_with_discount] {
+            governor.set_program_fee(program_fee);
+
+            let mut inputs = SendPublicInputs {
+                join_split: JoinSplitPublicInputs {
+                    input_commitments: vec![InputCommitment {
+                        root: Some(empty_root_raw()),
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                    }],
+                    output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                    recent_commitment_index: 123,
+                    fee_version: 0,
+                    amount: 0,
+                    fee: 0,
+                    optional_fee: OptionalFee::default(),
+                    token_id: 0,
+                    metadata: CommitmentMetadata::default(),
+                },
+                recipient_is_associated_token_account: false,
+                hashed_inputs: u256_from_str_skip_mr("1"),
+                solana_pay_transfer: false,
+            };
+            assert!(inputs.is_merge());
+            compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, program_fee);
+            let input_preparation_tx_count = prepare_public_inputs_instructions(
+                &inputs.public_signals_skip_mr(),
+                SendQuadraVKey::public_inputs_count(),
+            )
+            .len();
+
+            zero_program_account!(mut verification_acc, VerificationAccount);
+            verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+            verification_acc
+                .set_prepare_inputs_instructions_count(&(input_preparation_tx_count as u32));
+            verification_acc.set_other_data(&VerificationAccountData {
+                fee_payer: RawU256::new(fee_payer.key.to_bytes()),
+                ..Default::default()
+            });
+
+            let fee_collector_lamports = fee_collector.lamports();
+            assert_eq!(
+                init_verification_transfer_fee(
+                    &fee_payer,
+                    &fee_payer,
+                    &pool,
+                    &pool,
+                    &fee_collector,
+                    &fee_collector,
+                    &any,
+                    &any,
+                    &governor,
+                    &mut commitment_queue,
+                    &mut verification_acc,
+                    &sys,
+                    &sys,
+                    &mut fee_version_usage,
+                    &clock,
+                    0,
+                ),
+                Ok(())
+            );
+
+            results.push((
+                inputs.join_split.fee,
+                verification_acc.get_other_data(),
+                fee_collector_lamports - fee_collector.lamports(),
+                input_preparation_tx_count,
+            ));
+        }
+
+        let (fee, data, fee_collector_outflow, input_preparation_tx_count) = &results[0];
+        let (discounted_fee, discounted_data, discounted_fee_collector_outflow, _) = &results[1];
+        let merge_subvention = fee_with_discount
+            .merge_subvention(*input_preparation_tx_count)
+            .0;
+        assert!(merge_subvention > 0);
+
+        // A zero discount reproduces the regular fee
+        assert_eq!(
+            fee_without_discount.merge_subvention(*input_preparation_tx_count),
+            Lamports(0)
+        );
+        assert_eq!(
+            *fee,
+            fee_without_discount.commitment_hash_computation_fee(0).0
+                + fee_without_discount
+                    .proof_verification_computation_fee(*input_preparation_tx_count)
+                    .0
+                - fee_without_discount.proof_subvention.0
+        );
+
+        // The merge fee is reduced by the discount
+        assert_eq!(*fee - *discounted_fee, merge_subvention);
+
+        // Warden compensation is unchanged
+        assert_eq!(
+            data.proof_verification_fee,
+            discounted_data.proof_verification_fee
+        );
+        assert_eq!(
+            data.commitment_hash_fee_token,
+            discounted_data.commitment_hash_fee_token
+        );
+
+        // The fee collector funds the discount
+        assert_eq!(*fee_collector_outflow, data.subvention);
+        assert_eq!(
+            *discounted_fee_collector_outflow,
+            discounted_data.subvention
+        );
+        assert_eq!(
+            discounted_fee_collector_outflow - fee_collector_outflow,
+            merge_subvention
+        );
+    }
+
+    #[test]
+    fn test_init_verification_transfer_fee_estimated_fee_accepted() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        test_account_info!(fee_payer, 0);
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        test_account_info!(any, 0);
+        account_info!(sys, system_program::id());
+        clock_account_info!(clock, 0);
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+
+        let rent = system_program_account_rent().unwrap().0;
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..32 {
+            let program_fee = ProgramFee::new(
+                rng.gen_range(0..100_000),
+                11,
+                rng.gen_range(0..=10_000),
+                0,
+                0,
+                rng.gen_range(0..100_000),
+                rng.gen_range(0..100_000),
+            )
+            .unwrap();
+            governor.set_program_fee(&program_fee);
+
+            let mut inputs = SendPublicInputs {
+                join_split: JoinSplitPublicInputs {
+                    input_commitments: vec![InputCommitment {
+                        root: Some(empty_root_raw()),
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                    }],
+                    output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                    recent_commitment_index: 123,
+                    fee_version: 0,
+                    amount: rng.gen_range(rent..u64::MAX / 2),
+                    fee: 0,
+                    optional_fee: OptionalFee::default(),
+                    token_id: 0,
+                    metadata: CommitmentMetadata::default(),
+                },
+                recipient_is_associated_token_account: false,
+                hashed_inputs: u256_from_str_skip_mr("1"),
+                solana_pay_transfer: false,
+            };
+            compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &program_fee);
+            let input_preparation_tx_count = prepare_public_inputs_instructions(
+                &inputs.public_signals_skip_mr(),
+                SendQuadraVKey::public_inputs_count(),
+            )
+            .len();
+
+            // The estimated fee is never below the network fee
+            assert!(
+                inputs.join_split.fee
+                    >= program_fee.proof_network_fee.calc(inputs.join_split.amount)
+            );
+
+            for (fee, result) in [
+                (
+                    inputs.join_split.fee - 1,
+                    Err(ElusivError::InvalidFee.into()),
+                ),
+                (inputs.join_split.fee, Ok(())),
+            ] {
+                let mut inputs = inputs.clone();
+                inputs.join_split.fee = fee;
+
+                // Reset the pending reservations
+                pool.data.borrow_mut().fill(0);
+                zero_program_account!(mut verification_acc, VerificationAccount);
+                verification_acc.set_request(&ProofRequest::Send(inputs));
+                verification_acc
+                    .set_prepare_inputs_instructions_count(&(input_preparation_tx_count as u32));
+                verification_acc.set_other_data(&VerificationAccountData {
+                    fee_payer: RawU256::new(fee_payer.key.to_bytes()),
+                    ..Default::default()
+                });
+
+                assert_eq!(
+                    init_verification_transfer_fee(
+                        &fee_payer,
+                        &fee_payer,
+                        &pool,
+                        &pool,
+                        &fee_collector,
+                        &fee_collector,
+                        &any,
+                        &any,
+                        &governor,
+                        &mut commitment_queue,
+                        &mut verification_acc,
+                        &sys,
+                        &sys,
+                        &mut fee_version_usage,
+                        &clock,
+                        0,
+                    ),
+                    result
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_init_verification_transfer_fee_subvention_cap() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        test_account_info!(fee_payer, 0);
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        test_account_info!(any, 0);
+        account_info!(sys, system_program::id());
+        clock_account_info!(clock, 0);
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+
+        let program_fee = fee();
+        governor.set_program_fee(&program_fee);
+
+        // 1% of the amount
+        governor.set_proof_subvention_cap(&100);
+
+        for (amount, subvention) in [
+            // Large withdrawal: full flat subvention
+            (1_000_000, program_fee.proof_subvention.0),
+            (4_400, program_fee.proof_subvention.0),
+            // Dust withdrawals: capped fraction of the amount
+            (4_399, 43),
+            (2_000, 20),
+            (99, 0),
+        ] {
+            let mut inputs = SendPublicInputs {
+                join_split: JoinSplitPublicInputs {
+                    input_commitments: vec![InputCommitment {
+                        root: Some(empty_root_raw()),
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                    }],
+                    output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                    recent_commitment_index: 123,
+                    fee_version: 0,
+                    amount,
+                    fee: 0,
+                    optional_fee: OptionalFee::default(),
+                    token_id: 0,
+                    metadata: CommitmentMetadata::default(),
+                },
+                recipient_is_associated_token_account: false,
+                hashed_inputs: u256_from_str_skip_mr("1"),
+                solana_pay_transfer: false,
+            };
+            compute_fee_rec_lamports::<SendQuadraVKey, _>(&mut inputs, &program_fee);
+            let input_preparation_tx_count = prepare_public_inputs_instructions(
+                &inputs.public_signals_skip_mr(),
+                SendQuadraVKey::public_inputs_count(),
+            )
+            .len();
+
+            // The uncapped estimate only suffices if the full subvention is granted
+            let fee = inputs.join_split.fee + program_fee.proof_subvention.0 - subvention;
+            for (fee, result) in [
+                (fee - 1, Err(ElusivError::InvalidFee.into())),
+                (fee, Ok(())),
+            ] {
+                let mut inputs = inputs.clone();
+                inputs.join_split.fee = fee;
+
+                pool.data.borrow_mut().fill(0);
+                zero_program_account!(mut verification_acc, VerificationAccount);
+                verification_acc.set_request(&ProofRequest::Send(inputs));
+                verification_acc
+                    .set_prepare_inputs_instructions_count(&(input_preparation_tx_count as u32));
+                verification_acc.set_other_data(&VerificationAccountData {
+                    fee_payer: RawU256::new(fee_payer.key.to_bytes()),
+                    ..Default::default()
+                });
+
+                let fee_collector_lamports = fee_collector.lamports();
+                assert_eq!(
+                    init_verification_transfer_fee(
+                        &fee_payer,
+                        &fee_payer,
+                        &pool,
+                        &pool,
+                        &fee_collector,
+                        &fee_collector,
+                        &any,
+                        &any,
+                        &governor,
+                        &mut commitment_queue,
+                        &mut verification_acc,
+                        &sys,
+                        &sys,
+                        &mut fee_version_usage,
+                        &clock,
+                        0,
+                    ),
+                    result
+                );
+
+                if result.is_ok() {
+                    assert_eq!(verification_acc.get_other_data().subvention, subvention);
+                    assert_eq!(
+                        fee_collector_lamports - fee_collector.lamports(),
+                        subvention
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_init_verification_transfer_fee_token() {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        test_account_info!(fee_payer, 0);
+        account_info!(sys, system_program::id());
+        clock_account_info!(clock, 0);
+        account_info!(spl, spl_token::id());
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        governor.set_program_fee(&fee());
+
+        account_info!(
+            token_acc,
+            Pubkey::new_unique(),
+            spl_token_account_data(USDC_TOKEN_ID),
+            spl_token::id(),
+            false
+        );
+        account_info!(
+            wrong_token_acc,
+            Pubkey::new_unique(),
+            spl_token_account_data(USDT_TOKEN_ID),
+            spl_token::id(),
+            false
+        );
+
+        test_pda_account_info!(pool, PoolAccount, None);
+        test_pda_account_info!(fee_collector, FeeCollectorAccount, None);
+        program_token_account_info!(pool_token, PoolAccount, USDC_TOKEN_ID);
+        program_token_account_info!(fee_collector_token, FeeCollectorAccount, USDC_TOKEN_ID);
+
+        let sol_usd = Price {
+            price: 39,
+            conf: 1,
+            expo: 0,
+        };
+        let usdc_usd = Price {
+            price: 1,
+            conf: 1,
+            expo: 0,
+        };
+        let price = TokenPrice::new_from_sol_price(sol_usd, usdc_usd, USDC_TOKEN_ID).unwrap();
+        pyth_price_account_info!(sol, LAMPORTS_TOKEN_ID, sol_usd);
+        pyth_price_account_info!(usdc, USDC_TOKEN_ID, usdc_usd);
+
+        let mut inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 123,
+                fee_version: 0,
+                amount: 1_000_000,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: USDC_TOKEN_ID,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: false,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        };
+        compute_fee_rec::<SendQuadraVKey, _>(&mut inputs, &fee(), &price);
+        let instructions = prepare_public_inputs_instructions(
+            &inputs.public_signals_skip_mr(),
+            SendQuadraVKey::public_inputs_count(),
+        );
+
+        zero_program_account!(mut verification_acc, VerificationAccount);
+        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+        verification_acc.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
+        verification_acc.set_other_data(&VerificationAccountData {
+            fee_payer: RawU256::new(fee_payer.key.to_bytes()),
+            ..Default::default()
+        });
+
+        // Token verifications are rejected while the oracle is degraded (even with available prices)
+        governor.set_oracle_degraded(&true);
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ElusivError::OracleUnavailable.into())
+        );
+        assert_eq!(fee_version_usage.pending_count(0), Some(0));
+        governor.set_oracle_degraded(&false);
+
+        // Invalid fee (fee too low, since too high is allowed)
+        inputs.join_split.fee -= 1;
+        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ElusivError::InvalidFee.into())
+        );
+
+        inputs.join_split.fee = 0;
+        compute_fee_rec::<SendQuadraVKey, _>(&mut inputs, &fee(), &price);
+        verification_acc.set_request(&ProofRequest::Send(inputs.clone()));
+
+        // Invalid system_program
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &spl,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ProgramError::IncorrectProgramId)
+        );
+
+        // Invalid token_program
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &sys,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid fee_payer_account
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &wrong_token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // The fee_payer_account mint is checked before the price and fee computation
+        assert_eq!(
+            init_verification_transfer_fee(
                 &fee_payer,
                 &wrong_token_acc,
                 &pool,
-                &pool_token,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &usdc,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+        assert_eq!(
+            verification_acc.get_other_data().fee_payer_account,
+            RawU256::default()
+        );
+
+        // Invalid pool_account
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &fee_collector_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid fee_collector_account
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &pool_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid sol_usd_price_account
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &usdc,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(TokenError::InvalidPriceAccount.into())
+        );
+
+        // Invalid token_usd_price_account
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &sol,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Err(TokenError::InvalidPriceAccount.into())
+        );
+
+        assert_eq!(
+            init_verification_transfer_fee(
+                &fee_payer,
+                &token_acc,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &sol,
+                &usdc,
+                &governor,
+                &mut commitment_queue,
+                &mut verification_acc,
+                &spl,
+                &sys,
+                &mut fee_version_usage,
+                &clock,
+                0
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            verification_acc.get_state().unwrap(),
+            VerificationState::FeeTransferred
+        );
+
+        // Fee balance
+        let data = verification_acc.get_other_data();
+        let fees = verification_acc.get_accumulated_fees().unwrap();
+        assert_eq!(fees.fee_paid, inputs.join_split.fee);
+        assert_eq!(fees.subvention_received, data.subvention);
+        assert_eq!(fees.commitment_hash_fee, data.commitment_hash_fee_token);
+        verification_acc.assert_fee_balance_invariant().unwrap();
+
+        assert_eq!(
+            verification_acc.get_other_data().amount_usd,
+            ElusivOption::Some(
+                price
+                    .token_into_usd(Token::new(USDC_TOKEN_ID, 1_000_000))
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            verification_acc.get_other_data().amount_usd,
+            ElusivOption::Some(1_000_000)
+        );
+    }
+
+    #[test]
+    fn test_init_verification_proof() {
+        let proof = test_proof();
+        let valid_pk = Pubkey::new(&[0; 32]);
+        account_info!(fee_payer, valid_pk, vec![0; 0]);
+        zero_program_account!(mut verification_account, VerificationAccount);
+
+        // Account setup
+        verification_account.set_state(&VerificationState::ProofSetup);
+        assert_eq!(
+            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+        verification_account.set_state(&VerificationState::FeeTransferred);
+
+        // Computation already finished
+        verification_account.set_is_verified(&ElusivOption::Some(true));
+        assert_eq!(
+            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+        verification_account.set_is_verified(&ElusivOption::Some(false));
+        assert_eq!(
+            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+        verification_account.set_is_verified(&ElusivOption::None);
+
+        // Invalid fee_payer
+        let invalid_pk = Pubkey::new_unique();
+        account_info!(invalid_fee_payer, invalid_pk, vec![0; 0]);
+        assert_eq!(
+            init_verification_proof(&invalid_fee_payer, &mut verification_account, 0, proof),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Missing input preparation schedule
+        assert_eq!(
+            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            Err(ElusivError::CorruptedComputationSchedule.into())
+        );
+
+        let public_inputs = match verification_account.get_request().unwrap() {
+            ProofRequest::Send(public_inputs) => public_inputs.public_signals_skip_mr(),
+            ProofRequest::Migrate(_) => unreachable!(),
+        };
+        let instructions = prepare_public_inputs_instructions(&public_inputs, public_inputs.len());
+        verification_account
+            .setup_public_inputs_instructions(&instructions)
+            .unwrap();
+
+        // Tampered schedule (one round short or one round too many)
+        let last = instructions.len() - 1;
+        for rounds in [instructions[last] - 1, instructions[last] + 1] {
+            verification_account.set_prepare_inputs_instructions(last, &(rounds as u16));
+            assert_eq!(
+                init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+                Err(ElusivError::CorruptedComputationSchedule.into())
+            );
+        }
+        verification_account.set_prepare_inputs_instructions(last, &(instructions[last] as u16));
+
+        // Success
+        assert_eq!(
+            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            Ok(())
+        );
+        assert_eq!(
+            verification_account.get_state().unwrap(),
+            VerificationState::ProofSetup
+        );
+        assert_eq!(verification_account.a.get(), proof.a);
+        assert_eq!(verification_account.b.get(), proof.b);
+        assert_eq!(verification_account.c.get(), proof.c);
+
+        // Already setup proof
+        assert_eq!(
+            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+    }
+
+    #[test]
+    fn test_expire_verification() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        clock_account_info!(clock, 1100);
+        verification_account.set_creation_slot(&1000);
+        verification_account.set_state(&VerificationState::ProofSetup);
+
+        // Verifications do not expire without a TTL
+        assert_eq!(
+            expire_verification(&mut verification_account, &governor, &clock, 0),
+            Err(ElusivError::FeatureNotAvailable.into())
+        );
+
+        // TTL not exceeded
+        governor.set_verification_ttl_slots(&100);
+        assert_eq!(
+            expire_verification(&mut verification_account, &governor, &clock, 0),
+            Err(ElusivError::ComputationInProgress.into())
+        );
+
+        // Invalid clock
+        test_account_info!(any, 0);
+        assert_eq!(
+            expire_verification(&mut verification_account, &governor, &any, 0),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        clock_account_info!(clock, 1101);
+
+        // Verification finished just before the expiry
+        verification_account.set_is_verified(&ElusivOption::Some(true));
+        assert_eq!(
+            expire_verification(&mut verification_account, &governor, &clock, 0),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+        assert_eq!(
+            verification_account.get_is_verified(),
+            ElusivOption::Some(true)
+        );
+        verification_account.set_is_verified(&ElusivOption::None);
+
+        // Verifications in the finalization cannot be expired
+        for state in [
+            VerificationState::None,
+            VerificationState::InsertNullifiers,
+            VerificationState::Finalized,
+            VerificationState::Closed,
+        ] {
+            verification_account.set_state(&state);
+            assert_eq!(
+                expire_verification(&mut verification_account, &governor, &clock, 0),
+                Err(ElusivError::InvalidAccountState.into())
+            );
+        }
+
+        // Expired verifications fail (also without a submitted proof)
+        for state in [
+            VerificationState::ProofSetup,
+            VerificationState::FeeTransferred,
+        ] {
+            verification_account.set_state(&state);
+            verification_account.set_is_verified(&ElusivOption::None);
+            assert_eq!(
+                expire_verification(&mut verification_account, &governor, &clock, 0),
+                Ok(())
+            );
+            assert_eq!(
+                verification_account.get_is_verified(),
+                ElusivOption::Some(false)
+            );
+            assert_eq!(
+                verification_account.get_fault(),
+                ElusivOption::Some(VerificationFault::WardenFault)
+            );
+            assert_eq!(
+                verification_account.get_state().unwrap(),
+                VerificationState::ProofSetup
+            );
+        }
+    }
+
+    #[test]
+    fn test_cancel_delayed_send() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        let identifier_pk = Pubkey::new_unique();
+        account_info!(identifier, identifier_pk, true);
+        account_info!(other_identifier, Pubkey::new_unique(), true);
+        clock_account_info!(clock, 0, 999);
+        clock_account_info!(late_clock, 0, 1000);
+        verification_account.set_state(&VerificationState::ProofSetup);
+
+        // Only delayed sends can be cancelled
+        assert_eq!(
+            cancel_delayed_send(&identifier, &mut verification_account, &clock, 0),
+            Err(ElusivError::FeatureNotAvailable.into())
+        );
+
+        verification_account.set_earliest_finalize_time(&1000);
+        verification_account.set_delay_identifier(&identifier_pk.to_bytes());
+
+        // Invalid identifier
+        assert_eq!(
+            cancel_delayed_send(&other_identifier, &mut verification_account, &clock, 0),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Window closed
+        assert_eq!(
+            cancel_delayed_send(&identifier, &mut verification_account, &late_clock, 0),
+            Err(ElusivError::CancellationWindowClosed.into())
+        );
+
+        // Verifications in the finalization cannot be cancelled
+        for state in [
+            VerificationState::None,
+            VerificationState::InsertNullifiers,
+            VerificationState::Finalized,
+            VerificationState::Closed,
+        ] {
+            verification_account.set_state(&state);
+            assert_eq!(
+                cancel_delayed_send(&identifier, &mut verification_account, &clock, 0),
+                Err(ElusivError::InvalidAccountState.into())
+            );
+        }
+
+        // Failed verifications cannot be cancelled
+        verification_account.set_state(&VerificationState::ProofSetup);
+        verification_account.set_is_verified(&ElusivOption::Some(false));
+        assert_eq!(
+            cancel_delayed_send(&identifier, &mut verification_account, &clock, 0),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+
+        // Cancellation (also of already verified proofs or without a submitted proof)
+        for (state, is_verified) in [
+            (VerificationState::ProofSetup, ElusivOption::None),
+            (VerificationState::ProofSetup, ElusivOption::Some(true)),
+            (VerificationState::FeeTransferred, ElusivOption::None),
+        ] {
+            verification_account.set_state(&state);
+            verification_account.set_is_verified(&is_verified);
+            verification_account.set_fault(&ElusivOption::None);
+
+            assert_eq!(
+                cancel_delayed_send(&identifier, &mut verification_account, &clock, 0),
+                Ok(())
+            );
+            assert_eq!(
+                verification_account.get_is_verified(),
+                ElusivOption::Some(false)
+            );
+            assert_eq!(
+                verification_account.get_fault(),
+                ElusivOption::Some(VerificationFault::Cancelled)
+            );
+            assert_eq!(
+                verification_account.get_state().unwrap(),
+                VerificationState::ProofSetup
+            );
+        }
+    }
+
+    #[test]
+    fn test_expire_delayed_send() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        governor.set_verification_ttl_slots(&100);
+        verification_account.set_creation_slot(&1000);
+        verification_account.set_state(&VerificationState::ProofSetup);
+        verification_account.set_earliest_finalize_time(&5000);
+
+        // The TTL (100 slots, 40 seconds) starts again at `earliest_finalize_time`
+        let ttl_seconds = 100 * DEFAULT_MS_PER_SLOT as i64 / 1000;
+        for unix_timestamp in [0, 5000, 5000 + ttl_seconds] {
+            clock_account_info!(clock, 1101, unix_timestamp);
+            assert_eq!(
+                expire_verification(&mut verification_account, &governor, &clock, 0),
+                Err(ElusivError::ComputationInProgress.into())
+            );
+        }
+
+        clock_account_info!(clock, 1101, 5000 + ttl_seconds + 1);
+        assert_eq!(
+            expire_verification(&mut verification_account, &governor, &clock, 0),
+            Ok(())
+        );
+        assert_eq!(
+            verification_account.get_fault(),
+            ElusivOption::Some(VerificationFault::WardenFault)
+        );
+    }
+
+    #[test]
+    fn test_boost_verification() {
+        test_account_info!(booster0, 0);
+        test_account_info!(booster1, 0);
+        test_account_info!(pool, PoolAccount::SIZE);
+        account_info!(system_program, system_program::id(), vec![]);
+        zero_program_account!(mut verification_account, VerificationAccount);
+
+        // Uninitialized verification
+        assert_eq!(
+            boost_verification(
+                &booster0,
+                &pool,
+                &mut verification_account,
+                &system_program,
+                0,
+                1
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+
+        verification_account.set_state(&VerificationState::ProofSetup);
+
+        // Zero amount
+        assert_eq!(
+            boost_verification(
+                &booster0,
+                &pool,
+                &mut verification_account,
+                &system_program,
+                0,
+                0
+            ),
+            Err(ElusivError::InvalidAmount.into())
+        );
+
+        // Invalid system_program
+        assert_eq!(
+            boost_verification(&booster0, &pool, &mut verification_account, &pool, 0, 1),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        assert_eq!(verification_account.get_other_data().boost_count, 0);
+
+        // Boosts of different boosters accumulate
+        assert_eq!(
+            boost_verification(
+                &booster0,
+                &pool,
+                &mut verification_account,
+                &system_program,
+                0,
+                100
+            ),
+            Ok(())
+        );
+        verification_account.set_state(&VerificationState::Finalized);
+        assert_eq!(
+            boost_verification(
+                &booster1,
+                &pool,
+                &mut verification_account,
+                &system_program,
+                0,
+                23
+            ),
+            Ok(())
+        );
+
+        let data = verification_account.get_other_data();
+        assert_eq!(
+            data.active_boosts(),
+            [
+                VerificationBoost {
+                    booster: RawU256::new(booster0.key.to_bytes()),
+                    amount: Lamports(100),
+                },
+                VerificationBoost {
+                    booster: RawU256::new(booster1.key.to_bytes()),
+                    amount: Lamports(23),
+                },
+            ]
+        );
+        assert_eq!(data.total_boost(), Ok(Lamports(123)));
+
+        // Boost limit reached
+        assert_eq!(
+            boost_verification(
+                &booster0,
+                &pool,
+                &mut verification_account,
+                &system_program,
+                0,
+                1
+            ),
+            Err(ElusivError::TooManyBoosts.into())
+        );
+
+        // Closed verification
+        verification_account.set_state(&VerificationState::Closed);
+        assert_eq!(
+            boost_verification(
+                &booster0,
+                &pool,
+                &mut verification_account,
+                &system_program,
+                0,
+                1
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+    }
+
+    #[test]
+    fn test_compute_verification() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        vkey_account!(vkey, SendQuadraVKey);
+        vkey.set_version(&1);
+        test_account_info!(any, 0);
+
+        // Setup
+        let request = test_request();
+        let public_inputs = request.public_signals_skip_mr();
+        verification_account.set_request(&ProofRequest::Send(request));
+        for (i, &public_input) in public_inputs.iter().enumerate() {
+            verification_account.set_public_input(i, &RawU256::new(public_input));
+        }
+        let instructions = prepare_public_inputs_instructions(
+            &public_inputs,
+            SendQuadraVKey::public_inputs_count(),
+        );
+        verification_account.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
+        for (i, &ix) in instructions.iter().enumerate() {
+            verification_account.set_prepare_inputs_instructions(i, &(ix as u16));
+        }
+
+        // Computation is already finished (is_verified is Some)
+        verification_account.set_is_verified(&ElusivOption::Some(true));
+        assert_eq!(
+            compute_verification(
+                &any,
+                &mut verification_account,
+                &vkey,
+                &any,
+                &any,
+                &any,
+                0,
+                SendQuadraVKey::VKEY_ID
+            ),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+        verification_account.set_is_verified(&ElusivOption::None);
+
+        // Instructions count exceeding the worst case of the vkey
+        let max_instructions_count =
+            max_prepare_public_inputs_instructions(SendQuadraVKey::public_inputs_count());
+        verification_account
+            .set_prepare_inputs_instructions_count(&(max_instructions_count as u32 + 1));
+        assert_eq!(
+            compute_verification(
+                &any,
+                &mut verification_account,
+                &vkey,
+                &any,
+                &any,
+                &any,
+                0,
+                SendQuadraVKey::VKEY_ID
+            ),
+            Err(ElusivError::InputPreparationInstructionsExceeded.into())
+        );
+        assert_eq!(verification_account.get_instruction(), 0);
+        verification_account.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
+
+        // Success for public input preparation
+        for _ in 0..instructions.len() {
+            assert_eq!(
+                compute_verification(
+                    &any,
+                    &mut verification_account,
+                    &vkey,
+                    &any,
+                    &any,
+                    &any,
+                    0,
+                    SendQuadraVKey::VKEY_ID
+                ),
+                Ok(())
+            );
+        }
+
+        // Failure for miller loop (proof not setup)
+        assert_eq!(
+            compute_verification(
+                &any,
+                &mut verification_account,
+                &vkey,
+                &any,
+                &any,
+                &any,
+                0,
+                SendQuadraVKey::VKEY_ID
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+
+        let proof = test_proof();
+        verification_account.a.set(proof.a);
+        verification_account.b.set(proof.b);
+        verification_account.c.set(proof.c);
+        verification_account.set_state(&VerificationState::ProofSetup);
+
+        // Success
+        for _ in 0..COMBINED_MILLER_LOOP_IXS + FINAL_EXPONENTIATION_IXS {
+            assert_eq!(
+                compute_verification(
+                    &any,
+                    &mut verification_account,
+                    &vkey,
+                    &any,
+                    &any,
+                    &any,
+                    0,
+                    SendQuadraVKey::VKEY_ID
+                ),
+                Ok(())
+            );
+        }
+
+        // Computation is finished
+        assert_eq!(
+            compute_verification(
+                &any,
+                &mut verification_account,
+                &vkey,
+                &any,
+                &any,
+                &any,
+                0,
+                SendQuadraVKey::VKEY_ID
+            ),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+        assert_eq!(verification_account.get_is_verified().option(), Some(false));
+        assert_eq!(
+            verification_account.get_fault().option(),
+            Some(VerificationFault::UserFault)
+        );
+    }
+
+    #[test]
+    fn test_compute_verification_after_valid_result() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        vkey_account!(vkey, TestVKey);
+        vkey.set_version(&1);
+        test_account_info!(any, 0);
+
+        let proof = &valid_proofs()[0];
+        verification_account.set_vkey_id(&TestVKey::VKEY_ID);
+        verification_account.a.set(proof.proof.a);
+        verification_account.b.set(proof.proof.b);
+        verification_account.c.set(proof.proof.c);
+        verification_account.set_state(&VerificationState::ProofSetup);
+        for (i, &public_input) in proof.public_inputs.iter().enumerate() {
+            verification_account.set_public_input(i, &RawU256::new(public_input));
+        }
+        let instructions = prepare_public_inputs_instructions(
+            &proof.public_inputs,
+            TestVKey::public_inputs_count(),
+        );
+        verification_account
+            .setup_public_inputs_instructions(&instructions)
+            .unwrap();
+
+        // The inputs are prepared directly, since there is no request matching the test proof
+        vkey.execute_on_child_account_mut(0, |data| {
+            let vkey = VerifyingKey::new(data, TestVKey::public_inputs_count()).unwrap();
+            for _ in 0..instructions.len() {
+                verify_partial(
+                    &mut verification_account,
+                    &vkey,
+                    COMPUTE_VERIFICATION_IX_COUNT - 1,
+                )
+                .unwrap();
+            }
+        })
+        .unwrap();
+
+        for _ in 0..COMBINED_MILLER_LOOP_IXS + FINAL_EXPONENTIATION_IXS {
+            compute_verification(
+                &any,
+                &mut verification_account,
+                &vkey,
+                &any,
+                &any,
+                &any,
+                0,
+                TestVKey::VKEY_ID,
+            )
+            .unwrap();
+        }
+        assert_eq!(verification_account.get_is_verified().option(), Some(true));
+
+        // An additional call does not flip the result
+        assert_eq!(
+            compute_verification(
+                &any,
+                &mut verification_account,
+                &vkey,
+                &any,
+                &any,
+                &any,
+                0,
+                TestVKey::VKEY_ID
+            ),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+
+        // Even without a recorded result the computation is not advanced (nor failed)
+        verification_account.set_is_verified(&ElusivOption::None);
+        assert_eq!(
+            compute_verification(
+                &any,
+                &mut verification_account,
+                &vkey,
+                &any,
+                &any,
+                &any,
+                0,
+                TestVKey::VKEY_ID
+            ),
+            Err(ElusivError::ComputationIsAlreadyFinished.into())
+        );
+        assert_eq!(verification_account.get_is_verified().option(), None);
+        assert_eq!(verification_account.get_fault().option(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Public inputs do not match the request")]
+    fn test_compute_verification_corrupted_public_input() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        vkey_account!(vkey, SendQuadraVKey);
+        vkey.set_version(&1);
+        test_account_info!(any, 0);
+
+        let request = test_request();
+        let public_inputs = request.public_signals_skip_mr();
+        let instructions = prepare_public_inputs_instructions(
+            &public_inputs,
+            SendQuadraVKey::public_inputs_count(),
+        );
+        verification_account.set_request(&ProofRequest::Send(request));
+        verification_account
+            .setup_public_inputs_instructions(&instructions)
+            .unwrap();
+        for (i, &public_input) in public_inputs.iter().enumerate() {
+            verification_account.set_public_input(i, &RawU256::new(public_input));
+        }
+        assert!(verification_account.public_inputs_match_request().unwrap());
+
+        // Corrupted public input slot
+        let mut corrupted = public_inputs[2];
+        corrupted[0] ^= 1;
+        verification_account.set_public_input(2, &RawU256::new(corrupted));
+        assert!(!verification_account.public_inputs_match_request().unwrap());
+
+        compute_verification(
+            &any,
+            &mut verification_account,
+            &vkey,
+            &any,
+            &any,
+            &any,
+            0,
+            SendQuadraVKey::VKEY_ID,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_compute_verification_progress() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        vkey_account!(vkey, SendQuadraVKey);
+
+        let request = test_request();
+        let public_inputs = request.public_signals_skip_mr();
+        let instructions = prepare_public_inputs_instructions(
+            &public_inputs,
+            SendQuadraVKey::public_inputs_count(),
+        );
+        verification_account.set_request(&ProofRequest::Send(request));
+        verification_account
+            .setup_public_inputs_instructions(&instructions)
+            .unwrap();
+        for (i, &public_input) in public_inputs.iter().enumerate() {
+            verification_account.set_public_input(i, &RawU256::new(public_input));
+        }
+
+        let mut verify_partial_at = |instruction_index| {
+            vkey.execute_on_child_account_mut(0, |data| {
+                let vkey = VerifyingKey::new(data, SendQuadraVKey::public_inputs_count()).unwrap();
+                verify_partial(&mut verification_account, &vkey, instruction_index).unwrap();
+                computation_progress(&verification_account)
+            })
+            .unwrap()
+        };
+
+        let progress = verify_partial_at(0);
+
+        // Only the last compute-instruction of a transaction prepares the inputs
+        assert_eq!(
+            verify_partial_at(COMPUTE_VERIFICATION_IX_COUNT - 2),
+            progress
+        );
+        assert_ne!(
+            verify_partial_at(COMPUTE_VERIFICATION_IX_COUNT - 1),
+            progress
+        );
+    }
+
+    #[test]
+    fn test_settle_compute_verification_call() {
+        zero_program_account!(mut verification_account, VerificationAccount);
+        test_account_info!(warden, 0);
+        test_account_info!(pool, 0);
+        account_info!(sys, system_program::id());
+
+        let charge = Lamports(5_000);
+        let warden_balance = warden.lamports();
+        let pool_balance = pool.lamports();
+
+        // No-op calls are charged
+        for i in 1..=4 {
+            settle_compute_verification_call(
+                &warden,
+                &pool,
+                &sys,
+                &mut verification_account,
+                charge,
+                false,
+            )
+            .unwrap();
+            assert_eq!(
+                verification_account.get_pending_call_charges(),
+                Lamports(charge.0 * i)
+            );
+        }
+
+        // Invalid system program
+        assert_eq!(
+            settle_compute_verification_call(
+                &warden,
+                &pool,
+                &pool,
+                &mut verification_account,
+                charge,
+                false,
+            ),
+            Err(ProgramError::IncorrectProgramId)
+        );
+        assert_eq!(
+            verification_account.get_pending_call_charges(),
+            Lamports(charge.0 * 4)
+        );
+
+        // A productive call is refunded all pending charges
+        // - the charge transfers are system program CPIs (and therefore not executed in unit tests)
+        settle_compute_verification_call(
+            &warden,
+            &pool,
+            &sys,
+            &mut verification_account,
+            charge,
+            true,
+        )
+        .unwrap();
+        assert_eq!(verification_account.get_pending_call_charges(), Lamports(0));
+        assert_eq!(warden.lamports(), warden_balance + charge.0 * 4);
+        assert_eq!(pool.lamports(), pool_balance - charge.0 * 4);
+
+        // Nothing to refund
+        settle_compute_verification_call(
+            &warden,
+            &pool,
+            &sys,
+            &mut verification_account,
+            charge,
+            true,
+        )
+        .unwrap();
+        assert_eq!(warden.lamports(), warden_balance + charge.0 * 4);
+    }
+
+    macro_rules! finalize_send_test {
+        (
+            $token_id: expr,
+            $optional_fee: expr,
+            $public_inputs: ident,
+            $v_data: ident,
+            $recipient: ident,
+            $identifier: ident,
+            $reference: ident,
+            $finalize_data: ident
+        ) => {
+            finalize_send_test!(
+                $token_id,
+                0,
+                $optional_fee,
+                $public_inputs,
+                $v_data,
+                $recipient,
+                $identifier,
+                $reference,
+                $finalize_data,
+                _optional_fee_collector
+            )
+        };
+        (
+            $token_id: expr,
+            $amount: expr,
+            $optional_fee: expr,
+            $public_inputs: ident,
+            $v_data: ident,
+            $recipient: ident,
+            $identifier: ident,
+            $reference: ident,
+            $finalize_data: ident,
+            $optional_fee_collector: ident
+        ) => {
+            let $recipient = Pubkey::new_unique().to_bytes();
+            let $identifier = Pubkey::new_unique().to_bytes();
+            let $reference = Pubkey::new_unique().to_bytes();
+            let iv = Pubkey::new_unique().to_bytes();
+            let encrypted_owner = Pubkey::new_unique().to_bytes();
+
+            let metadata = CommitmentMetadata::default();
+            let $optional_fee_collector = Pubkey::new_unique();
+            let optional_fee = OptionalFee {
+                collector: $optional_fee_collector,
+                amount: $optional_fee,
+            };
+            let $public_inputs = SendPublicInputs {
+                join_split: JoinSplitPublicInputs {
+                    input_commitments: vec![InputCommitment {
+                        root: Some(empty_root_raw()),
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                    }],
+                    output_commitment: RawU256::new(u256_from_str_skip_mr("987654321")),
+                    recent_commitment_index: 123,
+                    fee_version: 0,
+                    amount: $amount,
+                    fee: 10000,
+                    optional_fee: optional_fee.clone(),
+                    token_id: $token_id,
+                    metadata,
+                },
+                recipient_is_associated_token_account: false,
+                hashed_inputs: generate_hashed_inputs(
+                    &$recipient,
+                    &$identifier,
+                    &iv,
+                    &encrypted_owner,
+                    &$reference,
+                    false,
+                    &metadata,
+                    &optional_fee,
+                    &None,
+                    &None,
+                    0,
+                ),
+                solana_pay_transfer: false,
+            };
+
+            let mut $v_data = vec![0; VerificationAccount::SIZE];
+            let mut v_account = VerificationAccount::new(&mut $v_data).unwrap();
+            let fee_payer = RawU256::new(Pubkey::new_unique().to_bytes());
+            v_account
+                .setup(
+                    fee_payer,
+                    ElusivOption::None,
+                    false,
+                    &[],
+                    &vec![0],
+                    0,
+                    ProofRequest::Send($public_inputs.clone()),
+                    [0, 1],
+                    [0; 16],
+                )
+                .unwrap();
+            v_account.set_state(&VerificationState::ProofSetup);
+            v_account.set_is_verified(&ElusivOption::Some(true));
+            v_account.set_other_data(&VerificationAccountData {
+                fee_payer,
+                fee_payer_account: fee_payer,
+                recipient_wallet: ElusivOption::Some(RawU256::new($recipient)),
+                token_id: $token_id,
+                ..Default::default()
+            });
+
+            let $finalize_data = FinalizeSendData {
+                total_amount: $public_inputs.join_split.total_amount(),
+                token_id: $token_id,
+                mt_index: 0,
+                commitment_index: 0,
+                encrypted_owner,
+                iv,
+            };
+        };
+    }
+
+    macro_rules! simple_storage_account {
+        ($id: ident) => {
+            let mut data = vec![0; StorageAccount::SIZE];
+            let $id =
+                <StorageAccount as elusiv_types::accounts::ProgramAccount>::new(&mut data).unwrap();
+        };
+    }
+
+    #[test]
+    fn test_finalize_verification_send_valid() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        let mut queue = CommitmentQueueAccount::zeroed();
+        simple_storage_account!(storage);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+        test_account_info!(any, 0);
+
+        // Proof is not submitted yet
+        for state in [VerificationState::None, VerificationState::FeeTransferred] {
+            verification_acc.set_state(&state);
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data.clone(),
+                    false,
+                ),
+                Err(ElusivError::ProofNotSubmitted.into())
+            );
+        }
+
+        // Already finalized or closed
+        for state in [
+            VerificationState::InsertNullifiers,
+            VerificationState::Finalized,
+            VerificationState::Closed,
+        ] {
+            verification_acc.set_state(&state);
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data.clone(),
+                    false,
+                ),
+                Err(ElusivError::InvalidAccountState.into())
+            );
+        }
+        verification_acc.set_state(&VerificationState::ProofSetup);
+
+        // Verification is not finished (before and during the computation)
+        verification_acc.set_is_verified(&ElusivOption::None);
+        for round in [0, 1] {
+            verification_acc.set_round(&round);
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data.clone(),
+                    false,
+                ),
+                Err(ElusivError::ComputationInProgress.into())
+            );
+        }
+        verification_acc.set_round(&0);
+
+        verification_acc.set_is_verified(&ElusivOption::Some(true));
+
+        // Invalid recipient
+        {
+            account_info!(recipient, Pubkey::new_from_array(identifier_bytes));
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data.clone(),
+                    false,
+                ),
+                Err(ElusivError::InputsMismatch.into())
+            );
+        }
+
+        // Invalid identifier
+        {
+            account_info!(identifier, Pubkey::new_from_array(recipient_bytes));
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data.clone(),
+                    false,
+                ),
+                Err(ElusivError::InputsMismatch.into())
+            );
+        }
+
+        // Invalid reference
+        {
+            account_info!(reference, Pubkey::new_from_array(recipient_bytes));
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data.clone(),
+                    false,
+                ),
+                Err(ElusivError::InputsMismatch.into())
+            );
+        }
+
+        // Writable or signing key-only accounts
+        for (writable, signer) in [(true, false), (false, true), (true, true)] {
+            let mut key_only = identifier.clone();
+            key_only.is_writable = writable;
+            key_only.is_signer = signer;
+
+            for (identifier, reference) in [(&key_only, &reference), (&identifier, &key_only)] {
+                assert_eq!(
+                    finalize_verification_send(
+                        &recipient,
+                        identifier,
+                        reference,
+                        &mut queue,
+                        &mut verification_acc,
+                        &storage,
+                        &mut buffer,
+                        &any,
+                        UnverifiedAccountInfo::new(&any),
+                        &any,
+                        0,
+                        finalize_data.clone(),
+                        false,
+                    ),
+                    Err(ElusivError::WritableOrSigningKeyOnlyAccount.into())
+                );
+            }
+        }
+
+        // Invalid finalize_data
+        for invalid_data in [
+            mutate(&finalize_data, |d| {
+                d.total_amount = public_inputs.join_split.amount
+            }),
+            mutate(&finalize_data, |d| d.token_id = 0),
+            mutate(&finalize_data, |d| d.commitment_index = 1),
+            mutate(&finalize_data, |d| d.mt_index = 1),
+            mutate(&finalize_data, |d| d.encrypted_owner = d.iv),
+            mutate(&finalize_data, |d| d.iv = d.encrypted_owner),
+        ] {
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    invalid_data,
+                    false,
+                ),
+                Err(ElusivError::InputsMismatch.into())
+            );
+        }
+
+        // The validated token_id is stored for the transfer
+        verification_acc.set_other_data(&mutate(&verification_acc.get_other_data(), |d| {
+            d.token_id = LAMPORTS_TOKEN_ID
+        }));
+
+        // Key-only accounts do not need to exist
+        **identifier.try_borrow_mut_lamports().unwrap() = 0;
+        **reference.try_borrow_mut_lamports().unwrap() = 0;
+
+        // Success
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data.clone(),
+                false,
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            verification_acc.get_state().unwrap(),
+            VerificationState::InsertNullifiers
+        );
+        assert_eq!(verification_acc.get_other_data().token_id, USDC_TOKEN_ID);
+
+        // Called twice
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+    }
+
+    macro_rules! set_zero_recipient {
+        ($verification_acc: ident, $public_inputs: ident, $identifier: ident, $reference: ident, $finalize_data: ident) => {
+            $verification_acc.set_request(&ProofRequest::Send(SendPublicInputs {
+                hashed_inputs: generate_hashed_inputs(
+                    &[0; 32],
+                    &$identifier,
+                    &$finalize_data.iv,
+                    &$finalize_data.encrypted_owner,
+                    &$reference,
+                    false,
+                    &$public_inputs.join_split.metadata,
+                    &$public_inputs.join_split.optional_fee,
+                    &None,
+                    &None,
+                    0,
+                ),
+                ..$public_inputs.clone()
+            }));
+        };
+    }
+
+    #[test]
+    fn test_finalize_verification_send_zero_recipient() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            0,
+            public_inputs,
+            verification_acc_data,
+            _recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data,
+            _optional_fee_collector
+        );
+        assert!(!public_inputs.is_merge());
+
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        let mut queue = CommitmentQueueAccount::zeroed();
+        simple_storage_account!(storage);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        test_account_info!(any, 0);
+
+        account_info!(recipient, Pubkey::new_from_array([0; 32]));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+
+        set_zero_recipient!(
+            verification_acc,
+            public_inputs,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data.clone(),
+                false,
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // An invalid proof can still be finalized
+        verification_acc.set_is_verified(&ElusivOption::Some(false));
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_finalize_verification_send_encrypted_note() -> ProgramResult {
+        let (note_log_pda, note_log_bump) = NoteLogAccount::find(None);
+        let mut note_log_data = vec![0; NoteLogAccount::SIZE];
+        note_log_data[0] = note_log_bump;
+        account_info!(note_log, note_log_pda, note_log_data);
+        account_info!(
+            invalid_note_log,
+            Pubkey::new_unique(),
+            vec![0; NoteLogAccount::SIZE]
+        );
+        let encrypted_note = [42; ENCRYPTED_NOTE_SIZE];
+
+        for with_note in [false, true] {
+            finalize_send_test!(
+                USDC_TOKEN_ID,
+                LAMPORTS_PER_SOL,
+                0,
+                public_inputs,
+                verification_acc_data,
+                recipient_bytes,
+                identifier_bytes,
+                reference_bytes,
+                finalize_data,
+                _optional_fee_collector
+            );
+
+            let mut verification_acc =
+                VerificationAccount::new(&mut verification_acc_data).unwrap();
+            let mut queue = CommitmentQueueAccount::zeroed();
+            simple_storage_account!(storage);
+            zero_program_account!(mut buffer, CommitmentBufferAccount);
+            test_account_info!(any, 0);
+
+            account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+            account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+            account_info!(reference, Pubkey::new_from_array(reference_bytes));
+
+            if with_note {
+                verification_acc.set_encrypted_note(&ElusivOption::Some(encrypted_note));
+
+                // The note has to be bound by the hashed inputs
+                assert_eq!(
+                    finalize_verification_send(
+                        &recipient,
+                        &identifier,
+                        &reference,
+                        &mut queue,
+                        &mut verification_acc,
+                        &storage,
+                        &mut buffer,
+                        &any,
+                        UnverifiedAccountInfo::new(&note_log),
+                        &any,
+                        0,
+                        finalize_data.clone(),
+                        false,
+                    ),
+                    Err(ElusivError::InputsMismatch.into())
+                );
+
+                verification_acc.set_request(&ProofRequest::Send(SendPublicInputs {
+                    hashed_inputs: generate_hashed_inputs(
+                        &recipient_bytes,
+                        &identifier_bytes,
+                        &finalize_data.iv,
+                        &finalize_data.encrypted_owner,
+                        &reference_bytes,
+                        false,
+                        &public_inputs.join_split.metadata,
+                        &public_inputs.join_split.optional_fee,
+                        &None,
+                        &Some(encrypted_note),
+                        0,
+                    ),
+                    ..public_inputs.clone()
+                }));
+
+                // Invalid note_log
+                assert_eq!(
+                    finalize_verification_send(
+                        &recipient,
+                        &identifier,
+                        &reference,
+                        &mut queue,
+                        &mut verification_acc,
+                        &storage,
+                        &mut buffer,
+                        &any,
+                        UnverifiedAccountInfo::new(&invalid_note_log),
+                        &any,
+                        0,
+                        finalize_data.clone(),
+                        false,
+                    ),
+                    Err(ProgramError::InvalidSeeds)
+                );
+            }
+
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&note_log),
+                    &any,
+                    0,
+                    finalize_data,
+                    false,
+                ),
+                Ok(())
+            );
+
+            pda_account!(mut note_log, NoteLogAccount, note_log);
+            let note_log = NoteLog::new(&mut note_log);
+            if with_note {
+                assert_eq!(
+                    note_log.entries(),
+                    vec![NoteLogEntry {
+                        commitment: public_inputs.join_split.output_commitment.reduce(),
+                        encrypted_note,
+                    }]
+                );
+            } else {
+                assert!(note_log.is_empty());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_send_data_validate() {
+        let data = FinalizeSendData {
+            token_id: SPL_TOKEN_COUNT as u16,
+            mt_index: MAX_TREES - 1,
+            commitment_index: MT_COMMITMENT_COUNT as u32 - 1,
+            ..Default::default()
+        };
+        assert_eq!(data.validate(), Ok(()));
+
+        assert_eq!(
+            mutate(&data, |d| d.token_id += 1).validate(),
+            Err(ElusivError::UnsupportedToken.into())
+        );
+        assert_eq!(
+            mutate(&data, |d| d.mt_index += 1).validate(),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+        assert_eq!(
+            mutate(&data, |d| d.commitment_index += 1).validate(),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+    }
+
+    #[test]
+    fn test_finalize_verification_send_invalid_data() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        let mut queue = CommitmentQueueAccount::zeroed();
+        simple_storage_account!(storage);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        test_account_info!(any, 0);
+
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+
+        // Out-of-range values are rejected before they are matched against the proof
+        for (data, err) in [
+            (
+                mutate(&finalize_data, |d| d.token_id = u16::MAX),
+                ElusivError::UnsupportedToken,
+            ),
+            (
+                mutate(&finalize_data, |d| d.commitment_index = u32::MAX),
+                ElusivError::InvalidInstructionData,
+            ),
+            (
+                mutate(&finalize_data, |d| d.mt_index = u32::MAX),
+                ElusivError::InvalidInstructionData,
+            ),
+        ] {
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    data,
+                    false,
+                ),
+                Err(err.into())
+            );
+        }
+
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_finalize_verification_send_delayed() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            0,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data,
+            _optional_fee_collector
+        );
+
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        let mut queue = CommitmentQueueAccount::zeroed();
+        simple_storage_account!(storage);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        test_account_info!(any, 0);
+
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+        clock_account_info!(clock, 0, 999);
+        clock_account_info!(later_clock, 0, 1000);
+
+        verification_acc.set_earliest_finalize_time(&1000);
+        verification_acc.set_delay_identifier(&identifier_bytes);
+
+        // The delay has to be bound by the hashed inputs
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &later_clock,
+                0,
+                finalize_data.clone(),
+                false,
+            ),
+            Err(ElusivError::InputsMismatch.into())
+        );
+
+        verification_acc.set_request(&ProofRequest::Send(SendPublicInputs {
+            hashed_inputs: generate_hashed_inputs(
+                &recipient_bytes,
+                &identifier_bytes,
+                &finalize_data.iv,
+                &finalize_data.encrypted_owner,
+                &reference_bytes,
+                false,
+                &public_inputs.join_split.metadata,
+                &public_inputs.join_split.optional_fee,
+                &None,
+                &None,
+                1000,
+            ),
+            ..public_inputs
+        }));
+
+        // The cancellation authority has to be the identifier of the proof
+        verification_acc.set_delay_identifier(&[1; 32]);
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &later_clock,
+                0,
+                finalize_data.clone(),
+                false,
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+        verification_acc.set_delay_identifier(&identifier_bytes);
+
+        // Before `earliest_finalize_time`
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &clock,
+                0,
+                finalize_data.clone(),
+                false,
+            ),
+            Err(ElusivError::FinalizationDelayed.into())
+        );
+
+        // Invalid clock
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data.clone(),
+                false,
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &later_clock,
+                0,
+                finalize_data,
+                false,
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            verification_acc.get_state().unwrap(),
+            VerificationState::InsertNullifiers
+        );
+    }
+
+    #[test]
+    fn test_finalize_verification_send_recipient_rent_exemption() {
+        let rent = Rent::default().minimum_balance(0);
+
+        let cases = [
+            // Fresh recipient below the rent-exemption
+            (0, rent - 1, Err(ElusivError::RecipientBelowRentExemption)),
+            // Fresh recipient exactly at the rent-exemption
+            (0, rent, Ok(())),
+            // Existing rent-exempt recipient
+            (rent, 1, Ok(())),
+            // Existing recipient that remains below the rent-exemption
+            (1, rent - 2, Err(ElusivError::RecipientBelowRentExemption)),
+            (1, rent - 1, Ok(())),
+        ];
+
+        for (recipient_lamports, amount, result) in cases {
+            finalize_send_test!(
+                LAMPORTS_TOKEN_ID,
+                amount,
+                0,
+                _public_inputs,
+                verification_acc_data,
+                recipient_bytes,
+                identifier_bytes,
+                reference_bytes,
+                finalize_data,
+                _optional_fee_collector
+            );
+
+            let mut verification_acc =
+                VerificationAccount::new(&mut verification_acc_data).unwrap();
+            let mut queue = CommitmentQueueAccount::zeroed();
+            simple_storage_account!(storage);
+            zero_program_account!(mut buffer, CommitmentBufferAccount);
+            test_account_info!(any, 0);
+
+            account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+            account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+            account_info!(reference, Pubkey::new_from_array(reference_bytes));
+            **recipient.try_borrow_mut_lamports().unwrap() = recipient_lamports;
+
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut queue,
+                    &mut verification_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data,
+                    false,
+                ),
+                result.map_err(|e| e.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_finalize_verification_merge_zero_recipient() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            0,
+            public_inputs,
+            verification_acc_data,
+            _recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+        assert!(public_inputs.is_merge());
+
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        let mut queue = CommitmentQueueAccount::zeroed();
+        simple_storage_account!(storage);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        test_account_info!(any, 0);
+
+        account_info!(recipient, Pubkey::new_from_array([0; 32]));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+
+        set_zero_recipient!(
+            verification_acc,
+            public_inputs,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            verification_acc.get_state().unwrap(),
+            VerificationState::InsertNullifiers
+        );
+    }
+
+    #[test]
+    fn test_finalize_verification_send_invalid() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        let mut queue = CommitmentQueueAccount::zeroed();
+        simple_storage_account!(storage);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        test_account_info!(any, 0);
+
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+
+        verification_acc.set_is_verified(&ElusivOption::Some(false));
+
+        assert_eq!(
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut queue,
+                &mut verification_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            verification_acc.get_state().unwrap(),
+            VerificationState::Finalized
+        );
+    }
+
+    #[test]
+    fn test_expire_verification_settlement() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        finalize_send_test!(
+            LAMPORTS_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+
+        let fee_payer_pk = Pubkey::new(
+            &VerificationAccount::new(&mut verification_acc_data)
+                .unwrap()
+                .get_other_data()
+                .fee_payer
+                .skip_mr(),
+        );
+        account_info!(f, fee_payer_pk); // fee_payer
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        test_account_info!(any, 0);
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
+        );
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        simple_storage_account!(storage);
+        clock_account_info!(clock, 1000);
+        governor.set_verification_ttl_slots(&100);
+        CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+
+        // Stalled computation
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_is_verified(&ElusivOption::None);
+            v_acc.set_creation_slot(&899);
+
+            expire_verification(&mut v_acc, &governor, &clock, 0)?;
+
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut commitment_queue,
+                &mut v_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
+            )?;
+            assert_eq!(v_acc.get_state().unwrap(), VerificationState::Finalized);
+        }
+
+        let fee_collector_lamports = fee_collector.lamports() + v_acc.lamports() + n_pda.lamports();
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
                 &fee_collector,
-                &fee_collector_token,
-                &sol,
-                &usdc,
+                &any,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
                 &governor,
-                &mut verification_acc,
-                &spl,
-                &sys,
+                &warden_rewards,
+                &clock,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
-            Err(ElusivError::InvalidAccount.into())
+            Ok(())
+        );
+
+        // The nullifier-duplicate-account is closed and the nullifiers are free again
+        assert_eq!(n_pda.lamports(), 0);
+        assert_eq!(v_acc.lamports(), 0);
+        assert_eq!(fee_collector.lamports(), fee_collector_lamports);
+
+        pda_account!(v_acc, VerificationAccount, v_acc);
+        assert_eq!(v_acc.get_state().unwrap(), VerificationState::Closed);
+
+        let commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        assert_eq!(commitment_queue.pending_commitments(), 0);
+        assert!(commitment_queue.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_verification_state_fails_closed() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        finalize_send_test!(
+            LAMPORTS_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            identifier_bytes,
+            reference_bytes,
+            finalize_data
+        );
+
+        let fee_payer_pk = Pubkey::new(
+            &VerificationAccount::new(&mut verification_acc_data)
+                .unwrap()
+                .get_other_data()
+                .fee_payer
+                .skip_mr(),
+        );
+        account_info!(f, fee_payer_pk); // fee_payer
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
+        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+        account_info!(authority, crate::id());
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        test_account_info!(any, 0);
+        account_info!(sys, system_program::id());
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
         );
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        parent_account!(mut nullifier_account, NullifierAccount);
+        vkey_account!(vkey, SendQuadraVKey);
+        vkey.set_version(&1);
+        simple_storage_account!(storage);
+        clock_account_info!(clock, 1000);
+        CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+
+        // A state introduced by a newer program version
+        let unknown_state = VerificationState::Closed as u8 + 1;
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_state_discriminant(unknown_state);
+            v_acc.set_is_verified(&ElusivOption::None);
+            v_acc.set_creation_slot(&899);
+
+            let unsupported = Err(ElusivError::UnsupportedAccountState.into());
+            assert_eq!(
+                init_verification_transfer_fee(
+                    &f,
+                    &f,
+                    &pool,
+                    &pool,
+                    &fee_collector,
+                    &fee_collector,
+                    &any,
+                    &any,
+                    &governor,
+                    &mut commitment_queue,
+                    &mut v_acc,
+                    &sys,
+                    &sys,
+                    &mut fee_version_usage,
+                    &clock,
+                    0,
+                ),
+                unsupported
+            );
+            assert_eq!(
+                init_verification_proof(&f, &mut v_acc, 0, test_proof()),
+                unsupported
+            );
+            assert_eq!(
+                boost_verification(&f, &pool, &mut v_acc, &sys, 0, 1),
+                unsupported
+            );
+            governor.set_verification_ttl_slots(&100);
+            assert_eq!(
+                expire_verification(&mut v_acc, &governor, &clock, 0),
+                unsupported
+            );
+            v_acc.set_vkey_id(&SendQuadraVKey::VKEY_ID);
+            assert_eq!(
+                compute_verification(
+                    &any,
+                    &mut v_acc,
+                    &vkey,
+                    &any,
+                    &any,
+                    &any,
+                    0,
+                    SendQuadraVKey::VKEY_ID
+                ),
+                unsupported
+            );
+            assert_eq!(
+                finalize_verification_send(
+                    &recipient,
+                    &identifier,
+                    &reference,
+                    &mut commitment_queue,
+                    &mut v_acc,
+                    &storage,
+                    &mut buffer,
+                    &any,
+                    UnverifiedAccountInfo::new(&any),
+                    &any,
+                    0,
+                    finalize_data.clone(),
+                    false,
+                ),
+                unsupported
+            );
+            assert_eq!(
+                finalize_verification_insert_nullifier(&mut v_acc, &mut nullifier_account, 0),
+                unsupported
+            );
+            assert_eq!(v_acc.get_is_verified(), ElusivOption::None);
+        }
 
-        // Invalid pool_account
         assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &token_acc,
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
                 &pool,
-                &fee_collector_token,
                 &fee_collector,
-                &fee_collector_token,
-                &sol,
-                &usdc,
+                &any,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
                 &governor,
-                &mut verification_acc,
-                &spl,
-                &sys,
+                &warden_rewards,
+                &clock,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
-            Err(ElusivError::InvalidAccount.into())
+            Err(ElusivError::UnsupportedAccountState.into())
         );
-
-        // Invalid fee_collector_account
         assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &token_acc,
+            finalize_verification_transfer_token(
+                &f,
+                &f,
+                &f,
+                &recipient,
+                &recipient,
+                &pool,
                 &pool,
-                &pool_token,
                 &fee_collector,
-                &pool_token,
-                &sol,
-                &usdc,
+                &fee_collector,
+                &any,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &any,
+                &any,
+                &any,
+                &any,
                 &governor,
-                &mut verification_acc,
-                &spl,
-                &sys,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
-            Err(ElusivError::InvalidAccount.into())
+            Err(ElusivError::UnsupportedAccountState.into())
         );
 
-        // Invalid sol_usd_price_account
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+
+            // Only the program authority can settle unknown states
+            assert_eq!(
+                force_settle_unknown_state(&f, &mut v_acc, &governor, &clock, 0),
+                Err(ElusivError::InvalidAccount.into())
+            );
+
+            // Timelock
+            clock_account_info!(early_clock, 999);
+            assert_eq!(
+                force_settle_unknown_state(&authority, &mut v_acc, &governor, &early_clock, 0),
+                Err(ElusivError::ComputationInProgress.into())
+            );
+
+            // Unknown requests cannot be settled
+            let request = v_acc.get_request().unwrap();
+            v_acc.set_request_discriminant(2);
+            assert_eq!(
+                force_settle_unknown_state(&authority, &mut v_acc, &governor, &clock, 0),
+                Err(ElusivError::UnsupportedAccountState.into())
+            );
+            v_acc.set_request(&request);
+
+            force_settle_unknown_state(&authority, &mut v_acc, &governor, &clock, 0)?;
+            assert_eq!(v_acc.get_is_verified(), ElusivOption::Some(false));
+            assert_eq!(
+                v_acc.get_fault(),
+                ElusivOption::Some(VerificationFault::Indeterminate)
+            );
+
+            // Known states are not affected
+            assert_eq!(
+                force_settle_unknown_state(&authority, &mut v_acc, &governor, &clock, 0),
+                Err(ElusivError::InvalidAccountState.into())
+            );
+
+            // Settled as a failed proof
+            finalize_verification_send(
+                &recipient,
+                &identifier,
+                &reference,
+                &mut commitment_queue,
+                &mut v_acc,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
+            )?;
+            assert_eq!(v_acc.get_state().unwrap(), VerificationState::Finalized);
+        }
+
+        finalize_verification_transfer_lamports(
+            &f,
+            &f,
+            &recipient,
+            &pool,
+            &fee_collector,
+            &any,
+            &mut commitment_queue,
+            &mut metadata_queue,
+            &v_acc,
+            &n_pda,
+            &any,
+            &any,
+            &governor,
+            &warden_rewards,
+            &clock,
+            &any,
+            &any,
+            &mut fee_version_usage,
+            0,
+        )?;
+        assert_eq!(n_pda.lamports(), 0);
+        assert_eq!(v_acc.lamports(), 0);
+
+        pda_account!(v_acc, VerificationAccount, v_acc);
+        assert_eq!(v_acc.get_state().unwrap(), VerificationState::Closed);
+        assert!(CommitmentQueue::new(&mut commitment_queue).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_verification_migrate() {
+        let migrate_public_inputs = MigratePublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 123,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 10000,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            current_nsmt_root: RawU256::new([0; 32]),
+            next_nsmt_root: RawU256::new([0; 32]),
+        };
+
+        let pk = Pubkey::new_unique();
+        account_info!(acc, pk);
+
+        let mut v_account = VerificationAccount::zeroed();
+        v_account.set_request(&ProofRequest::Migrate(migrate_public_inputs));
+        v_account.set_state(&VerificationState::ProofSetup);
+        v_account.set_is_verified(&ElusivOption::Some(true));
+
+        let mut queue = CommitmentQueueAccount::zeroed();
+
+        let finalize_data = FinalizeSendData::default();
+        simple_storage_account!(storage);
+        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        test_account_info!(any, 0);
+
         assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &token_acc,
-                &pool,
-                &pool_token,
-                &fee_collector,
-                &fee_collector_token,
-                &usdc,
-                &usdc,
-                &governor,
-                &mut verification_acc,
-                &spl,
-                &sys,
-                0
+            finalize_verification_send(
+                &acc,
+                &acc,
+                &acc,
+                &mut queue,
+                &mut v_account,
+                &storage,
+                &mut buffer,
+                &any,
+                UnverifiedAccountInfo::new(&any),
+                &any,
+                0,
+                finalize_data,
+                false,
             ),
-            Err(TokenError::InvalidPriceAccount.into())
+            Err(ElusivError::FeatureNotAvailable.into())
         );
+    }
 
-        // Invalid token_usd_price_account
-        assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &token_acc,
-                &pool,
-                &pool_token,
-                &fee_collector,
-                &fee_collector_token,
-                &sol,
-                &sol,
-                &governor,
-                &mut verification_acc,
-                &spl,
-                &sys,
-                0
-            ),
-            Err(TokenError::InvalidPriceAccount.into())
+    #[test]
+    fn test_finalize_verification_insert_nullifier() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            public_inputs,
+            verification_acc_data,
+            _recipient_bytes,
+            _identifier_bytes,
+            _reference_bytes,
+            _finalize_data
+        );
+
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        parent_account!(mut n_acc_0, NullifierAccount);
+
+        // finalize_verification_send not called
+        verification_acc.set_state(&VerificationState::InsertNullifiers);
+
+        // Nullifier duplicate
+        n_acc_0
+            .try_insert_nullifier_hash(
+                public_inputs.join_split.input_commitments[0]
+                    .nullifier_hash
+                    .reduce(),
+            )
+            .unwrap();
+        assert_eq!(
+            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_0, 0),
+            Err(ElusivError::CouldNotInsertNullifier.into())
         );
 
+        parent_account!(mut n_acc_0, NullifierAccount);
+
+        // Success
         assert_eq!(
-            init_verification_transfer_fee(
-                &fee_payer,
-                &token_acc,
-                &pool,
-                &pool_token,
-                &fee_collector,
-                &fee_collector_token,
-                &sol,
-                &usdc,
-                &governor,
-                &mut verification_acc,
-                &spl,
-                &sys,
-                0
-            ),
+            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_0, 0),
             Ok(())
         );
 
+        assert!(!n_acc_0
+            .can_insert_nullifier_hash(
+                public_inputs.join_split.input_commitments[0]
+                    .nullifier_hash
+                    .reduce()
+            )
+            .unwrap());
         assert_eq!(
-            verification_acc.get_state(),
-            VerificationState::FeeTransferred
+            verification_acc.get_state().unwrap(),
+            VerificationState::Finalized
         );
-    }
 
-    #[test]
-    fn test_init_verification_proof() {
-        let proof = test_proof();
-        let valid_pk = Pubkey::new(&[0; 32]);
-        account_info!(fee_payer, valid_pk, vec![0; 0]);
-        zero_program_account!(mut verification_account, VerificationAccount);
-
-        // Account setup
-        verification_account.set_state(&VerificationState::ProofSetup);
+        // Called twice
         assert_eq!(
-            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_0, 0),
             Err(ElusivError::InvalidAccountState.into())
         );
-        verification_account.set_state(&VerificationState::FeeTransferred);
 
-        // Computation already finished
-        verification_account.set_is_verified(&ElusivOption::Some(true));
+        // Closed
+        verification_acc.set_state(&VerificationState::Closed);
         assert_eq!(
-            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
-            Err(ElusivError::ComputationIsAlreadyFinished.into())
+            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_0, 0),
+            Err(ElusivError::InvalidAccountState.into())
         );
-        verification_account.set_is_verified(&ElusivOption::Some(false));
-        assert_eq!(
-            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
-            Err(ElusivError::ComputationIsAlreadyFinished.into())
+    }
+
+    #[test]
+    fn test_finalize_verification_insert_nullifier_max_arity() {
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            public_inputs,
+            verification_acc_data,
+            _recipient_bytes,
+            _identifier_bytes,
+            _reference_bytes,
+            _finalize_data
         );
-        verification_account.set_is_verified(&ElusivOption::None);
 
-        // Invalid fee_payer
-        let invalid_pk = Pubkey::new_unique();
-        account_info!(invalid_fee_payer, invalid_pk, vec![0; 0]);
+        // `JOIN_SPLIT_MAX_N_ARITY` input commitments spanning two MTs
+        let mut public_inputs = public_inputs;
+        public_inputs.join_split.input_commitments = vec![
+            InputCommitment {
+                root: Some(empty_root_raw()),
+                nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+            },
+            InputCommitment {
+                root: None,
+                nullifier_hash: RawU256::new(u256_from_str_skip_mr("2")),
+            },
+            InputCommitment {
+                root: Some(RawU256::new(u256_from_str_skip_mr("123"))),
+                nullifier_hash: RawU256::new(u256_from_str_skip_mr("4")),
+            },
+            InputCommitment {
+                root: None,
+                nullifier_hash: RawU256::new(u256_from_str_skip_mr("3")),
+            },
+        ];
         assert_eq!(
-            init_verification_proof(&invalid_fee_payer, &mut verification_account, 0, proof),
-            Err(ElusivError::InvalidAccount.into())
+            public_inputs.join_split.input_commitments.len(),
+            JOIN_SPLIT_MAX_N_ARITY
         );
 
-        // Success
+        // Nullifier-hashes are inserted MT by MT
+        let insert = |slot, v: &str| NullifierInsertionStep::Insert {
+            slot: LocalTreeSlot(slot),
+            nullifier_hash: RawU256::new(u256_from_str_skip_mr(v)).reduce(),
+        };
+        let move_nullifier_hashes = |slot| NullifierInsertionStep::MoveNullifierHashes {
+            slot: LocalTreeSlot(slot),
+        };
         assert_eq!(
-            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
-            Ok(())
-        );
+            nullifier_insertion_steps(&public_inputs.join_split),
+            vec![
+                insert(0, "1"),
+                insert(0, "2"),
+                insert(0, "3"),
+                move_nullifier_hashes(0),
+                insert(1, "4"),
+                move_nullifier_hashes(1),
+            ]
+        );
+
+        // The nullifier-duplicate PDA uses a single hashed seed
+        let (pda, _) = public_inputs.join_split.nullifier_duplicate_pda();
         assert_eq!(
-            verification_account.get_state(),
-            VerificationState::ProofSetup
+            pda,
+            NullifierDuplicateAccount::find_with_pubkey(
+                NullifierDuplicateAccount::associated_pubkey(
+                    &public_inputs
+                        .join_split
+                        .input_commitments
+                        .iter()
+                        .map(|c| &c.nullifier_hash)
+                        .collect::<Vec<_>>()
+                ),
+                None
+            )
+            .0
         );
-        assert_eq!(verification_account.a.get(), proof.a);
-        assert_eq!(verification_account.b.get(), proof.b);
-        assert_eq!(verification_account.c.get(), proof.c);
 
-        // Already setup proof
+        // The request fits into the verification account
+        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+        let request = ProofRequest::Send(public_inputs.clone());
+        verification_acc.set_request(&request);
+        verification_acc.set_tree_indices(0, &5);
+        verification_acc.set_tree_indices(1, &2);
+        assert_eq!(verification_acc.get_request().unwrap(), request);
+
+        parent_account!(mut n_acc_5, NullifierAccount);
+        parent_account!(mut n_acc_2, NullifierAccount);
+
+        verification_acc.set_state(&VerificationState::InsertNullifiers);
+        verification_acc.set_instruction(&0);
+
+        let mut tree_indices = Vec::new();
+        while verification_acc.get_state().unwrap() == VerificationState::InsertNullifiers {
+            let tree_index = nullifier_insertion_tree_index(&verification_acc);
+            let nullifier_account = match tree_index {
+                5 => &mut n_acc_5,
+                2 => &mut n_acc_2,
+                _ => panic!(),
+            };
+            finalize_verification_insert_nullifier(&mut verification_acc, nullifier_account, 0)
+                .unwrap();
+
+            tree_indices.push(tree_index);
+        }
+
+        // A single call per input commitment, MT by MT
+        assert_eq!(tree_indices, [5, 5, 5, 2]);
+        assert_eq!(n_acc_5.get_nullifier_hash_count(), 3);
+        assert_eq!(n_acc_2.get_nullifier_hash_count(), 1);
+
+        for (nullifier_hash, in_first_mt, in_second_mt) in [
+            ("1", true, false),
+            ("2", true, false),
+            ("3", true, false),
+            ("4", false, true),
+        ] {
+            let nullifier_hash = RawU256::new(u256_from_str_skip_mr(nullifier_hash)).reduce();
+            assert_eq!(
+                n_acc_5.can_insert_nullifier_hash(nullifier_hash).unwrap(),
+                !in_first_mt
+            );
+            assert_eq!(
+                n_acc_2.can_insert_nullifier_hash(nullifier_hash).unwrap(),
+                !in_second_mt
+            );
+        }
+
+        // Called after finalization
         assert_eq!(
-            init_verification_proof(&fee_payer, &mut verification_account, 0, proof),
+            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_5, 0),
             Err(ElusivError::InvalidAccountState.into())
         );
     }
 
     #[test]
-    fn test_compute_verification() {
-        zero_program_account!(mut verification_account, VerificationAccount);
-        vkey_account!(vkey, SendQuadraVKey);
-        vkey.set_version(&1);
+    fn test_finalize_verification_transfer_lamports_rent_beneficiary() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        for is_valid in [true, false] {
+            finalize_send_test!(
+                LAMPORTS_TOKEN_ID,
+                0,
+                public_inputs,
+                verification_acc_data,
+                recipient_bytes,
+                _i,
+                _r,
+                _f
+            );
+
+            account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+            let fee_payer_pk = Pubkey::new(
+                &VerificationAccount::new(&mut verification_acc_data)
+                    .unwrap()
+                    .get_other_data()
+                    .fee_payer
+                    .skip_mr(),
+            );
+            account_info!(f, fee_payer_pk); // fee_payer
+            test_account_info!(rent_beneficiary, 0);
+            test_account_info!(pool, PoolAccount::SIZE);
+            test_account_info!(fee_collector, 0);
+            test_account_info!(any, 0);
+            test_pda_account_info!(
+                n_pda,
+                NullifierDuplicateAccount,
+                public_inputs
+                    .join_split
+                    .associated_nullifier_duplicate_pda_pubkey(),
+                None
+            );
+            account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+            zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+            zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+            zero_program_account!(governor, GovernorAccount);
+            test_account_info!(warden_rewards, 0);
+            CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+
+            {
+                pda_account!(mut v_acc, VerificationAccount, v_acc);
+                v_acc.set_state(&VerificationState::Finalized);
+                v_acc.set_is_verified(&ElusivOption::Some(is_valid));
+
+                let mut data = v_acc.get_other_data();
+                data.rent_beneficiary =
+                    ElusivOption::Some(RawU256::new(rent_beneficiary.key.to_bytes()));
+                v_acc.set_other_data(&data);
+            }
+
+            // Invalid rent_beneficiary
+            assert_eq!(
+                finalize_verification_transfer_lamports(
+                    &f,
+                    &f,
+                    &recipient,
+                    &pool,
+                    &fee_collector,
+                    &any,
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    &v_acc,
+                    &n_pda,
+                    &any,
+                    &any,
+                    &governor,
+                    &warden_rewards,
+                    &any,
+                    &any,
+                    &any,
+                    &mut fee_version_usage,
+                    0
+                ),
+                Err(ElusivError::InvalidAccount.into())
+            );
+
+            let rent = v_acc.lamports() + n_pda.lamports();
+            let fee_payer_lamports = f.lamports();
+            let rent_beneficiary_lamports = rent_beneficiary.lamports();
+            let fee_collector_lamports = fee_collector.lamports();
+
+            assert_eq!(
+                finalize_verification_transfer_lamports(
+                    &f,
+                    &rent_beneficiary,
+                    &recipient,
+                    &pool,
+                    &fee_collector,
+                    &any,
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    &v_acc,
+                    &n_pda,
+                    &any,
+                    &any,
+                    &governor,
+                    &warden_rewards,
+                    &any,
+                    &any,
+                    &any,
+                    &mut fee_version_usage,
+                    0
+                ),
+                Ok(())
+            );
+
+            assert_eq!(v_acc.lamports(), 0);
+            assert_eq!(n_pda.lamports(), 0);
+            assert_eq!(f.lamports(), fee_payer_lamports);
+
+            if is_valid {
+                // Rent flows to the `rent_beneficiary`
+                assert_eq!(
+                    rent_beneficiary.lamports(),
+                    rent_beneficiary_lamports + rent
+                );
+                assert_eq!(fee_collector.lamports(), fee_collector_lamports);
+            } else {
+                // Rent flows to the `fee_collector`
+                assert_eq!(rent_beneficiary.lamports(), rent_beneficiary_lamports);
+                assert_eq!(fee_collector.lamports(), fee_collector_lamports + rent);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_verification_transfer_lamports() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        finalize_send_test!(
+            LAMPORTS_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            10,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            _i,
+            _r,
+            _f,
+            optional_fee_collector
+        );
+
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        let fee_payer_pk = Pubkey::new(
+            &VerificationAccount::new(&mut verification_acc_data)
+                .unwrap()
+                .get_other_data()
+                .fee_payer
+                .skip_mr(),
+        );
+        account_info!(f, fee_payer_pk); // fee_payer
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        account_info!(optional_fee_collector, optional_fee_collector);
         test_account_info!(any, 0);
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
+        );
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
 
-        // Setup
-        let public_inputs = test_public_inputs();
-        for (i, &public_input) in public_inputs.iter().enumerate() {
-            verification_account.set_public_input(i, &RawU256::new(public_input));
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_state(&VerificationState::None);
+            v_acc.set_is_verified(&ElusivOption::Some(true));
         }
-        let instructions = prepare_public_inputs_instructions(
-            &public_inputs,
-            SendQuadraVKey::public_inputs_count(),
+
+        // Invalid state
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_state(&VerificationState::Finalized);
+        }
+
+        // Invalid nullifier_duplicate_account
+        account_info!(
+            invalid_n_pda,
+            VerificationAccount::find_with_pubkey(*f.key, Some(0)).0,
+            vec![1]
+        );
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &invalid_n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
         );
-        verification_account.set_prepare_inputs_instructions_count(&(instructions.len() as u32));
-        for (i, &ix) in instructions.iter().enumerate() {
-            verification_account.set_prepare_inputs_instructions(i, &(ix as u16));
-        }
 
-        // Computation is already finished (is_verified is Some)
-        verification_account.set_is_verified(&ElusivOption::Some(true));
+        // Invalid original_fee_payer
         assert_eq!(
-            compute_verification(
-                &mut verification_account,
-                &vkey,
+            finalize_verification_transfer_lamports(
                 &any,
-                0,
-                SendQuadraVKey::VKEY_ID
+                &any,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
             ),
-            Err(ElusivError::ComputationIsAlreadyFinished.into())
+            Err(ElusivError::InvalidAccount.into())
         );
-        verification_account.set_is_verified(&ElusivOption::None);
 
-        // Success for public input preparation
-        for _ in 0..instructions.len() {
+        // Recipient is a PDA closed by the finalization
+        for invalid_recipient in [&n_pda, &v_acc] {
             assert_eq!(
-                compute_verification(
-                    &mut verification_account,
-                    &vkey,
+                finalize_verification_transfer_lamports(
+                    &f,
+                    &f,
+                    invalid_recipient,
+                    &pool,
+                    &fee_collector,
+                    &optional_fee_collector,
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    &v_acc,
+                    &n_pda,
                     &any,
-                    0,
-                    SendQuadraVKey::VKEY_ID
+                    &any,
+                    &governor,
+                    &warden_rewards,
+                    &any,
+                    &any,
+                    &any,
+                    &mut fee_version_usage,
+                    0
                 ),
-                Ok(())
+                Err(ElusivError::InvalidAccount.into())
             );
         }
 
-        // Failure for miller loop (proof not setup)
+        // Invalid recipient
         assert_eq!(
-            compute_verification(
-                &mut verification_account,
-                &vkey,
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
                 &any,
-                0,
-                SendQuadraVKey::VKEY_ID
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
             ),
-            Err(ElusivError::InvalidAccountState.into())
+            Err(ElusivError::InvalidRecipient.into())
         );
 
-        let proof = test_proof();
-        verification_account.a.set(proof.a);
-        verification_account.b.set(proof.b);
-        verification_account.c.set(proof.c);
-        verification_account.set_state(&VerificationState::ProofSetup);
+        // Invalid optional-fee-collector
+        test_account_info!(invalid_optional_fee_collector, 0);
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &invalid_optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
 
-        // Success
-        for _ in 0..COMBINED_MILLER_LOOP_IXS + FINAL_EXPONENTIATION_IXS {
-            assert_eq!(
-                compute_verification(
-                    &mut verification_account,
-                    &vkey,
-                    &any,
-                    0,
-                    SendQuadraVKey::VKEY_ID
-                ),
-                Ok(())
-            );
+        // Commitment queue is full
+        {
+            let mut queue = CommitmentQueue::new(&mut commitment_queue);
+            for _ in 0..CommitmentQueue::CAPACITY {
+                queue
+                    .enqueue(CommitmentHashRequest {
+                        commitment: [0; 32],
+                        fee_version: 0,
+                        min_batching_rate: 0,
+                    })
+                    .unwrap();
+            }
+        }
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ElusivError::QueueIsFull.into())
+        );
+
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+
+        // Boosts are paid to the fee_payer
+        test_account_info!(booster, 0);
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            let mut data = v_acc.get_other_data();
+            data.add_boost(RawU256::new(booster.key.to_bytes()), Lamports(1000))?;
+            data.add_boost(RawU256::new(booster.key.to_bytes()), Lamports(234))?;
+            v_acc.set_other_data(&data);
         }
+        let fee_payer_lamports = f.lamports() + v_acc.lamports() + n_pda.lamports() + 1234;
+        let booster_lamports = booster.lamports();
 
-        // Computation is finished
         assert_eq!(
-            compute_verification(
-                &mut verification_account,
-                &vkey,
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
                 &any,
-                0,
-                SendQuadraVKey::VKEY_ID
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
             ),
-            Err(ElusivError::ComputationIsAlreadyFinished.into())
+            Ok(())
         );
-        assert_eq!(verification_account.get_is_verified().option(), Some(false));
-    }
 
-    macro_rules! finalize_send_test {
-        (
-            $token_id: expr,
-            $optional_fee: expr,
-            $public_inputs: ident,
-            $v_data: ident,
-            $recipient: ident,
-            $identifier: ident,
-            $reference: ident,
-            $finalize_data: ident
-        ) => {
-            finalize_send_test!(
-                $token_id,
-                0,
-                $optional_fee,
-                $public_inputs,
-                $v_data,
-                $recipient,
-                $identifier,
-                $reference,
-                $finalize_data,
-                _optional_fee_collector
-            )
-        };
-        (
-            $token_id: expr,
-            $amount: expr,
-            $optional_fee: expr,
-            $public_inputs: ident,
-            $v_data: ident,
-            $recipient: ident,
-            $identifier: ident,
-            $reference: ident,
-            $finalize_data: ident,
-            $optional_fee_collector: ident
-        ) => {
-            let $recipient = Pubkey::new_unique().to_bytes();
-            let $identifier = Pubkey::new_unique().to_bytes();
-            let $reference = Pubkey::new_unique().to_bytes();
-            let iv = Pubkey::new_unique().to_bytes();
-            let encrypted_owner = Pubkey::new_unique().to_bytes();
+        assert_eq!(n_pda.lamports(), 0);
+        assert_eq!(v_acc.lamports(), 0);
+        assert_eq!(f.lamports(), fee_payer_lamports);
+        assert_eq!(booster.lamports(), booster_lamports);
+
+        // Retry on the closed account
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ElusivError::InvalidAccountState.into())
+        );
 
-            let metadata = CommitmentMetadata::default();
-            let $optional_fee_collector = Pubkey::new_unique();
-            let optional_fee = OptionalFee {
-                collector: $optional_fee_collector,
-                amount: $optional_fee,
-            };
-            let $public_inputs = SendPublicInputs {
-                join_split: JoinSplitPublicInputs {
-                    input_commitments: vec![InputCommitment {
-                        root: Some(empty_root_raw()),
-                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
-                    }],
-                    output_commitment: RawU256::new(u256_from_str_skip_mr("987654321")),
-                    recent_commitment_index: 123,
-                    fee_version: 0,
-                    amount: $amount,
-                    fee: 10000,
-                    optional_fee: optional_fee.clone(),
-                    token_id: $token_id,
-                    metadata,
-                },
-                recipient_is_associated_token_account: false,
-                hashed_inputs: generate_hashed_inputs(
-                    &$recipient,
-                    &$identifier,
-                    &iv,
-                    &encrypted_owner,
-                    &$reference,
-                    false,
-                    &metadata,
-                    &optional_fee,
-                    &None,
-                ),
-                solana_pay_transfer: false,
-            };
+        // Retry on the drained account
+        account_info!(drained_v_acc, *v_acc.key, vec![]);
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &drained_v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ProgramError::InvalidAccountData)
+        );
 
-            let mut $v_data = vec![0; VerificationAccount::SIZE];
-            let mut v_account = VerificationAccount::new(&mut $v_data).unwrap();
-            let fee_payer = RawU256::new(Pubkey::new_unique().to_bytes());
-            v_account
-                .setup(
-                    fee_payer,
-                    false,
-                    &[],
-                    &vec![0],
-                    0,
-                    ProofRequest::Send($public_inputs.clone()),
-                    [0, 1],
-                )
-                .unwrap();
-            v_account.set_state(&VerificationState::ProofSetup);
-            v_account.set_is_verified(&ElusivOption::Some(true));
-            v_account.set_other_data(&VerificationAccountData {
-                fee_payer,
-                fee_payer_account: fee_payer,
-                recipient_wallet: ElusivOption::Some(RawU256::new($recipient)),
-                ..Default::default()
-            });
+        pda_account!(v_acc, VerificationAccount, v_acc);
+        assert_eq!(v_acc.get_state().unwrap(), VerificationState::Closed);
 
-            let $finalize_data = FinalizeSendData {
-                total_amount: $public_inputs.join_split.total_amount(),
-                token_id: $token_id,
-                mt_index: 0,
-                commitment_index: 0,
-                encrypted_owner,
-                iv,
-            };
-        };
-    }
+        // The reserved slot is used by the commitment
+        let commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        assert_eq!(commitment_queue.pending_commitments(), 0);
+        assert_eq!(commitment_queue.len(), 1);
 
-    macro_rules! simple_storage_account {
-        ($id: ident) => {
-            let mut data = vec![0; StorageAccount::SIZE];
-            let $id =
-                <StorageAccount as elusiv_types::accounts::ProgramAccount>::new(&mut data).unwrap();
-        };
+        Ok(())
     }
 
     #[test]
-    fn test_finalize_verification_send_valid() {
+    fn test_finalize_verification_transfer_lamports_reservations() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
         finalize_send_test!(
-            USDC_TOKEN_ID,
+            LAMPORTS_TOKEN_ID,
             LAMPORTS_PER_SOL,
+            10,
             public_inputs,
             verification_acc_data,
             recipient_bytes,
-            identifier_bytes,
-            reference_bytes,
-            finalize_data
+            _i,
+            _r,
+            _f,
+            optional_fee_collector
         );
 
-        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
-        let mut data = vec![0; CommitmentQueueAccount::SIZE];
-        let mut queue = CommitmentQueueAccount::new(&mut data).unwrap();
-        simple_storage_account!(storage);
-        zero_program_account!(mut buffer, CommitmentBufferAccount);
-
         account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
-        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
-        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+        let (fee_payer_pk, reservation) = {
+            let v_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
+            let data = v_acc.get_other_data();
+            (
+                Pubkey::new(&data.fee_payer.skip_mr()),
+                pending_lamport_reservation(&v_acc.get_request().unwrap(), &data)?,
+            )
+        };
+        account_info!(f, fee_payer_pk); // fee_payer
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        account_info!(optional_fee_collector, optional_fee_collector);
         test_account_info!(any, 0);
-
-        // Verification is not finished
-        verification_acc.set_is_verified(&ElusivOption::None);
-        assert_eq!(
-            finalize_verification_send(
-                &recipient,
-                &identifier,
-                &reference,
-                &mut queue,
-                &mut verification_acc,
-                &storage,
-                &mut buffer,
-                &any,
-                0,
-                finalize_data.clone(),
-                false,
-            ),
-            Err(ElusivError::ComputationIsNotYetFinished.into())
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
         );
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
 
-        verification_acc.set_is_verified(&ElusivOption::Some(true));
-
-        // Invalid recipient
-        {
-            account_info!(recipient, Pubkey::new_from_array(identifier_bytes));
-            assert_eq!(
-                finalize_verification_send(
-                    &recipient,
-                    &identifier,
-                    &reference,
-                    &mut queue,
-                    &mut verification_acc,
-                    &storage,
-                    &mut buffer,
-                    &any,
-                    0,
-                    finalize_data.clone(),
-                    false,
-                ),
-                Err(ElusivError::InputsMismatch.into())
-            );
-        }
-
-        // Invalid identifier
+        CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
         {
-            account_info!(identifier, Pubkey::new_from_array(recipient_bytes));
-            assert_eq!(
-                finalize_verification_send(
-                    &recipient,
-                    &identifier,
-                    &reference,
-                    &mut queue,
-                    &mut verification_acc,
-                    &storage,
-                    &mut buffer,
-                    &any,
-                    0,
-                    finalize_data.clone(),
-                    false,
-                ),
-                Err(ElusivError::InputsMismatch.into())
-            );
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_state(&VerificationState::Finalized);
+            v_acc.set_is_verified(&ElusivOption::Some(true));
         }
 
-        // Invalid reference
+        // Two concurrent verifications reserved their payouts
         {
-            account_info!(reference, Pubkey::new_from_array(recipient_bytes));
-            assert_eq!(
-                finalize_verification_send(
-                    &recipient,
-                    &identifier,
-                    &reference,
-                    &mut queue,
-                    &mut verification_acc,
-                    &storage,
-                    &mut buffer,
-                    &any,
-                    0,
-                    finalize_data.clone(),
-                    false,
-                ),
-                Err(ElusivError::InputsMismatch.into())
-            );
+            pda_account!(mut pool, PoolAccount, pool);
+            pool.reserve_for_pending_verifications(reservation)?;
+            pool.reserve_for_pending_verifications(reservation)?;
         }
 
-        // Invalid finalize_data
-        for invalid_data in [
-            mutate(&finalize_data, |d| {
-                d.total_amount = public_inputs.join_split.amount
-            }),
-            mutate(&finalize_data, |d| d.token_id = 0),
-            mutate(&finalize_data, |d| d.commitment_index = 1),
-            mutate(&finalize_data, |d| d.mt_index = 1),
-            mutate(&finalize_data, |d| d.encrypted_owner = d.iv),
-            mutate(&finalize_data, |d| d.iv = d.encrypted_owner),
-        ] {
-            assert_eq!(
-                finalize_verification_send(
-                    &recipient,
-                    &identifier,
-                    &reference,
-                    &mut queue,
-                    &mut verification_acc,
-                    &storage,
-                    &mut buffer,
-                    &any,
-                    0,
-                    invalid_data,
-                    false,
-                ),
-                Err(ElusivError::InputsMismatch.into())
-            );
+        // The pool is not able to cover both payouts
+        **pool.try_borrow_mut_lamports()? = 2 * reservation - 1;
+        assert_eq!(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ElusivError::InsufficientFunds.into())
+        );
+        assert_eq!(pool.lamports(), 2 * reservation - 1);
+        {
+            pda_account!(pool, PoolAccount, pool);
+            assert_eq!(pool.get_pending_lamport_reservation(), 2 * reservation);
         }
 
-        // Success
+        // Only the other verification's reservation remains
+        let network_fee = {
+            pda_account!(v_acc, VerificationAccount, v_acc);
+            v_acc.get_other_data().network_fee
+        };
+        **pool.try_borrow_mut_lamports()? = 2 * reservation + network_fee;
         assert_eq!(
-            finalize_verification_send(
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
                 &recipient,
-                &identifier,
-                &reference,
-                &mut queue,
-                &mut verification_acc,
-                &storage,
-                &mut buffer,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
                 &any,
-                0,
-                finalize_data.clone(),
-                false,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
             ),
             Ok(())
         );
 
-        assert_eq!(
-            verification_acc.get_state(),
-            VerificationState::InsertNullifiers
-        );
+        // The other verification's payout is still covered
+        assert_eq!(pool.lamports(), reservation);
+        pda_account!(pool, PoolAccount, pool);
+        assert_eq!(pool.get_pending_lamport_reservation(), reservation);
 
-        // Called twice
-        assert_eq!(
-            finalize_verification_send(
-                &recipient,
-                &identifier,
-                &reference,
-                &mut queue,
-                &mut verification_acc,
-                &storage,
-                &mut buffer,
-                &any,
-                0,
-                finalize_data,
-                false,
-            ),
-            Err(ElusivError::InvalidAccountState.into())
-        );
+        Ok(())
     }
 
     #[test]
-    fn test_finalize_verification_send_invalid() {
+    fn test_transfer_verification_ownership() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
         finalize_send_test!(
-            USDC_TOKEN_ID,
+            LAMPORTS_TOKEN_ID,
             LAMPORTS_PER_SOL,
+            10,
             public_inputs,
             verification_acc_data,
             recipient_bytes,
-            identifier_bytes,
-            reference_bytes,
-            finalize_data
+            _i,
+            _r,
+            _f,
+            optional_fee_collector
         );
 
-        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
-        let mut data = vec![0; CommitmentQueueAccount::SIZE];
-        let mut queue = CommitmentQueueAccount::new(&mut data).unwrap();
-        simple_storage_account!(storage);
-        zero_program_account!(mut buffer, CommitmentBufferAccount);
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        let fee_payer_pk = Pubkey::new(
+            &VerificationAccount::new(&mut verification_acc_data)
+                .unwrap()
+                .get_other_data()
+                .fee_payer
+                .skip_mr(),
+        );
+        account_info!(f, fee_payer_pk); // fee_payer
+        test_account_info!(new_fee_payer, 0);
+        account_info!(
+            usdc_account,
+            Pubkey::new_unique(),
+            spl_token_account_data(USDC_TOKEN_ID),
+            spl_token::id(),
+            false
+        );
+        account_info!(system_program, system_program::id(), vec![]);
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        account_info!(optional_fee_collector, optional_fee_collector);
         test_account_info!(any, 0);
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
+        );
+        account_info!(invalid_n_pda, Pubkey::new_unique(), vec![1]);
+        let identifier = Pubkey::new_unique();
+        test_pda_account_info!(identifier_lock, IdentifierLockAccount, identifier, None);
+        account_info!(
+            invalid_identifier_lock,
+            Pubkey::new_unique(),
+            identifier_lock.data.borrow().to_vec()
+        );
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
+        CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+
+        macro_rules! set_state {
+            ($state: expr) => {{
+                pda_account!(mut v_acc, VerificationAccount, v_acc);
+                v_acc.set_state(&$state);
+            }};
+        }
 
-        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
-        account_info!(identifier, Pubkey::new_from_array(identifier_bytes));
-        account_info!(reference, Pubkey::new_from_array(reference_bytes));
+        macro_rules! set_fee_payer {
+            ($fee_payer: expr) => {{
+                pda_account!(mut v_acc, VerificationAccount, v_acc);
+                let mut data = v_acc.get_other_data();
+                data.fee_payer = RawU256::new($fee_payer.key.to_bytes());
+                v_acc.set_other_data(&data);
+            }};
+        }
 
-        verification_acc.set_is_verified(&ElusivOption::Some(false));
+        macro_rules! transfer {
+            ($fee_payer: expr, $new_fee_payer: expr, $new_fee_payer_account: expr, $n_pda: expr, $identifier_lock: expr, $system_program: expr) => {
+                transfer_verification_ownership(
+                    &$fee_payer,
+                    &$new_fee_payer,
+                    &$new_fee_payer_account,
+                    &v_acc,
+                    &$n_pda,
+                    &$identifier_lock,
+                    &$system_program,
+                    0,
+                )
+            };
+        }
+
+        // Invalid state (after the finalization)
+        for state in [VerificationState::Finalized, VerificationState::Closed] {
+            set_state!(state);
+            assert_eq!(
+                transfer!(f, new_fee_payer, new_fee_payer, n_pda, any, system_program),
+                Err(ElusivError::InvalidAccountState.into())
+            );
+        }
+
+        // Any state before the finalization
+        for state in [
+            VerificationState::None,
+            VerificationState::FeeTransferred,
+            VerificationState::ProofSetup,
+            VerificationState::InsertNullifiers,
+        ] {
+            set_state!(state);
+            assert_eq!(
+                transfer!(f, new_fee_payer, new_fee_payer, n_pda, any, system_program),
+                Ok(())
+            );
+            set_fee_payer!(f);
+        }
+
+        set_state!(VerificationState::ProofSetup);
 
+        // Invalid fee_payer
         assert_eq!(
-            finalize_verification_send(
-                &recipient,
-                &identifier,
-                &reference,
-                &mut queue,
-                &mut verification_acc,
-                &storage,
-                &mut buffer,
-                &any,
-                0,
-                finalize_data,
-                false,
+            transfer!(
+                any,
+                new_fee_payer,
+                new_fee_payer,
+                n_pda,
+                any,
+                system_program
             ),
-            Ok(())
+            Err(ElusivError::InvalidAccount.into())
         );
-        assert_eq!(verification_acc.get_state(), VerificationState::Finalized);
-    }
 
-    #[test]
-    fn test_finalize_verification_migrate() {
-        let migrate_public_inputs = MigratePublicInputs {
-            join_split: JoinSplitPublicInputs {
-                input_commitments: vec![InputCommitment {
-                    root: Some(empty_root_raw()),
-                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
-                }],
-                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
-                recent_commitment_index: 123,
-                fee_version: 0,
-                amount: LAMPORTS_PER_SOL,
-                fee: 10000,
-                optional_fee: OptionalFee::default(),
-                token_id: 0,
-                metadata: CommitmentMetadata::default(),
-            },
-            current_nsmt_root: RawU256::new([0; 32]),
-            next_nsmt_root: RawU256::new([0; 32]),
-        };
+        // Token-account of a different token-id
+        assert_eq!(
+            transfer!(f, new_fee_payer, usdc_account, n_pda, any, system_program),
+            Err(ElusivError::InvalidAccount.into())
+        );
 
-        let pk = Pubkey::new_unique();
-        account_info!(acc, pk);
+        // Invalid nullifier_duplicate_account
+        assert_eq!(
+            transfer!(
+                f,
+                new_fee_payer,
+                new_fee_payer,
+                invalid_n_pda,
+                any,
+                system_program
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
 
-        let mut data = vec![0; VerificationAccount::SIZE];
-        let mut v_account = VerificationAccount::new(&mut data).unwrap();
-        v_account.set_request(&ProofRequest::Migrate(migrate_public_inputs));
-        v_account.set_state(&VerificationState::ProofSetup);
-        v_account.set_is_verified(&ElusivOption::Some(true));
+        // Invalid identifier_lock_account
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_locked_identifier(&identifier.to_bytes());
+        }
+        assert_eq!(
+            transfer!(
+                f,
+                new_fee_payer,
+                new_fee_payer,
+                n_pda,
+                invalid_identifier_lock,
+                system_program
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
 
-        let mut data = vec![0; CommitmentQueueAccount::SIZE];
-        let mut queue = CommitmentQueueAccount::new(&mut data).unwrap();
+        // Invalid system_program
+        assert_eq!(
+            transfer!(f, new_fee_payer, new_fee_payer, n_pda, identifier_lock, any),
+            Err(ProgramError::IncorrectProgramId)
+        );
 
-        let finalize_data = FinalizeSendData::default();
-        simple_storage_account!(storage);
-        zero_program_account!(mut buffer, CommitmentBufferAccount);
-        test_account_info!(any, 0);
+        // An explicit rent_beneficiary keeps the rent (so nothing is reimbursed)
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            let mut data = v_acc.get_other_data();
+            data.rent_beneficiary = ElusivOption::Some(RawU256::new(any.key.to_bytes()));
+            v_acc.set_other_data(&data);
+        }
+        assert_eq!(
+            transfer!(f, new_fee_payer, new_fee_payer, invalid_n_pda, any, any),
+            Ok(())
+        );
+        {
+            pda_account!(v_acc, VerificationAccount, v_acc);
+            assert_eq!(
+                v_acc.get_other_data().rent_beneficiary().skip_mr(),
+                any.key.to_bytes()
+            );
+        }
+        set_fee_payer!(f);
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            let mut data = v_acc.get_other_data();
+            data.rent_beneficiary = ElusivOption::None;
+            v_acc.set_other_data(&data);
+        }
 
+        // Transfer mid-computation
         assert_eq!(
-            finalize_verification_send(
-                &acc,
-                &acc,
-                &acc,
-                &mut queue,
-                &mut v_account,
-                &storage,
-                &mut buffer,
-                &any,
-                0,
-                finalize_data,
-                false,
+            transfer!(
+                f,
+                new_fee_payer,
+                new_fee_payer,
+                n_pda,
+                identifier_lock,
+                system_program
             ),
-            Err(ElusivError::FeatureNotAvailable.into())
+            Ok(())
         );
-    }
+        {
+            pda_account!(v_acc, VerificationAccount, v_acc);
+            let data = v_acc.get_other_data();
+            assert_eq!(data.fee_payer.skip_mr(), new_fee_payer.key.to_bytes());
+            assert_eq!(
+                data.fee_payer_account.skip_mr(),
+                new_fee_payer.key.to_bytes()
+            );
 
-    #[test]
-    fn test_finalize_verification_insert_nullifier() {
-        finalize_send_test!(
-            USDC_TOKEN_ID,
-            LAMPORTS_PER_SOL,
-            public_inputs,
-            verification_acc_data,
-            _recipient_bytes,
-            _identifier_bytes,
-            _reference_bytes,
-            _finalize_data
-        );
+            // The reimbursed rent flows to the new fee_payer
+            assert_eq!(
+                data.rent_beneficiary().skip_mr(),
+                new_fee_payer.key.to_bytes()
+            );
+        }
 
-        let mut verification_acc = VerificationAccount::new(&mut verification_acc_data).unwrap();
-        parent_account!(mut n_acc_0, NullifierAccount);
+        // The previous fee_payer cannot transfer the verification anymore
+        assert_eq!(
+            transfer!(f, any, any, n_pda, identifier_lock, system_program),
+            Err(ElusivError::InvalidAccount.into())
+        );
 
-        // finalize_verification_send not called
-        verification_acc.set_state(&VerificationState::InsertNullifiers);
+        set_state!(VerificationState::Finalized);
 
-        // Nullifier duplicate
-        n_acc_0
-            .try_insert_nullifier_hash(
-                public_inputs.join_split.input_commitments[0]
-                    .nullifier_hash
-                    .reduce(),
-            )
-            .unwrap();
+        // The previous fee_payer cannot finalize the verification
         assert_eq!(
-            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_0, 0),
-            Err(ElusivError::CouldNotInsertNullifier.into())
+            finalize_verification_transfer_lamports(
+                &f,
+                &f,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Err(ElusivError::InvalidAccount.into())
         );
 
-        parent_account!(mut n_acc_0, NullifierAccount);
-
-        // Success
+        // The finalization pays the rewards and the reimbursed rent to the new fee_payer
+        let fee_payer_lamports = f.lamports();
+        let new_fee_payer_lamports = new_fee_payer.lamports()
+            + v_acc.lamports()
+            + n_pda.lamports()
+            + identifier_lock.lamports();
         assert_eq!(
-            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_0, 0),
+            finalize_verification_transfer_lamports(
+                &new_fee_payer,
+                &new_fee_payer,
+                &recipient,
+                &pool,
+                &fee_collector,
+                &optional_fee_collector,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &identifier_lock,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
             Ok(())
         );
+        assert_eq!(new_fee_payer.lamports(), new_fee_payer_lamports);
+        assert_eq!(f.lamports(), fee_payer_lamports);
 
-        assert!(!n_acc_0
-            .can_insert_nullifier_hash(
-                public_inputs.join_split.input_commitments[0]
-                    .nullifier_hash
-                    .reduce()
-            )
-            .unwrap());
-        assert_eq!(verification_acc.get_state(), VerificationState::Finalized);
-
-        // Called twice
-        assert_eq!(
-            finalize_verification_insert_nullifier(&mut verification_acc, &mut n_acc_0, 0),
-            Err(ElusivError::InvalidAccountState.into())
-        );
+        Ok(())
     }
 
     #[test]
-    fn test_finalize_verification_transfer_lamports() -> ProgramResult {
+    fn test_finalize_verification_transfer_lamports_invalid_proof() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
         finalize_send_test!(
             LAMPORTS_TOKEN_ID,
             LAMPORTS_PER_SOL,
@@ -3044,7 +8844,7 @@ mod tests {
                 .skip_mr(),
         );
         account_info!(f, fee_payer_pk); // fee_payer
-        test_account_info!(pool, 0);
+        test_account_info!(pool, PoolAccount::SIZE);
         test_account_info!(fee_collector, 0);
         account_info!(optional_fee_collector, optional_fee_collector);
         test_account_info!(any, 0);
@@ -3059,44 +8859,35 @@ mod tests {
         account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
         zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
         zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
 
         {
             pda_account!(mut v_acc, VerificationAccount, v_acc);
-            v_acc.set_state(&VerificationState::None);
-            v_acc.set_is_verified(&ElusivOption::Some(true));
+            v_acc.set_state(&VerificationState::Finalized);
+            v_acc.set_is_verified(&ElusivOption::Some(false));
         }
+        CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+        fee_version_usage.admit(public_inputs.join_split.fee_version)?;
 
-        // Invalid state
-        assert_eq!(
-            finalize_verification_transfer_lamports(
-                &f,
-                &recipient,
-                &pool,
-                &fee_collector,
-                &optional_fee_collector,
-                &mut commitment_queue,
-                &mut metadata_queue,
-                &v_acc,
-                &n_pda,
-                &any,
-                0
-            ),
-            Err(ElusivError::InvalidAccountState.into())
-        );
-
+        // Boosts of two boosters
+        test_account_info!(booster0, 0);
+        test_account_info!(booster1, 0);
         {
             pda_account!(mut v_acc, VerificationAccount, v_acc);
-            v_acc.set_state(&VerificationState::Finalized);
+            let mut data = v_acc.get_other_data();
+            data.add_boost(RawU256::new(booster0.key.to_bytes()), Lamports(100))?;
+            data.add_boost(RawU256::new(booster1.key.to_bytes()), Lamports(23))?;
+            v_acc.set_other_data(&data);
         }
+        let booster0_lamports = booster0.lamports();
+        let booster1_lamports = booster1.lamports();
+        let fee_payer_lamports = f.lamports();
 
-        // Invalid nullifier_duplicate_account
-        account_info!(
-            invalid_n_pda,
-            VerificationAccount::find_with_pubkey(*f.key, Some(0)).0,
-            vec![1]
-        );
+        // Invalid booster
         assert_eq!(
             finalize_verification_transfer_lamports(
+                &f,
                 &f,
                 &recipient,
                 &pool,
@@ -3105,17 +8896,24 @@ mod tests {
                 &mut commitment_queue,
                 &mut metadata_queue,
                 &v_acc,
-                &invalid_n_pda,
+                &n_pda,
+                &any,
+                &any,
+                &governor,
+                &warden_rewards,
                 &any,
+                &booster1,
+                &booster0,
+                &mut fee_version_usage,
                 0
             ),
             Err(ElusivError::InvalidAccount.into())
         );
 
-        // Invalid original_fee_payer
         assert_eq!(
             finalize_verification_transfer_lamports(
-                &any,
+                &f,
+                &f,
                 &recipient,
                 &pool,
                 &fee_collector,
@@ -3125,16 +8923,108 @@ mod tests {
                 &v_acc,
                 &n_pda,
                 &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &booster0,
+                &booster1,
+                &mut fee_version_usage,
                 0
             ),
-            Err(ElusivError::InvalidAccount.into())
+            Ok(())
         );
 
-        // Invalid recipient
+        // Boosts are refunded to the boosters
+        assert_eq!(booster0.lamports(), booster0_lamports + 100);
+        assert_eq!(booster1.lamports(), booster1_lamports + 23);
+        assert_eq!(f.lamports(), fee_payer_lamports);
+
+        assert_eq!(v_acc.lamports(), 0);
+        pda_account!(v_acc, VerificationAccount, v_acc);
+        assert_eq!(v_acc.get_state().unwrap(), VerificationState::Closed);
+
+        // The reserved slot is released without enqueuing a commitment
+        let commitment_queue = CommitmentQueue::new(&mut commitment_queue);
+        assert_eq!(commitment_queue.pending_commitments(), 0);
+        assert!(commitment_queue.is_empty());
+
+        // The use of the fee-version ends as well
         assert_eq!(
+            fee_version_usage.pending_count(public_inputs.join_split.fee_version),
+            Some(0)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_verification_transfer_lamports_fault_settlement() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        for fault in [
+            VerificationFault::UserFault,
+            VerificationFault::WardenFault,
+            VerificationFault::Indeterminate,
+        ] {
+            finalize_send_test!(
+                LAMPORTS_TOKEN_ID,
+                LAMPORTS_PER_SOL,
+                10,
+                public_inputs,
+                verification_acc_data,
+                recipient_bytes,
+                _i,
+                _r,
+                _f,
+                optional_fee_collector
+            );
+
+            account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+            let fee_payer_pk = Pubkey::new(
+                &VerificationAccount::new(&mut verification_acc_data)
+                    .unwrap()
+                    .get_other_data()
+                    .fee_payer
+                    .skip_mr(),
+            );
+            account_info!(f, fee_payer_pk); // fee_payer
+            test_account_info!(pool, PoolAccount::SIZE);
+            test_account_info!(fee_collector, 0);
+            account_info!(optional_fee_collector, optional_fee_collector);
+            test_account_info!(any, 0);
+            test_pda_account_info!(
+                n_pda,
+                NullifierDuplicateAccount,
+                public_inputs
+                    .join_split
+                    .associated_nullifier_duplicate_pda_pubkey(),
+                None
+            );
+            account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+            zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+            zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+            zero_program_account!(governor, GovernorAccount);
+            test_account_info!(warden_rewards, 0);
+            CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+
+            let data = {
+                pda_account!(mut v_acc, VerificationAccount, v_acc);
+                v_acc.set_state(&VerificationState::Finalized);
+                v_acc.fail(fault);
+                v_acc.get_other_data()
+            };
+
+            let rent = v_acc.lamports() + n_pda.lamports();
+            let pool_lamports = pool.lamports();
+            let fee_collector_lamports = fee_collector.lamports();
+            let fee_payer_lamports = f.lamports();
+            let recipient_lamports = recipient.lamports();
+            let optional_fee_collector_lamports = optional_fee_collector.lamports();
+
             finalize_verification_transfer_lamports(
                 &f,
-                &any,
+                &f,
+                &recipient,
                 &pool,
                 &fee_collector,
                 &optional_fee_collector,
@@ -3143,64 +9033,193 @@ mod tests {
                 &v_acc,
                 &n_pda,
                 &any,
-                0
-            ),
-            Err(ElusivError::InvalidRecipient.into())
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0,
+            )?;
+
+            // `subvention` and `commitment_hash_fee` flow from `pool` to `fee_collector`
+            let settled = data.subvention + data.commitment_hash_fee.0;
+            assert_eq!(pool.lamports(), pool_lamports - settled);
+            assert_eq!(
+                fee_collector.lamports(),
+                fee_collector_lamports + settled + rent
+            );
+
+            // The warden is not rewarded, the user is not charged
+            assert_eq!(f.lamports(), fee_payer_lamports);
+            assert_eq!(recipient.lamports(), recipient_lamports);
+            assert_eq!(
+                optional_fee_collector.lamports(),
+                optional_fee_collector_lamports
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_verification_transfer_lamports_cancelled_settlement() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        finalize_send_test!(
+            LAMPORTS_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            10,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            _i,
+            _r,
+            _f,
+            optional_fee_collector
         );
 
-        // Invalid optional-fee-collector
-        test_account_info!(invalid_optional_fee_collector, 0);
+        account_info!(recipient, Pubkey::new_from_array(recipient_bytes));
+        let fee_payer_pk = Pubkey::new(
+            &VerificationAccount::new(&mut verification_acc_data)
+                .unwrap()
+                .get_other_data()
+                .fee_payer
+                .skip_mr(),
+        );
+        account_info!(f, fee_payer_pk); // fee_payer
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        account_info!(optional_fee_collector, optional_fee_collector);
+        test_account_info!(any, 0);
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
+        );
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
+        CommitmentQueue::new(&mut commitment_queue).admit_pending_commitment(0)?;
+
+        let data = {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_state(&VerificationState::Finalized);
+            v_acc.fail(VerificationFault::Cancelled);
+            v_acc.set_other_data(&mutate(&v_acc.get_other_data(), |data| {
+                data.subvention = 11;
+                data.commitment_hash_fee = Lamports(22);
+            }));
+            v_acc.get_other_data()
+        };
+
+        let rent = v_acc.lamports() + n_pda.lamports();
+        let pool_lamports = pool.lamports();
+        let fee_collector_lamports = fee_collector.lamports();
+        let fee_payer_lamports = f.lamports();
+        let recipient_lamports = recipient.lamports();
+
+        finalize_verification_transfer_lamports(
+            &f,
+            &f,
+            &recipient,
+            &pool,
+            &fee_collector,
+            &optional_fee_collector,
+            &mut commitment_queue,
+            &mut metadata_queue,
+            &v_acc,
+            &n_pda,
+            &any,
+            &any,
+            &governor,
+            &warden_rewards,
+            &any,
+            &any,
+            &any,
+            &mut fee_version_usage,
+            0,
+        )?;
+
+        // Only the `subvention` flows back to `fee_collector`
         assert_eq!(
-            finalize_verification_transfer_lamports(
-                &f,
-                &recipient,
-                &pool,
-                &fee_collector,
-                &invalid_optional_fee_collector,
-                &mut commitment_queue,
-                &mut metadata_queue,
-                &v_acc,
-                &n_pda,
-                &any,
-                0
-            ),
-            Err(ElusivError::InvalidAccount.into())
+            pool.lamports(),
+            pool_lamports - data.subvention - data.commitment_hash_fee.0
+        );
+        assert_eq!(
+            fee_collector.lamports(),
+            fee_collector_lamports + data.subvention
         );
 
-        // Commitment queue is full
-        {
-            let mut queue = CommitmentQueue::new(&mut commitment_queue);
-            for _ in 0..CommitmentQueue::CAPACITY {
-                queue
-                    .enqueue(CommitmentHashRequest {
-                        commitment: [0; 32],
-                        fee_version: 0,
-                        min_batching_rate: 0,
-                    })
-                    .unwrap();
-            }
-        }
+        // The rent and the `commitment_hash_fee` are refunded to the fee payer (also the rent beneficiary here)
         assert_eq!(
-            finalize_verification_transfer_lamports(
-                &f,
-                &recipient,
-                &pool,
-                &fee_collector,
-                &optional_fee_collector,
-                &mut commitment_queue,
-                &mut metadata_queue,
-                &v_acc,
-                &n_pda,
-                &any,
-                0
-            ),
-            Err(ElusivError::QueueIsFull.into())
+            f.lamports(),
+            fee_payer_lamports + rent + data.commitment_hash_fee.0
+        );
+
+        // The user is not charged
+        assert_eq!(recipient.lamports(), recipient_lamports);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_verification_transfer_lamports_merge() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        finalize_send_test!(
+            LAMPORTS_TOKEN_ID,
+            0,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            _i,
+            _r,
+            _f
+        );
+
+        let fee_payer_pk = Pubkey::new(
+            &VerificationAccount::new(&mut verification_acc_data)
+                .unwrap()
+                .get_other_data()
+                .fee_payer
+                .skip_mr(),
+        );
+        account_info!(f, fee_payer_pk); // fee_payer
+        test_account_info!(pool, PoolAccount::SIZE);
+        test_account_info!(fee_collector, 0);
+        test_account_info!(optional_fee_collector, 0);
+        test_account_info!(any, 0);
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
         );
 
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
         zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(governor, GovernorAccount);
+        test_account_info!(warden_rewards, 0);
+
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_state(&VerificationState::Finalized);
+            v_acc.set_is_verified(&ElusivOption::Some(true));
+        }
 
+        // For merges (zero-amount) the recipient key is ignored
+        account_info!(recipient, Pubkey::new_unique());
         assert_eq!(
             finalize_verification_transfer_lamports(
+                &f,
                 &f,
                 &recipient,
                 &pool,
@@ -3211,21 +9230,40 @@ mod tests {
                 &v_acc,
                 &n_pda,
                 &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &any,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Ok(())
         );
 
-        assert_eq!(n_pda.lamports(), 0);
-        assert_eq!(v_acc.lamports(), 0);
-        pda_account!(v_acc, VerificationAccount, v_acc);
-        assert_eq!(v_acc.get_state(), VerificationState::Closed);
+        // The merged output-commitment is enqueued exactly once
+        let min_batching_rate = {
+            pda_account!(v_acc, VerificationAccount, v_acc);
+            v_acc.get_other_data().min_batching_rate
+        };
+        let queue = CommitmentQueue::new(&mut commitment_queue);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(
+            queue.view_first().unwrap(),
+            CommitmentHashRequest {
+                commitment: public_inputs.join_split.output_commitment.reduce(),
+                fee_version: public_inputs.join_split.fee_version,
+                min_batching_rate,
+            }
+        );
 
         Ok(())
     }
 
     #[test]
-    fn test_finalize_verification_transfer_lamports_merge() -> ProgramResult {
+    fn test_finalize_verification_transfer_lamports_epoch_rewards() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
         finalize_send_test!(
             LAMPORTS_TOKEN_ID,
             0,
@@ -3245,7 +9283,8 @@ mod tests {
                 .skip_mr(),
         );
         account_info!(f, fee_payer_pk); // fee_payer
-        test_account_info!(pool, 0);
+        account_info!(recipient, Pubkey::new_unique());
+        test_account_info!(pool, PoolAccount::SIZE);
         test_account_info!(fee_collector, 0);
         test_account_info!(optional_fee_collector, 0);
         test_account_info!(any, 0);
@@ -3261,17 +9300,30 @@ mod tests {
         account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
         zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
         zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+        account_info!(
+            warden_rewards,
+            Pubkey::new_unique(),
+            vec![0; WardenRewardsAccount::SIZE]
+        ); // opened by `fee_payer`
+        clock_account_info!(clock, 250);
+        governor.set_warden_rotation_epoch(&100);
 
         {
             pda_account!(mut v_acc, VerificationAccount, v_acc);
             v_acc.set_state(&VerificationState::Finalized);
             v_acc.set_is_verified(&ElusivOption::Some(true));
+
+            let mut data = v_acc.get_other_data();
+            data.commitment_hash_fee_token = 100;
+            data.proof_verification_fee = 23;
+            v_acc.set_other_data(&data);
         }
+        let fee_payer_lamports = f.lamports() + v_acc.lamports() + n_pda.lamports();
 
-        // For merges (zero-amount) the recipient key is ignored
-        account_info!(recipient, Pubkey::new_unique());
         assert_eq!(
             finalize_verification_transfer_lamports(
+                &f,
                 &f,
                 &recipient,
                 &pool,
@@ -3282,16 +9334,324 @@ mod tests {
                 &v_acc,
                 &n_pda,
                 &any,
+                &any,
+                &governor,
+                &warden_rewards,
+                &clock,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Ok(())
         );
 
+        // The reward is accrued for the current epoch instead of being transferred
+        assert_eq!(f.lamports(), fee_payer_lamports);
+        pda_account!(warden_rewards, WardenRewardsAccount, warden_rewards);
+        assert_eq!(
+            warden_rewards.get_entries(LAMPORTS_TOKEN_ID as usize),
+            WardenRewardEntry {
+                epoch: 2,
+                accrued: 123,
+                claimable: 0,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_accrue_warden_reward() -> ProgramResult {
+        zero_program_account!(mut governor, GovernorAccount);
+        clock_account_info!(clock, 250);
+        account_info!(
+            warden_rewards,
+            Pubkey::new_unique(),
+            vec![0; WardenRewardsAccount::SIZE]
+        );
+
+        // Epoch rewards disabled
+        assert_eq!(
+            accrue_warden_reward(&governor, &clock, &warden_rewards, USDC_TOKEN_ID, 10),
+            Ok(false)
+        );
+
+        // Not opened by the warden
+        governor.set_warden_rotation_epoch(&100);
+        test_account_info!(not_opened, 0);
+        assert_eq!(
+            accrue_warden_reward(&governor, &clock, &not_opened, USDC_TOKEN_ID, 10),
+            Ok(false)
+        );
+        account_info!(
+            foreign,
+            Pubkey::new_unique(),
+            vec![0; WardenRewardsAccount::SIZE],
+            Pubkey::new_unique(),
+            false
+        );
+        assert_eq!(
+            accrue_warden_reward(&governor, &clock, &foreign, USDC_TOKEN_ID, 10),
+            Ok(false)
+        );
+
+        // Token rewards are accrued in token units
+        assert_eq!(
+            accrue_warden_reward(&governor, &clock, &warden_rewards, USDC_TOKEN_ID, 10),
+            Ok(true)
+        );
+        pda_account!(warden_rewards, WardenRewardsAccount, warden_rewards);
+        assert_eq!(
+            warden_rewards.get_entries(USDC_TOKEN_ID as usize),
+            WardenRewardEntry {
+                epoch: 2,
+                accrued: 10,
+                claimable: 0,
+            }
+        );
+        assert_eq!(
+            warden_rewards.get_entries(LAMPORTS_TOKEN_ID as usize),
+            WardenRewardEntry::default()
+        );
+
         Ok(())
     }
 
+    #[test]
+    fn test_claim_epoch_rewards() {
+        account_info!(warden, Pubkey::new_unique());
+        test_pda_account_info!(pool, PoolAccount, None);
+        test_account_info!(any, 0);
+        zero_program_account!(mut governor, GovernorAccount);
+        zero_program_account!(mut warden_rewards, WardenRewardsAccount);
+        governor.set_warden_rotation_epoch(&100);
+        warden_rewards.accrue(LAMPORTS_TOKEN_ID, 2, 123).unwrap();
+
+        // Invalid clock
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &any,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Err(ProgramError::InvalidArgument)
+        );
+
+        // Rewards of the current epoch
+        clock_account_info!(clock, 299);
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &clock,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Err(ElusivError::NoClaimableRewards.into())
+        );
+
+        // Rewards of a completed epoch
+        clock_account_info!(clock, 300);
+
+        // Invalid pool_account
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden,
+                &pool,
+                &any,
+                &governor,
+                &mut warden_rewards,
+                &clock,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Lamports can only be claimed by `warden` itself
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &any,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &clock,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        let warden_lamports = warden.lamports();
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &clock,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Ok(())
+        );
+        assert_eq!(warden.lamports(), warden_lamports + 123);
+
+        // Only once per epoch
+        warden_rewards.accrue(LAMPORTS_TOKEN_ID, 3, 7).unwrap();
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &clock,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Err(ElusivError::NoClaimableRewards.into())
+        );
+
+        // With disabled epoch rewards, all rewards are claimable
+        governor.set_warden_rotation_epoch(&0);
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &any,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Ok(())
+        );
+        assert_eq!(warden.lamports(), warden_lamports + 130);
+        let entry = warden_rewards.get_entries(LAMPORTS_TOKEN_ID as usize);
+        assert_eq!((entry.accrued, entry.claimable), (0, 0));
+    }
+
+    #[test]
+    fn test_claim_epoch_rewards_token() {
+        account_info!(warden, Pubkey::new_unique());
+        account_info!(
+            warden_token,
+            Pubkey::new_unique(),
+            spl_token_account_data(USDC_TOKEN_ID),
+            spl_token::id(),
+            false
+        );
+        test_pda_account_info!(pool, PoolAccount, None);
+        program_token_account_info!(pool_token, PoolAccount, USDC_TOKEN_ID);
+        account_info!(spl, spl_token::id(), vec![]);
+        test_account_info!(any, 0);
+        zero_program_account!(governor, GovernorAccount);
+        zero_program_account!(mut warden_rewards, WardenRewardsAccount);
+        warden_rewards.accrue(USDC_TOKEN_ID, 0, 10).unwrap();
+
+        // Invalid pool_account
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden_token,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &any,
+                &spl,
+                USDC_TOKEN_ID
+            ),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Invalid warden token account
+        account_info!(
+            usdt_token,
+            Pubkey::new_unique(),
+            spl_token_account_data(USDT_TOKEN_ID),
+            spl_token::id(),
+            false
+        );
+        for invalid_account in [&warden, &usdt_token] {
+            assert_eq!(
+                claim_epoch_rewards(
+                    &warden,
+                    invalid_account,
+                    &pool,
+                    &pool_token,
+                    &governor,
+                    &mut warden_rewards,
+                    &any,
+                    &spl,
+                    USDC_TOKEN_ID
+                ),
+                Err(ElusivError::InvalidAccount.into())
+            );
+        }
+
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden_token,
+                &pool,
+                &pool_token,
+                &governor,
+                &mut warden_rewards,
+                &any,
+                &spl,
+                USDC_TOKEN_ID
+            ),
+            Ok(())
+        );
+
+        let entry = warden_rewards.get_entries(USDC_TOKEN_ID as usize);
+        assert_eq!((entry.accrued, entry.claimable), (0, 0));
+
+        // Lamports are claimed separately
+        assert_eq!(
+            claim_epoch_rewards(
+                &warden,
+                &warden,
+                &pool,
+                &pool,
+                &governor,
+                &mut warden_rewards,
+                &any,
+                &any,
+                LAMPORTS_TOKEN_ID
+            ),
+            Err(ElusivError::NoClaimableRewards.into())
+        );
+    }
+
     #[test]
     fn test_finalize_verification_transfer_token() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Admitted verifications are finalized with the prices locked at the admission, also while the oracle is degraded
+        governor.set_oracle_degraded(&true);
         finalize_send_test!(
             USDC_TOKEN_ID,
             LAMPORTS_PER_SOL,
@@ -3355,11 +9715,46 @@ mod tests {
             v_acc.set_is_verified(&ElusivOption::Some(true));
         }
 
+        // Recipient is a PDA closed by the finalization
+        for invalid_recipient in [&n_pda, &v_acc] {
+            assert_eq!(
+                finalize_verification_transfer_token(
+                    &fee_payer,
+                    &fee_payer_token,
+                    &fee_payer,
+                    invalid_recipient,
+                    &r,
+                    &pool,
+                    &pool_token,
+                    &fee_collector,
+                    &fee_collector_token,
+                    &optional_fee_collector,
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    &v_acc,
+                    &n_pda,
+                    &any,
+                    &spl,
+                    &any,
+                    &any,
+                    &any,
+                    &any,
+                    &governor,
+                    &any,
+                    &any,
+                    &mut fee_version_usage,
+                    0
+                ),
+                Err(ElusivError::InvalidAccount.into())
+            );
+        }
+
         // Invalid pool_account
         assert_eq!(
             finalize_verification_transfer_token(
                 &fee_payer,
                 &fee_payer_token,
+                &fee_payer,
                 &r,
                 &r,
                 &pool,
@@ -3371,9 +9766,16 @@ mod tests {
                 &mut metadata_queue,
                 &v_acc,
                 &n_pda,
+                &any,
                 &spl,
                 &any,
                 &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Err(ElusivError::InvalidAccount.into())
@@ -3384,6 +9786,7 @@ mod tests {
             finalize_verification_transfer_token(
                 &fee_payer,
                 &fee_payer_token,
+                &fee_payer,
                 &r,
                 &r,
                 &pool,
@@ -3395,9 +9798,16 @@ mod tests {
                 &mut metadata_queue,
                 &v_acc,
                 &n_pda,
+                &any,
                 &spl,
                 &any,
                 &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Err(ElusivError::InvalidAccount.into())
@@ -3409,6 +9819,7 @@ mod tests {
             finalize_verification_transfer_token(
                 &fee_payer,
                 &fee_payer_token,
+                &fee_payer,
                 &r,
                 &r,
                 &pool,
@@ -3420,9 +9831,16 @@ mod tests {
                 &mut metadata_queue,
                 &v_acc,
                 &n_pda,
+                &any,
                 &spl,
                 &any,
                 &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Err(ElusivError::InvalidAccount.into())
@@ -3433,6 +9851,7 @@ mod tests {
             finalize_verification_transfer_token(
                 &fee_payer,
                 &fee_payer_token,
+                &fee_payer,
                 &r,
                 &r,
                 &pool,
@@ -3447,6 +9866,13 @@ mod tests {
                 &any,
                 &any,
                 &any,
+                &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Err(ElusivError::InvalidAccount.into())
@@ -3457,6 +9883,7 @@ mod tests {
             finalize_verification_transfer_token(
                 &any,
                 &fee_payer_token,
+                &any,
                 &r,
                 &r,
                 &pool,
@@ -3468,9 +9895,16 @@ mod tests {
                 &mut metadata_queue,
                 &v_acc,
                 &n_pda,
+                &any,
                 &spl,
                 &any,
                 &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Err(ElusivError::InvalidAccount.into())
@@ -3481,6 +9915,7 @@ mod tests {
             finalize_verification_transfer_token(
                 &fee_payer,
                 &fee_payer_token,
+                &fee_payer,
                 &any,
                 &r,
                 &pool,
@@ -3492,18 +9927,72 @@ mod tests {
                 &mut metadata_queue,
                 &v_acc,
                 &n_pda,
+                &any,
                 &spl,
                 &any,
                 &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Err(ElusivError::InvalidRecipient.into())
         );
 
+        // Stored token_id diverges from the request's token_id
+        for token_id in [LAMPORTS_TOKEN_ID, USDT_TOKEN_ID] {
+            {
+                pda_account!(mut v_acc, VerificationAccount, v_acc);
+                v_acc.set_other_data(&mutate(&v_acc.get_other_data(), |d| d.token_id = token_id));
+            }
+
+            assert_eq!(
+                finalize_verification_transfer_token(
+                    &fee_payer,
+                    &fee_payer_token,
+                    &fee_payer,
+                    &r,
+                    &r,
+                    &pool,
+                    &pool_token,
+                    &fee_collector,
+                    &fee_collector_token,
+                    &optional_fee_collector,
+                    &mut commitment_queue,
+                    &mut metadata_queue,
+                    &v_acc,
+                    &n_pda,
+                    &any,
+                    &spl,
+                    &any,
+                    &any,
+                    &any,
+                    &any,
+                    &governor,
+                    &any,
+                    &any,
+                    &mut fee_version_usage,
+                    0
+                ),
+                Err(ElusivError::InvalidAccountState.into())
+            );
+        }
+
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_other_data(&mutate(&v_acc.get_other_data(), |d| {
+                d.token_id = USDC_TOKEN_ID
+            }));
+        }
+
         assert_eq!(
             finalize_verification_transfer_token(
                 &fee_payer,
                 &fee_payer_token,
+                &fee_payer,
                 &r,
                 &r,
                 &pool,
@@ -3515,9 +10004,16 @@ mod tests {
                 &mut metadata_queue,
                 &v_acc,
                 &n_pda,
+                &any,
                 &spl,
                 &any,
                 &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Ok(())
@@ -3526,13 +10022,15 @@ mod tests {
         assert_eq!(n_pda.lamports(), 0);
         assert_eq!(v_acc.lamports(), 0);
         pda_account!(v_acc, VerificationAccount, v_acc);
-        assert_eq!(v_acc.get_state(), VerificationState::Closed);
+        assert_eq!(v_acc.get_state().unwrap(), VerificationState::Closed);
 
         Ok(())
     }
 
     #[test]
     fn test_finalize_verification_transfer_token_merge() -> ProgramResult {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(governor, GovernorAccount);
         finalize_send_test!(
             USDC_TOKEN_ID,
             0,
@@ -3591,6 +10089,7 @@ mod tests {
             finalize_verification_transfer_token(
                 &fee_payer,
                 &fee_payer_token,
+                &fee_payer,
                 &r,
                 &r,
                 &pool,
@@ -3602,9 +10101,16 @@ mod tests {
                 &mut metadata_queue,
                 &v_acc,
                 &n_pda,
+                &any,
                 &spl,
                 &any,
                 &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
                 0
             ),
             Ok(())
@@ -3613,12 +10119,194 @@ mod tests {
         Ok(())
     }
 
+    /// Finalizes a valid or invalid token-send to an associated-token-account
+    /// - returns the Lamports of (`fee_payer`, `recipient`, `pool`, `fee_collector`) before and after the finalization
+    fn finalize_token_send_to_associated_token_account(
+        is_verified: bool,
+        recipient_lamports: u64,
+    ) -> Result<([u64; 4], [u64; 4]), ProgramError> {
+        zero_program_account!(mut fee_version_usage, FeeVersionUsageAccount);
+        zero_program_account!(governor, GovernorAccount);
+        finalize_send_test!(
+            USDC_TOKEN_ID,
+            LAMPORTS_PER_SOL,
+            0,
+            public_inputs,
+            verification_acc_data,
+            recipient_bytes,
+            _i,
+            _r,
+            _f,
+            _optional_fee_collector
+        );
+
+        let wallet = Pubkey::new_from_array(recipient_bytes);
+        let mint = elusiv_token(USDC_TOKEN_ID).unwrap().mint;
+        account_info!(r_wallet, wallet);
+        // (the associated-token-program CPI is not executed in unit tests, so `r` already has the token-program as owner)
+        account_info!(
+            r,
+            spl_associated_token_account::get_associated_token_address(&wallet, &mint),
+            vec![],
+            spl_token::id(),
+            false
+        );
+        account_info!(mint_account, mint);
+        **r.try_borrow_mut_lamports().unwrap() = recipient_lamports;
+
+        // The finalizing signer holds no Lamports
+        let fee_payer_pk = Pubkey::new(
+            &VerificationAccount::new(&mut verification_acc_data)
+                .unwrap()
+                .get_other_data()
+                .fee_payer
+                .skip_mr(),
+        );
+        account_info!(fee_payer, fee_payer_pk, vec![]);
+        **fee_payer.try_borrow_mut_lamports().unwrap() = 0;
+        account_info!(
+            fee_payer_token,
+            fee_payer_pk,
+            vec![],
+            spl_token::id(),
+            false
+        );
+
+        test_pda_account_info!(pool, PoolAccount, None);
+        test_pda_account_info!(fee_collector, FeeCollectorAccount, None);
+        program_token_account_info!(pool_token, PoolAccount, USDC_TOKEN_ID);
+        program_token_account_info!(fee_collector_token, FeeCollectorAccount, USDC_TOKEN_ID);
+
+        test_account_info!(any, 0);
+        account_info!(spl, spl_token::id(), vec![]);
+        test_pda_account_info!(
+            n_pda,
+            NullifierDuplicateAccount,
+            public_inputs
+                .join_split
+                .associated_nullifier_duplicate_pda_pubkey(),
+            None
+        );
+        account_info!(v_acc, Pubkey::new_unique(), verification_acc_data);
+        zero_program_account!(mut commitment_queue, CommitmentQueueAccount);
+        zero_program_account!(mut metadata_queue, MetadataQueueAccount);
+
+        {
+            pda_account!(mut v_acc, VerificationAccount, v_acc);
+            v_acc.set_request(&ProofRequest::Send(SendPublicInputs {
+                recipient_is_associated_token_account: true,
+                ..public_inputs
+            }));
+            v_acc.set_state(&VerificationState::Finalized);
+            v_acc.set_is_verified(&ElusivOption::Some(is_verified));
+        }
+
+        // Rent of the closed PDAs flows to `fee_payer` (rent beneficiary) or `fee_collector`
+        let closed_rent = v_acc.lamports() + n_pda.lamports();
+        let lamports = |closed: bool| {
+            [
+                fee_payer.lamports(),
+                r.lamports(),
+                pool.lamports(),
+                fee_collector.lamports()
+                    - if closed && !is_verified {
+                        closed_rent
+                    } else {
+                        0
+                    },
+            ]
+        };
+        let before = lamports(false);
+
+        assert_eq!(
+            finalize_verification_transfer_token(
+                &fee_payer,
+                &fee_payer_token,
+                &fee_payer,
+                &r,
+                &r_wallet,
+                &pool,
+                &pool_token,
+                &fee_collector,
+                &fee_collector_token,
+                &any,
+                &mut commitment_queue,
+                &mut metadata_queue,
+                &v_acc,
+                &n_pda,
+                &any,
+                &spl,
+                &mint_account,
+                &any,
+                &any,
+                &any,
+                &governor,
+                &any,
+                &any,
+                &mut fee_version_usage,
+                0
+            ),
+            Ok(())
+        );
+
+        let mut after = lamports(true);
+        if is_verified {
+            after[0] -= closed_rent;
+        }
+
+        Ok((before, after))
+    }
+
+    #[test]
+    fn test_finalize_verification_transfer_token_associated_token_account() {
+        let rent = spl_token_account_rent().unwrap().0;
+        assert!(rent > 0);
+
+        // Non-existing ATA: funded from the `pool` escrow (the signer has no Lamports)
+        let (before, after) = finalize_token_send_to_associated_token_account(true, 0).unwrap();
+        assert_eq!(after[0], before[0]);
+        assert_eq!(after[1], rent);
+        assert_eq!(after[2], before[2] - rent);
+        assert_eq!(after[3], before[3]);
+
+        // Existing ATA: the escrow is returned to `fee_payer`
+        let (before, after) = finalize_token_send_to_associated_token_account(true, 1).unwrap();
+        assert_eq!(after[0], before[0] + rent);
+        assert_eq!(after[1], 1);
+        assert_eq!(after[2], before[2] - rent);
+        assert_eq!(after[3], before[3]);
+
+        // Invalid proof: the escrow flows to `fee_collector` (with the `commitment_hash_fee`)
+        let (before, after) = finalize_token_send_to_associated_token_account(false, 0).unwrap();
+        assert_eq!(after[0], before[0]);
+        assert_eq!(after[1], 0);
+        assert_eq!(after[2], before[2] - rent);
+        assert_eq!(after[3], before[3] + rent);
+    }
+
     #[test]
     fn test_is_timestamp_valid() {
         assert!(is_timestamp_valid(0, 1));
         assert!(is_timestamp_valid(two_pow!(5) as u64 - 1, 0));
-
-        assert!(!is_timestamp_valid(two_pow!(5) as u64, 0));
+        assert!(is_timestamp_valid(two_pow!(5) as u64, 0));
+
+        // Future timestamps
+        let now = 1_700_000_000;
+        assert!(is_timestamp_valid(now, now));
+        assert!(is_timestamp_valid(now + 119, now));
+        assert!(is_timestamp_valid(now + 120, now));
+        assert!(!is_timestamp_valid(now + 121, now));
+
+        assert!(!is_timestamp_valid(u64::MAX, now));
+        assert!(is_timestamp_valid(u64::MAX, u64::MAX));
+        assert!(is_timestamp_valid(
+            u64::MAX,
+            u64::MAX - MAX_FUTURE_TOLERANCE_SECONDS
+        ));
+        assert!(!is_timestamp_valid(
+            u64::MAX,
+            u64::MAX - MAX_FUTURE_TOLERANCE_SECONDS - 1
+        ));
     }
 
     #[test]
@@ -3762,7 +10450,7 @@ mod tests {
                     &public_inputs,
                     &storage,
                     [&n_account, &n_account],
-                    &[0, 1]
+                    &[0, 1].map(GlobalTreeIndex)
                 ),
                 Err(err.into())
             );
@@ -3785,7 +10473,7 @@ mod tests {
                 }),
                 &storage,
                 [&n_account, &n_account],
-                &[0, 0]
+                &[0, 0].map(GlobalTreeIndex)
             ),
             Err(ElusivError::InvalidInstructionData.into())
         );
@@ -3796,7 +10484,7 @@ mod tests {
                 &valid_inputs,
                 &storage,
                 [&n_account, &n_account],
-                &[0, 1]
+                &[0, 1].map(GlobalTreeIndex)
             ),
             Ok(())
         );
@@ -3830,7 +10518,7 @@ mod tests {
                     &public_inputs,
                     &storage,
                     [&n_account, &n_account],
-                    &[0, 1]
+                    &[0, 1].map(GlobalTreeIndex)
                 ),
                 Ok(())
             );
@@ -3860,12 +10548,222 @@ mod tests {
                 }),
                 &storage,
                 [&n_account, &n_account],
-                &[0, 1]
+                &[0, 1].map(GlobalTreeIndex)
             ),
             Err(ElusivError::CouldNotInsertNullifier.into())
         );
     }
 
+    #[test]
+    fn test_local_tree_slot() {
+        assert_eq!(LocalTreeSlot::new(0), Some(LocalTreeSlot(0)));
+        assert_eq!(
+            LocalTreeSlot::new(MAX_MT_COUNT - 1),
+            Some(LocalTreeSlot(MAX_MT_COUNT - 1))
+        );
+        assert_eq!(LocalTreeSlot::new(MAX_MT_COUNT), None);
+
+        assert_eq!(LocalTreeSlot(0).get(&[3, 4]), 3);
+        assert_eq!(LocalTreeSlot(1).get(&[3, 4]), 4);
+    }
+
+    #[test]
+    fn test_check_join_split_public_inputs_tree_slots() {
+        parent_account!(mut storage, StorageAccount);
+        parent_account!(n_account, NullifierAccount);
+
+        let commitments_count = 1000;
+        storage.set_next_commitment_ptr(&commitments_count);
+        storage.set_node(&empty_root_raw().reduce(), 0, 0).unwrap();
+
+        let active_root = Some(empty_root_raw());
+        let closed_root = Some(RawU256::new(n_account.get_root()));
+        let inputs = |roots: &[Option<RawU256>]| JoinSplitPublicInputs {
+            input_commitments: roots
+                .iter()
+                .enumerate()
+                .map(|(i, root)| InputCommitment {
+                    root: *root,
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr(&i.to_string())),
+                })
+                .collect(),
+            output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+            recent_commitment_index: commitments_count,
+            fee_version: 0,
+            amount: 1000,
+            fee: 456,
+            optional_fee: OptionalFee::default(),
+            token_id: 0,
+            metadata: CommitmentMetadata::default(),
+        };
+
+        let cases = [
+            // The first slot references the active MT
+            (vec![active_root, closed_root], [0, 1], Ok(())),
+            (vec![active_root, None], [0, 1], Ok(())),
+            // The second slot references the active MT
+            (vec![closed_root, active_root], [1, 0], Ok(())),
+            (vec![closed_root, None], [1, 0], Ok(())),
+            // The global index (not the slot) determines whether a MT is active
+            (
+                vec![active_root, closed_root],
+                [1, 0],
+                Err(ElusivError::InvalidMerkleRoot),
+            ),
+            (
+                vec![active_root],
+                [1, 0],
+                Err(ElusivError::InvalidMerkleRoot),
+            ),
+            // More MTs than slots
+            (
+                vec![active_root, closed_root, closed_root],
+                [0, 1],
+                Err(ElusivError::InvalidPublicInputs),
+            ),
+        ];
+
+        for (roots, tree_indices, result) in cases {
+            assert_eq!(
+                check_join_split_public_inputs(
+                    &inputs(&roots),
+                    &storage,
+                    [&n_account, &n_account],
+                    &tree_indices.map(GlobalTreeIndex)
+                ),
+                result.map_err(|e| e.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_join_split_public_inputs_fuzz() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        parent_account!(mut storage, StorageAccount);
+        parent_account!(mut n_account0, NullifierAccount);
+        parent_account!(mut n_account1, NullifierAccount);
+
+        let commitments_count = 1000;
+        storage.set_next_commitment_ptr(&commitments_count);
+        storage.set_node(&empty_root_raw().reduce(), 0, 0).unwrap();
+
+        let active_root = empty_root_raw();
+        let closed_root = RawU256::new(n_account1.get_root());
+        let invalid_root = RawU256::new(u256_from_str_skip_mr("123"));
+
+        // Nullifier-hashes inserted into the MT with global index `0` (active) and `1` (closed)
+        let mut inserted: [HashSet<U256>; 2] = Default::default();
+        let mut accepted_count = 0;
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..10_000 {
+            let arity = rng.gen_range(1..=JOIN_SPLIT_MAX_N_ARITY + 1);
+            let input_commitments: Vec<InputCommitment> = (0..arity)
+                .map(|_| {
+                    // Every second nullifier-hash is from a small range to produce duplicates
+                    let nullifier_range = if rng.gen() { 8 } else { 4096 };
+                    InputCommitment {
+                        root: match rng.gen_range(0..6) {
+                            0 | 1 => None,
+                            2 | 3 => Some(active_root),
+                            4 => Some(closed_root),
+                            _ => Some(invalid_root),
+                        },
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr(
+                            &rng.gen_range(0..nullifier_range).to_string(),
+                        )),
+                    }
+                })
+                .collect();
+            let tree_indices = [rng.gen_range(0..2), rng.gen_range(0..2)];
+            let public_inputs = JoinSplitPublicInputs {
+                input_commitments,
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: commitments_count,
+                fee_version: 0,
+                amount: 1000,
+                fee: 456,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            };
+
+            let result = {
+                let n_accounts = [&n_account0, &n_account1];
+                check_join_split_public_inputs(
+                    &public_inputs,
+                    &storage,
+                    tree_indices.map(|i| n_accounts[i]),
+                    &tree_indices.map(|i| GlobalTreeIndex(i as u32)),
+                )
+            };
+            if result.is_err() {
+                continue;
+            }
+            accepted_count += 1;
+
+            // Primary root, arity and MT-count are bounded
+            let roots_count = public_inputs
+                .input_commitments
+                .iter()
+                .filter(|c| c.root.is_some())
+                .count();
+            assert!(public_inputs.input_commitments[0].root.is_some());
+            assert!(public_inputs.input_commitments.len() <= JOIN_SPLIT_MAX_N_ARITY);
+            assert!(roots_count <= MAX_MT_COUNT);
+            if roots_count > 1 {
+                assert_ne!(tree_indices[0], tree_indices[1]);
+            }
+
+            // Each nullifier-hash is new and unique within its MT
+            // - a root opens the next slot, `None` references the first slot
+            let mut roots_seen = 0;
+            let mut nullifier_hashes = Vec::new();
+            for input_commitment in &public_inputs.input_commitments {
+                let slot = match input_commitment.root {
+                    Some(_) => {
+                        roots_seen += 1;
+                        roots_seen - 1
+                    }
+                    None => 0,
+                };
+                let tree = tree_indices[slot];
+                let nullifier_hash = input_commitment.nullifier_hash.reduce();
+
+                assert!(!nullifier_hashes.contains(&(slot, nullifier_hash)));
+                assert!(!inserted[tree].contains(&nullifier_hash));
+                nullifier_hashes.push((slot, nullifier_hash));
+            }
+
+            // Inserting the nullifier-hashes leaves the nullifier-accounts consistent with the accepted inputs
+            for (slot, nullifier_hash) in nullifier_hashes {
+                let tree = tree_indices[slot];
+                let n_account = if tree == 0 {
+                    &mut n_account0
+                } else {
+                    &mut n_account1
+                };
+                n_account.try_insert_nullifier_hash(nullifier_hash).unwrap();
+                assert!(!n_account.can_insert_nullifier_hash(nullifier_hash).unwrap());
+                inserted[tree].insert(nullifier_hash);
+            }
+
+            let n_accounts = [&n_account0, &n_account1];
+            assert_eq!(
+                check_join_split_public_inputs(
+                    &public_inputs,
+                    &storage,
+                    tree_indices.map(|i| n_accounts[i]),
+                    &tree_indices.map(|i| GlobalTreeIndex(i as u32)),
+                ),
+                Err(ElusivError::CouldNotInsertNullifier.into())
+            );
+        }
+
+        assert!(accepted_count > 0);
+    }
+
     struct StubInstruction(u8, Option<Vec<u8>>, Pubkey);
 
     impl From<StubInstruction> for Instruction {
@@ -4300,6 +11198,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_finalize_event_fields() {
+        let mut data = VerificationAccountData::default();
+        assert_eq!(
+            finalize_event_fields(&data, &[0; 16]),
+            vec![b"finalize".to_vec(), vec![0; 16]]
+        );
+
+        data.amount_usd = ElusivOption::Some(39_000_000);
+        assert_eq!(
+            finalize_event_fields(&data, &[42; 16]),
+            vec![
+                b"finalize".to_vec(),
+                vec![42; 16],
+                39_000_000u64.to_le_bytes().to_vec()
+            ]
+        );
+    }
+
     #[test]
     fn test_get_memo_from_instructions() {
         let instruction =
@@ -4377,25 +11294,25 @@ mod tests {
         )
     }
 
-    fn test_public_inputs() -> Vec<U256> {
-        vec![
-            "7889586699914970744657798935358222218486353295005298675075639741334684257960",
-            "9606705614694883961284553030253534686862979817135488577431113592919470999200",
-            "3274987707755874055218761963679216380632837922347165546870932041376197622893",
-            "21565952902710874749074047612627661909010394770856499168277361914501522149919",
-            "18505238634407118839447741044834397583809065182892598442650259184768108193880",
-            "908158097066600914673776144051668000794530280731188389204488968169884520703",
-            "908158097066600914673776144051668000794530280731188389204488968169884520703",
-            "0",
-            "31050663472191212195134159867832583323",
-            "120000",
-            "1657140479",
-            "1",
-            "2",
-            "241513166508321350627618709707967777063380694253583200648944705250489865558",
-        ]
-        .iter()
-        .map(|s| u256_from_str_skip_mr(s))
-        .collect()
+    fn test_request() -> SendPublicInputs {
+        SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(empty_root_raw()),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("1")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("1")),
+                recent_commitment_index: 123,
+                fee_version: 0,
+                amount: LAMPORTS_PER_SOL,
+                fee: 0,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: false,
+            hashed_inputs: u256_from_str_skip_mr("1"),
+            solana_pay_transfer: false,
+        }
     }
 }
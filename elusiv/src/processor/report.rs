@@ -0,0 +1,404 @@
+use super::utils::verify_program_token_account;
+use crate::bytes::BorshSerDeSized;
+use crate::error::ElusivError;
+use crate::macros::guard;
+use crate::token::{elusiv_token, TokenError, TokenID, SPL_TOKEN_COUNT};
+use crate::types::U256;
+use borsh::{BorshDeserialize, BorshSerialize};
+use elusiv_derive::BorshSerDeSized;
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, program_pack::Pack,
+    sysvar::Sysvar,
+};
+
+/// Snapshot of the balances of a program-owned PDA (pool or fee collector) across all tokens
+#[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone, Default)]
+#[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
+pub struct PoolBalanceReport {
+    pub slot: u64,
+    pub lamports: u64,
+
+    /// Balances of the SPL-token accounts (indexed by `token_id - 1`)
+    pub token_amounts: [u64; SPL_TOKEN_COUNT],
+
+    /// Outstanding obligations (indexed by `token_id`)
+    ///
+    /// # Note
+    ///
+    /// Obligations are not tracked yet, so these are always zero.
+    pub obligations: [u64; SPL_TOKEN_COUNT + 1],
+}
+
+impl PoolBalanceReport {
+    pub const TAG: &'static [u8] = b"pool_balance_report";
+}
+
+/// Writes a [`PoolBalanceReport`] of the pool into `report_account`
+#[allow(clippy::too_many_arguments)]
+pub fn report_pool_balances<'a>(
+    pool: &AccountInfo<'a>,
+    pool_token_account1: &AccountInfo<'a>,
+    pool_token_account2: &AccountInfo<'a>,
+    pool_token_account3: &AccountInfo<'a>,
+    pool_token_account4: &AccountInfo<'a>,
+    pool_token_account5: &AccountInfo<'a>,
+    pool_token_account6: &AccountInfo<'a>,
+    pool_token_account7: &AccountInfo<'a>,
+    pool_token_account8: &AccountInfo<'a>,
+    report_account: &AccountInfo<'a>,
+    clock: &AccountInfo<'a>,
+    emit_event: bool,
+) -> ProgramResult {
+    report_balances(
+        pool,
+        [
+            pool_token_account1,
+            pool_token_account2,
+            pool_token_account3,
+            pool_token_account4,
+            pool_token_account5,
+            pool_token_account6,
+            pool_token_account7,
+            pool_token_account8,
+        ],
+        report_account,
+        clock,
+        emit_event,
+    )
+}
+
+/// Writes a [`PoolBalanceReport`] of the fee collector into `report_account`
+#[allow(clippy::too_many_arguments)]
+pub fn report_fee_collector_balances<'a>(
+    fee_collector: &AccountInfo<'a>,
+    fee_collector_token_account1: &AccountInfo<'a>,
+    fee_collector_token_account2: &AccountInfo<'a>,
+    fee_collector_token_account3: &AccountInfo<'a>,
+    fee_collector_token_account4: &AccountInfo<'a>,
+    fee_collector_token_account5: &AccountInfo<'a>,
+    fee_collector_token_account6: &AccountInfo<'a>,
+    fee_collector_token_account7: &AccountInfo<'a>,
+    fee_collector_token_account8: &AccountInfo<'a>,
+    report_account: &AccountInfo<'a>,
+    clock: &AccountInfo<'a>,
+    emit_event: bool,
+) -> ProgramResult {
+    report_balances(
+        fee_collector,
+        [
+            fee_collector_token_account1,
+            fee_collector_token_account2,
+            fee_collector_token_account3,
+            fee_collector_token_account4,
+            fee_collector_token_account5,
+            fee_collector_token_account6,
+            fee_collector_token_account7,
+            fee_collector_token_account8,
+        ],
+        report_account,
+        clock,
+        emit_event,
+    )
+}
+
+fn report_balances<'a>(
+    owner_pda: &AccountInfo<'a>,
+    token_accounts: [&AccountInfo<'a>; SPL_TOKEN_COUNT],
+    report_account: &AccountInfo<'a>,
+    clock: &AccountInfo<'a>,
+    emit_event: bool,
+) -> ProgramResult {
+    guard!(
+        report_account.data_len() == PoolBalanceReport::SIZE,
+        ElusivError::InvalidAccount
+    );
+
+    let mut report = PoolBalanceReport {
+        slot: Clock::from_account_info(clock)?.slot,
+        lamports: owner_pda.lamports(),
+        ..Default::default()
+    };
+
+    for (i, token_account) in token_accounts.iter().enumerate() {
+        verify_program_token_account(owner_pda, token_account, i as u16 + 1)?;
+        report.token_amounts[i] =
+            spl_token::state::Account::unpack(&token_account.data.borrow())?.amount;
+    }
+
+    let data = report.try_to_vec()?;
+    report_account.data.borrow_mut().copy_from_slice(&data);
+
+    if emit_event {
+        solana_program::log::sol_log_data(&[PoolBalanceReport::TAG, &data]);
+    }
+
+    Ok(())
+}
+
+/// A token supported by the program (the lamports pseudo-token has the id zero)
+#[derive(BorshDeserialize, BorshSerialize, PartialEq, Clone)]
+#[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
+pub struct TokenListEntry {
+    pub token_id: TokenID,
+    pub mint: U256,
+    pub decimals: u8,
+}
+
+impl TokenListEntry {
+    pub const TAG: &'static [u8] = b"token_list";
+}
+
+/// All tokens supported by the program, ordered by their token-id
+pub fn token_list() -> Result<Vec<TokenListEntry>, TokenError> {
+    (0..=SPL_TOKEN_COUNT as TokenID)
+        .map(|token_id| {
+            let token = elusiv_token(token_id)?;
+
+            Ok(TokenListEntry {
+                token_id,
+                mint: token.mint.to_bytes(),
+                decimals: token.decimals,
+            })
+        })
+        .collect()
+}
+
+/// Logs the Borsh-serialized [`token_list`]
+pub fn list_tokens() -> ProgramResult {
+    solana_program::log::sol_log_data(&[TokenListEntry::TAG, &token_list()?.try_to_vec()?]);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::{account_info, clock_account_info};
+    use crate::processor::program_token_account_address;
+    use crate::state::governor::{FeeCollectorAccount, PoolAccount};
+    use crate::token::{lamports_token, spl_token_account_data, LAMPORTS_TOKEN_ID, TOKENS};
+    use elusiv_types::PDAAccount;
+    use solana_program::pubkey::Pubkey;
+
+    fn token_account_data(token_id: u16, amount: u64) -> Vec<u8> {
+        let mut data = spl_token_account_data(token_id);
+        let mut account = spl_token::state::Account::unpack(&data).unwrap();
+        account.amount = amount;
+        spl_token::state::Account::pack(account, &mut data).unwrap();
+        data
+    }
+
+    /// Creates the program-token-accounts of `$pda_ty` for all SPL-tokens holding `$amounts`
+    macro_rules! staged_token_accounts {
+        ($id: ident, $pda_ty: ty, $amounts: expr) => {
+            let keys: Vec<Pubkey> = (1..=SPL_TOKEN_COUNT as u16)
+                .map(|token_id| program_token_account_address::<$pda_ty>(token_id, None).unwrap())
+                .collect();
+            let mut data: Vec<Vec<u8>> = $amounts
+                .iter()
+                .enumerate()
+                .map(|(i, amount)| token_account_data(i as u16 + 1, *amount))
+                .collect();
+            let mut lamports = vec![0; SPL_TOKEN_COUNT];
+            let owner = spl_token::id();
+            let $id: Vec<AccountInfo> = keys
+                .iter()
+                .zip(data.iter_mut())
+                .zip(lamports.iter_mut())
+                .map(|((key, data), lamports)| {
+                    AccountInfo::new(key, false, false, lamports, data, &owner, false, 0)
+                })
+                .collect();
+        };
+    }
+
+    fn report_pool<'a>(
+        pool: &AccountInfo<'a>,
+        token_accounts: &[AccountInfo<'a>],
+        report_account: &AccountInfo<'a>,
+        clock: &AccountInfo<'a>,
+        emit_event: bool,
+    ) -> ProgramResult {
+        report_pool_balances(
+            pool,
+            &token_accounts[0],
+            &token_accounts[1],
+            &token_accounts[2],
+            &token_accounts[3],
+            &token_accounts[4],
+            &token_accounts[5],
+            &token_accounts[6],
+            &token_accounts[7],
+            report_account,
+            clock,
+            emit_event,
+        )
+    }
+
+    fn amounts() -> [u64; SPL_TOKEN_COUNT] {
+        let mut amounts = [0; SPL_TOKEN_COUNT];
+        for (i, amount) in amounts.iter_mut().enumerate() {
+            *amount = (i as u64 + 1) * 1_000_000 + i as u64;
+        }
+        amounts
+    }
+
+    #[test]
+    fn test_report_pool_balances() {
+        let amounts = amounts();
+        account_info!(pool, PoolAccount::find(None).0, vec![]);
+        staged_token_accounts!(token_accounts, PoolAccount, amounts);
+        account_info!(
+            report,
+            Pubkey::new_unique(),
+            vec![0; PoolBalanceReport::SIZE]
+        );
+        clock_account_info!(clock, 1234);
+
+        // Invalid report account size
+        account_info!(
+            invalid_report,
+            Pubkey::new_unique(),
+            vec![0; PoolBalanceReport::SIZE + 1]
+        );
+        assert_eq!(
+            report_pool(&pool, &token_accounts, &invalid_report, &clock, false),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        report_pool(&pool, &token_accounts, &report, &clock, true).unwrap();
+
+        let report = PoolBalanceReport::try_from_slice(&report.data.borrow()).unwrap();
+        assert_eq!(
+            report,
+            PoolBalanceReport {
+                slot: 1234,
+                lamports: pool.lamports(),
+                token_amounts: amounts,
+                obligations: [0; SPL_TOKEN_COUNT + 1],
+            }
+        );
+    }
+
+    #[test]
+    fn test_report_fee_collector_balances() {
+        let amounts = amounts();
+        account_info!(fee_collector, FeeCollectorAccount::find(None).0, vec![]);
+        staged_token_accounts!(a, FeeCollectorAccount, amounts);
+        account_info!(
+            report,
+            Pubkey::new_unique(),
+            vec![0; PoolBalanceReport::SIZE]
+        );
+        clock_account_info!(clock, 5);
+
+        report_fee_collector_balances(
+            &fee_collector,
+            &a[0],
+            &a[1],
+            &a[2],
+            &a[3],
+            &a[4],
+            &a[5],
+            &a[6],
+            &a[7],
+            &report,
+            &clock,
+            false,
+        )
+        .unwrap();
+
+        let report = PoolBalanceReport::try_from_slice(&report.data.borrow()).unwrap();
+        assert_eq!(report.slot, 5);
+        assert_eq!(report.lamports, fee_collector.lamports());
+        assert_eq!(report.token_amounts, amounts);
+    }
+
+    #[test]
+    fn test_report_invalid_token_accounts() {
+        let amounts = amounts();
+        account_info!(pool, PoolAccount::find(None).0, vec![]);
+        staged_token_accounts!(token_accounts, PoolAccount, amounts);
+        staged_token_accounts!(fee_collector_accounts, FeeCollectorAccount, amounts);
+        account_info!(
+            report,
+            Pubkey::new_unique(),
+            vec![0; PoolBalanceReport::SIZE]
+        );
+        clock_account_info!(clock, 0);
+
+        // Swapped token accounts
+        let mut swapped = token_accounts.clone();
+        swapped.swap(0, 1);
+        assert_eq!(
+            report_pool(&pool, &swapped, &report, &clock, false),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Token account of a different PDA
+        let mut substituted = token_accounts.clone();
+        substituted[SPL_TOKEN_COUNT - 1] = fee_collector_accounts[SPL_TOKEN_COUNT - 1].clone();
+        assert_eq!(
+            report_pool(&pool, &substituted, &report, &clock, false),
+            Err(ElusivError::InvalidAccount.into())
+        );
+
+        // Uninitialized token account
+        let mut uninitialized = token_accounts.clone();
+        let mut data: [u8; 0] = [];
+        uninitialized[0].data = std::rc::Rc::new(std::cell::RefCell::new(&mut data[..]));
+        assert!(report_pool(&pool, &uninitialized, &report, &clock, false).is_err());
+
+        assert_eq!(
+            PoolBalanceReport::try_from_slice(&report.data.borrow()).unwrap(),
+            PoolBalanceReport::default()
+        );
+    }
+
+    #[test]
+    fn test_pool_balance_report_layout() {
+        assert_eq!(
+            PoolBalanceReport::SIZE,
+            8 + 8 + 8 * SPL_TOKEN_COUNT + 8 * (SPL_TOKEN_COUNT + 1)
+        );
+
+        let mut report = PoolBalanceReport {
+            slot: u64::MAX,
+            lamports: 1,
+            ..Default::default()
+        };
+        report.token_amounts = amounts();
+        report.obligations[SPL_TOKEN_COUNT] = 2;
+
+        let data = report.try_to_vec().unwrap();
+        assert_eq!(data.len(), PoolBalanceReport::SIZE);
+        assert_eq!(&data[..8], &u64::MAX.to_le_bytes());
+        assert_eq!(&data[16..24], &amounts()[0].to_le_bytes());
+        assert_eq!(PoolBalanceReport::try_from_slice(&data).unwrap(), report);
+    }
+
+    #[test]
+    fn test_token_list() {
+        let list = token_list().unwrap();
+        assert_eq!(list.len(), TOKENS.len());
+
+        for (i, token) in TOKENS.iter().enumerate() {
+            assert_eq!(
+                list[i],
+                TokenListEntry {
+                    token_id: i as TokenID,
+                    mint: token.mint.to_bytes(),
+                    decimals: token.decimals,
+                }
+            );
+        }
+
+        // Lamports pseudo-token
+        assert_eq!(list[0].token_id, LAMPORTS_TOKEN_ID);
+        assert_eq!(list[0].decimals, lamports_token().decimals);
+
+        let data = list.try_to_vec().unwrap();
+        assert_eq!(Vec::<TokenListEntry>::try_from_slice(&data).unwrap(), list);
+        assert_eq!(list_tokens(), Ok(()));
+    }
+}
@@ -9,10 +9,12 @@ use solana_program::pubkey::Pubkey;
 use solana_program::sysvar::instructions;
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError, rent::Rent,
-    sysvar::Sysvar,
 };
 use spl_associated_token_account::get_associated_token_address;
 
+#[cfg(not(test))]
+use solana_program::sysvar::Sysvar;
+
 pub use elusiv_utils::*;
 
 /// No-operation instruction
@@ -45,6 +47,26 @@ impl<'a, 'b> InstructionsSysvar for DefaultInstructionsSysvar<'a, 'b> {
     }
 }
 
+/// An account that is only passed for its key (e.g. identifiers and transaction references)
+/// - it is never writable nor a signer, so no state or lamports can be moved through it
+/// - the account does not need to exist (zero lamports are fine)
+pub struct KeyOnlyAccount<'a, 'b>(&'a AccountInfo<'b>);
+
+impl<'a, 'b> KeyOnlyAccount<'a, 'b> {
+    pub fn new(account: &'a AccountInfo<'b>) -> Result<Self, ProgramError> {
+        guard!(
+            !account.is_writable && !account.is_signer,
+            ElusivError::WritableOrSigningKeyOnlyAccount
+        );
+
+        Ok(Self(account))
+    }
+
+    pub fn key(&self) -> &'a Pubkey {
+        self.0.key
+    }
+}
+
 pub fn transfer_token<'a>(
     source: &AccountInfo<'a>,
     source_token_account: &AccountInfo<'a>,
@@ -83,7 +105,7 @@ pub fn transfer_token_from_pda<'a, T: PDAAccount>(
             transfer_lamports_from_pda_checked(source, destination, lamports.0)
         }
         Token::SPLToken(SPLToken { amount, .. }) => {
-            let bump = T::get_bump(source);
+            let bump = T::get_bump(source)?;
             let seeds = T::signers_seeds(pda_pubkey, pda_offset, bump);
             let signers_seeds = signers_seeds!(seeds);
 
@@ -218,10 +240,55 @@ pub fn system_program_account_rent() -> Result<Lamports, ProgramError> {
     }
 }
 
+/// Minimum balance of a rent-exempt account with `data_len` bytes
+pub fn rent_exempt_balance(data_len: usize) -> Result<Lamports, ProgramError> {
+    #[cfg(test)]
+    {
+        Ok(Lamports(Rent::default().minimum_balance(data_len)))
+    }
+
+    #[cfg(not(test))]
+    {
+        Ok(Lamports(Rent::get()?.minimum_balance(data_len)))
+    }
+}
+
+/// Verifies that `recipient` is rent-exempt after receiving `amount` lamports
+///
+/// # Note
+///
+/// The runtime rejects any transaction that leaves a (new) account funded below its rent-exemption.
+/// A fresh address can receive a smaller amount once it has been funded by someone else.
+pub fn verify_lamports_recipient_rent_exemption(
+    recipient: &AccountInfo,
+    amount: u64,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    guard!(
+        recipient.lamports().saturating_add(amount) >= rent_exempt_balance(recipient.data_len())?.0,
+        ElusivError::RecipientBelowRentExemption
+    );
+
+    Ok(())
+}
+
 pub fn spl_token_account_rent() -> Result<Lamports, ProgramError> {
-    Ok(Lamports(
-        Rent::get()?.minimum_balance(spl_token::state::Account::LEN),
-    ))
+    #[cfg(test)]
+    {
+        Ok(Lamports(
+            Rent::default().minimum_balance(spl_token::state::Account::LEN),
+        ))
+    }
+
+    #[cfg(not(test))]
+    {
+        Ok(Lamports(
+            Rent::get()?.minimum_balance(spl_token::state::Account::LEN),
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -229,7 +296,7 @@ mod tests {
     use super::*;
     use crate::{
         macros::{account_info, test_account_info},
-        state::{governor::PoolAccount, proof::VerificationAccount},
+        state::{governor::PoolAccount, program_account::SizedAccount, proof::VerificationAccount},
         token::TOKENS,
     };
     use solana_program::{pubkey::Pubkey, system_program};
@@ -514,6 +581,69 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_transfer_lamports_from_pda_leaving_rent() {
+        account_info!(pool, PoolAccount::find(None).0, vec![0; PoolAccount::SIZE]);
+        account_info!(recipient, Pubkey::new_unique(), vec![]);
+        let rent = Rent::default().minimum_balance(PoolAccount::SIZE);
+
+        for balance in [0, rent - 1, rent, rent + 1, u32::MAX as u64] {
+            **pool.try_borrow_mut_lamports().unwrap() = balance;
+            let recipient_balance = recipient.lamports();
+            let surplus = balance.saturating_sub(rent);
+
+            assert_eq!(
+                transfer_lamports_from_pda_leaving_rent(&pool, &recipient),
+                Ok(surplus)
+            );
+            assert_eq!(pool.lamports(), balance.min(rent));
+            assert_eq!(recipient.lamports(), recipient_balance + surplus);
+        }
+
+        // Draining again is a no-op
+        assert_eq!(
+            transfer_lamports_from_pda_leaving_rent(&pool, &recipient),
+            Ok(0)
+        );
+        assert_eq!(pool.lamports(), rent);
+    }
+
+    #[test]
+    fn test_verify_lamports_recipient_rent_exemption() {
+        account_info!(recipient, Pubkey::new_unique(), vec![]);
+        let rent = Rent::default().minimum_balance(0);
+
+        // Fresh recipient
+        **recipient.try_borrow_mut_lamports().unwrap() = 0;
+        assert_eq!(
+            verify_lamports_recipient_rent_exemption(&recipient, 0),
+            Ok(())
+        );
+        assert_eq!(
+            verify_lamports_recipient_rent_exemption(&recipient, rent - 1),
+            Err(ElusivError::RecipientBelowRentExemption.into())
+        );
+        assert_eq!(
+            verify_lamports_recipient_rent_exemption(&recipient, rent),
+            Ok(())
+        );
+
+        // Funded recipient
+        **recipient.try_borrow_mut_lamports().unwrap() = rent;
+        assert_eq!(
+            verify_lamports_recipient_rent_exemption(&recipient, 1),
+            Ok(())
+        );
+
+        // The rent-exemption depends on the recipient's data size
+        account_info!(data_recipient, Pubkey::new_unique(), vec![0; 100]);
+        **data_recipient.try_borrow_mut_lamports().unwrap() = 0;
+        assert_eq!(
+            verify_lamports_recipient_rent_exemption(&data_recipient, rent),
+            Err(ElusivError::RecipientBelowRentExemption.into())
+        );
+    }
+
     #[test]
     fn test_close_account() {
         account_info!(account, Pubkey::new_unique(), vec![]);
@@ -556,4 +686,22 @@ mod tests {
             Err(ElusivError::InvalidAccount.into())
         );
     }
+
+    #[test]
+    fn test_key_only_account() {
+        test_account_info!(account, 0);
+        **account.try_borrow_mut_lamports().unwrap() = 0;
+        assert_eq!(KeyOnlyAccount::new(&account).unwrap().key(), account.key);
+
+        for (is_writable, is_signer) in [(true, false), (false, true), (true, true)] {
+            let mut account = account.clone();
+            account.is_writable = is_writable;
+            account.is_signer = is_signer;
+
+            assert!(matches!(
+                KeyOnlyAccount::new(&account),
+                Err(e) if e == ElusivError::WritableOrSigningKeyOnlyAccount.into()
+            ));
+        }
+    }
 }
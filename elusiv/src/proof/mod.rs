@@ -2,4 +2,4 @@ pub mod verifier;
 pub mod vkey;
 
 #[cfg(test)]
-mod test_proofs;
+pub(crate) mod test_proofs;
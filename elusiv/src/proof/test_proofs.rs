@@ -1,9 +1,20 @@
 use crate::{
-    fields::u256_from_str_skip_mr,
+    fields::{fr_to_u256_le_repr, u256_from_str_skip_mr, Wrap, G1A, G2A},
     types::{Proof, U256},
 };
+use ark_bn254::{Bn254, Fr, G1Projective};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{Field, UniformRand};
+use ark_groth16::{
+    create_random_proof, generate_random_parameters, prepare_verifying_key, PreparedVerifyingKey,
+};
+use ark_relations::lc;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use borsh::BorshSerialize;
+use rand::{rngs::StdRng, SeedableRng};
 
 use super::verifier::proof_from_str_projective;
+use super::vkey::{TestVKey, VerifyingKey, VerifyingKeyInfo};
 
 pub struct TestProof {
     pub proof: Proof,
@@ -258,3 +269,108 @@ pub fn invalid_proofs() -> Vec<TestProof> {
         ),
     ]
 }
+
+/// Circuit proving the knowledge of a square root for each public input
+struct SquareRootsCircuit {
+    roots: Vec<Fr>,
+}
+
+impl ConstraintSynthesizer<Fr> for SquareRootsCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        for root in self.roots {
+            let input = cs.new_input_variable(|| Ok(root.square()))?;
+            let witness = cs.new_witness_variable(|| Ok(root))?;
+            cs.enforce_constraint(lc!() + witness, lc!() + witness, lc!() + input)?;
+        }
+        Ok(())
+    }
+}
+
+/// Deterministically generates a valid proof, its public inputs and the matching [`VerifyingKey`] source
+///
+/// # Notes
+///
+/// - proofs for the embedded vkeys can't be generated without their proving keys, so a new circuit with [`TestVKey::public_inputs_count`] public inputs is used
+pub fn generate_valid_proof(seed: u64) -> (Proof, Vec<U256>, Vec<u8>) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let roots: Vec<Fr> = (0..TestVKey::public_inputs_count())
+        .map(|_| Fr::rand(&mut rng))
+        .collect();
+
+    let pk = generate_random_parameters::<Bn254, _, _>(
+        SquareRootsCircuit {
+            roots: roots.clone(),
+        },
+        &mut rng,
+    )
+    .unwrap();
+    let proof = create_random_proof(
+        SquareRootsCircuit {
+            roots: roots.clone(),
+        },
+        &pk,
+        &mut rng,
+    )
+    .unwrap();
+
+    let public_inputs = roots
+        .iter()
+        .map(|root| fr_to_u256_le_repr(&root.square()))
+        .collect();
+
+    (
+        Proof {
+            a: G1A(proof.a),
+            b: G2A(proof.b),
+            c: G1A(proof.c),
+        },
+        public_inputs,
+        vkey_source(&prepare_verifying_key(&pk.vk)),
+    )
+}
+
+/// Serializes a [`PreparedVerifyingKey`] into the [`VerifyingKey`] source format
+fn vkey_source(pvk: &PreparedVerifyingKey<Bn254>) -> Vec<u8> {
+    let public_inputs_count = pvk.vk.gamma_abc_g1.len() - 1;
+    let mut source = Vec::with_capacity(VerifyingKey::source_size(public_inputs_count));
+
+    Wrap(pvk.alpha_g1_beta_g2).serialize(&mut source).unwrap();
+    G1A(pvk.vk.gamma_abc_g1[0]).serialize(&mut source).unwrap();
+
+    // gamma_abc[i][j][k - 1] = k * 256^j * gamma_abc_g1[i + 1]
+    for gamma_abc in &pvk.vk.gamma_abc_g1[1..] {
+        let mut base = gamma_abc.into_projective();
+        let mut windows = Vec::with_capacity(32 * 255);
+
+        for _ in 0..32 {
+            let mut acc = G1Projective::default();
+            for _ in 1..=255 {
+                acc += base;
+                windows.push(acc);
+            }
+            base = acc + base;
+        }
+
+        G1Projective::batch_normalization(&mut windows);
+        for window in windows {
+            G1A(window.into_affine()).serialize(&mut source).unwrap();
+        }
+    }
+
+    for pc in [&pvk.gamma_g2_neg_pc, &pvk.delta_g2_neg_pc] {
+        assert_eq!(pc.ell_coeffs.len(), 91);
+        for coeffs in &pc.ell_coeffs {
+            Wrap(coeffs.0).serialize(&mut source).unwrap();
+            Wrap(coeffs.1).serialize(&mut source).unwrap();
+            Wrap(coeffs.2).serialize(&mut source).unwrap();
+        }
+    }
+
+    G1A(pvk.vk.alpha_g1).serialize(&mut source).unwrap();
+    G2A(pvk.vk.beta_g2).serialize(&mut source).unwrap();
+    G2A(pvk.vk.gamma_g2).serialize(&mut source).unwrap();
+    G2A(pvk.vk.delta_g2).serialize(&mut source).unwrap();
+
+    assert_eq!(source.len(), VerifyingKey::source_size(public_inputs_count));
+    source
+}
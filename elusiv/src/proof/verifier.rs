@@ -9,14 +9,16 @@
 
 use super::vkey::VerifyingKey;
 use crate::bytes::{usize_as_u32_safe, usize_as_u8_safe};
+use crate::computation_metadata::{COMBINED_MILLER_LOOP, FINAL_EXPONENTIATION};
 use crate::error::ElusivError::{
-    self, ComputationIsAlreadyFinished, CouldNotProcessProof, InvalidAccountState,
-    PartialComputationError,
+    self, ComputationIsAlreadyFinished, CouldNotProcessProof, InputPreparationInstructionsExceeded,
+    InvalidAccountState, PartialComputationError,
 };
 use crate::error::ElusivResult;
 use crate::fields::{G2HomProjective, Wrap, G1A, G2A};
+use crate::macros::guard;
 use crate::processor::COMPUTE_VERIFICATION_IX_COUNT;
-use crate::state::proof::{RAMFq, VerificationAccount, VerificationState};
+use crate::state::proof::{RAMFq, VerificationAccount, VerificationState, RAM_FQ_SIZE};
 use crate::types::U256;
 use ark_bn254::{
     Fq, Fq12, Fq12Parameters, Fq2, Fq6, Fq6Parameters, G1Affine, G1Projective, G2Affine, Parameters,
@@ -28,14 +30,16 @@ use ark_ff::fields::models::{
 };
 use ark_ff::{biginteger::BigInteger256, field_new, CubicExtParameters, Field, One, Zero};
 use borsh::{BorshDeserialize, BorshSerialize};
-use elusiv_computation::{PartialComputation, RAM};
+#[cfg(test)]
+use elusiv_computation::PartialComputation;
+use elusiv_computation::RAM;
 use elusiv_derive::BorshSerDeSized;
 use elusiv_interpreter::elusiv_computations;
-use elusiv_utils::guard;
 use std::ops::{AddAssign, Neg};
 
 #[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, Clone)]
 #[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug, PartialEq))]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum VerificationStep {
     PublicInputPreparation,
     CombinedMillerLoop,
@@ -65,7 +69,7 @@ pub fn verify_partial(
         VerificationStep::CombinedMillerLoop => {
             // Proof first has to be setup
             guard!(
-                verification_account.get_state() == VerificationState::ProofSetup,
+                verification_account.get_state()? == VerificationState::ProofSetup,
                 InvalidAccountState
             );
 
@@ -93,9 +97,22 @@ pub fn prepare_public_inputs(
     instruction: usize,
     round: usize,
 ) -> ElusivResult {
+    // The instructions count is fixed in `init_verification`, any further instruction is rejected
+    guard!(
+        instruction < verification_account.get_prepare_inputs_instructions_count() as usize,
+        InputPreparationInstructionsExceeded
+    );
+
     let rounds = verification_account.get_prepare_inputs_instructions(instruction);
 
-    let result = prepare_public_inputs_partial(round, rounds as usize, verification_account, vkey);
+    // The rounds of all instructions can never exceed the total rounds of the vkey
+    guard!(
+        rounds > 0
+            && round + rounds as usize <= prepare_public_inputs_rounds(vkey.public_inputs_count),
+        InputPreparationInstructionsExceeded
+    );
+
+    let result = prepare_public_inputs_partial(round, rounds as usize, verification_account, vkey)?;
 
     if round + rounds as usize == prepare_public_inputs_rounds(vkey.public_inputs_count) {
         let prepared_inputs = result.ok_or(CouldNotProcessProof)?;
@@ -108,6 +125,9 @@ pub fn prepare_public_inputs(
         verification_account.set_round(&0);
         verification_account.set_instruction(&0);
     } else {
+        // Only the final preparation round is allowed to yield the prepared inputs
+        guard!(result.is_none(), CouldNotProcessProof);
+
         verification_account.set_round(&(round as u32 + rounds as u32));
         verification_account.set_instruction(&(instruction as u32 + 1));
     }
@@ -121,7 +141,7 @@ pub fn combined_miller_loop(
     instruction: usize,
     round: usize,
 ) -> ElusivResult {
-    let rounds = CombinedMillerLoop::INSTRUCTION_ROUNDS[instruction] as usize;
+    let rounds = COMBINED_MILLER_LOOP.instruction_rounds[instruction] as usize;
 
     let mut r = verification_account.r.get();
     let mut alt_b = verification_account.alt_b.get();
@@ -150,7 +170,7 @@ pub fn combined_miller_loop(
 
     verification_account.set_coeff_index(&usize_as_u8_safe(coeff_index));
 
-    if round + rounds == CombinedMillerLoop::TOTAL_ROUNDS as usize {
+    if round + rounds == COMBINED_MILLER_LOOP.total_rounds as usize {
         let f = result.ok_or(CouldNotProcessProof)?;
 
         // Add `f` for the final exponentiation
@@ -177,11 +197,11 @@ pub fn final_exponentiation(
     round: usize,
 ) -> Result<Option<bool>, ElusivError> {
     guard!(
-        instruction < FinalExponentiation::IX_COUNT,
+        instruction < FINAL_EXPONENTIATION.ix_count,
         ComputationIsAlreadyFinished
     );
 
-    let rounds = FinalExponentiation::INSTRUCTION_ROUNDS[instruction] as usize;
+    let rounds = FINAL_EXPONENTIATION.instruction_rounds[instruction] as usize;
 
     let f = verification_account.f.get().0;
 
@@ -193,7 +213,7 @@ pub fn final_exponentiation(
     verification_account.set_round(&usize_as_u32_safe(round + rounds));
     verification_account.set_instruction(&(instruction as u32 + 1));
 
-    if round + rounds == FinalExponentiation::TOTAL_ROUNDS as usize {
+    if round + rounds == FINAL_EXPONENTIATION.total_rounds as usize {
         let v = result.ok_or(CouldNotProcessProof)?;
         verification_account.f.set(Wrap(v));
 
@@ -206,14 +226,8 @@ pub fn final_exponentiation(
     Ok(None)
 }
 
-macro_rules! read_g1_p {
-    ($ram: expr, $o: literal) => {
-        G1Projective::new($ram.read($o), $ram.read($o + 1), $ram.read($o + 2))
-    };
-}
-
 const PREPARE_PUBLIC_INPUTS_ROUNDS: usize = 33;
-const fn prepare_public_inputs_rounds(public_inputs_count: usize) -> usize {
+pub(crate) const fn prepare_public_inputs_rounds(public_inputs_count: usize) -> usize {
     PREPARE_PUBLIC_INPUTS_ROUNDS * public_inputs_count
 }
 
@@ -232,8 +246,8 @@ fn prepare_public_inputs_partial(
     rounds: usize,
     storage: &mut VerificationAccount,
     vkey: &VerifyingKey,
-) -> Option<G1Affine> {
-    let mut acc: G1Projective = read_g1_p!(storage.ram_fq, 3);
+) -> Result<Option<G1Affine>, ElusivError> {
+    let mut acc = read_g1_projective(&mut storage.ram_fq, 3)?;
     let mut input_index = round / PREPARE_PUBLIC_INPUTS_ROUNDS;
     let mut public_input = storage.get_public_input(input_index).skip_mr();
 
@@ -251,7 +265,7 @@ fn prepare_public_inputs_partial(
             let mut g_ic = if input_index == 0 {
                 vkey.gamma_abc_base()
             } else {
-                read_g1_p!(storage.ram_fq, 0)
+                read_g1_projective(&mut storage.ram_fq, 0)?
             };
 
             if public_input != [0; 32] {
@@ -259,19 +273,19 @@ fn prepare_public_inputs_partial(
             }
 
             if input_index < vkey.public_inputs_count - 1 {
-                write_g1_projective(&mut storage.ram_fq, &g_ic, 0);
+                write_g1_projective(&mut storage.ram_fq, g_ic, 0)?;
 
                 input_index += 1;
                 public_input = storage.get_public_input(input_index).skip_mr();
             } else {
-                return Some(g_ic.into_affine());
+                return Ok(Some(g_ic.into_affine()));
             }
         }
     }
 
-    write_g1_projective(&mut storage.ram_fq, &acc, 3);
+    write_g1_projective(&mut storage.ram_fq, acc, 3)?;
 
-    None
+    Ok(None)
 }
 
 #[cfg(feature = "elusiv-client")]
@@ -359,6 +373,19 @@ pub fn prepare_public_inputs_instructions(
     instructions
 }
 
+/// Returns the maximum number of input preparation instructions for a vkey with `public_inputs_count` public inputs
+///
+/// # Notes
+///
+/// The worst case is reached if no window of any public input is zero (since every round then has a non-zero cost).
+pub fn max_prepare_public_inputs_instructions(public_inputs_count: usize) -> usize {
+    prepare_public_inputs_instructions(
+        &vec![[u8::MAX; 32]; public_inputs_count],
+        public_inputs_count,
+    )
+    .len()
+}
+
 #[cfg(test)]
 const_assert_eq!(ADDITION_STEP_ROUNDS_COUNT, 2);
 #[cfg(test)]
@@ -370,7 +397,15 @@ const_assert_eq!(MUL_BY_034_ROUNDS_COUNT, 3);
 #[cfg(test)]
 const_assert_eq!(COMBINED_ELL_ROUNDS_COUNT, 13);
 
+/// Number of [`CombinedMillerLoop`] instructions
+///
+/// # Note
+///
+/// Derived by `elusiv_computations!` from the compute unit costs annotated on every round (measured on-chain) and the budget of `250_000` per instruction.
+/// All five compute-instructions of a transaction perform miller loop rounds.
 pub const COMBINED_MILLER_LOOP_IXS: usize = 215;
+
+/// Number of [`FinalExponentiation`] instructions (only the last compute-instruction of a transaction performs rounds)
 pub const FINAL_EXPONENTIATION_IXS: usize = 17;
 
 #[cfg(test)]
@@ -385,6 +420,18 @@ const_assert_eq!(FinalExponentiation::IX_COUNT, FINAL_EXPONENTIATION_IXS);
 #[cfg(test)]
 const_assert_eq!(FinalExponentiation::TX_COUNT, 17);
 
+// The compute-instructions of a single transaction fit into its compute unit limit
+#[cfg(test)]
+const_assert!(
+    (COMPUTE_VERIFICATION_IX_COUNT as u32 - 2) * CombinedMillerLoop::COMPUTE_BUDGET_PER_IX
+        <= elusiv_computation::MAX_COMPUTE_UNIT_LIMIT
+);
+
+#[cfg(test)]
+const_assert!(
+    FinalExponentiation::COMPUTE_BUDGET_PER_IX <= elusiv_computation::MAX_COMPUTE_UNIT_LIMIT
+);
+
 elusiv_computations!(
     combined_miller_loop, CombinedMillerLoop, 250_000,
 
@@ -798,10 +845,26 @@ elusiv_computations!(
     }
 );
 
-fn write_g1_projective(ram: &mut RAMFq, g1p: &G1Projective, offset: usize) {
-    ram.write(g1p.x, offset);
-    ram.write(g1p.y, offset + 1);
-    ram.write(g1p.z, offset + 2);
+/// Reads the [`G1Projective`] stored at `offset..offset + 3` in `ram`
+fn read_g1_projective(ram: &mut RAMFq, offset: usize) -> Result<G1Projective, ElusivError> {
+    guard!(offset + 2 < RAM_FQ_SIZE, PartialComputationError);
+
+    Ok(G1Projective::new(
+        ram.read(offset),
+        ram.read(offset + 1),
+        ram.read(offset + 2),
+    ))
+}
+
+/// Writes `g` to `offset..offset + 3` in `ram`
+fn write_g1_projective(ram: &mut RAMFq, g: G1Projective, offset: usize) -> ElusivResult {
+    guard!(offset + 2 < RAM_FQ_SIZE, PartialComputationError);
+
+    ram.write(g.x, offset);
+    ram.write(g.y, offset + 1);
+    ram.write(g.z, offset + 2);
+
+    Ok(())
 }
 
 /// Inverse of 2 (in q)
@@ -958,8 +1021,9 @@ mod tests {
     use super::*;
     use crate::fields::{u256_from_str_skip_mr, u256_to_fr_skip_mr};
     use crate::macros::zero_program_account;
-    use crate::proof::test_proofs::{invalid_proofs, valid_proofs};
+    use crate::proof::test_proofs::{generate_valid_proof, invalid_proofs, valid_proofs};
     use crate::proof::vkey::{TestVKey, VerifyingKeyInfo};
+    use crate::state::fee::ProgramFee;
     use crate::state::metadata::CommitmentMetadata;
     use crate::state::storage::empty_root_raw;
     use crate::types::{
@@ -972,6 +1036,7 @@ mod tests {
     use ark_ec::PairingEngine;
     use ark_groth16::prepare_inputs;
     use solana_program::native_token::LAMPORTS_PER_SOL;
+    use std::collections::HashSet;
     use std::str::FromStr;
 
     fn setup_storage_account<VKey: VerifyingKeyInfo>(
@@ -1042,6 +1107,32 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_read_write_g1_projective() {
+        use crate::bytes::SizedType;
+
+        let mut data = vec![0; RAMFq::SIZE];
+        let mut ram = RAMFq::new(&mut data);
+        let g = G1Projective::prime_subgroup_generator().double();
+
+        for offset in 0..=RAM_FQ_SIZE - 3 {
+            write_g1_projective(&mut ram, g, offset).unwrap();
+            assert_eq!(read_g1_projective(&mut ram, offset), Ok(g));
+        }
+
+        // Out of bounds
+        for offset in RAM_FQ_SIZE - 2..RAM_FQ_SIZE + 1 {
+            assert_eq!(
+                write_g1_projective(&mut ram, g, offset),
+                Err(PartialComputationError)
+            );
+            assert_eq!(
+                read_g1_projective(&mut ram, offset),
+                Err(PartialComputationError)
+            );
+        }
+    }
+
     #[test]
     fn test_prepare_public_inputs() {
         vkey!(vkey, TestVKey);
@@ -1085,6 +1176,7 @@ mod tests {
             &mut storage,
             &vkey,
         )
+        .unwrap()
         .unwrap();
         let public_inputs: Vec<Fr> = public_inputs
             .iter()
@@ -1115,6 +1207,117 @@ mod tests {
         assert_eq!(storage.prepared_inputs.get().0, expected);
     }
 
+    #[test]
+    fn test_prepare_public_inputs_final_round() {
+        vkey!(vkey, TestVKey);
+        let pvk = TestVKey::arkworks_pvk();
+        let public_inputs = valid_proofs()[0].public_inputs.clone();
+
+        zero_program_account!(mut storage, VerificationAccount);
+        setup_storage_account::<TestVKey>(&mut storage, valid_proofs()[0].proof, &public_inputs);
+        let zero_prepared_inputs = storage.prepared_inputs.get().0;
+
+        let instructions_count = storage.get_prepare_inputs_instructions_count() as usize;
+        assert!(instructions_count > 1);
+
+        // All but the final instruction
+        for _ in 0..instructions_count - 1 {
+            assert_eq!(
+                verify_partial(&mut storage, &vkey, COMPUTE_VERIFICATION_IX_COUNT - 1),
+                Ok(None)
+            );
+        }
+        assert_eq!(storage.get_step(), VerificationStep::PublicInputPreparation);
+        assert_eq!(storage.prepared_inputs.get().0, zero_prepared_inputs);
+
+        // Final instruction
+        assert_eq!(
+            verify_partial(&mut storage, &vkey, COMPUTE_VERIFICATION_IX_COUNT - 1),
+            Ok(None)
+        );
+        let expected = prepare_inputs(
+            &pvk,
+            &public_inputs
+                .iter()
+                .map(|&x| u256_to_fr_skip_mr(&RawU256::new(x).reduce()))
+                .collect::<Vec<Fr>>(),
+        )
+        .unwrap()
+        .into_affine();
+        assert_eq!(storage.get_step(), VerificationStep::CombinedMillerLoop);
+        assert_eq!(storage.prepared_inputs.get().0, expected);
+    }
+
+    #[test]
+    fn test_prepare_public_inputs_result_before_final_round() {
+        vkey!(vkey, TestVKey);
+
+        zero_program_account!(mut storage, VerificationAccount);
+        storage.setup_public_inputs_instructions(&vec![2]).unwrap();
+
+        // The final round is reached with a round-offset that does not match the instructions
+        // - refused before any computation, since the rounds would exceed the total rounds
+        let round = prepare_public_inputs_rounds(TestVKey::public_inputs_count()) - 1;
+        assert_eq!(
+            prepare_public_inputs(&mut storage, &vkey, 0, round),
+            Err(InputPreparationInstructionsExceeded)
+        );
+        assert_eq!(storage.get_step(), VerificationStep::PublicInputPreparation);
+    }
+
+    #[test]
+    fn test_prepare_public_inputs_all_window_values() {
+        vkey!(vkey, TestVKey);
+        let pvk = TestVKey::arkworks_pvk();
+
+        // Every byte value (incl. all values with the high bits set) occurs in at least one window
+        let public_inputs: Vec<U256> = (0..TestVKey::public_inputs_count())
+            .map(|k| {
+                let mut v = [0; 32];
+                for (j, b) in v.iter_mut().enumerate().take(31) {
+                    *b = ((k * 31 + j) % 256) as u8;
+                }
+                v[31] = k as u8; // below the most significant byte of the scalar field modulus
+                v
+            })
+            .collect();
+
+        let mut windows = HashSet::new();
+        for public_input in &public_inputs {
+            windows.extend(public_input.iter().copied());
+        }
+        assert_eq!(windows.len(), 256);
+
+        zero_program_account!(mut storage, VerificationAccount);
+        for (i, public_input) in public_inputs.iter().enumerate() {
+            storage.set_public_input(i, &RawU256::new(*public_input));
+        }
+
+        let result = prepare_public_inputs_partial(
+            0,
+            prepare_public_inputs_rounds(TestVKey::public_inputs_count()),
+            &mut storage,
+            &vkey,
+        )
+        .unwrap()
+        .unwrap();
+        let expected = prepare_inputs(
+            &pvk,
+            &public_inputs
+                .iter()
+                .map(|&x| u256_to_fr_skip_mr(&RawU256::new(x).reduce()))
+                .collect::<Vec<Fr>>(),
+        )
+        .unwrap()
+        .into_affine();
+
+        assert_eq!(result, expected);
+        assert_eq!(
+            precomputed_input_preparation(&vkey, &public_inputs).unwrap(),
+            expected
+        );
+    }
+
     #[test]
     fn test_mul_by_characteristics() {
         zero_program_account!(mut storage, VerificationAccount);
@@ -1287,6 +1490,8 @@ mod tests {
             let round = storage.get_round();
             combined_miller_loop(&mut storage, &vkey, i, round as usize).unwrap();
         }
+        assert_eq!(COMBINED_MILLER_LOOP.ix_count, COMBINED_MILLER_LOOP_IXS);
+        assert_eq!(storage.get_step(), VerificationStep::FinalExponentiation);
         assert_eq!(storage.f.get().0, expected);
     }
 
@@ -1447,6 +1652,7 @@ mod tests {
             let round = storage.get_round();
             final_exponentiation(&mut storage, &vkey, i, round as usize).unwrap();
         }
+        assert_eq!(storage.get_round(), FINAL_EXPONENTIATION.total_rounds);
         assert_eq!(storage.f.get().0, expected);
     }
 
@@ -1495,6 +1701,91 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_max_prepare_public_inputs_instructions() {
+        let public_inputs_count = TestVKey::public_inputs_count();
+        let max = max_prepare_public_inputs_instructions(public_inputs_count);
+
+        // Boundary: no zero windows
+        assert_eq!(
+            prepare_public_inputs_instructions(
+                &vec![[u8::MAX; 32]; public_inputs_count],
+                public_inputs_count
+            )
+            .len(),
+            max
+        );
+
+        for p in valid_proofs().iter().chain(invalid_proofs().iter()) {
+            assert!(
+                prepare_public_inputs_instructions(&p.public_inputs, public_inputs_count).len()
+                    <= max
+            );
+        }
+
+        assert_eq!(max_prepare_public_inputs_instructions(0), 0);
+    }
+
+    #[test]
+    fn test_prepare_public_inputs_instructions_exceeded() {
+        vkey!(vkey, TestVKey);
+        let public_inputs = valid_proofs()[0].public_inputs.clone();
+        zero_program_account!(mut storage, VerificationAccount);
+        setup_storage_account::<TestVKey>(&mut storage, valid_proofs()[0].proof, &public_inputs);
+
+        let instructions_count = storage.get_prepare_inputs_instructions_count() as usize;
+        assert_eq!(
+            prepare_public_inputs(&mut storage, &vkey, instructions_count, 0),
+            Err(InputPreparationInstructionsExceeded)
+        );
+
+        // Rounds beyond the accepted instructions count are refused by `verify_partial`
+        storage.set_instruction(&(instructions_count as u32));
+        assert_eq!(
+            verify_partial(&mut storage, &vkey, COMPUTE_VERIFICATION_IX_COUNT - 1),
+            Err(InputPreparationInstructionsExceeded)
+        );
+        assert_eq!(storage.get_step(), VerificationStep::PublicInputPreparation);
+    }
+
+    #[test]
+    fn test_prepare_public_inputs_rounds_exceeded() {
+        vkey!(vkey, TestVKey);
+        let public_inputs = valid_proofs()[0].public_inputs.clone();
+        let total_rounds = prepare_public_inputs_rounds(TestVKey::public_inputs_count());
+        zero_program_account!(mut storage, VerificationAccount);
+        setup_storage_account::<TestVKey>(&mut storage, valid_proofs()[0].proof, &public_inputs);
+
+        // Instructions without any rounds
+        storage
+            .setup_public_inputs_instructions(&vec![0, 1])
+            .unwrap();
+        assert_eq!(
+            prepare_public_inputs(&mut storage, &vkey, 0, 0),
+            Err(InputPreparationInstructionsExceeded)
+        );
+
+        // Instructions exceeding the total rounds
+        storage
+            .setup_public_inputs_instructions(&vec![total_rounds as u32 + 1])
+            .unwrap();
+        assert_eq!(
+            prepare_public_inputs(&mut storage, &vkey, 0, 0),
+            Err(InputPreparationInstructionsExceeded)
+        );
+
+        storage
+            .setup_public_inputs_instructions(&vec![1, total_rounds as u32])
+            .unwrap();
+        assert_eq!(prepare_public_inputs(&mut storage, &vkey, 0, 0), Ok(()));
+        assert_eq!(
+            prepare_public_inputs(&mut storage, &vkey, 1, 1),
+            Err(InputPreparationInstructionsExceeded)
+        );
+        assert_eq!(storage.get_round(), 1);
+        assert_eq!(storage.get_step(), VerificationStep::PublicInputPreparation);
+    }
+
     fn full_verification<VKey: VerifyingKeyInfo>(
         proof: Proof,
         public_inputs: &[U256],
@@ -1535,6 +1826,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_verify_generated_proof() {
+        let (proof, public_inputs, source) = generate_valid_proof(0);
+        let vkey = VerifyingKey::new(&source, TestVKey::public_inputs_count()).unwrap();
+
+        assert!(full_verification::<TestVKey>(proof, &public_inputs, &vkey));
+
+        // Mutated public input
+        let mut invalid_public_inputs = public_inputs.clone();
+        invalid_public_inputs[0][0] ^= 1;
+        assert!(!full_verification::<TestVKey>(
+            proof,
+            &invalid_public_inputs,
+            &vkey
+        ));
+
+        // Mutated proof
+        let mut invalid_proof = proof;
+        invalid_proof.c = proof.a;
+        assert!(!full_verification::<TestVKey>(
+            invalid_proof,
+            &public_inputs,
+            &vkey
+        ));
+
+        // Deterministic generation
+        let (p, inputs, _) = generate_valid_proof(0);
+        assert_eq!(p, proof);
+        assert_eq!(inputs, public_inputs);
+    }
+
     #[test]
     fn test_verify_partial_too_many_calls() {
         let proof = valid_proofs()[0].proof;
@@ -1558,6 +1880,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_proof_base_tx_count() {
+        vkey!(vkey, TestVKey);
+
+        // Compute-instructions of a transaction follow the two compute-unit-instructions
+        let compute_ix_indices = 2..COMPUTE_VERIFICATION_IX_COUNT;
+
+        for p in valid_proofs().into_iter().chain(invalid_proofs()) {
+            zero_program_account!(mut storage, VerificationAccount);
+            setup_storage_account::<TestVKey>(&mut storage, p.proof, &p.public_inputs);
+            let prepare_inputs_tx_count = storage.get_prepare_inputs_instructions_count() as u64;
+
+            // Transactions are sent until the verification yields a result
+            let mut compute_tx_count = 0;
+            let mut result = None;
+            while result.is_none() {
+                compute_tx_count += 1;
+                for instruction_index in compute_ix_indices.clone() {
+                    result = verify_partial(&mut storage, &vkey, instruction_index).unwrap();
+                    if result.is_some() {
+                        break;
+                    }
+                }
+            }
+
+            // Init- and finalize-transaction
+            let tx_count = 1 + compute_tx_count + 1;
+
+            assert_eq!(
+                tx_count,
+                ProgramFee::proof_base_tx_count() + prepare_inputs_tx_count
+            );
+        }
+    }
+
     // https://github.com/arkworks-rs/algebra/blob/6ea310ef09f8b7510ce947490919ea6229bbecd6/ec/src/models/bn/mod.rs#L59
     fn reference_ell(f: Fq12, coeffs: (Fq2, Fq2, Fq2), p: G1Affine) -> Fq12 {
         let mut c0: Fq2 = coeffs.0;
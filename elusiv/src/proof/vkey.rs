@@ -1,13 +1,98 @@
 use crate::fields::{Wrap, G1A, G2A};
+use crate::types::JOIN_SPLIT_MAX_N_ARITY;
 use ark_bn254::{Fq12, Fq2, G1Affine, G1Projective};
 use ark_ec::AffineCurve;
 use ark_ff::Zero;
 use borsh::BorshDeserialize;
 use elusiv_types::BorshSerDeSized;
 
+/// A named public signal of a circuit
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Signal {
+    NullifierHash,
+    Root,
+    Amount,
+    OutputCommitment,
+    RecentCommitmentIndex,
+    FeeVersion,
+    TokenId,
+    HashedInputs,
+    CurrentNsmtRoot,
+    NextNsmtRoot,
+}
+
+/// The ordering of the public signals of a circuit
+/// - each entry is a [`Signal`] with the number of its consecutive instances
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SignalLayout(pub &'static [(Signal, usize)]);
+
+impl SignalLayout {
+    /// Total number of public signals
+    pub const fn signals_count(&self) -> usize {
+        let mut count = 0;
+        let mut i = 0;
+        while i < self.0.len() {
+            count += self.0[i].1;
+            i += 1;
+        }
+        count
+    }
+
+    /// Number of instances of `signal`
+    pub fn count(&self, signal: Signal) -> usize {
+        self.0
+            .iter()
+            .find(|(s, _)| *s == signal)
+            .map_or(0, |&(_, count)| count)
+    }
+
+    /// The public signal index of the `k`-th instance of `signal`
+    pub fn index(&self, signal: Signal, k: usize) -> Option<usize> {
+        let mut start = 0;
+        for &(s, count) in self.0 {
+            if s == signal {
+                return (k < count).then_some(start + k);
+            }
+            start += count;
+        }
+        None
+    }
+
+    /// All signals in their public signal ordering
+    pub fn signals(&self) -> impl Iterator<Item = (Signal, usize)> + '_ {
+        self.0
+            .iter()
+            .flat_map(|&(signal, count)| (0..count).map(move |k| (signal, k)))
+    }
+}
+
+/// https://github.com/elusiv-privacy/circuits/blob/master/circuits/send.circom
+pub const SEND_SIGNAL_LAYOUT: SignalLayout = SignalLayout(&[
+    (Signal::NullifierHash, JOIN_SPLIT_MAX_N_ARITY),
+    (Signal::Root, JOIN_SPLIT_MAX_N_ARITY),
+    (Signal::Amount, 1),
+    (Signal::OutputCommitment, 1),
+    (Signal::RecentCommitmentIndex, 1),
+    (Signal::FeeVersion, 1),
+    (Signal::TokenId, 1),
+    (Signal::HashedInputs, 1),
+]);
+
+/// https://github.com/elusiv-privacy/circuits/blob/master/circuits/migrate.circom
+pub const MIGRATE_SIGNAL_LAYOUT: SignalLayout = SignalLayout(&[
+    (Signal::NullifierHash, 1),
+    (Signal::Root, 1),
+    (Signal::OutputCommitment, 1),
+    (Signal::RecentCommitmentIndex, 1),
+    (Signal::CurrentNsmtRoot, 1),
+    (Signal::NextNsmtRoot, 1),
+    (Signal::Amount, 1),
+]);
+
 pub trait VerifyingKeyInfo {
     const VKEY_ID: u32;
     const PUBLIC_INPUTS_COUNT: u32;
+    const SIGNAL_LAYOUT: SignalLayout;
 
     #[cfg(feature = "elusiv-client")]
     const DIRECTORY: &'static str;
@@ -43,12 +128,15 @@ pub trait VerifyingKeyInfo {
 }
 
 macro_rules! verification_key_info {
-    ($ident: ident, $id: expr, $public_inputs_count: expr, $dir: literal) => {
+    ($ident: ident, $id: expr, $public_inputs_count: expr, $layout: expr, $dir: literal) => {
         pub struct $ident;
 
+        const _: () = assert!($layout.signals_count() == $public_inputs_count);
+
         impl VerifyingKeyInfo for $ident {
             const VKEY_ID: u32 = $id;
             const PUBLIC_INPUTS_COUNT: u32 = $public_inputs_count;
+            const SIGNAL_LAYOUT: SignalLayout = $layout;
 
             #[cfg(feature = "elusiv-client")]
             const DIRECTORY: &'static str = $dir;
@@ -66,11 +154,17 @@ macro_rules! verification_key_info {
     };
 }
 
-verification_key_info!(SendQuadraVKey, 0, 14, "send_quadra");
-verification_key_info!(MigrateUnaryVKey, 1, 7, "migrate_unary");
+verification_key_info!(SendQuadraVKey, 0, 14, SEND_SIGNAL_LAYOUT, "send_quadra");
+verification_key_info!(
+    MigrateUnaryVKey,
+    1,
+    7,
+    MIGRATE_SIGNAL_LAYOUT,
+    "migrate_unary"
+);
 
 #[cfg(test)]
-verification_key_info!(TestVKey, 2, 14, "test");
+verification_key_info!(TestVKey, 2, 14, SEND_SIGNAL_LAYOUT, "test");
 
 /// A Groth16 verifying key with precomputed values
 pub struct VerifyingKey<'a> {
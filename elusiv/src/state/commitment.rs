@@ -1,18 +1,23 @@
-use super::metadata::CommitmentMetadata;
+use super::metadata::{CommitmentMetadata, MetadataQueue};
 use super::queue::{queue_account, RingQueue};
 use crate::buffer::buffer_account;
-use crate::bytes::usize_as_u32_safe;
+use crate::bytes::{usize_as_u32_safe, ElusivOption};
 use crate::commitment::poseidon_hash::BinarySpongeHashingState;
-use crate::commitment::{commitments_per_batch, MAX_HT_SIZE, MT_HEIGHT};
+use crate::commitment::{
+    commitments_per_batch, BaseCommitmentHashComputation, MAX_HT_SIZE, MT_HEIGHT,
+};
 use crate::error::ElusivError;
-use crate::fields::{fr_to_u256_le, u256_to_fr_skip_mr};
-use crate::macros::{elusiv_account, guard, two_pow};
+use crate::fields::{fr_to_u256_le, is_element_scalar_field, u256_to_fr_skip_mr};
+use crate::macros::{elusiv_account, guard, two_pow, BorshSerDeSized};
 use crate::processor::{BaseCommitmentHashRequest, CommitmentHashRequest};
 use crate::state::program_account::PDAAccountData;
 use crate::state::storage::{StorageAccount, HISTORY_ARRAY_SIZE};
 use crate::types::U256;
 use ark_bn254::Fr;
 use ark_ff::{BigInteger256, PrimeField};
+use borsh::{BorshDeserialize, BorshSerialize};
+use elusiv_computation::PartialComputation;
+use solana_program::entrypoint::ProgramResult;
 use solana_program::program_error::ProgramError;
 
 /// Account used for computing `commitment = h(base_commitment, amount)`
@@ -67,6 +72,27 @@ impl<'a> BaseCommitmentHashingAccount<'a> {
 
         Ok(())
     }
+
+    /// Returns the commitment after all rounds have been computed
+    ///
+    /// # Notes
+    ///
+    /// The Poseidon permutation's output is pseudo-random in all three state elements, so there is no known constant the capacity could be checked against.
+    /// Instead all state elements are required to be canonical scalar field elements (in montgomery form), which catches corrupted account data.
+    pub fn finalize(&self) -> Result<U256, ElusivError> {
+        guard!(
+            (self.get_instruction() as usize) == BaseCommitmentHashComputation::IX_COUNT,
+            ElusivError::ComputationIsNotYetFinished
+        );
+
+        let state = self.get_state();
+        guard!(
+            state.0.iter().all(|e| is_element_scalar_field(e.0)),
+            ElusivError::ComputationCorrupted
+        );
+
+        Ok(fr_to_u256_le(&state.result()))
+    }
 }
 
 /// Account used for computing the hashes of a MT
@@ -284,42 +310,201 @@ buffer_account!(
 
 pub const COMMITMENT_QUEUE_LEN: usize = 240;
 
+/// Number of corrupted requests that can be held by the [`CommitmentQueueAccount`] until they are settled
+pub const COMMITMENT_QUARANTINE_LEN: usize = 4;
+
+/// A request (and its metadata) that has been removed from the queue by [`CommitmentQueue::validate`]
+#[derive(
+    BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Copy, Clone, Debug, Default,
+)]
+pub struct QuarantinedCommitment {
+    pub request: CommitmentHashRequest,
+    pub metadata: CommitmentMetadata,
+}
+
+pub type CommitmentQuarantine = [ElusivOption<QuarantinedCommitment>; COMMITMENT_QUARANTINE_LEN];
+
 // Queue used for storing commitments that should sequentially inserted into the active MT
 queue_account!(
     CommitmentQueue,
     CommitmentQueueAccount,
     COMMITMENT_QUEUE_LEN,
     CommitmentHashRequest,
+    /// Number of admitted proof verifications whose commitments are not yet enqueued
+    pending_commitments: u32,
+    /// Position (in the ring) of the next request to be checked by [`CommitmentQueue::validate`]
+    validation_ptr: u32,
+    quarantine: CommitmentQuarantine,
 );
 
 impl<'a, 'b> CommitmentQueue<'a, 'b> {
-    /// Returns the next batch of commitments to be hashed together
+    pub fn pending_commitments(&self) -> u32 {
+        self.account.get_pending_commitments()
+    }
+
+    /// Number of empty slots that are not reserved for pending commitments
+    pub fn unreserved_slots(&self) -> u32 {
+        self.empty_slots()
+            .saturating_sub(self.pending_commitments())
+    }
+
+    /// Reserves a slot for the commitment of a newly admitted proof verification
+    ///
+    /// # Note
+    ///
+    /// - Rejects the admission once the queue length plus the pending commitments reaches the `high_water_mark`.
+    /// - A `high_water_mark` of zero (or above [`RingQueue::CAPACITY`]) falls back to the capacity.
+    pub fn admit_pending_commitment(&mut self, high_water_mark: u32) -> ProgramResult {
+        let high_water_mark = if high_water_mark == 0 {
+            Self::CAPACITY
+        } else {
+            std::cmp::min(high_water_mark, Self::CAPACITY)
+        };
+
+        let pending_commitments = self.pending_commitments();
+        guard!(
+            self.len() + pending_commitments < high_water_mark,
+            ElusivError::CommitmentQueueCongested
+        );
+
+        self.account
+            .set_pending_commitments(&(pending_commitments + 1));
+
+        Ok(())
+    }
+
+    /// Releases the slot reserved by [`Self::admit_pending_commitment`]
+    ///
+    /// # Note
+    ///
+    /// Saturates at zero, so that verifications admitted before the reservations were tracked can still be finalized.
+    pub fn release_pending_commitment(&mut self) {
+        let pending_commitments = self.pending_commitments();
+        self.account
+            .set_pending_commitments(&pending_commitments.saturating_sub(1));
+    }
+
+    /// Returns the next batch of commitments to be hashed together and its batching rate
+    ///
+    /// # Note
+    ///
+    /// - A batch only contains requests with the batching rate recorded by its first request, so every commitment is hashed under the rate it was priced at.
+    /// - If a request with a different batching rate follows, the batch is closed early (and padded with empty commitments by the hashing).
     pub fn next_batch(&self) -> Result<(Vec<CommitmentHashRequest>, u32), ProgramError> {
-        let mut requests = Vec::new();
-        let mut highest_batching_rate = 0;
-        let mut commitment_count: usize = u32::MAX as usize;
-        let mut fee_version = None;
+        let first = self.view(0)?;
+        let batching_rate = first.min_batching_rate;
+        let commitment_count = commitments_per_batch(batching_rate);
+        let mut requests = vec![first];
 
         while requests.len() < commitment_count {
             let request = self.view(requests.len())?;
-
-            highest_batching_rate = std::cmp::max(highest_batching_rate, request.min_batching_rate);
-            commitment_count = commitments_per_batch(highest_batching_rate);
+            if request.min_batching_rate != batching_rate {
+                break;
+            }
 
             // Just a (hopefully always) redundant fee-check (depends on the fee upgrade logic)
-            if let Some(f) = fee_version {
-                guard!(f == request.fee_version, ElusivError::InvalidFeeVersion);
-            }
-            fee_version = Some(request.fee_version);
+            guard!(
+                request.fee_version == requests[0].fee_version,
+                ElusivError::InvalidFeeVersion
+            );
 
             requests.push(request);
         }
 
-        if requests.is_empty() {
-            return Err(ElusivError::QueueIsEmpty.into());
+        Ok((requests, batching_rate))
+    }
+
+    /// Checks up to `max_entries` pending requests (continuing where the last call stopped)
+    ///
+    /// # Note
+    ///
+    /// - A request violating [`CommitmentHashRequest::verify_with_fee_version`] is moved (together with its metadata) into the quarantine.
+    /// - The order of the remaining requests is preserved.
+    /// - The queue is swept cyclically (but each request is checked at most once per call), so a stale cursor only delays checks.
+    /// - Returns the number of quarantined requests.
+    pub fn validate(
+        &mut self,
+        metadata_queue: &mut MetadataQueue,
+        fee_version: u32,
+        max_entries: u32,
+    ) -> Result<u32, ProgramError> {
+        guard!(
+            metadata_queue.len() == self.len(),
+            ElusivError::InvalidAccountState
+        );
+
+        let mut offset = self.validation_offset();
+        let mut quarantined = 0;
+
+        for _ in 0..std::cmp::min(max_entries, self.len()) {
+            if offset >= self.len() {
+                offset = 0;
+            }
+
+            let request = self.view(offset as usize)?;
+            if request.verify_with_fee_version(fee_version).is_ok() {
+                offset += 1;
+                continue;
+            }
+
+            let slot = self
+                .quarantined_requests()
+                .iter()
+                .position(|q| q.option().is_none())
+                .ok_or(ElusivError::QueueIsFull)?;
+
+            // The following request moves into `offset`
+            self.remove_at(offset as usize)?;
+            let metadata = metadata_queue.remove_at(offset as usize)?;
+
+            let mut quarantine = self.quarantined_requests();
+            quarantine[slot] = ElusivOption::Some(QuarantinedCommitment { request, metadata });
+            self.account.set_quarantine(&quarantine);
+
+            quarantined += 1;
+        }
+
+        self.account
+            .set_validation_ptr(&((self.get_head() + offset) % Self::SIZE));
+
+        Ok(quarantined)
+    }
+
+    /// Offset (relative to the head) of the next request to be validated
+    ///
+    /// # Note
+    ///
+    /// Restarts at the head, once the cursor is outside of the queue.
+    fn validation_offset(&self) -> u32 {
+        let offset =
+            (self.account.get_validation_ptr() + Self::SIZE - self.get_head()) % Self::SIZE;
+        if offset > self.len() {
+            0
+        } else {
+            offset
         }
+    }
+
+    pub fn quarantined_requests(&self) -> CommitmentQuarantine {
+        self.account.get_quarantine()
+    }
+
+    pub fn quarantined_request(&self, index: usize) -> Result<QuarantinedCommitment, ProgramError> {
+        self.quarantined_requests()
+            .get(index)
+            .and_then(|q| q.option())
+            .ok_or_else(|| ElusivError::MissingValue.into())
+    }
 
-        Ok((requests, highest_batching_rate))
+    /// Removes the quarantined request at `index`
+    pub fn release_quarantined_request(&mut self, index: usize) -> ProgramResult {
+        self.quarantined_request(index)?;
+
+        let mut quarantine = self.quarantined_requests();
+        quarantine[index] = ElusivOption::None;
+        self.account.set_quarantine(&quarantine);
+
+        Ok(())
     }
 }
 
@@ -350,10 +535,12 @@ pub fn base_commitment_request(
 mod tests {
     use super::*;
     use crate::commitment::{
-        hash_count_per_batch, MAX_COMMITMENT_BATCHING_RATE, MAX_HT_COMMITMENTS,
+        compute_base_commitment_hash_partial, hash_count_per_batch, MAX_COMMITMENT_BATCHING_RATE,
+        MAX_HT_COMMITMENTS,
     };
     use crate::fields::{u64_to_scalar, u64_to_scalar_skip_mr, u64_to_u256_skip_mr};
     use crate::macros::{parent_account, zero_program_account};
+    use crate::state::metadata::MetadataQueueAccount;
     use crate::state::queue::Queue;
     use crate::types::RawU256;
     use ark_bn254::Fr;
@@ -366,6 +553,41 @@ mod tests {
         fr_to_u256_le(&u64_to_scalar(v))
     }
 
+    #[test]
+    fn test_base_commitment_hashing_account_finalize() {
+        zero_program_account!(mut account, BaseCommitmentHashingAccount);
+
+        assert_eq!(
+            account.finalize(),
+            Err(ElusivError::ComputationIsNotYetFinished)
+        );
+
+        for _ in 0..BaseCommitmentHashComputation::IX_COUNT {
+            compute_base_commitment_hash_partial(&mut account).unwrap();
+        }
+
+        let commitment = fr_to_u256_le(
+            &Fr::from_str(
+                "14744269619966411208579211824598458697587494354926760081771325075741142829156",
+            )
+            .unwrap(),
+        );
+        assert_eq!(account.finalize(), Ok(commitment));
+
+        // Corrupted capacity elements
+        for i in 1..3 {
+            let state = account.get_state();
+            let mut corrupted_state = state.clone();
+            corrupted_state.0[i] = u256_to_fr_skip_mr(&[u8::MAX; 32]);
+            account.set_state(&corrupted_state);
+
+            assert_eq!(account.finalize(), Err(ElusivError::ComputationCorrupted));
+
+            account.set_state(&state);
+            assert_eq!(account.finalize(), Ok(commitment));
+        }
+    }
+
     #[test]
     #[allow(clippy::needless_range_loop)]
     fn test_next_hashing_state() {
@@ -659,6 +881,66 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_commitment_queue_pending_commitments() {
+        let mut data = vec![0; <CommitmentQueueAccount as elusiv_types::SizedAccount>::SIZE];
+        let mut q = CommitmentQueueAccount::new(&mut data).unwrap();
+        let mut q = CommitmentQueue::new(&mut q);
+        let request = CommitmentHashRequest {
+            commitment: [0; 32],
+            fee_version: 0,
+            min_batching_rate: 0,
+        };
+
+        for _ in 0..3 {
+            q.enqueue(request).unwrap();
+        }
+
+        // Admission until the high-water mark is reached
+        q.admit_pending_commitment(5).unwrap();
+        q.admit_pending_commitment(5).unwrap();
+        assert_eq!(q.pending_commitments(), 2);
+        assert_eq!(
+            q.admit_pending_commitment(5),
+            Err(ElusivError::CommitmentQueueCongested.into())
+        );
+        assert_eq!(q.pending_commitments(), 2);
+        assert_eq!(q.unreserved_slots(), CommitmentQueue::CAPACITY - 5);
+
+        // Releasing reopens the admission
+        q.release_pending_commitment();
+        assert_eq!(q.pending_commitments(), 1);
+        q.admit_pending_commitment(5).unwrap();
+
+        // Dequeueing reopens the admission
+        q.dequeue_first().unwrap();
+        q.admit_pending_commitment(5).unwrap();
+        assert_eq!(q.pending_commitments(), 3);
+
+        // Releasing saturates at zero
+        for _ in 0..4 {
+            q.release_pending_commitment();
+        }
+        assert_eq!(q.pending_commitments(), 0);
+
+        // Zero or a too high mark falls back to the capacity
+        for high_water_mark in [0, u32::MAX] {
+            q.clear();
+            for _ in 0..CommitmentQueue::CAPACITY {
+                q.admit_pending_commitment(high_water_mark).unwrap();
+            }
+            assert_eq!(
+                q.admit_pending_commitment(high_water_mark),
+                Err(ElusivError::CommitmentQueueCongested.into())
+            );
+            assert_eq!(q.unreserved_slots(), 0);
+
+            for _ in 0..CommitmentQueue::CAPACITY {
+                q.release_pending_commitment();
+            }
+        }
+    }
+
     #[test]
     fn test_commitment_queue_next_batch() {
         let mut data = vec![0; <CommitmentQueueAccount as elusiv_types::SizedAccount>::SIZE];
@@ -679,12 +961,11 @@ mod tests {
         // Complete batches (with variing batching rates)
         q.clear();
         for b in 0..=MAX_COMMITMENT_BATCHING_RATE {
-            let c = commitments_per_batch(b as u32);
-            for i in 0..c {
+            for i in 0..commitments_per_batch(b as u32) {
                 q.enqueue(CommitmentHashRequest {
                     commitment: fr_to_u256_le(&u64_to_scalar(i as u64)),
                     fee_version: 0,
-                    min_batching_rate: if i == 0 { b as u32 } else { 0 },
+                    min_batching_rate: b as u32,
                 })
                 .unwrap();
             }
@@ -692,11 +973,12 @@ mod tests {
 
         for b in 0..=MAX_COMMITMENT_BATCHING_RATE {
             let (batch, batching_rate) = q.next_batch().unwrap();
-            for _ in 0..commitments_per_batch(batching_rate) {
+            for _ in 0..batch.len() {
                 q.dequeue_first().unwrap();
             }
 
             assert_eq!(batching_rate as usize, b);
+            assert_eq!(batch.len(), commitments_per_batch(batching_rate));
             for (i, c) in batch.iter().enumerate() {
                 assert_eq!(c.commitment, fr_to_u256_le(&u64_to_scalar(i as u64)));
             }
@@ -718,4 +1000,134 @@ mod tests {
         .unwrap();
         assert_eq!(q.next_batch(), Err(ElusivError::InvalidFeeVersion.into()));
     }
+
+    #[test]
+    fn test_commitment_queue_validate() {
+        let mut data = vec![0; <CommitmentQueueAccount as elusiv_types::SizedAccount>::SIZE];
+        let mut q = CommitmentQueueAccount::new(&mut data).unwrap();
+        let mut q = CommitmentQueue::new(&mut q);
+        let mut m_data = vec![0; <MetadataQueueAccount as elusiv_types::SizedAccount>::SIZE];
+        let mut m = MetadataQueueAccount::new(&mut m_data).unwrap();
+        let mut m = MetadataQueue::new(&mut m);
+
+        let request = |i: u8| CommitmentHashRequest {
+            commitment: [i; 32],
+            fee_version: 0,
+            min_batching_rate: 0,
+        };
+
+        // Wrap around the end of the ring
+        let start = CommitmentQueue::SIZE - 3;
+        q.set_head(&start);
+        q.set_tail(&start);
+        m.set_head(&start);
+        m.set_tail(&start);
+
+        let corrupted = [2, 5];
+        for i in 1..=7 {
+            let r = if corrupted.contains(&i) {
+                CommitmentHashRequest {
+                    commitment: [0; 32],
+                    ..request(i)
+                }
+            } else {
+                request(i)
+            };
+            q.enqueue(r).unwrap();
+            m.enqueue([i; CommitmentMetadata::SIZE]).unwrap();
+        }
+
+        // The (zeroed) cursor initially points to the fourth request
+        assert_eq!(q.validation_offset(), 3);
+        assert_eq!(q.validate(&mut m, 0, 2), Ok(1));
+        assert_eq!(q.validation_offset(), 4);
+
+        // The sweep wraps around to the head
+        assert_eq!(q.validate(&mut m, 0, 100), Ok(1));
+        assert_eq!(q.validate(&mut m, 0, 100), Ok(0));
+
+        // The order of the remaining requests (and their metadata) is preserved
+        for i in [1, 3, 4, 6, 7] {
+            assert_eq!(q.dequeue_first(), Ok(request(i)));
+            assert_eq!(m.dequeue_first(), Ok([i; CommitmentMetadata::SIZE]));
+        }
+        assert!(q.is_empty());
+
+        let quarantined: Vec<u8> = q
+            .quarantined_requests()
+            .iter()
+            .filter_map(|q| q.option())
+            .map(|q| q.metadata[0])
+            .collect();
+        assert_eq!(quarantined, vec![5, 2]);
+
+        // The cursor restarts at the head and requests are not quarantined twice
+        q.enqueue(request(8)).unwrap();
+        m.enqueue([8; CommitmentMetadata::SIZE]).unwrap();
+        assert_eq!(q.validate(&mut m, 0, 1), Ok(0));
+        assert_eq!(q.validation_offset(), 1);
+
+        // Fee version ahead of the current fee version
+        q.enqueue(CommitmentHashRequest {
+            fee_version: 1,
+            ..request(9)
+        })
+        .unwrap();
+        m.enqueue([9; CommitmentMetadata::SIZE]).unwrap();
+        assert_eq!(q.validate(&mut m, 0, 1), Ok(1));
+
+        // Full quarantine
+        q.release_quarantined_request(0).unwrap();
+        for i in 10..13 {
+            q.enqueue(CommitmentHashRequest {
+                min_batching_rate: MAX_COMMITMENT_BATCHING_RATE as u32 + 1,
+                ..request(i)
+            })
+            .unwrap();
+            m.enqueue([i; CommitmentMetadata::SIZE]).unwrap();
+        }
+        assert_eq!(
+            q.validate(&mut m, 0, 3),
+            Err(ElusivError::QueueIsFull.into())
+        );
+
+        // Metadata out of sync
+        m.dequeue_first().unwrap();
+        assert_eq!(
+            q.validate(&mut m, 0, 1),
+            Err(ElusivError::InvalidAccountState.into())
+        );
+    }
+
+    #[test]
+    fn test_commitment_queue_next_batch_groups_by_batching_rate() {
+        let mut data = vec![0; <CommitmentQueueAccount as elusiv_types::SizedAccount>::SIZE];
+        let mut q = CommitmentQueueAccount::new(&mut data).unwrap();
+        let mut q = CommitmentQueue::new(&mut q);
+
+        let request = |i: u8, min_batching_rate: u32| CommitmentHashRequest {
+            commitment: [i; 32],
+            fee_version: 0,
+            min_batching_rate,
+        };
+
+        // Two requests priced at rate 2, followed by requests priced at rate 1 and 3
+        for (i, rate) in [2, 2, 1, 1, 1, 3].iter().enumerate() {
+            q.enqueue(request(i as u8, *rate)).unwrap();
+        }
+
+        // The rate-2 batch is closed early
+        assert_eq!(q.next_batch(), Ok((vec![request(0, 2), request(1, 2)], 2)));
+        q.remove(2).unwrap();
+
+        // A higher rate of a following request is never applied to the batch
+        assert_eq!(q.next_batch(), Ok((vec![request(2, 1), request(3, 1)], 1)));
+        q.remove(2).unwrap();
+
+        assert_eq!(q.next_batch(), Ok((vec![request(4, 1)], 1)));
+        q.remove(1).unwrap();
+
+        // Incomplete batch at the end of the queue
+        assert_eq!(q.next_batch(), Err(ElusivError::InvalidQueueAccess.into()));
+    }
 }
@@ -0,0 +1,369 @@
+//! Bucketed index of the commitments in the active MT
+//!
+//! Wallets can check whether (and at which leaf index) their commitment has been inserted without scanning the MT.
+//!
+//! # Bucket derivation (for clients)
+//!
+//! - `commitment` is the MT leaf (as in [`crate::processor::CommitmentInserted::commitment`]).
+//! - The bucket is the LE `u32` of `commitment[0..4]` modulo [`COMMITMENT_INDEX_BUCKETS_COUNT`], the fingerprint is the LE `u32` of `commitment[4..8]`.
+//! - A lookup probes up to [`COMMITMENT_INDEX_MAX_PROBES`] consecutive buckets (wrapping around) and stops at the first bucket with a free entry.
+//! - [`commitment_index_bucket_range`] yields the child-account and the byte-range of a bucket, so RPC clients can fetch a single bucket using `getAccountInfo` with `dataSlice`.
+//! - A bucket is parsed with [`lookup_in_bucket`].
+//!
+//! # Notes
+//!
+//! - Entries only store a fingerprint, so a hit has to be confirmed with the commitment at the returned leaf index.
+//!   The false-positive rate is below `COMMITMENT_INDEX_BUCKET_LEN * COMMITMENT_INDEX_MAX_PROBES / 2^32` per lookup.
+//! - A miss is only authoritative if [`CommitmentIndexAccount::get_unindexed_count`] is zero.
+
+use super::program_account::PDAAccountData;
+use super::storage::{MT_COMMITMENT_COUNT, MT_HEIGHT};
+use crate::macros::{elusiv_account, two_pow};
+use crate::types::U256;
+use elusiv_types::{child_account_config_offsets, ChildAccount, ElusivOption, ParentAccount};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use std::ops::Range;
+
+pub const COMMITMENT_INDEX_BUCKETS_COUNT: usize = two_pow!(17);
+
+/// Number of entries per bucket
+pub const COMMITMENT_INDEX_BUCKET_LEN: usize = 16;
+
+/// An entry consists of the LE `u32` fingerprint and the LE `u32` position `(tree_tag << MT_HEIGHT) | leaf_index` (zero: free)
+const ENTRY_SIZE: usize = 8;
+
+pub const COMMITMENT_INDEX_BUCKET_SIZE: usize = COMMITMENT_INDEX_BUCKET_LEN * ENTRY_SIZE;
+
+/// Number of consecutive buckets probed by an insertion or lookup
+pub const COMMITMENT_INDEX_MAX_PROBES: usize = 4;
+
+const BUCKETS_PER_CHILD_ACCOUNT: usize = two_pow!(16);
+const ACCOUNTS_COUNT: usize = COMMITMENT_INDEX_BUCKETS_COUNT / BUCKETS_PER_CHILD_ACCOUNT;
+
+/// Number of distinct tree tags (a tag is reused every [`TREE_TAGS_COUNT`] MTs)
+const TREE_TAGS_COUNT: u32 = two_pow!(32 - MT_HEIGHT) as u32 - 1;
+
+#[cfg(test)]
+const_assert_eq!(ACCOUNTS_COUNT, 2);
+
+// The index has twice as many entries as the MT has leaves
+#[cfg(test)]
+const_assert_eq!(
+    COMMITMENT_INDEX_BUCKETS_COUNT * COMMITMENT_INDEX_BUCKET_LEN,
+    2 * MT_COMMITMENT_COUNT
+);
+
+pub struct CommitmentIndexChildAccount;
+
+impl ChildAccount for CommitmentIndexChildAccount {
+    const INNER_SIZE: usize = BUCKETS_PER_CHILD_ACCOUNT * COMMITMENT_INDEX_BUCKET_SIZE;
+}
+
+/// Maps the commitments of the active MT to their leaf indices (see the module docs)
+#[elusiv_account(parent_account: { child_account_count: ACCOUNTS_COUNT, child_account: CommitmentIndexChildAccount }, eager_type: true)]
+pub struct CommitmentIndexAccount {
+    #[no_getter]
+    #[no_setter]
+    pda_data: PDAAccountData,
+    pubkeys: [ElusivOption<Pubkey>; ACCOUNTS_COUNT],
+
+    /// The MT of the most recently indexed commitment
+    pub tree_index: u32,
+
+    /// Commitments of the MT at `tree_index` that are missing in the index
+    /// - inserted before the index existed or while all probed buckets were full
+    pub unindexed_count: u32,
+}
+
+/// The result of searching a single bucket
+#[derive(PartialEq, Eq, Debug)]
+pub enum BucketLookup {
+    /// Leaf index of an entry with a matching fingerprint
+    Found(u32),
+
+    /// The bucket has a free entry, so the commitment is not indexed
+    Absent,
+
+    /// The bucket is full, so the next bucket has to be searched
+    Full,
+}
+
+/// The bucket and fingerprint of `commitment`
+pub fn commitment_index_bucket(commitment: &U256) -> (usize, u32) {
+    let bucket = u32::from_le_bytes(commitment[..4].try_into().unwrap()) as usize
+        % COMMITMENT_INDEX_BUCKETS_COUNT;
+    let fingerprint = u32::from_le_bytes(commitment[4..8].try_into().unwrap());
+
+    (bucket, fingerprint)
+}
+
+/// The child-account index and the byte-range of `bucket` in the child-account's data (including the [`elusiv_types::ChildAccountConfig`])
+pub fn commitment_index_bucket_range(bucket: usize) -> (usize, Range<usize>) {
+    let (child_index, range) = child_account_and_local_range(bucket);
+    let start = child_account_config_offsets::CHILD_ACCOUNT_CONFIG.end + range.start;
+
+    (child_index, start..start + COMMITMENT_INDEX_BUCKET_SIZE)
+}
+
+/// Searches the data of a single bucket for `commitment` in the MT at `tree_index` with `next_commitment_ptr` commitments
+pub fn lookup_in_bucket(
+    bucket: &[u8],
+    commitment: &U256,
+    tree_index: u32,
+    next_commitment_ptr: u32,
+) -> BucketLookup {
+    let (_, fingerprint) = commitment_index_bucket(commitment);
+    let mut is_full = true;
+
+    for entry in bucket.chunks_exact(ENTRY_SIZE) {
+        match read_entry(entry, tree_index, next_commitment_ptr) {
+            Some((f, leaf_index)) if f == fingerprint => return BucketLookup::Found(leaf_index),
+            Some(_) => {}
+            None => is_full = false,
+        }
+    }
+
+    if is_full {
+        BucketLookup::Full
+    } else {
+        BucketLookup::Absent
+    }
+}
+
+fn tree_tag(tree_index: u32) -> u32 {
+    tree_index % TREE_TAGS_COUNT + 1
+}
+
+/// Returns the fingerprint and leaf index of an entry of the MT at `tree_index` ([`None`] for free or outdated entries)
+fn read_entry(entry: &[u8], tree_index: u32, next_commitment_ptr: u32) -> Option<(u32, u32)> {
+    let fingerprint = u32::from_le_bytes(entry[..4].try_into().unwrap());
+    let position = u32::from_le_bytes(entry[4..].try_into().unwrap());
+    let leaf_index = position & (MT_COMMITMENT_COUNT as u32 - 1);
+
+    if position >> MT_HEIGHT != tree_tag(tree_index) || leaf_index >= next_commitment_ptr {
+        return None;
+    }
+
+    Some((fingerprint, leaf_index))
+}
+
+fn child_account_and_local_range(bucket: usize) -> (usize, Range<usize>) {
+    let child_index = bucket / BUCKETS_PER_CHILD_ACCOUNT;
+    let start = (bucket % BUCKETS_PER_CHILD_ACCOUNT) * COMMITMENT_INDEX_BUCKET_SIZE;
+
+    (child_index, start..start + COMMITMENT_INDEX_BUCKET_SIZE)
+}
+
+fn probed_buckets(commitment: &U256) -> impl Iterator<Item = usize> {
+    let (bucket, _) = commitment_index_bucket(commitment);
+    (0..COMMITMENT_INDEX_MAX_PROBES).map(move |i| (bucket + i) % COMMITMENT_INDEX_BUCKETS_COUNT)
+}
+
+impl<'a, 'b, 't> CommitmentIndexAccount<'a, 'b, 't> {
+    /// Indexes the `commitment` at `leaf_index` of the MT at `tree_index`
+    ///
+    /// # Note
+    ///
+    /// Never fails because of full buckets, the commitment is counted as unindexed instead.
+    pub fn insert(
+        &mut self,
+        commitment: &U256,
+        leaf_index: u32,
+        tree_index: u32,
+        next_commitment_ptr: u32,
+    ) -> ProgramResult {
+        if self.get_tree_index() != tree_index {
+            self.set_tree_index(&tree_index);
+            self.set_unindexed_count(&0);
+        }
+
+        let (_, fingerprint) = commitment_index_bucket(commitment);
+        let position = (tree_tag(tree_index) << MT_HEIGHT) | leaf_index;
+
+        for bucket in probed_buckets(commitment) {
+            let (child_index, range) = child_account_and_local_range(bucket);
+            let inserted = self.execute_on_child_account_mut(child_index, |data| {
+                let free_entry = data[range]
+                    .chunks_exact_mut(ENTRY_SIZE)
+                    .find(|entry| read_entry(entry, tree_index, next_commitment_ptr).is_none());
+
+                match free_entry {
+                    Some(entry) => {
+                        entry[..4].copy_from_slice(&fingerprint.to_le_bytes());
+                        entry[4..].copy_from_slice(&position.to_le_bytes());
+                        true
+                    }
+                    None => false,
+                }
+            })?;
+
+            if inserted {
+                return Ok(());
+            }
+        }
+
+        self.set_unindexed_count(&self.get_unindexed_count().saturating_add(1));
+
+        Ok(())
+    }
+
+    /// The leaf index of `commitment` in the MT at `tree_index` with `next_commitment_ptr` commitments
+    /// - [`None`] if the commitment is not indexed
+    pub fn lookup(
+        &self,
+        commitment: &U256,
+        tree_index: u32,
+        next_commitment_ptr: u32,
+    ) -> Result<Option<u32>, ProgramError> {
+        for bucket in probed_buckets(commitment) {
+            let (child_index, range) = child_account_and_local_range(bucket);
+            let result = self.execute_on_child_account(child_index, |data| {
+                lookup_in_bucket(&data[range], commitment, tree_index, next_commitment_ptr)
+            })?;
+
+            match result {
+                BucketLookup::Found(leaf_index) => return Ok(Some(leaf_index)),
+                BucketLookup::Absent => return Ok(None),
+                BucketLookup::Full => {}
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::parent_account;
+    use elusiv_types::SizedAccount;
+
+    /// A commitment in `bucket` with `fingerprint`
+    fn commitment(bucket: usize, fingerprint: u32) -> U256 {
+        let mut commitment = [0; 32];
+        commitment[..4].copy_from_slice(&(bucket as u32).to_le_bytes());
+        commitment[4..8].copy_from_slice(&fingerprint.to_le_bytes());
+        commitment
+    }
+
+    #[test]
+    fn test_commitment_index_bucket() {
+        assert_eq!(commitment_index_bucket(&commitment(7, 9)), (7, 9));
+        assert_eq!(
+            commitment_index_bucket(&commitment(COMMITMENT_INDEX_BUCKETS_COUNT + 7, 9)),
+            (7, 9)
+        );
+    }
+
+    #[test]
+    fn test_commitment_index_bucket_range() {
+        assert_eq!(
+            commitment_index_bucket_range(0),
+            (0, 1..1 + COMMITMENT_INDEX_BUCKET_SIZE)
+        );
+        assert_eq!(
+            commitment_index_bucket_range(BUCKETS_PER_CHILD_ACCOUNT - 1),
+            (
+                0,
+                CommitmentIndexChildAccount::SIZE - COMMITMENT_INDEX_BUCKET_SIZE
+                    ..CommitmentIndexChildAccount::SIZE
+            )
+        );
+        assert_eq!(
+            commitment_index_bucket_range(BUCKETS_PER_CHILD_ACCOUNT + 2),
+            (
+                1,
+                1 + 2 * COMMITMENT_INDEX_BUCKET_SIZE..1 + 3 * COMMITMENT_INDEX_BUCKET_SIZE
+            )
+        );
+    }
+
+    #[test]
+    fn test_insert_and_lookup() {
+        parent_account!(mut index, CommitmentIndexAccount);
+
+        for leaf_index in 0..10 {
+            index
+                .insert(&commitment(leaf_index * 1000, 1), leaf_index as u32, 0, 10)
+                .unwrap();
+        }
+
+        for leaf_index in 0..10 {
+            assert_eq!(
+                index.lookup(&commitment(leaf_index * 1000, 1), 0, 10),
+                Ok(Some(leaf_index as u32))
+            );
+        }
+
+        // Absent commitments
+        assert_eq!(index.lookup(&commitment(1000, 2), 0, 10), Ok(None));
+        assert_eq!(index.lookup(&commitment(1, 1), 0, 10), Ok(None));
+
+        // Client-side lookup of a single bucket
+        let (child_index, range) = commitment_index_bucket_range(1000);
+        let bucket = index
+            .execute_on_child_account(child_index, |data| {
+                let config_end = child_account_config_offsets::CHILD_ACCOUNT_CONFIG.end;
+                data[range.start - config_end..range.end - config_end].to_vec()
+            })
+            .unwrap();
+        assert_eq!(
+            lookup_in_bucket(&bucket, &commitment(1000, 1), 0, 10),
+            BucketLookup::Found(1)
+        );
+        assert_eq!(
+            lookup_in_bucket(&bucket, &commitment(1000, 2), 0, 10),
+            BucketLookup::Absent
+        );
+
+        // Commitments of other MTs and leaves beyond the active MT are ignored
+        assert_eq!(index.lookup(&commitment(1000, 1), 1, 10), Ok(None));
+        assert_eq!(index.lookup(&commitment(9000, 1), 0, 9), Ok(None));
+    }
+
+    #[test]
+    fn test_bucket_collisions() {
+        parent_account!(mut index, CommitmentIndexAccount);
+        let bucket = COMMITMENT_INDEX_BUCKETS_COUNT - 1;
+        let capacity = COMMITMENT_INDEX_BUCKET_LEN * COMMITMENT_INDEX_MAX_PROBES;
+        let count = capacity as u32 + 3;
+
+        for leaf_index in 0..count - 1 {
+            index
+                .insert(&commitment(bucket, leaf_index), leaf_index, 0, count)
+                .unwrap();
+        }
+        index
+            .insert(&commitment(0, u32::MAX), count - 1, 0, count)
+            .unwrap();
+
+        // Colliding commitments overflow into the next buckets (wrapping around)
+        for leaf_index in 0..capacity as u32 {
+            assert_eq!(
+                index.lookup(&commitment(bucket, leaf_index), 0, count),
+                Ok(Some(leaf_index))
+            );
+        }
+
+        // The overflow is also probed by commitments of the following buckets
+        assert_eq!(
+            index.lookup(&commitment(0, u32::MAX), 0, count),
+            Ok(Some(count - 1))
+        );
+
+        // Once all probed buckets are full, commitments are counted as unindexed
+        assert_eq!(
+            index.lookup(&commitment(bucket, capacity as u32), 0, count),
+            Ok(None)
+        );
+        assert_eq!(index.get_unindexed_count(), 2);
+
+        // A new MT reuses the entries
+        index.insert(&commitment(bucket, 0), 0, 1, 1).unwrap();
+        assert_eq!(index.get_tree_index(), 1);
+        assert_eq!(index.get_unindexed_count(), 0);
+        assert_eq!(index.lookup(&commitment(bucket, 0), 1, 1), Ok(Some(0)));
+    }
+}
@@ -1,24 +1,37 @@
 use super::program_account::PDAAccountData;
-use crate::bytes::{div_ceiling_u64, u64_as_usize_safe};
-use crate::commitment::{
-    commitment_hash_computation_instructions, commitments_per_batch, BaseCommitmentHashComputation,
-    MAX_COMMITMENT_BATCHING_RATE,
-};
-use crate::macros::elusiv_account;
-use crate::proof::verifier::{CombinedMillerLoop, FinalExponentiation};
+use crate::bytes::div_ceiling_u64;
+use crate::commitment::{commitments_per_batch, MAX_COMMITMENT_BATCHING_RATE};
+use crate::computation_metadata::{self, BASE_COMMITMENT_HASH, PROOF_BASE_TX_COUNT};
+use crate::error::ElusivError;
+use crate::macros::{elusiv_account, guard, hardened_guard};
 use crate::token::{Lamports, Token, TokenError, TokenPrice};
 use borsh::{BorshDeserialize, BorshSerialize};
-use elusiv_computation::PartialComputation;
 use elusiv_derive::BorshSerDeSized;
+use solana_program::entrypoint::ProgramResult;
 
 #[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone)]
 #[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
 pub struct BasisPointFee(pub u64);
 
 impl BasisPointFee {
+    /// The fee on `amount`, rounded up (in favor of the protocol)
+    /// - saturates at `u64::MAX` for basis points above 10_000
     pub fn calc(&self, amount: u64) -> u64 {
-        self.0 * amount / 10_000
+        let fee = (self.0 as u128 * amount as u128).div_ceil(10_000);
+        u64::try_from(fee).unwrap_or(u64::MAX)
     }
+
+    /// The fee on `amount`, rounded down (in favor of the user, used for discounts and refunds)
+    pub fn calc_floor(&self, amount: u64) -> u64 {
+        let fee = self.0 as u128 * amount as u128 / 10_000;
+        u64::try_from(fee).unwrap_or(u64::MAX)
+    }
+}
+
+/// The saturating fee arithmetic fails with [`TokenError::Overflow`] instead in `hardened` builds
+fn unsaturated(value: u64) -> Result<u64, TokenError> {
+    hardened_guard!(value != u64::MAX, TokenError::Overflow);
+    Ok(value)
 }
 
 #[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone)]
@@ -43,6 +56,27 @@ pub struct ProgramFee {
 
     /// Current tx count for init, combined miller loop, final exponentiation and finalization (dynamic tx for input preparation ignored)
     pub proof_base_tx_count: u64,
+
+    /// Discount in basis points on the proof-verification-fee for merges (funded by an additional subvention)
+    pub merge_fee_discount: BasisPointFee,
+
+    /// Reward for each additional commitment in a commitment hash batch (see [`ProgramFee::commitment_hash_warden_reward`])
+    pub warden_hash_commitment_reward: Lamports,
+}
+
+/// The fields of the [`ProgramFee`] stored in place of the `program_fee` of [`FeeAccount`]s and [`crate::state::governor::GovernorAccount`]s
+/// - fields added to the [`ProgramFee`] later on are appended to these accounts (with a version increment and a migration)
+#[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone)]
+#[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
+pub struct ProgramFeeV0 {
+    pub lamports_per_tx: Lamports,
+    pub base_commitment_network_fee: BasisPointFee,
+    pub proof_network_fee: BasisPointFee,
+    pub base_commitment_subvention: Lamports,
+    pub proof_subvention: Lamports,
+    pub warden_hash_tx_reward: Lamports,
+    pub warden_proof_reward: Lamports,
+    pub proof_base_tx_count: u64,
 }
 
 impl ProgramFee {
@@ -65,6 +99,8 @@ impl ProgramFee {
             warden_hash_tx_reward: Lamports(warden_hash_tx_reward),
             warden_proof_reward: Lamports(warden_proof_reward),
             proof_base_tx_count: Self::proof_base_tx_count(),
+            merge_fee_discount: BasisPointFee(0),
+            warden_hash_commitment_reward: Lamports(0),
         };
 
         if s.is_valid() {
@@ -74,8 +110,55 @@ impl ProgramFee {
         }
     }
 
+    /// Combines the stored fields of the initial layout with the fields appended with the account version 1
+    pub fn from_stored(
+        fee: ProgramFeeV0,
+        merge_fee_discount: BasisPointFee,
+        warden_hash_commitment_reward: Lamports,
+    ) -> Self {
+        Self {
+            lamports_per_tx: fee.lamports_per_tx,
+            base_commitment_network_fee: fee.base_commitment_network_fee,
+            proof_network_fee: fee.proof_network_fee,
+            base_commitment_subvention: fee.base_commitment_subvention,
+            proof_subvention: fee.proof_subvention,
+            warden_hash_tx_reward: fee.warden_hash_tx_reward,
+            warden_proof_reward: fee.warden_proof_reward,
+            proof_base_tx_count: fee.proof_base_tx_count,
+            merge_fee_discount,
+            warden_hash_commitment_reward,
+        }
+    }
+
+    /// The fields stored in the initial layout (see [`ProgramFeeV0`])
+    pub fn v0(&self) -> ProgramFeeV0 {
+        ProgramFeeV0 {
+            lamports_per_tx: self.lamports_per_tx,
+            base_commitment_network_fee: self.base_commitment_network_fee.clone(),
+            proof_network_fee: self.proof_network_fee.clone(),
+            base_commitment_subvention: self.base_commitment_subvention,
+            proof_subvention: self.proof_subvention,
+            warden_hash_tx_reward: self.warden_hash_tx_reward,
+            warden_proof_reward: self.warden_proof_reward,
+            proof_base_tx_count: self.proof_base_tx_count,
+        }
+    }
+
     /// Verifies that possible subventions are not too high
     pub fn is_valid(&self) -> bool {
+        if self.merge_fee_discount.0 > 10_000 {
+            return false;
+        }
+
+        // Commitments can be hashed in batches of a higher batching rate than the one they paid for
+        for batching_rate in 1..=MAX_COMMITMENT_BATCHING_RATE as u32 {
+            if self.commitment_hash_computation_fee(batching_rate).0
+                > self.commitment_hash_computation_fee(batching_rate - 1).0
+            {
+                return false;
+            }
+        }
+
         for min_batching_rate in 0..MAX_COMMITMENT_BATCHING_RATE as u32 {
             let commitment_fee = self.commitment_hash_computation_fee(min_batching_rate).0;
             if self.base_commitment_subvention.0 > commitment_fee {
@@ -83,12 +166,28 @@ impl ProgramFee {
             }
 
             // For proof verification we assume the cheapest scenario to be proof_base_tx_count (and network fee to be zero)
-            let proof_fee = self.proof_base_tx_count * self.lamports_per_tx.0
-                + self.commitment_hash_computation_fee(min_batching_rate).0;
+            let proof_fee = self
+                .proof_base_tx_count
+                .saturating_mul(self.lamports_per_tx.0)
+                .saturating_add(self.commitment_hash_computation_fee(min_batching_rate).0);
             if self.proof_subvention.0 > proof_fee {
                 return false;
             }
 
+            // The merge-subvention grows at most as fast as the proof-verification-fee, so the cheapest scenario suffices
+            let merge_fee = self
+                .proof_verification_computation_fee(0)
+                .0
+                .saturating_add(self.commitment_hash_computation_fee(min_batching_rate).0);
+            if self
+                .proof_subvention
+                .0
+                .saturating_add(self.merge_subvention(0).0)
+                > merge_fee
+            {
+                return false;
+            }
+
             if self.proof_base_tx_count != Self::proof_base_tx_count() {
                 return false;
             }
@@ -96,40 +195,259 @@ impl ProgramFee {
         true
     }
 
-    pub fn proof_base_tx_count() -> u64 {
-        (CombinedMillerLoop::TX_COUNT + FinalExponentiation::TX_COUNT + 2) as u64
+    pub const fn proof_base_tx_count() -> u64 {
+        PROOF_BASE_TX_COUNT as u64
+    }
+}
+
+/// Sets the fields appended to the [`FeeAccount`] in version 1 (the account is resized beforehand, see [`crate::processor::migrate_fee_account`])
+fn fee_account_migration(fee_account: &mut FeeAccount, from_version: u8) -> ProgramResult {
+    match from_version {
+        0 => {
+            fee_account.set_merge_fee_discount(&BasisPointFee(0));
+            fee_account.set_warden_hash_commitment_reward(&Lamports(0));
+            Ok(())
+        }
+        _ => Err(ElusivError::InvalidAccountState.into()),
     }
 }
 
 /// Specifies the program fees and compensation for wardens
-#[elusiv_account]
+#[elusiv_account(version: 1, migration: fee_account_migration)]
 pub struct FeeAccount {
     #[no_getter]
     #[no_setter]
     pda_data: PDAAccountData,
 
-    pub program_fee: ProgramFee,
+    #[no_getter]
+    #[no_setter]
+    pub program_fee: ProgramFeeV0,
+
+    // Version 1
+    merge_fee_discount: BasisPointFee,
+    warden_hash_commitment_reward: Lamports,
+}
+
+impl<'a> FeeAccount<'a> {
+    pub fn get_program_fee(&self) -> ProgramFee {
+        ProgramFee::from_stored(
+            ProgramFeeV0::try_from_slice(self.program_fee).unwrap(),
+            self.get_merge_fee_discount(),
+            self.get_warden_hash_commitment_reward(),
+        )
+    }
+
+    pub fn set_program_fee(&mut self, value: &ProgramFee) {
+        let mut slice = &mut self.program_fee[..];
+        value.v0().serialize(&mut slice).unwrap();
+        self.set_merge_fee_discount(&value.merge_fee_discount);
+        self.set_warden_hash_commitment_reward(&value.warden_hash_commitment_reward);
+    }
+}
+
+/// Number of consecutive fee-versions tracked by the [`FeeVersionUsageAccount`]
+pub const FEE_VERSION_USAGE_WINDOW: usize = 8;
+
+/// Counts the pending uses of the tracked fee-versions, so that drained fee-versions can be retired
+///
+/// # Note
+///
+/// - A use starts with the admission of a proof verification (fee transfer) or a base commitment (store).
+/// - A use ends once its commitment has been inserted into the active MT, or with the finalization of a failed verification.
+/// - The fee-versions `min_fee_version..min_fee_version + FEE_VERSION_USAGE_WINDOW` are tracked, all lower fee-versions are retired.
+#[elusiv_account(eager_type: true)]
+pub struct FeeVersionUsageAccount {
+    #[no_getter]
+    #[no_setter]
+    pda_data: PDAAccountData,
+
+    /// The lowest fee-version that has not been retired
+    pub min_fee_version: u32,
+
+    /// The pending uses of the fee-version `v` at the index `v % FEE_VERSION_USAGE_WINDOW`
+    pub pending_counts: [u32; FEE_VERSION_USAGE_WINDOW],
+}
+
+impl<'a> FeeVersionUsageAccount<'a> {
+    fn is_tracked(&self, fee_version: u32) -> bool {
+        let min_fee_version = self.get_min_fee_version();
+        fee_version >= min_fee_version
+            && ((fee_version - min_fee_version) as usize) < FEE_VERSION_USAGE_WINDOW
+    }
+
+    /// The pending uses of `fee_version` (none if the fee-version is not tracked)
+    pub fn pending_count(&self, fee_version: u32) -> Option<u32> {
+        if !self.is_tracked(fee_version) {
+            return None;
+        }
+
+        Some(self.get_pending_counts(fee_version as usize % FEE_VERSION_USAGE_WINDOW))
+    }
+
+    /// Starts a use of `fee_version` (retired and untracked fee-versions are refused)
+    pub fn admit(&mut self, fee_version: u32) -> Result<(), ElusivError> {
+        let count = self
+            .pending_count(fee_version)
+            .ok_or(ElusivError::InvalidFeeVersion)?
+            .checked_add(1)
+            .ok_or(ElusivError::InvalidAccountState)?;
+        self.set_pending_counts(fee_version as usize % FEE_VERSION_USAGE_WINDOW, &count);
+
+        Ok(())
+    }
+
+    /// Ends `count` uses of `fee_version`
+    ///
+    /// # Note
+    ///
+    /// Saturates at zero and ignores untracked fee-versions, so that uses started before the account existed can still be settled.
+    pub fn release(&mut self, fee_version: u32, count: u32) {
+        if let Some(pending_count) = self.pending_count(fee_version) {
+            self.set_pending_counts(
+                fee_version as usize % FEE_VERSION_USAGE_WINDOW,
+                &pending_count.saturating_sub(count),
+            );
+        }
+    }
+
+    /// Verifies that `fee_version` can be tracked without retiring further fee-versions
+    pub fn verify_trackable(&self, fee_version: u32) -> Result<(), ElusivError> {
+        let window_end = self
+            .get_min_fee_version()
+            .saturating_add(FEE_VERSION_USAGE_WINDOW as u32);
+        guard!(fee_version < window_end, ElusivError::FeeVersionInUse);
+
+        Ok(())
+    }
+
+    /// Retires the oldest tracked fee-version, once it has no pending uses
+    /// - fee-versions are retired in order and only below `current_fee_version`
+    pub fn retire(
+        &mut self,
+        fee_version: u32,
+        current_fee_version: u32,
+    ) -> Result<(), ElusivError> {
+        guard!(
+            fee_version == self.get_min_fee_version() && fee_version < current_fee_version,
+            ElusivError::InvalidFeeVersion
+        );
+        guard!(
+            self.pending_count(fee_version) == Some(0),
+            ElusivError::FeeVersionInUse
+        );
+
+        self.set_min_fee_version(&(fee_version + 1));
+
+        Ok(())
+    }
 }
 
+/// The components of a base-commitment-fee
+///
+/// # Note
+///
+/// Used both on-chain and by clients quoting the fee of a deposit, so that both always agree.
+pub struct BaseCommitmentFee {
+    pub computation_fee: Lamports,
+    pub computation_fee_token: Token,
+    pub network_fee: Token,
+    pub subvention: Token,
+}
+
+impl BaseCommitmentFee {
+    /// The fee that has to be paid by the sender (on top of the deposited amount)
+    pub fn total(&self) -> Result<Token, TokenError> {
+        (self.computation_fee_token + self.network_fee)? - self.subvention
+    }
+}
+
+/// The components of a proof-verification-fee
+///
+/// # Note
+///
+/// Used both on-chain and by clients estimating the fee, so that both always agree.
+pub struct ProofFee {
+    pub proof_verification_fee: Token,
+    pub commitment_hash_fee: Lamports,
+    pub commitment_hash_fee_token: Token,
+    pub network_fee: Token,
+    pub subvention: Token,
+}
+
+impl ProofFee {
+    /// The fee that has to be paid by the user
+    pub fn total(&self) -> Result<Token, TokenError> {
+        ((self.commitment_hash_fee_token + self.proof_verification_fee)? + self.network_fee)?
+            - self.subvention
+    }
+
+    /// Caps the subvention at `cap` basis points of `amount` (a `cap` of zero disables the cap)
+    /// - rounded down, since the subvention is paid by the protocol
+    pub fn cap_subvention(&mut self, cap: u64, amount: u64) {
+        if cap == 0 {
+            return;
+        }
+
+        let max_subvention = BasisPointFee(cap).calc_floor(amount);
+        if self.subvention.amount() > max_subvention {
+            self.subvention = Token::new(self.subvention.token_id(), max_subvention);
+        }
+    }
+}
+
+// All fee computations saturate instead of overflowing (the resulting fees are simply too high to be paid)
 impl ProgramFee {
     pub fn hash_tx_compensation(&self) -> Lamports {
-        Lamports(self.lamports_per_tx.0 + self.warden_hash_tx_reward.0)
+        Lamports(
+            self.lamports_per_tx
+                .0
+                .saturating_add(self.warden_hash_tx_reward.0),
+        )
     }
 
     pub fn base_commitment_hash_computation_fee(&self) -> Lamports {
         // extra `lamports_per_tx` for the second signature, paid for by the fee-payer
 
         Lamports(
-            BaseCommitmentHashComputation::TX_COUNT as u64 * self.hash_tx_compensation().0
-                + self.lamports_per_tx.0,
+            (BASE_COMMITMENT_HASH.tx_count as u64)
+                .saturating_mul(self.hash_tx_compensation().0)
+                .saturating_add(self.lamports_per_tx.0),
+        )
+    }
+
+    /// The warden reward for hashing a batch of `commitments_per_batch(batching_rate)` commitments
+    /// - `warden_hash_tx_reward` for each tx of a single commitment batch and `warden_hash_commitment_reward` for each additional commitment
+    pub fn commitment_hash_warden_reward(&self, batching_rate: u32) -> Lamports {
+        let base_tx_count = computation_metadata::commitment_hash(0).ix_count as u64;
+        let additional_commitments = commitments_per_batch(batching_rate) as u64 - 1;
+        Lamports(
+            base_tx_count
+                .saturating_mul(self.warden_hash_tx_reward.0)
+                .saturating_add(
+                    additional_commitments.saturating_mul(self.warden_hash_commitment_reward.0),
+                ),
+        )
+    }
+
+    /// The compensation for each commitment hash tx of a batch
+    /// - the warden reward is split evenly over the txs (rounded down)
+    pub fn commitment_hash_tx_compensation(&self, batching_rate: u32) -> Lamports {
+        let tx_count = computation_metadata::commitment_hash(batching_rate).ix_count as u64;
+        Lamports(
+            self.lamports_per_tx
+                .0
+                .saturating_add(self.commitment_hash_warden_reward(batching_rate).0 / tx_count),
         )
     }
 
     pub fn commitment_hash_computation_fee(&self, min_batching_rate: u32) -> Lamports {
-        let tx_count_total = commitment_hash_computation_instructions(min_batching_rate).len();
+        // Each commitment hash instruction is sent in its own transaction
+        let tx_count_total = computation_metadata::commitment_hash(min_batching_rate).ix_count;
         let commitments_per_batch = commitments_per_batch(min_batching_rate);
         Lamports(div_ceiling_u64(
-            tx_count_total as u64 * self.hash_tx_compensation().0,
+            (tx_count_total as u64)
+                .saturating_mul(self.lamports_per_tx.0)
+                .saturating_add(self.commitment_hash_warden_reward(min_batching_rate).0),
             commitments_per_batch as u64,
         ))
     }
@@ -138,30 +456,425 @@ impl ProgramFee {
         &self,
         input_preparation_tx_count: usize,
     ) -> Lamports {
-        let amount = (input_preparation_tx_count + u64_as_usize_safe(self.proof_base_tx_count))
-            as u64
-            * self.lamports_per_tx.0
-            + self.warden_proof_reward.0;
-        Lamports(amount)
+        let tx_count = (input_preparation_tx_count as u64).saturating_add(self.proof_base_tx_count);
+        Lamports(
+            tx_count
+                .saturating_mul(self.lamports_per_tx.0)
+                .saturating_add(self.warden_proof_reward.0),
+        )
     }
 
+    /// The additional subvention for merges, which funds the `merge_fee_discount` on the proof-verification-fee
+    /// - rounded down, since the subvention is paid by the protocol
+    pub fn merge_subvention(&self, input_preparation_tx_count: usize) -> Lamports {
+        Lamports(
+            self.merge_fee_discount.calc_floor(
+                self.proof_verification_computation_fee(input_preparation_tx_count)
+                    .0,
+            ),
+        )
+    }
+
+    /// The total subvention for a proof (including the merge-subvention for merges)
+    pub fn proof_subvention(
+        &self,
+        input_preparation_tx_count: usize,
+        is_merge: bool,
+    ) -> Result<Lamports, TokenError> {
+        if is_merge {
+            self.proof_subvention + self.merge_subvention(input_preparation_tx_count)
+        } else {
+            Ok(self.proof_subvention)
+        }
+    }
+
+    pub fn proof_fee(
+        &self,
+        input_preparation_tx_count: usize,
+        min_batching_rate: u32,
+        amount: u64,
+        token_id: u16,
+        is_merge: bool,
+        price: &TokenPrice,
+    ) -> Result<ProofFee, TokenError> {
+        let commitment_hash_fee = Lamports(unsaturated(
+            self.commitment_hash_computation_fee(min_batching_rate).0,
+        )?);
+        let proof_verification_fee = Lamports(unsaturated(
+            self.proof_verification_computation_fee(input_preparation_tx_count)
+                .0,
+        )?);
+
+        Ok(ProofFee {
+            proof_verification_fee: proof_verification_fee.into_token(price, token_id)?,
+            commitment_hash_fee,
+            commitment_hash_fee_token: commitment_hash_fee.into_token(price, token_id)?,
+            network_fee: Token::new(token_id, unsaturated(self.proof_network_fee.calc(amount))?),
+            subvention: self
+                .proof_subvention(input_preparation_tx_count, is_merge)?
+                .into_token(price, token_id)?,
+        })
+    }
+
+    /// The total fee of a proof (computed by clients with [`crate::types::compute_fee_rec`])
     pub fn proof_verification_fee(
         &self,
         input_preparation_tx_count: usize,
         min_batching_rate: u32,
         amount: u64,
         token_id: u16,
+        is_merge: bool,
         price: &TokenPrice,
     ) -> Result<Token, TokenError> {
-        let proof_verification_fee = self
-            .proof_verification_computation_fee(input_preparation_tx_count)
-            .into_token(price, token_id)?;
-        let commitment_hash_fee = self
-            .commitment_hash_computation_fee(min_batching_rate)
-            .into_token(price, token_id)?;
-        let network_fee = Token::new(token_id, self.proof_network_fee.calc(amount));
-        let subvention = self.proof_subvention.into_token(price, token_id)?;
-
-        ((proof_verification_fee + commitment_hash_fee)? + network_fee)? - subvention
+        self.proof_fee(
+            input_preparation_tx_count,
+            min_batching_rate,
+            amount,
+            token_id,
+            is_merge,
+            price,
+        )?
+        .total()
+    }
+
+    pub fn base_commitment_fee(
+        &self,
+        min_batching_rate: u32,
+        amount: u64,
+        token_id: u16,
+        price: &TokenPrice,
+    ) -> Result<BaseCommitmentFee, TokenError> {
+        let computation_fee = (self.base_commitment_hash_computation_fee()
+            + self.commitment_hash_computation_fee(min_batching_rate))?;
+
+        Ok(BaseCommitmentFee {
+            computation_fee,
+            computation_fee_token: computation_fee.into_token(price, token_id)?,
+            network_fee: Token::new(token_id, self.base_commitment_network_fee.calc(amount)),
+            subvention: self
+                .base_commitment_subvention
+                .into_token(price, token_id)?,
+        })
+    }
+
+    /// The total fee of a deposit (net of the subvention), mirrors [`ProgramFee::proof_verification_fee`]
+    pub fn base_commitment_fee_quote(
+        &self,
+        min_batching_rate: u32,
+        amount: u64,
+        token_id: u16,
+        price: &TokenPrice,
+    ) -> Result<Token, TokenError> {
+        self.base_commitment_fee(min_batching_rate, amount, token_id, price)?
+            .total()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::zero_program_account;
+    use elusiv_types::tokens::Price;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn test_basis_point_fee_rounding() {
+        assert_eq!(BasisPointFee(1).calc(1), 1);
+        assert_eq!(BasisPointFee(1).calc_floor(1), 0);
+
+        assert_eq!(BasisPointFee(100).calc(10_000), 100);
+        assert_eq!(BasisPointFee(100).calc_floor(10_000), 100);
+        assert_eq!(BasisPointFee(100).calc(10_001), 101);
+        assert_eq!(BasisPointFee(100).calc_floor(10_001), 100);
+
+        assert_eq!(BasisPointFee(0).calc(u64::MAX), 0);
+        assert_eq!(BasisPointFee(10_000).calc(0), 0);
+    }
+
+    #[test]
+    fn test_basis_point_fee_extremes() {
+        assert_eq!(BasisPointFee(10_000).calc(u64::MAX), u64::MAX);
+        assert_eq!(BasisPointFee(10_000).calc_floor(u64::MAX), u64::MAX);
+        assert_eq!(BasisPointFee(1).calc(u64::MAX), u64::MAX / 10_000 + 1);
+        assert_eq!(BasisPointFee(1).calc_floor(u64::MAX), u64::MAX / 10_000);
+
+        // Saturating
+        assert_eq!(BasisPointFee(10_001).calc(u64::MAX), u64::MAX);
+        assert_eq!(BasisPointFee(u64::MAX).calc(u64::MAX), u64::MAX);
+        assert_eq!(BasisPointFee(u64::MAX).calc_floor(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_basis_point_fee_random() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        for _ in 0..10_000 {
+            let bps = rng.gen_range(0..=10_000);
+            let amount = match rng.gen_range(0..3) {
+                0 => rng.gen_range(0..10_000),
+                1 => rng.gen(),
+                _ => u64::MAX - rng.gen_range(0..10_000),
+            };
+            let fee = BasisPointFee(bps);
+            let (ceil, floor) = (fee.calc(amount), fee.calc_floor(amount));
+
+            assert!(floor <= ceil && ceil - floor <= 1);
+            assert!(ceil <= amount);
+            assert!(ceil as u128 * 10_000 >= bps as u128 * amount as u128);
+            assert!(floor as u128 * 10_000 <= bps as u128 * amount as u128);
+
+            // A non-zero fee is never rounded away
+            if bps > 0 && amount > 0 {
+                assert!(ceil > 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_program_fee_extremes() {
+        for v in [0, 1, u64::MAX / 2, u64::MAX] {
+            let fee = ProgramFee {
+                lamports_per_tx: Lamports(v),
+                base_commitment_network_fee: BasisPointFee(v),
+                proof_network_fee: BasisPointFee(v),
+                base_commitment_subvention: Lamports(v),
+                proof_subvention: Lamports(v),
+                warden_hash_tx_reward: Lamports(v),
+                warden_proof_reward: Lamports(v),
+                proof_base_tx_count: v,
+                merge_fee_discount: BasisPointFee(v),
+                warden_hash_commitment_reward: Lamports(v),
+            };
+
+            fee.is_valid();
+            fee.base_commitment_hash_computation_fee();
+            fee.commitment_hash_computation_fee(MAX_COMMITMENT_BATCHING_RATE as u32);
+            fee.commitment_hash_tx_compensation(MAX_COMMITMENT_BATCHING_RATE as u32);
+            fee.proof_verification_computation_fee(usize::MAX);
+            fee.merge_subvention(usize::MAX);
+
+            for is_merge in [false, true] {
+                let _ = fee.proof_verification_fee(
+                    usize::MAX,
+                    0,
+                    u64::MAX,
+                    0,
+                    is_merge,
+                    &TokenPrice::new_lamports(),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_proof_fee_saturated() {
+        let fee = ProgramFee::new(u64::MAX, 0, 0, 0, 0, 0, 0).unwrap();
+        let proof_fee = fee.proof_fee(1, 0, 0, 0, false, &TokenPrice::new_lamports());
+
+        // Saturated fees are only rejected by hardened builds
+        if cfg!(feature = "hardened") {
+            assert!(matches!(proof_fee, Err(TokenError::Overflow)));
+        } else {
+            assert_eq!(
+                proof_fee.unwrap().proof_verification_fee,
+                Token::new(0, u64::MAX)
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_subvention_rounded_down() {
+        let fee = ProgramFee {
+            merge_fee_discount: BasisPointFee(1),
+            ..ProgramFee::new(1, 0, 0, 0, 0, 0, 0).unwrap()
+        };
+        assert!(fee.is_valid());
+        let computation_fee = fee.proof_verification_computation_fee(0).0;
+        assert!(computation_fee < 10_000);
+        assert_eq!(fee.merge_subvention(0), Lamports(0));
+    }
+
+    #[test]
+    fn test_commitment_hash_reward_curve() {
+        let fee = ProgramFee {
+            warden_hash_commitment_reward: Lamports(100),
+            ..ProgramFee::new(5000, 0, 0, 0, 0, 300, 555).unwrap()
+        };
+        assert!(fee.is_valid());
+        let base_tx_count = computation_metadata::commitment_hash(0).ix_count as u64;
+
+        for batching_rate in [1, 2, 4] {
+            let commitments = commitments_per_batch(batching_rate) as u64;
+            assert_eq!(
+                fee.commitment_hash_warden_reward(batching_rate).0,
+                base_tx_count * 300 + (commitments - 1) * 100
+            );
+
+            // The fees of a batch cover the compensation of all of its txs
+            let tx_count = computation_metadata::commitment_hash(batching_rate).ix_count as u64;
+            assert!(
+                commitments * fee.commitment_hash_computation_fee(batching_rate).0
+                    >= tx_count * fee.commitment_hash_tx_compensation(batching_rate).0
+            );
+        }
+
+        // The per-commitment cost decreases with the batch size
+        let fee_1 = fee.commitment_hash_computation_fee(1).0;
+        let fee_2 = fee.commitment_hash_computation_fee(2).0;
+        let fee_4 = fee.commitment_hash_computation_fee(4).0;
+        assert!(fee_1 > fee_2);
+        assert!(fee_2 > fee_4);
+
+        // A single commitment batch is rewarded with `warden_hash_tx_reward` per tx
+        assert_eq!(
+            fee.commitment_hash_tx_compensation(0),
+            fee.hash_tx_compensation()
+        );
+
+        // Larger batches must not be more expensive per commitment
+        assert!(!ProgramFee {
+            warden_hash_commitment_reward: Lamports(1_000_000),
+            ..fee
+        }
+        .is_valid());
+    }
+
+    #[test]
+    fn test_base_commitment_fee_quote() {
+        let fee = ProgramFee::new(5000, 11, 100, 33, 44, 300, 555).unwrap();
+        let usdc_price = TokenPrice::new_from_sol_price(
+            Price {
+                price: 39,
+                conf: 1,
+                expo: 0,
+            },
+            Price {
+                price: 1,
+                conf: 1,
+                expo: 0,
+            },
+            1,
+        )
+        .unwrap();
+
+        for (token_id, amount, price) in [
+            (0, 1_000_000_000, TokenPrice::new_lamports()),
+            (1, 1_000_000, usdc_price),
+        ] {
+            let computation_fee = (fee.base_commitment_hash_computation_fee()
+                + fee.commitment_hash_computation_fee(2))
+            .unwrap();
+            let expected = computation_fee
+                .into_token(&price, token_id)
+                .unwrap()
+                .amount()
+                + fee.base_commitment_network_fee.calc(amount)
+                - fee
+                    .base_commitment_subvention
+                    .into_token(&price, token_id)
+                    .unwrap()
+                    .amount();
+
+            assert_eq!(
+                fee.base_commitment_fee_quote(2, amount, token_id, &price),
+                Ok(Token::new(token_id, expected))
+            );
+        }
+    }
+
+    #[test]
+    fn test_cap_subvention() {
+        let mut proof_fee = ProofFee {
+            proof_verification_fee: Token::new(0, 1000),
+            commitment_hash_fee: Lamports(100),
+            commitment_hash_fee_token: Token::new(0, 100),
+            network_fee: Token::new(0, 10),
+            subvention: Token::new(0, 50),
+        };
+
+        // No cap
+        proof_fee.cap_subvention(0, 1);
+        assert_eq!(proof_fee.subvention.amount(), 50);
+
+        // Large amount
+        proof_fee.cap_subvention(100, 1_000_000);
+        assert_eq!(proof_fee.subvention.amount(), 50);
+
+        // Dust amount
+        proof_fee.cap_subvention(100, 1_999);
+        assert_eq!(proof_fee.subvention.amount(), 19);
+        assert_eq!(proof_fee.total().unwrap().amount(), 1000 + 100 + 10 - 19);
+
+        proof_fee.cap_subvention(10_000, 1);
+        assert_eq!(proof_fee.subvention.amount(), 1);
+    }
+
+    #[test]
+    fn test_fee_version_usage() {
+        zero_program_account!(mut usage, FeeVersionUsageAccount);
+
+        usage.admit(0).unwrap();
+        usage.admit(0).unwrap();
+        usage.admit(1).unwrap();
+        assert_eq!(usage.pending_count(0), Some(2));
+        assert_eq!(usage.pending_count(1), Some(1));
+
+        // Untracked fee-versions
+        assert_eq!(
+            usage.admit(FEE_VERSION_USAGE_WINDOW as u32),
+            Err(ElusivError::InvalidFeeVersion)
+        );
+        assert_eq!(usage.pending_count(FEE_VERSION_USAGE_WINDOW as u32), None);
+        usage.release(FEE_VERSION_USAGE_WINDOW as u32, 1);
+
+        // Retirement blocked by pending uses
+        assert_eq!(usage.retire(0, 2), Err(ElusivError::FeeVersionInUse));
+
+        usage.release(0, 1);
+        assert_eq!(usage.pending_count(0), Some(1));
+        usage.release(0, 1);
+        assert_eq!(usage.pending_count(0), Some(0));
+
+        // Saturating (uses started before the account existed)
+        usage.release(0, 1);
+        assert_eq!(usage.pending_count(0), Some(0));
+
+        // Only the oldest tracked fee-version below the current fee-version can be retired
+        assert_eq!(usage.retire(1, 2), Err(ElusivError::InvalidFeeVersion));
+        assert_eq!(usage.retire(0, 0), Err(ElusivError::InvalidFeeVersion));
+
+        usage.retire(0, 2).unwrap();
+        assert_eq!(usage.get_min_fee_version(), 1);
+        assert_eq!(usage.pending_count(0), None);
+        assert_eq!(usage.admit(0), Err(ElusivError::InvalidFeeVersion));
+        assert_eq!(usage.retire(0, 2), Err(ElusivError::InvalidFeeVersion));
+
+        // The slot of the retired fee-version is reused
+        usage.admit(FEE_VERSION_USAGE_WINDOW as u32).unwrap();
+        assert_eq!(
+            usage.pending_count(FEE_VERSION_USAGE_WINDOW as u32),
+            Some(1)
+        );
+        assert_eq!(usage.pending_count(1), Some(1));
+    }
+
+    #[test]
+    fn test_fee_version_usage_verify_trackable() {
+        zero_program_account!(mut usage, FeeVersionUsageAccount);
+
+        assert_eq!(
+            usage.verify_trackable(FEE_VERSION_USAGE_WINDOW as u32 - 1),
+            Ok(())
+        );
+        assert_eq!(
+            usage.verify_trackable(FEE_VERSION_USAGE_WINDOW as u32),
+            Err(ElusivError::FeeVersionInUse)
+        );
+
+        usage.retire(0, 1).unwrap();
+        assert_eq!(
+            usage.verify_trackable(FEE_VERSION_USAGE_WINDOW as u32),
+            Ok(())
+        );
     }
 }
@@ -1,7 +1,16 @@
-use super::{fee::ProgramFee, program_account::PDAAccountData};
-use crate::macros::elusiv_account;
+use super::{
+    fee::{BasisPointFee, ProgramFee, ProgramFeeV0},
+    program_account::PDAAccountData,
+};
+use crate::error::ElusivError;
+use crate::macros::{elusiv_account, guard};
+use crate::token::{elusiv_token, Lamports, TokenError, TOKENS};
+use borsh::{BorshDeserialize, BorshSerialize};
+use elusiv_derive::BorshSerDeSized;
+use elusiv_types::ElusivOption;
+use solana_program::entrypoint::ProgramResult;
 
-#[elusiv_account(eager_type: true)]
+#[elusiv_account(eager_type: true, version: 1, migration: governor_account_migration)]
 pub struct GovernorAccount {
     #[no_getter]
     #[no_setter]
@@ -10,13 +19,308 @@ pub struct GovernorAccount {
     /// The current fee-version (new requests are forced to use this version)
     pub fee_version: u32,
 
-    /// The `ProgramFee` for the `FeeAccount` with the offset `fee_version`
-    pub program_fee: ProgramFee,
+    /// The `ProgramFee` for the `FeeAccount` with the offset `fee_version` (see [`GovernorAccount::get_program_fee`])
+    #[no_getter]
+    #[no_setter]
+    pub program_fee: ProgramFeeV0,
 
     /// The number of commitments in a MT-root hashing batch
     pub commitment_batching_rate: u32,
 
     program_version: u32,
+
+    /// Queue length (including pending commitments) above which no new proof verifications are admitted (zero: the queue capacity)
+    pub commitment_queue_high_water_mark: u32,
+
+    /// Number of slots per warden-reward epoch (zero: rewards are paid per proof)
+    pub warden_rotation_epoch: u64,
+
+    /// Number of slots after which an incomplete verification can be expired (zero: verifications never expire)
+    /// - has to exceed the duration of an honest computation by far
+    pub verification_ttl_slots: u64,
+
+    /// Maximum proof-subvention in basis points of the join-split amount
+    /// - zero disables the cap, so every proof receives the full flat `proof_subvention` of its `ProgramFee`
+    pub proof_subvention_cap: u64,
+
+    /// Maximum join-split amount of a send per token-id (zero: no limit)
+    pub max_token_amounts: [u64; TOKENS.len()],
+
+    /// Verbosity of the program logs (none: [`LogLevel::Events`])
+    pub log_level: ElusivOption<LogLevel>,
+
+    /// The `ProgramFee` of the fee-version `fee_version + 1` and the slot from which on proofs can use it
+    pub scheduled_fee_update: ElusivOption<ScheduledFeeUpdate>,
+
+    /// Number of slots after the activation of a scheduled fee update during which the previous fee-version is still accepted
+    pub fee_version_grace_slots: u64,
+
+    /// Verifications can carry encrypted notes, which are logged in the [`crate::state::note::NoteLogAccount`]
+    pub note_log_enabled: bool,
+
+    /// The price accounts are unavailable, so only Lamports requests are admitted
+    pub oracle_degraded: bool,
+
+    // The `ProgramFee` fields appended with version 1
+    merge_fee_discount: BasisPointFee,
+    warden_hash_commitment_reward: Lamports,
+}
+
+/// Upgrades the data layout of a [`GovernorAccount`] (the account is resized beforehand, see [`crate::processor::migrate_governor_account`])
+/// - version 1: the fields following `program_version` and the appended `ProgramFee` fields are initialized with their defaults
+fn governor_account_migration(governor: &mut GovernorAccount, from_version: u8) -> ProgramResult {
+    match from_version {
+        0 => {
+            governor.set_commitment_queue_high_water_mark(&0);
+            governor.set_warden_rotation_epoch(&0);
+            governor.set_verification_ttl_slots(&0);
+            governor.set_proof_subvention_cap(&0);
+            for token_id in 0..TOKENS.len() {
+                governor.set_max_token_amounts(token_id, &0);
+            }
+            governor.set_log_level(&ElusivOption::None);
+            governor.set_scheduled_fee_update(&ElusivOption::None);
+            governor.set_fee_version_grace_slots(&0);
+            governor.set_note_log_enabled(&false);
+            governor.set_oracle_degraded(&false);
+            governor.set_merge_fee_discount(&BasisPointFee(0));
+            governor.set_warden_hash_commitment_reward(&Lamports(0));
+            Ok(())
+        }
+        _ => Err(ElusivError::InvalidAccountState.into()),
+    }
+}
+
+impl<'a> GovernorAccount<'a> {
+    pub fn get_program_fee(&self) -> ProgramFee {
+        ProgramFee::from_stored(
+            ProgramFeeV0::try_from_slice(self.program_fee).unwrap(),
+            self.get_merge_fee_discount(),
+            self.get_warden_hash_commitment_reward(),
+        )
+    }
+
+    pub fn set_program_fee(&mut self, value: &ProgramFee) {
+        let mut slice = &mut self.program_fee[..];
+        value.v0().serialize(&mut slice).unwrap();
+        self.set_merge_fee_discount(&value.merge_fee_discount);
+        self.set_warden_hash_commitment_reward(&value.warden_hash_commitment_reward);
+    }
+
+    /// Verifies that a new request in `token_id`-Token can be priced
+    /// - Lamports requests never require prices
+    pub fn verify_oracle_available(&self, token_id: u16) -> Result<(), ElusivError> {
+        guard!(
+            token_id == 0 || !self.get_oracle_degraded(),
+            ElusivError::OracleUnavailable
+        );
+
+        Ok(())
+    }
+
+    /// The maximum join-split amount of a send in `token_id`-Token ([`u64::MAX`] if there is no limit)
+    pub fn get_max_token_amount(&self, token_id: u16) -> Result<u64, TokenError> {
+        elusiv_token(token_id)?;
+
+        match self.get_max_token_amounts(token_id as usize) {
+            0 => Ok(u64::MAX),
+            max => Ok(max),
+        }
+    }
+
+    /// The `ProgramFee` of `fee_version`, if proofs initialized at `slot` are allowed to use this version
+    /// - before the activation of a scheduled update only the current fee-version is accepted
+    /// - after the activation the scheduled fee-version is accepted (and the current one during the grace window)
+    pub fn accepted_program_fee(
+        &self,
+        fee_version: u32,
+        slot: u64,
+    ) -> Result<ProgramFee, ElusivError> {
+        let current_fee_version = self.get_fee_version();
+
+        if let Some(update) = self.activated_fee_update(slot) {
+            if fee_version == current_fee_version.saturating_add(1) {
+                return Ok(update.program_fee);
+            }
+
+            guard!(
+                fee_version == current_fee_version
+                    && slot < update.grace_end_slot(self.get_fee_version_grace_slots()),
+                ElusivError::InvalidFeeVersion
+            );
+        } else {
+            guard!(
+                fee_version == current_fee_version,
+                ElusivError::InvalidFeeVersion
+            );
+        }
+
+        Ok(self.get_program_fee())
+    }
+
+    /// The fee-version (and its `ProgramFee`) that new proofs submitted at `slot` should use
+    pub fn fee_version_at(&self, slot: u64) -> (u32, ProgramFee) {
+        match self.activated_fee_update(slot) {
+            Some(update) => (self.get_fee_version() + 1, update.program_fee),
+            None => (self.get_fee_version(), self.get_program_fee()),
+        }
+    }
+
+    /// The highest fee-version a request can have been created with
+    pub fn latest_fee_version(&self) -> u32 {
+        match self.get_scheduled_fee_update() {
+            ElusivOption::Some(_) => self.get_fee_version() + 1,
+            ElusivOption::None => self.get_fee_version(),
+        }
+    }
+
+    /// Schedules the `ProgramFee` of the next fee-version
+    /// - a previous update is applied first, once its grace window has passed
+    pub fn schedule_fee_update(
+        &mut self,
+        program_fee: ProgramFee,
+        activation_slot: u64,
+        slot: u64,
+    ) -> Result<(), ElusivError> {
+        guard!(activation_slot > slot, ElusivError::InvalidInstructionData);
+
+        if let ElusivOption::Some(update) = self.get_scheduled_fee_update() {
+            guard!(
+                slot >= update.grace_end_slot(self.get_fee_version_grace_slots()),
+                ElusivError::FeeUpdateAlreadyScheduled
+            );
+
+            self.set_fee_version(&(self.get_fee_version() + 1));
+            self.set_program_fee(&update.program_fee);
+        }
+
+        self.set_scheduled_fee_update(&ElusivOption::Some(ScheduledFeeUpdate {
+            program_fee,
+            activation_slot,
+        }));
+
+        Ok(())
+    }
+
+    /// Cancels the scheduled fee update (only possible before its activation)
+    pub fn cancel_scheduled_fee_update(&mut self, slot: u64) -> Result<(), ElusivError> {
+        match self.get_scheduled_fee_update() {
+            ElusivOption::Some(update) if slot < update.activation_slot => {
+                self.set_scheduled_fee_update(&ElusivOption::None);
+                Ok(())
+            }
+            _ => Err(ElusivError::InvalidAccountState),
+        }
+    }
+
+    /// Sets the grace window of scheduled fee updates (not possible once a scheduled update is activated)
+    /// - otherwise the running grace window would be shortened or an already closed one reopened
+    pub fn update_fee_version_grace_slots(
+        &mut self,
+        fee_version_grace_slots: u64,
+        slot: u64,
+    ) -> Result<(), ElusivError> {
+        guard!(
+            self.activated_fee_update(slot).is_none(),
+            ElusivError::InvalidAccountState
+        );
+
+        self.set_fee_version_grace_slots(&fee_version_grace_slots);
+
+        Ok(())
+    }
+
+    fn activated_fee_update(&self, slot: u64) -> Option<ScheduledFeeUpdate> {
+        self.get_scheduled_fee_update()
+            .option()
+            .filter(|update| slot >= update.activation_slot)
+    }
+
+    /// Whether `event` is logged with the configured [`LogLevel`]
+    pub fn logs(&self, event: LogEvent) -> bool {
+        self.get_log_level()
+            .option()
+            .unwrap_or_default()
+            .includes(event)
+    }
+}
+
+/// A `ProgramFee` that becomes the fee of the next fee-version at `activation_slot`
+#[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone)]
+#[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
+pub struct ScheduledFeeUpdate {
+    pub program_fee: ProgramFee,
+    pub activation_slot: u64,
+}
+
+impl ScheduledFeeUpdate {
+    /// The first slot at which the previous fee-version is no longer accepted
+    fn grace_end_slot(&self, grace_slots: u64) -> u64 {
+        self.activation_slot.saturating_add(grace_slots)
+    }
+}
+
+/// Verbosity of the program logs
+#[derive(
+    BorshDeserialize,
+    BorshSerialize,
+    BorshSerDeSized,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum LogLevel {
+    Off,
+    Errors,
+    #[default]
+    Events,
+    Verbose,
+}
+
+impl LogLevel {
+    pub fn includes(&self, event: LogEvent) -> bool {
+        event.log_level() <= *self
+    }
+}
+
+/// Logs emitted by the program in addition to errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogEvent {
+    /// The [`crate::state::proof::VerificationFault`] of a failed verification
+    VerificationFailure,
+
+    /// Finalized verification (with the external reference and USD value)
+    Finalize,
+
+    /// Commitment inserted into the active MT
+    CommitmentInserted,
+
+    /// The fee breakdown of a verification
+    FeeBreakdown,
+}
+
+impl LogEvent {
+    pub const ALL: [LogEvent; 4] = [
+        LogEvent::VerificationFailure,
+        LogEvent::Finalize,
+        LogEvent::CommitmentInserted,
+        LogEvent::FeeBreakdown,
+    ];
+
+    /// The minimum [`LogLevel`] at which the event is logged
+    pub const fn log_level(&self) -> LogLevel {
+        match self {
+            LogEvent::VerificationFailure => LogLevel::Errors,
+            LogEvent::Finalize | LogEvent::CommitmentInserted => LogLevel::Events,
+            LogEvent::FeeBreakdown => LogLevel::Verbose,
+        }
+    }
 }
 
 #[elusiv_account(eager_type: true)]
@@ -24,6 +328,47 @@ pub struct PoolAccount {
     #[no_getter]
     #[no_setter]
     pda_data: PDAAccountData,
+
+    /// Lamports reserved for the payouts of pending Lamports proof verifications
+    pub pending_lamport_reservation: u64,
+}
+
+impl<'a> PoolAccount<'a> {
+    /// Reserves `amount` Lamports for the payout of a pending proof verification
+    pub fn reserve_for_pending_verifications(&mut self, amount: u64) -> Result<(), ElusivError> {
+        let reservation = self
+            .get_pending_lamport_reservation()
+            .checked_add(amount)
+            .ok_or(ElusivError::InvalidAmount)?;
+        self.set_pending_lamport_reservation(&reservation);
+
+        Ok(())
+    }
+
+    /// Releases the reservation of a finalized proof verification
+    /// - `pool_lamports` need to cover `amount` without using the reservations of other pending verifications
+    pub fn release_reservation(
+        &mut self,
+        pool_lamports: u64,
+        amount: u64,
+        verify_payout: bool,
+    ) -> Result<(), ElusivError> {
+        // Verifications initialized before reservations existed have not reserved anything
+        let reservation = self
+            .get_pending_lamport_reservation()
+            .saturating_sub(amount);
+
+        if verify_payout {
+            guard!(
+                pool_lamports.saturating_sub(reservation) >= amount,
+                ElusivError::InsufficientFunds
+            );
+        }
+
+        self.set_pending_lamport_reservation(&reservation);
+
+        Ok(())
+    }
 }
 
 #[elusiv_account(eager_type: true)]
@@ -32,3 +377,166 @@ pub struct FeeCollectorAccount {
     #[no_setter]
     pda_data: PDAAccountData,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::zero_program_account;
+    use crate::state::fee::ProgramFee;
+    use crate::token::{LAMPORTS_TOKEN_ID, USDC_TOKEN_ID};
+
+    #[test]
+    fn test_get_max_token_amount() {
+        zero_program_account!(mut governor, GovernorAccount);
+        assert_eq!(
+            governor.get_max_token_amount(LAMPORTS_TOKEN_ID),
+            Ok(u64::MAX)
+        );
+        assert_eq!(governor.get_max_token_amount(USDC_TOKEN_ID), Ok(u64::MAX));
+
+        governor.set_max_token_amounts(USDC_TOKEN_ID as usize, &100);
+        assert_eq!(
+            governor.get_max_token_amount(LAMPORTS_TOKEN_ID),
+            Ok(u64::MAX)
+        );
+        assert_eq!(governor.get_max_token_amount(USDC_TOKEN_ID), Ok(100));
+
+        assert_eq!(
+            governor.get_max_token_amount(TOKENS.len() as u16),
+            Err(TokenError::InvalidTokenID)
+        );
+    }
+
+    #[test]
+    fn test_log_level() {
+        zero_program_account!(mut governor, GovernorAccount);
+
+        // Defaults to `LogLevel::Events`
+        assert_eq!(governor.get_log_level(), ElusivOption::None);
+        for event in LogEvent::ALL {
+            assert_eq!(governor.logs(event), event != LogEvent::FeeBreakdown);
+        }
+
+        governor.set_log_level(&ElusivOption::Some(LogLevel::Off));
+        for event in LogEvent::ALL {
+            assert!(!governor.logs(event));
+        }
+
+        governor.set_log_level(&ElusivOption::Some(LogLevel::Errors));
+        for event in LogEvent::ALL {
+            assert_eq!(governor.logs(event), event == LogEvent::VerificationFailure);
+        }
+
+        governor.set_log_level(&ElusivOption::Some(LogLevel::Verbose));
+        for event in LogEvent::ALL {
+            assert!(governor.logs(event));
+        }
+    }
+
+    #[test]
+    fn test_pool_reservations() {
+        zero_program_account!(mut pool, PoolAccount);
+
+        pool.reserve_for_pending_verifications(100).unwrap();
+        pool.reserve_for_pending_verifications(50).unwrap();
+        assert_eq!(pool.get_pending_lamport_reservation(), 150);
+
+        assert_eq!(
+            pool.reserve_for_pending_verifications(u64::MAX),
+            Err(ElusivError::InvalidAmount)
+        );
+
+        // The reservation of the other verification cannot be used
+        assert_eq!(
+            pool.release_reservation(149, 100, true),
+            Err(ElusivError::InsufficientFunds)
+        );
+        assert_eq!(pool.get_pending_lamport_reservation(), 150);
+
+        pool.release_reservation(150, 100, true).unwrap();
+        assert_eq!(pool.get_pending_lamport_reservation(), 50);
+
+        // Failed verifications release their reservation without a payout
+        pool.release_reservation(0, 50, false).unwrap();
+        assert_eq!(pool.get_pending_lamport_reservation(), 0);
+
+        // Unreserved verifications
+        pool.release_reservation(10, 10, true).unwrap();
+        assert_eq!(pool.get_pending_lamport_reservation(), 0);
+    }
+
+    #[test]
+    fn test_scheduled_fee_update() {
+        zero_program_account!(mut governor, GovernorAccount);
+        let fee0 = ProgramFee::new(5000, 11, 100, 33, 44, 300, 555).unwrap();
+        let fee1 = ProgramFee::new(6000, 11, 100, 33, 44, 300, 555).unwrap();
+        let fee2 = ProgramFee::new(7000, 11, 100, 33, 44, 300, 555).unwrap();
+        governor.set_program_fee(&fee0);
+        governor.set_fee_version_grace_slots(&10);
+
+        // Activation slot in the past
+        assert_eq!(
+            governor.schedule_fee_update(fee1.clone(), 100, 100),
+            Err(ElusivError::InvalidInstructionData)
+        );
+
+        governor.schedule_fee_update(fee1.clone(), 100, 50).unwrap();
+        assert_eq!(governor.latest_fee_version(), 1);
+
+        // Before the activation slot
+        assert_eq!(governor.accepted_program_fee(0, 99), Ok(fee0.clone()));
+        assert_eq!(
+            governor.accepted_program_fee(1, 99),
+            Err(ElusivError::InvalidFeeVersion)
+        );
+        assert_eq!(governor.fee_version_at(99), (0, fee0.clone()));
+
+        // From the activation slot on (the previous version only during the grace window)
+        assert_eq!(governor.accepted_program_fee(1, 100), Ok(fee1.clone()));
+        assert_eq!(governor.accepted_program_fee(0, 109), Ok(fee0.clone()));
+        assert_eq!(
+            governor.accepted_program_fee(0, 110),
+            Err(ElusivError::InvalidFeeVersion)
+        );
+        assert_eq!(
+            governor.accepted_program_fee(2, 110),
+            Err(ElusivError::InvalidFeeVersion)
+        );
+        assert_eq!(governor.fee_version_at(100), (1, fee1.clone()));
+
+        // Overlapping schedules
+        for slot in [99, 100, 109] {
+            assert_eq!(
+                governor.schedule_fee_update(fee2.clone(), 200, slot),
+                Err(ElusivError::FeeUpdateAlreadyScheduled)
+            );
+        }
+
+        // Cancellation is only possible before the activation
+        assert_eq!(
+            governor.cancel_scheduled_fee_update(100),
+            Err(ElusivError::InvalidAccountState)
+        );
+
+        // A new schedule applies the previous update
+        governor
+            .schedule_fee_update(fee2.clone(), 200, 110)
+            .unwrap();
+        assert_eq!(governor.get_fee_version(), 1);
+        assert_eq!(governor.get_program_fee(), fee1);
+        assert_eq!(governor.latest_fee_version(), 2);
+
+        governor.cancel_scheduled_fee_update(199).unwrap();
+        assert_eq!(governor.get_scheduled_fee_update(), ElusivOption::None);
+        assert_eq!(governor.latest_fee_version(), 1);
+        assert_eq!(governor.accepted_program_fee(1, 200), Ok(fee1.clone()));
+        assert_eq!(
+            governor.accepted_program_fee(2, 200),
+            Err(ElusivError::InvalidFeeVersion)
+        );
+        assert_eq!(
+            governor.cancel_scheduled_fee_update(199),
+            Err(ElusivError::InvalidAccountState)
+        );
+    }
+}
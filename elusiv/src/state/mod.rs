@@ -1,10 +1,215 @@
 pub mod commitment;
+pub mod commitment_index;
 pub mod fee;
 pub mod governor;
 pub mod metadata;
+pub mod note;
 pub mod nullifier;
 pub mod program_account;
 pub mod proof;
 pub mod queue;
 pub mod storage;
 pub mod vkey;
+pub mod warden;
+
+/// Version of the data layouts of all `elusiv_account`s (see the generated `*_offsets` modules)
+/// - incremented with every change of any field offset, clients should assert it before slicing raw account data
+const LAYOUT_VERSION: u32 = 3;
+
+pub const fn layout_version() -> u32 {
+    LAYOUT_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::commitment::*;
+    use super::commitment_index::CommitmentIndexAccount;
+    use super::fee::{FeeAccount, FeeVersionUsageAccount};
+    use super::governor::*;
+    use super::metadata::*;
+    use super::note::NoteLogAccount;
+    use super::nullifier::*;
+    use super::proof::*;
+    use super::storage::StorageAccount;
+    use super::vkey::VKeyAccount;
+    use super::warden::WardenRewardsAccount;
+
+    // Compile test for the `zeroed` method of all `elusiv_account`s
+    macro_rules! assert_zeroed {
+        ($($ty: ty),* $(,)?) => {
+            $(
+                let account = <$ty>::zeroed();
+                assert_eq!(account.get_pda_version(), 0);
+            )*
+        };
+    }
+
+    #[test]
+    fn test_zeroed() {
+        use super::program_account::VersionedAccount;
+
+        assert_zeroed!(
+            BaseCommitmentBufferAccount,
+            BaseCommitmentHashingAccount,
+            CommitmentBufferAccount,
+            CommitmentHashingAccount,
+            CommitmentIndexAccount,
+            CommitmentQueueAccount,
+            FeeAccount,
+            FeeCollectorAccount,
+            FeeVersionUsageAccount,
+            GovernorAccount,
+            IdentifierLockAccount,
+            MetadataAccount,
+            MetadataQueueAccount,
+            NoteLogAccount,
+            NullifierAccount,
+            ArchivedNullifierAccount,
+            NullifierDuplicateAccount,
+            PoolAccount,
+            StorageAccount,
+            VKeyAccount,
+            VerificationAccount,
+            WardenRewardsAccount,
+        );
+    }
+
+    macro_rules! layout_fingerprint {
+        ($($offsets: path),* $(,)?) => {{
+            let mut accounts = Vec::new();
+            $(
+                let fields: Vec<String> = $offsets
+                    .iter()
+                    .map(|(name, range)| format!("{}:{}..{}", name, range.start, range.end))
+                    .collect();
+                accounts.push(fields.join(","));
+            )*
+            solana_program::hash::hash(accounts.join("|").as_bytes())
+        }};
+    }
+
+    #[test]
+    fn test_layout_version() {
+        // If this fails, a field offset has changed: increment `LAYOUT_VERSION` and update the fingerprint
+        let fingerprint = layout_fingerprint!(
+            base_commitment_buffer_account_offsets::FIELDS,
+            base_commitment_hashing_account_offsets::FIELDS,
+            commitment_buffer_account_offsets::FIELDS,
+            commitment_hashing_account_offsets::FIELDS,
+            super::commitment_index::commitment_index_account_offsets::FIELDS,
+            commitment_queue_account_offsets::FIELDS,
+            super::fee::fee_account_offsets::FIELDS,
+            fee_collector_account_offsets::FIELDS,
+            super::fee::fee_version_usage_account_offsets::FIELDS,
+            governor_account_offsets::FIELDS,
+            identifier_lock_account_offsets::FIELDS,
+            metadata_account_offsets::FIELDS,
+            metadata_queue_account_offsets::FIELDS,
+            super::note::note_log_account_offsets::FIELDS,
+            nullifier_account_offsets::FIELDS,
+            archived_nullifier_account_offsets::FIELDS,
+            nullifier_duplicate_account_offsets::FIELDS,
+            pool_account_offsets::FIELDS,
+            super::storage::storage_account_offsets::FIELDS,
+            super::vkey::v_key_account_offsets::FIELDS,
+            verification_account_offsets::FIELDS,
+            super::warden::warden_rewards_account_offsets::FIELDS,
+        );
+
+        assert_eq!(super::layout_version(), 3);
+        assert_eq!(
+            fingerprint.to_string(),
+            "4jJdYK3enJGiSiFXiaHybirZRffg662DxV2vL22bY868"
+        );
+    }
+
+    #[test]
+    fn test_offsets() {
+        use super::program_account::{ParentAccount, ProgramAccount, SizedAccount};
+        use super::storage::storage_account_offsets;
+        use crate::types::U256;
+        use borsh::BorshDeserialize;
+        use elusiv_types::ElusivOption;
+        use solana_program::pubkey::Pubkey;
+
+        // Reading the raw data through the offsets matches the getters
+        let mut data = vec![0; VerificationAccount::SIZE];
+        let (creation_slot, tree_index, state, fee_payer) = {
+            let mut account = VerificationAccount::new(&mut data).unwrap();
+            account.set_creation_slot(&123);
+            account.set_tree_indices(1, &456);
+            account.set_state(&VerificationState::InsertNullifiers);
+            account.set_other_data(&VerificationAccountData {
+                fee_payer: crate::types::RawU256::new([7; 32]),
+                ..Default::default()
+            });
+
+            (
+                account.get_creation_slot(),
+                account.get_tree_indices(1),
+                account.get_state().unwrap(),
+                account.get_other_data().fee_payer,
+            )
+        };
+        assert_eq!(
+            u64::try_from_slice(&data[verification_account_offsets::CREATION_SLOT]).unwrap(),
+            creation_slot
+        );
+        assert_eq!(
+            u32::try_from_slice(&data[verification_account_offsets::tree_indices(1)]).unwrap(),
+            tree_index
+        );
+        assert_eq!(
+            VerificationState::try_from_slice(&data[verification_account_offsets::STATE]).unwrap(),
+            state
+        );
+        assert_eq!(
+            VerificationAccountData::try_from_slice(
+                &data[verification_account_offsets::OTHER_DATA]
+            )
+            .unwrap()
+            .fee_payer,
+            fee_payer
+        );
+
+        let mut data = vec![0; StorageAccount::SIZE];
+        let (child_pubkey, root, trees_count) = {
+            let mut account = StorageAccount::new(&mut data).unwrap();
+            account.set_child_pubkey(2, ElusivOption::Some(Pubkey::new_from_array([1; 32])));
+            account.set_active_mt_root_history(3, &[2; 32]);
+            account.set_trees_count(&4);
+
+            (
+                account.get_child_pubkey(2),
+                account.get_active_mt_root_history(3),
+                account.get_trees_count(),
+            )
+        };
+        assert_eq!(
+            ElusivOption::<Pubkey>::try_from_slice(&data[storage_account_offsets::pubkeys(2)])
+                .unwrap()
+                .option(),
+            child_pubkey
+        );
+        assert_eq!(
+            U256::try_from_slice(&data[storage_account_offsets::active_mt_root_history(3)])
+                .unwrap(),
+            root
+        );
+        assert_eq!(
+            u32::try_from_slice(&data[storage_account_offsets::TREES_COUNT]).unwrap(),
+            trees_count
+        );
+
+        let mut data = vec![0; GovernorAccount::SIZE];
+        let fee_version = {
+            let mut account = GovernorAccount::new(&mut data).unwrap();
+            account.set_fee_version(&5);
+            account.get_fee_version()
+        };
+        assert_eq!(
+            u32::try_from_slice(&data[governor_account_offsets::FEE_VERSION]).unwrap(),
+            fee_version
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use super::program_account::PDAAccountData;
+use super::queue::{queue_account, RingQueue};
+use crate::types::U256;
+use borsh::{BorshDeserialize, BorshSerialize};
+use elusiv_derive::BorshSerDeSized;
+use solana_program::entrypoint::ProgramResult;
+
+/// Size of an [`EncryptedNote`]
+pub const ENCRYPTED_NOTE_SIZE: usize = 96;
+
+/// A note (amount, blinding, ...) encrypted under the recipient's key
+/// - opaque to the program
+pub type EncryptedNote = [u8; ENCRYPTED_NOTE_SIZE];
+
+/// Number of slots of the [`NoteLogAccount`] ring (one slot is always unused)
+pub const NOTE_LOG_LEN: usize = 64;
+
+/// An [`EncryptedNote`] next to the commitment it belongs to
+#[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Eq, Clone, Copy, Debug)]
+pub struct NoteLogEntry {
+    pub commitment: U256,
+    pub encrypted_note: EncryptedNote,
+}
+
+impl Default for NoteLogEntry {
+    fn default() -> Self {
+        NoteLogEntry {
+            commitment: [0; 32],
+            encrypted_note: [0; ENCRYPTED_NOTE_SIZE],
+        }
+    }
+}
+
+// Ring of the most recent encrypted notes, which wallets can scan and trial-decrypt
+queue_account!(NoteLog, NoteLogAccount, NOTE_LOG_LEN, NoteLogEntry);
+
+impl<'a, 'b> NoteLog<'a, 'b> {
+    /// Appends an entry, evicting the oldest entry if the log is full
+    pub fn append(&mut self, entry: NoteLogEntry) -> ProgramResult {
+        if self.empty_slots() == 0 {
+            self.dequeue_first()?;
+        }
+
+        self.enqueue(entry)
+    }
+
+    /// All entries, from the oldest to the most recent one
+    #[cfg(feature = "elusiv-client")]
+    pub fn entries(&self) -> Vec<NoteLogEntry> {
+        (0..self.len() as usize)
+            .map(|offset| self.view(offset).unwrap())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::zero_program_account;
+    use crate::state::queue::Queue;
+
+    fn entry(i: u8) -> NoteLogEntry {
+        NoteLogEntry {
+            commitment: [i; 32],
+            encrypted_note: [i; ENCRYPTED_NOTE_SIZE],
+        }
+    }
+
+    #[test]
+    fn test_append() {
+        zero_program_account!(mut note_log, NoteLogAccount);
+        let mut note_log = NoteLog::new(&mut note_log);
+
+        for i in 0..NoteLog::CAPACITY as u8 {
+            note_log.append(entry(i)).unwrap();
+        }
+        assert_eq!(
+            note_log.entries(),
+            (0..NoteLog::CAPACITY as u8).map(entry).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_append_evicts_oldest_entry() {
+        zero_program_account!(mut note_log, NoteLogAccount);
+        let mut note_log = NoteLog::new(&mut note_log);
+        let capacity = NoteLog::CAPACITY as u8;
+
+        for i in 0..capacity + 3 {
+            note_log.append(entry(i)).unwrap();
+        }
+
+        assert_eq!(note_log.len(), NoteLog::CAPACITY);
+        assert_eq!(note_log.view_first().unwrap(), entry(3));
+        assert_eq!(
+            note_log.entries(),
+            (3..capacity + 3).map(entry).collect::<Vec<_>>()
+        );
+    }
+}
@@ -11,7 +11,7 @@ use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 
 /// The count of nullifiers is the count of leaves in the MT
-const NULLIFIERS_COUNT: usize = two_pow!(MT_HEIGHT);
+pub const NULLIFIERS_COUNT: usize = two_pow!(MT_HEIGHT);
 
 /// We store nullifiers with the `NullifierMap` data structure for efficient searching and later N-SMT construction
 pub type NullifierMap<'a> = ElusivSet<'a, OrdU256, NULLIFIERS_PER_ACCOUNT>;
@@ -22,6 +22,9 @@ const ACCOUNTS_COUNT: usize = div_ceiling_usize(NULLIFIERS_COUNT, NULLIFIERS_PER
 #[cfg(test)]
 const_assert_eq!(ACCOUNTS_COUNT, 16);
 
+/// Count of nullifiers from which on each insertion that reaches a new percentage of [`NULLIFIERS_COUNT`] is logged
+pub const CAPACITY_WARN_THRESHOLD: usize = div_ceiling_usize(NULLIFIERS_COUNT * 90, 100);
+
 pub struct NullifierChildAccount;
 
 impl ChildAccount for NullifierChildAccount {
@@ -142,6 +145,11 @@ impl<'a, 'b, 'c> NullifierAccount<'a, 'b, 'c> {
             self.set_all_moved_values(&moved_values);
         }
 
+        // Wardens monitor this log to open a new tree before the active one is exhausted
+        if let Some(percentage) = near_capacity_percentage(count as usize + 1) {
+            solana_program::msg!("NullifierAccount near capacity: {}%", percentage);
+        }
+
         Ok(())
     }
 
@@ -218,6 +226,12 @@ impl<'a, 'b, 'c> NullifierAccount<'a, 'b, 'c> {
         self.get_moved_values_count() == 0
     }
 
+    /// All nullifiers of the MT have been inserted, so no further spends are possible
+    pub fn is_fully_spent(&self) -> bool {
+        self.get_nullifier_hash_count() as usize >= NULLIFIERS_COUNT
+            && self.is_moved_nullifier_empty()
+    }
+
     /// Sorts the provided values from large to small
     fn sort_all_moved_values(moved_values: &mut [(OrdU256, u8)]) {
         moved_values.sort_by(|(a, _), (b, _)| b.cmp(a));
@@ -258,6 +272,22 @@ impl<'a, 'b, 'c> NullifierAccount<'a, 'b, 'c> {
     }
 }
 
+/// Returns the filled percentage, if `nullifier_hash_count` crosses [`CAPACITY_WARN_THRESHOLD`] or reaches a new percentage above it
+fn near_capacity_percentage(nullifier_hash_count: usize) -> Option<usize> {
+    if nullifier_hash_count < CAPACITY_WARN_THRESHOLD {
+        return None;
+    }
+
+    let percentage = nullifier_hash_count * 100 / NULLIFIERS_COUNT;
+    if nullifier_hash_count == CAPACITY_WARN_THRESHOLD
+        || percentage != (nullifier_hash_count - 1) * 100 / NULLIFIERS_COUNT
+    {
+        Some(percentage)
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -586,4 +616,26 @@ mod tests {
             3
         );
     }
+
+    #[test]
+    fn test_near_capacity_percentage() {
+        let percentage_count = |p: usize| div_ceiling_usize(NULLIFIERS_COUNT * p, 100);
+
+        // 89%
+        assert_eq!(near_capacity_percentage(percentage_count(89)), None);
+        assert_eq!(near_capacity_percentage(CAPACITY_WARN_THRESHOLD - 1), None);
+
+        // 90%
+        assert_eq!(near_capacity_percentage(CAPACITY_WARN_THRESHOLD), Some(90));
+        assert_eq!(near_capacity_percentage(CAPACITY_WARN_THRESHOLD + 1), None);
+
+        // 91% - 99%
+        for p in 91..100 {
+            assert_eq!(near_capacity_percentage(percentage_count(p)), Some(p));
+            assert_eq!(near_capacity_percentage(percentage_count(p) + 1), None);
+        }
+
+        // 100%
+        assert_eq!(near_capacity_percentage(NULLIFIERS_COUNT), Some(100));
+    }
 }
@@ -3,10 +3,13 @@ pub use elusiv_types::accounts::*;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::macros::{account_info, parent_account};
+    use crate::macros::{account_info, elusiv_account, parent_account};
     use borsh::BorshDeserialize;
     use elusiv_types::{split_child_account_data, BorshSerDeSized, ElusivOption};
-    use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+    use solana_program::{
+        account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+        pubkey::Pubkey,
+    };
 
     struct TestPDAAccount;
 
@@ -31,6 +34,112 @@ mod tests {
         //assert_eq!(TestPDAAccount::find(None).0, Pubkey::find_program_address(&[TestPDAAccount::SEED], &crate::PROGRAM_ID).0);
     }
 
+    #[test]
+    fn test_verify_closed_account() {
+        let pubkey = Pubkey::new_unique();
+        let (pda, bump) = TestPDAAccount::find_with_pubkey(pubkey, Some(0));
+        account_info!(account, pda, vec![bump]);
+        assert_eq!(TestPDAAccount::get_bump(&account), Ok(bump));
+        assert_eq!(
+            TestPDAAccount::verify_account_with_pubkey(&account, pubkey, Some(0)),
+            Ok(())
+        );
+
+        // Drained account without data
+        account_info!(closed_account, pda, vec![]);
+        assert_eq!(
+            TestPDAAccount::get_bump(&closed_account),
+            Err(ProgramError::UninitializedAccount)
+        );
+        assert_eq!(
+            TestPDAAccount::verify_account_with_pubkey(&closed_account, pubkey, Some(0)),
+            Err(ProgramError::UninitializedAccount)
+        );
+        assert_eq!(
+            TestPDAAccount::verify_account(&closed_account, Some(0)),
+            Err(ProgramError::UninitializedAccount)
+        );
+    }
+
+    impl MultiInstancePDAAccount for TestPDAAccount {
+        const MAX_INSTANCES: u64 = 3;
+    }
+
+    #[test]
+    fn test_iter_valid_instances() {
+        let pubkey = Pubkey::new_unique();
+        let instances: Vec<(u64, Pubkey)> =
+            iter_valid_instances::<TestPDAAccount>(pubkey).collect();
+
+        assert_eq!(
+            instances,
+            (0..3)
+                .map(|i| (
+                    i,
+                    TestPDAAccount::find_with_pubkey(pubkey, Some(i as u32)).0
+                ))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_get_open_instance_count() {
+        let pubkey = Pubkey::new_unique();
+        let (pk0, bump0) = TestPDAAccount::find_with_pubkey(pubkey, Some(0));
+        let (pk1, bump1) = TestPDAAccount::find_with_pubkey(pubkey, Some(1));
+        let (pk2, bump2) = TestPDAAccount::find_with_pubkey(pubkey, Some(2));
+        account_info!(acc0, pk0, vec![bump0]);
+        account_info!(acc1, pk1, vec![bump1]);
+        account_info!(acc2, pk2, vec![bump2]);
+
+        let accounts = [acc0.clone(), acc1.clone(), acc2.clone()];
+        assert_eq!(
+            get_open_instance_count::<TestPDAAccount>(pubkey, &accounts),
+            Ok(3)
+        );
+
+        // Closing instances
+        **acc1.lamports.borrow_mut() = 0;
+        assert_eq!(
+            get_open_instance_count::<TestPDAAccount>(pubkey, &accounts),
+            Ok(2)
+        );
+
+        **acc0.lamports.borrow_mut() = 0;
+        **acc2.lamports.borrow_mut() = 0;
+        assert_eq!(
+            get_open_instance_count::<TestPDAAccount>(pubkey, &accounts),
+            Ok(0)
+        );
+
+        // Reopening an instance
+        **acc2.lamports.borrow_mut() = 1;
+        assert_eq!(
+            get_open_instance_count::<TestPDAAccount>(pubkey, &accounts),
+            Ok(1)
+        );
+
+        // Subset of the instances
+        assert_eq!(
+            get_open_instance_count::<TestPDAAccount>(pubkey, &accounts[..2]),
+            Ok(0)
+        );
+
+        // Invalid order
+        let accounts = [acc2.clone(), acc0.clone()];
+        assert_eq!(
+            get_open_instance_count::<TestPDAAccount>(pubkey, &accounts),
+            Err(ProgramError::InvalidSeeds)
+        );
+
+        // Too many accounts
+        let accounts = [acc0.clone(), acc1.clone(), acc2.clone(), acc0.clone()];
+        assert_eq!(
+            get_open_instance_count::<TestPDAAccount>(pubkey, &accounts),
+            Err(ProgramError::InvalidArgument)
+        );
+    }
+
     struct TestChildAccount;
 
     impl ChildAccount for TestChildAccount {
@@ -160,8 +269,8 @@ mod tests {
     ) {
         parent_account!(mut parent, TestParentAccount);
         for (i, &is_setup) in pubkey_is_setup.iter().enumerate() {
-            if is_setup {
-                parent.set_child_pubkey(i, ElusivOption::Some(*parent.accounts[i].unwrap().key));
+            if !is_setup {
+                parent.set_child_pubkey(i, ElusivOption::None);
             }
         }
 
@@ -265,4 +374,80 @@ mod tests {
 
         assert!(unverified_account_info.get_safe().is_ok());
     }
+
+    fn migrate_test_versioned_account(
+        account: &mut TestVersionedAccount,
+        from_version: u8,
+    ) -> ProgramResult {
+        let value = account.get_value();
+        account.set_value(&(value * 10 + from_version as u64 + 1));
+        Ok(())
+    }
+
+    #[elusiv_account(version: 2, migration: migrate_test_versioned_account)]
+    struct TestVersionedAccount {
+        #[no_getter]
+        #[no_setter]
+        pda_data: PDAAccountData,
+
+        value: u64,
+    }
+
+    fn versioned_account_data(version: u8, value: u64) -> Vec<u8> {
+        let mut data = vec![0; TestVersionedAccount::SIZE];
+        data[pda_account_data_offsets::VERSION.start] = version;
+        data[test_versioned_account_offsets::VALUE].copy_from_slice(&value.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_current_version() {
+        assert_eq!(TestVersionedAccount::CURRENT_VERSION, 2);
+        assert_eq!(TestPDAAccount::CURRENT_VERSION, 0);
+        assert_eq!(crate::state::governor::PoolAccount::CURRENT_VERSION, 0);
+    }
+
+    #[test]
+    fn test_migrate_if_needed() {
+        let mut data = versioned_account_data(0, 1);
+        let mut account = TestVersionedAccount::new(&mut data).unwrap();
+        assert_eq!(
+            account.verify_version(),
+            Err(AccountVersionError::Outdated.into())
+        );
+
+        // Migrations are applied in order
+        account.migrate_if_needed().unwrap();
+        assert_eq!(account.get_pda_version(), 2);
+        assert_eq!(account.get_value(), (10 + 1) * 10 + 2);
+        assert_eq!(account.verify_version(), Ok(()));
+
+        // Current version
+        account.migrate_if_needed().unwrap();
+        assert_eq!(account.get_value(), 112);
+
+        let mut data = versioned_account_data(1, 1);
+        let mut account = TestVersionedAccount::new(&mut data).unwrap();
+        account.migrate_if_needed().unwrap();
+        assert_eq!(account.get_pda_version(), 2);
+        assert_eq!(account.get_value(), 12);
+        assert_eq!(PDAAccountData::new(&data).unwrap().bump_seed, 0);
+    }
+
+    #[test]
+    fn test_migrate_if_needed_version_too_new() {
+        let mut data = versioned_account_data(3, 1);
+        let mut account = TestVersionedAccount::new(&mut data).unwrap();
+
+        assert_eq!(
+            account.migrate_if_needed(),
+            Err(AccountVersionError::TooNew.into())
+        );
+        assert_eq!(
+            account.verify_version(),
+            Err(AccountVersionError::TooNew.into())
+        );
+        assert_eq!(account.get_pda_version(), 3);
+        assert_eq!(account.get_value(), 1);
+    }
 }
@@ -1,12 +1,16 @@
 use crate::bytes::{
     usize_as_u32_safe, BorshSerDeSized, BorshSerDeSizedEnum, ElusivOption, SizedType,
 };
+use crate::error::ElusivError;
 use crate::fields::{G2HomProjective, Wrap, G1A, G2A};
-use crate::processor::{ProofRequest, MAX_MT_COUNT};
-use crate::proof::verifier::VerificationStep;
-use crate::state::program_account::PDAAccountData;
+use crate::macros::{guard, hardened_guard};
+use crate::processor::{ProofRequest, MAX_MT_COUNT, RESERVED_VERIFICATION_ACCOUNT_IDS};
+use crate::proof::verifier::{prepare_public_inputs_rounds, VerificationStep};
+use crate::proof::vkey::Signal;
+use crate::state::note::EncryptedNote;
+use crate::state::program_account::{MultiInstancePDAAccount, PDAAccountData};
 use crate::token::Lamports;
-use crate::types::{Lazy, LazyField, RawU256, U256};
+use crate::types::{Lazy, LazyField, PublicInputs, RawU256, U256};
 use ark_bn254::{Fq, Fq12, Fq2, Fq6};
 use borsh::{BorshDeserialize, BorshSerialize};
 use elusiv_computation::RAM;
@@ -15,7 +19,10 @@ use elusiv_proc_macros::elusiv_account;
 use solana_program::entrypoint::ProgramResult;
 use solana_program::pubkey::Pubkey;
 
-pub type RAMFq<'a> = LazyRAM<'a, Fq, 6>;
+/// Number of [`Fq`] elements stored in a [`RAMFq`]
+pub const RAM_FQ_SIZE: usize = 6;
+
+pub type RAMFq<'a> = LazyRAM<'a, Fq, RAM_FQ_SIZE>;
 pub type RAMFq2<'a> = LazyRAM<'a, Fq2, 10>;
 pub type RAMFq6<'a> = LazyRAM<'a, Fq6, 3>;
 pub type RAMFq12<'a> = LazyRAM<'a, Fq12, 7>;
@@ -24,10 +31,14 @@ pub type RAMG2A<'a> = LazyRAM<'a, G2A, 1>;
 const MAX_PUBLIC_INPUTS_COUNT: usize = 14;
 const MAX_PREPARE_INPUTS_INSTRUCTIONS: usize = MAX_PUBLIC_INPUTS_COUNT * 10;
 
+/// Opaque caller-supplied reference (all zeros if unset)
+pub type ExternalReference = [u8; 16];
+
 /// Describes the state of the proof-verification initialization and finalization
 #[derive(
     BorshDeserialize, BorshSerialize, BorshSerDeSized, EnumVariantIndex, Debug, Clone, PartialEq, Eq,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum VerificationState {
     // Init
     None,
@@ -40,6 +51,42 @@ pub enum VerificationState {
     Closed,
 }
 
+impl VerificationState {
+    /// The transitions of the verification state-machine
+    pub fn can_transition_to(&self, next: &Self) -> bool {
+        use VerificationState::*;
+
+        matches!(
+            (self, next),
+            (None, FeeTransferred)
+                | (FeeTransferred, ProofSetup)
+                | (ProofSetup, ProofSetup)
+                | (ProofSetup, InsertNullifiers)
+                | (ProofSetup, Finalized)
+                | (InsertNullifiers, Finalized)
+                | (Finalized, Closed)
+        )
+    }
+}
+
+/// Attribution of a failed verification
+/// - derived only from on-chain data at the failure site
+#[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub enum VerificationFault {
+    /// The proof or its public inputs are invalid
+    UserFault,
+
+    /// The warden did not complete the verification within `verification_ttl_slots`
+    WardenFault,
+
+    /// The failure cannot be attributed (e.g. a state unknown to this program version)
+    Indeterminate,
+
+    /// The identifier cancelled a delayed send within its window
+    Cancelled,
+}
+
 /// Account used for verifying proofs over the span of multiple transactions
 ///
 /// # Note
@@ -59,6 +106,7 @@ pub struct VerificationAccount {
 
     pub vkey_id: u32,
     pub step: VerificationStep,
+    #[no_getter]
     pub state: VerificationState,
 
     // Public inputs
@@ -100,6 +148,38 @@ pub struct VerificationAccount {
     #[no_getter]
     pub request: ProofRequest,
     pub tree_indices: [u32; MAX_MT_COUNT],
+
+    /// Correlation id of the warden, emitted with every event of this verification
+    /// - not part of any public input
+    pub external_reference: ExternalReference,
+
+    /// The slot of [`crate::processor::init_verification`]
+    pub creation_slot: u64,
+
+    /// The identifier locked by an [`IdentifierLockAccount`] until the finalization (zero: no lock)
+    pub locked_identifier: U256,
+
+    /// The [`VerificationFault`] of a failed verification
+    pub fault: ElusivOption<VerificationFault>,
+
+    /// Charges of [`crate::processor::compute_verification`] calls that did not advance the computation
+    /// - refunded to the signer of the next call that advances the computation
+    pub pending_call_charges: Lamports,
+
+    /// Logged next to the commitment in the [`crate::state::note::NoteLogAccount`] by [`crate::processor::finalize_verification_send`]
+    pub encrypted_note: ElusivOption<EncryptedNote>,
+
+    /// Unix timestamp before which a valid proof cannot be finalized (zero: no delay)
+    /// - bound into the `hashed_inputs`
+    pub earliest_finalize_time: u64,
+
+    /// The identifier of a delayed send, which can cancel it until `earliest_finalize_time` (zero: no delay)
+    pub delay_identifier: U256,
+}
+
+/// Instances are indexed by the `verification_account_index` (`0..=RESERVED_VERIFICATION_ACCOUNT_IDS`)
+impl<'a> MultiInstancePDAAccount for VerificationAccount<'a> {
+    const MAX_INSTANCES: u64 = RESERVED_VERIFICATION_ACCOUNT_IDS as u64 + 1;
 }
 
 #[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone, Default)]
@@ -107,6 +187,9 @@ pub struct VerificationAccount {
 pub struct VerificationAccountData {
     pub fee_payer: RawU256,
     pub fee_payer_account: RawU256,
+
+    /// Receives the reclaimed rent after a successful verification (`fee_payer` if `None`)
+    pub rent_beneficiary: ElusivOption<RawU256>,
     pub recipient_wallet: ElusivOption<RawU256>,
 
     /// Flag that can be used to skip the renting of a nullifier_pda (if it already exists)
@@ -133,6 +216,57 @@ pub struct VerificationAccountData {
 
     /// The expected associated-token-account-rent in `token_id`-Token
     pub associated_token_account_rent: u64,
+
+    /// The USD value (with [`crate::token::USD_DECIMALS`] decimals) of the join-split amount at the time of the fee-transfer
+    /// - for Lamports only available if the SOL price account is supplied
+    pub amount_usd: ElusivOption<u64>,
+
+    /// Additional rewards for the `fee_payer` (paid on success, refunded to the boosters otherwise)
+    pub boost_count: u8,
+    pub boosts: [VerificationBoost; MAX_VERIFICATION_BOOSTS],
+}
+
+impl VerificationAccountData {
+    /// The account receiving the reclaimed rent after a successful verification
+    pub fn rent_beneficiary(&self) -> RawU256 {
+        self.rent_beneficiary.option().unwrap_or(self.fee_payer)
+    }
+
+    /// Records a boost of `amount` by `booster`
+    pub fn add_boost(&mut self, booster: RawU256, amount: Lamports) -> Result<(), ElusivError> {
+        let index = self.boost_count as usize;
+        guard!(index < MAX_VERIFICATION_BOOSTS, ElusivError::TooManyBoosts);
+
+        self.boosts[index] = VerificationBoost { booster, amount };
+        self.boost_count += 1;
+
+        Ok(())
+    }
+
+    /// All recorded boosts
+    pub fn active_boosts(&self) -> &[VerificationBoost] {
+        &self.boosts[..(self.boost_count as usize).min(MAX_VERIFICATION_BOOSTS)]
+    }
+
+    /// Sum of all recorded boosts
+    pub fn total_boost(&self) -> Result<Lamports, ElusivError> {
+        self.active_boosts()
+            .iter()
+            .try_fold(0u64, |acc, boost| acc.checked_add(boost.amount.0))
+            .map(Lamports)
+            .ok_or(ElusivError::InvalidAmount)
+    }
+}
+
+/// Maximum number of boosts per verification
+pub const MAX_VERIFICATION_BOOSTS: usize = 2;
+
+/// Lamports added by a `booster` to the reward of an in-progress verification
+#[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "elusiv-client", derive(Debug))]
+pub struct VerificationBoost {
+    pub booster: RawU256,
+    pub amount: Lamports,
 }
 
 impl<'a> VerificationAccount<'a> {
@@ -140,15 +274,18 @@ impl<'a> VerificationAccount<'a> {
     pub fn setup(
         &mut self,
         signer: RawU256,
+        rent_beneficiary: ElusivOption<RawU256>,
         skip_nullifier_pda: bool,
         public_inputs: &[RawU256],
         instructions: &Vec<u32>,
         vkey_id: u32,
         request: ProofRequest,
         tree_indices: [u32; MAX_MT_COUNT],
+        external_reference: ExternalReference,
     ) -> ProgramResult {
         self.set_vkey_id(&vkey_id);
         self.set_request(&request);
+        self.set_external_reference(&external_reference);
         for (i, tree_index) in tree_indices.iter().enumerate() {
             self.set_tree_indices(i, tree_index);
         }
@@ -164,6 +301,7 @@ impl<'a> VerificationAccount<'a> {
         // Remembers the authorized signer
         self.set_other_data(&VerificationAccountData {
             fee_payer: signer,
+            rent_beneficiary,
             skip_nullifier_pda,
             ..Default::default()
         });
@@ -210,9 +348,365 @@ impl<'a> VerificationAccount<'a> {
         m
     }
 
-    pub fn get_request(&self) -> ProofRequest {
-        ProofRequest::deserialize_enum_full(&mut &self.request[..]).unwrap()
+    /// The [`VerificationState`]
+    /// - fails for states unknown to this program version (e.g. after a downgrade)
+    pub fn get_state(&self) -> Result<VerificationState, ElusivError> {
+        VerificationState::try_from_slice(self.state)
+            .map_err(|_| ElusivError::UnsupportedAccountState)
+    }
+
+    /// Sets the [`VerificationState`]
+    /// - `hardened` builds reject transitions not part of [`VerificationState::can_transition_to`]
+    pub fn transition_state(&mut self, next: &VerificationState) -> Result<(), ElusivError> {
+        hardened_guard!(
+            self.get_state()?.can_transition_to(next),
+            ElusivError::InvalidAccountState
+        );
+        self.set_state(next);
+
+        Ok(())
+    }
+
+    /// Fails the verification and records the responsible party
+    pub fn fail(&mut self, fault: VerificationFault) {
+        self.set_is_verified(&ElusivOption::Some(false));
+        self.set_fault(&ElusivOption::Some(fault));
+    }
+
+    /// The [`ProofRequest`]
+    /// - fails for requests unknown to this program version (e.g. after a downgrade)
+    pub fn get_request(&self) -> Result<ProofRequest, ElusivError> {
+        ProofRequest::deserialize_enum_full(&mut &self.request[..])
+            .map_err(|_| ElusivError::UnsupportedAccountState)
+    }
+
+    /// Returns whether the stored public inputs are the public signals of the stored [`ProofRequest`]
+    ///
+    /// # Note
+    ///
+    /// Only valid before the public input preparation has been started.
+    pub fn public_inputs_match_request(&self) -> Result<bool, ElusivError> {
+        let public_inputs = match self.get_request()? {
+            ProofRequest::Send(public_inputs) => public_inputs.public_signals_skip_mr(),
+            ProofRequest::Migrate(public_inputs) => public_inputs.public_signals_skip_mr(),
+        };
+
+        Ok(public_inputs
+            .iter()
+            .enumerate()
+            .all(|(i, public_input)| self.load_raw_public_input(i) == *public_input))
+    }
+
+    /// Returns whether the stored input preparation instructions perform exactly the preparation rounds of the stored [`ProofRequest`]
+    ///
+    /// # Note
+    ///
+    /// Only the sum of the rounds is checked (the split into instructions depends on the public inputs).
+    pub fn prepare_inputs_instructions_match_request(&self) -> Result<bool, ElusivError> {
+        let public_inputs_count = match self.get_request()? {
+            ProofRequest::Send(public_inputs) => public_inputs.public_signals().len(),
+            ProofRequest::Migrate(public_inputs) => public_inputs.public_signals().len(),
+        };
+
+        let instructions_count = self.get_prepare_inputs_instructions_count() as usize;
+        if instructions_count > MAX_PREPARE_INPUTS_INSTRUCTIONS {
+            return Ok(false);
+        }
+
+        let total_rounds: usize = (0..instructions_count)
+            .map(|i| self.get_prepare_inputs_instructions(i) as usize)
+            .sum();
+
+        Ok(total_rounds == prepare_public_inputs_rounds(public_inputs_count))
+    }
+
+    /// The stored `k`-th public input of `signal` (without montgomery reduction)
+    /// - fails if `signal` is not part of the [`crate::proof::vkey::SignalLayout`] of the stored [`ProofRequest`]
+    pub fn get_signal(&self, signal: Signal, k: usize) -> Result<U256, ElusivError> {
+        let index = self
+            .get_request()?
+            .signal_layout()
+            .index(signal, k)
+            .ok_or(ElusivError::InvalidPublicInputs)?;
+
+        Ok(self.load_raw_public_input(index))
+    }
+
+    pub fn get_signal_nullifier_hash(&self, k: usize) -> Result<U256, ElusivError> {
+        self.get_signal(Signal::NullifierHash, k)
+    }
+
+    pub fn get_signal_root(&self, k: usize) -> Result<U256, ElusivError> {
+        self.get_signal(Signal::Root, k)
+    }
+
+    pub fn get_signal_output_commitment(&self) -> Result<U256, ElusivError> {
+        self.get_signal(Signal::OutputCommitment, 0)
+    }
+
+    pub fn get_signal_hashed_inputs(&self) -> Result<U256, ElusivError> {
+        self.get_signal(Signal::HashedInputs, 0)
+    }
+
+    /// The total amount (amount and fee) of the join-split
+    pub fn get_signal_amount(&self) -> Result<u64, ElusivError> {
+        let amount = self.get_signal(Signal::Amount, 0)?;
+        Ok(u64::from_le_bytes(amount[..8].try_into().unwrap()))
+    }
+
+    /// Writes a raw state discriminant (e.g. of a state introduced by a newer program version)
+    #[cfg(test)]
+    pub(crate) fn set_state_discriminant(&mut self, discriminant: u8) {
+        self.state[0] = discriminant;
+    }
+
+    /// Writes a raw request discriminant (e.g. of a request introduced by a newer program version)
+    #[cfg(test)]
+    pub(crate) fn set_request_discriminant(&mut self, discriminant: u8) {
+        self.request[0] = discriminant;
+    }
+
+    /// Collects all fee components (in `token_id`-Token) recorded at the fee-transfer
+    pub fn get_accumulated_fees(&self) -> Result<VerificationFeeAudit, ElusivError> {
+        let data = self.get_other_data();
+
+        Ok(VerificationFeeAudit {
+            fee_paid: self.get_request()?.fee(),
+            subvention_received: data.subvention,
+            commitment_hash_fee: data.commitment_hash_fee_token,
+            proof_verification_fee: data.proof_verification_fee,
+            network_fee: data.network_fee,
+            associated_token_account_rent: data.associated_token_account_rent,
+        })
+    }
+
+    /// Verifies that the fee paid by the sender and the subvention cover exactly all fees
+    /// - the associated-token-account-rent is paid from the amount, not the fee
+    pub fn assert_fee_balance_invariant(&self) -> Result<(), ElusivError> {
+        let fees = self.get_accumulated_fees()?;
+
+        let income = fees
+            .fee_paid
+            .checked_add(fees.subvention_received)
+            .ok_or(ElusivError::InvalidFee)?;
+        let expenses = [
+            fees.commitment_hash_fee,
+            fees.proof_verification_fee,
+            fees.network_fee,
+        ]
+        .iter()
+        .try_fold(0u64, |acc, &fee| acc.checked_add(fee))
+        .ok_or(ElusivError::InvalidFee)?;
+
+        guard!(income == expenses, ElusivError::InvalidFee);
+
+        Ok(())
+    }
+}
+
+/// All fee components of a proof verification (in `token_id`-Token)
+#[derive(PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "elusiv-client", derive(Debug))]
+pub struct VerificationFeeAudit {
+    pub fee_paid: u64,
+    pub subvention_received: u64,
+    pub commitment_hash_fee: u64,
+    pub proof_verification_fee: u64,
+    pub network_fee: u64,
+    pub associated_token_account_rent: u64,
+}
+
+/// Snapshot of all typed fields of a [`VerificationAccount`] (e.g. for analyzing stuck verifications offline)
+///
+/// # Note
+///
+/// Binary values are hex-encoded, the [`PDAAccountData`] is not part of the snapshot.
+#[cfg(feature = "elusiv-client")]
+#[derive(PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct VerificationAccountDump {
+    pub instruction: u32,
+    pub round: u32,
+    pub prepare_inputs_instructions: Vec<u16>,
+    pub vkey_id: u32,
+    pub step: VerificationStep,
+    pub state: VerificationState,
+    pub public_inputs: Vec<String>,
+
+    pub a: String,
+    pub b: String,
+    pub c: String,
+
+    pub prepared_inputs: String,
+    pub r: String,
+    pub f: String,
+    pub alt_b: String,
+    pub coeff_index: u8,
+
+    pub ram_fq: String,
+    pub ram_fq2: String,
+    pub ram_fq6: String,
+    pub ram_fq12: String,
+
+    pub is_verified: Option<bool>,
+    pub other_data: String,
+    pub request: ProofRequest,
+    pub tree_indices: Vec<u32>,
+    pub external_reference: String,
+    pub creation_slot: u64,
+    pub locked_identifier: String,
+    pub fault: Option<VerificationFault>,
+    pub pending_call_charges: u64,
+    pub encrypted_note: Option<String>,
+}
+
+#[cfg(feature = "elusiv-client")]
+impl<'a> VerificationAccount<'a> {
+    /// Creates a [`VerificationAccountDump`] of the account
+    pub fn dump(&mut self) -> Result<VerificationAccountDump, ElusivError> {
+        let instructions_count = self.get_prepare_inputs_instructions_count() as usize;
+
+        Ok(VerificationAccountDump {
+            instruction: self.get_instruction(),
+            round: self.get_round(),
+            prepare_inputs_instructions: (0..instructions_count)
+                .map(|i| self.get_prepare_inputs_instructions(i))
+                .collect(),
+            vkey_id: self.get_vkey_id(),
+            step: self.get_step(),
+            state: self.get_state()?,
+            public_inputs: (0..MAX_PUBLIC_INPUTS_COUNT)
+                .map(|i| encode_hex(&self.load_raw_public_input(i)))
+                .collect(),
+
+            a: encode_hex(&self.a.get().try_to_vec().unwrap()),
+            b: encode_hex(&self.b.get().try_to_vec().unwrap()),
+            c: encode_hex(&self.c.get().try_to_vec().unwrap()),
+
+            prepared_inputs: encode_hex(&self.prepared_inputs.get().try_to_vec().unwrap()),
+            r: encode_hex(&self.r.get().try_to_vec().unwrap()),
+            f: encode_hex(&self.f.get().try_to_vec().unwrap()),
+            alt_b: encode_hex(&self.alt_b.get().try_to_vec().unwrap()),
+            coeff_index: self.get_coeff_index(),
+
+            ram_fq: encode_hex(self.ram_fq.source()),
+            ram_fq2: encode_hex(self.ram_fq2.source()),
+            ram_fq6: encode_hex(self.ram_fq6.source()),
+            ram_fq12: encode_hex(self.ram_fq12.source()),
+
+            is_verified: self.get_is_verified().option(),
+            other_data: encode_hex(&self.get_other_data().try_to_vec().unwrap()),
+            request: self.get_request()?,
+            tree_indices: self.all_tree_indices().to_vec(),
+            external_reference: encode_hex(&self.get_external_reference()),
+            creation_slot: self.get_creation_slot(),
+            locked_identifier: encode_hex(&self.get_locked_identifier()),
+            fault: self.get_fault().option(),
+            pending_call_charges: self.get_pending_call_charges().0,
+            encrypted_note: self
+                .get_encrypted_note()
+                .option()
+                .map(|note| encode_hex(&note)),
+        })
     }
+
+    /// Writes all values of a [`VerificationAccountDump`] into the account
+    pub fn load_dump(&mut self, dump: &VerificationAccountDump) -> Result<(), std::io::Error> {
+        let invalid_data = || std::io::Error::from(std::io::ErrorKind::InvalidData);
+
+        if dump.prepare_inputs_instructions.len() > MAX_PREPARE_INPUTS_INSTRUCTIONS
+            || dump.public_inputs.len() != MAX_PUBLIC_INPUTS_COUNT
+            || dump.tree_indices.len() != MAX_MT_COUNT
+        {
+            return Err(invalid_data());
+        }
+
+        self.set_instruction(&dump.instruction);
+        self.set_round(&dump.round);
+        self.set_prepare_inputs_instructions_count(&usize_as_u32_safe(
+            dump.prepare_inputs_instructions.len(),
+        ));
+        for (i, instruction) in dump.prepare_inputs_instructions.iter().enumerate() {
+            self.set_prepare_inputs_instructions(i, instruction);
+        }
+        self.set_vkey_id(&dump.vkey_id);
+        self.set_step(&dump.step);
+        self.set_state(&dump.state);
+        for (i, public_input) in dump.public_inputs.iter().enumerate() {
+            let public_input = U256::try_from_slice(&decode_hex(public_input)?)?;
+            self.set_public_input(i, &RawU256::new(public_input));
+        }
+
+        self.a.set(G1A::try_from_slice(&decode_hex(&dump.a)?)?);
+        self.b.set(G2A::try_from_slice(&decode_hex(&dump.b)?)?);
+        self.c.set(G1A::try_from_slice(&decode_hex(&dump.c)?)?);
+
+        self.prepared_inputs
+            .set(G1A::try_from_slice(&decode_hex(&dump.prepared_inputs)?)?);
+        self.r
+            .set(G2HomProjective::try_from_slice(&decode_hex(&dump.r)?)?);
+        self.f
+            .set(Wrap::<Fq12>::try_from_slice(&decode_hex(&dump.f)?)?);
+        self.alt_b
+            .set(G2A::try_from_slice(&decode_hex(&dump.alt_b)?)?);
+        self.set_coeff_index(&dump.coeff_index);
+
+        self.ram_fq.set_source(&decode_hex(&dump.ram_fq)?)?;
+        self.ram_fq2.set_source(&decode_hex(&dump.ram_fq2)?)?;
+        self.ram_fq6.set_source(&decode_hex(&dump.ram_fq6)?)?;
+        self.ram_fq12.set_source(&decode_hex(&dump.ram_fq12)?)?;
+
+        self.set_is_verified(&dump.is_verified.into());
+        self.set_other_data(&VerificationAccountData::try_from_slice(&decode_hex(
+            &dump.other_data,
+        )?)?);
+        self.set_request(&dump.request);
+        for (i, tree_index) in dump.tree_indices.iter().enumerate() {
+            self.set_tree_indices(i, tree_index);
+        }
+        self.set_external_reference(
+            &decode_hex(&dump.external_reference)?
+                .try_into()
+                .map_err(|_| invalid_data())?,
+        );
+        self.set_creation_slot(&dump.creation_slot);
+        self.set_locked_identifier(
+            &decode_hex(&dump.locked_identifier)?
+                .try_into()
+                .map_err(|_| invalid_data())?,
+        );
+        self.set_fault(&dump.fault.into());
+        self.set_pending_call_charges(&Lamports(dump.pending_call_charges));
+        self.set_encrypted_note(&match &dump.encrypted_note {
+            Some(note) => {
+                ElusivOption::Some(decode_hex(note)?.try_into().map_err(|_| invalid_data())?)
+            }
+            None => ElusivOption::None,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "elusiv-client")]
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(feature = "elusiv-client")]
+fn decode_hex(s: &str) -> Result<Vec<u8>, std::io::Error> {
+    if !s.len().is_multiple_of(2) {
+        return Err(std::io::Error::from(std::io::ErrorKind::InvalidData));
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| {
+            s.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidData))
+        })
+        .collect()
 }
 
 /// Stores data lazily on the heap, read requests will trigger deserialization
@@ -296,6 +790,26 @@ where
         }
     }
 
+    /// The serialized values
+    #[cfg(feature = "elusiv-client")]
+    pub fn source(&self) -> &[u8] {
+        self.source
+    }
+
+    /// Overwrites all serialized values and discards all cached values
+    #[cfg(feature = "elusiv-client")]
+    pub fn set_source(&mut self, source: &[u8]) -> Result<(), std::io::Error> {
+        if source.len() != self.source.len() {
+            return Err(std::io::Error::from(std::io::ErrorKind::InvalidData));
+        }
+
+        self.source.copy_from_slice(source);
+        self.data.clear();
+        self.changes.clear();
+
+        Ok(())
+    }
+
     pub fn serialize(&mut self) -> Result<(), std::io::Error> {
         for (i, &change) in self.changes.iter().enumerate() {
             if change {
@@ -310,11 +824,20 @@ where
     }
 }
 
+/// Prevents concurrent verifications of the same nullifier-hashes
+/// - verifications initialized with `skip_nullifier_pda` are attached to the account of the initiating verification
+/// - the account is closed once neither the initiating nor an attached verification is in flight
 #[elusiv_account]
 pub struct NullifierDuplicateAccount {
     #[no_getter]
     #[no_setter]
     pda_data: PDAAccountData,
+
+    /// The `VerificationAccount` that opened this account (none once that verification has been closed)
+    pub initiator: ElusivOption<Pubkey>,
+
+    /// Count of attached `skip_nullifier_pda` verifications that are still in flight
+    pub skip_verifications_count: u32,
 }
 
 impl<'a> NullifierDuplicateAccount<'a> {
@@ -325,14 +848,31 @@ impl<'a> NullifierDuplicateAccount<'a> {
     }
 }
 
+/// Prevents concurrent send verifications with the same identifier
+///
+/// # Note
+///
+/// Exists only while a verification that locked the identifier is in flight.
+#[elusiv_account]
+pub struct IdentifierLockAccount {
+    #[no_getter]
+    #[no_setter]
+    pda_data: PDAAccountData,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
         fields::{u256_from_str, u256_from_str_skip_mr},
+        proof::vkey::{
+            MigrateUnaryVKey, SendQuadraVKey, VerifyingKeyInfo, MIGRATE_SIGNAL_LAYOUT,
+            SEND_SIGNAL_LAYOUT,
+        },
         state::{metadata::CommitmentMetadata, program_account::ProgramAccount},
         types::{
-            InputCommitment, JoinSplitPublicInputs, OptionalFee, PublicInputs, SendPublicInputs,
+            InputCommitment, JoinSplitPublicInputs, MigratePublicInputs, OptionalFee, PublicInputs,
+            SendPublicInputs, JOIN_SPLIT_MAX_N_ARITY,
         },
     };
     use elusiv_types::SizedAccount;
@@ -342,6 +882,9 @@ mod tests {
         let mut data = vec![0; VerificationAccount::SIZE];
         let mut verification_account = VerificationAccount::new(&mut data).unwrap();
 
+        // Unset by default
+        assert_eq!(verification_account.get_external_reference(), [0; 16]);
+
         let public_inputs = SendPublicInputs {
             join_split: JoinSplitPublicInputs {
                 input_commitments: vec![InputCommitment {
@@ -364,6 +907,7 @@ mod tests {
         let request = ProofRequest::Send(public_inputs.clone());
         let data = VerificationAccountData {
             fee_payer: RawU256::new([1; 32]),
+            rent_beneficiary: ElusivOption::Some(RawU256::new([2; 32])),
             skip_nullifier_pda: true,
             ..Default::default()
         };
@@ -375,17 +919,23 @@ mod tests {
         verification_account
             .setup(
                 data.fee_payer,
+                data.rent_beneficiary,
                 true,
                 &public_inputs,
                 &instructions,
                 vkey_id,
                 request,
                 [123, 456],
+                [7; 16],
             )
             .unwrap();
 
-        assert_eq!(verification_account.get_state(), VerificationState::None);
+        assert_eq!(
+            verification_account.get_state().unwrap(),
+            VerificationState::None
+        );
         assert_eq!(verification_account.get_vkey_id(), vkey_id);
+        assert_eq!(verification_account.get_external_reference(), [7; 16]);
 
         assert_eq!(
             verification_account.get_prepare_inputs_instructions_count() as usize,
@@ -423,6 +973,316 @@ mod tests {
         const SIZE: usize = u64::SIZE;
     }
 
+    #[test]
+    fn test_fee_balance_invariant() {
+        let mut data = vec![0; VerificationAccount::SIZE];
+        let mut verification_account = VerificationAccount::new(&mut data).unwrap();
+
+        let mut public_inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(RawU256::new(u256_from_str("22"))),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("333")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("44444")),
+                recent_commitment_index: 456,
+                fee_version: 0,
+                amount: 666666,
+                fee: 100,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            hashed_inputs: u256_from_str_skip_mr("7777777"),
+            recipient_is_associated_token_account: true,
+            solana_pay_transfer: false,
+        };
+        verification_account.set_request(&ProofRequest::Send(public_inputs.clone()));
+        verification_account.set_other_data(&VerificationAccountData {
+            subvention: 20,
+            commitment_hash_fee_token: 50,
+            proof_verification_fee: 60,
+            network_fee: 10,
+            associated_token_account_rent: 7,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            verification_account.get_accumulated_fees().unwrap(),
+            VerificationFeeAudit {
+                fee_paid: 100,
+                subvention_received: 20,
+                commitment_hash_fee: 50,
+                proof_verification_fee: 60,
+                network_fee: 10,
+                associated_token_account_rent: 7,
+            }
+        );
+        assert_eq!(verification_account.assert_fee_balance_invariant(), Ok(()));
+
+        // Overpaid fee
+        public_inputs.join_split.fee = 101;
+        verification_account.set_request(&ProofRequest::Send(public_inputs.clone()));
+        assert_eq!(
+            verification_account.assert_fee_balance_invariant(),
+            Err(ElusivError::InvalidFee)
+        );
+
+        // Insufficient fee
+        public_inputs.join_split.fee = 99;
+        verification_account.set_request(&ProofRequest::Send(public_inputs));
+        assert_eq!(
+            verification_account.assert_fee_balance_invariant(),
+            Err(ElusivError::InvalidFee)
+        );
+    }
+
+    #[test]
+    fn test_unknown_discriminants() {
+        let mut data = vec![0; VerificationAccount::SIZE];
+        let mut verification_account = VerificationAccount::new(&mut data).unwrap();
+        assert_eq!(
+            verification_account.get_state(),
+            Ok(VerificationState::None)
+        );
+
+        verification_account.set_state(&VerificationState::Closed);
+        verification_account.set_state_discriminant(VerificationState::Closed as u8 + 1);
+        assert_eq!(
+            verification_account.get_state(),
+            Err(ElusivError::UnsupportedAccountState)
+        );
+
+        verification_account.set_state_discriminant(u8::MAX);
+        assert_eq!(
+            verification_account.get_state(),
+            Err(ElusivError::UnsupportedAccountState)
+        );
+
+        verification_account.set_request_discriminant(2);
+        assert!(matches!(
+            verification_account.get_request(),
+            Err(ElusivError::UnsupportedAccountState)
+        ));
+        assert_eq!(
+            verification_account.assert_fee_balance_invariant(),
+            Err(ElusivError::UnsupportedAccountState)
+        );
+    }
+
+    fn setup_signals(verification_account: &mut VerificationAccount, request: ProofRequest) {
+        let public_signals = match &request {
+            ProofRequest::Send(public_inputs) => public_inputs.public_signals(),
+            ProofRequest::Migrate(public_inputs) => public_inputs.public_signals(),
+        };
+        verification_account
+            .setup(
+                RawU256::new([1; 32]),
+                ElusivOption::None,
+                false,
+                &public_signals,
+                &vec![],
+                request.vkey_id(),
+                request,
+                [0, 0],
+                [0; 16],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_get_signal_send() {
+        let mut data = vec![0; VerificationAccount::SIZE];
+        let mut verification_account = VerificationAccount::new(&mut data).unwrap();
+        let public_inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![
+                    InputCommitment {
+                        root: Some(RawU256::new(u256_from_str_skip_mr("22"))),
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("333")),
+                    },
+                    InputCommitment {
+                        root: None,
+                        nullifier_hash: RawU256::new(u256_from_str_skip_mr("334")),
+                    },
+                ],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("44444")),
+                recent_commitment_index: 456,
+                fee_version: 55555,
+                amount: 666666,
+                fee: 123,
+                optional_fee: OptionalFee::default(),
+                token_id: 1,
+                metadata: CommitmentMetadata::default(),
+            },
+            hashed_inputs: u256_from_str_skip_mr("7777777"),
+            recipient_is_associated_token_account: false,
+            solana_pay_transfer: false,
+        };
+        setup_signals(
+            &mut verification_account,
+            ProofRequest::Send(public_inputs.clone()),
+        );
+
+        // Every named signal round-trips
+        for (signal, k) in SendQuadraVKey::SIGNAL_LAYOUT.signals() {
+            assert_eq!(
+                verification_account.get_signal(signal, k).unwrap(),
+                public_inputs.signal(signal, k).unwrap().skip_mr()
+            );
+        }
+
+        assert_eq!(
+            verification_account.get_signal_nullifier_hash(1).unwrap(),
+            u256_from_str_skip_mr("334")
+        );
+        assert_eq!(
+            verification_account.get_signal_root(0).unwrap(),
+            u256_from_str_skip_mr("22")
+        );
+        assert_eq!(verification_account.get_signal_root(1).unwrap(), [0; 32]);
+        assert_eq!(
+            verification_account.get_signal_nullifier_hash(3).unwrap(),
+            [0; 32]
+        );
+        assert_eq!(
+            verification_account.get_signal_output_commitment().unwrap(),
+            u256_from_str_skip_mr("44444")
+        );
+        assert_eq!(
+            verification_account.get_signal_hashed_inputs().unwrap(),
+            u256_from_str_skip_mr("7777777")
+        );
+        assert_eq!(
+            verification_account.get_signal_amount().unwrap(),
+            666666 + 123
+        );
+
+        // Signals outside of the layout
+        assert_eq!(
+            verification_account.get_signal_root(JOIN_SPLIT_MAX_N_ARITY),
+            Err(ElusivError::InvalidPublicInputs)
+        );
+        assert_eq!(
+            verification_account.get_signal(Signal::CurrentNsmtRoot, 0),
+            Err(ElusivError::InvalidPublicInputs)
+        );
+    }
+
+    #[test]
+    fn test_get_signal_migrate() {
+        let mut data = vec![0; VerificationAccount::SIZE];
+        let mut verification_account = VerificationAccount::new(&mut data).unwrap();
+        let public_inputs = MigratePublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(RawU256::new(u256_from_str_skip_mr("22"))),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("333")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("44444")),
+                recent_commitment_index: 456,
+                fee_version: 0,
+                amount: 0,
+                fee: 123,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            current_nsmt_root: RawU256::new(u256_from_str_skip_mr("5")),
+            next_nsmt_root: RawU256::new(u256_from_str_skip_mr("6")),
+        };
+        setup_signals(
+            &mut verification_account,
+            ProofRequest::Migrate(public_inputs.clone()),
+        );
+
+        // Every named signal round-trips
+        for (signal, k) in MigrateUnaryVKey::SIGNAL_LAYOUT.signals() {
+            assert_eq!(
+                verification_account.get_signal(signal, k).unwrap(),
+                public_inputs.signal(signal, k).unwrap().skip_mr()
+            );
+        }
+
+        assert_eq!(
+            verification_account
+                .get_signal(Signal::NextNsmtRoot, 0)
+                .unwrap(),
+            u256_from_str_skip_mr("6")
+        );
+        assert_eq!(verification_account.get_signal_amount().unwrap(), 123);
+
+        // Signals outside of the layout
+        assert_eq!(
+            verification_account.get_signal_nullifier_hash(1),
+            Err(ElusivError::InvalidPublicInputs)
+        );
+        assert_eq!(
+            verification_account.get_signal_hashed_inputs(),
+            Err(ElusivError::InvalidPublicInputs)
+        );
+    }
+
+    #[test]
+    fn test_signal_layout() {
+        assert_eq!(
+            SendQuadraVKey::SIGNAL_LAYOUT.signals_count(),
+            SendQuadraVKey::public_inputs_count()
+        );
+        assert_eq!(
+            MigrateUnaryVKey::SIGNAL_LAYOUT.signals_count(),
+            MigrateUnaryVKey::public_inputs_count()
+        );
+
+        assert_eq!(SEND_SIGNAL_LAYOUT.index(Signal::NullifierHash, 3), Some(3));
+        assert_eq!(SEND_SIGNAL_LAYOUT.index(Signal::Root, 0), Some(4));
+        assert_eq!(SEND_SIGNAL_LAYOUT.index(Signal::HashedInputs, 0), Some(13));
+        assert_eq!(SEND_SIGNAL_LAYOUT.index(Signal::HashedInputs, 1), None);
+        assert_eq!(MIGRATE_SIGNAL_LAYOUT.index(Signal::Amount, 0), Some(6));
+        assert_eq!(MIGRATE_SIGNAL_LAYOUT.index(Signal::FeeVersion, 0), None);
+
+        assert_eq!(
+            SEND_SIGNAL_LAYOUT.count(Signal::Root),
+            JOIN_SPLIT_MAX_N_ARITY
+        );
+        assert_eq!(MIGRATE_SIGNAL_LAYOUT.count(Signal::Root), 1);
+        assert_eq!(MIGRATE_SIGNAL_LAYOUT.count(Signal::TokenId), 0);
+    }
+
+    #[test]
+    fn test_transition_state() {
+        let mut data = vec![0; VerificationAccount::SIZE];
+        let mut verification_account = VerificationAccount::new(&mut data).unwrap();
+
+        // Full lifecycle
+        for state in [
+            VerificationState::FeeTransferred,
+            VerificationState::ProofSetup,
+            VerificationState::InsertNullifiers,
+            VerificationState::Finalized,
+            VerificationState::Closed,
+        ] {
+            verification_account.transition_state(&state).unwrap();
+            assert_eq!(verification_account.get_state(), Ok(state));
+        }
+
+        // Invalid transitions are only rejected by hardened builds
+        let result = verification_account.transition_state(&VerificationState::ProofSetup);
+        if cfg!(feature = "hardened") {
+            assert_eq!(result, Err(ElusivError::InvalidAccountState));
+            assert_eq!(
+                verification_account.get_state(),
+                Ok(VerificationState::Closed)
+            );
+        } else {
+            assert_eq!(result, Ok(()));
+        }
+
+        assert!(!VerificationState::None.can_transition_to(&VerificationState::ProofSetup));
+        assert!(!VerificationState::Closed.can_transition_to(&VerificationState::None));
+        assert!(!VerificationState::InsertNullifiers.can_transition_to(&VerificationState::Closed));
+    }
+
     #[test]
     fn test_lazy_ram() {
         let mut data = vec![0; u64::SIZE * 2];
@@ -465,4 +1325,113 @@ mod tests {
         assert_eq!(ram.data.len(), 3);
         assert_eq!(ram.changes.len(), 3);
     }
+
+    #[test]
+    fn test_max_prepare_inputs_instructions() {
+        use crate::proof::verifier::max_prepare_public_inputs_instructions;
+
+        // Every accepted input preparation fits into the account
+        assert!(
+            max_prepare_public_inputs_instructions(MAX_PUBLIC_INPUTS_COUNT)
+                <= MAX_PREPARE_INPUTS_INSTRUCTIONS
+        );
+    }
+
+    #[test]
+    fn test_verification_account_dump() {
+        use ark_bn254::{G1Affine, G2Affine};
+        use ark_ec::AffineCurve;
+        use ark_ff::{One, Zero};
+
+        let mut data = vec![0; VerificationAccount::SIZE];
+        let mut verification_account = VerificationAccount::new(&mut data).unwrap();
+
+        let public_inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![InputCommitment {
+                    root: Some(RawU256::new(u256_from_str("22"))),
+                    nullifier_hash: RawU256::new(u256_from_str_skip_mr("333")),
+                }],
+                output_commitment: RawU256::new(u256_from_str_skip_mr("44444")),
+                recent_commitment_index: 456,
+                fee_version: 55555,
+                amount: 666666,
+                fee: 123,
+                optional_fee: OptionalFee::default(),
+                token_id: 0,
+                metadata: CommitmentMetadata::default(),
+            },
+            hashed_inputs: u256_from_str_skip_mr("7777777"),
+            recipient_is_associated_token_account: true,
+            solana_pay_transfer: false,
+        };
+        verification_account
+            .setup(
+                RawU256::new([1; 32]),
+                ElusivOption::Some(RawU256::new([2; 32])),
+                false,
+                &public_inputs.public_signals(),
+                &vec![3, 4, 5],
+                1,
+                ProofRequest::Send(public_inputs),
+                [7, 8],
+                [9; 16],
+            )
+            .unwrap();
+
+        // Fully populated account
+        let g1 = G1Affine::prime_subgroup_generator();
+        let g2 = G2Affine::prime_subgroup_generator();
+        verification_account.a.set(G1A(g1));
+        verification_account.b.set(G2A(g2));
+        verification_account.c.set(G1A(g1 + g1));
+        verification_account.prepared_inputs.set(G1A(-g1));
+        verification_account.r.set(G2HomProjective {
+            x: Fq2::one(),
+            y: Fq2::zero(),
+            z: Fq2::one() + Fq2::one(),
+        });
+        verification_account.f.set(Wrap(Fq12::one()));
+        verification_account.alt_b.set(G2A(-g2));
+        verification_account.set_coeff_index(&11);
+        verification_account.ram_fq.write(Fq::from(12u64), 1);
+        verification_account.ram_fq2.write(Fq2::one(), 2);
+        verification_account.ram_fq6.write(Fq6::one(), 0);
+        verification_account.ram_fq12.write(Fq12::one(), 6);
+        verification_account.serialize_rams().unwrap();
+        verification_account.set_instruction(&2);
+        verification_account.set_round(&13);
+        verification_account.set_step(&VerificationStep::CombinedMillerLoop);
+        verification_account.set_state(&VerificationState::ProofSetup);
+        verification_account.fail(VerificationFault::WardenFault);
+        verification_account.set_creation_slot(&14);
+        verification_account.set_locked_identifier(&[15; 32]);
+
+        let dump = verification_account.dump().unwrap();
+        assert_eq!(dump.prepare_inputs_instructions, vec![3, 4, 5]);
+        assert_eq!(dump.state, VerificationState::ProofSetup);
+        assert_eq!(dump.external_reference, "09".repeat(16));
+
+        // JSON round-trip
+        let json = serde_json::to_value(&dump).unwrap();
+        assert_eq!(json["is_verified"], serde_json::json!(false));
+        let parsed: VerificationAccountDump =
+            serde_json::from_str(&serde_json::to_string(&json).unwrap()).unwrap();
+        assert_eq!(parsed, dump);
+
+        let mut loaded_data = vec![0; VerificationAccount::SIZE];
+        let mut loaded = VerificationAccount::new(&mut loaded_data).unwrap();
+        loaded.load_dump(&parsed).unwrap();
+        assert_eq!(loaded.dump().unwrap(), dump);
+        drop(loaded);
+        drop(verification_account);
+        assert_eq!(loaded_data, data);
+
+        // Invalid hex
+        let mut invalid = dump;
+        invalid.a = "0g".to_string();
+        let mut invalid_data = vec![0; VerificationAccount::SIZE];
+        let mut invalid_account = VerificationAccount::new(&mut invalid_data).unwrap();
+        assert!(invalid_account.load_dump(&invalid).is_err());
+    }
 }
@@ -2,13 +2,17 @@
 
 use crate::bytes::*;
 use crate::error::ElusivError::{InvalidQueueAccess, QueueIsEmpty, QueueIsFull};
-use crate::macros::guard;
+use crate::macros::{guard, hardened_guard};
 use elusiv_types::ProgramAccount;
 use solana_program::program_error::ProgramError;
 
 /// Generates a [`QueueAccount`] and a [`Queue`] that implements the [`RingQueue`] trait
+///
+/// # Note
+///
+/// Additional fields can be appended to the account with `field: Type` after the element type (`Type` has to be a plain identifier).
 macro_rules! queue_account {
-    ($id: ident, $id_account: ident, $size: expr, $ty_element: ty $(,)?) => {
+    ($id: ident, $id_account: ident, $size: expr, $ty_element: ty $(, $(#[$attr: meta])* $field: ident: $ty_field: ident)* $(,)?) => {
         #[allow(dead_code)] // required for the pda_data field
         #[crate::macros::elusiv_account]
         pub struct $id_account {
@@ -19,6 +23,10 @@ macro_rules! queue_account {
             head: u32,
             tail: u32,
             raw_data: [$ty_element; $size],
+            $(
+                $(#[$attr])*
+                pub $field: $ty_field,
+            )*
         }
 
         #[cfg(test)]
@@ -27,6 +35,7 @@ macro_rules! queue_account {
             <elusiv_types::accounts::PDAAccountData as elusiv_types::bytes::BorshSerDeSized>::SIZE
                 + (4 + 4)
                 + <$ty_element as elusiv_types::bytes::BorshSerDeSized>::SIZE * ($size)
+                $(+ <$ty_field as elusiv_types::bytes::BorshSerDeSized>::SIZE)*
         );
 
         #[cfg(test)]
@@ -112,8 +121,19 @@ pub trait RingQueue {
     fn get_data(&self, index: usize) -> Self::N;
     fn set_data(&mut self, index: usize, value: &Self::N);
 
+    /// The pointers have to be in `[0; SIZE)` (only enforced by `hardened` builds)
+    fn verify_pointers(&self) -> Result<(), ProgramError> {
+        hardened_guard!(
+            self.get_head() < Self::SIZE && self.get_tail() < Self::SIZE,
+            InvalidQueueAccess
+        );
+
+        Ok(())
+    }
+
     /// Try to enqueue a new element in the queue
     fn enqueue(&mut self, value: Self::N) -> Result<(), ProgramError> {
+        self.verify_pointers()?;
         let head = self.get_head();
         let tail = self.get_tail();
 
@@ -132,6 +152,7 @@ pub trait RingQueue {
     }
 
     fn view(&self, offset: usize) -> Result<Self::N, ProgramError> {
+        self.verify_pointers()?;
         let head = self.get_head();
         let tail = self.get_tail();
         guard!(head != tail, QueueIsEmpty);
@@ -142,6 +163,7 @@ pub trait RingQueue {
 
     /// Try to remove the first element from the queue
     fn dequeue_first(&mut self) -> Result<Self::N, ProgramError> {
+        self.verify_pointers()?;
         let head = self.get_head();
         let tail = self.get_tail();
         guard!(head != tail, QueueIsEmpty);
@@ -153,12 +175,32 @@ pub trait RingQueue {
     }
 
     fn remove(&mut self, count: u32) -> Result<(), ProgramError> {
+        self.verify_pointers()?;
         let head = self.get_head();
         guard!(self.len() >= count, InvalidQueueAccess);
         self.set_head(&((head + count) % Self::SIZE));
         Ok(())
     }
 
+    /// Removes the element at `offset` (relative to the head)
+    ///
+    /// # Note
+    ///
+    /// All preceding elements are moved by one towards the tail, so the order of the remaining elements is preserved.
+    fn remove_at(&mut self, offset: usize) -> Result<Self::N, ProgramError> {
+        let value = self.view(offset)?;
+        let head = self.get_head() as usize;
+        let size = Self::SIZE as usize;
+
+        for i in (0..offset).rev() {
+            let element = self.get_data((head + i) % size);
+            self.set_data((head + i + 1) % size, &element);
+        }
+        self.set_head(&usize_as_u32_safe((head + 1) % size));
+
+        Ok(value)
+    }
+
     fn contains(&self, value: &Self::N) -> bool {
         let mut ptr = self.get_head();
         let tail = self.get_tail();
@@ -406,6 +448,41 @@ mod tests {
         queue.remove(1).unwrap();
     }
 
+    #[test]
+    fn test_remove_at() {
+        for head in 0..7 {
+            test_queue!(queue, 7, 0, 0);
+            queue.head = head;
+            queue.tail = head;
+
+            for i in 0..5 {
+                queue.enqueue(i).unwrap();
+            }
+
+            assert_eq!(queue.remove_at(2), Ok(2));
+            assert_eq!(queue.len(), 4);
+            assert_eq!(
+                queue.remove_at(4),
+                Err(ElusivError::InvalidQueueAccess.into())
+            );
+
+            assert_eq!(queue.remove_at(3), Ok(4));
+            assert_eq!(queue.remove_at(0), Ok(0));
+
+            assert_eq!(queue.dequeue_first(), Ok(1));
+            assert_eq!(queue.dequeue_first(), Ok(3));
+            assert!(queue.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_invalid_pointers() {
+        test_queue!(queue, 10, 11, 0);
+
+        // Out-of-range pointers are only rejected by hardened builds
+        assert_eq!(queue.enqueue(1).is_err(), cfg!(feature = "hardened"));
+    }
+
     #[test]
     fn test_clear_queue() {
         test_queue!(queue, 13, 0, 0);
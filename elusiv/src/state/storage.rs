@@ -1,7 +1,9 @@
 use super::program_account::*;
 use crate::bytes::*;
-use crate::macros::{elusiv_account, two_pow};
-use crate::types::U256;
+use crate::commitment::poseidon_hash::const_full_poseidon2_hash;
+use crate::error::ElusivError;
+use crate::macros::{elusiv_account, guard, two_pow};
+use crate::types::{le_limbs_to_u256, U256};
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::entrypoint::ProgramResult;
 use solana_program::program_error::ProgramError;
@@ -24,6 +26,10 @@ pub const fn mt_size(height: u32) -> usize {
 /// Number of all commitments (leaves) in the MT
 pub const MT_COMMITMENT_COUNT: usize = two_pow!(MT_HEIGHT);
 
+/// Maximum number of MTs (closed ones and the active one)
+/// - once reached, the active MT cannot be closed anymore
+pub const MAX_TREES: u32 = two_pow!(16) as u32;
+
 /// Since before submitting a proof request the current root can change, we store the [`HISTORY_ARRAY_SIZE`] previous ones
 pub const HISTORY_ARRAY_SIZE: usize = 100;
 
@@ -75,6 +81,17 @@ impl<'a, 'b, 't> StorageAccount<'a, 'b, 't> {
         }
     }
 
+    /// Verifies that all child-accounts have been enabled
+    /// - a partially initialized MT would only fail in the middle of an insertion
+    pub fn verify_fully_initialized(&self) -> ProgramResult {
+        guard!(
+            (0..ACCOUNTS_COUNT).all(|i| self.get_child_pubkey(i).is_some()),
+            ElusivError::StorageNotFullyInitialized
+        );
+
+        Ok(())
+    }
+
     pub fn is_full(&self) -> bool {
         let ptr = self.get_next_commitment_ptr() as usize;
         ptr >= MT_COMMITMENT_COUNT
@@ -120,6 +137,16 @@ impl<'a, 'b, 't> StorageAccount<'a, 'b, 't> {
         Ok(())
     }
 
+    /// The commitment at `leaf_index` of the active MT
+    pub fn get_commitment(&self, leaf_index: usize) -> Result<U256, ProgramError> {
+        guard!(
+            leaf_index < self.get_next_commitment_ptr() as usize,
+            ElusivError::InvalidInstructionData
+        );
+
+        self.get_node(leaf_index, MT_HEIGHT as usize)
+    }
+
     pub fn get_root(&self) -> Result<U256, ProgramError> {
         self.get_node(0, 0)
     }
@@ -256,11 +283,44 @@ pub const EMPTY_TREE: [U256; MT_HEIGHT as usize + 1] = [
     ],
 ];
 
+/// Root of the empty MT (in mr-form)
+pub const EMPTY_ROOT: U256 = EMPTY_TREE[MT_HEIGHT as usize];
+
+#[cfg(test)]
+const_assert!(u256_eq(&EMPTY_ROOT, &compute_empty_tree_root()));
+
+/// Computes the root of the empty MT by hashing the empty commitment (`poseidon(0, 0)`) up to the root level
+///
+/// # Note
+///
+/// Only intended for compile time evaluation.
+pub const fn compute_empty_tree_root() -> U256 {
+    let mut node = const_full_poseidon2_hash([0; 4], [0; 4]);
+    let mut level = 0;
+    while level < MT_HEIGHT {
+        node = const_full_poseidon2_hash(node, node);
+        level += 1;
+    }
+    le_limbs_to_u256(node)
+}
+
+#[cfg(test)]
+const fn u256_eq(a: &U256, b: &U256) -> bool {
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
 #[cfg(feature = "test-elusiv")]
 pub fn empty_root_raw() -> crate::types::RawU256 {
     use crate::fields::{fr_to_u256_le_repr, scalar_skip_mr, u256_to_big_uint};
     crate::types::RawU256::new(fr_to_u256_le_repr(&scalar_skip_mr(u256_to_big_uint(
-        &EMPTY_TREE[MT_HEIGHT as usize],
+        &EMPTY_ROOT,
     ))))
 }
 
@@ -399,6 +459,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_get_commitment() {
+        parent_account!(mut storage_account, StorageAccount);
+        assert_eq!(
+            storage_account.get_commitment(0),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+
+        for i in 0..4 {
+            storage_account
+                .set_node(&[i as u8 + 1; 32], i, MT_HEIGHT as usize)
+                .unwrap();
+            storage_account.set_next_commitment_ptr(&(i as u32 + 1));
+        }
+
+        for i in 0..4 {
+            assert_eq!(storage_account.get_commitment(i), Ok([i as u8 + 1; 32]));
+        }
+        assert_eq!(
+            storage_account.get_commitment(4),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+        assert_eq!(
+            storage_account.get_commitment(MT_COMMITMENT_COUNT),
+            Err(ElusivError::InvalidInstructionData.into())
+        );
+    }
+
     #[test]
     fn test_get_root() {
         parent_account!(mut storage_account, StorageAccount);
@@ -438,4 +526,13 @@ mod tests {
         assert!(storage_account.is_root_valid(&EMPTY_TREE[MT_HEIGHT as usize]));
         assert!(!storage_account.is_root_valid(&[0; 32]));
     }
+
+    #[test]
+    fn test_compute_empty_tree_root() {
+        let root = compute_empty_tree_root();
+        assert_eq!(root, EMPTY_ROOT);
+
+        parent_account!(storage_account, StorageAccount);
+        assert!(storage_account.is_root_valid(&root));
+    }
 }
@@ -0,0 +1,220 @@
+use super::program_account::PDAAccountData;
+use crate::error::ElusivError;
+use crate::macros::{elusiv_account, guard};
+use crate::token::{TokenID, SPL_TOKEN_COUNT};
+use borsh::{BorshDeserialize, BorshSerialize};
+use elusiv_derive::BorshSerDeSized;
+
+/// The epoch of `slot` with `epoch_length` slots per epoch
+/// - an `epoch_length` of zero disables epoch rewards
+pub fn warden_reward_epoch(slot: u64, epoch_length: u64) -> Option<u64> {
+    slot.checked_div(epoch_length)
+}
+
+/// The rewards of a warden in a single token
+#[derive(
+    BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Eq, Clone, Copy, Default,
+)]
+#[cfg_attr(any(test, feature = "elusiv-client"), derive(Debug))]
+pub struct WardenRewardEntry {
+    /// The epoch in which `accrued` has been accumulated
+    pub epoch: u64,
+    pub accrued: u64,
+
+    /// The rewards of all epochs before `epoch`
+    pub claimable: u64,
+}
+
+impl WardenRewardEntry {
+    /// Moves the rewards of all epochs before `epoch` into `claimable`
+    fn roll_over(&mut self, epoch: u64) -> Result<(), ElusivError> {
+        if self.epoch < epoch {
+            self.claimable = self
+                .claimable
+                .checked_add(self.accrued)
+                .ok_or(ElusivError::InvalidAmount)?;
+            self.accrued = 0;
+            self.epoch = epoch;
+        }
+
+        Ok(())
+    }
+}
+
+/// Accumulates the proof verification rewards of a single warden, which can be claimed once per epoch
+///
+/// # Note
+///
+/// Each warden opens its own account (PDA of the warden's pubkey), so finalizations of different wardens never lock the same account.
+#[elusiv_account(eager_type: true)]
+pub struct WardenRewardsAccount {
+    #[no_getter]
+    #[no_setter]
+    pda_data: PDAAccountData,
+
+    /// The rewards per [`TokenID`]
+    pub entries: [WardenRewardEntry; SPL_TOKEN_COUNT + 1],
+}
+
+impl<'a> WardenRewardsAccount<'a> {
+    /// Accrues `amount` of `token_id` in `epoch`
+    pub fn accrue(
+        &mut self,
+        token_id: TokenID,
+        epoch: u64,
+        amount: u64,
+    ) -> Result<(), ElusivError> {
+        let index = Self::entry_index(token_id)?;
+        let mut entry = self.get_entries(index);
+        entry.roll_over(epoch)?;
+        entry.accrued = entry
+            .accrued
+            .checked_add(amount)
+            .ok_or(ElusivError::InvalidAmount)?;
+        self.set_entries(index, &entry);
+
+        Ok(())
+    }
+
+    /// Removes and returns the rewards of `token_id` of all epochs before `epoch`
+    pub fn claim(&mut self, token_id: TokenID, epoch: u64) -> Result<u64, ElusivError> {
+        let index = Self::entry_index(token_id)?;
+        let mut entry = self.get_entries(index);
+        entry.roll_over(epoch)?;
+
+        let amount = entry.claimable;
+        guard!(amount > 0, ElusivError::NoClaimableRewards);
+        entry.claimable = 0;
+        self.set_entries(index, &entry);
+
+        Ok(amount)
+    }
+
+    fn entry_index(token_id: TokenID) -> Result<usize, ElusivError> {
+        let index = token_id as usize;
+        guard!(index <= SPL_TOKEN_COUNT, ElusivError::UnsupportedToken);
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::macros::zero_program_account;
+    use crate::token::{LAMPORTS_TOKEN_ID, USDC_TOKEN_ID};
+
+    #[test]
+    fn test_warden_reward_epoch() {
+        assert_eq!(warden_reward_epoch(0, 0), None);
+        assert_eq!(warden_reward_epoch(99, 100), Some(0));
+        assert_eq!(warden_reward_epoch(100, 100), Some(1));
+    }
+
+    #[test]
+    fn test_accrue_within_epoch() {
+        zero_program_account!(mut account, WardenRewardsAccount);
+
+        account.accrue(LAMPORTS_TOKEN_ID, 5, 100).unwrap();
+        account.accrue(USDC_TOKEN_ID, 5, 10).unwrap();
+        account.accrue(LAMPORTS_TOKEN_ID, 5, 23).unwrap();
+
+        // Entries are indexed by token-id
+        assert_eq!(
+            account.get_entries(LAMPORTS_TOKEN_ID as usize),
+            WardenRewardEntry {
+                epoch: 5,
+                accrued: 123,
+                claimable: 0,
+            }
+        );
+        assert_eq!(
+            account.get_entries(USDC_TOKEN_ID as usize),
+            WardenRewardEntry {
+                epoch: 5,
+                accrued: 10,
+                claimable: 0,
+            }
+        );
+
+        // Nothing can be claimed in the same epoch
+        assert_eq!(
+            account.claim(LAMPORTS_TOKEN_ID, 5),
+            Err(ElusivError::NoClaimableRewards)
+        );
+        assert_eq!(account.get_entries(0).accrued, 123);
+    }
+
+    #[test]
+    fn test_claim_across_epochs() {
+        zero_program_account!(mut account, WardenRewardsAccount);
+
+        account.accrue(LAMPORTS_TOKEN_ID, 0, 100).unwrap();
+        account.accrue(USDC_TOKEN_ID, 0, 50).unwrap();
+        account.accrue(LAMPORTS_TOKEN_ID, 1, 7).unwrap();
+
+        // Only the rewards of completed epochs are claimable
+        assert_eq!(account.claim(LAMPORTS_TOKEN_ID, 1), Ok(100));
+        assert_eq!(
+            account.claim(LAMPORTS_TOKEN_ID, 1),
+            Err(ElusivError::NoClaimableRewards)
+        );
+        assert_eq!(account.claim(LAMPORTS_TOKEN_ID, 2), Ok(7));
+        assert_eq!(
+            account.get_entries(0),
+            WardenRewardEntry {
+                epoch: 2,
+                accrued: 0,
+                claimable: 0,
+            }
+        );
+
+        // Tokens are claimed separately
+        assert_eq!(account.claim(USDC_TOKEN_ID, 3), Ok(50));
+        assert_eq!(
+            account.claim(USDC_TOKEN_ID, 3),
+            Err(ElusivError::NoClaimableRewards)
+        );
+    }
+
+    #[test]
+    fn test_epoch_length_change() {
+        zero_program_account!(mut account, WardenRewardsAccount);
+
+        // Accrued at slot 1_000 with 100 slots per epoch
+        account
+            .accrue(
+                LAMPORTS_TOKEN_ID,
+                warden_reward_epoch(1_000, 100).unwrap(),
+                7,
+            )
+            .unwrap();
+
+        // A longer epoch lowers the epoch numbers, which locks the accrued rewards
+        let epoch = warden_reward_epoch(1_000, 500).unwrap();
+        account.accrue(LAMPORTS_TOKEN_ID, epoch, 3).unwrap();
+        assert_eq!(
+            account.claim(LAMPORTS_TOKEN_ID, epoch + 1),
+            Err(ElusivError::NoClaimableRewards)
+        );
+        assert_eq!(account.get_entries(0).accrued, 10);
+
+        // A shorter epoch raises the epoch numbers, which makes them claimable immediately
+        let epoch = warden_reward_epoch(1_000, 10).unwrap();
+        assert_eq!(account.claim(LAMPORTS_TOKEN_ID, epoch), Ok(10));
+    }
+
+    #[test]
+    fn test_unsupported_token() {
+        zero_program_account!(mut account, WardenRewardsAccount);
+        let token_id = SPL_TOKEN_COUNT as TokenID + 1;
+
+        assert_eq!(
+            account.accrue(token_id, 0, 1),
+            Err(ElusivError::UnsupportedToken)
+        );
+        assert_eq!(
+            account.claim(token_id, 1),
+            Err(ElusivError::UnsupportedToken)
+        );
+    }
+}
@@ -2,8 +2,11 @@ use crate::bytes::BorshSerDeSized;
 use crate::fields::{fr_to_u256_le, u256_to_big_uint, u64_to_u256_skip_mr, G1A, G2A};
 use crate::macros::BorshSerDeSized;
 use crate::processor::MAX_MT_COUNT;
-use crate::proof::vkey::{MigrateUnaryVKey, SendQuadraVKey, VerifyingKeyInfo};
+use crate::proof::vkey::{
+    MigrateUnaryVKey, SendQuadraVKey, Signal, SignalLayout, VerifyingKeyInfo,
+};
 use crate::state::metadata::CommitmentMetadata;
+use crate::state::note::EncryptedNote;
 use crate::state::proof::NullifierDuplicateAccount;
 use crate::u64_array;
 use ark_bn254::Fr;
@@ -24,7 +27,7 @@ pub type U256 = [u8; 32];
     BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Clone, Copy, Debug, Default,
 )]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-pub struct RawU256(U256);
+pub struct RawU256(#[cfg_attr(feature = "serde", serde(with = "u256_hex"))] U256);
 
 impl RawU256 {
     pub const ZERO: Self = RawU256([0; 32]);
@@ -47,6 +50,63 @@ impl RawU256 {
     }
 }
 
+/// Serde representation of a [`U256`] as a hex-string of its bytes (in LE order)
+/// - byte-arrays are still accepted when deserializing
+#[cfg(feature = "serde")]
+pub mod u256_hex {
+    use super::U256;
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt::Write;
+
+    pub fn serialize<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(64);
+        for byte in value {
+            write!(hex, "{:02x}", byte).unwrap();
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+        deserializer.deserialize_any(U256Visitor)
+    }
+
+    struct U256Visitor;
+
+    impl<'de> Visitor<'de> for U256Visitor {
+        type Value = U256;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a hex-string or an array of 32 bytes")
+        }
+
+        fn visit_str<E: Error>(self, v: &str) -> Result<U256, E> {
+            if v.len() != 64 || !v.is_ascii() {
+                return Err(E::invalid_length(v.len(), &self));
+            }
+
+            let mut value = [0; 32];
+            for (i, byte) in value.iter_mut().enumerate() {
+                *byte = u8::from_str_radix(&v[i * 2..i * 2 + 2], 16).map_err(E::custom)?;
+            }
+            Ok(value)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<U256, A::Error> {
+            let mut value = [0; 32];
+            for (i, byte) in value.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+            if seq.next_element::<u8>()?.is_some() {
+                return Err(A::Error::invalid_length(33, &self));
+            }
+            Ok(value)
+        }
+    }
+}
+
 #[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, PartialEq, Eq, Clone, Copy, Debug)]
 pub struct OrdU256(pub U256);
 
@@ -331,6 +391,12 @@ impl JoinSplitPublicInputs {
         NullifierDuplicateAccount::associated_pubkey(&nullifier_hashes)
     }
 
+    /// The [`NullifierDuplicateAccount`] of the nullifier-hashes and its canonical bump
+    ///
+    /// # Note
+    ///
+    /// The program always derives the canonical bump itself, since accepting a client-supplied bump would allow multiple
+    /// [`NullifierDuplicateAccount`]s (at non-canonical PDAs) for the same nullifier-hashes.
     pub fn nullifier_duplicate_pda(&self) -> (Pubkey, u8) {
         NullifierDuplicateAccount::find_with_pubkey(
             self.associated_nullifier_duplicate_pda_pubkey(),
@@ -345,7 +411,7 @@ impl JoinSplitPublicInputs {
         NullifierDuplicateAccount::create_with_pubkey(
             self.associated_nullifier_duplicate_pda_pubkey(),
             None,
-            NullifierDuplicateAccount::get_bump(account),
+            NullifierDuplicateAccount::get_bump(account)?,
         )
     }
 
@@ -374,6 +440,7 @@ impl BorshSerDeSized for JoinSplitPublicInputs {
 
 pub trait PublicInputs {
     const PUBLIC_INPUTS_COUNT: usize;
+    const SIGNAL_LAYOUT: SignalLayout;
 
     /// Verifies the public inputs based on static value constraints
     fn verify_additional_constraints(&self) -> bool;
@@ -381,9 +448,29 @@ pub trait PublicInputs {
 
     fn set_fee(&mut self, fee: u64);
 
-    /// Returns the actual public signals used for the proof verification
+    /// Merges are zero-amount sends (consolidating commitments without a transfer)
+    fn is_merge(&self) -> bool {
+        false
+    }
+
+    /// Returns the `k`-th instance of `signal` (`None` if not part of [`PublicInputs::SIGNAL_LAYOUT`])
+    /// - no montgomery reduction is performed
+    fn signal(&self, signal: Signal, k: usize) -> Option<RawU256>;
+
+    /// Returns the actual public signals used for the proof verification (ordered by [`PublicInputs::SIGNAL_LAYOUT`])
     /// - no montgomery reduction is performed
-    fn public_signals(&self) -> Vec<RawU256>;
+    fn public_signals(&self) -> Vec<RawU256> {
+        // Each input commitment requires its own nullifier-hash signal
+        assert!(
+            self.join_split_inputs().input_commitments.len()
+                <= Self::SIGNAL_LAYOUT.count(Signal::NullifierHash)
+        );
+
+        Self::SIGNAL_LAYOUT
+            .signals()
+            .map(|(signal, k)| self.signal(signal, k).unwrap())
+            .collect()
+    }
 
     fn public_signals_skip_mr(&self) -> Vec<U256> {
         self.public_signals().iter().map(|&p| p.skip_mr()).collect()
@@ -398,6 +485,7 @@ pub struct SendPublicInputs {
     pub join_split: JoinSplitPublicInputs,
     pub recipient_is_associated_token_account: bool,
     pub solana_pay_transfer: bool,
+    #[cfg_attr(feature = "serde", serde(with = "u256_hex"))]
     pub hashed_inputs: U256,
 }
 
@@ -412,6 +500,8 @@ pub fn generate_hashed_inputs(
     metadata: &CommitmentMetadata,
     optional_fee: &OptionalFee,
     memo: &Option<Vec<u8>>,
+    encrypted_note: &Option<EncryptedNote>,
+    earliest_finalize_time: u64,
 ) -> U256 {
     let mut data = recipient.to_vec();
     data.extend(identifier);
@@ -429,6 +519,17 @@ pub fn generate_hashed_inputs(
 
     let mut hash = solana_program::hash::hash(&data).to_bytes();
 
+    // The note is bound in a second round, since appending it to the (variable length) memo would be ambiguous
+    if let Some(encrypted_note) = encrypted_note {
+        hash = solana_program::hash::hashv(&[&hash, encrypted_note]).to_bytes();
+    }
+
+    // The finalization delay is bound in a further round, so requests without a delay keep their hash
+    if earliest_finalize_time > 0 {
+        hash =
+            solana_program::hash::hashv(&[&hash, &earliest_finalize_time.to_le_bytes()]).to_bytes();
+    }
+
     // mask the lower 253 bits
     hash[31] &= 0b11111;
     hash
@@ -445,6 +546,7 @@ pub struct MigratePublicInputs {
 
 impl PublicInputs for SendPublicInputs {
     const PUBLIC_INPUTS_COUNT: usize = SendQuadraVKey::PUBLIC_INPUTS_COUNT as usize;
+    const SIGNAL_LAYOUT: SignalLayout = SendQuadraVKey::SIGNAL_LAYOUT;
 
     fn verify_additional_constraints(&self) -> bool {
         // Maximum commitment-count is 4
@@ -471,44 +573,42 @@ impl PublicInputs for SendPublicInputs {
         &self.join_split
     }
 
-    /// Reference: https://github.com/elusiv-privacy/circuits/blob/master/circuits/main/send_quadra.circom
-    /// Ordering: https://github.com/elusiv-privacy/circuits/blob/master/circuits/send.circom
-    fn public_signals(&self) -> Vec<RawU256> {
-        let mut public_signals = Vec::with_capacity(Self::PUBLIC_INPUTS_COUNT);
-
-        // nullifierHash[nArity]
-        for input_commitment in &self.join_split.input_commitments {
-            public_signals.push(input_commitment.nullifier_hash)
-        }
-        for _ in self.join_split.input_commitments.len()..JOIN_SPLIT_MAX_N_ARITY {
-            public_signals.push(RawU256::ZERO);
-        }
+    fn is_merge(&self) -> bool {
+        self.join_split.amount == 0
+    }
 
-        // root[nArity]
-        for input_commitment in &self.join_split.input_commitments {
-            match input_commitment.root {
-                Some(root) => public_signals.push(root),
-                None => public_signals.push(RawU256::ZERO),
+    /// Reference: https://github.com/elusiv-privacy/circuits/blob/master/circuits/main/send_quadra.circom
+    fn signal(&self, signal: Signal, k: usize) -> Option<RawU256> {
+        // Unused input commitments are zero-padded up to `JOIN_SPLIT_MAX_N_ARITY`
+        let input_commitment = self.join_split.input_commitments.get(k);
+
+        match (signal, k) {
+            (Signal::NullifierHash, k) if k < JOIN_SPLIT_MAX_N_ARITY => Some(
+                input_commitment
+                    .map(|c| c.nullifier_hash)
+                    .unwrap_or(RawU256::ZERO),
+            ),
+            (Signal::Root, k) if k < JOIN_SPLIT_MAX_N_ARITY => Some(
+                input_commitment
+                    .and_then(|c| c.root)
+                    .unwrap_or(RawU256::ZERO),
+            ),
+            (Signal::Amount, 0) => {
+                Some(RawU256(u64_to_u256_skip_mr(self.join_split.total_amount())))
             }
-        }
-        for _ in self.join_split.input_commitments.len()..JOIN_SPLIT_MAX_N_ARITY {
-            public_signals.push(RawU256::ZERO);
-        }
-
-        public_signals.extend(vec![
-            RawU256(u64_to_u256_skip_mr(self.join_split.total_amount())),
-            self.join_split.output_commitment,
-            RawU256(u64_to_u256_skip_mr(
+            (Signal::OutputCommitment, 0) => Some(self.join_split.output_commitment),
+            (Signal::RecentCommitmentIndex, 0) => Some(RawU256(u64_to_u256_skip_mr(
                 self.join_split.recent_commitment_index as u64,
-            )),
-            RawU256(u64_to_u256_skip_mr(self.join_split.fee_version as u64)),
-            RawU256(u64_to_u256_skip_mr(self.join_split.token_id as u64)),
-            RawU256(self.hashed_inputs),
-        ]);
-
-        assert_eq!(public_signals.len(), Self::PUBLIC_INPUTS_COUNT);
-
-        public_signals
+            ))),
+            (Signal::FeeVersion, 0) => Some(RawU256(u64_to_u256_skip_mr(
+                self.join_split.fee_version as u64,
+            ))),
+            (Signal::TokenId, 0) => Some(RawU256(u64_to_u256_skip_mr(
+                self.join_split.token_id as u64,
+            ))),
+            (Signal::HashedInputs, 0) => Some(RawU256(self.hashed_inputs)),
+            _ => None,
+        }
     }
 
     fn set_fee(&mut self, fee: u64) {
@@ -518,6 +618,7 @@ impl PublicInputs for SendPublicInputs {
 
 impl PublicInputs for MigratePublicInputs {
     const PUBLIC_INPUTS_COUNT: usize = MigrateUnaryVKey::PUBLIC_INPUTS_COUNT as usize;
+    const SIGNAL_LAYOUT: SignalLayout = MigrateUnaryVKey::SIGNAL_LAYOUT;
 
     fn verify_additional_constraints(&self) -> bool {
         // commitment-count is 1
@@ -539,20 +640,21 @@ impl PublicInputs for MigratePublicInputs {
     }
 
     /// Reference: https://github.com/elusiv-privacy/circuits/blob/master/circuits/main/migrate_unary.circom
-    /// Ordering: https://github.com/elusiv-privacy/circuits/blob/master/circuits/migrate.circom
-    fn public_signals(&self) -> Vec<RawU256> {
-        vec![
-            self.join_split.input_commitments[0].nullifier_hash,
-            self.join_split.input_commitments[0].root.unwrap(),
-            self.join_split.output_commitment,
-            RawU256(u64_to_u256_skip_mr(
+    fn signal(&self, signal: Signal, k: usize) -> Option<RawU256> {
+        match (signal, k) {
+            (Signal::NullifierHash, 0) => Some(self.join_split.input_commitments[0].nullifier_hash),
+            (Signal::Root, 0) => Some(self.join_split.input_commitments[0].root.unwrap()),
+            (Signal::OutputCommitment, 0) => Some(self.join_split.output_commitment),
+            (Signal::RecentCommitmentIndex, 0) => Some(RawU256(u64_to_u256_skip_mr(
                 self.join_split.recent_commitment_index as u64,
-            )),
-            self.current_nsmt_root,
-            self.next_nsmt_root,
-            // RawU256(u64_to_u256_skip_mr(self.join_split.fee_version as u64)),
-            RawU256(u64_to_u256_skip_mr(self.join_split.total_amount())),
-        ]
+            ))),
+            (Signal::CurrentNsmtRoot, 0) => Some(self.current_nsmt_root),
+            (Signal::NextNsmtRoot, 0) => Some(self.next_nsmt_root),
+            (Signal::Amount, 0) => {
+                Some(RawU256(u64_to_u256_skip_mr(self.join_split.total_amount())))
+            }
+            _ => None,
+        }
     }
 
     fn set_fee(&mut self, fee: u64) {
@@ -576,6 +678,7 @@ pub fn compute_fee_rec<V: crate::proof::vkey::VerifyingKeyInfo, P: PublicInputs>
             0,
             public_inputs.join_split_inputs().amount,
             public_inputs.join_split_inputs().token_id,
+            public_inputs.is_merge(),
             price,
         )
         .unwrap()
@@ -596,7 +699,7 @@ pub fn compute_fee_rec_lamports<V: crate::proof::vkey::VerifyingKeyInfo, P: Publ
     compute_fee_rec::<V, P>(public_inputs, program_fee, &TokenPrice::new_lamports())
 }
 
-pub fn u256_to_le_limbs(v: U256) -> [u64; 4] {
+pub const fn u256_to_le_limbs(v: U256) -> [u64; 4] {
     [
         u64::from_le_bytes(u64_array!(v, 0)),
         u64::from_le_bytes(u64_array!(v, 8)),
@@ -605,6 +708,16 @@ pub fn u256_to_le_limbs(v: U256) -> [u64; 4] {
     ]
 }
 
+pub const fn le_limbs_to_u256(limbs: [u64; 4]) -> U256 {
+    let mut v = [0; 32];
+    let mut i = 0;
+    while i < 32 {
+        v[i] = limbs[i / 8].to_le_bytes()[i % 8];
+        i += 1;
+    }
+    v
+}
+
 /// Can be used to split a number > scalar field modulus (like Curve25519 keys) into two public inputs
 pub fn split_u256_into_limbs(v: U256) -> [U256; 2] {
     let mut a = v;
@@ -625,6 +738,7 @@ mod test {
         fields::{u256_from_str_skip_mr, u256_to_fr_skip_mr},
         processor::MAX_MT_COUNT,
         proof::verifier::proof_from_str,
+        state::note::ENCRYPTED_NOTE_SIZE,
     };
     use ark_bn254::{Fq, Fq2, G1Affine, G2Affine};
     use std::str::FromStr;
@@ -901,6 +1015,68 @@ mod test {
         result.try_to_vec().unwrap();
     }
 
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_send_public_inputs_serde_hex() {
+        let inputs = SendPublicInputs {
+            join_split: JoinSplitPublicInputs {
+                input_commitments: vec![
+                    InputCommitment {
+                        root: Some(RawU256::new([1; 32])),
+                        nullifier_hash: RawU256::new([2; 32]),
+                    },
+                    InputCommitment {
+                        root: None,
+                        nullifier_hash: RawU256::new([3; 32]),
+                    },
+                ],
+                output_commitment: RawU256::new([0xab; 32]),
+                recent_commitment_index: 123,
+                fee_version: 1,
+                amount: 100_000_000,
+                fee: 120_000,
+                optional_fee: OptionalFee {
+                    collector: Pubkey::new_unique(),
+                    amount: 99,
+                },
+                token_id: 2,
+                metadata: CommitmentMetadata::default(),
+            },
+            recipient_is_associated_token_account: true,
+            solana_pay_transfer: false,
+            hashed_inputs: [0x0f; 32],
+        };
+
+        let json = serde_json::to_string(&inputs).unwrap();
+        assert!(json.contains(&format!("\"output_commitment\":\"{}\"", "ab".repeat(32))));
+        assert!(json.contains(&format!("\"hashed_inputs\":\"{}\"", "0f".repeat(32))));
+        assert_eq!(
+            serde_json::from_str::<SendPublicInputs>(&json).unwrap(),
+            inputs
+        );
+
+        // Upper-case hex-strings and byte-arrays are accepted
+        assert_eq!(
+            serde_json::from_str::<RawU256>(&format!("\"{}\"", "AB".repeat(32))).unwrap(),
+            RawU256::new([0xab; 32])
+        );
+        assert_eq!(
+            serde_json::from_str::<RawU256>(&serde_json::to_string(&[0xab; 32]).unwrap()).unwrap(),
+            RawU256::new([0xab; 32])
+        );
+
+        // Invalid lengths and characters
+        for invalid in [
+            format!("\"{}\"", "ab".repeat(31)),
+            format!("\"{}\"", "ab".repeat(33)),
+            format!("\"{}\"", "zz".repeat(32)),
+            serde_json::to_string(&vec![0xab; 31]).unwrap(),
+            serde_json::to_string(&vec![0xab; 33]).unwrap(),
+        ] {
+            assert!(serde_json::from_str::<RawU256>(&invalid).is_err());
+        }
+    }
+
     #[test]
     fn test_migrate_public_inputs_verify() {
         let valid_inputs = MigratePublicInputs {
@@ -1061,7 +1237,9 @@ mod test {
                 is_associated_token_account,
                 &metadata,
                 &optional_fee,
-                &None
+                &None,
+                &None,
+                0,
             ),
             expected
         );
@@ -1081,9 +1259,55 @@ mod test {
                 is_associated_token_account,
                 &metadata,
                 &optional_fee,
-                &memo
+                &memo,
+                &None,
+                0,
             ),
             expected
         );
+
+        // An encrypted note cannot be moved into the memo
+        let encrypted_note = [7; ENCRYPTED_NOTE_SIZE];
+        let hash = |memo: &Option<Vec<u8>>, encrypted_note: &Option<EncryptedNote>| {
+            generate_hashed_inputs(
+                &recipient,
+                &identifier,
+                &iv,
+                &encrypted_owner,
+                &solana_pay_id,
+                is_associated_token_account,
+                &metadata,
+                &optional_fee,
+                memo,
+                encrypted_note,
+                0,
+            )
+        };
+        let note_hash = hash(&memo, &Some(encrypted_note));
+        assert_ne!(note_hash, expected);
+
+        let mut memo_with_note = memo.clone().unwrap();
+        memo_with_note.extend(encrypted_note);
+        assert_ne!(note_hash, hash(&Some(memo_with_note), &None));
+
+        // A finalization delay changes the hash
+        let delayed_hash = |earliest_finalize_time: u64| {
+            generate_hashed_inputs(
+                &recipient,
+                &identifier,
+                &iv,
+                &encrypted_owner,
+                &solana_pay_id,
+                is_associated_token_account,
+                &metadata,
+                &optional_fee,
+                &memo,
+                &None,
+                earliest_finalize_time,
+            )
+        };
+        assert_eq!(delayed_hash(0), expected);
+        assert_ne!(delayed_hash(1), expected);
+        assert_ne!(delayed_hash(1), delayed_hash(2));
     }
 }
@@ -13,14 +13,17 @@ use elusiv::state::program_account::PDAOffset;
 use elusiv::state::queue::{Queue, RingQueue};
 use elusiv::state::{
     fee::FeeAccount,
-    governor::{FeeCollectorAccount, GovernorAccount, PoolAccount},
+    governor::{FeeCollectorAccount, GovernorAccount, PoolAccount, ScheduledFeeUpdate},
     nullifier::{NullifierAccount, NullifierChildAccount},
     program_account::{PDAAccount, PDAAccountData, ProgramAccount, SizedAccount},
     storage::{StorageAccount, MT_COMMITMENT_COUNT},
 };
 use elusiv::token::SPL_TOKEN_COUNT;
-use elusiv_types::split_child_account_data_mut;
-use solana_program::instruction::{AccountMeta, Instruction};
+use elusiv_types::{split_child_account_data_mut, ElusivOption};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
 use solana_program_test::*;
 use solana_sdk::signer::Signer;
 
@@ -129,10 +132,73 @@ async fn test_setup_fee_account() {
 
     test.ix_should_succeed_simple(ElusivInstruction::init_new_fee_version_instruction(
         1,
-        genesis_fee,
+        genesis_fee.clone(),
+        WritableSignerAccount(payer),
+    ))
+    .await;
+
+    // The fee account of a scheduled fee update can only be setup with the scheduled fee
+    let mut scheduled_fee = genesis_fee.clone();
+    scheduled_fee.lamports_per_tx.0 += 1;
+    test.set_pda_account::<GovernorAccount, _>(&elusiv::id(), None, None, |data| {
+        let mut account = GovernorAccount::new(data).unwrap();
+        account.set_scheduled_fee_update(&ElusivOption::Some(ScheduledFeeUpdate {
+            program_fee: scheduled_fee.clone(),
+            activation_slot: u64::MAX,
+        }));
+    })
+    .await;
+
+    test.ix_should_fail_simple(ElusivInstruction::init_new_fee_version_instruction(
+        2,
+        genesis_fee.clone(),
+        WritableSignerAccount(payer),
+    ))
+    .await;
+
+    test.ix_should_succeed_simple(ElusivInstruction::init_new_fee_version_instruction(
+        2,
+        scheduled_fee.clone(),
         WritableSignerAccount(payer),
     ))
     .await;
+
+    pda_account!(fee, FeeAccount, None, Some(2), test);
+    assert_eq!(fee.get_program_fee(), scheduled_fee);
+
+    pda_account!(governor, GovernorAccount, None, None, test);
+    assert_eq!(governor.get_program_fee(), genesis_fee);
+}
+
+#[tokio::test]
+async fn test_set_verification_ttl_slots() {
+    let mut test = start_test_with_setup().await;
+
+    // Only the program authority can set the TTL
+    test.ix_should_fail_simple(ElusivInstruction::set_verification_ttl_slots_instruction(
+        100,
+        SignerAccount(test.payer()),
+    ))
+    .await;
+    assert!(process_as_authority(
+        &mut test,
+        ElusivInstruction::set_verification_ttl_slots_instruction(
+            100,
+            SignerAccount(Pubkey::new_unique()),
+        ),
+    )
+    .await
+    .is_err());
+
+    process_as_authority(
+        &mut test,
+        ElusivInstruction::set_verification_ttl_slots_instruction(100, SignerAccount(elusiv::id())),
+    )
+    .await
+    .unwrap();
+
+    pda_account!(governor, GovernorAccount, None, None, test);
+    assert_eq!(governor.get_verification_ttl_slots(), 100);
 }
 
 #[tokio::test]
@@ -20,6 +20,7 @@ use elusiv::{
             BaseCommitmentHashingAccount, CommitmentHashingAccount, CommitmentQueue,
             CommitmentQueueAccount,
         },
+        commitment_index::CommitmentIndexAccount,
         governor::{FeeCollectorAccount, GovernorAccount, PoolAccount},
         metadata::{CommitmentMetadata, MetadataQueue, MetadataQueueAccount},
         program_account::{PDAAccount, ProgramAccount, SizedAccount},
@@ -97,12 +98,19 @@ async fn test_store_base_commitment_lamports_transfer() {
     let network_fee = fee.base_commitment_network_fee.calc(request.amount);
     let hashing_account_rent = test.rent(BaseCommitmentHashingAccount::SIZE).await;
 
-    client
-        .airdrop(
-            0,
-            request.amount + computation_fee + network_fee - subvention,
-            &mut test,
+    // The client is charged exactly the quoted fee
+    let quote = fee
+        .base_commitment_fee_quote(
+            request.min_batching_rate,
+            request.amount,
+            LAMPORTS_TOKEN_ID,
+            &TokenPrice::new_lamports(),
         )
+        .unwrap();
+    assert_eq!(quote.amount(), computation_fee + network_fee - subvention);
+
+    client
+        .airdrop(0, request.amount + quote.amount(), &mut test)
         .await;
     warden
         .airdrop(0, computation_fee + hashing_account_rent.0, &mut test)
@@ -217,13 +225,22 @@ async fn test_store_base_commitment_token_transfer() {
     );
     let hashing_account_rent = test.rent(BaseCommitmentHashingAccount::SIZE).await;
 
-    client
-        .airdrop(
+    // The client is charged exactly the quoted fee
+    let quote = fee
+        .base_commitment_fee_quote(
+            request.min_batching_rate,
+            request.amount,
             USDC_TOKEN_ID,
-            request.amount + computation_fee_token.amount() + network_fee.amount()
-                - subvention.amount(),
-            &mut test,
+            &price,
         )
+        .unwrap();
+    assert_eq!(
+        quote.amount(),
+        computation_fee_token.amount() + network_fee.amount() - subvention.amount()
+    );
+
+    client
+        .airdrop(USDC_TOKEN_ID, request.amount + quote.amount(), &mut test)
         .await;
     warden
         .airdrop(0, computation_fee.0 + hashing_account_rent.0, &mut test)
@@ -772,9 +789,11 @@ async fn test_single_commitment() {
     let warden = test.new_actor().await;
 
     setup_storage_account(&mut test).await;
+    setup_commitment_index_account(&mut test).await;
     setup_metadata_account(&mut test).await;
 
     let storage_accounts = storage_accounts(&mut test).await;
+    let commitment_index_accounts = commitment_index_accounts(&mut test).await;
     let metadata_accounts = metadata_accounts(&mut test).await;
 
     let metadata = [3; CommitmentMetadata::SIZE];
@@ -855,6 +874,7 @@ async fn test_single_commitment() {
 
     let finalize_ix = ElusivInstruction::finalize_commitment_hash_instruction(
         &writable_user_accounts(&storage_accounts),
+        &writable_user_accounts(&commitment_index_accounts),
     );
 
     let compute_ix = ElusivInstruction::compute_commitment_hash_instruction(
@@ -1016,7 +1036,9 @@ async fn test_commitment_correct_storage_account_insertion() {
     let mut test = start_test_with_setup().await;
 
     setup_storage_account(&mut test).await;
+    setup_commitment_index_account(&mut test).await;
     let storage_accounts = storage_accounts(&mut test).await;
+    let commitment_index_accounts = commitment_index_accounts(&mut test).await;
 
     let len = commitment_hash_computation_instructions(0).len() as u32;
     let commitment_count = 33;
@@ -1035,6 +1057,7 @@ async fn test_commitment_correct_storage_account_insertion() {
 
         test.ix_should_succeed_simple(ElusivInstruction::finalize_commitment_hash_instruction(
             &writable_user_accounts(&storage_accounts),
+            &writable_user_accounts(&commitment_index_accounts),
         ))
         .await;
     }
@@ -1057,9 +1080,11 @@ async fn test_commitment_hash_multiple_commitments_zero_batch() {
     let warden = test.new_actor().await;
 
     setup_storage_account(&mut test).await;
+    setup_commitment_index_account(&mut test).await;
     setup_metadata_account(&mut test).await;
 
     let storage_accounts = storage_accounts(&mut test).await;
+    let commitment_index_accounts = commitment_index_accounts(&mut test).await;
     let metadata_accounts = metadata_accounts(&mut test).await;
 
     let pool = PoolAccount::find(None).0;
@@ -1166,6 +1191,7 @@ async fn test_commitment_hash_multiple_commitments_zero_batch() {
 
         test.ix_should_succeed_simple(ElusivInstruction::finalize_commitment_hash_instruction(
             &writable_user_accounts(&storage_accounts),
+            &writable_user_accounts(&commitment_index_accounts),
         ))
         .await;
 
@@ -1214,9 +1240,11 @@ async fn test_commitment_hash_with_batching_rate(
     let warden = test.new_actor().await;
 
     setup_storage_account(&mut test).await;
+    setup_commitment_index_account(&mut test).await;
     setup_metadata_account(&mut test).await;
 
     let storage_accounts = storage_accounts(&mut test).await;
+    let commitment_index_accounts = commitment_index_accounts(&mut test).await;
     let metadata_accounts = metadata_accounts(&mut test).await;
 
     let pool = PoolAccount::find(None).0;
@@ -1275,6 +1303,7 @@ async fn test_commitment_hash_with_batching_rate(
     for _ in 0..=batching_rate {
         test.ix_should_succeed_simple(ElusivInstruction::finalize_commitment_hash_instruction(
             &writable_user_accounts(&storage_accounts),
+            &writable_user_accounts(&commitment_index_accounts),
         ))
         .await;
     }
@@ -1294,6 +1323,17 @@ async fn test_commitment_hash_with_batching_rate(
     })
     .await;
 
+    // Verify the commitment index
+    commitment_index_account(None, &mut test, |c: &CommitmentIndexAccount| {
+        for (i, request) in requests.iter().enumerate() {
+            assert_eq!(
+                c.lookup(&request.commitment, 0, commitments.len() as u32),
+                Ok(Some(i as u32))
+            );
+        }
+    })
+    .await;
+
     // Verify all metadata
     metadata_account(None, &mut test, |m| {
         for i in 0..requests.len() {
@@ -6,6 +6,7 @@ use elusiv::{
     instruction::ElusivInstruction,
     proof::verifier::{CombinedMillerLoop, FinalExponentiation},
     state::{
+        commitment_index::CommitmentIndexAccount,
         fee::{BasisPointFee, ProgramFee},
         metadata::MetadataAccount,
         nullifier::NullifierAccount,
@@ -50,9 +51,68 @@ pub async fn genesis_fee(test: &mut ElusivProgramTest) -> ProgramFee {
         warden_proof_reward: Lamports(555),
         proof_base_tx_count: (CombinedMillerLoop::TX_COUNT + FinalExponentiation::TX_COUNT + 2)
             as u64,
+        merge_fee_discount: BasisPointFee(0),
+        warden_hash_commitment_reward: Lamports(0),
     }
 }
 
+/// Processes `ix` with the program authority as signer
+///
+/// # Note
+///
+/// The authority is the program-id, which cannot sign transactions.
+/// So `ix` is processed by the program's processor (as registered with `processor!`) directly on the current account states, which are stored afterwards.
+pub async fn process_as_authority(test: &mut ElusivProgramTest, ix: Instruction) -> ProgramResult {
+    let mut accounts = Vec::new();
+    for meta in &ix.accounts {
+        let account = test
+            .context()
+            .banks_client
+            .get_account(meta.pubkey)
+            .await
+            .unwrap()
+            .unwrap_or_default();
+        accounts.push((meta.pubkey, account));
+    }
+
+    let result = {
+        let account_infos: Vec<AccountInfo> = accounts
+            .iter_mut()
+            .zip(&ix.accounts)
+            .map(|((pubkey, account), meta)| {
+                AccountInfo::new(
+                    pubkey,
+                    meta.is_signer,
+                    meta.is_writable,
+                    &mut account.lamports,
+                    &mut account.data,
+                    &account.owner,
+                    account.executable,
+                    account.rent_epoch,
+                )
+            })
+            .collect();
+
+        elusiv::process_instruction(&elusiv::id(), &account_infos, &ix.data)
+    };
+
+    if result.is_ok() {
+        for ((pubkey, account), meta) in accounts.iter().zip(&ix.accounts) {
+            if meta.is_writable {
+                test.set_account(
+                    pubkey,
+                    &account.data,
+                    Lamports(account.lamports),
+                    &account.owner,
+                )
+                .await;
+            }
+        }
+    }
+
+    result
+}
+
 pub async fn setup_initial_pdas(test: &mut ElusivProgramTest) {
     let ixs = initial_single_instance_pdas(test.payer());
     test.tx_should_succeed_simple(&ixs).await;
@@ -63,6 +123,9 @@ pub fn initial_single_instance_pdas(payer: Pubkey) -> Vec<Instruction> {
         ElusivInstruction::setup_governor_account_instruction(WritableSignerAccount(payer)),
         ElusivInstruction::open_single_instance_accounts_instruction(WritableSignerAccount(payer)),
         ElusivInstruction::create_new_accounts_v1_instruction(WritableSignerAccount(payer)),
+        ElusivInstruction::create_new_accounts_v3_instruction(WritableSignerAccount(payer)),
+        ElusivInstruction::create_new_accounts_v4_instruction(WritableSignerAccount(payer)),
+        ElusivInstruction::create_new_accounts_v5_instruction(WritableSignerAccount(payer)),
     ]
 }
 
@@ -106,6 +169,12 @@ setup_parent_account!(
     enable_metadata_child_account_instruction
 );
 
+setup_parent_account!(
+    setup_commitment_index_account,
+    CommitmentIndexAccount,
+    enable_commitment_index_child_account_instruction
+);
+
 pub async fn create_merkle_tree(test: &mut ElusivProgramTest, mt_index: u32) -> Vec<Pubkey> {
     let mut instructions = vec![ElusivInstruction::open_nullifier_account_instruction(
         mt_index,
@@ -140,6 +209,7 @@ macro_rules! child_accounts_getter_simple {
 
 child_accounts_getter_simple!(storage_accounts, StorageAccount);
 child_accounts_getter_simple!(metadata_accounts, MetadataAccount);
+child_accounts_getter_simple!(commitment_index_accounts, CommitmentIndexAccount);
 
 pub async fn nullifier_accounts(test: &mut ElusivProgramTest, mt_index: u32) -> Vec<Pubkey> {
     let mut data = test.data(&NullifierAccount::find(Some(mt_index)).0).await;
@@ -187,7 +257,9 @@ pub(crate) use pda_account;
 #[allow(unused_imports)]
 pub(crate) use queue;
 
-use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, pubkey::Pubkey,
+};
 use solana_program_test::processor;
 use spl_associated_token_account::instruction::create_associated_token_account;
 
@@ -240,6 +312,7 @@ macro_rules! parent_account {
 parent_account!(storage_account, StorageAccount);
 parent_account!(nullifier_account, NullifierAccount);
 parent_account!(metadata_account, MetadataAccount);
+parent_account!(commitment_index_account, CommitmentIndexAccount);
 
 pub fn u256_from_str(str: &str) -> U256 {
     fr_to_u256_le(&ark_bn254::Fr::from_str(str).unwrap())
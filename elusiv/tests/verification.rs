@@ -21,7 +21,9 @@ use elusiv::state::governor::{FeeCollectorAccount, PoolAccount};
 use elusiv::state::metadata::{CommitmentMetadata, MetadataQueue};
 use elusiv::state::nullifier::{NullifierAccount, NullifierMap, NULLIFIERS_PER_ACCOUNT};
 use elusiv::state::program_account::{PDAAccount, PDAAccountData, ProgramAccount, SizedAccount};
-use elusiv::state::proof::{VerificationAccount, VerificationState};
+use elusiv::state::proof::{
+    verification_account_offsets, NullifierDuplicateAccount, VerificationAccount, VerificationState,
+};
 use elusiv::state::queue::RingQueue;
 use elusiv::state::storage::{empty_root_raw, StorageAccount, MT_HEIGHT};
 use elusiv::state::vkey::{VKeyAccount, VKeyAccountEager};
@@ -259,6 +261,8 @@ impl ExtraData {
             &self.metadata,
             &self.optional_fee,
             &self.memo,
+            &None,
+            0,
         )
     }
 
@@ -290,9 +294,16 @@ async fn init_verification_simple(
             [0, 1],
             ProofRequest::Send(public_inputs.clone()),
             false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
             WritableSignerAccount(test.payer()),
             WritableUserAccount(public_inputs.join_split.nullifier_duplicate_pda().0),
+            UserAccount(Pubkey::new_unique()),
             UserAccount(Pubkey::new_from_array(identifier)),
+            WritableUserAccount(test.payer()),
             &user_accounts(&[nullifier_accounts[0]]),
             &[],
         ),
@@ -301,6 +312,7 @@ async fn init_verification_simple(
             0,
             *proof,
             SignerAccount(test.payer()),
+            UserAccount(test.payer()),
         ),
     ])
     .await
@@ -456,7 +468,7 @@ async fn test_init_proof_signers() {
     let commitment_hash_fee = fee.commitment_hash_computation_fee(0);
 
     let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
-    let nullifier_duplicate_account_rent = test.rent(PDAAccountData::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
     warden
         .airdrop(
             LAMPORTS_TOKEN_ID,
@@ -479,9 +491,16 @@ async fn test_init_proof_signers() {
             [0, 1],
             ProofRequest::Send(request.public_inputs.clone()),
             false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(nullifier_duplicate_account),
             UserAccount(Pubkey::new_unique()),
+            UserAccount(Pubkey::new_unique()),
+            WritableUserAccount(warden.pubkey),
             &user_accounts(&[nullifier_accounts[0]]),
             &[],
         ),
@@ -495,6 +514,7 @@ async fn test_init_proof_signers() {
             0,
             WritableSignerAccount(warden2.pubkey),
             WritableUserAccount(warden2.pubkey),
+            UserAccount(warden.pubkey),
             WritableUserAccount(pool),
             WritableUserAccount(fee_collector),
             UserAccount(system_program::id()),
@@ -510,6 +530,7 @@ async fn test_init_proof_signers() {
             0,
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(warden.pubkey),
+            UserAccount(warden.pubkey),
             WritableUserAccount(pool),
             WritableUserAccount(fee_collector),
             UserAccount(system_program::id()),
@@ -529,6 +550,7 @@ async fn test_init_proof_signers() {
                 .try_into()
                 .unwrap(),
             SignerAccount(warden2.pubkey),
+            UserAccount(warden.pubkey),
         ),
         &[&warden2.keypair],
     )
@@ -542,6 +564,7 @@ async fn test_init_proof_signers() {
                 .try_into()
                 .unwrap(),
             SignerAccount(warden.pubkey),
+            UserAccount(warden.pubkey),
         ),
         &[&warden.keypair],
     )
@@ -564,7 +587,7 @@ async fn test_init_proof_lamports() {
     let nullifier_duplicate_account = request.public_inputs.join_split.nullifier_duplicate_pda().0;
 
     let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
-    let nullifier_duplicate_account_rent = test.rent(PDAAccountData::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
     warden
         .airdrop(
             LAMPORTS_TOKEN_ID,
@@ -587,9 +610,16 @@ async fn test_init_proof_lamports() {
                 [0, 1],
                 ProofRequest::Send(request.public_inputs),
                 skip_nullifier_pda,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(nullifier_duplicate_account),
+                UserAccount(VerificationAccount::find_with_pubkey(warden.pubkey, Some(0)).0),
                 UserAccount(Pubkey::new_unique()),
+                WritableUserAccount(warden.pubkey),
                 &user_accounts(&[nullifier_accounts[0]]),
                 &[],
             )
@@ -654,7 +684,7 @@ async fn test_init_proof_lamports() {
         Some(0),
         |data| {
             let mut verification_account = VerificationAccount::new(data).unwrap();
-            let mut request = verification_account.get_request();
+            let mut request = verification_account.get_request().unwrap();
             if let ProofRequest::Send(public_inputs) = &mut request {
                 public_inputs.join_split.amount = rent_exemption.0 - 1;
             }
@@ -667,6 +697,7 @@ async fn test_init_proof_lamports() {
         0,
         WritableSignerAccount(warden.pubkey),
         WritableUserAccount(warden.pubkey),
+        UserAccount(warden.pubkey),
         WritableUserAccount(pool),
         WritableUserAccount(fee_collector),
         UserAccount(system_program::id()),
@@ -744,7 +775,7 @@ async fn test_init_proof_token() {
     let nullifier_accounts = nullifier_accounts(&mut test, 0).await;
 
     let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
-    let nullifier_duplicate_account_rent = test.rent(PDAAccountData::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
     warden
         .airdrop(
             LAMPORTS_TOKEN_ID,
@@ -770,9 +801,16 @@ async fn test_init_proof_token() {
             [0, 1],
             ProofRequest::Send(request.public_inputs.clone()),
             false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(nullifier_duplicate_account),
             UserAccount(Pubkey::new_unique()),
+            UserAccount(Pubkey::new_unique()),
+            WritableUserAccount(warden.pubkey),
             &user_accounts(&[nullifier_accounts[0]]),
             &[],
         ),
@@ -802,6 +840,7 @@ async fn test_init_proof_token() {
             0,
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(warden.get_token_account(USDC_TOKEN_ID)),
+            UserAccount(warden.pubkey),
             WritableUserAccount(pool_account),
             WritableUserAccount(fee_collector_account),
             UserAccount(sol_price_account),
@@ -869,7 +908,7 @@ async fn test_finalize_proof_lamports() {
             .calc(request.public_inputs.join_split.amount),
     );
     let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
-    let nullifier_duplicate_account_rent = test.rent(PDAAccountData::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
 
     warden
         .airdrop(
@@ -891,9 +930,16 @@ async fn test_finalize_proof_lamports() {
                 [0, 1],
                 ProofRequest::Send(request.public_inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(nullifier_duplicate_account),
+                UserAccount(Pubkey::new_unique()),
                 UserAccount(Pubkey::new_from_array(extra_data.identifier)),
+                WritableUserAccount(warden.pubkey),
                 &user_accounts(&[nullifier_accounts[0]]),
                 &[],
             ),
@@ -902,6 +948,7 @@ async fn test_finalize_proof_lamports() {
                 0,
                 request.proof,
                 SignerAccount(warden.pubkey),
+                UserAccount(warden.pubkey),
             ),
         ],
         &[&warden.keypair],
@@ -959,10 +1006,15 @@ async fn test_finalize_proof_lamports() {
     let finalize_verification_transfer_lamports_instruction =
         ElusivInstruction::finalize_verification_transfer_lamports_instruction(
             0,
+            UserAccount(warden.pubkey),
             WritableSignerAccount(warden.pubkey),
+            WritableUserAccount(warden.pubkey),
             WritableUserAccount(recipient),
             WritableUserAccount(optional_fee_collector.pubkey),
             WritableUserAccount(nullifier_duplicate_account),
+            WritableUserAccount(warden.pubkey),
+            WritableUserAccount(warden.pubkey),
+            WritableUserAccount(warden.pubkey),
         );
 
     // IMPORTANT: Pool already contains subvention (so we airdrop commitment_hash_fee - subvention)
@@ -1056,6 +1108,220 @@ async fn test_finalize_proof_lamports() {
     );
 }
 
+#[tokio::test]
+async fn test_transfer_verification_ownership() {
+    let mut test = start_verification_test().await;
+    let warden = test.new_actor().await;
+    let new_owner = test.new_actor().await;
+    let nullifier_accounts = nullifier_accounts(&mut test, 0).await;
+    let fee = genesis_fee(&mut test).await;
+    setup_vkey_account::<SendQuadraVKey>(&mut test).await;
+
+    let mut request = send_request(0);
+    let extra_data = ExtraData::default();
+    request.public_inputs.hashed_inputs = extra_data.hash();
+    request.update_fee_lamports(&fee);
+
+    let pool = PoolAccount::find(None).0;
+    let fee_collector = FeeCollectorAccount::find(None).0;
+    let nullifier_duplicate_account = request.public_inputs.join_split.nullifier_duplicate_pda().0;
+
+    let public_inputs = request.public_inputs.public_signals_skip_mr();
+    let input_preparation_tx_count =
+        prepare_public_inputs_instructions(&public_inputs, SendQuadraVKey::public_inputs_count())
+            .len();
+    let subvention = fee.proof_subvention;
+    let proof_verification_fee = fee.proof_verification_computation_fee(input_preparation_tx_count);
+    let commitment_hash_fee = fee.commitment_hash_computation_fee(0);
+    let network_fee = Lamports(
+        fee.proof_network_fee
+            .calc(request.public_inputs.join_split.amount),
+    );
+    let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
+
+    warden
+        .airdrop(
+            LAMPORTS_TOKEN_ID,
+            verification_account_rent.0
+                + nullifier_duplicate_account_rent.0
+                + commitment_hash_fee.0,
+            &mut test,
+        )
+        .await;
+    new_owner
+        .airdrop(
+            LAMPORTS_TOKEN_ID,
+            verification_account_rent.0 + nullifier_duplicate_account_rent.0,
+            &mut test,
+        )
+        .await;
+    test.airdrop_lamports(&fee_collector, subvention.0).await;
+
+    test.tx_should_succeed(
+        &[
+            ElusivInstruction::init_verification_instruction(
+                0,
+                SendQuadraVKey::VKEY_ID,
+                [0, 1],
+                ProofRequest::Send(request.public_inputs.clone()),
+                false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
+                WritableSignerAccount(warden.pubkey),
+                WritableUserAccount(nullifier_duplicate_account),
+                UserAccount(Pubkey::new_unique()),
+                UserAccount(Pubkey::new_from_array(extra_data.identifier)),
+                WritableUserAccount(warden.pubkey),
+                &user_accounts(&[nullifier_accounts[0]]),
+                &[],
+            ),
+            ElusivInstruction::init_verification_transfer_fee_sol_instruction(0, warden.pubkey),
+        ],
+        &[&warden.keypair],
+    )
+    .await;
+
+    assert_eq!(0, warden.lamports(&mut test).await);
+
+    let transfer_ownership_instruction =
+        ElusivInstruction::transfer_verification_ownership_instruction(
+            0,
+            WritableSignerAccount(warden.pubkey),
+            WritableSignerAccount(new_owner.pubkey),
+            UserAccount(new_owner.pubkey),
+            UserAccount(warden.pubkey),
+            UserAccount(nullifier_duplicate_account),
+            UserAccount(Pubkey::new_unique()),
+        );
+
+    // Missing signature of the current owner
+    let mut ix = transfer_ownership_instruction.clone();
+    ix.accounts[0].is_signer = false;
+    test.ix_should_fail(ix, &[&new_owner.keypair]).await;
+
+    // Missing signature of the new owner
+    let mut ix = transfer_ownership_instruction.clone();
+    ix.accounts[1].is_signer = false;
+    test.ix_should_fail(ix, &[&warden.keypair]).await;
+
+    // Transfer before the proof has been submitted
+    test.ix_should_succeed(
+        transfer_ownership_instruction.clone(),
+        &[&warden.keypair, &new_owner.keypair],
+    )
+    .await;
+
+    // `new_owner` reimbursed the rent of the verification-account and the nullifier-duplicate-account to `warden`
+    assert_eq!(
+        verification_account_rent.0 + nullifier_duplicate_account_rent.0,
+        warden.lamports(&mut test).await
+    );
+    assert_eq!(0, new_owner.lamports(&mut test).await);
+
+    // The previous owner cannot submit the proof anymore
+    let init_proof_instruction = |fee_payer: Pubkey| {
+        ElusivInstruction::init_verification_proof_instruction(
+            0,
+            request.proof,
+            SignerAccount(fee_payer),
+            UserAccount(warden.pubkey),
+        )
+    };
+    test.ix_should_fail(init_proof_instruction(warden.pubkey), &[&warden.keypair])
+        .await;
+    test.ix_should_succeed(
+        init_proof_instruction(new_owner.pubkey),
+        &[&new_owner.keypair],
+    )
+    .await;
+
+    // The previous owner cannot transfer the verification anymore
+    test.ix_should_fail(
+        transfer_ownership_instruction,
+        &[&warden.keypair, &new_owner.keypair],
+    )
+    .await;
+
+    skip_computation(warden.pubkey, 0, true, &mut test).await;
+
+    let recipient = Pubkey::new_from_array(extra_data.recipient);
+    let identifier = Pubkey::new_from_array(extra_data.identifier);
+    let reference = Pubkey::new_from_array(extra_data.reference);
+
+    test.airdrop_lamports(
+        &pool,
+        request.public_inputs.join_split.amount + commitment_hash_fee.0 - subvention.0
+            + proof_verification_fee.0
+            + network_fee.0,
+    )
+    .await;
+
+    let finalize_instructions = |fee_payer: Pubkey| {
+        vec![
+            request_compute_units(1_400_000),
+            ElusivInstruction::finalize_verification_send_instruction(
+                0,
+                FinalizeSendData {
+                    total_amount: request.public_inputs.join_split.total_amount(),
+                    encrypted_owner: extra_data.encrypted_owner,
+                    iv: extra_data.iv,
+                    ..Default::default()
+                },
+                false,
+                UserAccount(recipient),
+                UserAccount(identifier),
+                UserAccount(reference),
+                UserAccount(warden.pubkey),
+            ),
+            ElusivInstruction::finalize_verification_insert_nullifier_instruction(
+                0,
+                UserAccount(warden.pubkey),
+                Some(0),
+                &writable_user_accounts(&[nullifier_accounts[0]]),
+            ),
+            ElusivInstruction::finalize_verification_transfer_lamports_instruction(
+                0,
+                UserAccount(warden.pubkey),
+                WritableSignerAccount(fee_payer),
+                WritableUserAccount(fee_payer),
+                WritableUserAccount(recipient),
+                WritableUserAccount(Pubkey::new_unique()),
+                WritableUserAccount(nullifier_duplicate_account),
+                WritableUserAccount(fee_payer),
+                WritableUserAccount(fee_payer),
+                WritableUserAccount(fee_payer),
+            ),
+        ]
+    };
+
+    // The previous owner cannot finalize the verification
+    test.tx_should_fail(&finalize_instructions(warden.pubkey), &[&warden.keypair])
+        .await;
+
+    test.tx_should_succeed(
+        &finalize_instructions(new_owner.pubkey),
+        &[&new_owner.keypair],
+    )
+    .await;
+
+    // The rewards and the reimbursed rent flow to `new_owner`
+    assert_eq!(
+        commitment_hash_fee.0
+            + proof_verification_fee.0
+            + verification_account_rent.0
+            + nullifier_duplicate_account_rent.0,
+        new_owner.lamports(&mut test).await
+    );
+    assert_eq!(
+        verification_account_rent.0 + nullifier_duplicate_account_rent.0,
+        warden.lamports(&mut test).await
+    );
+}
+
 #[tokio::test]
 async fn test_finalize_proof_token() {
     let mut test = start_verification_test().await;
@@ -1138,7 +1404,7 @@ async fn test_finalize_proof_token() {
             .calc(request.public_inputs.join_split.amount),
     );
     let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
-    let nullifier_duplicate_account_rent = test.rent(PDAAccountData::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
 
     let pool_account = program_token_account_address::<PoolAccount>(USDC_TOKEN_ID, None).unwrap();
     let fee_collector_account =
@@ -1164,9 +1430,16 @@ async fn test_finalize_proof_token() {
                 [0, 1],
                 ProofRequest::Send(request.public_inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(nullifier_duplicate_account),
+                UserAccount(Pubkey::new_unique()),
                 UserAccount(Pubkey::new_from_array(extra_data.identifier)),
+                WritableUserAccount(warden.pubkey),
                 &user_accounts(&[nullifier_accounts[0]]),
                 &[],
             ),
@@ -1174,6 +1447,7 @@ async fn test_finalize_proof_token() {
                 0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(warden.get_token_account(USDC_TOKEN_ID)),
+                UserAccount(warden.pubkey),
                 WritableUserAccount(pool_account),
                 WritableUserAccount(fee_collector_account),
                 UserAccount(sol_price_account),
@@ -1184,6 +1458,7 @@ async fn test_finalize_proof_token() {
                 0,
                 request.proof,
                 SignerAccount(warden.pubkey),
+                UserAccount(warden.pubkey),
             ),
         ],
         &[&warden.keypair],
@@ -1232,15 +1507,20 @@ async fn test_finalize_proof_token() {
     let finalize_verification_transfer_token_instruction =
         ElusivInstruction::finalize_verification_transfer_token_instruction(
             0,
+            UserAccount(warden.pubkey),
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(warden.get_token_account(USDC_TOKEN_ID)),
+            WritableUserAccount(warden.pubkey),
             WritableUserAccount(recipient_token_account),
             UserAccount(recipient_token_account),
             WritableUserAccount(pool_account),
             WritableUserAccount(fee_collector_account),
             WritableUserAccount(optional_fee_collector.get_token_account(USDC_TOKEN_ID)),
             WritableUserAccount(nullifier_duplicate_account),
+            WritableUserAccount(warden.pubkey),
             UserAccount(spl_token::id()),
+            WritableUserAccount(warden.pubkey),
+            WritableUserAccount(warden.pubkey),
         );
 
     // IMPORTANT: Pool already contains subvention (so we airdrop commitment_hash_fee - subvention)
@@ -1378,9 +1658,16 @@ async fn test_finalize_proof_skip_nullifier_pda() {
                 [0, 1],
                 ProofRequest::Send(request.public_inputs.clone()),
                 skip_nullifier_pda,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(nullifier_duplicate_account),
+                UserAccount(VerificationAccount::find_with_pubkey(warden.pubkey, Some(0)).0),
                 UserAccount(Pubkey::new_from_array(extra_data.identifier)),
+                WritableUserAccount(warden.pubkey),
                 &user_accounts(&[nullifier_accounts[0]]),
                 &[],
             ),
@@ -1392,6 +1679,7 @@ async fn test_finalize_proof_skip_nullifier_pda() {
                 v_index,
                 request.proof,
                 SignerAccount(warden.pubkey),
+                UserAccount(warden.pubkey),
             ),
         ]
     };
@@ -1442,10 +1730,15 @@ async fn test_finalize_proof_skip_nullifier_pda() {
             ),
             ElusivInstruction::finalize_verification_transfer_lamports_instruction(
                 v_index,
+                UserAccount(warden.pubkey),
                 WritableSignerAccount(warden.pubkey),
+                WritableUserAccount(warden.pubkey),
                 WritableUserAccount(recipient.pubkey),
                 WritableUserAccount(Pubkey::new_unique()),
                 WritableUserAccount(nullifier_duplicate_account),
+                WritableUserAccount(warden.pubkey),
+                WritableUserAccount(warden.pubkey),
+                WritableUserAccount(warden.pubkey),
             ),
         ];
 
@@ -1523,9 +1816,16 @@ async fn test_finalize_proof_commitment_index() {
             [0, 1],
             ProofRequest::Send(request.public_inputs.clone()),
             false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(nullifier_duplicate_account),
+            UserAccount(Pubkey::new_unique()),
             UserAccount(Pubkey::new_from_array(extra_data.identifier)),
+            WritableUserAccount(warden.pubkey),
             &user_accounts(&[nullifier_accounts[0]]),
             &[],
         ),
@@ -1534,6 +1834,7 @@ async fn test_finalize_proof_commitment_index() {
             0,
             request.proof,
             SignerAccount(warden.pubkey),
+            UserAccount(warden.pubkey),
         ),
     ];
 
@@ -1567,10 +1868,15 @@ async fn test_finalize_proof_commitment_index() {
             ),
             ElusivInstruction::finalize_verification_transfer_lamports_instruction(
                 0,
+                UserAccount(warden.pubkey),
                 WritableSignerAccount(warden.pubkey),
+                WritableUserAccount(warden.pubkey),
                 WritableUserAccount(recipient.pubkey),
                 WritableUserAccount(Pubkey::new_unique()),
                 WritableUserAccount(nullifier_duplicate_account),
+                WritableUserAccount(warden.pubkey),
+                WritableUserAccount(warden.pubkey),
+                WritableUserAccount(warden.pubkey),
             ),
         ]
     };
@@ -1650,7 +1956,7 @@ async fn test_associated_token_account() {
     let nullifier_accounts = nullifier_accounts(&mut test, 0).await;
 
     let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
-    let nullifier_duplicate_account_rent = test.rent(PDAAccountData::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
     let token_account_rent = test.rent(spl_token::state::Account::LEN).await;
     let token_account_rent_token = token_account_rent
         .into_token(&price, USDC_TOKEN_ID)
@@ -1678,9 +1984,16 @@ async fn test_associated_token_account() {
             [0, 1],
             ProofRequest::Send(request.clone().public_inputs),
             false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(nullifier_duplicate_account),
+            UserAccount(Pubkey::new_unique()),
             UserAccount(Pubkey::new_from_array(extra_data.identifier)),
+            WritableUserAccount(warden.pubkey),
             &user_accounts(&[nullifier_accounts[0]]),
             &[],
         ),
@@ -1745,15 +2058,20 @@ async fn test_associated_token_account() {
             ),
             ElusivInstruction::finalize_verification_transfer_token_instruction(
                 0,
+                UserAccount(warden.pubkey),
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(warden.get_token_account(USDC_TOKEN_ID)),
+                WritableUserAccount(warden.pubkey),
                 WritableUserAccount(recipient),
                 UserAccount(recipient_wallet),
                 WritableUserAccount(pool_account),
                 WritableUserAccount(fee_collector_account),
                 WritableUserAccount(Pubkey::new_unique()),
                 WritableUserAccount(nullifier_duplicate_account),
+                WritableUserAccount(warden.pubkey),
                 UserAccount(mint),
+                WritableUserAccount(warden.pubkey),
+                WritableUserAccount(warden.pubkey),
             ),
         ]
     };
@@ -1884,7 +2202,7 @@ async fn test_compute_proof_verifcation_invalid_proof() {
     let subvention = fee.proof_subvention;
     let commitment_hash_fee = fee.commitment_hash_computation_fee(0);
     let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
-    let nullifier_duplicate_account_rent = test.rent(PDAAccountData::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
 
     warden
         .airdrop(
@@ -1905,9 +2223,16 @@ async fn test_compute_proof_verifcation_invalid_proof() {
                 [0, 1],
                 ProofRequest::Send(request.public_inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(nullifier_duplicate_account),
                 UserAccount(Pubkey::new_unique()),
+                UserAccount(Pubkey::new_unique()),
+                WritableUserAccount(warden.pubkey),
                 &user_accounts(&[nullifier_accounts[0]]),
                 &[],
             ),
@@ -1916,6 +2241,7 @@ async fn test_compute_proof_verifcation_invalid_proof() {
                 0,
                 request.proof,
                 SignerAccount(warden.pubkey),
+                UserAccount(warden.pubkey),
             ),
         ],
         &[&warden.keypair],
@@ -1928,30 +2254,35 @@ async fn test_compute_proof_verifcation_invalid_proof() {
         ElusivInstruction::compute_verification_instruction(
             0,
             SendQuadraVKey::VKEY_ID,
+            WritableSignerAccount(warden.pubkey),
             UserAccount(warden.pubkey),
             &[UserAccount(vkey_sub_account)],
         ),
         ElusivInstruction::compute_verification_instruction(
             0,
             SendQuadraVKey::VKEY_ID,
+            WritableSignerAccount(warden.pubkey),
             UserAccount(warden.pubkey),
             &[UserAccount(vkey_sub_account)],
         ),
         ElusivInstruction::compute_verification_instruction(
             0,
             SendQuadraVKey::VKEY_ID,
+            WritableSignerAccount(warden.pubkey),
             UserAccount(warden.pubkey),
             &[UserAccount(vkey_sub_account)],
         ),
         ElusivInstruction::compute_verification_instruction(
             0,
             SendQuadraVKey::VKEY_ID,
+            WritableSignerAccount(warden.pubkey),
             UserAccount(warden.pubkey),
             &[UserAccount(vkey_sub_account)],
         ),
         ElusivInstruction::compute_verification_instruction(
             0,
             SendQuadraVKey::VKEY_ID,
+            WritableSignerAccount(warden.pubkey),
             UserAccount(warden.pubkey),
             &[UserAccount(vkey_sub_account)],
         ),
@@ -1959,7 +2290,8 @@ async fn test_compute_proof_verifcation_invalid_proof() {
 
     // Input preparation
     for _ in 0..input_preparation_tx_count {
-        test.tx_should_succeed_simple(&instructions).await;
+        test.tx_should_succeed(&instructions, &[&warden.keypair])
+            .await;
     }
 
     pda_account!(
@@ -1974,7 +2306,8 @@ async fn test_compute_proof_verifcation_invalid_proof() {
 
     // Combined miller loop
     for _ in 0..CombinedMillerLoop::TX_COUNT {
-        test.tx_should_succeed_simple(&instructions).await;
+        test.tx_should_succeed(&instructions, &[&warden.keypair])
+            .await;
     }
 
     pda_account!(
@@ -1989,7 +2322,8 @@ async fn test_compute_proof_verifcation_invalid_proof() {
 
     // Final exponentiation
     for _ in 0..FinalExponentiation::TX_COUNT {
-        test.tx_should_succeed_simple(&instructions).await;
+        test.tx_should_succeed(&instructions, &[&warden.keypair])
+            .await;
     }
 
     pda_account!(
@@ -2003,6 +2337,130 @@ async fn test_compute_proof_verifcation_invalid_proof() {
     assert_eq!(v_acc.get_step(), VerificationStep::FinalExponentiation);
 }
 
+#[tokio::test]
+async fn test_force_settle_unknown_state() {
+    let mut test = start_verification_test().await;
+    let warden = test.new_actor().await;
+    let nullifier_accounts = nullifier_accounts(&mut test, 0).await;
+    setup_vkey_account::<SendQuadraVKey>(&mut test).await;
+
+    let fee = genesis_fee(&mut test).await;
+    let mut request = send_request(0);
+    request.update_fee_lamports(&fee);
+
+    let verification_account_rent = test.rent(VerificationAccount::SIZE).await;
+    let nullifier_duplicate_account_rent = test.rent(NullifierDuplicateAccount::SIZE).await;
+    warden
+        .airdrop(
+            LAMPORTS_TOKEN_ID,
+            verification_account_rent.0 + nullifier_duplicate_account_rent.0,
+            &mut test,
+        )
+        .await;
+
+    test.ix_should_succeed(
+        ElusivInstruction::init_verification_instruction(
+            0,
+            SendQuadraVKey::VKEY_ID,
+            [0, 1],
+            ProofRequest::Send(request.public_inputs.clone()),
+            false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
+            WritableSignerAccount(warden.pubkey),
+            WritableUserAccount(request.public_inputs.join_split.nullifier_duplicate_pda().0),
+            UserAccount(VerificationAccount::find_with_pubkey(warden.pubkey, Some(0)).0),
+            UserAccount(Pubkey::new_unique()),
+            WritableUserAccount(warden.pubkey),
+            &user_accounts(&[nullifier_accounts[0]]),
+            &[],
+        ),
+        &[&warden.keypair],
+    )
+    .await;
+
+    // A state introduced by a newer program version
+    test.set_pda_account::<VerificationAccount, _>(
+        &elusiv::id(),
+        Some(warden.pubkey),
+        Some(0),
+        |data| {
+            data[verification_account_offsets::STATE.start] = VerificationState::Closed as u8 + 1;
+        },
+    )
+    .await;
+
+    let force_settle_instruction = |authority: Pubkey| {
+        ElusivInstruction::force_settle_unknown_state_instruction(
+            0,
+            SignerAccount(authority),
+            UserAccount(warden.pubkey),
+        )
+    };
+    let set_verification_ttl_slots_instruction = |verification_ttl_slots: u64| {
+        ElusivInstruction::set_verification_ttl_slots_instruction(
+            verification_ttl_slots,
+            SignerAccount(elusiv::id()),
+        )
+    };
+
+    // The verification TTL is disabled by default
+    assert!(
+        process_as_authority(&mut test, force_settle_instruction(elusiv::id()))
+            .await
+            .is_err()
+    );
+
+    process_as_authority(&mut test, set_verification_ttl_slots_instruction(100))
+        .await
+        .unwrap();
+
+    // Timelock
+    assert!(
+        process_as_authority(&mut test, force_settle_instruction(elusiv::id()))
+            .await
+            .is_err()
+    );
+
+    pda_account!(
+        v_acc,
+        VerificationAccount,
+        Some(warden.pubkey),
+        Some(0),
+        test
+    );
+    let creation_slot = v_acc.get_creation_slot();
+    test.context().warp_to_slot(creation_slot + 101).unwrap();
+
+    // Only the program authority can settle unknown states
+    test.ix_should_fail(force_settle_instruction(warden.pubkey), &[&warden.keypair])
+        .await;
+
+    process_as_authority(&mut test, force_settle_instruction(elusiv::id()))
+        .await
+        .unwrap();
+
+    pda_account!(
+        v_acc,
+        VerificationAccount,
+        Some(warden.pubkey),
+        Some(0),
+        test
+    );
+    assert_eq!(v_acc.get_state().unwrap(), VerificationState::ProofSetup);
+    assert_eq!(v_acc.get_is_verified().option(), Some(false));
+
+    // Known states are not affected
+    assert!(
+        process_as_authority(&mut test, force_settle_instruction(elusiv::id()))
+            .await
+            .is_err()
+    );
+}
+
 #[tokio::test]
 async fn test_enforced_finalization_order() {
     let mut test = start_verification_test().await;
@@ -2055,10 +2513,15 @@ async fn test_enforced_finalization_order() {
     let finalize_verification_transfer_lamports_instruction =
         ElusivInstruction::finalize_verification_transfer_lamports_instruction(
             0,
+            UserAccount(test.payer()),
             WritableSignerAccount(test.payer()),
+            WritableUserAccount(test.payer()),
             WritableUserAccount(extra_data.recipient()),
             WritableUserAccount(Pubkey::new_unique()),
             WritableUserAccount(nullifier_duplicate_account),
+            WritableUserAccount(test.payer()),
+            WritableUserAccount(test.payer()),
+            WritableUserAccount(test.payer()),
         );
 
     set_verification_state(test.payer(), 0, VerificationState::ProofSetup, &mut test).await;
@@ -2201,10 +2664,15 @@ async fn nullifier_finalization_test(number_of_start_nullifiers: u64, input_comm
     instructions.push(
         ElusivInstruction::finalize_verification_transfer_lamports_instruction(
             0,
+            UserAccount(test.payer()),
             WritableSignerAccount(test.payer()),
+            WritableUserAccount(test.payer()),
             WritableUserAccount(recipient),
             WritableUserAccount(Pubkey::new_unique()),
             WritableUserAccount(nullifier_duplicate_account),
+            WritableUserAccount(test.payer()),
+            WritableUserAccount(test.payer()),
+            WritableUserAccount(test.payer()),
         ),
     );
 
@@ -2222,6 +2690,211 @@ async fn test_finalization_nullifier_insertions() {
     }
 }
 
+#[tokio::test]
+async fn test_finalization_nullifier_insertions_two_trees() {
+    let mut test = start_verification_test().await;
+    setup_vkey_account::<SendQuadraVKey>(&mut test).await;
+    let nullifier_accounts = [
+        nullifier_accounts(&mut test, 0).await,
+        nullifier_accounts(&mut test, 1).await,
+    ];
+    let pool = PoolAccount::find(None).0;
+    let fee_collector = FeeCollectorAccount::find(None).0;
+
+    // MT 0 is closed, MT 1 is active
+    let closed_root = RawU256::new(u256_from_str_skip_mr("123"));
+    test.set_pda_account::<StorageAccount, _>(&elusiv::id(), None, None, |data| {
+        let mut storage_account = StorageAccount::new(data).unwrap();
+        storage_account.set_trees_count(&1);
+    })
+    .await;
+    test.set_pda_account::<NullifierAccount, _>(&elusiv::id(), None, Some(0), |data| {
+        let mut nullifier_account = NullifierAccount::new(data).unwrap();
+        nullifier_account.set_root(&closed_root.reduce());
+    })
+    .await;
+
+    // `JOIN_SPLIT_MAX_N_ARITY` input commitments with distinct nullifier-hashes, three in MT 0 and one in MT 1
+    let nullifier_hash = |i: u64| RawU256::new(u64_to_u256_skip_mr(u64::MAX - i));
+    let input_commitments = vec![
+        InputCommitment {
+            root: Some(closed_root),
+            nullifier_hash: nullifier_hash(0),
+        },
+        InputCommitment {
+            root: None,
+            nullifier_hash: nullifier_hash(1),
+        },
+        InputCommitment {
+            root: Some(empty_root_raw()),
+            nullifier_hash: nullifier_hash(2),
+        },
+        InputCommitment {
+            root: None,
+            nullifier_hash: nullifier_hash(3),
+        },
+    ];
+    assert_eq!(input_commitments.len(), JOIN_SPLIT_MAX_N_ARITY);
+
+    let extra_data = ExtraData::default();
+    let proof = send_request(0).proof;
+    let mut public_inputs = SendPublicInputs {
+        join_split: JoinSplitPublicInputs {
+            input_commitments,
+            output_commitment: RawU256::new(u256_from_str_skip_mr(
+                "685960310506634721912121951341598678325833230508240750559904196809564625591",
+            )),
+            recent_commitment_index: 0,
+            fee_version: 0,
+            amount: LAMPORTS_PER_SOL * 123,
+            fee: 0,
+            optional_fee: OptionalFee::default(),
+            token_id: 0,
+            metadata: CommitmentMetadata::default(),
+        },
+        recipient_is_associated_token_account: false,
+        hashed_inputs: extra_data.hash(),
+        solana_pay_transfer: false,
+    };
+    compute_fee_rec_lamports::<SendQuadraVKey, _>(
+        &mut public_inputs,
+        &genesis_fee(&mut test).await,
+    );
+    let nullifier_duplicate_account = public_inputs.join_split.nullifier_duplicate_pda().0;
+    let identifier = Pubkey::new_from_array(extra_data.identifier);
+    let reference = Pubkey::new_from_array(extra_data.reference);
+    let recipient = Pubkey::new_from_array(extra_data.recipient);
+
+    test.airdrop_lamports(&fee_collector, LAMPORTS_PER_SOL)
+        .await;
+    test.airdrop_lamports(&pool, LAMPORTS_PER_SOL * 1000).await;
+
+    test.tx_should_succeed_simple(&[
+        ElusivInstruction::init_verification_instruction(
+            0,
+            SendQuadraVKey::VKEY_ID,
+            [0, 1],
+            ProofRequest::Send(public_inputs.clone()),
+            false,
+            [0; 16],
+            false,
+            ElusivOption::None,
+            ElusivOption::None,
+            0,
+            WritableSignerAccount(test.payer()),
+            WritableUserAccount(nullifier_duplicate_account),
+            UserAccount(Pubkey::new_unique()),
+            UserAccount(identifier),
+            WritableUserAccount(test.payer()),
+            &user_accounts(&[nullifier_accounts[0][0]]),
+            &user_accounts(&[nullifier_accounts[1][0]]),
+        ),
+        ElusivInstruction::init_verification_transfer_fee_sol_instruction(0, test.payer()),
+        ElusivInstruction::init_verification_proof_instruction(
+            0,
+            proof,
+            SignerAccount(test.payer()),
+            UserAccount(test.payer()),
+        ),
+    ])
+    .await;
+    skip_computation(test.payer(), 0, true, &mut test).await;
+    set_verification_state(test.payer(), 0, VerificationState::ProofSetup, &mut test).await;
+
+    let mut instructions = vec![
+        request_compute_units(1_400_000),
+        ElusivInstruction::finalize_verification_send_instruction(
+            0,
+            FinalizeSendData {
+                total_amount: public_inputs.join_split.total_amount(),
+                encrypted_owner: extra_data.encrypted_owner,
+                iv: extra_data.iv,
+                ..Default::default()
+            },
+            false,
+            UserAccount(recipient),
+            UserAccount(identifier),
+            UserAccount(reference),
+            UserAccount(test.payer()),
+        ),
+    ];
+
+    // The nullifier-hashes are inserted MT by MT
+    for (tree_index, nullifier_hashes) in [
+        (
+            0,
+            vec![nullifier_hash(0), nullifier_hash(1), nullifier_hash(3)],
+        ),
+        (1, vec![nullifier_hash(2)]),
+    ] {
+        pda_account!(
+            nullifier_account,
+            NullifierAccount,
+            None,
+            Some(tree_index),
+            test
+        );
+        let nullifier_accounts = &nullifier_accounts[tree_index as usize];
+        let nullifier_hashes: Vec<U256> = nullifier_hashes.iter().map(|n| n.reduce()).collect();
+
+        for nullifier_hash in &nullifier_hashes {
+            let child_account_index = nullifier_account.find_child_account_index(nullifier_hash);
+
+            instructions.push(
+                ElusivInstruction::finalize_verification_insert_nullifier_instruction(
+                    0,
+                    UserAccount(test.payer()),
+                    Some(tree_index),
+                    &writable_user_accounts(
+                        &nullifier_accounts[child_account_index..child_account_index + 1],
+                    ),
+                ),
+            );
+        }
+
+        let number_of_movement_instructions =
+            nullifier_account.number_of_movement_instructions(&nullifier_hashes);
+        for i in 0..number_of_movement_instructions {
+            instructions.push(
+                ElusivInstruction::finalize_verification_insert_nullifier_instruction(
+                    0,
+                    UserAccount(test.payer()),
+                    Some(tree_index),
+                    &writable_user_accounts(&[nullifier_accounts[i + 1]]),
+                ),
+            );
+        }
+    }
+
+    instructions.push(
+        ElusivInstruction::finalize_verification_transfer_lamports_instruction(
+            0,
+            UserAccount(test.payer()),
+            WritableSignerAccount(test.payer()),
+            WritableUserAccount(test.payer()),
+            WritableUserAccount(recipient),
+            WritableUserAccount(Pubkey::new_unique()),
+            WritableUserAccount(nullifier_duplicate_account),
+            WritableUserAccount(test.payer()),
+            WritableUserAccount(test.payer()),
+            WritableUserAccount(test.payer()),
+        ),
+    );
+
+    test.tx_should_succeed_simple(&instructions).await;
+
+    pda_account!(nullifier_account0, NullifierAccount, None, Some(0), test);
+    pda_account!(nullifier_account1, NullifierAccount, None, Some(1), test);
+    assert_eq!(nullifier_account0.get_nullifier_hash_count(), 3);
+    assert_eq!(nullifier_account1.get_nullifier_hash_count(), 1);
+    assert!(
+        test.account_does_not_exist(
+            &VerificationAccount::find_with_pubkey(test.payer(), Some(0)).0
+        )
+        .await
+    );
+}
+
 async fn finalize_instructions(
     test: &mut ElusivProgramTest,
     request: &FullSendRequest,
@@ -2255,10 +2928,15 @@ async fn finalize_instructions(
         ),
         ElusivInstruction::finalize_verification_transfer_lamports_instruction(
             0,
+            UserAccount(*signer),
             WritableSignerAccount(*signer),
+            WritableUserAccount(*signer),
             WritableUserAccount(extra_data.recipient()),
             WritableUserAccount(Pubkey::new_unique()),
             WritableUserAccount(request.public_inputs.join_split.nullifier_duplicate_pda().0),
+            WritableUserAccount(*signer),
+            WritableUserAccount(*signer),
+            WritableUserAccount(*signer),
         ),
     ]
 }
@@ -2660,9 +3338,16 @@ async fn test_solana_pay_tokens() {
                 [0, 1],
                 ProofRequest::Send(request.public_inputs.clone()),
                 false,
+                [0; 16],
+                false,
+                ElusivOption::None,
+                ElusivOption::None,
+                0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(nullifier_duplicate_account),
+                UserAccount(Pubkey::new_unique()),
                 UserAccount(Pubkey::new_from_array(extra_data.identifier)),
+                WritableUserAccount(warden.pubkey),
                 &user_accounts(&[nullifier_accounts[0]]),
                 &[],
             ),
@@ -2670,6 +3355,7 @@ async fn test_solana_pay_tokens() {
                 0,
                 WritableSignerAccount(warden.pubkey),
                 WritableUserAccount(warden.get_token_account(USDC_TOKEN_ID)),
+                UserAccount(warden.pubkey),
                 WritableUserAccount(pool_account),
                 WritableUserAccount(fee_collector_account),
                 UserAccount(sol_price_account),
@@ -2680,6 +3366,7 @@ async fn test_solana_pay_tokens() {
                 0,
                 request.proof,
                 SignerAccount(warden.pubkey),
+                UserAccount(warden.pubkey),
             ),
         ],
         &[&warden.keypair],
@@ -2712,15 +3399,20 @@ async fn test_solana_pay_tokens() {
         ),
         ElusivInstruction::finalize_verification_transfer_token_instruction(
             0,
+            UserAccount(warden.pubkey),
             WritableSignerAccount(warden.pubkey),
             WritableUserAccount(warden.get_token_account(USDC_TOKEN_ID)),
+            WritableUserAccount(warden.pubkey),
             WritableUserAccount(recipient_token_account),
             UserAccount(recipient_token_account),
             WritableUserAccount(pool_account),
             WritableUserAccount(fee_collector_account),
             WritableUserAccount(Pubkey::new_unique()),
             WritableUserAccount(nullifier_duplicate_account),
+            WritableUserAccount(warden.pubkey),
             UserAccount(spl_token::id()),
+            WritableUserAccount(warden.pubkey),
+            WritableUserAccount(warden.pubkey),
         ),
     ];
 
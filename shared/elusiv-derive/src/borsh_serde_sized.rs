@@ -43,7 +43,7 @@ pub fn impl_borsh_serde_sized(ast: &syn::DeriveInput) -> TokenStream {
                 quote! {
                     match variant_index {
                         #len
-                        _ => panic!()
+                        _ => 0
                     }
                 }
             };
@@ -11,6 +11,10 @@ const MAP_ATTR: &str = "map";
 
 const RESERVED_ATTR_IDENTS: [&str; 4] = [ACC_ATTR, SYS_ATTR, PDA_ATTR, MAP_ATTR];
 
+/// Field types that are included in the short description of an instruction
+const DESCRIPTION_FIELD_TYPES: [&str; 9] =
+    ["u8", "u16", "u32", "u64", "i8", "i16", "i32", "i64", "bool"];
+
 enum AttrType {
     Docs,
     Any,
@@ -24,6 +28,8 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
     let mut functions = quote!();
     let mut abi_functions = quote!();
     let mut variant_indices = quote!();
+    let mut descriptions = quote!();
+    let mut accounts_metas = quote!();
 
     if let syn::Data::Enum(e) = &ast.data {
         for (var_index, var) in e.variants.clone().iter().enumerate() {
@@ -44,21 +50,36 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
             let mut fields = quote!();
             let mut signature = quote!();
 
+            // Processor signature with the mutability of each program account (all other types are inferred)
+            let mut access_signature = quote!();
+
             // Instruction creation
             let mut fields_with_type = quote!();
             let mut user_accounts = quote!();
             let mut instruction_accounts = quote!();
+            let mut accounts_meta = quote!();
 
             let mut docs = quote!();
             let mut other_attrs = quote!();
             let mut current_attr_type = AttrType::Docs;
 
+            // Short description (only primitive fields are displayed)
+            let mut description_fields = quote!();
+            let mut description_args = Vec::new();
+
+            let mut fields_access_signature = quote!();
             for field in &var.fields {
                 let field_name = field.ident.clone().unwrap();
                 let ty = field.ty.clone();
 
                 fields.extend(quote! { #field_name, });
                 fields_with_type.extend(quote! { #field_name: #ty, });
+                fields_access_signature.extend(quote! { _, });
+
+                if DESCRIPTION_FIELD_TYPES.contains(&ty.to_token_stream().to_string().as_str()) {
+                    description_fields.extend(quote! { #field_name, });
+                    description_args.push(field_name);
+                }
             }
 
             // Account attributes
@@ -123,6 +144,7 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                     .collect();
 
                 let mut account: TokenStream = sub_attrs[0].0.parse().unwrap();
+                let account_ident = account.clone();
                 let mut account_init = Vec::new(); // used for creating the instruction objects with the abi-feature
 
                 accounts.extend(quote! {
@@ -159,6 +181,15 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                 // `AccountInfo`?
                 let as_account_info = contains_key(&sub_attrs, "account_info");
 
+                // Writable program accounts are passed as `&mut` and need to be mutated by the processor function
+                if !ignore {
+                    if attr_name == PDA_ATTR && is_writable && !as_account_info {
+                        access_signature.extend(quote! { &mut _, });
+                    } else {
+                        access_signature.extend(quote! { _, });
+                    }
+                }
+
                 let mut_token = if is_writable {
                     quote! { mut }
                 } else {
@@ -189,6 +220,12 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                         account_init.push(quote!{
                             accounts.push(solana_program::instruction::AccountMeta::#account_init_fn(#account.0, #is_signer));
                         });
+                        accounts_meta.extend(quote!{
+                            let #account = elusiv_types::accounts::#user_account_type(
+                                user_accounts.next().expect("Missing user account")
+                            );
+                            accounts.push(solana_program::instruction::AccountMeta::#account_init_fn(#account.0, #is_signer));
+                        });
                     }
 
                     // System program `AccountInfo` (usage: <name> <key = ..>)
@@ -204,6 +241,9 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                         account_init.push(quote!{
                             accounts.push(solana_program::instruction::AccountMeta::#account_init_fn(#key, #is_signer));
                         });
+                        accounts_meta.extend(quote!{
+                            accounts.push(solana_program::instruction::AccountMeta::#account_init_fn(#key, #is_signer));
+                        });
                     }
 
                     // PDA accounts (usage: <name> <AccountType> <pda_offset: u32 = ..>? <account_info>? <include_child_accounts>? <ownership>)
@@ -228,6 +268,27 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                             contains_key(&sub_attrs, "include_child_accounts");
 
                         let skip_abi = contains_key(&sub_attrs, "skip_abi");
+                        if skip_abi {
+                            // The PDA seeds are not part of the instruction data
+                            accounts_meta.extend(quote!{
+                                let #account_ident = user_accounts.next().expect("Missing user account");
+                                accounts.push(solana_program::instruction::AccountMeta::#account_init_fn(#account_ident, #is_signer));
+                            });
+                        } else {
+                            let pda_pubkey = match &pda_pubkey {
+                                Some(pda_pubkey) => quote! { Some(#pda_pubkey) },
+                                None => quote! { None },
+                            };
+                            accounts_meta.extend(quote!{
+                                accounts.push(
+                                    solana_program::instruction::AccountMeta::#account_init_fn(
+                                        <#ty as elusiv_types::accounts::PDAAccount>::find_with_program_id(program_id, #pda_pubkey, #pda_offset).0,
+                                        #is_signer
+                                    )
+                                );
+                            });
+                        }
+
                         if skip_abi {
                             let offset_ident: TokenStream =
                                 format!("{}_pda_offset", sub_attrs[0].0).parse().unwrap();
@@ -313,11 +374,19 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                             accounts.extend(check_pda);
                         }
 
+                        // Writable accounts are migrated to the current data layout version
+                        let version_check = if is_writable {
+                            quote! { elusiv_types::accounts::VersionedAccount::migrate_if_needed(&mut #account)?; }
+                        } else {
+                            quote! { elusiv_types::accounts::VersionedAccount::verify_version(&#account)?; }
+                        };
+
                         if include_child_accounts {
                             // ParentAccount with arbitrary number of child-accounts
                             accounts.extend(quote!{
                                 let acc_data = &mut #account.data.borrow_mut()[..];
                                 let mut #account = <#ty as elusiv_types::accounts::ProgramAccount>::new(acc_data)?;
+                                #version_check
 
                                 let child_accounts = <#ty as elusiv_types::accounts::ParentAccount>::find_child_accounts(
                                     &#account,
@@ -353,12 +422,14 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                             accounts.extend(quote!{
                                 let acc_data = &mut #account.data.borrow_mut()[..];
                                 let #mut_token #account = <#ty as elusiv_types::accounts::ProgramAccount>::new(acc_data)?;
+                                #version_check
                             });
                             account = quote! { &mut #account };
                         } else {
                             accounts.extend(quote!{
                                 let acc_data = &mut #account.data.borrow_mut()[..];
                                 let #mut_token #account = <#ty as elusiv_types::accounts::ProgramAccount>::new(acc_data)?;
+                                #version_check
                             });
                             account = quote! { &#account };
                         }
@@ -403,10 +474,37 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
                 },
             });
 
+            let description_format = description_args
+                .iter()
+                .map(|field_name| format!("{field_name}={{}}"))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let description = if description_args.is_empty() {
+                let description_format = ident.to_string();
+                quote! { String::from(#description_format) }
+            } else {
+                let description_format = format!("{ident}({description_format})");
+                quote! { format!(#description_format, #(#description_args),*) }
+            };
+            accounts_metas.extend(quote! {
+                #other_attrs
+                #ast_ident::#ident { #fields } => {
+                    #accounts_meta
+                }
+            });
+
+            descriptions.extend(quote! {
+                #other_attrs
+                #ast_ident::#ident { #description_fields .. } => #description,
+            });
+
             functions.extend(quote!{
                 #docs
                 #other_attrs
                 fn #fn_name(accounts: &[solana_program::account_info::AccountInfo], #fields_with_type) -> solana_program::entrypoint::ProgramResult {
+                    // Fails to compile if the processor function only reads a writable program account (which should be declared read-only instead)
+                    let _: fn(#access_signature #fields_access_signature) -> _ = processor::#fn_name;
+
                     let mut account_info_iter = &mut accounts.iter();
                     #accounts
                     processor::#fn_name(#signature #fields)
@@ -449,6 +547,38 @@ pub fn impl_elusiv_instruction(ast: &syn::DeriveInput) -> proc_macro2::TokenStre
             #[cfg(feature = "elusiv-client")]
             impl #ast_ident {
                 #abi_functions
+
+                /// The [`solana_program::instruction::AccountMeta`]s of all accounts read by the instruction
+                /// - PDAs are derived for `program_id` from the instruction data
+                /// - `user_accounts` are all non-PDA, non-system accounts (and `skip_abi` PDAs) in their instruction order
+                /// - child-accounts of parent-accounts are not included
+                #[allow(unused_variables)]
+                pub fn accounts_meta(
+                    &self,
+                    program_id: &solana_program::pubkey::Pubkey,
+                    user_accounts: &[solana_program::pubkey::Pubkey],
+                ) -> Vec<solana_program::instruction::AccountMeta> {
+                    let mut accounts = Vec::new();
+                    let mut user_accounts = user_accounts.iter().copied();
+
+                    // Owned copy of the instruction data (for the PDA seeds)
+                    let instruction = <#ast_ident as borsh::BorshDeserialize>::try_from_slice(
+                        &borsh::BorshSerialize::try_to_vec(self).unwrap()
+                    ).unwrap();
+
+                    match instruction {
+                        #accounts_metas
+                    }
+
+                    accounts
+                }
+
+                /// Short single-line representation of the instruction (containing only the primitive fields)
+                pub fn short_description(&self) -> String {
+                    match self {
+                        #descriptions
+                    }
+                }
             }
 
         }
@@ -81,7 +81,7 @@ pub fn enum_variant_index(input: proc_macro::TokenStream) -> proc_macro::TokenSt
 }
 
 /// Derives the [`PDAAccount`] trait
-#[proc_macro_derive(PDAAccount)]
+#[proc_macro_derive(PDAAccount, attributes(pda_version))]
 pub fn pda_account(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
     impl_pda_account(&ast).into()
@@ -24,6 +24,16 @@ pub fn impl_pda_account(ast: &syn::DeriveInput) -> TokenStream {
     let first_pubkey: TokenStream = format!("{:?}", first_pubkey.to_bytes()).parse().unwrap();
     let ident_str = ident_str.as_str();
 
+    // Optional data layout version (syntax: `#[pda_version(version)]`)
+    let current_version = ast
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("pda_version"))
+        .map(|attr| {
+            let version: syn::LitInt = attr.parse_args().unwrap();
+            quote! { const CURRENT_VERSION: u8 = #version; }
+        });
+
     if let syn::Data::Struct(_s) = &ast.data {
         // TODO: The first field always has to be [`PDAAccountData`] (serialization also needs to ensure this order)
 
@@ -32,6 +42,7 @@ pub fn impl_pda_account(ast: &syn::DeriveInput) -> TokenStream {
                 const PROGRAM_ID: solana_program::pubkey::Pubkey = crate::PROGRAM_ID;
                 const SEED: &'static [u8] = &#pda_seed_tokens;
                 const FIRST_PDA: (solana_program::pubkey::Pubkey, u8) = (solana_program::pubkey::Pubkey::new_from_array(#first_pubkey), #first_bump);
+                #current_version
 
                 #[cfg(feature = "elusiv-client")]
                 const IDENT: &'static str = #ident_str;
@@ -128,6 +128,13 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
     let mut eager_defs = quote!();
     let mut eager_init = quote!();
     let mut use_eager_type = false;
+    let mut pda_version = quote!();
+    let mut migration = quote! { Ok(()) };
+    let mut offsets = quote!();
+    let mut offset_fields = quote!();
+    let mut offset_checks = quote!();
+    let mut previous_offset: Option<TokenStream> = None;
+    let mut field_sizes = Vec::new();
 
     // 'a lifetime for the `ProgramAccount` impl
     let program_account_lifetime = quote!('a);
@@ -238,6 +245,18 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
                 use_eager_type = true;
             }
 
+            // Sets the data layout version of new accounts
+            "version" => {
+                let version = attr.value;
+                pda_version = quote! { #[pda_version(#version)] };
+            }
+
+            // Function upgrading the data layout of an account by one version (syntax: `fn(&mut Self, from_version: u8) -> ProgramResult`)
+            "migration" => {
+                let migration_fn = attr.value;
+                migration = quote! { #migration_fn(self, from_version) };
+            }
+
             any => panic!("Invalid attribute '{}'", any),
         }
     }
@@ -400,7 +419,7 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
 
                 let ty = array.elem.clone().into_token_stream();
                 let len = array.len.clone();
-                let size = quote! { <#ty as elusiv_types::bytes::BorshSerDeSized>::SIZE * #len };
+                let size = quote! { <#ty as elusiv_types::bytes::BorshSerDeSized>::SIZE * (#len) };
                 sizes.push(size.clone());
 
                 fields_split.extend(quote! {
@@ -435,6 +454,62 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
             }
             _ => panic!("Invalid field type '{:?}' for '{:?}'", ty, field_ident),
         }
+
+        // Byte range of the field
+        // - the sizes are evaluated in the scope of the account (not in the offsets module, whose consts could shadow imported names)
+        let account_ident = &ast.ident;
+        let field_index = field_sizes.len();
+        let field_size = sizes.last().unwrap().clone();
+        let element_size = match ty {
+            Type::Array(array) => {
+                let ty = array.elem.clone().into_token_stream();
+                quote! { <#ty as elusiv_types::bytes::BorshSerDeSized>::SIZE }
+            }
+            _ => field_size.clone(),
+        };
+        field_sizes.push(quote! { (#field_size, #element_size) });
+
+        let offset_ident: TokenStream = field_ident.to_string().to_uppercase().parse().unwrap();
+        let offset_start = match &previous_offset {
+            Some(previous) => quote! { #previous.end },
+            None => quote! { 0 },
+        };
+        let field_name = field_ident.to_string();
+
+        offsets.extend(quote! {
+            #doc
+            pub const #offset_ident: core::ops::Range<usize> = #offset_start..#offset_start + super::#account_ident::FIELD_SIZES[#field_index].0;
+        });
+        offset_fields.extend(quote! {
+            (#field_name, #offset_ident),
+        });
+
+        if !custom_field {
+            offset_checks.extend(quote! {
+                let start = account.#field_ident.as_ptr() as usize - base;
+                assert_eq!(start..start + account.#field_ident.len(), offsets::#offset_ident);
+            });
+        }
+
+        if let Type::Array(array) = ty {
+            let len = array.len.clone();
+
+            offsets.extend(quote! {
+                /// Byte range of the element at `index`
+                pub const fn #field_ident(index: usize) -> core::ops::Range<usize> {
+                    let size = super::#account_ident::FIELD_SIZES[#field_index].1;
+                    let start = #offset_ident.start + index * size;
+                    start..start + size
+                }
+            });
+
+            offset_checks.extend(quote! {
+                assert_eq!(offsets::#field_ident(0).start, offsets::#offset_ident.start);
+                assert_eq!(offsets::#field_ident(#len - 1).end, offsets::#offset_ident.end);
+            });
+        }
+
+        previous_offset = Some(offset_ident);
     }
 
     let account_size_test: TokenStream =
@@ -449,6 +524,17 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
         }
     });
     let anonymous_lifetimes = lifetimes.as_anonymous_lifetimes();
+    let field_count = field_sizes.len();
+    let offsets_ident: TokenStream = format!("{}_offsets", to_snake_case(&ident.to_string()))
+        .parse()
+        .unwrap();
+    let offsets_doc = format!(
+        " Byte ranges of the fields of [`{}`] in the raw account data (e.g. for RPC data slices)",
+        ident
+    );
+    let offsets_test: TokenStream = format!("test_{}_offsets", to_snake_case(&ident.to_string()))
+        .parse()
+        .unwrap();
 
     let eager_type = if use_eager_type {
         quote! {
@@ -484,12 +570,24 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
     quote! {
         #struct_attrs
         #[derive(elusiv_derive::PDAAccount)]
+        #pda_version
         #vis struct #ident < #lifetimes > {
             #field_defs
         }
 
         impl < #lifetimes > #ident < #lifetimes > {
             #fns
+
+            /// Sizes of all fields (and of their elements for arrays) in the order of the data layout
+            const FIELD_SIZES: [(usize, usize); #field_count] = [#(#field_sizes),*];
+
+            /// Creates a zeroed account (backed by a leaked buffer, which keeps the borrow alive)
+            #[cfg(test)]
+            #[allow(dead_code)]
+            pub fn zeroed() -> Self {
+                let data = Box::leak(vec![0; <Self as elusiv_types::accounts::SizedAccount>::SIZE].into_boxed_slice());
+                <Self as elusiv_types::accounts::ProgramAccount>::new(data).unwrap()
+            }
         }
 
         #impls
@@ -506,6 +604,23 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
             }
         }
 
+        impl < #lifetimes > elusiv_types::accounts::VersionedAccount < #program_account_lifetime > for #ident < #lifetimes > {
+            fn get_pda_version(&self) -> u8 {
+                <elusiv_types::accounts::PDAAccountData as borsh::BorshDeserialize>::try_from_slice(self.pda_data).unwrap().version
+            }
+
+            fn set_pda_version(&mut self, version: u8) {
+                let mut pda_data = <elusiv_types::accounts::PDAAccountData as borsh::BorshDeserialize>::try_from_slice(self.pda_data).unwrap();
+                pda_data.version = version;
+                let mut slice = &mut self.pda_data[..];
+                borsh::BorshSerialize::serialize(&pda_data, &mut slice).unwrap();
+            }
+
+            fn migrate(&mut self, from_version: u8) -> solana_program::entrypoint::ProgramResult {
+                #migration
+            }
+        }
+
         impl < #lifetimes > elusiv_types::accounts::SizedAccount for #ident < #lifetimes > {
             const SIZE: usize = #account_size;
         }
@@ -521,10 +636,65 @@ pub fn impl_elusiv_account(ast: &syn::DeriveInput, attrs: TokenStream) -> TokenS
             }
         }
 
+        #[doc = #offsets_doc]
+        ///
+        /// # Note
+        ///
+        /// Any change of these ranges requires incrementing the layout version of the program.
+        #[allow(dead_code)]
+        #vis mod #offsets_ident {
+            #offsets
+
+            /// All fields in the order of the data layout
+            pub const FIELDS: &[(&str, core::ops::Range<usize>)] = &[#offset_fields];
+        }
+
+        // Test to verify the offsets to match the data layout of the account
+        #[cfg(test)]
+        mod #offsets_test {
+            use super::*;
+            use super::#offsets_ident as offsets;
+
+            #[test]
+            fn #offsets_test() {
+                let mut data = vec![0; <#ident as elusiv_types::accounts::SizedAccount>::SIZE];
+                let base = data.as_ptr() as usize;
+                let account = <#ident as elusiv_types::accounts::ProgramAccount>::new(&mut data).unwrap();
+
+                #offset_checks
+
+                // All accounts lead with the same `PDAAccountData`
+                assert_eq!(offsets::PDA_DATA, elusiv_types::accounts::pda_account_data_offsets::PDA_DATA);
+
+                let mut end = 0;
+                for (_, range) in offsets::FIELDS {
+                    assert_eq!(range.start, end);
+                    end = range.end;
+                }
+                assert_eq!(end, <#ident as elusiv_types::accounts::SizedAccount>::SIZE);
+            }
+        }
+
         #eager_type
     }
 }
 
+/// Converts an `UpperCamelCase` identifier into `snake_case`
+fn to_snake_case(ident: &str) -> String {
+    let mut s = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                s.push('_');
+            }
+            s.extend(c.to_lowercase());
+        } else {
+            s.push(c);
+        }
+    }
+    s
+}
+
 /// Matches attributes with the syntac `ident: value, ..` with value being a `TokenStream`
 fn match_attrs(tree: &[TokenTree]) -> Vec<ElusivAccountAttr> {
     match tree {
@@ -18,7 +18,7 @@ use syn::{parse_macro_input, DeriveInput};
 ///
 /// # Notes
 ///
-/// Automatically also derives [`elusiv_types::PDAAccount`]
+/// Automatically also derives [`elusiv_types::PDAAccount`] and implements [`elusiv_types::accounts::VersionedAccount`]
 #[proc_macro_attribute]
 pub fn elusiv_account(
     args: proc_macro::TokenStream,
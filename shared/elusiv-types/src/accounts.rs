@@ -40,7 +40,7 @@ pub trait ChildAccount: Sized {
         }
         config.is_in_use = true;
 
-        let mut slice = &mut config_data[..ChildAccountConfig::SIZE];
+        let mut slice = &mut config_data[child_account_config_offsets::CHILD_ACCOUNT_CONFIG];
         borsh::BorshSerialize::serialize(&config, &mut slice).unwrap();
 
         Ok(())
@@ -49,7 +49,8 @@ pub trait ChildAccount: Sized {
 
 /// Splits the accounts data into the [`ChildAccountConfig`] and inner-data
 pub fn split_child_account_data(data: &[u8]) -> Result<(&[u8], &[u8]), ProgramError> {
-    let (config, inner_data) = data.split_at(ChildAccountConfig::SIZE);
+    let (config, inner_data) =
+        data.split_at(child_account_config_offsets::CHILD_ACCOUNT_CONFIG.end);
     Ok((config, inner_data))
 }
 
@@ -57,7 +58,8 @@ pub fn split_child_account_data(data: &[u8]) -> Result<(&[u8], &[u8]), ProgramEr
 pub fn split_child_account_data_mut(
     data: &mut [u8],
 ) -> Result<(&mut [u8], &mut [u8]), ProgramError> {
-    let (config, inner_data) = data.split_at_mut(ChildAccountConfig::SIZE);
+    let (config, inner_data) =
+        data.split_at_mut(child_account_config_offsets::CHILD_ACCOUNT_CONFIG.end);
     Ok((config, inner_data))
 }
 
@@ -66,6 +68,20 @@ pub struct ChildAccountConfig {
     pub is_in_use: bool,
 }
 
+/// Byte ranges of the [`ChildAccountConfig`] leading the data of every [`ChildAccount`]
+pub mod child_account_config_offsets {
+    use super::ChildAccountConfig;
+    use crate::bytes::BorshSerDeSized;
+    use core::ops::Range;
+
+    pub const IS_IN_USE: Range<usize> = 0..<bool as BorshSerDeSized>::SIZE;
+
+    /// Byte range of the whole [`ChildAccountConfig`] (followed by the inner data)
+    pub const CHILD_ACCOUNT_CONFIG: Range<usize> = 0..IS_IN_USE.end;
+
+    const _: () = assert!(CHILD_ACCOUNT_CONFIG.end == ChildAccountConfig::SIZE);
+}
+
 pub const fn child_account_size(inner_size: usize) -> usize {
     inner_size + ChildAccountConfig::SIZE
 }
@@ -226,6 +242,9 @@ pub trait PDAAccount {
     /// The PDA associated with no [`Pubkey`] and the [`None`] [`PDAOffset`]
     const FIRST_PDA: (Pubkey, u8);
 
+    /// The data layout version of new accounts (stored in [`PDAAccountData::version`])
+    const CURRENT_VERSION: u8 = 0;
+
     #[cfg(feature = "elusiv-client")]
     const IDENT: &'static str;
 
@@ -255,6 +274,19 @@ pub trait PDAAccount {
         }
     }
 
+    /// Finds the PDA for an arbitrary `program_id` (instead of [`PDAAccount::PROGRAM_ID`])
+    #[cfg(feature = "elusiv-client")]
+    fn find_with_program_id(
+        program_id: &Pubkey,
+        pubkey: Option<Pubkey>,
+        offset: PDAOffset,
+    ) -> (Pubkey, u8) {
+        let seed = Self::seeds(Self::SEED, pubkey, offset);
+        let seed: Vec<&[u8]> = seed.iter().map(|x| &x[..]).collect();
+
+        Pubkey::find_program_address(&seed, program_id)
+    }
+
     fn create(offset: PDAOffset, bump: u8) -> Result<Pubkey, ProgramError> {
         if offset.is_none() {
             return Ok(Self::FIRST_PDA.0);
@@ -301,13 +333,21 @@ pub trait PDAAccount {
     ///
     /// # Note
     ///
-    /// This requires the account to store [`PDAAccountData`] as the leading data
-    fn get_bump(account: &AccountInfo) -> u8 {
-        account.data.borrow()[0]
+    /// This requires the account to store [`PDAAccountData`] as the leading data.
+    /// A closed (drained) account without data results in [`ProgramError::UninitializedAccount`].
+    fn get_bump(account: &AccountInfo) -> Result<u8, ProgramError> {
+        match account
+            .data
+            .borrow()
+            .get(pda_account_data_offsets::BUMP_SEED.start)
+        {
+            Some(&bump) => Ok(bump),
+            None => Err(ProgramError::UninitializedAccount),
+        }
     }
 
     fn verify_account(account: &AccountInfo, offset: PDAOffset) -> ProgramResult {
-        if Self::create(offset, Self::get_bump(account))? != *account.key {
+        if Self::create(offset, Self::get_bump(account)?)? != *account.key {
             return Err(ProgramError::InvalidSeeds);
         }
 
@@ -319,7 +359,7 @@ pub trait PDAAccount {
         pubkey: Pubkey,
         offset: PDAOffset,
     ) -> ProgramResult {
-        if Self::create_with_pubkey(pubkey, offset, Self::get_bump(account))? != *account.key {
+        if Self::create_with_pubkey(pubkey, offset, Self::get_bump(account)?)? != *account.key {
             return Err(ProgramError::InvalidSeeds);
         }
 
@@ -327,6 +367,49 @@ pub trait PDAAccount {
     }
 }
 
+/// A [`PDAAccount`] with multiple instances per [`Pubkey`], distinguished by their [`PDAOffset`]
+pub trait MultiInstancePDAAccount: PDAAccount {
+    /// Instances use the offsets `0..MAX_INSTANCES`
+    const MAX_INSTANCES: u64;
+}
+
+/// Enumerates the `(index, pda)` pairs of all instances associated with `pubkey`
+#[cfg(feature = "elusiv-client")]
+pub fn iter_valid_instances<T: MultiInstancePDAAccount>(
+    pubkey: Pubkey,
+) -> impl Iterator<Item = (u64, Pubkey)> {
+    (0..T::MAX_INSTANCES).map(move |index| {
+        let offset = Some(index.try_into().unwrap());
+        (index, T::find_with_pubkey(pubkey, offset).0)
+    })
+}
+
+/// Counts the open instances (non-zero lamports) associated with `pubkey`
+///
+/// # Note
+///
+/// The account at position `i` of `accounts` has to be the instance with the offset `i`.
+pub fn get_open_instance_count<T: MultiInstancePDAAccount>(
+    pubkey: Pubkey,
+    accounts: &[AccountInfo],
+) -> Result<u64, ProgramError> {
+    if accounts.len() as u64 > T::MAX_INSTANCES {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut count = 0;
+    for (index, account) in accounts.iter().enumerate() {
+        if account.lamports() == 0 {
+            continue;
+        }
+
+        T::verify_account_with_pubkey(account, pubkey, Some(index as u32))?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
 pub trait ComputationAccount: PDAAccount {
     fn instruction(&self) -> u32;
     fn round(&self) -> u32;
@@ -337,13 +420,85 @@ pub trait ComputationAccount: PDAAccount {
 pub struct PDAAccountData {
     pub bump_seed: u8,
 
-    /// Used for future account migrations
+    /// The data layout version, used for account migrations (see [`PDAAccount::CURRENT_VERSION`])
     pub version: u8,
 }
 
 impl PDAAccountData {
     pub fn new(data: &[u8]) -> Result<Self, std::io::Error> {
-        PDAAccountData::try_from_slice(&data[..Self::SIZE])
+        PDAAccountData::try_from_slice(&data[pda_account_data_offsets::PDA_DATA])
+    }
+}
+
+/// Byte ranges of the [`PDAAccountData`] leading every PDA account (the `PDA_DATA` of each generated `*_offsets` module)
+pub mod pda_account_data_offsets {
+    use super::PDAAccountData;
+    use crate::bytes::BorshSerDeSized;
+    use core::ops::Range;
+
+    pub const BUMP_SEED: Range<usize> = 0..<u8 as BorshSerDeSized>::SIZE;
+    pub const VERSION: Range<usize> = BUMP_SEED.end..BUMP_SEED.end + <u8 as BorshSerDeSized>::SIZE;
+
+    /// Byte range of the whole [`PDAAccountData`]
+    pub const PDA_DATA: Range<usize> = 0..VERSION.end;
+
+    const _: () = assert!(PDA_DATA.end == PDAAccountData::SIZE);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AccountVersionError {
+    /// The account has been written by a newer program version
+    TooNew,
+
+    /// The account requires a migration
+    Outdated,
+}
+
+impl From<AccountVersionError> for ProgramError {
+    fn from(e: AccountVersionError) -> Self {
+        ProgramError::Custom(e as u32 + 200)
+    }
+}
+
+/// A [`ProgramAccount`] whose data layout is versioned by [`PDAAccountData::version`]
+///
+/// # Note
+///
+/// Implemented by all `elusiv_account`s (with the `version` and `migration` attributes).
+pub trait VersionedAccount<'a>: ProgramAccount<'a> + PDAAccount {
+    fn get_pda_version(&self) -> u8;
+    fn set_pda_version(&mut self, version: u8);
+
+    /// Upgrades the data layout from `from_version` to `from_version + 1`
+    fn migrate(&mut self, from_version: u8) -> ProgramResult;
+
+    /// Upgrades an account with an older version to [`PDAAccount::CURRENT_VERSION`]
+    /// - rejects accounts with a version newer than supported by the program
+    fn migrate_if_needed(&mut self) -> ProgramResult {
+        let version = self.get_pda_version();
+        if version > Self::CURRENT_VERSION {
+            return Err(AccountVersionError::TooNew.into());
+        }
+
+        for from_version in version..Self::CURRENT_VERSION {
+            self.migrate(from_version)?;
+            self.set_pda_version(from_version + 1);
+        }
+
+        Ok(())
+    }
+
+    /// Verifies that a read-only account does not require a migration
+    fn verify_version(&self) -> ProgramResult {
+        let version = self.get_pda_version();
+        if version > Self::CURRENT_VERSION {
+            return Err(AccountVersionError::TooNew.into());
+        }
+        if version < Self::CURRENT_VERSION {
+            return Err(AccountVersionError::Outdated.into());
+        }
+
+        Ok(())
     }
 }
 
@@ -10,6 +10,8 @@ pub trait SizedType {
 }
 
 pub trait BorshSerDeSizedEnum: BorshSerDeSized {
+    /// The size of the fields of the variant `variant_index`
+    /// - zero for unknown variants (which then fail to deserialize)
     fn len(variant_index: u8) -> usize;
 
     /// Deserializes an enum by reading only up to `len` bytes of the buffer
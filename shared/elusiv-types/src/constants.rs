@@ -0,0 +1,23 @@
+//! Constants the elusiv and the warden-network program need to agree on
+
+/// Start of the custom program error codes of [`crate::tokens::TokenError`] (raised by both programs)
+pub const TOKEN_ERROR_CODES_START: u32 = 100;
+
+/// End (exclusive) of the custom program error codes of [`crate::tokens::TokenError`]
+///
+/// # Note
+///
+/// Program specific error codes are required to lie outside of `TOKEN_ERROR_CODES_START..TOKEN_ERROR_CODES_END`.
+pub const TOKEN_ERROR_CODES_END: u32 = 200;
+
+/// Returns true if `code` collides with a [`crate::tokens::TokenError`] code
+pub const fn is_token_error_code(code: u32) -> bool {
+    code >= TOKEN_ERROR_CODES_START && code < TOKEN_ERROR_CODES_END
+}
+
+/// Tags of the elusiv program instructions tracked by the warden-network
+pub mod elusiv_instruction_tags {
+    pub const FINALIZE_BASE_COMMITMENT_HASH: u8 = 2;
+    pub const FINALIZE_VERIFICATION_TRANSFER_LAMPORTS: u8 = 13;
+    pub const FINALIZE_VERIFICATION_TRANSFER_TOKEN: u8 = 14;
+}
@@ -2,8 +2,11 @@
 pub mod accounts;
 #[cfg(feature = "bytes")]
 pub mod bytes;
+pub mod constants;
 #[cfg(feature = "tokens")]
 pub mod tokens;
+#[cfg(all(feature = "accounts", feature = "bytes", feature = "tokens"))]
+pub mod warden;
 
 #[cfg(feature = "accounts")]
 pub use accounts::*;
@@ -11,3 +14,5 @@ pub use accounts::*;
 pub use bytes::*;
 #[cfg(feature = "tokens")]
 pub use tokens::*;
+#[cfg(all(feature = "accounts", feature = "bytes", feature = "tokens"))]
+pub use warden::*;
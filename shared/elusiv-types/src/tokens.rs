@@ -149,7 +149,7 @@ pub enum TokenError {
 
 impl From<TokenError> for ProgramError {
     fn from(e: TokenError) -> Self {
-        ProgramError::Custom(e as u32 + 100)
+        ProgramError::Custom(e as u32 + crate::constants::TOKEN_ERROR_CODES_START)
     }
 }
 
@@ -337,6 +337,25 @@ impl TokenPrice {
         }
     }
 
+    /// Loads the actual SOL price for Lamports
+    ///
+    /// # Notes
+    ///
+    /// In contrast to [`TokenPrice::new_lamports`] (the identity conversion), this is required for [`TokenPrice::token_into_usd`] with `token_id = 0`.
+    pub fn new_lamports_usd(sol_usd_price_account: &AccountInfo) -> Result<Self, ProgramError> {
+        if TOKENS[0].pyth_usd_price_key != *sol_usd_price_account.key {
+            return Err(TokenError::InvalidPriceAccount.into());
+        }
+
+        let lamports_usd = Self::load_token_usd_price(sol_usd_price_account, 0)?;
+
+        Ok(Self {
+            lamports_usd,
+            token_usd: lamports_usd,
+            token_id: 0,
+        })
+    }
+
     pub fn new_lamports() -> Self {
         Self {
             lamports_usd: Price {
@@ -365,7 +384,7 @@ impl TokenPrice {
         let usd = self
             .token_usd
             .mul(&Price {
-                price: token.amount().try_into().unwrap(),
+                price: token.amount().try_into().or(Err(TokenError::Overflow))?,
                 conf: 0,
                 expo: -(elusiv_token(self.token_id)?.decimals as i32),
             })
@@ -392,7 +411,7 @@ impl TokenPrice {
         let usd = self
             .lamports_usd
             .mul(&Price {
-                price: lamports.0.try_into().unwrap(),
+                price: lamports.0.try_into().or(Err(TokenError::Overflow))?,
                 conf: 0,
                 expo: 0,
             })
@@ -405,8 +424,41 @@ impl TokenPrice {
             .ok_or(TokenError::PriceError)?;
         Token::new_from_price(token_id, price, false)
     }
+
+    /// Converts `token` into USD with [`USD_DECIMALS`] decimals
+    pub fn token_into_usd(&self, token: Token) -> Result<u64, TokenError> {
+        if token.token_id() != self.token_id {
+            return Err(TokenError::InvalidTokenID);
+        }
+
+        let (price, expo) = if self.token_id == 0 {
+            (&self.lamports_usd, 0)
+        } else {
+            (
+                &self.token_usd,
+                -(elusiv_token(self.token_id)?.decimals as i32),
+            )
+        };
+
+        let usd = price
+            .mul(&Price {
+                price: token.amount().try_into().or(Err(TokenError::Overflow))?,
+                conf: 0,
+                expo,
+            })
+            .ok_or(TokenError::PriceError)?;
+
+        usd.scale_to_exponent(-(USD_DECIMALS as i32))
+            .ok_or(TokenError::PriceError)?
+            .price
+            .try_into()
+            .or(Err(TokenError::PriceError))
+    }
 }
 
+/// Decimals of USD values computed by [`TokenPrice::token_into_usd`]
+pub const USD_DECIMALS: u8 = 6;
+
 #[cfg(feature = "test-elusiv")]
 pub fn pyth_price_account_data(price: &Price) -> Result<Vec<u8>, TokenError> {
     use bytemuck::bytes_of;
@@ -0,0 +1,40 @@
+use crate as elusiv_types;
+use crate::accounts::pda_account_data_offsets;
+use crate::bytes::BorshSerDeSized;
+use crate::tokens::TOKENS;
+use borsh::{BorshDeserialize, BorshSerialize};
+use elusiv_derive::BorshSerDeSized;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+
+/// The liveness-relevant state of a basic Warden
+///
+/// # Note
+///
+/// - Stored directly after the [`crate::accounts::PDAAccountData`] of each `BasicWardenAccount` of the warden-network program.
+/// - Allows other programs and off-chain clients to read a Warden without depending on the warden-network crate.
+#[derive(BorshDeserialize, BorshSerialize, BorshSerDeSized, Clone, PartialEq, Eq, Debug)]
+pub struct WardenView {
+    pub warden_id: u32,
+    pub key: Pubkey,
+    pub is_active: bool,
+
+    /// The slot of the last heartbeat (or of the registration)
+    pub last_heartbeat_slot: u64,
+
+    /// The [`TOKENS`] supported by the Warden
+    pub tokens: [bool; TOKENS.len()],
+}
+
+impl WardenView {
+    /// Deserializes a [`WardenView`] from the raw data of a `BasicWardenAccount`
+    pub fn from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        let start = pda_account_data_offsets::PDA_DATA.end;
+        if data.len() < start + Self::SIZE {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Self::try_from_slice(&data[start..start + Self::SIZE])
+            .or(Err(ProgramError::InvalidAccountData))
+    }
+}
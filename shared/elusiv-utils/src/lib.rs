@@ -103,6 +103,7 @@ pub fn open_pda_account<'a, T: PDAAccount>(
         pda_account,
         account_size,
         bump,
+        T::CURRENT_VERSION,
         &signers_seeds,
     )
 }
@@ -113,6 +114,7 @@ pub fn create_pda_account<'a>(
     pda_account: &AccountInfo<'a>,
     account_size: usize,
     bump: u8,
+    version: u8,
     signers_seeds: &[&[u8]],
 ) -> ProgramResult {
     // We require the test-unit feature since cfg!(test) does not work in deps
@@ -144,7 +146,7 @@ pub fn create_pda_account<'a>(
     borsh::BorshSerialize::serialize(
         &PDAAccountData {
             bump_seed: bump,
-            version: 0,
+            version,
         },
         &mut data,
     )?;
@@ -152,6 +154,37 @@ pub fn create_pda_account<'a>(
     Ok(())
 }
 
+/// Grows a program owned account to `account_size` (the additional bytes are zeroed), `payer` funds the additional rent
+/// - accounts that already have the size are left unchanged
+pub fn resize_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    account_size: usize,
+) -> ProgramResult {
+    // We require the test-unit feature since cfg!(test) does not work in deps
+    if cfg!(feature = "test-unit") {
+        return Ok(());
+    }
+
+    guard!(
+        account.data_len() <= account_size,
+        ProgramError::InvalidAccountData
+    );
+    if account.data_len() == account_size {
+        return Ok(());
+    }
+
+    let lamports_required = Rent::get()?
+        .minimum_balance(account_size)
+        .saturating_sub(account.lamports());
+    if lamports_required > 0 {
+        transfer_with_system_program(payer, account, system_program, lamports_required)?;
+    }
+
+    account.realloc(account_size, true)
+}
+
 pub fn transfer_with_system_program<'a>(
     source: &AccountInfo<'a>,
     destination: &AccountInfo<'a>,
@@ -204,6 +237,28 @@ pub fn transfer_lamports_from_pda_checked<'a>(
     unsafe { transfer_lamports_from_pda(pda, recipient, lamports) }
 }
 
+/// Transfers all lamports of `pda` above its rent-exempt minimum to `recipient` and returns the transferred amount
+/// - `pda` is never drained below its rent-exemption (which would make it purgeable)
+pub fn transfer_lamports_from_pda_leaving_rent<'a>(
+    pda: &AccountInfo<'a>,
+    recipient: &AccountInfo<'a>,
+) -> Result<u64, ProgramError> {
+    let rent = if cfg!(feature = "test-unit") {
+        Rent::default()
+    } else {
+        Rent::get()?
+    };
+    let surplus = pda
+        .lamports()
+        .saturating_sub(rent.minimum_balance(pda.data_len()));
+
+    if surplus > 0 {
+        unsafe { transfer_lamports_from_pda(pda, recipient, surplus)? };
+    }
+
+    Ok(surplus)
+}
+
 pub fn close_account<'a>(payer: &AccountInfo<'a>, account: &AccountInfo<'a>) -> ProgramResult {
     unsafe { transfer_lamports_from_pda(account, payer, account.lamports()) }
 }